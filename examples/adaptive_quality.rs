@@ -0,0 +1,53 @@
+use std::{sync::{atomic::{AtomicU64, Ordering}, Arc}, time::Duration};
+
+use crabgrab::prelude::*;
+
+// Captures a display at full size, then drops to half size and 10fps without restarting the stream, as when bandwidth drops
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let display = content.displays().next().unwrap();
+    let display_size = display.rect().size;
+    let pixel_format = CaptureStream::supported_pixel_formats()[0];
+    let config = CaptureConfig::with_display(display.clone(), pixel_format);
+
+    let frame_count = Arc::new(AtomicU64::new(0));
+    let callback_frame_count = frame_count.clone();
+    let mut stream = CaptureStream::new(token, config, move |result| {
+        match result {
+            Ok(StreamEvent::Video(frame)) => {
+                if callback_frame_count.fetch_add(1, Ordering::AcqRel) % 30 == 0 {
+                    println!("frame {}: {:?}", frame.frame_id(), frame.size());
+                }
+            },
+            Err(error) => println!("stream error: {}", error),
+            _ => {}
+        }
+    }).unwrap();
+
+    std::thread::sleep(Duration::from_millis(2000));
+    println!("full quality: {} frames", frame_count.swap(0, Ordering::AcqRel));
+
+    let reduced_config = CaptureConfig::with_display(display.clone(), pixel_format)
+        .with_output_size(Size { width: display_size.width / 2.0, height: display_size.height / 2.0 })
+        .with_buffer_count(2)
+        .with_maximum_fps(Some(10.0));
+    stream.update_config(reduced_config).unwrap();
+    std::thread::sleep(Duration::from_millis(2000));
+    println!("reduced quality: {} frames", frame_count.swap(0, Ordering::AcqRel));
+
+    // The pixel format can't change on a running stream
+    let other_pixel_format = CaptureStream::supported_pixel_formats().iter().copied().find(|format| *format != pixel_format);
+    if let Some(other_pixel_format) = other_pixel_format {
+        match stream.update_config(CaptureConfig::with_display(display, other_pixel_format)) {
+            Err(StreamUpdateError::RequiresRestart(setting)) => println!("changing the {} requires a new stream", setting),
+            result => println!("unexpected result changing the pixel format: {:?}", result),
+        }
+    }
+
+    stream.close().await.unwrap();
+}
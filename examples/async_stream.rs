@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use crabgrab::prelude::*;
+use futures::StreamExt;
+
+// Consumes a capture stream with async iteration rather than a callback, for a slow consumer that can't keep up with every frame,
+// checking that frames arrive in order despite the drops, and that the end of the stream is always received
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let config = CaptureConfig::with_display(content.displays().next().unwrap(), CaptureStream::supported_pixel_formats()[0]);
+
+    let (mut stream, mut events) = CaptureStream::new_async(token, config, 2).unwrap();
+
+    let mut frame_count = 0;
+    let mut last_frame_id = None;
+    let mut end_count = 0;
+    while let Some(event) = events.next().await {
+        match event {
+            Ok(StreamEvent::Video(frame)) => {
+                assert!(last_frame_id.map_or(true, |last_frame_id| frame.frame_id() > last_frame_id), "Expected frames in order");
+                last_frame_id = Some(frame.frame_id());
+                frame_count += 1;
+                println!("frame {} ({} dropped so far)", frame.frame_id(), events.dropped_count());
+                std::thread::sleep(Duration::from_millis(100));
+                if frame_count == 20 {
+                    stream.stop().unwrap();
+                }
+            },
+            Ok(StreamEvent::End { reason }) => {
+                println!("stream ended: {:?}", reason);
+                end_count += 1;
+            },
+            Err(error) => println!("stream error: {}", error),
+            _ => {}
+        }
+    }
+    assert_eq!(end_count, 1, "Expected the end of the stream to be received once");
+}
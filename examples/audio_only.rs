@@ -0,0 +1,36 @@
+use std::{sync::Arc, time::Duration};
+
+use crabgrab::prelude::*;
+use parking_lot::Mutex;
+
+// Captures only the system audio of the first display for a few seconds, and checks no video frames arrive
+// (play some audio while it runs)
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let display = content.displays().next().unwrap();
+    let config = CaptureConfig::audio_only(display, AudioCaptureConfig::new()).unwrap();
+
+    // (audio frames, video frames)
+    let counts = Arc::new(Mutex::new((0usize, 0usize)));
+    let callback_counts = counts.clone();
+    let stream = CaptureStream::new(token, config, move |result| {
+        match result {
+            Ok(StreamEvent::Audio(_)) => callback_counts.lock().0 += 1,
+            Ok(StreamEvent::Video(_)) => callback_counts.lock().1 += 1,
+            Ok(event) => println!("Event: {:?}", event),
+            Err(error) => println!("Stream error: {}", error),
+        }
+    }).unwrap();
+    std::thread::sleep(Duration::from_millis(3000));
+    stream.close().await.unwrap();
+
+    let (audio_frames, video_frames) = *counts.lock();
+    println!("{} audio frames, {} video frames", audio_frames, video_frames);
+    assert_eq!(video_frames, 0, "Expected no video frames from an audio-only stream");
+    assert!(audio_frames > 0, "Expected audio frames");
+}
@@ -0,0 +1,39 @@
+use std::{sync::Arc, time::Duration};
+
+use crabgrab::prelude::*;
+use parking_lot::Mutex;
+
+// Captures a display with a callback that panics on the first frame, and checks that the process survives,
+// the panic reaches the panic handler, and the callback receives `StreamEvent::End` afterwards
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let panic_messages = Arc::new(Mutex::new(Vec::new()));
+    let handler_panic_messages = panic_messages.clone();
+    CaptureStream::set_callback_panic_handler(move |panic| {
+        handler_panic_messages.lock().push(panic.message().unwrap_or("unknown panic").to_string());
+    });
+
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let display = content.displays().next().unwrap();
+    let config = CaptureConfig::with_display(display, CapturePixelFormat::Bgra8888);
+
+    let end_reasons = Arc::new(Mutex::new(Vec::new()));
+    let callback_end_reasons = end_reasons.clone();
+    let stream = CaptureStream::new(token, config, move |result| {
+        match result {
+            Ok(StreamEvent::Video(_)) => panic!("panicking on purpose"),
+            Ok(StreamEvent::End { reason }) => callback_end_reasons.lock().push(reason),
+            _ => {}
+        }
+    }).unwrap();
+    std::thread::sleep(Duration::from_millis(1000));
+    stream.close().await.unwrap();
+
+    println!("panics: {:?}, end reasons: {:?}", panic_messages.lock(), end_reasons.lock());
+    assert_eq!(*panic_messages.lock(), vec!["panicking on purpose".to_string()], "Expected the panic to reach the handler once");
+    assert!(matches!(end_reasons.lock().as_slice(), [StreamEndReason::CallbackPanicked]), "Expected the stream to end once after the panic");
+}
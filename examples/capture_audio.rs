@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use crabgrab::prelude::*;
+
+// Captures 48kHz stereo system audio alongside a display, reporting silent frames and dropped samples
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let audio_config = AudioCaptureConfig::new()
+        .with_sample_rate(AudioSampleRate::Hz48000)
+        .with_channel_count(AudioChannelCount::Stereo);
+    let config = CaptureConfig::with_display(content.displays().next().unwrap(), CaptureStream::supported_pixel_formats()[0])
+        .with_audio_capture(audio_config);
+
+    let stream = CaptureStream::new(token, config, |result| {
+        match result {
+            Ok(StreamEvent::Audio(mut frame)) => {
+                let (origin_time, duration, silent, discontinuity) = (frame.origin_time(), frame.duration(), frame.silent(), frame.discontinuity());
+                let peak = match frame.audio_channel_buffer(0) {
+                    Ok(AudioChannelData::F32(samples)) => (0..samples.length()).map(|i| samples.get(i).abs()).fold(0.0f32, f32::max),
+                    _ => 0.0,
+                };
+                println!("audio frame {} at {:?} for {:?}: peak {:.3}{}{}", frame.frame_id(), origin_time, duration, peak,
+                    if silent { ", silent" } else { "" },
+                    if discontinuity { ", after dropped samples" } else { "" });
+            },
+            Err(error) => println!("stream error: {}", error),
+            _ => {}
+        }
+    }).unwrap();
+
+    std::thread::sleep(Duration::from_millis(5000));
+
+    stream.close().await.unwrap();
+}
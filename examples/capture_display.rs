@@ -4,17 +4,29 @@ use crabgrab::prelude::*;
 
 #[tokio::main]
 async fn main() { 
+    if std::env::args().any(|arg| arg == "--verbose") {
+        println!("{}", environment_report());
+    }
     let token = match CaptureStream::test_access(false) {
         Some(token) => token,
-        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+        None => match CaptureStream::request_access(false).await {
+            Some(token) => token,
+            None => {
+                handle_permission_denied();
+                return;
+            }
+        }
     };
     let filter = CapturableContentFilter::DISPLAYS;
     let content = CapturableContent::new(filter).await.unwrap();
     let config = CaptureConfig::with_display(content.displays().next().unwrap(), CapturePixelFormat::Bgra8888);
 
     let mut stream = CaptureStream::new(token, config, |result| {
-        if let StreamEvent::Video(frame) = result.expect("Expected stream event") {
-            println!("Got frame: {}", frame.frame_id());
+        match result {
+            Ok(StreamEvent::Video(frame)) => println!("Got frame: {}", frame.frame_id()),
+            Ok(_) => {},
+            // Errors such as StreamError::EmptyFrame don't end the stream
+            Err(error) => println!("Stream error: {}", error),
         }
     }).unwrap();
 
@@ -22,3 +34,13 @@ async fn main() {
 
     stream.stop().unwrap();
 }
+
+// Send the user to the OS settings page where capture access can be granted
+fn handle_permission_denied() {
+    println!("Capture access was denied");
+    match CaptureStream::open_permission_settings() {
+        Ok(OpenSettingsOutcome::Opened) => println!("Grant capture access in the settings page that was opened, then run this example again"),
+        Ok(OpenSettingsOutcome::NeedsRestart) => println!("Grant capture access in the settings page that was opened, then restart your terminal and run this example again"),
+        Err(error) => println!("Failed to open capture permission settings: {} (settings url: {:?})", error, CaptureStream::permission_settings_url()),
+    }
+}
@@ -0,0 +1,45 @@
+use std::{sync::Arc, time::Duration};
+
+use crabgrab::prelude::*;
+use parking_lot::Mutex;
+
+// Captures every display in one group for 2 seconds, printing the first frame of each on the group's shared clock, and checks that
+// stopping the group ends each display's stream exactly once
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let displays = content.displays().collect::<Vec<_>>();
+    let configs = displays.iter().map(|display| CaptureConfig::with_display(display.clone(), CapturePixelFormat::Bgra8888));
+
+    // The origin time of the first frame, and the number of ends, of each stream
+    let stream_states = Arc::new(Mutex::new(vec![(None, 0); displays.len()]));
+    let callback_stream_states = stream_states.clone();
+    let group = CaptureStreamGroup::new(token, configs, move |stream_index, result| {
+        let mut stream_states = callback_stream_states.lock();
+        let (first_frame_time, end_count) = &mut stream_states[stream_index];
+        match result {
+            Ok(StreamEvent::Video(frame)) if first_frame_time.is_none() => {
+                println!("display {}: first frame at {:?}", stream_index, frame.origin_time());
+                *first_frame_time = Some(frame.origin_time());
+            },
+            Ok(StreamEvent::End { reason }) => {
+                println!("display {}: ended ({:?})", stream_index, reason);
+                *end_count += 1;
+            },
+            _ => {}
+        }
+    }).unwrap();
+    std::thread::sleep(Duration::from_secs(2));
+    for (stream_index, stream) in group.streams().iter().enumerate() {
+        println!("display {}: start skew {:?}", stream_index, stream.statistics().start_skew);
+    }
+    group.close().await.unwrap();
+
+    for (stream_index, (_, end_count)) in stream_states.lock().iter().enumerate() {
+        assert_eq!(*end_count, 1, "Expected display {} to end exactly once", stream_index);
+    }
+}
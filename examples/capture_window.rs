@@ -3,12 +3,21 @@ use std::time::Duration;
 use crabgrab::prelude::*;
 
 fn main() { 
+    if std::env::args().any(|arg| arg == "--verbose") {
+        println!("{}", environment_report());
+    }
     let runtime = tokio::runtime::Builder::new_multi_thread()
         .build().unwrap();
     let future = runtime.spawn(async {
         let token = match CaptureStream::test_access(true) {
             Some(token) => token,
-            None => CaptureStream::request_access(true).await.expect("Expected capture access")
+            None => match CaptureStream::request_access(true).await {
+                Some(token) => token,
+                None => {
+                    handle_permission_denied();
+                    return;
+                }
+            }
         };
         let filter = CapturableContentFilter::NORMAL_WINDOWS;
         let content = CapturableContent::new(filter).await.unwrap();
@@ -48,3 +57,13 @@ fn main() {
     runtime.block_on(future).unwrap();
     runtime.shutdown_timeout(Duration::from_millis(100000));
 }
+
+// Send the user to the OS settings page where capture access can be granted
+fn handle_permission_denied() {
+    println!("Capture access was denied");
+    match CaptureStream::open_permission_settings() {
+        Ok(OpenSettingsOutcome::Opened) => println!("Grant capture access in the settings page that was opened, then run this example again"),
+        Ok(OpenSettingsOutcome::NeedsRestart) => println!("Grant capture access in the settings page that was opened, then restart your terminal and run this example again"),
+        Err(error) => println!("Failed to open capture permission settings: {} (settings url: {:?})", error, CaptureStream::permission_settings_url()),
+    }
+}
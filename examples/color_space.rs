@@ -0,0 +1,38 @@
+use std::{sync::Arc, time::Duration};
+
+use crabgrab::prelude::*;
+use parking_lot::Mutex;
+
+// Captures the first display as 8 bit BGRA and prints the color space of its frames, checking that they're
+// standard dynamic range - run this with an SDR display. 8 bit frames are always sRGB on Windows,
+// while on MacOS a wide gamut display may tag them as Display P3.
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let display = content.displays().next().unwrap();
+    println!("capturing display: {}", display.name());
+    let config = CaptureConfig::with_display(display, CapturePixelFormat::Bgra8888);
+
+    let color_spaces = Arc::new(Mutex::new(Vec::new()));
+    let callback_color_spaces = color_spaces.clone();
+    let stream = CaptureStream::new(token, config, move |result| {
+        if let Ok(StreamEvent::Video(frame)) = result {
+            callback_color_spaces.lock().push(frame.color_space());
+        }
+    }).unwrap();
+    std::thread::sleep(Duration::from_millis(1000));
+    stream.close().await.unwrap();
+
+    let color_spaces = color_spaces.lock();
+    println!("color spaces of {} frames: {:?}", color_spaces.len(), color_spaces.first());
+    assert!(!color_spaces.is_empty(), "Expected some frames");
+    for color_space in color_spaces.iter() {
+        assert!(!color_space.is_hdr(), "Expected an SDR display to produce SDR frames");
+        #[cfg(target_os = "windows")]
+        assert_eq!(*color_space, ColorSpace::Srgb, "Expected 8 bit frames to be sRGB");
+    }
+}
@@ -0,0 +1,45 @@
+use crabgrab::prelude::*;
+
+// Checks that invalid capture configs are rejected by `CaptureConfig::validate()` with the specific error for each problem,
+// and that `CaptureStream::new()` rejects them without starting a capture
+#[tokio::main]
+async fn main() {
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let display = content.displays().next().unwrap();
+    let display_size = display.rect().size;
+    let config = CaptureConfig::with_display(display, CapturePixelFormat::Bgra8888);
+    config.validate().expect("Expected the default display config to be valid");
+    println!("supported buffer counts: {:?}", CaptureStream::supported_buffer_counts());
+
+    let outside = Rect {
+        origin: Point { x: display_size.width + 100.0, y: display_size.height + 100.0 },
+        size: Size { width: 50.0, height: 50.0 },
+    };
+    let mut invalid_configs = vec![
+        ("zero output size", config.clone().with_output_size(Size { width: 0.0, height: 0.0 }), CaptureConfigError::InvalidOutputSize),
+        ("sub-pixel output height", config.clone().with_output_size(Size { width: 640.0, height: 0.5 }), CaptureConfigError::InvalidOutputSize),
+        ("infinite output size", config.clone().with_output_size(Size { width: f64::INFINITY, height: 480.0 }), CaptureConfigError::InvalidOutputSize),
+        ("source rect outside the display", config.clone().with_source_rect(outside), CaptureConfigError::SourceRectOutOfBounds),
+        ("no buffers", config.clone().with_buffer_count(0), CaptureConfigError::InvalidBufferCount),
+    ];
+    if let Some(too_many_buffers) = CaptureStream::supported_buffer_counts().end().checked_add(1) {
+        invalid_configs.push(("too many buffers", config.clone().with_buffer_count(too_many_buffers), CaptureConfigError::InvalidBufferCount));
+    }
+    for (description, invalid_config, expected_error) in &invalid_configs {
+        let error = invalid_config.validate().expect_err(description);
+        println!("{}: {}", description, error);
+        assert_eq!(error.kind(), expected_error.kind(), "Expected {} for {}", expected_error, description);
+    }
+
+    // Stream creation validates the config before capturing anything
+    let Some(token) = CaptureStream::test_access(false) else {
+        println!("skipping stream creation, as capture access hasn't been granted");
+        return;
+    };
+    let (_, invalid_config, expected_error) = &invalid_configs[0];
+    match CaptureStream::new(token, invalid_config, |_| {}) {
+        Err(StreamCreateError::InvalidConfig(error)) => assert_eq!(error.kind(), expected_error.kind()),
+        Err(error) => panic!("Expected StreamCreateError::InvalidConfig, got {}", error),
+        Ok(_) => panic!("Expected stream creation to fail"),
+    }
+}
@@ -0,0 +1,32 @@
+use crabgrab::prelude::*;
+
+// Checks the application filter's matching over a made up list of application identifiers, then enumerates windows
+// excluding the application owning the first window, and checks that none of its windows are left
+#[tokio::main]
+async fn main() {
+    let identifiers = ["com.apple.Safari", "com.apple.finder", "notepad.exe", "Code.exe"];
+
+    let including = CapturableContentFilter::NORMAL_WINDOWS.including_apps(&["com.apple.safari", "CODE.EXE"]);
+    let included: Vec<&str> = identifiers.iter().copied().filter(|identifier| including.matches_application_identifier(identifier)).collect();
+    assert_eq!(included, ["com.apple.Safari", "Code.exe"], "Expected identifiers to match exactly, ignoring case");
+
+    let excluding = CapturableContentFilter::NORMAL_WINDOWS.excluding_apps(&["notepad.exe", "com.apple"]);
+    let included: Vec<&str> = identifiers.iter().copied().filter(|identifier| excluding.matches_application_identifier(identifier)).collect();
+    assert_eq!(included, ["com.apple.Safari", "com.apple.finder", "Code.exe"], "Expected only whole identifiers to be excluded");
+
+    let both = including.clone().excluding_apps(&["Code.exe"]);
+    assert!(!both.matches_application_identifier("code.exe"), "Expected exclusions to take precedence");
+    assert!(both.matches_application_identifier("com.apple.Safari"));
+
+    let content = CapturableContent::new(CapturableContentFilter::NORMAL_WINDOWS).await.unwrap();
+    let Some(window) = content.windows().next() else {
+        println!("no windows to filter");
+        return;
+    };
+    let identifier = window.application().identifier();
+    let filter = CapturableContentFilter::NORMAL_WINDOWS.excluding_apps(&[&identifier]);
+    let filtered_content = CapturableContent::new(filter).await.unwrap();
+    println!("{} windows, {} without {}", content.windows().count(), filtered_content.windows().count(), identifier);
+    assert!(filtered_content.windows().all(|window| window.application().identifier() != identifier), "Expected the excluded application's windows to be filtered out");
+    assert!(filtered_content.applications().all(|application| application.identifier() != identifier), "Expected the excluded application to be filtered out");
+}
@@ -0,0 +1,29 @@
+use std::{sync::{atomic::{self, AtomicUsize}, Arc}, time::Duration};
+
+use crabgrab::prelude::*;
+
+// Prints the windows opened and closed, and the displays connected and disconnected, over ten seconds - then checks that
+// dropping the subscription stops the callback
+#[tokio::main]
+async fn main() {
+    let event_count = Arc::new(AtomicUsize::new(0));
+    let callback_event_count = event_count.clone();
+    let subscription = CapturableContent::subscribe_changes(CapturableContentFilter::EVERYTHING_NORMAL, move |result| {
+        callback_event_count.fetch_add(1, atomic::Ordering::SeqCst);
+        match result {
+            Ok(ContentChangeEvent::WindowAdded(window)) => println!("window added: \"{}\" ({:?})", window.title(), window.id()),
+            Ok(ContentChangeEvent::WindowRemoved(window)) => println!("window removed: {:?}", window.id()),
+            Ok(ContentChangeEvent::DisplayAdded(display)) => println!("display added: {} ({:?})", display.name(), display.id()),
+            Ok(ContentChangeEvent::DisplayRemoved(display)) => println!("display removed: {:?}", display.id()),
+            Err(error) => println!("error: {}", error),
+        }
+    }).await.unwrap();
+    println!("open or close some windows...");
+    std::thread::sleep(Duration::from_secs(10));
+    drop(subscription);
+
+    let dropped_event_count = event_count.load(atomic::Ordering::SeqCst);
+    println!("{} changes", dropped_event_count);
+    std::thread::sleep(Duration::from_secs(2));
+    assert_eq!(event_count.load(atomic::Ordering::SeqCst), dropped_event_count, "Expected no changes after the subscription was dropped");
+}
@@ -0,0 +1,29 @@
+use std::{collections::HashSet, time::Duration};
+
+use crabgrab::prelude::*;
+
+// Refreshes the capturable content once a second for a few seconds, printing the windows opened and closed in between,
+// and checks that the displays keep their ids across refreshes
+#[tokio::main]
+async fn main() {
+    let mut content = CapturableContent::new(CapturableContentFilter::EVERYTHING_NORMAL).await.unwrap();
+    let display_ids: HashSet<DisplayId> = content.displays().map(|display| display.id()).collect();
+    let mut window_ids: HashSet<WindowId> = content.windows().map(|window| window.id()).collect();
+    println!("{} windows, {} displays", window_ids.len(), display_ids.len());
+
+    for _ in 0..5 {
+        std::thread::sleep(Duration::from_millis(1000));
+        content.refresh().await.unwrap();
+        let refreshed_window_ids: HashSet<WindowId> = content.windows().map(|window| window.id()).collect();
+        for window in content.windows().filter(|window| !window_ids.contains(&window.id())) {
+            println!("opened: \"{}\" ({:?})", window.title(), window.id());
+        }
+        for window_id in window_ids.difference(&refreshed_window_ids) {
+            println!("closed: {:?}", window_id);
+        }
+        window_ids = refreshed_window_ids;
+
+        let refreshed_display_ids: HashSet<DisplayId> = content.displays().map(|display| display.id()).collect();
+        assert_eq!(refreshed_display_ids, display_ids, "Expected the displays to keep their ids across refreshes");
+    }
+}
@@ -0,0 +1,21 @@
+use std::time::{Duration, Instant};
+
+use crabgrab::prelude::*;
+
+// Enumerates the capturable content with a timeout too short for the OS to answer in, checking that it gives up promptly,
+// then again with a generous timeout, checking that it succeeds. A real stall in the OS can't be simulated here, so the
+// first call may still succeed if the content is enumerated fast enough
+#[tokio::main]
+async fn main() {
+    let start = Instant::now();
+    match CapturableContent::new_with_timeout(CapturableContentFilter::EVERYTHING_NORMAL, Duration::from_millis(1)).await {
+        Ok(content) => println!("enumerated {} windows within 1ms", content.windows().count()),
+        Err(CapturableContentError::Timeout) => println!("timed out after {:?}", start.elapsed()),
+        Err(error) => panic!("Expected content or a timeout, got: {}", error),
+    }
+    assert!(start.elapsed() < Duration::from_millis(100), "Expected the timeout to return promptly, took {:?}", start.elapsed());
+
+    let content = CapturableContent::new_with_timeout(CapturableContentFilter::EVERYTHING_NORMAL, Duration::from_secs(5)).await
+        .expect("Expected content within 5 seconds");
+    println!("{} windows, {} displays", content.windows().count(), content.displays().count());
+}
@@ -0,0 +1,52 @@
+use std::{sync::Arc, time::Duration};
+
+use crabgrab::prelude::*;
+use parking_lot::Mutex;
+
+// Captures just a region around the center of a display from the first frame, with a crop that overhangs the display's
+// bottom-right corner to show it being clamped, and checks the frames are the size of the clamped region
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let display = content.displays().next().unwrap();
+    let display_size = display.rect().size;
+    let source_rect = Rect {
+        origin: Point { x: display_size.width / 2.0, y: display_size.height / 2.0 },
+        size: display_size,
+    };
+    let config = CaptureConfig::with_display(display, CapturePixelFormat::Bgra8888)
+        .with_source_rect(source_rect);
+
+    let frame_sizes = Arc::new(Mutex::new(Vec::new()));
+    let callback_frame_sizes = frame_sizes.clone();
+    let stream = CaptureStream::new(token, config, move |result| {
+        if let Ok(StreamEvent::Video(frame)) = result {
+            callback_frame_sizes.lock().push(frame.size());
+        }
+    }).unwrap();
+    let clamped_rect = stream.source_rect().expect("Expected the stream to be cropped");
+    println!("source rect {:?} clamped to {:?}", source_rect, clamped_rect);
+    assert_eq!((clamped_rect.size.width, clamped_rect.size.height), (display_size.width / 2.0, display_size.height / 2.0));
+
+    std::thread::sleep(Duration::from_millis(1000));
+    stream.close().await.unwrap();
+
+    let frame_sizes = frame_sizes.lock();
+    let first_frame_size = *frame_sizes.first().expect("Expected a frame");
+    println!("{} frames, first frame size: {:?}", frame_sizes.len(), first_frame_size);
+    // Frames are in pixels rather than the display's units, so compare their aspect ratio to the region's
+    let frame_aspect = first_frame_size.width / first_frame_size.height;
+    let region_aspect = clamped_rect.size.width / clamped_rect.size.height;
+    assert!((frame_aspect - region_aspect).abs() < 0.05, "Expected the first frame to be cropped to the region");
+
+    // A region entirely outside the display can't be captured
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let display = content.displays().next().unwrap();
+    let config = CaptureConfig::with_display(display, CapturePixelFormat::Bgra8888)
+        .with_source_rect(Rect { origin: Point { x: display_size.width, y: 0.0 }, size: display_size });
+    assert!(matches!(CaptureStream::new(token, config, |_| {}), Err(StreamCreateError::Other(_))));
+}
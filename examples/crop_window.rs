@@ -0,0 +1,52 @@
+use std::{sync::Arc, time::Duration};
+
+use crabgrab::prelude::*;
+use parking_lot::Mutex;
+
+// Captures a window, then crops the running stream to the window's top-left quadrant, printing the frame size before and after
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::NORMAL_WINDOWS).await.unwrap();
+    let window = content.windows().next().expect("Expected a window to capture");
+    println!("capturing window \"{}\"", window.title());
+    let window_size = window.rect().size;
+    let config = CaptureConfig::with_window(window, CaptureStream::supported_pixel_formats()[0]).unwrap();
+
+    let frame_size = Arc::new(Mutex::new(None));
+    let callback_frame_size = frame_size.clone();
+    let mut stream = CaptureStream::new(token, config, move |result| {
+        if let Ok(StreamEvent::Video(frame)) = result {
+            *callback_frame_size.lock() = Some(frame.size());
+        }
+    }).unwrap();
+
+    std::thread::sleep(Duration::from_millis(1000));
+    let full_size = frame_size.lock().take();
+    println!("full frame size: {:?}", full_size);
+
+    stream.set_source_rect(Some(Rect {
+        origin: Point::ZERO,
+        size: Size { width: window_size.width / 2.0, height: window_size.height / 2.0 },
+    })).unwrap();
+    std::thread::sleep(Duration::from_millis(1000));
+    let cropped_size = frame_size.lock().take();
+    println!("cropped frame size: {:?}", cropped_size);
+
+    if let (Some(full_size), Some(cropped_size)) = (full_size, cropped_size) {
+        assert!(cropped_size.width <= (full_size.width / 2.0).ceil() + 1.0 && cropped_size.height <= (full_size.height / 2.0).ceil() + 1.0,
+            "Expected the cropped frames to be a quarter of the full frames");
+    }
+
+    // Beyond the window's bounds
+    assert!(stream.set_source_rect(Some(Rect { origin: Point::ZERO, size: Size { width: window_size.width * 2.0, height: window_size.height } })).is_err());
+
+    stream.set_source_rect(None).unwrap();
+    std::thread::sleep(Duration::from_millis(1000));
+    println!("reset frame size: {:?}", frame_size.lock().take());
+
+    stream.close().await.unwrap();
+}
@@ -0,0 +1,95 @@
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::sync::Arc;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::time::Duration;
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use crabgrab::prelude::*;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use parking_lot::Mutex;
+
+#[cfg(target_os = "macos")]
+#[repr(C)]
+struct CGPoint {
+    x: f64,
+    y: f64,
+}
+
+#[cfg(target_os = "macos")]
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGWarpMouseCursorPosition(point: CGPoint) -> i32;
+}
+
+#[cfg(target_os = "windows")]
+#[link(name = "user32")]
+extern "system" {
+    fn SetCursorPos(x: i32, y: i32) -> i32;
+}
+
+// Move the cursor to a point in virtual screen coordinates
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn move_cursor(point: Point) {
+    #[cfg(target_os = "macos")]
+    unsafe { CGWarpMouseCursorPosition(CGPoint { x: point.x, y: point.y }); }
+    #[cfg(target_os = "windows")]
+    unsafe { SetCursorPos(point.x as i32, point.y as i32); }
+}
+
+// Moves the cursor to two points on the first display, and checks that the cursor reported with the frames captured
+// at each point moved by the same amount - then prints the cursor's image. The cursor is left in the frames, so that
+// moving it produces new frames. Run this as a per-monitor DPI aware process on Windows, and don't touch the mouse
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let display = content.displays().next().unwrap();
+    let display_rect = display.rect();
+    let config = CaptureConfig::with_display(display, CapturePixelFormat::Bgra8888)
+        .with_show_cursor(true);
+
+    let latest_frame = Arc::new(Mutex::new(None));
+    let callback_latest_frame = latest_frame.clone();
+    let stream = CaptureStream::new(token, config, move |result| {
+        if let Ok(StreamEvent::Video(frame)) = result {
+            *callback_latest_frame.lock() = Some(frame);
+        }
+    }).unwrap();
+
+    let offsets = [Point { x: 100.0, y: 100.0 }, Point { x: 300.0, y: 200.0 }];
+    let mut cursors = Vec::new();
+    let mut content_rect = None;
+    for offset in offsets {
+        move_cursor(Point { x: display_rect.origin.x + offset.x, y: display_rect.origin.y + offset.y });
+        std::thread::sleep(Duration::from_millis(500));
+        let frame = latest_frame.lock().take().expect("Expected a frame after moving the cursor");
+        let cursor = frame.cursor().expect("Expected the cursor to be on the display");
+        println!("moved to {:?}, cursor at {:?} in frame, visible: {}", offset, cursor.position, cursor.visible);
+        content_rect = Some(frame.content_rect());
+        cursors.push(cursor);
+    }
+    stream.close().await.unwrap();
+
+    // The frame may be scaled from the display's virtual screen size
+    let content_rect = content_rect.unwrap();
+    let scale_x = content_rect.size.width / display_rect.size.width;
+    let scale_y = content_rect.size.height / display_rect.size.height;
+    let expected = ((offsets[1].x - offsets[0].x) * scale_x, (offsets[1].y - offsets[0].y) * scale_y);
+    let moved = (cursors[1].position.x - cursors[0].position.x, cursors[1].position.y - cursors[0].position.y);
+    println!("cursor moved by {:?} in frame, {:?} expected", moved, expected);
+    assert!((moved.0 - expected.0).abs() <= 2.0 && (moved.1 - expected.1).abs() <= 2.0, "Expected the cursor's position to track its movement");
+
+    match &cursors[1].image {
+        Some(image) => println!("cursor image: {}x{}, hotspot at {:?}", image.width, image.height, image.hotspot),
+        None => println!("couldn't read the cursor image"),
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn main() {
+    println!("Cursors are only captured on MacOS and Windows");
+}
@@ -0,0 +1,56 @@
+use std::{sync::Arc, time::Duration};
+
+use crabgrab::prelude::*;
+use parking_lot::Mutex;
+
+// Captures a mostly-static terminal or explorer window for a few seconds and prints the regions each frame changed,
+// checking that a small change (type a few characters into the window while it runs) is reported as less than the full frame.
+// The changed regions aren't known on Windows, where every frame is treated as fully changed.
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::NORMAL_WINDOWS).await.unwrap();
+    let window = content.windows().find(|window| {
+        let app_identifier = window.application().identifier().to_lowercase();
+        !window.title().is_empty() && (app_identifier.contains("terminal") || app_identifier.contains("explorer"))
+    }).expect("Expected a terminal or explorer window");
+    println!("capturing window: {}", window.title());
+    let config = CaptureConfig::with_window(window, CapturePixelFormat::Bgra8888).unwrap();
+
+    // (changed area, full area) of each frame whose changed regions are known, and the number of frames where they aren't
+    let frames = Arc::new(Mutex::new((Vec::new(), 0usize)));
+    let callback_frames = frames.clone();
+    let stream = CaptureStream::new(token, config, move |result| {
+        let Ok(StreamEvent::Video(frame)) = result else {
+            return;
+        };
+        let mut frames = callback_frames.lock();
+        match frame.dirty_rects() {
+            Some(dirty_rects) => {
+                let dirty_area: f64 = dirty_rects.iter().map(Rect::area).sum();
+                let full_area = frame.content_rect().area();
+                println!("frame {}: {} dirty rects, {:.1}% of the frame", frame.frame_id(), dirty_rects.len(), 100.0 * dirty_area / full_area);
+                frames.0.push((dirty_area, full_area));
+            },
+            None => frames.1 += 1,
+        }
+    }).unwrap();
+    std::thread::sleep(Duration::from_millis(5000));
+    stream.close().await.unwrap();
+
+    let (known_frames, unknown_frames) = &*frames.lock();
+    if known_frames.is_empty() {
+        println!("Changed regions aren't known on this platform ({} frames)", unknown_frames);
+        return;
+    }
+    // The first frame is the whole window, so look for a later one which changed less than that
+    let partial_frames = known_frames.iter()
+        .skip(1)
+        .filter(|(dirty_area, full_area)| dirty_area < full_area)
+        .count();
+    println!("{} of {} frames changed only part of the window", partial_frames, known_frames.len());
+    assert!(partial_frames > 0, "Expected a small change to be reported as less than the full frame");
+}
@@ -0,0 +1,22 @@
+use crabgrab::prelude::*;
+
+// Lists every display with the metadata a picker would show, and checks that each display reports
+// a name, a pixel size and a scale factor
+#[tokio::main]
+async fn main() {
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    for display in content.displays() {
+        let rect = display.rect();
+        let pixel_size = display.pixel_size();
+        let scale_factor = display.scale_factor();
+        let refresh_rate = display.refresh_rate().map_or("unknown".to_string(), |refresh_rate| format!("{:.2}Hz", refresh_rate));
+        println!("\"{}\": {}x{} pixels at {}x scale, {} refresh, rect: {:?}", display.name(), pixel_size.width, pixel_size.height, scale_factor, refresh_rate, rect);
+
+        assert!(!display.name().is_empty(), "Expected every display to have a name");
+        assert!(pixel_size.width > 0.0 && pixel_size.height > 0.0, "Expected a non-empty pixel size");
+        assert!(scale_factor >= 1.0, "Expected a scale factor of at least 1");
+        if let Some(refresh_rate) = display.refresh_rate() {
+            assert!(refresh_rate > 0.0, "Expected a positive refresh rate");
+        }
+    }
+}
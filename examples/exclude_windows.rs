@@ -0,0 +1,76 @@
+#[cfg(target_os = "macos")]
+use std::time::Duration;
+
+#[cfg(target_os = "macos")]
+use crabgrab::feature::bitmap::{FrameBitmap, FrameBitmapBgraUnorm8x4, VideoFrameBitmap as _};
+#[cfg(target_os = "macos")]
+use crabgrab::platform::macos::MacosCaptureConfigExt as _;
+#[cfg(target_os = "macos")]
+use crabgrab::prelude::*;
+
+// Captures the display showing a marker window with and without excluding that window, and checks that the marker's color is
+// only at the window's center when the window isn't excluded. Show a window filled with a solid color that differs from what's
+// behind it, and pass its title, E.G. `cargo run --example exclude_windows --features bitmap -- "Marker"`
+// (requires the `bitmap` feature)
+#[cfg(target_os = "macos")]
+#[tokio::main]
+async fn main() {
+    let marker_title = std::env::args().nth(1).expect("Expected the title of a solid-color marker window");
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::EVERYTHING_NORMAL).await.unwrap();
+    let marker_window = content.windows().find(|window| window.title() == marker_title).expect("Expected the marker window to be on screen");
+    let window_rect = marker_window.rect();
+    let center = (window_rect.origin.x + window_rect.size.width / 2.0, window_rect.origin.y + window_rect.size.height / 2.0);
+    let display = content.displays().find(|display| {
+        let rect = display.rect();
+        center.0 >= rect.origin.x && center.0 < rect.origin.x + rect.size.width &&
+        center.1 >= rect.origin.y && center.1 < rect.origin.y + rect.size.height
+    }).expect("Expected a display showing the marker window");
+    let display_rect = display.rect();
+
+    let marker_config = CaptureConfig::with_window(marker_window.clone(), CapturePixelFormat::Bgra8888).unwrap();
+    let marker_bitmap = capture_bitmap(token, marker_config).await;
+    let marker_color = marker_bitmap.data[(marker_bitmap.height / 2) * marker_bitmap.width + marker_bitmap.width / 2];
+
+    let display_config = CaptureConfig::with_display(display.clone(), CapturePixelFormat::Bgra8888);
+    let excluding_config = display_config.clone().with_excluded_windows(vec![marker_window]);
+    let center_color = |bitmap: &FrameBitmapBgraUnorm8x4<Box<[[u8; 4]]>>| {
+        let x = ((center.0 - display_rect.origin.x) * bitmap.width as f64 / display_rect.size.width) as usize;
+        let y = ((center.1 - display_rect.origin.y) * bitmap.height as f64 / display_rect.size.height) as usize;
+        bitmap.data[y * bitmap.width + x]
+    };
+    let included_color = center_color(&capture_bitmap(token, display_config).await);
+    let excluded_color = center_color(&capture_bitmap(token, excluding_config).await);
+    println!("marker color: {:?}, with the window: {:?}, excluding the window: {:?}", marker_color, included_color, excluded_color);
+    assert!(color_distance(marker_color, included_color) < 16, "Expected the marker window in the display capture");
+    assert!(color_distance(marker_color, excluded_color) >= 16, "Expected the marker window to be left out of the display capture");
+}
+
+#[cfg(target_os = "macos")]
+async fn capture_bitmap(token: CaptureAccessToken, config: CaptureConfig) -> FrameBitmapBgraUnorm8x4<Box<[[u8; 4]]>> {
+    let (frame_tx, frame_rx) = std::sync::mpsc::channel();
+    let stream = CaptureStream::new(token, config, move |result| {
+        if let Ok(StreamEvent::Video(frame)) = result {
+            let _ = frame_tx.send(frame);
+        }
+    }).unwrap();
+    let frame = frame_rx.recv_timeout(Duration::from_secs(2)).expect("Expected a frame");
+    stream.close().await.unwrap();
+    match frame.get_bitmap().expect("Expected a bitmap") {
+        FrameBitmap::BgraUnorm8x4(bitmap) => bitmap,
+        _ => panic!("Expected a BgraUnorm8x4 bitmap"),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn color_distance(a: [u8; 4], b: [u8; 4]) -> u8 {
+    (0..3).map(|i| a[i].abs_diff(b[i])).max().unwrap()
+}
+
+#[cfg(not(target_os = "macos"))]
+fn main() {
+    println!("Excluding windows from display capture is only available on MacOS");
+}
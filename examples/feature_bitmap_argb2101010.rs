@@ -0,0 +1,27 @@
+use crabgrab::feature::bitmap::{BoxedSliceFrameBitmap, FrameBitmapArgbUnormPacked2101010};
+
+// Decodes Argb2101010 bitmaps with known packed pixels into their components and to 8 bit RGBA, without capturing anything
+// (requires the `bitmap` feature)
+fn main() {
+    // (packed pixel, expected [a, r, g, b], expected 8 bit [r, g, b, a])
+    let golden_values = [
+        (0xC000_0000u32,                            [3,    0,    0,    0], [  0,   0,   0, 255]),
+        ((3 << 30) | (1023 << 20),                  [3, 1023,    0,    0], [255,   0,   0, 255]),
+        ((3 << 30) | (1023 << 10),                  [3,    0, 1023,    0], [  0, 255,   0, 255]),
+        ((3 << 30) | 1023,                          [3,    0,    0, 1023], [  0,   0, 255, 255]),
+        ((1 << 30) | (512 << 20) | (256 << 10) | 4, [1,  512,  256,    4], [128,  64,   1,  85]),
+    ];
+    for (pixel, expected_components, expected_rgba) in golden_values {
+        let bitmap = FrameBitmapArgbUnormPacked2101010 {
+            data: vec![pixel].into_boxed_slice(),
+            width: 1,
+            height: 1,
+            change_metric: None,
+        };
+        let components = bitmap.pixel_at(0, 0);
+        println!("{:#010x} -> {:?}", pixel, components);
+        assert_eq!(components, expected_components);
+        let rgba = BoxedSliceFrameBitmap::ArgbUnormPacked2101010(bitmap).to_rgba_unorm8().data[0];
+        assert_eq!(rgba, expected_rgba);
+    }
+}
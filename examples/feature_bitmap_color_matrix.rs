@@ -0,0 +1,92 @@
+#[cfg(target_os = "macos")]
+use std::time::Duration;
+
+#[cfg(target_os = "macos")]
+use crabgrab::feature::bitmap::{FrameBitmap, VideoFrameBitmap as _, YCbCrColorMatrix};
+#[cfg(target_os = "macos")]
+use crabgrab::platform::macos::{MacosCaptureConfigExt as _, MacosColorMatrix};
+#[cfg(target_os = "macos")]
+use crabgrab::prelude::*;
+
+// Captures the first display as BGRA and as 4:2:0 YCbCr with the BT.601 color matrix, then converts sampled YCbCr pixels back
+// to RGB with the BT.601 and BT.709 equations, checking that the BT.601 conversion matches the BGRA capture. Best run with
+// colorful, static content on screen
+// (requires the `bitmap` feature)
+#[cfg(target_os = "macos")]
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let display = content.displays().next().unwrap();
+
+    let bgra_config = CaptureConfig::with_display(display.clone(), CapturePixelFormat::Bgra8888);
+    let ycbcr_config = CaptureConfig::with_display(display, CapturePixelFormat::V420)
+        .with_color_matrix(MacosColorMatrix::ItuR601_4);
+    let bgra_bitmap = match capture_frame(token, bgra_config).await.get_bitmap().expect("Expected a bitmap") {
+        FrameBitmap::BgraUnorm8x4(bitmap) => bitmap,
+        _ => panic!("Expected a BgraUnorm8x4 bitmap"),
+    };
+    let ycbcr_bitmap = match capture_frame(token, ycbcr_config).await.get_bitmap().expect("Expected a bitmap") {
+        FrameBitmap::YCbCr(bitmap) => bitmap,
+        _ => panic!("Expected a YCbCr bitmap"),
+    };
+    assert_eq!((bgra_bitmap.width, bgra_bitmap.height), (ycbcr_bitmap.luma_width, ycbcr_bitmap.luma_height));
+    assert_eq!(ycbcr_bitmap.color_matrix, YCbCrColorMatrix::ItuR601);
+
+    // Kr and Kb for each matrix
+    let matrices = [("BT.601", 0.299, 0.114), ("BT.709", 0.2126, 0.0722)];
+    let mut errors = [0.0f64; 2];
+    let mut samples = 0;
+    for y in (0..ycbcr_bitmap.luma_height).step_by(16) {
+        for x in (0..ycbcr_bitmap.luma_width).step_by(16) {
+            let luma = ycbcr_bitmap.luma_data[y * ycbcr_bitmap.luma_width + x];
+            let [cb, cr] = ycbcr_bitmap.chroma_data[(y / 2) * ycbcr_bitmap.chroma_width + x / 2];
+            let [b, g, r, _] = bgra_bitmap.data[y * bgra_bitmap.width + x];
+            for (error, (_, kr, kb)) in errors.iter_mut().zip(matrices) {
+                let converted = ycbcr_to_rgb(luma, cb, cr, kr, kb);
+                *error += converted.iter().zip([r, g, b]).map(|(c, expected)| (c - expected as f64).abs()).sum::<f64>() / 3.0;
+            }
+            samples += 1;
+        }
+    }
+    for ((name, _, _), error) in matrices.iter().zip(&mut errors) {
+        *error /= samples as f64;
+        println!("{} mean error: {:.2}", name, error);
+    }
+    // Loose, to allow for chroma subsampling and video range quantization
+    assert!(errors[0] < 8.0, "Expected BT.601 conversion to match the BGRA capture");
+    assert!(errors[0] <= errors[1], "Expected BT.601 conversion to match better than BT.709");
+}
+
+#[cfg(target_os = "macos")]
+async fn capture_frame(token: CaptureAccessToken, config: CaptureConfig) -> VideoFrame {
+    let (frame_tx, frame_rx) = std::sync::mpsc::channel();
+    let stream = CaptureStream::new(token, config, move |result| {
+        if let Ok(StreamEvent::Video(frame)) = result {
+            let _ = frame_tx.send(frame);
+        }
+    }).unwrap();
+    let frame = frame_rx.recv_timeout(Duration::from_secs(2)).expect("Expected a frame");
+    stream.close().await.unwrap();
+    frame
+}
+
+// Video range YCbCr to 8 bit RGB
+#[cfg(target_os = "macos")]
+fn ycbcr_to_rgb(luma: u8, cb: u8, cr: u8, kr: f64, kb: f64) -> [f64; 3] {
+    let y = (luma as f64 - 16.0) / 219.0;
+    let pb = (cb as f64 - 128.0) / 224.0;
+    let pr = (cr as f64 - 128.0) / 224.0;
+    let r = y + 2.0 * (1.0 - kr) * pr;
+    let b = y + 2.0 * (1.0 - kb) * pb;
+    let g = (y - kr * r - kb * b) / (1.0 - kr - kb);
+    [r, g, b].map(|c| (c * 255.0).clamp(0.0, 255.0))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn main() {
+    println!("Color matrix selection is only available on MacOS");
+}
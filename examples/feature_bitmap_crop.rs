@@ -0,0 +1,85 @@
+use crabgrab::feature::bitmap::{BoxedSliceFrameBitmap, FrameBitmap, FrameBitmapBgraUnorm8x4, FrameBitmapYCbCr, VideoRange, YCbCrColorMatrix};
+use crabgrab::prelude::{Point, Rect, Size};
+
+// Crops bitmaps with known patterns, where each pixel holds its own coordinates, and checks that the corners of each crop land
+// in the expected output positions, without capturing anything
+// (requires the `bitmap` feature)
+fn main() {
+    let rect = |x: f64, y: f64, width: f64, height: f64| Rect { origin: Point { x, y }, size: Size { width, height } };
+
+    // Bgra: [x, y, 0, 255] at each pixel of a 7x5 bitmap
+    let (width, height) = (7, 5);
+    let bgra_bitmap: BoxedSliceFrameBitmap = FrameBitmap::BgraUnorm8x4(FrameBitmapBgraUnorm8x4 {
+        data: (0..width * height).map(|i| [(i % width) as u8, (i / width) as u8, 0, 255]).collect(),
+        width,
+        height,
+        change_metric: None,
+    });
+    // (crop rect, expected (x, y, width, height) of the crop in the source)
+    let bgra_crops = [
+        (rect(2.0, 1.0, 3.0, 2.0),         (2, 1, 3, 2)),
+        // Partly covered pixels are included
+        (rect(1.5, 0.5, 2.0, 1.0),         (1, 0, 3, 2)),
+        // Clamped to the bounds
+        (rect(5.0, 3.0, 10.0, 10.0),       (5, 3, 2, 2)),
+        (rect(-2.0, -2.0, 100.0, 100.0),   (0, 0, 7, 5)),
+        // Entirely outside
+        (rect(20.0, 0.0, 2.0, 2.0),        (0, 0, 0, 0)),
+    ];
+    for (crop_rect, (x, y, crop_width, crop_height)) in bgra_crops {
+        let FrameBitmap::BgraUnorm8x4(cropped) = bgra_bitmap.crop(crop_rect) else {
+            panic!("Expected a Bgra crop");
+        };
+        println!("bgra crop {:?} -> {}x{}", crop_rect, cropped.width, cropped.height);
+        assert_eq!((cropped.width, cropped.height), (crop_width, crop_height));
+        assert_eq!(cropped.data.len(), crop_width * crop_height);
+        if crop_width == 0 {
+            continue;
+        }
+        let corners = [(0, 0), (crop_width - 1, 0), (0, crop_height - 1), (crop_width - 1, crop_height - 1)];
+        for (corner_x, corner_y) in corners {
+            let pixel = cropped.data[cropped.width * corner_y + corner_x];
+            assert_eq!(pixel, [(x + corner_x) as u8, (y + corner_y) as u8, 0, 255], "Expected the crop's corner to come from the source");
+        }
+    }
+
+    // YCbCr 4:2:0: luma of x + 16 * y, and chroma of [x, y] for each 2x2 block of a 9x7 bitmap
+    let (luma_width, luma_height) = (9, 7);
+    let (chroma_width, chroma_height) = (5, 4);
+    let ycbcr_bitmap = FrameBitmapYCbCr {
+        luma_data: (0..luma_width * luma_height).map(|i| ((i % luma_width) + 16 * (i / luma_width)) as u8).collect::<Box<[u8]>>(),
+        luma_width,
+        luma_height,
+        chroma_data: (0..chroma_width * chroma_height).map(|i| [(i % chroma_width) as u8, (i / chroma_width) as u8]).collect::<Box<[[u8; 2]]>>(),
+        chroma_width,
+        chroma_height,
+        range: VideoRange::Full,
+        color_matrix: YCbCrColorMatrix::ItuR709,
+        change_metric: None,
+    };
+    // (crop rect, expected luma (x, y, width, height), expected chroma (x, y, width, height))
+    let ycbcr_crops = [
+        (rect(2.0, 2.0, 4.0, 2.0),     (2, 2, 4, 2), (1, 1, 2, 1)),
+        // Odd offsets are widened to the even luma column and row before them
+        (rect(3.0, 1.0, 3.0, 3.0),     (2, 0, 4, 4), (1, 0, 2, 2)),
+        // Odd sizes take the chroma covering their last luma column and row
+        (rect(4.0, 4.0, 5.0, 3.0),     (4, 4, 5, 3), (2, 2, 3, 2)),
+        (rect(20.0, 20.0, 2.0, 2.0),   (0, 0, 0, 0), (0, 0, 0, 0)),
+    ];
+    for (crop_rect, (x, y, crop_width, crop_height), (chroma_x, chroma_y, crop_chroma_width, crop_chroma_height)) in ycbcr_crops {
+        let cropped = ycbcr_bitmap.crop(crop_rect);
+        println!("ycbcr crop {:?} -> luma {}x{}, chroma {}x{}", crop_rect, cropped.luma_width, cropped.luma_height, cropped.chroma_width, cropped.chroma_height);
+        assert_eq!((cropped.luma_width, cropped.luma_height), (crop_width, crop_height));
+        assert_eq!((cropped.chroma_width, cropped.chroma_height), (crop_chroma_width, crop_chroma_height));
+        if crop_width == 0 {
+            continue;
+        }
+        let corners = [(0, 0), (crop_width - 1, 0), (0, crop_height - 1), (crop_width - 1, crop_height - 1)];
+        for (corner_x, corner_y) in corners {
+            assert_eq!(cropped.luma_data[cropped.luma_width * corner_y + corner_x], ((x + corner_x) + 16 * (y + corner_y)) as u8);
+            // Each luma pixel keeps the chroma sample it had in the source
+            assert_eq!(cropped.chroma_at(corner_x, corner_y), ycbcr_bitmap.chroma_at(x + corner_x, y + corner_y));
+        }
+        assert_eq!(cropped.chroma_data[0], [chroma_x as u8, chroma_y as u8]);
+    }
+}
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use crabgrab::feature::bitmap::{FrameBitmap, VideoFrameBitmap as _};
+use crabgrab::prelude::*;
+
+// Captures the first display as 16 bit float RGBA where that's supported, and checks that bitmaps of its frames are RgbaF16x4
+// in the scRGB color space - prints the brightest value, which is above 1.0 for HDR content on an HDR display
+// (requires the `bitmap` feature)
+#[tokio::main]
+async fn main() {
+    if !CaptureStream::supported_pixel_formats().contains(&CapturePixelFormat::RgbaF16) {
+        println!("16 bit float capture isn't supported on this platform");
+        return;
+    }
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let config = CaptureConfig::with_display(content.displays().next().unwrap(), CapturePixelFormat::RgbaF16);
+
+    let (frame_tx, frame_rx) = std::sync::mpsc::channel();
+    let stream = CaptureStream::new(token, config, move |result| {
+        if let Ok(StreamEvent::Video(frame)) = result {
+            let _ = frame_tx.send(frame);
+        }
+    }).unwrap();
+    let frame = frame_rx.recv_timeout(Duration::from_secs(2)).expect("Expected a frame");
+    stream.close().await.unwrap();
+
+    assert_eq!(frame.color_space(), ColorSpace::LinearExtendedSrgb, "Expected float frames to be scRGB");
+    match frame.get_bitmap().expect("Expected a bitmap") {
+        FrameBitmap::RgbaF16x4(bitmap) => {
+            let brightest = bitmap.data.iter()
+                .flat_map(|[r, g, b, _]| [r.to_f32(), g.to_f32(), b.to_f32()])
+                .fold(0.0f32, f32::max);
+            println!("{}x{} bitmap, brightest value: {}", bitmap.width, bitmap.height, brightest);
+            assert_eq!(bitmap.data.len(), bitmap.width * bitmap.height);
+        },
+        _ => panic!("Expected an RgbaF16x4 bitmap"),
+    }
+}
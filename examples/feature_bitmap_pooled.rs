@@ -34,6 +34,8 @@ fn main() {
                                                 crabgrab::feature::bitmap::FrameBitmap::ArgbUnormPacked2101010(_) => println!("format: ArgbUnormPacked2101010"),
                                                 crabgrab::feature::bitmap::FrameBitmap::RgbaF16x4(_) => println!("format: RgbaF16x4"),
                                                 crabgrab::feature::bitmap::FrameBitmap::YCbCr(_) => println!("format: YCbCr"),
+                                                crabgrab::feature::bitmap::FrameBitmap::RgbaUnorm16x4(_) => println!("format: RgbaUnorm16x4"),
+                                                _ => println!("format: other"),
                                             }
                                         },
                                         Err(e) => {
@@ -0,0 +1,42 @@
+use std::{sync::Arc, time::Duration};
+
+use crabgrab::{feature::bitmap::{FrameBitmap, VideoFrameBitmap as _}, prelude::*};
+use parking_lot::Mutex;
+
+// Captures a display, and checks that borrowing each frame's pixels with `with_bitmap_view` gives the same bytes as copying them with `get_bitmap`
+// (requires the `bitmap` feature)
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let display = content.displays().next().unwrap();
+    let config = CaptureConfig::with_display(display, CapturePixelFormat::Bgra8888);
+
+    let compared_frames = Arc::new(Mutex::new(0usize));
+    let callback_compared_frames = compared_frames.clone();
+    let stream = CaptureStream::new(token, config, move |result| {
+        let Ok(StreamEvent::Video(frame)) = result else {
+            return;
+        };
+        let FrameBitmap::BgraUnorm8x4(copied) = frame.get_bitmap().unwrap() else {
+            panic!("Expected a Bgra8888 bitmap");
+        };
+        // A simple hash of the pixels, as a consumer of the view might compute
+        let (width, height, hash) = frame.with_bitmap_view(|view| {
+            // The view's rows may be padded, so they're compared without the padding
+            assert!(view.rows().eq(copied.data.as_ref().chunks(copied.width)), "Expected the view to match the copied bitmap");
+            let hash = view.rows().flatten().flatten().fold(0u64, |hash, byte| hash.wrapping_mul(31).wrapping_add(*byte as u64));
+            (view.width, view.height, hash)
+        }).unwrap();
+        assert_eq!((width, height), (copied.width, copied.height));
+        println!("frame {}: {}x{}, hash {:016x}", frame.frame_id(), width, height, hash);
+        *callback_compared_frames.lock() += 1;
+    }).unwrap();
+    std::thread::sleep(Duration::from_millis(1000));
+    stream.close().await.unwrap();
+
+    assert!(*compared_frames.lock() > 0, "Expected frames to compare");
+}
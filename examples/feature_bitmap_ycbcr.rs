@@ -0,0 +1,54 @@
+use crabgrab::feature::bitmap::{FrameBitmapPool, FrameBitmapYCbCr, VideoRange, YCbCrColorMatrix};
+
+// Converts YCbCr bitmaps with known values to RGB and checks them against their expected BT.709 colors, without capturing anything
+fn main() {
+    // (range, [y, cb, cr], expected [r, g, b])
+    let golden_values = [
+        (VideoRange::Video, [ 16, 128, 128], [  0,   0,   0]),
+        (VideoRange::Video, [235, 128, 128], [255, 255, 255]),
+        (VideoRange::Video, [ 63, 102, 240], [255,   0,   0]),
+        (VideoRange::Video, [173,  42,  26], [  0, 255,   0]),
+        (VideoRange::Video, [ 32, 240, 118], [  0,   0, 255]),
+        (VideoRange::Full,  [  0, 128, 128], [  0,   0,   0]),
+        (VideoRange::Full,  [255, 128, 128], [255, 255, 255]),
+        (VideoRange::Full,  [ 54,  99, 255], [255,   0,   0]),
+    ];
+    for (range, [y, cb, cr], expected) in golden_values {
+        let bitmap = FrameBitmapYCbCr {
+            luma_data: vec![y; 4].into_boxed_slice(),
+            luma_width: 2,
+            luma_height: 2,
+            chroma_data: vec![[cb, cr]].into_boxed_slice(),
+            chroma_width: 1,
+            chroma_height: 1,
+            range,
+            color_matrix: YCbCrColorMatrix::ItuR709,
+            change_metric: None,
+        };
+        let [r, g, b, a] = bitmap.to_rgba_unorm8().data[0];
+        println!("{:?} {:?} -> {:?}", range, [y, cb, cr], [r, g, b]);
+        // The 8 bit YCbCr values are rounded, so allow an off-by-one
+        assert!([r, g, b].iter().zip(expected).all(|(actual, expected)| actual.abs_diff(expected) <= 1), "Expected {:?}", expected);
+        assert_eq!(a, 255);
+        assert_eq!(bitmap.to_bgra_unorm8().data[0], [b, g, r, a]);
+    }
+
+    // With odd dimensions, the last chroma column and row cover a single luma column and row
+    let bitmap = FrameBitmapYCbCr {
+        luma_data: vec![128; 9].into_boxed_slice(),
+        luma_width: 3,
+        luma_height: 3,
+        chroma_data: vec![[128, 128], [128, 240], [240, 128], [240, 240]].into_boxed_slice(),
+        chroma_width: 2,
+        chroma_height: 2,
+        range: VideoRange::Full,
+        color_matrix: YCbCrColorMatrix::ItuR709,
+        change_metric: None,
+    };
+    // The pooled conversion matches the boxed one
+    let pool = FrameBitmapPool::new(1);
+    let pooled = bitmap.to_pooled_bgra_unorm8(&pool);
+    assert_eq!((pooled.width, pooled.height), (3, 3));
+    assert!(pooled.data.as_ref() == bitmap.to_bgra_unorm8().data.as_ref());
+    println!("odd sized conversion: {:?}", bitmap.to_rgba_unorm8().data);
+}
@@ -0,0 +1,55 @@
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::time::Duration;
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use crabgrab::prelude::*;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use crabgrab::feature::content_picker::{pick_sharable_content, PickedSharableContent, SharableContentPickerConfig, SharableContentPickerError};
+
+// Shows the system content picker, then captures a few frames of whatever was picked - this needs someone to pick a window or display.
+// Also checks that configs the picker can't honor are rejected up front.
+// (requires the `content_picker` feature)
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+
+    // The windows picker always offers both windows and displays
+    #[cfg(target_os = "windows")]
+    {
+        let windows_only = SharableContentPickerConfig { display: false, ..Default::default() };
+        assert!(matches!(pick_sharable_content(windows_only).await, Err(SharableContentPickerError::ConfigFilteringUnsupported)));
+    }
+    let nothing = SharableContentPickerConfig { display: false, window: false, ..Default::default() };
+    assert!(matches!(pick_sharable_content(nothing).await, Err(SharableContentPickerError::EmptyConfig)));
+
+    let config = match pick_sharable_content(SharableContentPickerConfig::default()).await.unwrap() {
+        Some(PickedSharableContent::Window(window)) => {
+            println!("picked window: {}", window.title());
+            CaptureConfig::with_window(window, CapturePixelFormat::Bgra8888).unwrap()
+        },
+        Some(PickedSharableContent::Display(display)) => {
+            println!("picked display: {}", display.name());
+            CaptureConfig::with_display(display, CapturePixelFormat::Bgra8888)
+        },
+        None => {
+            println!("picker cancelled");
+            return;
+        }
+    };
+    let stream = CaptureStream::new(token, config, |result| {
+        if let Ok(StreamEvent::Video(frame)) = result {
+            println!("frame {}: {:?}", frame.frame_id(), frame.size());
+        }
+    }).unwrap();
+    std::thread::sleep(Duration::from_millis(1000));
+    stream.close().await.unwrap();
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn main() {
+    println!("The content picker is only available on MacOS and Windows");
+}
@@ -0,0 +1,36 @@
+use std::{sync::Arc, time::Duration};
+
+use crabgrab::{feature::diagnostic::{FrameDiagnostic, FrameDiagnosticExt}, prelude::*};
+use parking_lot::Mutex;
+
+// Captures a display with the CGDisplayStream backend on MacOS, and prints the diagnostic of its first frame
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let display = content.displays().next().unwrap();
+    let config = CaptureConfig::with_display(display, CapturePixelFormat::Bgra8888);
+    #[cfg(target_os = "macos")]
+    let config = {
+        use crabgrab::platform::macos::{MacosCaptureConfigExt as _, MacosDisplayCaptureBackend};
+        config.with_display_capture_backend(MacosDisplayCaptureBackend::CGDisplayStream)
+    };
+
+    let diagnostic: Arc<Mutex<Option<FrameDiagnostic>>> = Arc::new(Mutex::new(None));
+    let callback_diagnostic = diagnostic.clone();
+    let stream = CaptureStream::new(token, config, move |result| {
+        if let Ok(StreamEvent::Video(frame)) = result {
+            callback_diagnostic.lock().get_or_insert_with(|| frame.diagnostic());
+        }
+    }).unwrap();
+    std::thread::sleep(Duration::from_millis(1000));
+    stream.close().await.unwrap();
+
+    let diagnostic = diagnostic.lock().take().expect("Expected a frame");
+    println!("Frame diagnostic: {:?}", diagnostic);
+    #[cfg(target_os = "macos")]
+    assert!(diagnostic.iosurface_info.is_some(), "Expected the frame's IOSurface info");
+}
@@ -0,0 +1,63 @@
+#[cfg(target_os = "macos")]
+use std::sync::Arc;
+#[cfg(target_os = "macos")]
+use std::time::Duration;
+
+#[cfg(target_os = "macos")]
+use crabgrab::feature::iosurface::{IoSurfacePixelFormat, MacosIoSurfaceVideoFrameExt as _};
+#[cfg(target_os = "macos")]
+use crabgrab::prelude::*;
+#[cfg(target_os = "macos")]
+use parking_lot::Mutex;
+
+// Captures a display as 4:2:0 YCbCr, then locks the IOSurface of a frame and prints the layout of its planes,
+// checking that the chroma plane is half the size of the luminance plane
+// (requires the `iosurface` feature)
+#[cfg(target_os = "macos")]
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let config = CaptureConfig::with_display(content.displays().next().unwrap(), CapturePixelFormat::V420);
+
+    let latest_frame = Arc::new(Mutex::new(None));
+    let callback_latest_frame = latest_frame.clone();
+    let stream = CaptureStream::new(token, config, move |result| {
+        if let Ok(StreamEvent::Video(frame)) = result {
+            *callback_latest_frame.lock() = Some(frame);
+        }
+    }).unwrap();
+    std::thread::sleep(Duration::from_millis(500));
+    let frame = latest_frame.lock().take().expect("Expected a frame");
+    stream.close().await.unwrap();
+
+    let surface = frame.get_iosurface().expect("Expected an IOSurface");
+    // The surface stays valid after the frame is gone
+    drop(frame);
+    println!("surface: {}x{}, {:?}", surface.get_width(), surface.get_height(), surface.get_pixel_format());
+    assert_eq!(surface.get_pixel_format(), IoSurfacePixelFormat::V420, "Expected a 4:2:0 surface");
+    assert_eq!(surface.get_plane_count(), 2, "Expected luminance and chroma planes");
+
+    let lock = surface.lock(true).expect("Expected to lock the surface");
+    for plane in 0..surface.get_plane_count() {
+        let data = lock.plane_data(plane).expect("Expected plane data");
+        println!("plane {}: {}x{}, {} bytes per row, {} bytes",
+            plane,
+            surface.get_width_of_plane(plane),
+            surface.get_height_of_plane(plane),
+            surface.get_bytes_per_row_of_plane(plane),
+            data.len()
+        );
+    }
+    assert!(lock.plane_data(surface.get_plane_count()).is_none(), "Expected no data past the last plane");
+    assert_eq!(surface.get_width_of_plane(1), surface.get_width_of_plane(0) / 2);
+    assert_eq!(surface.get_height_of_plane(1), surface.get_height_of_plane(0) / 2);
+}
+
+#[cfg(not(target_os = "macos"))]
+fn main() {
+    println!("IOSurfaces are only available on MacOS");
+}
@@ -0,0 +1,60 @@
+#[cfg(target_os = "macos")]
+use std::sync::Arc;
+#[cfg(target_os = "macos")]
+use std::time::Duration;
+
+#[cfg(target_os = "macos")]
+use crabgrab::feature::metal::{MetalCapturableDisplayExt as _, MetalCaptureStreamExt as _, MetalVideoFrameExt as _, MetalVideoFramePlaneTexture};
+#[cfg(target_os = "macos")]
+use crabgrab::prelude::*;
+#[cfg(target_os = "macos")]
+use parking_lot::Mutex;
+
+// Prints the metal device driving each display, then captures the first display without choosing a metal device, and
+// checks that the stream and its frames' textures use the display's device. The check is skipped on Macs with a single GPU
+// (requires the `metal` feature)
+#[cfg(target_os = "macos")]
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    for display in content.displays() {
+        let device_name = display.preferred_metal_device().map(|device| device.name().to_string());
+        println!("display: {}, metal device: {:?}", display.name(), device_name);
+    }
+
+    let device_count = metal::Device::all().len();
+    if device_count < 2 {
+        println!("skipping the device check, as there's only {} metal device", device_count);
+        return;
+    }
+
+    let display = content.displays().next().unwrap();
+    let display_device = display.preferred_metal_device().expect("Expected a metal device for the display");
+    let config = CaptureConfig::with_display(display, CapturePixelFormat::Bgra8888);
+
+    let latest_frame = Arc::new(Mutex::new(None));
+    let callback_latest_frame = latest_frame.clone();
+    let stream = CaptureStream::new(token, config, move |result| {
+        if let Ok(StreamEvent::Video(frame)) = result {
+            *callback_latest_frame.lock() = Some(frame);
+        }
+    }).unwrap();
+    std::thread::sleep(Duration::from_millis(500));
+    let stream_device = stream.get_metal_device();
+    let frame = latest_frame.lock().take().expect("Expected a frame");
+    stream.close().await.unwrap();
+
+    println!("stream metal device: {}", stream_device.name());
+    assert_eq!(stream_device.registry_id(), display_device.registry_id(), "Expected the stream to use the display's metal device");
+    let texture = frame.get_metal_texture(MetalVideoFramePlaneTexture::Rgba).expect("Expected a metal texture");
+    assert_eq!(texture.device().registry_id(), display_device.registry_id(), "Expected the frame's texture to be on the display's metal device");
+}
+
+#[cfg(not(target_os = "macos"))]
+fn main() {
+    println!("Metal devices are only available on MacOS");
+}
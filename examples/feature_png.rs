@@ -0,0 +1,68 @@
+use crabgrab::{feature::bitmap::VideoFrameBitmap as _, prelude::*};
+
+// Screenshots the first display in each supported pixel format, saves it as a PNG, and reads the pixels back out of the file
+// to check they match the frame's bitmap (requires the `png` and `screenshot` features)
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let display = content.displays().next().unwrap();
+
+    for (i, pixel_format) in CaptureStream::supported_pixel_formats().iter().enumerate() {
+        let config = CaptureConfig::with_display(display.clone(), *pixel_format);
+        let frame = crabgrab::feature::screenshot::take_screenshot(token, config).await.unwrap();
+        let path = std::env::temp_dir().join(format!("crabgrab_screenshot_{}.png", i));
+        frame.save_png(&path).unwrap();
+
+        let expected = frame.get_bitmap().unwrap().to_rgba_unorm8();
+        let (width, height, pixels) = read_png(&std::fs::read(&path).unwrap());
+        assert_eq!((width, height), (expected.width, expected.height), "Expected the png to be the size of the frame");
+        assert!(pixels == expected.data.iter().flatten().copied().collect::<Vec<_>>(), "Expected the png to contain the frame's pixels");
+        println!("{:?}: saved {}x{} frame to {}", pixel_format, width, height, path.display());
+    }
+}
+
+// Read back the width, height and Rgba8888 pixels of a PNG written by `save_png`, which only uses uncompressed deflate blocks and no row filters
+fn read_png(png: &[u8]) -> (usize, usize, Vec<u8>) {
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n", "Expected a png signature");
+    let (mut width, mut height, mut zlib_stream) = (0, 0, Vec::new());
+    let mut offset = 8;
+    while offset < png.len() {
+        let len = u32::from_be_bytes(png[offset..offset + 4].try_into().unwrap()) as usize;
+        let data = &png[offset + 8..offset + 8 + len];
+        match &png[offset + 4..offset + 8] {
+            b"IHDR" => {
+                width = u32::from_be_bytes(data[0..4].try_into().unwrap()) as usize;
+                height = u32::from_be_bytes(data[4..8].try_into().unwrap()) as usize;
+                assert_eq!(&data[8..10], &[8, 6], "Expected 8 bit rgba");
+            },
+            b"IDAT" => zlib_stream.extend_from_slice(data),
+            _ => {}
+        }
+        offset += len + 12;
+    }
+
+    // Skip the zlib header, then concatenate the stored blocks
+    let mut image_data = Vec::new();
+    let mut offset = 2;
+    loop {
+        let is_final = zlib_stream[offset] & 1 != 0;
+        assert_eq!(zlib_stream[offset] >> 1, 0, "Expected a stored deflate block");
+        let len = u16::from_le_bytes([zlib_stream[offset + 1], zlib_stream[offset + 2]]) as usize;
+        image_data.extend_from_slice(&zlib_stream[offset + 5..offset + 5 + len]);
+        offset += len + 5;
+        if is_final {
+            break;
+        }
+    }
+
+    // Strip each row's filter type byte
+    let pixels = image_data.chunks_exact(width * 4 + 1).flat_map(|row| {
+        assert_eq!(row[0], 0, "Expected unfiltered rows");
+        row[1..].iter().copied()
+    }).collect();
+    (width, height, pixels)
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use crabgrab::{feature::bitmap::VideoFrameBitmap as _, prelude::*};
 use futures::executor::block_on;
  
@@ -5,7 +7,13 @@ fn main() {
     block_on(async {
         let token = match CaptureStream::test_access(false) {
             Some(token) => token,
-            None => CaptureStream::request_access(false).await.expect("Expected capture access")
+            None => match CaptureStream::request_access(false).await {
+                Some(token) => token,
+                None => {
+                    handle_permission_denied();
+                    return;
+                }
+            }
         };
         let filter = CapturableContentFilter::NORMAL_WINDOWS;
         let content = CapturableContent::new(filter).await.unwrap();
@@ -17,16 +25,18 @@ fn main() {
             Some(window) => {
                 println!("screenshotting window: {}", window.title()); 
                 let config = CaptureConfig::with_window(window, CaptureStream::supported_pixel_formats()[0]).unwrap();
-                match crabgrab::feature::screenshot::take_screenshot(token, config).await {
+                // A minimized window may never produce a frame
+                match crabgrab::feature::screenshot::take_screenshot_with_timeout(token, config, Duration::from_secs(5)).await {
                     Ok(frame) => { 
                         println!("Got frame: {}", frame.frame_id());
                         match frame.get_bitmap() {
                             Ok(bitmap) => {
                                 match bitmap {
                                     crabgrab::feature::bitmap::FrameBitmap::BgraUnorm8x4(_) => println!("format: BgraUnorm8x4"),
-                                    crabgrab::feature::bitmap::FrameBitmap::ArgbUnormPacked2101010(_) => println!("format: RgbaUnormPacked1010102"),
+                                    crabgrab::feature::bitmap::FrameBitmap::ArgbUnormPacked2101010(_) => println!("format: ArgbUnormPacked2101010"),
                                     crabgrab::feature::bitmap::FrameBitmap::RgbaF16x4(_) => println!("format: RgbaF16x4"),
                                     crabgrab::feature::bitmap::FrameBitmap::YCbCr(_) => println!("format: YCbCr"),
+                                    crabgrab::feature::bitmap::FrameBitmap::RgbaUnorm16x4(_) => println!("format: RgbaUnorm16x4"),
                                 }
                             },
                             Err(e) => {
@@ -34,6 +44,8 @@ fn main() {
                             }
                         }
                     },
+                    Err(crabgrab::feature::screenshot::ScreenshotError::PermissionDenied) => handle_permission_denied(),
+                    Err(crabgrab::feature::screenshot::ScreenshotError::Timeout) => println!("screenshot timed out - is the window minimized?"),
                     Err(_) => println!("screenshot failed!"),
                 }
             },
@@ -41,3 +53,13 @@ fn main() {
         }
     });
 }
+
+// Send the user to the OS settings page where capture access can be granted
+fn handle_permission_denied() {
+    println!("Capture access was denied");
+    match CaptureStream::open_permission_settings() {
+        Ok(OpenSettingsOutcome::Opened) => println!("Grant capture access in the settings page that was opened, then run this example again"),
+        Ok(OpenSettingsOutcome::NeedsRestart) => println!("Grant capture access in the settings page that was opened, then restart your terminal and run this example again"),
+        Err(error) => println!("Failed to open capture permission settings: {} (settings url: {:?})", error, CaptureStream::permission_settings_url()),
+    }
+}
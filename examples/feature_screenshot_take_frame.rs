@@ -0,0 +1,29 @@
+use crabgrab::feature::bitmap::{FrameBitmap, VideoFrameBitmap as _};
+use crabgrab::feature::screenshot::take_frame;
+use crabgrab::prelude::*;
+use futures::executor::block_on;
+
+// Grabs a single frame of the first display synchronously, without an async runtime or a stream, and checks that its bitmap
+// is the size of the frame
+// (requires the `screenshot` feature)
+fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => block_on(CaptureStream::request_access(false)).expect("Expected capture access")
+    };
+    let content = block_on(CapturableContent::new(CapturableContentFilter::DISPLAYS)).unwrap();
+    let display = content.displays().next().unwrap();
+    println!("grabbing a frame of display: {}", display.name());
+    let config = CaptureConfig::with_display(display, CapturePixelFormat::Bgra8888);
+
+    let frame = take_frame(token, config).expect("Expected a frame");
+    let frame_size = frame.size();
+    println!("frame size: {:?}", frame_size);
+    match frame.get_bitmap().expect("Expected a bitmap") {
+        FrameBitmap::BgraUnorm8x4(bitmap) => {
+            assert_eq!((bitmap.width as f64, bitmap.height as f64), (frame_size.width, frame_size.height), "Expected the bitmap to be the size of the frame");
+            assert_eq!(bitmap.data.len(), bitmap.width * bitmap.height);
+        },
+        _ => panic!("Expected a BgraUnorm8x4 bitmap"),
+    }
+}
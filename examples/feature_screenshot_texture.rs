@@ -0,0 +1,45 @@
+use crabgrab::feature::screenshot::take_screenshot;
+use crabgrab::prelude::*;
+
+// Takes a screenshot of every window on screen, as for thumbnails, and checks that each screenshot's GPU texture is the size
+// of the frame, without making any bitmaps
+// (requires the `screenshot` feature, and the `metal` feature on MacOS or the `dx11` feature on Windows)
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::NORMAL_WINDOWS).await.unwrap();
+    for window in content.windows() {
+        let config = match CaptureConfig::with_window(window.clone(), CapturePixelFormat::Bgra8888) {
+            Ok(config) => config,
+            Err(_) => continue,
+        };
+        let frame = match take_screenshot(token, config).await {
+            Ok(frame) => frame,
+            Err(error) => {
+                println!("{}: failed to take screenshot: {}", window.title(), error);
+                continue;
+            }
+        };
+        let frame_size = frame.size();
+        let texture_size = texture_size(&frame);
+        println!("{}: frame size {:?}, texture size {:?}", window.title(), frame_size, texture_size);
+        assert_eq!(texture_size, (frame_size.width as u64, frame_size.height as u64), "Expected the texture to be the size of the frame");
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn texture_size(frame: &VideoFrame) -> (u64, u64) {
+    let texture = frame.get_metal_texture(MetalVideoFramePlaneTexture::Rgba).expect("Expected a metal texture");
+    (texture.width(), texture.height())
+}
+
+#[cfg(target_os = "windows")]
+fn texture_size(frame: &VideoFrame) -> (u64, u64) {
+    let (texture, _) = frame.get_dx11_texture().expect("Expected a dx11 texture");
+    let mut desc = windows::Win32::Graphics::Direct3D11::D3D11_TEXTURE2D_DESC::default();
+    unsafe { texture.GetDesc(&mut desc as *mut _) };
+    (desc.Width as u64, desc.Height as u64)
+}
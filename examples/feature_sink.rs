@@ -0,0 +1,60 @@
+use std::{sync::Arc, time::Duration};
+
+use crabgrab::prelude::*;
+use crabgrab::feature::sink::{PngSequenceSink, SinkError, VideoFrameSink};
+use parking_lot::Mutex;
+
+// Wraps another sink, counting its frames and how many times it's ended
+struct CountingSink<Sink: VideoFrameSink> {
+    sink: Sink,
+    counts: Arc<Mutex<(usize, usize)>>,
+}
+
+impl<Sink: VideoFrameSink> VideoFrameSink for CountingSink<Sink> {
+    fn on_frame(&mut self, frame: &VideoFrame) -> Result<(), SinkError> {
+        self.sink.on_frame(frame)?;
+        self.counts.lock().0 += 1;
+        Ok(())
+    }
+
+    fn on_end(&mut self) {
+        self.sink.on_end();
+        self.counts.lock().1 += 1;
+    }
+}
+
+// Records a display to a PNG sequence in the temp directory, dropping the stream mid-capture,
+// and checks that a file was written for every frame and the sink was ended exactly once
+// (requires the `sink` and `png` features)
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let display = content.displays().next().unwrap();
+    let config = CaptureConfig::with_display(display, CapturePixelFormat::Bgra8888);
+
+    let directory = std::env::temp_dir().join("crabgrab_feature_sink");
+    let _ = std::fs::remove_dir_all(&directory);
+    let png_sink = PngSequenceSink::new(&directory).unwrap();
+    let first_frame_path = png_sink.frame_path(0);
+    let counts = Arc::new(Mutex::new((0usize, 0usize)));
+    let stream = CaptureStream::new_with_sink(token, &config, CountingSink {
+        sink: png_sink,
+        counts: counts.clone(),
+    }).unwrap();
+    std::thread::sleep(Duration::from_millis(1000));
+    drop(stream);
+    // The platform may deliver the end of the stream shortly after it's dropped
+    std::thread::sleep(Duration::from_millis(200));
+
+    let (frames, ends) = *counts.lock();
+    let files = std::fs::read_dir(&directory).unwrap().count();
+    println!("{} frames saved to {}", frames, directory.display());
+    assert!(frames > 0, "Expected some frames to be saved");
+    assert!(first_frame_path.exists(), "Expected the first frame to be saved");
+    assert_eq!(files, frames, "Expected a file for every frame");
+    assert_eq!(ends, 1, "Expected the sink to be ended exactly once");
+}
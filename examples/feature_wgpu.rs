@@ -68,7 +68,7 @@ fn main() {
                                     .expect("Expected to send result");
                             }
                         },
-                        StreamEvent::End => {
+                        StreamEvent::End { .. } => {
                             if let Some(tx_result) = tx_result.take() {
                                 tx_result.send(Ok(None))
                                     .expect("Expected to send result");
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::executor::block_on;
+use crabgrab::prelude::*;
+use crabgrab::feature::wgpu::{create_shareable_wgpu_texture, WgpuCaptureConfigExt as _, WgpuCaptureStreamExt as _, WgpuVideoFrameExt as _};
+use parking_lot::Mutex;
+
+const FRAME_COUNT: usize = 120;
+
+#[allow(unused)]
+struct Gfx {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+}
+
+impl AsRef<wgpu::Device> for Gfx {
+    fn as_ref(&self) -> &wgpu::Device {
+        &self.device
+    }
+}
+
+// Copies a couple of seconds of frames into a single shareable wgpu texture, reporting how long each copy took, and checks
+// that on Windows the frames were copied straight into it rather than through textures of the stream's own.
+// Note that frames are only produced when the display content changes, so leave something animating on screen
+// (requires the `wgpu` feature)
+fn main() {
+    block_on(async {
+        let token = match CaptureStream::test_access(false) {
+            Some(token) => token,
+            None => CaptureStream::request_access(false).await.expect("Expected capture access")
+        };
+        let wgpu_instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            #[cfg(target_os = "windows")]
+            backends: wgpu::Backends::DX12,
+            #[cfg(target_os = "macos")]
+            backends: wgpu::Backends::METAL,
+            ..Default::default()
+        });
+        let wgpu_adapter = wgpu_instance.request_adapter(&wgpu::RequestAdapterOptions::default()).await
+            .expect("Expected wgpu adapter");
+        let (device, queue) = wgpu_adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("copy to texture device"),
+            required_features: wgpu::Features::default(),
+            required_limits: wgpu::Limits::default(),
+        }, None).await.expect("Expected wgpu device");
+        let gfx = Arc::new(Gfx { device, queue });
+
+        let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await
+            .expect("Expected to get capturable displays");
+        let display = content.displays().next()
+            .expect("Expected at least one capturable display");
+        let config = CaptureConfig::with_display(display, CapturePixelFormat::Bgra8888)
+            .with_wgpu_device(gfx.clone())
+            .expect("Expected config with wgpu device");
+
+        let latest_frame = Arc::new(Mutex::new(None));
+        let callback_latest_frame = latest_frame.clone();
+        let stream = CaptureStream::new(token, config, move |event_result| {
+            if let Ok(StreamEvent::Video(frame)) = event_result {
+                *callback_latest_frame.lock() = Some(frame);
+            }
+        }).expect("Expected capture stream");
+
+        let mut cached_destination = None;
+        let mut copy_count = 0;
+        let mut copy_time = Duration::ZERO;
+        for _ in 0..FRAME_COUNT {
+            std::thread::sleep(Duration::from_millis(16));
+            let Some(frame) = latest_frame.lock().take() else {
+                continue;
+            };
+            let (width, height) = (frame.size().width, frame.size().height);
+            // The destination is recreated if the display changes size
+            let destination = match cached_destination.take() {
+                Some(destination) if (destination.width(), destination.height()) == (width as u32, height as u32) => destination,
+                _ => create_shareable_wgpu_texture(&gfx.device, &wgpu::TextureDescriptor {
+                    label: Some("frame destination"),
+                    size: wgpu::Extent3d { width: width as u32, height: height as u32, depth_or_array_layers: 1 },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Bgra8Unorm,
+                    usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                }).expect("Expected shareable texture"),
+            };
+            let start = Instant::now();
+            let mut encoder = gfx.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("frame copy") });
+            frame.copy_to_wgpu_texture(WgpuVideoFramePlaneTexture::Rgba, &mut encoder, &destination)
+                .expect("Expected to copy frame");
+            gfx.queue.submit([encoder.finish()]);
+            drop(frame);
+            gfx.device.poll(wgpu::Maintain::Wait);
+            copy_time += start.elapsed();
+            copy_count += 1;
+            cached_destination = Some(destination);
+        }
+        let allocation_count = stream.wgpu_texture_allocation_count();
+        stream.close().await.unwrap();
+
+        assert!(copy_count > 0, "Expected some frames");
+        println!("copied {} frames in {:?} each, with {} intermediate texture allocations", copy_count, copy_time / copy_count, allocation_count);
+        assert_eq!(allocation_count, 0, "Expected frames to be copied straight into the shareable texture");
+    });
+}
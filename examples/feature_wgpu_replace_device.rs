@@ -0,0 +1,86 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::executor::block_on;
+use crabgrab::prelude::*;
+use crabgrab::feature::wgpu::{WgpuCaptureConfigExt as _, WgpuCaptureStreamExt as _, WgpuVideoFrameExt as _};
+use parking_lot::Mutex;
+
+#[allow(unused)]
+struct Gfx {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+}
+
+impl AsRef<wgpu::Device> for Gfx {
+    fn as_ref(&self) -> &wgpu::Device {
+        &self.device
+    }
+}
+
+async fn create_gfx(wgpu_adapter: &wgpu::Adapter, label: &'static str) -> Arc<Gfx> {
+    let (device, queue) = wgpu_adapter.request_device(&wgpu::DeviceDescriptor {
+        label: Some(label),
+        required_features: wgpu::Features::default(),
+        required_limits: wgpu::Limits::default(),
+    }, None).await.expect("Expected wgpu device");
+    Arc::new(Gfx { device, queue })
+}
+
+// Destroys the wgpu device mid-capture and swaps in a new one, checking that textures are still produced for new frames
+fn main() {
+    block_on(async {
+        let token = match CaptureStream::test_access(false) {
+            Some(token) => token,
+            None => CaptureStream::request_access(false).await.expect("Expected capture access")
+        };
+        let wgpu_instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            #[cfg(target_os = "windows")]
+            backends: wgpu::Backends::DX12,
+            #[cfg(target_os = "macos")]
+            backends: wgpu::Backends::METAL,
+            ..Default::default()
+        });
+        let wgpu_adapter = wgpu_instance.request_adapter(&wgpu::RequestAdapterOptions::default()).await
+            .expect("Expected wgpu adapter");
+        let original_gfx = create_gfx(&wgpu_adapter, "original device").await;
+
+        let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await
+            .expect("Expected to get capturable displays");
+        let display = content.displays().next()
+            .expect("Expected at least one capturable display");
+        let config = CaptureConfig::with_display(display, CapturePixelFormat::Bgra8888)
+            .with_wgpu_device(original_gfx.clone())
+            .expect("Expected config with wgpu device");
+
+        let latest_frame = Arc::new(Mutex::new(None));
+        let callback_latest_frame = latest_frame.clone();
+        let mut stream = CaptureStream::new(token, config, move |event_result| {
+            if let Ok(StreamEvent::Video(frame)) = event_result {
+                *callback_latest_frame.lock() = Some(frame);
+            }
+        }).expect("Expected capture stream");
+
+        let report_texture = |stage: &str| {
+            std::thread::sleep(Duration::from_millis(500));
+            match latest_frame.lock().as_ref().map(|frame| frame.get_wgpu_texture(WgpuVideoFramePlaneTexture::Rgba, Some("frame texture"))) {
+                Some(Ok(texture)) => println!("{}: got {}x{} texture", stage, texture.width(), texture.height()),
+                Some(Err(error)) => println!("{}: {}", stage, error),
+                None => println!("{}: no frame yet (frames are only produced when the display content changes)", stage),
+            }
+        };
+
+        report_texture("original device");
+        original_gfx.device.destroy();
+        drop(original_gfx);
+        report_texture("original device destroyed");
+
+        let replacement_gfx = create_gfx(&wgpu_adapter, "replacement device").await;
+        stream.replace_wgpu_device(replacement_gfx.clone())
+            .expect("Expected to replace wgpu device");
+        *latest_frame.lock() = None;
+        report_texture("replacement device");
+
+        stream.close().await.expect("Expected to close stream");
+    });
+}
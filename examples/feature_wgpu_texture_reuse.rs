@@ -0,0 +1,88 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::executor::block_on;
+use crabgrab::prelude::*;
+use crabgrab::feature::wgpu::{WgpuCaptureConfigExt as _, WgpuCaptureStreamExt as _, WgpuVideoFrameExt as _};
+use parking_lot::Mutex;
+
+const FRAME_COUNT: usize = 120;
+
+#[allow(unused)]
+struct Gfx {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+}
+
+impl AsRef<wgpu::Device> for Gfx {
+    fn as_ref(&self) -> &wgpu::Device {
+        &self.device
+    }
+}
+
+// Gets a wgpu texture for a couple of seconds of frames, dropping each texture before the next, and checks that the stream
+// reuses a few textures rather than creating one for every frame - also reports how long getting each texture took.
+// Note that frames are only produced when the display content changes, so leave something animating on screen
+// (requires the `wgpu` feature)
+fn main() {
+    block_on(async {
+        let token = match CaptureStream::test_access(false) {
+            Some(token) => token,
+            None => CaptureStream::request_access(false).await.expect("Expected capture access")
+        };
+        let wgpu_instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            #[cfg(target_os = "windows")]
+            backends: wgpu::Backends::DX12,
+            #[cfg(target_os = "macos")]
+            backends: wgpu::Backends::METAL,
+            ..Default::default()
+        });
+        let wgpu_adapter = wgpu_instance.request_adapter(&wgpu::RequestAdapterOptions::default()).await
+            .expect("Expected wgpu adapter");
+        let (device, queue) = wgpu_adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("texture reuse device"),
+            required_features: wgpu::Features::default(),
+            required_limits: wgpu::Limits::default(),
+        }, None).await.expect("Expected wgpu device");
+        let gfx = Arc::new(Gfx { device, queue });
+
+        let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await
+            .expect("Expected to get capturable displays");
+        let display = content.displays().next()
+            .expect("Expected at least one capturable display");
+        let config = CaptureConfig::with_display(display, CapturePixelFormat::Bgra8888)
+            .with_wgpu_device(gfx.clone())
+            .expect("Expected config with wgpu device");
+
+        let latest_frame = Arc::new(Mutex::new(None));
+        let callback_latest_frame = latest_frame.clone();
+        let stream = CaptureStream::new(token, config, move |event_result| {
+            if let Ok(StreamEvent::Video(frame)) = event_result {
+                *callback_latest_frame.lock() = Some(frame);
+            }
+        }).expect("Expected capture stream");
+
+        let mut texture_count = 0;
+        let mut texture_time = Duration::ZERO;
+        for _ in 0..FRAME_COUNT {
+            std::thread::sleep(Duration::from_millis(16));
+            let Some(frame) = latest_frame.lock().take() else {
+                continue;
+            };
+            let start = Instant::now();
+            let texture = frame.get_wgpu_texture(WgpuVideoFramePlaneTexture::Rgba, Some("frame texture"))
+                .expect("Expected wgpu texture");
+            texture_time += start.elapsed();
+            texture_count += 1;
+            drop(texture);
+            // Lets wgpu release the dropped texture
+            gfx.device.poll(wgpu::Maintain::Wait);
+        }
+        let allocation_count = stream.wgpu_texture_allocation_count();
+        stream.close().await.unwrap();
+
+        assert!(texture_count > 0, "Expected some frames");
+        println!("got {} textures in {:?} each, with {} texture allocations", texture_count, texture_time / texture_count, allocation_count);
+        assert!(allocation_count <= 4, "Expected textures to be reused, but {} were allocated for {} frames", allocation_count, texture_count);
+    });
+}
@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use crabgrab::prelude::*;
+
+// A solid color rectangle in the corner of every frame
+struct Watermark {
+    origin: (usize, usize),
+    size: (usize, usize),
+    bgra: [u8; 4],
+    use_gpu: bool,
+}
+
+impl Watermark {
+    // The watermark's rectangle, clipped to a frame of the given size
+    fn clipped_rect(&self, width: usize, height: usize) -> Option<(usize, usize, usize, usize)> {
+        let x = self.origin.0.min(width);
+        let y = self.origin.1.min(height);
+        let w = self.size.0.min(width - x);
+        let h = self.size.1.min(height - y);
+        (w > 0 && h > 0).then_some((x, y, w, h))
+    }
+
+    fn patch(&self, w: usize, h: usize) -> Vec<u8> {
+        self.bgra.repeat(w * h)
+    }
+}
+
+impl FrameCompositor for Watermark {
+    fn prefers_gpu(&self) -> bool {
+        self.use_gpu
+    }
+
+    fn composite(&mut self, target: CompositorTarget<'_>) {
+        match target {
+            CompositorTarget::Cpu { pixel_format: CapturePixelFormat::Bgra8888, mut planes } => {
+                let plane = &mut planes[0];
+                if let Some((x, y, w, h)) = self.clipped_rect(plane.width, plane.height) {
+                    for row in y..y + h {
+                        let start = row * plane.bytes_per_row + x * 4;
+                        plane.data[start..start + w * 4].copy_from_slice(&self.patch(w, 1));
+                    }
+                }
+            },
+            #[cfg(target_os = "windows")]
+            CompositorTarget::D3D11 { pixel_format: CapturePixelFormat::Bgra8888, texture, context } => unsafe {
+                use windows::Win32::Graphics::Direct3D11::{D3D11_BOX, D3D11_TEXTURE2D_DESC};
+                let mut desc = D3D11_TEXTURE2D_DESC::default();
+                texture.GetDesc(&mut desc as *mut _);
+                if let Some((x, y, w, h)) = self.clipped_rect(desc.Width as usize, desc.Height as usize) {
+                    let patch = self.patch(w, h);
+                    let dest_box = D3D11_BOX { left: x as u32, top: y as u32, front: 0, right: (x + w) as u32, bottom: (y + h) as u32, back: 1 };
+                    context.UpdateSubresource(texture, 0, Some(&dest_box as *const _), patch.as_ptr() as *const _, (w * 4) as u32, 0);
+                }
+            },
+            #[cfg(all(target_os = "macos", feature = "metal"))]
+            CompositorTarget::Metal { pixel_format: CapturePixelFormat::Bgra8888, textures, command_buffer } => {
+                let texture = &textures[0];
+                if let Some((x, y, w, h)) = self.clipped_rect(texture.width() as usize, texture.height() as usize) {
+                    let patch = self.patch(w, h);
+                    let buffer = texture.device().new_buffer_with_data(patch.as_ptr() as *const _, patch.len() as u64, metal::MTLResourceOptions::StorageModeShared);
+                    let blit_encoder = command_buffer.new_blit_command_encoder();
+                    blit_encoder.copy_from_buffer(
+                        &buffer, 0, (w * 4) as u64, patch.len() as u64,
+                        metal::MTLSize { width: w as u64, height: h as u64, depth: 1 },
+                        texture, 0, 0,
+                        metal::MTLOrigin { x: x as u64, y: y as u64, z: 0 },
+                    );
+                    blit_encoder.end_encoding();
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+// Draws a watermark into every frame before it's delivered - pass "cpu" to composite on the CPU where the platform allows it
+#[tokio::main]
+async fn main() {
+    let use_gpu = std::env::args().nth(1).as_deref() != Some("cpu");
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let watermark = Watermark {
+        origin: (32, 32),
+        size: (256, 64),
+        bgra: [0x20, 0x80, 0xff, 0xff],
+        use_gpu,
+    };
+    let config = CaptureConfig::with_display(content.displays().next().unwrap(), CapturePixelFormat::Bgra8888)
+        .with_frame_compositor(Box::new(watermark));
+
+    let stream = CaptureStream::new(token, config, |result| {
+        match result {
+            Ok(StreamEvent::Video(frame)) => println!("composited frame {}", frame.frame_id()),
+            Err(error) => println!("stream error: {}", error),
+            _ => {}
+        }
+    }).unwrap();
+
+    std::thread::sleep(Duration::from_millis(5000));
+
+    stream.close().await.unwrap();
+}
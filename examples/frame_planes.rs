@@ -0,0 +1,44 @@
+use std::{sync::Arc, time::Duration};
+
+use crabgrab::prelude::*;
+use parking_lot::Mutex;
+
+// Captures a display and reads the raw planes of the first frame without the bitmap feature, checking their layout
+// matches the stream's pixel format
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let display = content.displays().next().unwrap();
+    let config = CaptureConfig::with_display(display, CapturePixelFormat::Bgra8888);
+
+    // (format, width, height, bytes per row, average of the first row's bytes) of each plane
+    let planes: Arc<Mutex<Option<Vec<(CapturePixelFormat, usize, usize, usize, usize)>>>> = Arc::new(Mutex::new(None));
+    let callback_planes = planes.clone();
+    let stream = CaptureStream::new(token, config, move |result| {
+        let mut planes = callback_planes.lock();
+        if let (Ok(StreamEvent::Video(frame)), None) = (result, planes.as_ref()) {
+            *planes = Some(frame.map_planes(|planes| {
+                planes.iter().map(|plane| {
+                    assert_eq!(plane.data.len(), plane.bytes_per_row * plane.height, "Expected the plane to hold every row");
+                    let first_row = plane.row(0);
+                    let average = first_row.iter().map(|byte| *byte as usize).sum::<usize>() / first_row.len().max(1);
+                    (plane.format, plane.width, plane.height, plane.bytes_per_row, average)
+                }).collect()
+            }).unwrap());
+        }
+    }).unwrap();
+    std::thread::sleep(Duration::from_millis(1000));
+    stream.close().await.unwrap();
+
+    let planes = planes.lock().take().expect("Expected a frame");
+    assert_eq!(planes.len(), 1, "Expected a single Bgra8888 plane");
+    for (format, width, height, bytes_per_row, average) in planes {
+        println!("{:?} plane: {}x{}, {} bytes per row, first row average {}", format, width, height, bytes_per_row, average);
+        assert_eq!(format, CapturePixelFormat::Bgra8888);
+        assert!(bytes_per_row >= width * 4, "Expected each row to fit 4 bytes per pixel");
+    }
+}
@@ -0,0 +1,42 @@
+use std::{sync::Arc, time::Duration};
+
+#[cfg(target_os = "windows")]
+use crabgrab::platform::windows::WindowsCaptureConfigExt as _;
+use crabgrab::prelude::*;
+use parking_lot::Mutex;
+
+// Captures a display for 3 seconds and prints the video and idle events it delivers, checking that nothing is delivered after the end
+// of the stream. Leave the display static to see the stream go idle
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let config = CaptureConfig::with_display(content.displays().next().unwrap(), CapturePixelFormat::Bgra8888);
+    #[cfg(target_os = "windows")]
+    let config = config.with_idle_timeout(Some(Duration::from_millis(500)));
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let callback_events = events.clone();
+    let stream = CaptureStream::new(token, config, move |result| {
+        match result {
+            Ok(StreamEvent::Video(_)) => callback_events.lock().push("video"),
+            Ok(StreamEvent::Idle) => callback_events.lock().push("idle"),
+            Ok(StreamEvent::End { .. }) => callback_events.lock().push("end"),
+            _ => {}
+        }
+    }).unwrap();
+    std::thread::sleep(Duration::from_secs(3));
+    stream.close().await.unwrap();
+    // Give a late idle event the chance to arrive, if one was going to
+    std::thread::sleep(Duration::from_millis(1000));
+
+    let events = events.lock();
+    let video_count = events.iter().filter(|event| **event == "video").count();
+    let idle_count = events.iter().filter(|event| **event == "idle").count();
+    println!("{} video events, {} idle events", video_count, idle_count);
+    assert_eq!(events.last(), Some(&"end"), "Expected the stream to end with its last event");
+    assert_eq!(events.iter().filter(|event| **event == "end").count(), 1, "Expected the stream to end once");
+}
@@ -0,0 +1,51 @@
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::sync::atomic::{self, AtomicU64};
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::sync::Arc;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::time::Duration;
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use crabgrab::prelude::*;
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+const MAXIMUM_FPS: f32 = 5.0;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+const CAPTURE_SECONDS: u64 = 3;
+
+// Captures a display at 5fps for 3 seconds, and checks that the frame rate was capped.
+// Note that frames are only produced when the display content changes, so leave something animating on screen
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let config = CaptureConfig::with_display(content.displays().next().unwrap(), CapturePixelFormat::Bgra8888)
+        .with_maximum_fps(Some(MAXIMUM_FPS));
+
+    let frame_count = Arc::new(AtomicU64::new(0));
+    let callback_frame_count = frame_count.clone();
+    let stream = CaptureStream::new(token, config, move |result| {
+        if let Ok(StreamEvent::Video(_)) = result {
+            callback_frame_count.fetch_add(1, atomic::Ordering::AcqRel);
+        }
+    }).unwrap();
+    println!("effective config: {:?}", stream.effective_config());
+    std::thread::sleep(Duration::from_secs(CAPTURE_SECONDS));
+    stream.close().await.unwrap();
+
+    // Frames may be delivered slightly early, and the first frame isn't throttled
+    let frame_count = frame_count.load(atomic::Ordering::Acquire);
+    let maximum_frame_count = (CAPTURE_SECONDS as f32 * MAXIMUM_FPS * 1.15) as u64 + 1;
+    println!("captured {} frames, at most {} expected", frame_count, maximum_frame_count);
+    assert!(frame_count >= 1, "Expected some frames");
+    assert!(frame_count <= maximum_frame_count, "Expected at most {} frames, got {}", maximum_frame_count, frame_count);
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn main() {
+    println!("Maximum frame rates are only configurable on MacOS and Windows");
+}
@@ -0,0 +1,35 @@
+use std::{sync::{atomic::{AtomicU64, Ordering}, Arc}, time::Duration};
+
+use crabgrab::prelude::*;
+
+// Pauses a stream for a while between two recording intervals, counting the frames delivered in each
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let config = CaptureConfig::with_display(content.displays().next().unwrap(), CaptureStream::supported_pixel_formats()[0]);
+
+    let frame_count = Arc::new(AtomicU64::new(0));
+    let callback_frame_count = frame_count.clone();
+    let mut stream = CaptureStream::new(token, config, move |result| {
+        if let Ok(StreamEvent::Video(_)) = result {
+            callback_frame_count.fetch_add(1, Ordering::AcqRel);
+        }
+    }).unwrap();
+
+    std::thread::sleep(Duration::from_millis(1000));
+    println!("running: {} frames", frame_count.swap(0, Ordering::AcqRel));
+
+    stream.pause().unwrap();
+    std::thread::sleep(Duration::from_millis(1000));
+    println!("paused: {} frames", frame_count.swap(0, Ordering::AcqRel));
+
+    stream.resume().unwrap();
+    std::thread::sleep(Duration::from_millis(1000));
+    println!("resumed: {} frames", frame_count.swap(0, Ordering::AcqRel));
+
+    stream.close().await.unwrap();
+}
@@ -0,0 +1,29 @@
+use crabgrab::prelude::*;
+
+// Prints the pixel format chosen for each purpose on this platform, and checks the chosen formats are supported
+#[tokio::main]
+async fn main() {
+    let supported_pixel_formats = CaptureStream::supported_pixel_formats();
+    println!("Supported pixel formats: {:?}", supported_pixel_formats);
+    for purpose in [PixelFormatPurpose::CpuBitmap, PixelFormatPurpose::GpuTexture, PixelFormatPurpose::VideoEncode] {
+        let pixel_format = CaptureStream::preferred_pixel_format(purpose).expect("Expected a supported pixel format");
+        println!("{:?}: {:?}", purpose, pixel_format);
+        assert!(supported_pixel_formats.contains(&pixel_format));
+    }
+
+    // Pick from the application's own preferences - F420 isn't supported on Windows, so it falls back to Bgra8888 there
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let display = content.displays().next().unwrap();
+    let config = CaptureConfig::with_display(display.clone(), CapturePixelFormat::Bgra8888)
+        .with_any_pixel_format(&[CapturePixelFormat::F420, CapturePixelFormat::Bgra8888])
+        .unwrap();
+    println!("Chose {:?} from [F420, Bgra8888]", config.pixel_format());
+    assert!(supported_pixel_formats.contains(&config.pixel_format()));
+
+    // None of the preferences being supported is an error
+    let rgba16_unsupported = !supported_pixel_formats.contains(&CapturePixelFormat::Rgba16);
+    if rgba16_unsupported {
+        let result = CaptureConfig::with_display(display, CapturePixelFormat::Bgra8888).with_any_pixel_format(&[CapturePixelFormat::Rgba16]);
+        assert!(matches!(result, Err(CaptureConfigError::UnsupportedPixelFormat)));
+    }
+}
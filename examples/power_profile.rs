@@ -0,0 +1,65 @@
+use std::sync::atomic::{self, AtomicU64};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crabgrab::prelude::*;
+
+const CAPTURE_SECONDS: u64 = 5 * 60;
+
+// The user and kernel CPU time used by this process so far
+#[cfg(target_os = "macos")]
+fn process_cpu_time() -> Duration {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage as *mut _) };
+    let timeval_duration = |time: libc::timeval| Duration::from_secs(time.tv_sec as u64) + Duration::from_micros(time.tv_usec as u64);
+    timeval_duration(usage.ru_utime) + timeval_duration(usage.ru_stime)
+}
+
+// The user and kernel CPU time used by this process so far
+#[cfg(target_os = "windows")]
+fn process_cpu_time() -> Duration {
+    use windows::Win32::{Foundation::FILETIME, System::Threading::{GetCurrentProcess, GetProcessTimes}};
+    let (mut creation_time, mut exit_time, mut kernel_time, mut user_time) = (FILETIME::default(), FILETIME::default(), FILETIME::default(), FILETIME::default());
+    let _ = unsafe { GetProcessTimes(GetCurrentProcess(), &mut creation_time, &mut exit_time, &mut kernel_time, &mut user_time) };
+    // FILETIMEs count 100ns intervals
+    let filetime_duration = |time: FILETIME| Duration::from_nanos((((time.dwHighDateTime as u64) << 32) | time.dwLowDateTime as u64) * 100);
+    filetime_duration(kernel_time) + filetime_duration(user_time)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn process_cpu_time() -> Duration {
+    Duration::ZERO
+}
+
+// Captures a display for five minutes with each power profile, reporting frames delivered and process CPU time used
+// Leave the screen static while this runs, so that the difference in power use comes from the capture pipeline
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let display = content.displays().next().unwrap();
+
+    for power_profile in [PowerProfile::HighPerformance, PowerProfile::Balanced, PowerProfile::Efficiency] {
+        let config = CaptureConfig::with_display(display.clone(), CaptureStream::supported_pixel_formats()[0])
+            .with_power_profile(power_profile);
+        let frame_count = Arc::new(AtomicU64::new(0));
+        let callback_frame_count = frame_count.clone();
+        let start_cpu_time = process_cpu_time();
+        let stream = CaptureStream::new(token, config, move |result| {
+            match result {
+                Ok(StreamEvent::Video(_)) => {
+                    callback_frame_count.fetch_add(1, atomic::Ordering::AcqRel);
+                },
+                Ok(_) => {},
+                Err(error) => println!("Stream error: {}", error),
+            }
+        }).unwrap();
+        println!("{:?}: {:?}", power_profile, stream.effective_config());
+        std::thread::sleep(Duration::from_secs(CAPTURE_SECONDS));
+        stream.close().await.unwrap();
+        println!("{:?}: {} frames delivered, {:?} cpu time used", power_profile, frame_count.load(atomic::Ordering::Acquire), process_cpu_time() - start_cpu_time);
+    }
+}
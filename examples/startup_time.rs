@@ -0,0 +1,36 @@
+use std::time::{Duration, Instant};
+
+use crabgrab::prelude::*;
+
+// Measures each stage of starting a capture - only creating the stream should touch the GPU
+#[tokio::main]
+async fn main() {
+    let start = Instant::now();
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    println!("access: {:?}", start.elapsed());
+
+    let start = Instant::now();
+    let content = CapturableContent::new(CapturableContentFilter::EVERYTHING_NORMAL).await.unwrap();
+    println!("enumeration: {:?} ({} windows, {} displays)", start.elapsed(), content.windows().count(), content.displays().count());
+
+    let start = Instant::now();
+    let config = CaptureConfig::with_display(content.displays().next().unwrap(), CaptureStream::supported_pixel_formats()[0]);
+    println!("config: {:?}", start.elapsed());
+
+    let start = Instant::now();
+    let stream = CaptureStream::new(token, config, |_| {}).unwrap();
+    println!("stream creation: {:?}", start.elapsed());
+
+    let start = Instant::now();
+    match stream.await_first_frame(Duration::from_secs(5)).await {
+        Ok(()) => println!("first frame: {:?}", start.elapsed()),
+        Err(error) => println!("first frame: {}", error),
+    }
+
+    let start = Instant::now();
+    stream.close().await.unwrap();
+    println!("close: {:?}", start.elapsed());
+}
@@ -0,0 +1,75 @@
+use std::{sync::Arc, time::Duration};
+
+use crabgrab::prelude::*;
+use parking_lot::Mutex;
+
+// Stops one stream from another thread, and a second stream from inside its own callback after a few frames,
+// checking that each stream's callback receives `StreamEvent::End` exactly once
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let display = content.displays().next().unwrap();
+    // The config is borrowed, so it can be reused for the second stream
+    let config = CaptureConfig::with_display(display, CapturePixelFormat::Bgra8888);
+
+    // Stopped from another thread
+    let end_count = Arc::new(Mutex::new(0usize));
+    let callback_end_count = end_count.clone();
+    let mut stream = CaptureStream::new(token, &config, move |result| {
+        if let Ok(StreamEvent::End { reason }) = result {
+            println!("First stream ended: {:?}", reason);
+            *callback_end_count.lock() += 1;
+        }
+    }).unwrap();
+    let stop_handle = stream.stop_handle();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(500));
+        stop_handle.stop().unwrap();
+        // Stopping again does nothing
+        stop_handle.stop().unwrap();
+    }).join().unwrap();
+    stream.stop().unwrap();
+    stream.close().await.unwrap();
+    assert_eq!(*end_count.lock(), 1, "Expected the first stream to end exactly once");
+
+    // Stopped from inside its own callback - (video frames, ends, whether the callback stopped the stream, frames after stopping)
+    let state = Arc::new(Mutex::new((0usize, 0usize, false, 0usize)));
+    let callback_state = state.clone();
+    let stop_handle_slot = Arc::new(Mutex::new(None::<StopHandle>));
+    let callback_stop_handle_slot = stop_handle_slot.clone();
+    let stream = CaptureStream::new(token, &config, move |result| {
+        let mut state = callback_state.lock();
+        match result {
+            Ok(StreamEvent::Video(_)) => {
+                if state.2 {
+                    state.3 += 1;
+                    return;
+                }
+                state.0 += 1;
+                if state.0 >= 5 {
+                    if let Some(stop_handle) = callback_stop_handle_slot.lock().as_ref() {
+                        stop_handle.stop().unwrap();
+                        state.2 = true;
+                    }
+                }
+            },
+            Ok(StreamEvent::End { reason }) => {
+                println!("Second stream ended after {} frames: {:?}", state.0, reason);
+                state.1 += 1;
+            },
+            _ => {}
+        }
+    }).unwrap();
+    *stop_handle_slot.lock() = Some(stream.stop_handle());
+    std::thread::sleep(Duration::from_millis(2000));
+    stream.close().await.unwrap();
+
+    let (_, ends, stopped_from_callback, frames_after_stop) = *state.lock();
+    assert!(stopped_from_callback, "Expected enough frames to stop from the callback");
+    assert_eq!(ends, 1, "Expected the second stream to end exactly once");
+    assert_eq!(frames_after_stop, 0, "Expected no frames after stopping from the callback");
+}
@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+use crabgrab::prelude::*;
+
+// Captures a display at a fractional output size, and checks that the output size, pixel format and target reported by the stream
+// before its first frame match the first frame it delivers
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let display = content.displays().next().unwrap();
+    let display_size = display.rect().size;
+    let requested_size = Size { width: display_size.width / 3.0 + 0.5, height: display_size.height / 3.0 + 0.5 };
+    let config = CaptureConfig::with_display(display.clone(), CapturePixelFormat::Bgra8888)
+        .with_output_size(requested_size);
+
+    let (frame_tx, frame_rx) = std::sync::mpsc::channel();
+    let stream = CaptureStream::new(token, config, move |result| {
+        if let Ok(StreamEvent::Video(frame)) = result {
+            let _ = frame_tx.send(frame);
+        }
+    }).unwrap();
+    let output_size = stream.output_size();
+    println!("requested {:?}, stream output size {:?}", requested_size, output_size);
+    assert_eq!(stream.pixel_format(), CapturePixelFormat::Bgra8888);
+    assert!(*stream.target() == Capturable::Display(display), "Expected the stream to report the display it was created with");
+    assert_eq!((output_size.width.fract(), output_size.height.fract()), (0.0, 0.0), "Expected whole pixels");
+
+    let frame = frame_rx.recv_timeout(Duration::from_secs(2)).expect("Expected a frame");
+    stream.close().await.unwrap();
+    println!("first frame size {:?}", frame.size());
+    let frame_size = frame.size();
+    assert_eq!((frame_size.width, frame_size.height), (output_size.width, output_size.height), "Expected the first frame to be the stream's output size");
+}
@@ -0,0 +1,45 @@
+use std::sync::atomic::{self, AtomicU64};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crabgrab::prelude::*;
+
+// Captures a display throttled to one frame every two seconds, pausing it for part of the capture, and checks that the stream's
+// statistics count the frames the callback saw as delivered, and the throttled and paused frames as dropped.
+// Note that frames are only produced when the display content changes, so leave something animating on screen
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let config = CaptureConfig::with_display(content.displays().next().unwrap(), CapturePixelFormat::Bgra8888)
+        .with_maximum_fps(Some(0.5));
+
+    let frame_count = Arc::new(AtomicU64::new(0));
+    let callback_frame_count = frame_count.clone();
+    let mut stream = CaptureStream::new(token, config, move |result| {
+        if let Ok(StreamEvent::Video(_)) = result {
+            callback_frame_count.fetch_add(1, atomic::Ordering::AcqRel);
+        }
+    }).unwrap();
+    std::thread::sleep(Duration::from_secs(3));
+    stream.pause().unwrap();
+    std::thread::sleep(Duration::from_secs(1));
+    stream.resume().unwrap();
+    std::thread::sleep(Duration::from_secs(2));
+    // No frames are delivered once the stream is stopped, so the counts can be compared
+    stream.stop().unwrap();
+    let statistics = stream.statistics();
+    stream.close().await.unwrap();
+
+    let frame_count = frame_count.load(atomic::Ordering::Acquire);
+    println!("callback saw {} frames, statistics: {:?}", frame_count, statistics);
+    assert_eq!(statistics.frames_delivered, frame_count, "Expected the delivered count to match the frames the callback saw");
+    assert!(statistics.frames_dropped > 0, "Expected throttled and paused frames to be dropped");
+    if frame_count >= 2 {
+        let last_frame_interval = statistics.last_frame_interval.expect("Expected a frame interval");
+        assert!(last_frame_interval >= Duration::from_millis(1500), "Expected frames at most every two seconds, got {:?}", last_frame_interval);
+    }
+}
@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use crabgrab::prelude::*;
+
+// Captures a display, then switches the running stream between a few windows and back to the display
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::EVERYTHING_NORMAL).await.unwrap();
+    let display = content.displays().next().unwrap();
+    let config = CaptureConfig::with_display(display.clone(), CaptureStream::supported_pixel_formats()[0]);
+
+    let mut stream = CaptureStream::new(token, config, |result| {
+        match result {
+            Ok(StreamEvent::Video(frame)) => println!("frame {}: {:?}", frame.frame_id(), frame.content_rect()),
+            Ok(StreamEvent::End { reason }) => println!("stream ended: {:?}", reason),
+            Err(error) => println!("stream error: {}", error),
+            _ => {}
+        }
+    }).unwrap();
+
+    std::thread::sleep(Duration::from_millis(1000));
+
+    let targets = content.windows()
+        .take(3)
+        .map(Capturable::Window)
+        .chain(std::iter::once(Capturable::Display(display)));
+    for target in targets {
+        match &target {
+            Capturable::Window(window) => println!("switching to window \"{}\"", window.title()),
+            Capturable::Display(_) => println!("switching back to the display"),
+        }
+        if let Err(error) = stream.update_target(target) {
+            println!("failed to switch target: {}", error);
+        }
+        std::thread::sleep(Duration::from_millis(1000));
+    }
+
+    stream.close().await.unwrap();
+}
@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+use crabgrab::prelude::*;
+
+// Captures the same display with two streams on a shared clock, so their frame timestamps can be compared
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let content = CapturableContent::new(CapturableContentFilter::DISPLAYS).await.unwrap();
+    let display = content.displays().next().unwrap();
+
+    let clock = CaptureClock::new();
+    let mut streams = Vec::new();
+    for stream_index in 0..2 {
+        let config = CaptureConfig::with_display(display.clone(), CaptureStream::supported_pixel_formats()[0])
+            .with_shared_clock(&clock);
+        streams.push(CaptureStream::new(token, config, move |result| {
+            if let Ok(StreamEvent::Video(frame)) = result {
+                println!("stream {}: frame {} at {:?}", stream_index, frame.frame_id(), frame.origin_time());
+            }
+        }).unwrap());
+    }
+
+    CaptureClock::start_all(&streams);
+    std::thread::sleep(Duration::from_millis(2000));
+
+    for (stream_index, stream) in streams.into_iter().enumerate() {
+        println!("stream {} start skew: {:?}", stream_index, stream.statistics().start_skew);
+        stream.close().await.unwrap();
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::sync::atomic::{self, AtomicU64};
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::sync::Arc;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::time::Duration;
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use crabgrab::prelude::*;
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+const FRAME_INTERVAL_SECONDS: u64 = 5;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+const CAPTURE_SECONDS: u64 = 60;
+
+// Note that frames are only produced when the display content changes, so leave something animating on screen
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+#[tokio::main]
+async fn main() {
+    let token = match CaptureStream::test_access(false) {
+        Some(token) => token,
+        None => CaptureStream::request_access(false).await.expect("Expected capture access")
+    };
+    let filter = CapturableContentFilter::DISPLAYS;
+    let content = CapturableContent::new(filter).await.unwrap();
+    let config = CaptureConfig::with_display(content.displays().next().unwrap(), CapturePixelFormat::Bgra8888)
+        .with_maximum_fps(Some(1.0 / FRAME_INTERVAL_SECONDS as f32));
+
+    let frame_count = Arc::new(AtomicU64::new(0));
+    let callback_frame_count = frame_count.clone();
+    let mut stream = CaptureStream::new(token, config, move |result| {
+        match result {
+            Ok(StreamEvent::Video(frame)) => {
+                callback_frame_count.fetch_add(1, atomic::Ordering::AcqRel);
+                println!("Got frame: {}", frame.frame_id());
+            },
+            Ok(_) => {},
+            Err(error) => println!("Stream error: {}", error),
+        }
+    }).unwrap();
+
+    std::thread::sleep(Duration::from_secs(CAPTURE_SECONDS));
+
+    stream.stop().unwrap();
+
+    let frame_count = frame_count.load(atomic::Ordering::Acquire);
+    let expected_frame_count = CAPTURE_SECONDS / FRAME_INTERVAL_SECONDS;
+    println!("Captured {} frames, expected {}", frame_count, expected_frame_count);
+    assert!(frame_count.abs_diff(expected_frame_count) <= 1, "Expected {} +/- 1 frames, got {}", expected_frame_count, frame_count);
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn main() {
+    println!("Maximum frame rates are only configurable on MacOS and Windows");
+}
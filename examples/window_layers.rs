@@ -0,0 +1,31 @@
+use crabgrab::prelude::*;
+
+// Lists every window with its coarse layer, and checks that well-known windows land in the expected layer:
+// Finder / Explorer windows are normal, while the Dock and menu bar items on MacOS, and the desktop on Windows, are part of the system
+#[tokio::main]
+async fn main() {
+    let content = CapturableContent::new(CapturableContentFilter::ALL_WINDOWS).await.unwrap();
+    for window in content.windows() {
+        let app_identifier = window.application().identifier().to_lowercase();
+        let layer = window.z_order_hint();
+        println!("{:?}: \"{}\" ({})", layer, window.title(), app_identifier);
+
+        if app_identifier.contains("com.apple.dock") || app_identifier.contains("com.apple.controlcenter") {
+            assert_eq!(layer, WindowLayer::System, "Expected the Dock and menu bar items to be system windows");
+        }
+        // The desktop window on Windows
+        if app_identifier.contains("explorer.exe") && window.title() == "Program Manager" {
+            assert_eq!(layer, WindowLayer::System, "Expected the desktop to be a system window");
+        }
+    }
+
+    // The file browser's normal windows
+    let content = CapturableContent::new(CapturableContentFilter::NORMAL_WINDOWS).await.unwrap();
+    for window in content.windows().filter(|window| window.is_on_screen()) {
+        let app_identifier = window.application().identifier().to_lowercase();
+        let is_file_browser = app_identifier.contains("finder") || app_identifier.contains("explorer.exe");
+        if is_file_browser && !window.title().is_empty() && window.title() != "Program Manager" {
+            assert_eq!(window.z_order_hint(), WindowLayer::Normal, "Expected \"{}\" to be a normal window", window.title());
+        }
+    }
+}
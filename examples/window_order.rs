@@ -0,0 +1,25 @@
+use crabgrab::prelude::*;
+
+// Lists the on-screen windows front to back, as a window picker would, and checks two consecutive snapshots agree on their order
+#[tokio::main]
+async fn main() {
+    let first = window_order().await;
+    for (z_order, title) in first.iter() {
+        println!("{}: \"{}\"", z_order, title);
+    }
+
+    let second = window_order().await;
+    let first_titles = first.iter().map(|(_, title)| title);
+    let second_titles = second.iter().map(|(_, title)| title);
+    assert!(first_titles.eq(second_titles), "Expected the window order to be stable between snapshots");
+}
+
+// Get the z order and title of each normal window on screen, front to back
+async fn window_order() -> Vec<(usize, String)> {
+    let content = CapturableContent::new(CapturableContentFilter::NORMAL_WINDOWS).await.unwrap();
+    let mut windows = content.windows()
+        .filter_map(|window| Some((window.z_order()?, window.title())))
+        .collect::<Vec<_>>();
+    windows.sort_by_key(|(z_order, _)| *z_order);
+    windows
+}
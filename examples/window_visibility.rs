@@ -0,0 +1,21 @@
+use std::time::Duration;
+
+use crabgrab::prelude::*;
+
+// Watches the first normal window for ten seconds, printing whenever it's minimized, restored, hidden or shown - try minimizing it
+#[tokio::main]
+async fn main() {
+    let content = CapturableContent::new(CapturableContentFilter::NORMAL_WINDOWS).await.unwrap();
+    let window = content.windows().next().expect("Expected a window");
+    println!("watching window \"{}\"", window.title());
+
+    let mut last_state = None;
+    for _ in 0..100 {
+        let state = (window.is_on_screen(), window.is_minimized());
+        if last_state != Some(state) {
+            println!("on screen: {}, minimized: {}", state.0, state.1);
+            last_state = Some(state);
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
@@ -1,17 +1,54 @@
-use std::{error::Error, fmt::{Debug, Display}};
+use std::{collections::{HashMap, HashSet}, error::Error, fmt::{Debug, Display}, hash::{Hash, Hasher}, sync::{atomic::{self, AtomicU32}, Arc}, thread::JoinHandle, time::Duration};
 
-use crate::{platform::platform_impl::{ImplCapturableApplication, ImplCapturableContent, ImplCapturableContentFilter, ImplCapturableDisplay, ImplCapturableWindow}, util::Rect};
+use parking_lot::{Condvar, Mutex};
+
+use crate::{error::{ErrorKind, PlatformErrorCode}, platform::platform_impl::{ImplCapturableApplication, ImplCapturableContent, ImplCapturableContentFilter, ImplCapturableDisplay, ImplCapturableWindow, ImplContentChangeNotifier}, util::{with_timeout, Rect, Size}};
 
 /// Represents an error that occurred when enumerating capturable content
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum CapturableContentError {
-    Other(String)
+    Other(String),
+    /// Capture isn't supported on this platform (see the `stub-unsupported` feature)
+    UnsupportedPlatform,
+    /// An OS API failed, with the given message and platform error code
+    Platform(String, PlatformErrorCode),
+    /// The OS didn't enumerate the content in time - see `CapturableContent::new_with_timeout`
+    Timeout,
+}
+
+impl CapturableContentError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Other(_) => ErrorKind::CapturableContentOther,
+            Self::UnsupportedPlatform => ErrorKind::CapturableContentUnsupportedPlatform,
+            Self::Platform(..) => ErrorKind::CapturableContentPlatform,
+            Self::Timeout => ErrorKind::CapturableContentTimeout,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+
+    /// Get the error code reported by the OS, if this error came from a failing OS API
+    pub fn platform_code(&self) -> Option<&PlatformErrorCode> {
+        match self {
+            Self::Platform(_, platform_code) => Some(platform_code),
+            _ => None,
+        }
+    }
 }
 
 impl Display for CapturableContentError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Other(message) => f.write_fmt(format_args!("CapturableContentError::Other(\"{}\")", message))
+            Self::Other(message) => f.write_fmt(format_args!("CapturableContentError::Other(\"{}\")", message)),
+            Self::UnsupportedPlatform => f.write_str("CapturableContentError::UnsupportedPlatform"),
+            Self::Platform(message, platform_code) => f.write_fmt(format_args!("CapturableContentError::Platform(\"{}\", {:?})", message, platform_code)),
+            Self::Timeout => f.write_str("CapturableContentError::Timeout"),
         }
     }
 }
@@ -52,6 +89,12 @@ pub struct CapturableContentFilter {
     pub(crate) windows: Option<CapturableWindowFilter>,
     /// Whether to enumerate capturable displays
     pub(crate) displays: bool,
+    /// The minimum size of enumerated windows, if any
+    pub(crate) minimum_window_size: Option<Size>,
+    /// The identifiers of the only applications to enumerate windows of, if any
+    pub(crate) included_applications: Option<Vec<String>>,
+    /// The identifiers of applications not to enumerate windows of
+    pub(crate) excluded_applications: Vec<String>,
     /// Platform-specific filtering options
     pub(crate) impl_capturable_content_filter: ImplCapturableContentFilter,
 }
@@ -62,10 +105,68 @@ impl CapturableContentFilter {
         Self {
             displays,
             windows,
+            minimum_window_size: None,
+            included_applications: None,
+            excluded_applications: Vec::new(),
             impl_capturable_content_filter: ImplCapturableContentFilter::default()
         }
     }
 
+    /// Exclude windows smaller than the given size in either dimension, E.G. 1x1 utility windows
+    pub fn with_minimum_size(self, minimum_size: Size) -> Self {
+        Self {
+            minimum_window_size: Some(minimum_size),
+            ..self
+        }
+    }
+
+    /// Only enumerate windows of applications with one of the given identifiers (see `CapturableApplication::identifier()`)
+    /// 
+    /// Identifiers match exactly, ignoring case - E.G. `"com.apple.Safari"` on MacOS, or `"notepad.exe"` on Windows.
+    /// Calling this again adds to the list. Displays aren't affected, and the windows are filtered after enumeration.
+    pub fn including_apps(self, identifiers: &[&str]) -> Self {
+        let mut included_applications = self.included_applications.unwrap_or_default();
+        included_applications.extend(identifiers.iter().map(|identifier| identifier.to_lowercase()));
+        Self {
+            included_applications: Some(included_applications),
+            ..self
+        }
+    }
+
+    /// Don't enumerate windows of applications with any of the given identifiers (see `CapturableApplication::identifier()`)
+    /// 
+    /// Identifiers match as for `including_apps`, and exclusions take precedence over inclusions. Calling this again adds to the list.
+    pub fn excluding_apps(self, identifiers: &[&str]) -> Self {
+        let mut excluded_applications = self.excluded_applications;
+        excluded_applications.extend(identifiers.iter().map(|identifier| identifier.to_lowercase()));
+        Self {
+            excluded_applications,
+            ..self
+        }
+    }
+
+    /// Whether windows of the application with the given identifier pass the filter's application lists
+    pub fn matches_application_identifier(&self, identifier: &str) -> bool {
+        let identifier = identifier.to_lowercase();
+        if self.excluded_applications.contains(&identifier) {
+            return false;
+        }
+        match &self.included_applications {
+            Some(included_applications) => included_applications.contains(&identifier),
+            None => true,
+        }
+    }
+
+    /// Whether a window with the given rect passes the minimum size threshold
+    pub(crate) fn filter_window_rect(&self, rect: &Rect) -> bool {
+        if let Some(minimum_size) = &self.minimum_window_size {
+            if rect.size.width < minimum_size.width || rect.size.height < minimum_size.height {
+                return false;
+            }
+        }
+        true
+    }
+
     /// Whether this filter allows any capturable content
     pub fn is_empty(&self) -> bool {
         !(
@@ -78,6 +179,9 @@ impl CapturableContentFilter {
     pub const DISPLAYS: Self = CapturableContentFilter {
         windows: None,
         displays: true,
+        minimum_window_size: None,
+        included_applications: None,
+        excluded_applications: Vec::new(),
         impl_capturable_content_filter: ImplCapturableContentFilter::DEFAULT,
     };
 
@@ -88,6 +192,9 @@ impl CapturableContentFilter {
             onscreen_only: false,
         }),
         displays: false,
+        minimum_window_size: None,
+        included_applications: None,
+        excluded_applications: Vec::new(),
         impl_capturable_content_filter: ImplCapturableContentFilter::DEFAULT,
     };
 
@@ -98,6 +205,9 @@ impl CapturableContentFilter {
             onscreen_only: false,
         }),
         displays: true,
+        minimum_window_size: None,
+        included_applications: None,
+        excluded_applications: Vec::new(),
         impl_capturable_content_filter: ImplCapturableContentFilter::DEFAULT,
     };
 
@@ -108,6 +218,9 @@ impl CapturableContentFilter {
             onscreen_only: true
         }),
         displays: false,
+        minimum_window_size: None,
+        included_applications: None,
+        excluded_applications: Vec::new(),
         impl_capturable_content_filter: ImplCapturableContentFilter::NORMAL_WINDOWS,
     };
 
@@ -118,13 +231,20 @@ impl CapturableContentFilter {
             onscreen_only: true,
         }),
         displays: true,
+        minimum_window_size: None,
+        included_applications: None,
+        excluded_applications: Vec::new(),
         impl_capturable_content_filter: ImplCapturableContentFilter::NORMAL_WINDOWS,
     };
 }
 
 /// A collection of capturable content (windows, screens)
 pub struct CapturableContent {
-    impl_capturable_content: ImplCapturableContent
+    impl_capturable_content: ImplCapturableContent,
+    window_indices_by_pid: HashMap<i32, Vec<usize>>,
+    // Read once for all the windows when the content is enumerated, since each read enumerates every window on screen
+    window_z_orders: Vec<Option<usize>>,
+    filter: CapturableContentFilter,
 }
 
 unsafe impl Send for CapturableContent {}
@@ -143,18 +263,22 @@ impl Iterator for CapturableWindowIterator<'_> {
         if self.i < self.content.impl_capturable_content.windows.len() {
             let i = self.i;
             self.i += 1;
-            Some(CapturableWindow { impl_capturable_window: ImplCapturableWindow::from_impl(self.content.impl_capturable_content.windows[i].clone()) })
+            Some(self.content.window(i))
         } else {
             None
         }
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.i, Some(self.content.impl_capturable_content.windows.len()))
+        let len = self.len();
+        (len, Some(len))
     }
 }
 
 impl ExactSizeIterator for CapturableWindowIterator<'_> {
+    fn len(&self) -> usize {
+        self.content.impl_capturable_content.windows.len() - self.i
+    }
 }
 
 /// An iterator over capturable displays
@@ -183,7 +307,37 @@ impl Iterator for CapturableDisplayIterator<'_> {
 
 impl ExactSizeIterator for CapturableDisplayIterator<'_> {
     fn len(&self) -> usize {
-        self.content.impl_capturable_content.displays.len()
+        self.content.impl_capturable_content.displays.len() - self.i
+    }
+}
+
+/// An iterator over applications with capturable content
+pub struct CapturableApplicationIterator<'content> {
+    content: &'content CapturableContent,
+    i: usize
+}
+
+impl Iterator for CapturableApplicationIterator<'_> {
+    type Item = CapturableApplication;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.i < self.content.impl_capturable_content.applications.len() {
+            let i = self.i;
+            self.i += 1;
+            Some(CapturableApplication { impl_capturable_application: ImplCapturableApplication::from_impl(self.content.impl_capturable_content.applications[i].clone()) })
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.i, Some(self.content.impl_capturable_content.applications.len()))
+    }
+}
+
+impl ExactSizeIterator for CapturableApplicationIterator<'_> {
+    fn len(&self) -> usize {
+        self.content.impl_capturable_content.applications.len() - self.i
     }
 }
 
@@ -193,11 +347,74 @@ impl CapturableContent {
     /// Note that the returned capturable content may be stale - for example, a window enumerated in this capturable content
     /// may have been closed before it is used to open a stream, and creating a stream for that window will result in an error.
     pub async fn new(filter: CapturableContentFilter) -> Result<Self, CapturableContentError> {
+        let mut impl_capturable_content = ImplCapturableContent::new(filter.clone()).await?;
+        Self::filter_applications(&mut impl_capturable_content, &filter);
+        let window_indices_by_pid = Self::window_indices_by_pid(&impl_capturable_content);
+        let window_z_orders = impl_capturable_content.window_z_orders();
         Ok(Self {
-            impl_capturable_content: ImplCapturableContent::new(filter).await?
+            impl_capturable_content,
+            window_indices_by_pid,
+            window_z_orders,
+            filter,
         })
     }
 
+    /// Requests capturable content from the OS, failing with `CapturableContentError::Timeout` if the OS doesn't respond within the timeout
+    /// 
+    /// On MacOS, enumerating content waits on the screen capture service, which may never respond if it's wedged - this bounds
+    /// that wait. On timeout, a late response from the OS is discarded.
+    pub async fn new_with_timeout(filter: CapturableContentFilter, timeout: Duration) -> Result<Self, CapturableContentError> {
+        with_timeout(Box::pin(Self::new(filter)), Some(timeout)).await
+            .ok_or(CapturableContentError::Timeout)?
+    }
+
+    /// Requests the capturable content from the OS again, with the same filter, replacing this content
+    /// 
+    /// Windows and displays keep their ids across refreshes (see `CapturableWindow::id()`), so the previous content can be
+    /// diffed against the refreshed content. If the request fails, this content is left unchanged.
+    pub async fn refresh(&mut self) -> Result<(), CapturableContentError> {
+        let mut impl_capturable_content = ImplCapturableContent::new(self.filter.clone()).await?;
+        Self::filter_applications(&mut impl_capturable_content, &self.filter);
+        self.window_indices_by_pid = Self::window_indices_by_pid(&impl_capturable_content);
+        self.window_z_orders = impl_capturable_content.window_z_orders();
+        self.impl_capturable_content = impl_capturable_content;
+        Ok(())
+    }
+
+    // Drop the windows and applications which the filter's application lists exclude
+    fn filter_applications(impl_capturable_content: &mut ImplCapturableContent, filter: &CapturableContentFilter) {
+        if filter.included_applications.is_none() && filter.excluded_applications.is_empty() {
+            return;
+        }
+        let mut matches_by_pid = HashMap::<i32, bool>::new();
+        impl_capturable_content.windows.retain(|window| {
+            let application = ImplCapturableWindow::from_impl(window.clone()).application();
+            *matches_by_pid.entry(application.pid())
+                .or_insert_with(|| filter.matches_application_identifier(&application.identifier()))
+        });
+        impl_capturable_content.applications.retain(|application| {
+            let application = ImplCapturableApplication::from_impl(application.clone());
+            *matches_by_pid.entry(application.pid())
+                .or_insert_with(|| filter.matches_application_identifier(&application.identifier()))
+        });
+    }
+
+    fn window(&self, i: usize) -> CapturableWindow {
+        CapturableWindow {
+            impl_capturable_window: ImplCapturableWindow::from_impl(self.impl_capturable_content.windows[i].clone()),
+            z_order: self.window_z_orders[i],
+        }
+    }
+
+    fn window_indices_by_pid(impl_capturable_content: &ImplCapturableContent) -> HashMap<i32, Vec<usize>> {
+        let mut window_indices_by_pid = HashMap::<i32, Vec<usize>>::new();
+        for (i, window) in impl_capturable_content.windows.iter().enumerate() {
+            let pid = ImplCapturableWindow::from_impl(window.clone()).application().pid();
+            window_indices_by_pid.entry(pid).or_default().push(i);
+        }
+        window_indices_by_pid
+    }
+
     /// Get an iterator over the capturable windows
     pub fn windows<'a>(&'a self) -> CapturableWindowIterator<'a> {
         CapturableWindowIterator { content: self, i: 0 }
@@ -207,24 +424,297 @@ impl CapturableContent {
     pub fn displays<'a>(&'a self) -> CapturableDisplayIterator<'a> {
         CapturableDisplayIterator { content: self, i: 0 }
     }
+
+    /// Get an iterator over the applications in this content, without duplicates
+    /// 
+    /// On MacOS, this includes applications with no capturable windows. On windows, only applications owning
+    /// at least one of the enumerated windows are included.
+    pub fn applications<'a>(&'a self) -> CapturableApplicationIterator<'a> {
+        CapturableApplicationIterator { content: self, i: 0 }
+    }
+
+    /// Watch for windows and displays matching the filter being added or removed, calling the callback with each change until the
+    /// returned subscription is dropped
+    /// 
+    /// Changes are found by refreshing the content whenever the OS reports that windows or displays may have changed, and
+    /// comparing it by id against the previous content. The callback is called from a background thread, and a failed refresh
+    /// is reported to the callback as an error.
+    /// 
+    /// On MacOS, there are no notifications for windows opening and closing, so the content is also refreshed once a second.
+    /// The display and application notifications are only delivered while the main run loop is running.
+    pub async fn subscribe_changes(filter: CapturableContentFilter, callback: impl FnMut(Result<ContentChangeEvent, CapturableContentError>) + Send + 'static) -> Result<ContentChangeSubscription, CapturableContentError> {
+        Self::subscribe(filter, false, callback, |_content| {}).await
+    }
+
+    // Starts watching for changes, also calling `on_refresh` with the content once it's enumerated and after every refresh
+    async fn subscribe(filter: CapturableContentFilter, window_geometry: bool, callback: impl FnMut(Result<ContentChangeEvent, CapturableContentError>) + Send + 'static, mut on_refresh: impl FnMut(&CapturableContent) + Send + 'static) -> Result<ContentChangeSubscription, CapturableContentError> {
+        let shared = Arc::new(ContentChangeShared {
+            state: Mutex::new(ContentChangeState { pending: false, stopped: false }),
+            condvar: Condvar::new(),
+        });
+        let notifier_shared = shared.clone();
+        // The notifier is created before the content is enumerated, so that no change between the two is missed
+        let notifier = ImplContentChangeNotifier::new(move || notifier_shared.wake(), window_geometry)?;
+        let content = Self::new(filter).await?;
+        on_refresh(&content);
+        let thread_shared = shared.clone();
+        let thread = std::thread::spawn(move || content_change_thread(content, thread_shared, callback, on_refresh));
+        Ok(ContentChangeSubscription {
+            shared,
+            thread: Some(thread),
+            _notifier: notifier,
+        })
+    }
 }
 
-#[derive(Clone, Debug)]
-pub(crate) enum Capturable {
+/// A window or display being added or removed - see `CapturableContent::subscribe_changes()`
+#[derive(Debug, Clone)]
+pub enum ContentChangeEvent {
+    /// A window matching the filter appeared
+    WindowAdded(CapturableWindow),
+    /// A window no longer matches the filter, or was closed
+    WindowRemoved(CapturableWindow),
+    /// A display matching the filter was connected
+    DisplayAdded(CapturableDisplay),
+    /// A display no longer matches the filter, or was disconnected
+    DisplayRemoved(CapturableDisplay),
+}
+
+struct ContentChangeState {
+    pending: bool,
+    stopped: bool,
+}
+
+struct ContentChangeShared {
+    state: Mutex<ContentChangeState>,
+    condvar: Condvar,
+}
+
+impl ContentChangeShared {
+    fn wake(&self) {
+        self.state.lock().pending = true;
+        self.condvar.notify_one();
+    }
+}
+
+// Notifications tend to arrive in bursts (a window is created, then shown), so they're given time to settle before refreshing
+const CONTENT_CHANGE_SETTLE_TIME: Duration = Duration::from_millis(100);
+
+fn content_change_thread(mut content: CapturableContent, shared: Arc<ContentChangeShared>, mut callback: impl FnMut(Result<ContentChangeEvent, CapturableContentError>), mut on_refresh: impl FnMut(&CapturableContent)) {
+    loop {
+        let mut state = shared.state.lock();
+        while !state.pending && !state.stopped {
+            match ImplContentChangeNotifier::POLL_INTERVAL {
+                Some(poll_interval) => if shared.condvar.wait_for(&mut state, poll_interval).timed_out() {
+                    break;
+                },
+                None => shared.condvar.wait(&mut state),
+            }
+        }
+        if state.stopped {
+            return;
+        }
+        drop(state);
+        std::thread::sleep(CONTENT_CHANGE_SETTLE_TIME);
+        shared.state.lock().pending = false;
+
+        let previous_windows: Vec<CapturableWindow> = content.windows().collect();
+        let previous_displays: Vec<CapturableDisplay> = content.displays().collect();
+        if let Err(error) = futures::executor::block_on(content.refresh()) {
+            callback(Err(error));
+            continue;
+        }
+        on_refresh(&content);
+        let window_ids: HashSet<WindowId> = content.windows().map(|window| window.id()).collect();
+        let previous_window_ids: HashSet<WindowId> = previous_windows.iter().map(|window| window.id()).collect();
+        let display_ids: HashSet<DisplayId> = content.displays().map(|display| display.id()).collect();
+        let previous_display_ids: HashSet<DisplayId> = previous_displays.iter().map(|display| display.id()).collect();
+        let events = previous_windows.into_iter().filter(|window| !window_ids.contains(&window.id())).map(ContentChangeEvent::WindowRemoved)
+            .chain(content.windows().filter(|window| !previous_window_ids.contains(&window.id())).map(ContentChangeEvent::WindowAdded))
+            .chain(previous_displays.into_iter().filter(|display| !display_ids.contains(&display.id())).map(ContentChangeEvent::DisplayRemoved))
+            .chain(content.displays().filter(|display| !previous_display_ids.contains(&display.id())).map(ContentChangeEvent::DisplayAdded));
+        for event in events {
+            if shared.state.lock().stopped {
+                return;
+            }
+            callback(Ok(event));
+        }
+    }
+}
+
+/// Delivers changes to the capturable content until dropped - see `CapturableContent::subscribe_changes()`
+/// 
+/// Once dropped, the callback won't be called again - unless it's dropped from within the callback, in which case the
+/// current call is allowed to finish.
+pub struct ContentChangeSubscription {
+    shared: Arc<ContentChangeShared>,
+    thread: Option<JoinHandle<()>>,
+    _notifier: ImplContentChangeNotifier,
+}
+
+impl Drop for ContentChangeSubscription {
+    fn drop(&mut self) {
+        self.shared.state.lock().stopped = true;
+        self.shared.condvar.notify_one();
+        if let Some(thread) = self.thread.take() {
+            // Dropped from the callback, so the thread will exit once the callback returns
+            if thread.thread().id() != std::thread::current().id() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+/// Keeps a window's visibility estimate up to date in the background - see `CapturableWindow::monitor_visibility()`
+/// 
+/// The estimate stops being updated once the monitor is dropped.
+pub struct WindowVisibilityMonitor {
+    visibility: Arc<AtomicU32>,
+    _subscription: ContentChangeSubscription,
+}
+
+impl WindowVisibilityMonitor {
+    /// Gets the latest estimate of the fraction (0.0 - 1.0) of the window that is visible, without enumerating any content
+    /// 
+    /// This is cheap enough to check for every captured frame.
+    pub fn visibility(&self) -> f32 {
+        f32::from_bits(self.visibility.load(atomic::Ordering::Acquire))
+    }
+}
+
+/// Either a capturable window or a capturable display
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Capturable {
     Window(CapturableWindow),
     Display(CapturableDisplay),
 }
 
+/// Common functionality of capturable windows and displays, so that either can be handled generically
+/// 
+/// ```no_run
+/// use crabgrab::prelude::*;
+/// 
+/// // Works for whatever window or display the user picked
+/// fn capture_target(token: CaptureAccessToken, target: impl CaptureTarget) -> Result<CaptureStream, StreamCreateError> {
+///     println!("capturing: {} at {:?}", target.name(), target.rect());
+///     let config = CaptureConfig::with_target(target, CaptureStream::supported_pixel_formats()[0])
+///         .map_err(|error| StreamCreateError::Other(error.to_string()))?;
+///     CaptureStream::new(token, config, |_event| {})
+/// }
+/// ```
+pub trait CaptureTarget {
+    /// Gets the virtual screen rectangle of the target
+    fn rect(&self) -> Rect;
+    /// Gets a human readable name for the target - the title of a window, or a description of a display
+    fn name(&self) -> String;
+    /// Converts the target into a `Capturable`, for matching on the kind of target
+    fn to_capturable(&self) -> Capturable;
+}
+
+impl CaptureTarget for CapturableWindow {
+    fn rect(&self) -> Rect {
+        CapturableWindow::rect(self)
+    }
+
+    fn name(&self) -> String {
+        self.title()
+    }
+
+    fn to_capturable(&self) -> Capturable {
+        Capturable::Window(self.clone())
+    }
+}
+
+impl CaptureTarget for CapturableDisplay {
+    fn rect(&self) -> Rect {
+        CapturableDisplay::rect(self)
+    }
+
+    fn name(&self) -> String {
+        CapturableDisplay::name(self)
+    }
+
+    fn to_capturable(&self) -> Capturable {
+        Capturable::Display(self.clone())
+    }
+}
+
+impl CaptureTarget for Capturable {
+    fn rect(&self) -> Rect {
+        match self {
+            Self::Window(window) => CaptureTarget::rect(window),
+            Self::Display(display) => CaptureTarget::rect(display),
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Self::Window(window) => window.name(),
+            Self::Display(display) => display.name(),
+        }
+    }
+
+    fn to_capturable(&self) -> Capturable {
+        self.clone()
+    }
+}
+
+/// The coarse layer a window is shown in - see `CapturableWindow::z_order_hint()`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum WindowLayer {
+    /// An ordinary application window
+    Normal,
+    /// A window kept above normal windows, like a floating palette, a utility panel or an "always on top" window
+    AboveNormal,
+    /// A transient window shown above applications, like a menu, popup or tooltip
+    Overlay,
+    /// A window of the OS shell, like the desktop, the dock or taskbar, or the menu bar
+    System,
+}
+
 /// Represents a capturable application window
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone)]
 pub struct CapturableWindow {
-    pub(crate) impl_capturable_window: ImplCapturableWindow
+    pub(crate) impl_capturable_window: ImplCapturableWindow,
+    z_order: Option<usize>,
 }
 
 unsafe impl Send for CapturableWindow {}
 unsafe impl Sync for CapturableWindow {}
 
+// The z-order is a snapshot, so the same window from different snapshots is still equal
+impl PartialEq for CapturableWindow {
+    fn eq(&self, other: &Self) -> bool {
+        self.impl_capturable_window == other.impl_capturable_window
+    }
+}
+
+impl Eq for CapturableWindow {}
+
+impl Hash for CapturableWindow {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.impl_capturable_window.hash(state);
+    }
+}
+
 impl CapturableWindow {
+    // Wrap a window found outside of `CapturableContent`, reading its z-order now
+    pub(crate) fn from_impl(impl_capturable_window: ImplCapturableWindow) -> Self {
+        let z_order = impl_capturable_window.z_order();
+        Self {
+            impl_capturable_window,
+            z_order,
+        }
+    }
+
+    /// Gets the stable identity of the window, for matching it up across `CapturableContent` snapshots
+    /// 
+    /// This wraps the window's `CGWindowID` on MacOS, and its `HWND` on Windows. Windows compare equal when their ids do.
+    pub fn id(&self) -> WindowId {
+        WindowId(self.impl_capturable_window.id())
+    }
+
     /// Gets the title of the window
     pub fn title(&self) -> String {
         self.impl_capturable_window.title()
@@ -246,10 +736,135 @@ impl CapturableWindow {
     pub fn is_visible(&self) -> bool {
         self.impl_capturable_window.is_visible()
     }
+
+    /// Checks whether the window is on screen right now - shown, and not minimized
+    /// 
+    /// This reads the window's current state, without fetching `CapturableContent` again - unlike `is_visible`, which on MacOS
+    /// reports the state when the content was fetched. A window on screen may still be covered by others, see `visibility_estimate`.
+    pub fn is_on_screen(&self) -> bool {
+        self.impl_capturable_window.is_on_screen()
+    }
+
+    /// Checks whether the window is minimized right now
+    /// 
+    /// On MacOS the window server doesn't report minimization, so every normal window which is off screen is reported as minimized -
+    /// including windows on other Spaces, and the windows of hidden applications.
+    pub fn is_minimized(&self) -> bool {
+        self.impl_capturable_window.is_minimized()
+    }
+
+    /// Gets the window's position in the front-to-back order of the windows on screen, where 0 is the frontmost window
+    /// 
+    /// This is the order when the window was enumerated - it's read once for every window when `CapturableContent` is fetched or
+    /// refreshed, so windows from the same content are ordered consistently. Positions count every window on screen - including
+    /// windows excluded from `CapturableContent`, like menus and overlays - so they're ordered but not contiguous.
+    /// Returns None if the window wasn't on screen.
+    pub fn z_order(&self) -> Option<usize> {
+        self.z_order
+    }
+
+    /// Gets the coarse layer the window is shown in, for sorting or filtering windows such as overlays out of a window picker
+    /// 
+    /// On MacOS this groups the window's level (see `MacosCapturableWindowExt::get_window_level`), and on Windows it's inferred
+    /// from the window's extended styles (E.G. `WS_EX_TOPMOST`) and class. Like `is_on_screen`, this reads the window's current state,
+    /// and returns `WindowLayer::Normal` if the window no longer exists.
+    pub fn z_order_hint(&self) -> WindowLayer {
+        self.impl_capturable_window.z_order_hint()
+    }
+
+    /// Estimates the fraction (0.0 - 1.0) of this window that is visible on screen, from the rects of the visible windows
+    /// in `content` which are above it in z-order (see `z_order`).
+    /// 
+    /// This is only an estimate - window shapes, transparency and windows excluded from `content` aren't accounted for.
+    /// The window is looked up in `content` by id; if it isn't there, 1.0 is returned. A window which isn't on screen is 0.0 visible.
+    pub fn visibility_estimate(&self, content: &CapturableContent) -> f32 {
+        let id = self.id();
+        let Some(window) = content.windows().find(|window| window.id() == id) else {
+            return 1.0;
+        };
+        let Some(z_order) = window.z_order().filter(|_| window.is_visible()) else {
+            return 0.0;
+        };
+        let windows_above = content.windows()
+            .filter(|other| other.id() != id && other.is_visible() && other.z_order().is_some_and(|other_z_order| other_z_order < z_order))
+            .map(|other| other.rect());
+        visible_fraction(window.rect(), windows_above)
+    }
+
+    /// Starts keeping this window's `visibility_estimate` up to date in the background, for checking on every captured frame with
+    /// `WindowVisibilityMonitor::visibility()`
+    /// 
+    /// The windows which may cover this one are enumerated with `filter`, and enumerated again whenever the OS reports that windows
+    /// may have opened, closed or moved. On Windows, that's when a window is shown, hidden, activated, minimized or restored, or moved
+    /// or resized by the user - but not when an application moves its windows itself. On MacOS, windows are also enumerated again once a second.
+    pub async fn monitor_visibility(&self, filter: CapturableContentFilter) -> Result<WindowVisibilityMonitor, CapturableContentError> {
+        let visibility = Arc::new(AtomicU32::new(1.0f32.to_bits()));
+        let refresh_visibility = visibility.clone();
+        let window = self.clone();
+        let subscription = CapturableContent::subscribe(filter, true, |_event| {}, move |content| {
+            refresh_visibility.store(window.visibility_estimate(content).to_bits(), atomic::Ordering::Release);
+        }).await?;
+        Ok(WindowVisibilityMonitor {
+            visibility,
+            _subscription: subscription,
+        })
+    }
+}
+
+// The fraction of a rect which isn't covered by any of the rects above it
+fn visible_fraction(rect: Rect, rects_above: impl IntoIterator<Item = Rect>) -> f32 {
+    let area = rect.area();
+    if area <= 0.0 {
+        return 0.0;
+    }
+    let covers: Vec<Rect> = rects_above.into_iter()
+        .filter_map(|rect_above| rect.intersection(&rect_above))
+        .collect();
+    (1.0 - (union_area(&covers) / area)).clamp(0.0, 1.0) as f32
+}
+
+/// The total area covered by a set of possibly overlapping rects
+fn union_area(rects: &[Rect]) -> f64 {
+    let mut x_edges = Vec::with_capacity(rects.len() * 2);
+    for rect in rects {
+        x_edges.push(rect.origin.x);
+        x_edges.push(rect.origin.x + rect.size.width);
+    }
+    x_edges.sort_by(|a, b| a.total_cmp(b));
+    x_edges.dedup();
+    let mut area = 0.0;
+    let mut y_spans = Vec::with_capacity(rects.len());
+    for slab in x_edges.windows(2) {
+        let (x_min, x_max) = (slab[0], slab[1]);
+        y_spans.clear();
+        for rect in rects {
+            if rect.origin.x <= x_min && rect.origin.x + rect.size.width >= x_max {
+                y_spans.push((rect.origin.y, rect.origin.y + rect.size.height));
+            }
+        }
+        y_spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut covered_height = 0.0;
+        let mut current_span: Option<(f64, f64)> = None;
+        for span in y_spans.iter() {
+            current_span = match current_span {
+                Some((start, end)) if span.0 <= end => Some((start, end.max(span.1))),
+                Some((start, end)) => {
+                    covered_height += end - start;
+                    Some(*span)
+                },
+                None => Some(*span),
+            };
+        }
+        if let Some((start, end)) = current_span {
+            covered_height += end - start;
+        }
+        area += covered_height * (x_max - x_min);
+    }
+    area
 }
 
 /// Represents a capturable display
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct CapturableDisplay {
     pub(crate) impl_capturable_display: ImplCapturableDisplay
 }
@@ -261,17 +876,92 @@ impl CapturableDisplay {
     pub fn rect(&self) -> Rect {
         self.impl_capturable_display.rect()
     }
+
+    /// Gets the stable identity of the display, for matching it up across `CapturableContent` snapshots
+    /// 
+    /// This wraps the display's `CGDirectDisplayID` on MacOS, and its `HMONITOR` on Windows. Displays compare equal when their ids do.
+    pub fn id(&self) -> DisplayId {
+        DisplayId(self.impl_capturable_display.id())
+    }
+
+    /// Gets the human readable name of this display, such as "DELL U2720Q"
+    /// 
+    /// If the OS doesn't provide a name, this falls back to a description of the display's size and position
+    pub fn name(&self) -> String {
+        match self.impl_capturable_display.name() {
+            Some(name) => name,
+            None => {
+                let rect = self.rect();
+                format!("Display ({}x{} at {}, {})", rect.size.width, rect.size.height, rect.origin.x, rect.origin.y)
+            }
+        }
+    }
+
+    /// Gets the resolution of this display in pixels, in its current display mode
+    /// 
+    /// Note: On MacOS, `rect()` is measured in points, so this is `rect().size` multiplied by `scale_factor()`
+    pub fn pixel_size(&self) -> Size {
+        self.impl_capturable_display.pixel_size()
+    }
+
+    /// Gets the scale the OS applies to content on this display, E.G. 2.0 for a Retina display, or a display scaled to 200% on windows
+    /// 
+    /// The logical size of the display is `pixel_size()` divided by this
+    pub fn scale_factor(&self) -> f64 {
+        self.impl_capturable_display.scale_factor()
+    }
+
+    /// Gets the refresh rate of this display in Hz, if the OS reports one
+    /// 
+    /// Note: Some displays, such as the built-in displays of Macs, don't report a refresh rate
+    pub fn refresh_rate(&self) -> Option<f64> {
+        self.impl_capturable_display.refresh_rate()
+    }
+
+    /// Gets the mirror set this display belongs to, if it's mirrored or is being mirrored
+    /// 
+    /// All displays in the same mirror set show the same content and return the same `MirrorGroupId`,
+    /// so a picker can collapse them into a single entry. Capturing any member of a mirror set captures
+    /// the shared content, at the resolution of the display being captured.
+    /// 
+    /// Note: On windows, duplicated displays are enumerated as a single display, so this always returns `None`
+    pub fn mirror_group(&self) -> Option<MirrorGroupId> {
+        self.impl_capturable_display.mirror_group().map(MirrorGroupId)
+    }
 }
 
+/// Identifies a set of mirrored displays
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MirrorGroupId(u64);
+
+/// Identifies a window, across `CapturableContent` snapshots - see `CapturableWindow::id()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WindowId(u64);
+
+/// Identifies a display, across `CapturableContent` snapshots - see `CapturableDisplay::id()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DisplayId(u64);
+
 unsafe impl Send for CapturableDisplay {}
 unsafe impl Sync for CapturableDisplay {}
 
 /// Represents an application with capturable windows
+#[derive(Clone)]
 pub struct CapturableApplication {
-    impl_capturable_application: ImplCapturableApplication
+    pub(crate) impl_capturable_application: ImplCapturableApplication
 }
 
+unsafe impl Send for CapturableApplication {}
+unsafe impl Sync for CapturableApplication {}
+
 impl CapturableApplication {
+    /// Gets the windows in the given capturable content belonging to this application, in the same order as `CapturableContent::windows()`
+    pub fn windows(&self, content: &CapturableContent) -> Vec<CapturableWindow> {
+        content.window_indices_by_pid.get(&self.pid()).map_or(Vec::new(), |indices| {
+            indices.iter().map(|i| content.window(*i)).collect()
+        })
+    }
+
     /// Gets the "identifier" of the application
     /// 
     /// On MacOS, this is the application bundle, and on windows, this is the application file name
@@ -289,3 +979,177 @@ impl CapturableApplication {
         self.impl_capturable_application.pid()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::util::{Point, Rect, Size};
+
+    use super::{union_area, visible_fraction, CapturableContentFilter};
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect { origin: Point { x, y }, size: Size { width, height } }
+    }
+
+    #[test]
+    fn union_area_of_no_rects_is_zero() {
+        assert_eq!(union_area(&[]), 0.0);
+    }
+
+    #[test]
+    fn union_area_of_disjoint_rects_is_their_sum() {
+        assert_eq!(union_area(&[rect(0.0, 0.0, 10.0, 10.0), rect(20.0, 0.0, 5.0, 4.0)]), 120.0);
+    }
+
+    #[test]
+    fn union_area_counts_overlaps_once() {
+        assert_eq!(union_area(&[rect(0.0, 0.0, 10.0, 10.0), rect(5.0, 5.0, 10.0, 10.0)]), 175.0);
+        assert_eq!(union_area(&[rect(0.0, 0.0, 10.0, 10.0), rect(0.0, 0.0, 10.0, 10.0)]), 100.0);
+    }
+
+    #[test]
+    fn union_area_ignores_contained_rects() {
+        assert_eq!(union_area(&[rect(0.0, 0.0, 10.0, 10.0), rect(2.0, 2.0, 3.0, 3.0), rect(4.0, 1.0, 1.0, 8.0)]), 100.0);
+    }
+
+    #[test]
+    fn union_area_merges_spans_within_a_slab() {
+        // Two stacked rects touching in y, and one bridging them, all spanning the same x range
+        assert_eq!(union_area(&[rect(0.0, 0.0, 10.0, 5.0), rect(0.0, 5.0, 10.0, 5.0), rect(0.0, 3.0, 10.0, 4.0)]), 100.0);
+        // A gap between spans isn't counted
+        assert_eq!(union_area(&[rect(0.0, 0.0, 10.0, 2.0), rect(0.0, 8.0, 10.0, 2.0)]), 40.0);
+    }
+
+    #[test]
+    fn uncovered_window_is_fully_visible() {
+        assert_eq!(visible_fraction(rect(0.0, 0.0, 100.0, 100.0), []), 1.0);
+        // Windows only touching the edge don't cover it
+        assert_eq!(visible_fraction(rect(0.0, 0.0, 100.0, 100.0), [rect(100.0, 0.0, 50.0, 100.0)]), 1.0);
+    }
+
+    #[test]
+    fn covered_window_is_not_visible() {
+        assert_eq!(visible_fraction(rect(0.0, 0.0, 100.0, 100.0), [rect(-10.0, -10.0, 200.0, 200.0)]), 0.0);
+        assert_eq!(visible_fraction(rect(0.0, 0.0, 100.0, 100.0), [rect(0.0, 0.0, 50.0, 100.0), rect(50.0, 0.0, 50.0, 100.0)]), 0.0);
+    }
+
+    #[test]
+    fn window_stack_covers_union_of_windows_above() {
+        // The left half, and a window overlapping the left half and the right half
+        let visible = visible_fraction(rect(0.0, 0.0, 100.0, 100.0), [rect(0.0, 0.0, 50.0, 100.0), rect(25.0, 25.0, 50.0, 50.0)]);
+        assert_eq!(visible, 0.375);
+    }
+
+    #[test]
+    fn windows_above_are_clipped_to_the_window() {
+        // Only the 50x50 corner of the window above overlaps the window
+        assert_eq!(visible_fraction(rect(0.0, 0.0, 100.0, 100.0), [rect(-50.0, -50.0, 100.0, 100.0)]), 0.75);
+        // A window far away changes nothing
+        assert_eq!(visible_fraction(rect(0.0, 0.0, 100.0, 100.0), [rect(500.0, 500.0, 100.0, 100.0), rect(-50.0, -50.0, 100.0, 100.0)]), 0.75);
+    }
+
+    #[test]
+    fn empty_window_is_not_visible() {
+        assert_eq!(visible_fraction(rect(0.0, 0.0, 0.0, 100.0), []), 0.0);
+    }
+
+    const APPLICATIONS: [&str; 4] = ["com.apple.Safari", "com.apple.finder", "notepad.exe", "Code.exe"];
+
+    // The identifiers in `APPLICATIONS` which pass the filter's application lists
+    fn matching_applications(filter: &CapturableContentFilter) -> Vec<&'static str> {
+        APPLICATIONS.into_iter().filter(|identifier| filter.matches_application_identifier(identifier)).collect()
+    }
+
+    #[test]
+    fn filters_without_application_lists_match_every_application() {
+        assert_eq!(matching_applications(&CapturableContentFilter::NORMAL_WINDOWS), APPLICATIONS);
+    }
+
+    #[test]
+    fn including_apps_matches_only_the_given_identifiers_ignoring_case() {
+        let filter = CapturableContentFilter::NORMAL_WINDOWS.including_apps(&["COM.APPLE.SAFARI", "code.exe"]);
+        assert_eq!(matching_applications(&filter), ["com.apple.Safari", "Code.exe"]);
+        // Calling it again adds to the list
+        let filter = filter.including_apps(&["notepad.exe"]);
+        assert_eq!(matching_applications(&filter), ["com.apple.Safari", "notepad.exe", "Code.exe"]);
+        // An empty list includes nothing
+        assert!(matching_applications(&CapturableContentFilter::NORMAL_WINDOWS.including_apps(&[])).is_empty());
+    }
+
+    #[test]
+    fn excluding_apps_drops_the_given_identifiers() {
+        let filter = CapturableContentFilter::NORMAL_WINDOWS.excluding_apps(&["Notepad.exe"]).excluding_apps(&["com.apple.finder"]);
+        assert_eq!(matching_applications(&filter), ["com.apple.Safari", "Code.exe"]);
+    }
+
+    #[test]
+    fn exclusions_take_precedence_over_inclusions() {
+        let filter = CapturableContentFilter::NORMAL_WINDOWS
+            .including_apps(&["com.apple.Safari", "notepad.exe"])
+            .excluding_apps(&["notepad.exe"]);
+        assert_eq!(matching_applications(&filter), ["com.apple.Safari"]);
+    }
+
+    #[test]
+    fn application_identifiers_match_exactly_rather_than_by_substring() {
+        let filter = CapturableContentFilter::NORMAL_WINDOWS.including_apps(&["safari", "notepad"]).excluding_apps(&["code"]);
+        assert!(matching_applications(&filter).is_empty());
+        let filter = CapturableContentFilter::NORMAL_WINDOWS.excluding_apps(&["code"]);
+        assert_eq!(matching_applications(&filter), APPLICATIONS);
+    }
+
+    // Enumerates content, so this only checks anything when it can be captured (E.G. with screen recording permission)
+    #[cfg(any(target_os = "macos", target_os = "windows"))]
+    #[test]
+    fn iterator_lengths_count_the_remaining_items() {
+        use super::CapturableContent;
+
+        let Ok(content) = futures::executor::block_on(CapturableContent::new(CapturableContentFilter::EVERYTHING)) else {
+            return;
+        };
+        fn check<I: ExactSizeIterator>(iterator: impl Fn() -> I) {
+            let total = iterator().len();
+            for skip in 0..=total {
+                let mut remaining = iterator();
+                for _ in 0..skip {
+                    assert!(remaining.next().is_some());
+                }
+                assert_eq!(remaining.len(), total - skip);
+                assert_eq!(remaining.size_hint(), (total - skip, Some(total - skip)));
+                assert_eq!(remaining.collect::<Vec<_>>().len(), total - skip);
+            }
+        }
+        check(|| content.windows());
+        check(|| content.displays());
+        check(|| content.applications());
+    }
+
+    // Enumerates the windows on screen, so this only checks anything when content can be captured (E.G. with screen recording permission)
+    #[cfg(any(target_os = "macos", target_os = "windows"))]
+    #[test]
+    fn z_orders_are_consistent_across_snapshots() {
+        use std::collections::HashMap;
+
+        use super::{CapturableContent, WindowId};
+
+        let snapshot = || -> Option<HashMap<WindowId, usize>> {
+            let content = futures::executor::block_on(CapturableContent::new(CapturableContentFilter::NORMAL_WINDOWS)).ok()?;
+            Some(content.windows().filter_map(|window| Some((window.id(), window.z_order()?))).collect())
+        };
+        let (Some(first), Some(second)) = (snapshot(), snapshot()) else {
+            return;
+        };
+        // Every window has its own position within a snapshot
+        let mut first_z_orders: Vec<usize> = first.values().copied().collect();
+        first_z_orders.sort();
+        first_z_orders.dedup();
+        assert_eq!(first_z_orders.len(), first.len());
+        // Positions count windows which aren't enumerated, so only the order of the windows in both snapshots is compared
+        for (a, a_first) in first.iter() {
+            for (b, b_first) in first.iter() {
+                if let (Some(a_second), Some(b_second)) = (second.get(a), second.get(b)) {
+                    assert_eq!(a_first < b_first, a_second < b_second, "windows {a:?} and {b:?} swapped between snapshots");
+                }
+            }
+        }
+    }
+}
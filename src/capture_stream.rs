@@ -1,34 +1,105 @@
+use std::any::Any;
+use std::borrow::Borrow;
+use std::cell::Cell;
 use std::fmt::Debug;
+use std::ops::RangeInclusive;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{self, AtomicBool, AtomicU64};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::{error::Error, fmt::Display};
 
-use crate::platform::platform_impl::{ImplAudioCaptureConfig, ImplCaptureAccessToken, ImplCaptureConfig, ImplCaptureStream};
-use crate::capturable_content::Capturable;
+use futures::channel::oneshot;
+use parking_lot::Mutex;
+
+use crate::platform::platform_impl::{ImplAudioCaptureConfig, ImplCaptureAccessToken, ImplCaptureConfig, ImplCaptureStream, ImplStopHandle};
+use crate::capturable_content::{Capturable, CaptureTarget};
+use crate::error::{ErrorKind, PlatformErrorCode};
+use crate::compositor::{FrameCompositor, SharedFrameCompositor};
 use crate::prelude::{AudioChannelCount, AudioFrame, AudioSampleRate, CapturableDisplay, CapturableWindow, VideoFrame};
-use crate::util::Size;
+use crate::util::{with_timeout, Point, Rect, Size};
 
 /// Represents an event in a capture stream
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum StreamEvent {
+    /// This event is produced once, right before the first frame of the stream is delivered - after the capture delay, if one was configured
+    Started,
     /// This event is produced when the stream receives a new audio packet
     Audio(AudioFrame),
     /// This event is produced when the stream receives a new video frame
     Video(VideoFrame),
     /// This event is produced when the stream goes idle - IE when no new frames are expected for some time, like when a window minimizes
+    /// 
+    /// On Windows, this is produced when no frame arrives for the idle timeout - see `WindowsCaptureConfigExt::with_idle_timeout`
     Idle,
     /// This event is produced once at the end of the stream
-    End,
+    End {
+        /// Why the stream ended
+        reason: StreamEndReason,
+    },
+}
+
+/// The reason a stream ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamEndReason {
+    /// The stream was stopped with `CaptureStream::stop()`, or was dropped
+    Stopped,
+    /// The captured window was closed, or the captured display was disconnected
+    TargetGone,
+    /// A stream callback panicked - see `CaptureStream::set_callback_panic_handler`
+    CallbackPanicked,
+    /// The OS stopped the stream for another reason
+    Other(String),
 }
 
 /// This represents an error during a stream, for example a failure to retrieve a video or audio frame
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum StreamError {
     Other(String),
+    /// An OS API failed, with the given message and platform error code
+    Platform(String, PlatformErrorCode),
+    /// No frame arrived in time - see `CaptureStream::await_first_frame()`. Contains diagnostic information.
+    Timeout(String),
+    /// The OS produced a frame without any image data, so it was dropped
+    /// 
+    /// This is not fatal - the stream continues delivering frames
+    EmptyFrame,
+}
+
+impl StreamError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Other(_) => ErrorKind::StreamOther,
+            Self::Platform(..) => ErrorKind::StreamPlatform,
+            Self::Timeout(_) => ErrorKind::StreamTimeout,
+            Self::EmptyFrame => ErrorKind::StreamEmptyFrame,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+
+    /// Get the error code reported by the OS, if this error came from a failing OS API
+    pub fn platform_code(&self) -> Option<&PlatformErrorCode> {
+        match self {
+            Self::Platform(_, platform_code) => Some(platform_code),
+            _ => None,
+        }
+    }
 }
 
 impl Display for StreamError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Other(message) => f.write_fmt(format_args!("StreamError::Other(\"{}\")", message))
+            Self::Other(message) => f.write_fmt(format_args!("StreamError::Other(\"{}\")", message)),
+            Self::Platform(message, platform_code) => f.write_fmt(format_args!("StreamError::Platform(\"{}\", {:?})", message, platform_code)),
+            Self::Timeout(diagnostic) => f.write_fmt(format_args!("StreamError::Timeout(\"{}\")", diagnostic)),
+            Self::EmptyFrame => f.write_str("StreamError::EmptyFrame"),
         }
     }
 }
@@ -49,6 +120,7 @@ impl Error for StreamError {
 
 /// This represents an error when creating a capture stream
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum StreamCreateError {
     Other(String),
     /// The supplied pixel format is unsupported by the implementation
@@ -56,25 +128,64 @@ pub enum StreamCreateError {
     //GpuLost,
     /// Requested features are not authorized
     UnauthorizedFeature(String),
+    /// Capture isn't supported on this platform (see the `stub-unsupported` feature)
+    UnsupportedPlatform,
+    /// An OS API failed, with the given message and platform error code
+    Platform(String, PlatformErrorCode),
+    /// The capture config is invalid - see `CaptureConfig::validate()`
+    InvalidConfig(CaptureConfigError),
 }
 
 unsafe impl Send for StreamCreateError {}
 unsafe impl Sync for StreamCreateError {}
 
 
+impl StreamCreateError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Other(_) => ErrorKind::StreamCreateOther,
+            Self::UnsupportedPixelFormat => ErrorKind::StreamCreateUnsupportedPixelFormat,
+            Self::UnauthorizedFeature(_) => ErrorKind::StreamCreateUnauthorizedFeature,
+            Self::UnsupportedPlatform => ErrorKind::StreamCreateUnsupportedPlatform,
+            Self::Platform(..) => ErrorKind::StreamCreatePlatform,
+            Self::InvalidConfig(_) => ErrorKind::StreamCreateInvalidConfig,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+
+    /// Get the error code reported by the OS, if this error came from a failing OS API
+    pub fn platform_code(&self) -> Option<&PlatformErrorCode> {
+        match self {
+            Self::Platform(_, platform_code) => Some(platform_code),
+            _ => None,
+        }
+    }
+}
+
 impl Display for StreamCreateError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Other(message) => f.write_fmt(format_args!("StreamCreateError::Other(\"{}\")", message)),
             Self::UnsupportedPixelFormat => f.write_fmt(format_args!("StreamCreateError::UnsupportedPixelFormat")),
             Self::UnauthorizedFeature(feature) => f.write_fmt(format_args!("StreamCreateError::UnauthorizedFeature({})", feature)),
+            Self::UnsupportedPlatform => f.write_fmt(format_args!("StreamCreateError::UnsupportedPlatform")),
+            Self::Platform(message, platform_code) => f.write_fmt(format_args!("StreamCreateError::Platform(\"{}\", {:?})", message, platform_code)),
+            Self::InvalidConfig(error) => f.write_fmt(format_args!("StreamCreateError::InvalidConfig({})", error)),
         }
     }
 }
 
 impl Error for StreamCreateError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
-        None
+        match self {
+            Self::InvalidConfig(error) => Some(error),
+            _ => None,
+        }
     }
 
     fn description(&self) -> &str {
@@ -88,6 +199,7 @@ impl Error for StreamCreateError {
 
 /// This represents an error while stopping a stream
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum StreamStopError {
     Other(String),
     /// The stream was already stopped
@@ -98,6 +210,21 @@ pub enum StreamStopError {
 unsafe impl Send for StreamStopError {}
 unsafe impl Sync for StreamStopError {}
 
+impl StreamStopError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Other(_) => ErrorKind::StreamStopOther,
+            Self::AlreadyStopped => ErrorKind::StreamStopAlreadyStopped,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+}
+
 impl Display for StreamStopError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -121,6 +248,156 @@ impl Error for StreamStopError {
     }
 }
 
+/// This represents an error while changing the target or config of a running stream
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum StreamUpdateError {
+    Other(String),
+    /// The stream's capture backend can't change targets while running, or can't capture the new target with the stream's config
+    Unsupported,
+    /// The stream has already stopped
+    AlreadyStopped,
+    /// The named setting can't change while the stream runs - create a new stream with it instead
+    RequiresRestart(String),
+    /// An OS API failed while updating the stream, with the given message and platform error code
+    Platform(String, PlatformErrorCode),
+    /// The new config has settings the capture can't use - see `CaptureConfig::validate()`
+    InvalidConfig(CaptureConfigError),
+}
+
+unsafe impl Send for StreamUpdateError {}
+unsafe impl Sync for StreamUpdateError {}
+
+impl StreamUpdateError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Other(_) => ErrorKind::StreamUpdateOther,
+            Self::Unsupported => ErrorKind::StreamUpdateUnsupported,
+            Self::AlreadyStopped => ErrorKind::StreamUpdateAlreadyStopped,
+            Self::RequiresRestart(_) => ErrorKind::StreamUpdateRequiresRestart,
+            Self::Platform(..) => ErrorKind::StreamUpdatePlatform,
+            Self::InvalidConfig(_) => ErrorKind::StreamUpdateInvalidConfig,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+
+    /// Get the error code reported by the OS, if this error came from a failing OS API
+    pub fn platform_code(&self) -> Option<&PlatformErrorCode> {
+        match self {
+            Self::Platform(_, platform_code) => Some(platform_code),
+            _ => None,
+        }
+    }
+}
+
+impl Display for StreamUpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Other(message) => f.write_fmt(format_args!("StreamUpdateError::Other(\"{}\")", message)),
+            Self::Unsupported => f.write_str("StreamUpdateError::Unsupported"),
+            Self::AlreadyStopped => f.write_str("StreamUpdateError::AlreadyStopped"),
+            Self::RequiresRestart(setting) => f.write_fmt(format_args!("StreamUpdateError::RequiresRestart(\"{}\")", setting)),
+            Self::Platform(message, platform_code) => f.write_fmt(format_args!("StreamUpdateError::Platform(\"{}\", {:?})", message, platform_code)),
+            Self::InvalidConfig(error) => f.write_fmt(format_args!("StreamUpdateError::InvalidConfig({})", error)),
+        }
+    }
+}
+
+impl Error for StreamUpdateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::InvalidConfig(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    fn description(&self) -> &str {
+        "description() is deprecated; use Display"
+    }
+
+    fn cause(&self) -> Option<&dyn Error> {
+        self.source()
+    }
+}
+
+/// What the user needs to do after the capture permission settings page was opened
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OpenSettingsOutcome {
+    /// The settings page was opened, and access granted there takes effect immediately
+    Opened,
+    /// The settings page was opened, but the application must be restarted before access granted there takes effect
+    /// 
+    /// On MacOS, screen recording permission is only picked up when an application launches
+    NeedsRestart,
+}
+
+/// This represents an error while opening the capture permission settings page
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum OpenSettingsError {
+    Other(String),
+    /// There's no capture permission settings page on this platform or OS version
+    Unavailable,
+    /// An OS API failed to open the settings page, with the given message and platform error code
+    Platform(String, PlatformErrorCode),
+}
+
+unsafe impl Send for OpenSettingsError {}
+unsafe impl Sync for OpenSettingsError {}
+
+impl OpenSettingsError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Other(_) => ErrorKind::OpenSettingsOther,
+            Self::Unavailable => ErrorKind::OpenSettingsUnavailable,
+            Self::Platform(..) => ErrorKind::OpenSettingsPlatform,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+
+    /// Get the error code reported by the OS, if this error came from a failing OS API
+    pub fn platform_code(&self) -> Option<&PlatformErrorCode> {
+        match self {
+            Self::Platform(_, platform_code) => Some(platform_code),
+            _ => None,
+        }
+    }
+}
+
+impl Display for OpenSettingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Other(message) => f.write_fmt(format_args!("OpenSettingsError::Other(\"{}\")", message)),
+            Self::Unavailable => f.write_str("OpenSettingsError::Unavailable"),
+            Self::Platform(message, platform_code) => f.write_fmt(format_args!("OpenSettingsError::Platform(\"{}\", {:?})", message, platform_code)),
+        }
+    }
+}
+
+impl Error for OpenSettingsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+
+    fn description(&self) -> &str {
+        "description() is deprecated; use Display"
+    }
+
+    fn cause(&self) -> Option<&dyn Error> {
+        self.source()
+    }
+}
+
 /// Configuration settings for audio streams
 #[derive(Clone, Debug)]
 #[allow(unused)]
@@ -130,6 +407,12 @@ pub struct AudioCaptureConfig {
     pub(crate) impl_capture_audio_config: ImplAudioCaptureConfig,
 }
 
+impl Default for AudioCaptureConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AudioCaptureConfig {
     /// Creates a new audio capture config with default settings:
     /// * 24000 Hz
@@ -141,6 +424,22 @@ impl AudioCaptureConfig {
             impl_capture_audio_config: ImplAudioCaptureConfig::new()
         }
     }
+
+    /// Configure the rate to capture audio samples at
+    pub fn with_sample_rate(self, sample_rate: AudioSampleRate) -> Self {
+        Self {
+            sample_rate,
+            ..self
+        }
+    }
+
+    /// Configure the number of audio channels to capture
+    pub fn with_channel_count(self, channel_count: AudioChannelCount) -> Self {
+        Self {
+            channel_count,
+            ..self
+        }
+    }
 }
 
 /// The pixel format of returned video frames
@@ -159,6 +458,341 @@ pub enum CapturePixelFormat {
     /// * 1 channel, luminance (Y), 8 bits per pixel, full range: [0, 255]
     /// * 2 channels, chrominance (CbCr) 8 bits bits per channel per two pixels vertically, range: [0, 255]
     F420,
+    /// One plane, 4 channels, 16 bits per channel: { r: u16, g: u16, b: u16, a: u16 }, full range: [0, 65535]
+    /// 
+    /// Only available where the OS capture API can produce it, as listed by `CaptureStream::supported_pixel_formats()` - on Windows it's
+    /// listed when Windows.Graphics.Capture accepts R16G16B16A16 frame pools, and it's never available on MacOS
+    Rgba16,
+    /// One plane, 4 channels, 16 bit floats per channel: { r: f16, g: f16, b: f16, a: f16 }, linear, where 1.0 is SDR white and HDR content goes above it
+    /// 
+    /// Only available on Windows - see `ColorSpace::LinearExtendedSrgb`
+    RgbaF16,
+}
+
+/// What the frames of a stream will be used for, to choose a pixel format with `CaptureStream::preferred_pixel_format()`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PixelFormatPurpose {
+    /// Reading pixels on the CPU, such as with the `bitmap` feature - prefers packed RGB formats, which need no conversion
+    CpuBitmap,
+    /// Sampling frames as GPU textures, such as with the `wgpu` or `metal` features - prefers formats with a single RGB plane
+    GpuTexture,
+    /// Feeding frames to a video encoder - prefers 4:2:0 YCbCr formats, which encoders take as-is
+    VideoEncode,
+}
+
+// The pixel formats each capture backend can produce - on Windows, `Rgba16` is only supported where the OS accepts it
+#[cfg(any(target_os = "macos", test))]
+pub(crate) const MACOS_PIXEL_FORMATS: &[CapturePixelFormat] = &[CapturePixelFormat::V420, CapturePixelFormat::F420, CapturePixelFormat::Bgra8888, CapturePixelFormat::Argb2101010];
+#[cfg(any(target_os = "windows", test))]
+pub(crate) const WINDOWS_PIXEL_FORMATS: &[CapturePixelFormat] = &[CapturePixelFormat::Bgra8888, CapturePixelFormat::Argb2101010, CapturePixelFormat::RgbaF16, CapturePixelFormat::Rgba16];
+
+impl PixelFormatPurpose {
+    // The pixel formats best suited to the purpose, best first
+    fn preferred_pixel_formats(&self) -> &'static [CapturePixelFormat] {
+        match self {
+            Self::CpuBitmap => &[CapturePixelFormat::Bgra8888, CapturePixelFormat::Argb2101010, CapturePixelFormat::Rgba16, CapturePixelFormat::RgbaF16, CapturePixelFormat::F420, CapturePixelFormat::V420],
+            Self::GpuTexture => &[CapturePixelFormat::Bgra8888, CapturePixelFormat::Argb2101010, CapturePixelFormat::Rgba16, CapturePixelFormat::RgbaF16, CapturePixelFormat::V420, CapturePixelFormat::F420],
+            Self::VideoEncode => &[CapturePixelFormat::V420, CapturePixelFormat::F420, CapturePixelFormat::Bgra8888, CapturePixelFormat::Argb2101010, CapturePixelFormat::Rgba16, CapturePixelFormat::RgbaF16],
+        }
+    }
+
+    // The best of the supported pixel formats for the purpose
+    fn preferred_pixel_format(&self, supported_pixel_formats: &[CapturePixelFormat]) -> Option<CapturePixelFormat> {
+        self.preferred_pixel_formats().iter()
+            .copied()
+            .find(|pixel_format| supported_pixel_formats.contains(pixel_format))
+    }
+}
+
+/// How captured content is scaled into the output size of a stream
+/// 
+/// Any part of the output not covered by the content is filled with opaque black.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScalingQuality {
+    /// Scale the content to the output size with the platform's (bilinear) scaler
+    Smooth,
+    /// Scale the content by the largest whole number factor that fits within the output, with nearest-neighbor sampling, centered in the output
+    /// 
+    /// Content larger than the output is scaled down to fit with nearest-neighbor sampling instead
+    NearestNeighbor,
+    /// Don't scale the content: it's delivered at its native size, centered in the output, and cropped if it's larger than the output
+    None,
+}
+
+impl ScalingQuality {
+    // The rect the content covers within the output, in output pixels - it may extend past the output for `None`
+    pub(crate) fn content_rect(&self, content_size: Size, output_size: Size) -> Rect {
+        let content_width = content_size.width.max(1.0);
+        let content_height = content_size.height.max(1.0);
+        let scale = match self {
+            ScalingQuality::Smooth => (output_size.width / content_width).min(output_size.height / content_height),
+            ScalingQuality::NearestNeighbor => {
+                let scale = (output_size.width / content_width).min(output_size.height / content_height);
+                if scale >= 1.0 { scale.floor() } else { scale }
+            },
+            ScalingQuality::None => 1.0,
+        };
+        let size = Size {
+            width: (content_width * scale).round().max(1.0),
+            height: (content_height * scale).round().max(1.0),
+        };
+        Rect {
+            origin: Point {
+                x: ((output_size.width - size.width) / 2.0).floor(),
+                y: ((output_size.height - size.height) / 2.0).floor(),
+            },
+            size,
+        }
+    }
+
+    // The whole pixel size of the output content is placed into, which is the size of the bitmaps of frames placed into it
+    #[cfg(any(target_os = "macos", test))]
+    pub(crate) fn placed_output_size(output_size: Size) -> Size {
+        Size {
+            width: output_size.width.floor().max(1.0),
+            height: output_size.height.floor().max(1.0),
+        }
+    }
+}
+
+// How a frame of content is cropped and scaled into the frame that's delivered, for platforms which scale frames themselves
+#[cfg(any(target_os = "windows", test))]
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct FrameLayout {
+    // The part of the content that's delivered, in content pixels
+    pub(crate) crop_rect: Rect,
+    // The size of the delivered frame
+    pub(crate) target_size: (usize, usize),
+    // Where the cropped content goes in the delivered frame
+    pub(crate) content_rect: Rect,
+    // Whether the content can be delivered as it was captured
+    pub(crate) unscaled: bool,
+}
+
+#[cfg(any(target_os = "windows", test))]
+impl FrameLayout {
+    // The source rect is relative to the size of the content, so that it follows the content as the content is resized - smoothly scaled content
+    // is delivered at its native size until it's larger than the maximum dimensions, while placed content always fills the output
+    pub(crate) fn new(content_size: (usize, usize), source_rect: Option<Rect>, scaling_quality: ScalingQuality, max_dimensions: Option<Size>, scaled_output_size: Size) -> Self {
+        let content_size_f64 = Size { width: content_size.0 as f64, height: content_size.1 as f64 };
+        let crop_rect = match source_rect {
+            Some(source_rect) => {
+                let crop_rect = source_rect.scaled_2d((content_size_f64.width, content_size_f64.height));
+                let origin = Point { x: crop_rect.origin.x.floor(), y: crop_rect.origin.y.floor() };
+                Rect {
+                    origin,
+                    size: Size {
+                        width: crop_rect.size.width.round().min(content_size_f64.width - origin.x).max(1.0),
+                        height: crop_rect.size.height.round().min(content_size_f64.height - origin.y).max(1.0),
+                    },
+                }
+            },
+            None => Rect { origin: Point::ZERO, size: content_size_f64 },
+        };
+        let native_size = crop_rect.size;
+        let (target_size, content_rect) = match scaling_quality {
+            ScalingQuality::Smooth => {
+                let capped_size = max_dimensions.map_or(native_size, |max_dimensions| native_size.fit_within(max_dimensions));
+                ((capped_size.width as usize, capped_size.height as usize), Rect { origin: Point::ZERO, size: capped_size })
+            },
+            scaling_quality => {
+                let target_size = ((scaled_output_size.width as usize).max(1), (scaled_output_size.height as usize).max(1));
+                let target_size_f64 = Size { width: target_size.0 as f64, height: target_size.1 as f64 };
+                (target_size, scaling_quality.content_rect(native_size, target_size_f64))
+            }
+        };
+        let unscaled = source_rect.is_none() && target_size == content_size && content_rect.origin.x == 0.0 && content_rect.origin.y == 0.0;
+        Self {
+            crop_rect,
+            target_size,
+            content_rect,
+            unscaled,
+        }
+    }
+}
+
+// Skips frames which arrive sooner than the minimum frame interval after the last delivered frame. Frames are expected on roughly the
+// OS frame interval, so frames up to a quarter of the interval early (but no earlier than the maximum tolerance) are accepted,
+// rather than waiting a whole extra OS frame interval
+#[cfg(any(target_os = "macos", target_os = "windows", test))]
+pub(crate) struct FrameIntervalLimiter {
+    interval: Option<Duration>,
+    max_tolerance: Duration,
+    last_frame_time: Option<Instant>,
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", test))]
+impl FrameIntervalLimiter {
+    pub(crate) fn new(interval: Option<Duration>, max_tolerance: Duration) -> Self {
+        Self {
+            interval,
+            max_tolerance,
+            last_frame_time: None,
+        }
+    }
+
+    // Change the interval, measuring it from the last frame delivered with the old interval
+    #[cfg(any(target_os = "windows", test))]
+    pub(crate) fn set_interval(&mut self, interval: Option<Duration>) {
+        self.interval = interval;
+    }
+
+    pub(crate) fn should_deliver(&mut self, now: Instant) -> bool {
+        if let (Some(interval), Some(last_frame_time)) = (self.interval, self.last_frame_time) {
+            if now.saturating_duration_since(last_frame_time) + (interval / 4).min(self.max_tolerance) < interval {
+                return false;
+            }
+        }
+        self.last_frame_time = Some(now);
+        true
+    }
+}
+
+/// How a capture stream trades latency and smoothness for power use - see `CaptureConfig::with_power_profile`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum PowerProfile {
+    /// Favor latency and smoothness over power use
+    /// 
+    /// The configured buffer count and frame rate are used as-is, and the capture pipeline is scheduled ahead of normal work
+    /// (user-interactive dispatch queues on MacOS, above normal priority threads on Windows).
+    /// This gives the fewest dropped frames when the system is under load, at the highest power cost.
+    HighPerformance,
+    /// The configured buffer count and frame rate are used as-is, with default OS scheduling
+    #[default]
+    Balanced,
+    /// Favor power use, for long running background capture such as recording
+    /// 
+    /// * Frames are delivered no more often than every 100ms (10 fps), or the configured frame interval if that's longer
+    /// * At most 2 frames are queued
+    /// * The capture pipeline is scheduled behind normal work (utility dispatch queues on MacOS, below normal priority threads on Windows)
+    /// * On MacOS 14 and later, window captures skip rendering the window's shadow
+    /// 
+    /// Expect higher latency, choppier motion, and frames to be dropped more often when the system is busy or the callback is slow.
+    /// On MacOS the frame interval is enforced by the OS, so unchanged content costs very little; on Windows, frames beyond the limit are still
+    /// produced by the OS, but are returned to the frame pool without being processed.
+    Efficiency,
+}
+
+/// The minimum interval between frames delivered by streams with `PowerProfile::Efficiency`
+const EFFICIENCY_FRAME_INTERVAL: Duration = Duration::from_millis(100);
+/// The maximum buffer count of streams with `PowerProfile::Efficiency`
+const EFFICIENCY_MAX_BUFFER_COUNT: usize = 2;
+
+impl PowerProfile {
+    // The minimum interval between delivered frames for this profile, given the interval the stream was configured with
+    pub(crate) fn frame_interval(&self, configured_interval: Option<Duration>) -> Option<Duration> {
+        match self {
+            PowerProfile::Efficiency => Some(configured_interval.map_or(EFFICIENCY_FRAME_INTERVAL, |interval| interval.max(EFFICIENCY_FRAME_INTERVAL))),
+            _ => configured_interval,
+        }
+    }
+
+    // The buffer count for this profile, given the buffer count the stream was configured with
+    pub(crate) fn buffer_count(&self, configured_buffer_count: usize) -> usize {
+        match self {
+            PowerProfile::Efficiency => configured_buffer_count.clamp(1, EFFICIENCY_MAX_BUFFER_COUNT),
+            _ => configured_buffer_count,
+        }
+    }
+}
+
+/// The settings a capture stream is actually running with, after applying its power profile - see `CaptureStream::effective_config()`
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct EffectiveCaptureConfig {
+    /// The power profile the stream was configured with
+    pub power_profile: PowerProfile,
+    /// The minimum interval between delivered video frames, if frames are throttled
+    pub minimum_frame_interval: Option<Duration>,
+    /// The number of frames in the capture queue
+    pub buffer_count: usize,
+    /// Descriptions of the platform-specific settings applied for the power profile, E.G. `"dispatch qos: utility"`
+    pub platform_settings: Vec<&'static str>,
+}
+
+/// A clock shared by several capture streams, so that their frames can be matched in time
+/// 
+/// Streams use the clock when they're configured with `CaptureConfig::with_shared_clock`. The `origin_time()` of video frames from those streams
+/// is measured from the clock's origin - the moment the clock was created - using the time each frame was delivered (see `VideoFrame::capture_time`),
+/// rather than from a per-stream origin.
+/// 
+/// Streams using a shared clock start capturing as soon as they're created, so that their platform pipelines are warmed up,
+/// but they don't deliver `StreamEvent::Started` or any frames until they're released together with `CaptureClock::start_all`.
+/// 
+/// ### Synchronization bounds
+/// Each released stream delivers whichever frame the platform produces next, so streams start within about one frame interval of each other:
+/// * On Windows, frames are produced when the captured content is presented, so streams of changing content start within a display refresh or so of each other
+/// * On MacOS, frames are only produced when the captured content changes (no more often than the maximum frame rate), so a stream of static content starts when its content next changes
+/// 
+/// Use `CaptureStream::statistics()` to measure the start skew that was actually achieved.
+/// 
+/// `CaptureStreamGroup` creates and releases streams on a shared clock, and delivers their events to one callback.
+#[derive(Clone, Copy, Debug)]
+pub struct CaptureClock {
+    origin: Instant,
+}
+
+impl CaptureClock {
+    /// Create a new clock, with its origin at the current time
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+
+    /// Get the instant that the timestamps of frames from streams using this clock are measured from
+    pub fn origin(&self) -> Instant {
+        self.origin
+    }
+
+    /// Release all of the given streams at once, so that they begin delivering frames as close to simultaneously as the platforms allow
+    /// 
+    /// Streams which don't use a shared clock are already delivering frames, but they are still included when measuring start skew.
+    /// The streams can be given as any collection of references, E.G. `&streams` for a `Vec<CaptureStream>`.
+    pub fn start_all<'a>(streams: impl IntoIterator<Item = &'a CaptureStream>) {
+        release_streams(streams.into_iter().map(|stream| (&stream.first_frame, stream.start_gate.as_ref())));
+    }
+}
+
+impl Default for CaptureClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Group streams for measuring start skew, then open the start gates of those using a shared clock
+fn release_streams<'a>(streams: impl IntoIterator<Item = (&'a Arc<Mutex<FirstFrameState>>, Option<&'a Arc<AtomicBool>>)>) {
+    let streams: Vec<_> = streams.into_iter().collect();
+    let start_group: Arc<[Arc<Mutex<FirstFrameState>>]> = streams.iter()
+        .map(|(first_frame, _)| (*first_frame).clone())
+        .collect();
+    for (first_frame, _) in streams.iter() {
+        first_frame.lock().start_group = Some(start_group.clone());
+    }
+    // Release the streams back to back, once all of the bookkeeping is done
+    for (_, start_gate) in streams.iter() {
+        if let Some(start_gate) = start_gate {
+            start_gate.store(true, atomic::Ordering::Release);
+        }
+    }
+}
+
+/// Statistics about a capture stream - see `CaptureStream::statistics()`
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct StreamStatistics {
+    /// For streams started together with `CaptureClock::start_all`, how long after the earliest first frame of those streams this stream delivered its first frame
+    /// 
+    /// This is `None` until the stream has delivered a frame, or if the stream wasn't started with `CaptureClock::start_all`
+    pub start_skew: Option<Duration>,
+    /// How many video frames have been delivered to the callback
+    pub frames_delivered: u64,
+    /// How many video frames were captured but never delivered - because the stream was paused or stopping, was throttled to its
+    /// maximum frame rate (see `CaptureConfig::with_maximum_fps`), or hadn't passed its capture delay or shared clock start yet
+    pub frames_dropped: u64,
+    /// The time between the capture of the last two delivered frames, or `None` until two frames have been delivered
+    pub last_frame_interval: Option<Duration>,
 }
 
 /// Configuration settings for a capture stream
@@ -169,28 +803,67 @@ pub struct CaptureConfig {
     pub(crate) show_cursor: bool,
     pub(crate) pixel_format: CapturePixelFormat,
     pub(crate) capture_audio: Option<AudioCaptureConfig>,
+    pub(crate) capture_video: bool,
     pub(crate) impl_capture_config: ImplCaptureConfig,
     pub(crate) buffer_count: usize,
+    pub(crate) capture_delay: Duration,
+    pub(crate) max_dimensions: Option<Size>,
+    pub(crate) source_rect: Option<Rect>,
+    pub(crate) scaling_quality: ScalingQuality,
+    pub(crate) shared_clock: Option<CaptureClock>,
+    pub(crate) power_profile: PowerProfile,
+    pub(crate) maximum_fps: Option<f32>,
+    pub(crate) frame_compositor: Option<SharedFrameCompositor>,
+    #[cfg(feature = "bitmap")]
+    pub(crate) change_metric: bool,
 }
 
 /// Represents an error creating the capture config
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum CaptureConfigError {
     /// The pixel format is unsupported by the implementation
     UnsupportedPixelFormat,
-    /// The buffer count is out of the valid range for the implementation
+    /// The buffer count is out of the valid range for the implementation - see `CaptureStream::supported_buffer_counts()`
     InvalidBufferCount,
+    /// The output size is less than a pixel in either dimension, or isn't finite
+    InvalidOutputSize,
+    /// The source rect lies entirely outside the bounds of the capture target, or is empty
+    SourceRectOutOfBounds,
+    /// The maximum frame rate is zero, negative or NaN
+    InvalidMaximumFps,
 }
 
 
 unsafe impl Send for CaptureConfigError {}
 unsafe impl Sync for CaptureConfigError {}
 
+impl CaptureConfigError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::UnsupportedPixelFormat => ErrorKind::CaptureConfigUnsupportedPixelFormat,
+            Self::InvalidBufferCount => ErrorKind::CaptureConfigInvalidBufferCount,
+            Self::InvalidOutputSize => ErrorKind::CaptureConfigInvalidOutputSize,
+            Self::SourceRectOutOfBounds => ErrorKind::CaptureConfigSourceRectOutOfBounds,
+            Self::InvalidMaximumFps => ErrorKind::CaptureConfigInvalidMaximumFps,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+}
+
 impl Display for CaptureConfigError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::UnsupportedPixelFormat => f.write_fmt(format_args!("CaptureConfigError::UnsupportedPixelFormat")),
             Self::InvalidBufferCount => f.write_fmt(format_args!("CaptureConfigError::InvalidBufferCount")),
+            Self::InvalidOutputSize => f.write_fmt(format_args!("CaptureConfigError::InvalidOutputSize")),
+            Self::SourceRectOutOfBounds => f.write_fmt(format_args!("CaptureConfigError::SourceRectOutOfBounds")),
+            Self::InvalidMaximumFps => f.write_fmt(format_args!("CaptureConfigError::InvalidMaximumFps")),
         }
     }
 }
@@ -211,7 +884,12 @@ impl Error for CaptureConfigError {
 
 impl CaptureConfig {
     /// Create a capture configuration for a given capturable window
+    /// 
+    /// Returns `CaptureConfigError::UnsupportedPixelFormat` if the pixel format isn't in `CaptureStream::supported_pixel_formats()`.
     pub fn with_window(window: CapturableWindow, pixel_format: CapturePixelFormat) -> Result<CaptureConfig, CaptureConfigError> {
+        if !CaptureStream::supported_pixel_formats().contains(&pixel_format) {
+            return Err(CaptureConfigError::UnsupportedPixelFormat);
+        }
         let rect = window.rect();
         Ok(CaptureConfig {
             target: Capturable::Window(window),
@@ -220,7 +898,18 @@ impl CaptureConfig {
             show_cursor: false,
             impl_capture_config: ImplCaptureConfig::new(),
             capture_audio: None,
+            capture_video: true,
             buffer_count: 3,
+            capture_delay: Duration::ZERO,
+            max_dimensions: None,
+            source_rect: None,
+            scaling_quality: ScalingQuality::Smooth,
+            shared_clock: None,
+            power_profile: PowerProfile::Balanced,
+            maximum_fps: None,
+            frame_compositor: None,
+            #[cfg(feature = "bitmap")]
+            change_metric: false,
         })
     }
 
@@ -234,8 +923,68 @@ impl CaptureConfig {
             show_cursor: false,
             impl_capture_config: ImplCaptureConfig::new(),
             capture_audio: None,
+            capture_video: true,
             buffer_count: 3,
+            capture_delay: Duration::ZERO,
+            max_dimensions: None,
+            source_rect: None,
+            scaling_quality: ScalingQuality::Smooth,
+            shared_clock: None,
+            power_profile: PowerProfile::Balanced,
+            maximum_fps: None,
+            frame_compositor: None,
+            #[cfg(feature = "bitmap")]
+            change_metric: false,
+        }
+    }
+
+    /// Create a capture configuration for any capture target, either a window or a display
+    /// 
+    /// Windows are configured as with `with_window`, and displays as with `with_display`. Returns `CaptureConfigError::UnsupportedPixelFormat`
+    /// if the pixel format isn't in `CaptureStream::supported_pixel_formats()`.
+    pub fn with_target(target: impl CaptureTarget, pixel_format: CapturePixelFormat) -> Result<CaptureConfig, CaptureConfigError> {
+        if !CaptureStream::supported_pixel_formats().contains(&pixel_format) {
+            return Err(CaptureConfigError::UnsupportedPixelFormat);
         }
+        match target.to_capturable() {
+            Capturable::Window(window) => Self::with_window(window, pixel_format),
+            Capturable::Display(display) => Ok(Self::with_display(display, pixel_format)),
+        }
+    }
+
+    /// Create a capture configuration which only captures audio, delivering `StreamEvent::Audio` but never `StreamEvent::Video`
+    /// 
+    /// The target still determines when the stream ends, e.g. with `StreamEndReason::TargetGone` when a captured window closes.
+    /// On MacOS, the stream is always captured with ScreenCaptureKit, which is given the smallest, least frequent video it allows, and whose audio
+    /// is filtered as configured by the `AudioCaptureConfig` (see `MacosAudioCaptureConfigExt`).
+    /// On Windows, only the loopback audio of the default output device is captured, and the target is never captured with Windows.Graphics.Capture,
+    /// so no capture border is shown.
+    pub fn audio_only(target: impl CaptureTarget, audio_config: AudioCaptureConfig) -> Result<CaptureConfig, CaptureConfigError> {
+        Ok(Self {
+            capture_video: false,
+            ..Self::with_target(target, CapturePixelFormat::Bgra8888)?.with_audio_capture(audio_config)
+        })
+    }
+
+    /// Configure the pixel format as the first of the given formats this platform supports, in order of preference
+    /// 
+    /// Returns `CaptureConfigError::UnsupportedPixelFormat` if none of them are supported - see `CaptureStream::supported_pixel_formats()`.
+    /// Use `pixel_format()` to find which format was chosen.
+    pub fn with_any_pixel_format(self, pixel_formats: &[CapturePixelFormat]) -> Result<Self, CaptureConfigError> {
+        let supported_pixel_formats = CaptureStream::supported_pixel_formats();
+        let pixel_format = pixel_formats.iter()
+            .copied()
+            .find(|pixel_format| supported_pixel_formats.contains(pixel_format))
+            .ok_or(CaptureConfigError::UnsupportedPixelFormat)?;
+        Ok(Self {
+            pixel_format,
+            ..self
+        })
+    }
+
+    /// Get the pixel format frames of the stream will be delivered in
+    pub fn pixel_format(&self) -> CapturePixelFormat {
+        self.pixel_format
     }
 
     /// Configure the buffer count - the number of frames in the capture queue.
@@ -248,7 +997,40 @@ impl CaptureConfig {
         }
     }
 
-    /// Configure whether the cursor is visible in the capture
+    /// Configure a delay between starting the stream and delivering the first frame - by default, there is no delay
+    /// 
+    /// Frames produced during the delay are discarded, and `StreamEvent::Started` is delivered right before the first frame after it.
+    pub fn with_capture_delay(self, capture_delay: Duration) -> Self {
+        Self {
+            capture_delay,
+            ..self
+        }
+    }
+
+    #[cfg(feature = "bitmap")]
+    /// Configure whether pooled bitmaps of frames from this stream measure how much they changed since the previous frame
+    /// (requires `bitmap` feature)
+    /// 
+    /// See `FrameBitmap::change_metric()` for details
+    pub fn with_change_metric(self, change_metric: bool) -> Self {
+        Self {
+            change_metric,
+            ..self
+        }
+    }
+
+    /// Capture system audio along with video, delivered to the stream callback as `StreamEvent::Audio`
+    /// 
+    /// On Windows, audio is captured from the default output device in loopback, converted to the configured rate and channel count,
+    /// as 32 bit float samples.
+    pub fn with_audio_capture(self, audio_config: AudioCaptureConfig) -> Self {
+        Self {
+            capture_audio: Some(audio_config),
+            ..self
+        }
+    }
+
+    /// Configure whether the cursor is visible in the capture
     pub fn with_show_cursor(self, show_cursor: bool) -> Self {
         Self {
             show_cursor,
@@ -263,11 +1045,397 @@ impl CaptureConfig {
             ..self
         }
     }
+
+    /// Configure an upper bound on the size of delivered frames, to bound memory use when capturing arbitrarily large windows
+    /// 
+    /// Whenever the output size would exceed the bound, frames are scaled down to fit within it, preserving their aspect ratio.
+    /// Use `VideoFrame::native_content_size()` to find the size of the content before scaling.
+    /// 
+    /// On MacOS, the captured content is scaled into a fixed size output, so a window growing past the bound is scaled rather than reallocated.
+    /// On Windows, frames are captured at their native size and then scaled on the GPU, following the window as it resizes.
+    pub fn with_max_dimensions(self, width: f64, height: f64) -> Self {
+        Self {
+            max_dimensions: Some(Size { width, height }),
+            ..self
+        }
+    }
+
+    /// Capture only a rectangle of the target, so frames hold just that region rather than the full content - by default, the full target is captured
+    /// 
+    /// The rectangle is relative to the top-left of the capture target, in the units of the target's `rect()`, and frames are cropped as with
+    /// `CaptureStream::set_source_rect()`, which can move the crop once the stream is running. When the stream is created, the rectangle is clamped
+    /// to the target's bounds at that time (its intersection with them), and stream creation fails with `CaptureConfigError::SourceRectOutOfBounds`
+    /// if the rectangle lies entirely outside the target.
+    pub fn with_source_rect(self, rect: Rect) -> Self {
+        Self {
+            source_rect: Some(rect),
+            ..self
+        }
+    }
+
+    /// Measure the timestamps of video frames from the clock's origin, and hold back frames until the stream is started with `CaptureClock::start_all`
+    /// 
+    /// See `CaptureClock` for details
+    pub fn with_shared_clock(self, clock: &CaptureClock) -> Self {
+        Self {
+            shared_clock: Some(*clock),
+            ..self
+        }
+    }
+
+    /// Configure how the captured content is scaled into the output size - by default, `ScalingQuality::Smooth`
+    /// 
+    /// Use `ScalingQuality::NearestNeighbor` for pixel-perfect scaling of low resolution content, such as pixel art.
+    /// 
+    /// On Windows, `NearestNeighbor` and `None` frames are placed into the output on the GPU, so frames are always delivered at the output size.
+    /// On MacOS, the content is captured at its native size and placed into the output when copying bitmaps (requires the `bitmap` feature),
+    /// while the GPU textures of frames (`metal`, `iosurface` and `wgpu` features) contain the content at its native size.
+    /// Displays captured with the CGDisplayStream backend on MacOS are always scaled smoothly.
+    pub fn with_scaling_quality(self, scaling_quality: ScalingQuality) -> Self {
+        Self {
+            scaling_quality,
+            ..self
+        }
+    }
+
+    /// Configure how the stream trades latency and smoothness for power use - by default, `PowerProfile::Balanced`
+    /// 
+    /// See `PowerProfile` for the trade-offs of each profile, and `CaptureStream::effective_config()` for the settings that were applied
+    pub fn with_power_profile(self, power_profile: PowerProfile) -> Self {
+        Self {
+            power_profile,
+            ..self
+        }
+    }
+
+    /// Configure the maximum frame rate of the stream, or `None` to deliver a frame for every update of the content - by default, `None`
+    /// 
+    /// Rates below 1.0 are supported for timelapse-style capture. Frames are only produced when content changes, so static content
+    /// may produce fewer frames.
+    /// 
+    /// On MacOS, ScreenCaptureKit is asked for the frame interval, and is never asked for intervals longer than one second - longer
+    /// intervals are enforced by skipping frames in the crate instead.
+    /// On Windows, Windows.Graphics.Capture can't be throttled, so frames arriving sooner than the frame interval are returned to the
+    /// frame pool before they're copied or converted.
+    pub fn with_maximum_fps(self, maximum_fps: Option<f32>) -> Self {
+        Self {
+            maximum_fps,
+            ..self
+        }
+    }
+
+    /// Draw into each frame before it's delivered, such as for picture-in-picture overlays or watermarks
+    /// 
+    /// See `FrameCompositor` for which target the compositor receives on each platform
+    pub fn with_frame_compositor(self, frame_compositor: Box<dyn FrameCompositor>) -> Self {
+        Self {
+            frame_compositor: Some(SharedFrameCompositor::new(frame_compositor)),
+            ..self
+        }
+    }
+
+    /// Check the configuration for settings the capture can't use, without starting a capture - `CaptureStream::new()` fails with
+    /// `StreamCreateError::InvalidConfig` for any config this rejects
+    /// 
+    /// This checks that:
+    /// * The pixel format is supported - see `CaptureStream::supported_pixel_formats()`
+    /// * The buffer count is supported - see `CaptureStream::supported_buffer_counts()`
+    /// * The output size is at least a pixel in each dimension
+    /// * The source rect, if any, overlaps the target as it is now - see `with_source_rect()`
+    /// * The maximum frame rate, if any, is greater than zero - see `with_maximum_fps()`
+    /// 
+    /// Every audio sample rate and channel count can be captured on every platform, so audio settings are always valid.
+    pub fn validate(&self) -> Result<(), CaptureConfigError> {
+        let limits = ConfigLimits {
+            pixel_formats: CaptureStream::supported_pixel_formats(),
+            buffer_counts: CaptureStream::supported_buffer_counts(),
+            target_size: self.target.rect().size,
+        };
+        validate_settings(self.pixel_format, self.buffer_count, self.output_size, self.source_rect, self.maximum_fps, &limits)
+    }
+
+    // The source rect clamped to the bounds of the target, if any - fails if it's entirely outside the target
+    pub(crate) fn clamped_source_rect(&self) -> Result<Option<Rect>, String> {
+        let Some(source_rect) = self.source_rect else {
+            return Ok(None);
+        };
+        let target_size = self.target.rect().size;
+        clamp_source_rect(source_rect, target_size)
+            .map(Some)
+            .ok_or_else(|| format!("Source rect {:?} is outside the target bounds {:?}", source_rect, target_size))
+    }
+
+    // Scale a size down to fit within the maximum dimensions, if any, preserving its aspect ratio
+    pub(crate) fn cap_size(&self, size: Size) -> Size {
+        match self.max_dimensions {
+            Some(max_dimensions) => size.fit_within(max_dimensions),
+            None => size,
+        }
+    }
+}
+
+// What the platform supports, and the size of the capture target, for validating a config against
+struct ConfigLimits {
+    pixel_formats: &'static [CapturePixelFormat],
+    buffer_counts: RangeInclusive<usize>,
+    target_size: Size,
+}
+
+// Clamp a source rect to the bounds of a target of the given size - `None` if it doesn't overlap the target
+fn clamp_source_rect(source_rect: Rect, target_size: Size) -> Option<Rect> {
+    source_rect.intersection(&Rect { origin: Point::ZERO, size: target_size })
+}
+
+// The checks of `CaptureConfig::validate()`, apart from the target and the platform so that they can be tested anywhere
+fn validate_settings(pixel_format: CapturePixelFormat, buffer_count: usize, output_size: Size, source_rect: Option<Rect>, maximum_fps: Option<f32>, limits: &ConfigLimits) -> Result<(), CaptureConfigError> {
+    if !limits.pixel_formats.contains(&pixel_format) {
+        return Err(CaptureConfigError::UnsupportedPixelFormat);
+    }
+    if !limits.buffer_counts.contains(&buffer_count) {
+        return Err(CaptureConfigError::InvalidBufferCount);
+    }
+    let Size { width, height } = output_size;
+    if !(width.is_finite() && height.is_finite() && width >= 1.0 && height >= 1.0) {
+        return Err(CaptureConfigError::InvalidOutputSize);
+    }
+    if source_rect.is_some_and(|source_rect| clamp_source_rect(source_rect, limits.target_size).is_none()) {
+        return Err(CaptureConfigError::SourceRectOutOfBounds);
+    }
+    // An infinite rate is allowed, and delivers every frame as `None` does
+    if maximum_fps.is_some_and(|maximum_fps| maximum_fps.is_nan() || maximum_fps <= 0.0) {
+        return Err(CaptureConfigError::InvalidMaximumFps);
+    }
+    Ok(())
 }
 
 /// Represents an active capture stream
 pub struct CaptureStream {
     pub(crate) impl_capture_stream: ImplCaptureStream,
+    first_frame: Arc<Mutex<FirstFrameState>>,
+    pixel_format: CapturePixelFormat,
+    // Set by `CaptureClock::start_all` to release a stream using a shared clock
+    start_gate: Option<Arc<AtomicBool>>,
+    // Set while the stream is paused, to drop frames rather than deliver them
+    paused: Arc<AtomicBool>,
+    frame_counts: Arc<FrameCounts>,
+    // The current capture target, and the crop applied to it with `set_source_rect`
+    target: Capturable,
+    source_rect: Option<Rect>,
+    // Settings which `update_config` can't change
+    scaling_quality: ScalingQuality,
+    stop_handle: StopHandle,
+}
+
+/// A cheap, cloneable handle for stopping a capture stream from any thread - see `CaptureStream::stop_handle()`
+/// 
+/// Stopping through a handle is the same as `CaptureStream::stop()`. The stream's callback receives `StreamEvent::End` exactly once,
+/// however many times and from however many handles the stream is stopped, and whether or not the stream already ended on its own.
+/// 
+/// A handle can also stop the stream from inside the stream's own callback - `StreamEvent::End` is then delivered as soon as the callback returns.
+/// Stopping doesn't release the stream, which still needs to be dropped or closed with `CaptureStream::close()`.
+#[derive(Clone)]
+pub struct StopHandle {
+    // Checked by the stream's callback, so that a stop requested from inside it ends the stream once it returns
+    stop_requested: Arc<AtomicBool>,
+    impl_stop_handle: ImplStopHandle,
+}
+
+unsafe impl Send for StopHandle {}
+unsafe impl Sync for StopHandle {}
+
+impl StopHandle {
+    /// Stop the stream - see `CaptureStream::stop()`
+    /// 
+    /// Stopping a stream which has already stopped or ended does nothing.
+    pub fn stop(&self) -> Result<(), StreamStopError> {
+        self.stop_requested.store(true, atomic::Ordering::Release);
+        // The stream's callback is locked while it runs, so from inside it the end of the stream is left to the callback to deliver
+        let in_callback = DELIVERING_STREAM.with(|delivering_stream| std::ptr::eq(delivering_stream.get(), Arc::as_ptr(&self.stop_requested)));
+        self.impl_stop_handle.stop(!in_callback)
+    }
+}
+
+thread_local! {
+    // The stop request flag of the stream whose callback is running on this thread, if any
+    static DELIVERING_STREAM: Cell<*const AtomicBool> = const { Cell::new(std::ptr::null()) };
+}
+
+// Tracks whether a stream has delivered its first video frame, for `CaptureStream::await_first_frame`
+#[derive(Default)]
+struct FirstFrameState {
+    delivered: bool,
+    first_frame_time: Option<Instant>,
+    end_reason: Option<StreamEndReason>,
+    waiters: Vec<oneshot::Sender<()>>,
+    // The streams started together with this one by `CaptureClock::start_all`, including this one
+    start_group: Option<Arc<[Arc<Mutex<FirstFrameState>>]>>,
+}
+
+impl FirstFrameState {
+    fn observe(&mut self, event: &Result<StreamEvent, StreamError>) {
+        match event {
+            Ok(StreamEvent::Video(frame)) => self.observe_frame(frame.capture_time()),
+            Ok(StreamEvent::End { reason }) => self.observe_end(reason),
+            _ => {},
+        }
+    }
+
+    fn observe_frame(&mut self, capture_time: Instant) {
+        if !self.delivered {
+            self.delivered = true;
+            self.first_frame_time = Some(capture_time);
+            self.wake_waiters();
+        }
+    }
+
+    fn observe_end(&mut self, reason: &StreamEndReason) {
+        if self.end_reason.is_none() {
+            self.end_reason = Some(reason.clone());
+            self.wake_waiters();
+        }
+    }
+
+    fn wake_waiters(&mut self) {
+        for waiter in self.waiters.drain(..) {
+            let _ = waiter.send(());
+        }
+    }
+}
+
+// Pause or resume a stream's gate, unless the stream has ended
+fn set_paused(first_frame: &Mutex<FirstFrameState>, paused_flag: &AtomicBool, paused: bool) -> Result<(), StreamError> {
+    if let Some(end_reason) = &first_frame.lock().end_reason {
+        return Err(StreamError::Other(format!("Stream has ended: {:?}", end_reason)));
+    }
+    paused_flag.store(paused, atomic::Ordering::Release);
+    Ok(())
+}
+
+// How long after the earliest first frame of a stream's start group the stream delivered its first frame
+fn start_skew(first_frame: &Mutex<FirstFrameState>) -> Option<Duration> {
+    // The stream's own state is in its group, so it's unlocked before the group is
+    let (first_frame_time, start_group) = {
+        let first_frame = first_frame.lock();
+        (first_frame.first_frame_time?, first_frame.start_group.clone()?)
+    };
+    let earliest_first_frame_time = start_group.iter()
+        .filter_map(|first_frame| first_frame.lock().first_frame_time)
+        .min()
+        .unwrap_or(first_frame_time);
+    Some(first_frame_time.saturating_duration_since(earliest_first_frame_time))
+}
+
+// The video frames the callback received and the frames held back from it, for `CaptureStream::statistics()` - frames dropped by the
+// platform are counted by the `ImplCaptureStream`
+#[derive(Default)]
+struct FrameCounts {
+    delivered: AtomicU64,
+    dropped: AtomicU64,
+    // The capture time of the last delivered frame, and the time between it and the frame before
+    last_frame: Mutex<(Option<Instant>, Option<Duration>)>,
+}
+
+impl FrameCounts {
+    // Count an event which wasn't delivered - only video frames count as dropped
+    fn drop_event(&self, kind: StreamEventKind) {
+        if kind == StreamEventKind::Video {
+            self.dropped.fetch_add(1, atomic::Ordering::Relaxed);
+        }
+    }
+
+    fn deliver_frame(&self, capture_time: Instant) {
+        let mut last_frame = self.last_frame.lock();
+        if let Some(last_frame_time) = last_frame.0.replace(capture_time) {
+            last_frame.1 = Some(capture_time.saturating_duration_since(last_frame_time));
+        }
+        self.delivered.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    fn last_frame_interval(&self) -> Option<Duration> {
+        self.last_frame.lock().1
+    }
+}
+
+// The kinds of event the platform gives a stream's callback, for `StreamGate`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum StreamEventKind {
+    Video,
+    Audio,
+    Idle,
+    Other,
+}
+
+impl StreamEventKind {
+    fn of(event: &Result<StreamEvent, StreamError>) -> Self {
+        match event {
+            Ok(StreamEvent::Video(_)) => Self::Video,
+            Ok(StreamEvent::Audio(_)) => Self::Audio,
+            Ok(StreamEvent::Idle) => Self::Idle,
+            _ => Self::Other,
+        }
+    }
+
+    fn is_content(self) -> bool {
+        matches!(self, Self::Video | Self::Audio | Self::Idle)
+    }
+}
+
+// What a stream's callback does with an event from the platform
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum GateAction {
+    // Deliver the event, after delivering `StreamEvent::Started` if `start` is set
+    Deliver { start: bool },
+    // Drop the event, counting it if it's a video frame
+    Drop,
+    // Drop the event without counting it
+    Ignore,
+    // Drop the event, and end the stream in its place
+    End,
+}
+
+// Decides which of the platform's events a stream's callback delivers - the flags are shared with the `CaptureStream`
+struct StreamGate {
+    ended: bool,
+    started: bool,
+    // Content is held back until the capture delay has passed
+    start_time: Instant,
+    capture_video: bool,
+    start_gate: Option<Arc<AtomicBool>>,
+    paused: Arc<AtomicBool>,
+    stop_requested: Arc<AtomicBool>,
+}
+
+impl StreamGate {
+    fn admit(&mut self, kind: StreamEventKind, now: Instant) -> GateAction {
+        if self.ended {
+            return GateAction::Drop;
+        }
+        // A stop was requested while this event was on its way, so the stream ends in its place
+        if self.stop_requested.load(atomic::Ordering::Acquire) {
+            return GateAction::End;
+        }
+        if kind.is_content() && self.paused.load(atomic::Ordering::Acquire) {
+            return GateAction::Drop;
+        }
+        // Audio-only streams never deliver video, even if the platform produces a frame
+        if !self.capture_video && matches!(kind, StreamEventKind::Video | StreamEventKind::Idle) {
+            return GateAction::Ignore;
+        }
+        if !self.started {
+            if kind.is_content() {
+                // Streams using a shared clock run from creation, but hold back frames until they're released by `CaptureClock::start_all`
+                let held = self.start_gate.as_ref().is_some_and(|start_gate| !start_gate.load(atomic::Ordering::Acquire));
+                if held || now < self.start_time {
+                    return GateAction::Drop;
+                }
+            }
+            if matches!(kind, StreamEventKind::Video | StreamEventKind::Audio) {
+                self.started = true;
+                return GateAction::Deliver { start: true };
+            }
+        }
+        GateAction::Deliver { start: false }
+    }
 }
 
 unsafe impl Send for CaptureStream {}
@@ -282,12 +1450,61 @@ unsafe impl Send for CaptureAccessToken {}
 unsafe impl Sync for CaptureAccessToken {}
 
 impl CaptureAccessToken {
+    /// Whether this token allows capturing windows without the capture border
+    /// 
+    /// On Windows this requires requesting access with `borderless` set, on MacOS it is always allowed.
     pub fn allows_borderless(&self) -> bool {
         self.impl_capture_access_token.allows_borderless()
     }
 }
 
+/// Information about a panic that occurred inside a stream callback
+#[derive(Debug, Clone)]
+pub struct CallbackPanic {
+    message: Option<String>,
+}
+
+impl CallbackPanic {
+    pub(crate) fn from_payload(payload: &(dyn Any + Send)) -> Self {
+        let message = if let Some(message) = payload.downcast_ref::<&'static str>() {
+            Some(message.to_string())
+        } else {
+            payload.downcast_ref::<String>().cloned()
+        };
+        Self {
+            message
+        }
+    }
+
+    /// Gets the panic message, if the panic payload was a string
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
+
+type CallbackPanicHandler = Arc<dyn Fn(&CallbackPanic) + Send + Sync + 'static>;
+
+static CALLBACK_PANIC_HANDLER: Mutex<Option<CallbackPanicHandler>> = parking_lot::const_mutex(None);
+
+pub(crate) fn handle_callback_panic(payload: &(dyn Any + Send)) {
+    let handler = CALLBACK_PANIC_HANDLER.lock().clone();
+    if let Some(handler) = handler {
+        let panic = CallbackPanic::from_payload(payload);
+        let _ = catch_unwind(AssertUnwindSafe(|| handler(&panic)));
+    }
+}
+
 impl CaptureStream {
+    /// Set a handler which is called when a stream callback panics, replacing any previous handler
+    /// 
+    /// Panics inside stream callbacks are always caught, so they never unwind into the OS capture APIs.
+    /// After a panic, the stream delivers `StreamEvent::End` with `StreamEndReason::CallbackPanicked` to its callback and then stops delivering events -
+    /// you should still call `stop()` on the stream to release it. This handler lets you log or report those panics,
+    /// which would otherwise only be visible through the default panic hook.
+    pub fn set_callback_panic_handler(handler: impl Fn(&CallbackPanic) + Send + Sync + 'static) {
+        *CALLBACK_PANIC_HANDLER.lock() = Some(Arc::new(handler));
+    }
+
     /// Test whether the calling application has permission to capture content
     pub fn test_access(borderless: bool) -> Option<CaptureAccessToken> {
         ImplCaptureStream::check_access(borderless).map(|impl_capture_access_token|
@@ -306,23 +1523,786 @@ impl CaptureStream {
         )
     }
 
+    /// Get the URL of the OS settings page where the user can grant capture permission, without opening it
+    /// 
+    /// On MacOS this is the Screen Recording section of Privacy & Security in System Settings (or System Preferences before MacOS 13),
+    /// and on Windows it's the screen capture privacy page of the Settings app.
+    /// Returns `None` if there's no such page on this platform or OS version - on versions of Windows without one, use `request_access()` to show the classic prompt instead.
+    pub fn permission_settings_url() -> Option<&'static str> {
+        ImplCaptureStream::permission_settings_url()
+    }
+
+    /// Open the OS settings page where the user can grant capture permission - see `permission_settings_url()`
+    /// 
+    /// Use this when `request_access()` fails, or a stream or screenshot fails with a permission error.
+    /// On MacOS this returns `OpenSettingsOutcome::NeedsRestart`, since newly granted access only takes effect once the application is restarted.
+    pub fn open_permission_settings() -> Result<OpenSettingsOutcome, OpenSettingsError> {
+        ImplCaptureStream::open_permission_settings()
+    }
+
     /// Gets the implementation's supported pixel formats
+    /// 
+    /// These are in the platform's own order, so rather than taking the first, use `preferred_pixel_format()` to choose one for a purpose -
+    /// or `CaptureConfig::with_any_pixel_format()` to choose from an application's own preferences.
     pub fn supported_pixel_formats() -> &'static [CapturePixelFormat] {
         ImplCaptureStream::supported_pixel_formats()
     }
 
+    /// Gets the range of buffer counts the implementation supports - see `CaptureConfig::with_buffer_count()`
+    /// 
+    /// On MacOS, this is bounded by the largest queue depth ScreenCaptureKit accepts.
+    pub fn supported_buffer_counts() -> RangeInclusive<usize> {
+        ImplCaptureStream::supported_buffer_counts()
+    }
+
+    /// Gets the supported pixel format best suited to the given purpose - see `PixelFormatPurpose`
+    /// 
+    /// E.G. `CpuBitmap` gives `CapturePixelFormat::Bgra8888` on both MacOS and Windows, while `VideoEncode` gives `CapturePixelFormat::V420`
+    /// on MacOS, and falls back to `CapturePixelFormat::Bgra8888` on Windows, where YCbCr formats aren't supported.
+    /// Returns `None` if the platform supports no pixel formats.
+    pub fn preferred_pixel_format(purpose: PixelFormatPurpose) -> Option<CapturePixelFormat> {
+        purpose.preferred_pixel_format(Self::supported_pixel_formats())
+    }
+
     /// Start a new capture stream with the given stream callback
-    pub fn new(token: CaptureAccessToken, config: CaptureConfig, callback: impl FnMut(Result<StreamEvent, StreamError>) + Send + 'static) -> Result<Self, StreamCreateError> {
-        let boxed_callback = Box::new(callback);
+    /// 
+    /// The config may be passed by value or borrowed - a borrowed config is copied, so it can be reused for other streams afterwards.
+    pub fn new(token: CaptureAccessToken, config: impl Borrow<CaptureConfig>, callback: impl FnMut(Result<StreamEvent, StreamError>) + Send + 'static) -> Result<Self, StreamCreateError> {
+        let config = config.borrow().clone();
+        config.validate().map_err(StreamCreateError::InvalidConfig)?;
+        let mut callback = callback;
+        let start_time = Instant::now() + config.capture_delay;
+        let pixel_format = config.pixel_format;
+        let target = config.target.clone();
+        let scaling_quality = config.scaling_quality;
+        let capture_video = config.capture_video;
+        let source_rect = config.clamped_source_rect().map_err(|_| StreamCreateError::InvalidConfig(CaptureConfigError::SourceRectOutOfBounds))?;
+        let config = CaptureConfig {
+            source_rect,
+            ..config
+        };
+        let first_frame = Arc::new(Mutex::new(FirstFrameState::default()));
+        let callback_first_frame = first_frame.clone();
+        let clock_origin = config.shared_clock.map(|clock| clock.origin);
+        let start_gate = config.shared_clock.map(|_| Arc::new(AtomicBool::new(false)));
+        let paused = Arc::new(AtomicBool::new(false));
+        let frame_counts = Arc::new(FrameCounts::default());
+        let callback_frame_counts = frame_counts.clone();
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let callback_stop_requested = stop_requested.clone();
+        // Deliver an event, catching panics - returns false if the stream has ended
+        let mut deliver = move |event: Result<StreamEvent, StreamError>, ended: &mut bool| {
+            let previous_delivering_stream = DELIVERING_STREAM.with(|delivering_stream| delivering_stream.replace(Arc::as_ptr(&callback_stop_requested)));
+            let mut next_event = Some(event);
+            while let Some(event) = next_event.take() {
+                callback_first_frame.lock().observe(&event);
+                *ended = matches!(event, Ok(StreamEvent::End { .. }));
+                if let Err(payload) = catch_unwind(AssertUnwindSafe(|| callback(event))) {
+                    handle_callback_panic(payload.as_ref());
+                    if !*ended {
+                        *ended = true;
+                        if let Err(payload) = catch_unwind(AssertUnwindSafe(|| callback(Ok(StreamEvent::End { reason: StreamEndReason::CallbackPanicked })))) {
+                            handle_callback_panic(payload.as_ref());
+                        }
+                    }
+                }
+                // The callback stopped the stream through a `StopHandle`, so end it now that the callback has returned
+                if !*ended && callback_stop_requested.load(atomic::Ordering::Acquire) {
+                    next_event = Some(Ok(StreamEvent::End { reason: StreamEndReason::Stopped }));
+                }
+            }
+            DELIVERING_STREAM.with(|delivering_stream| delivering_stream.set(previous_delivering_stream));
+            !*ended
+        };
+        let mut gate = StreamGate {
+            ended: false,
+            started: false,
+            start_time,
+            capture_video,
+            start_gate: start_gate.clone(),
+            paused: paused.clone(),
+            stop_requested: stop_requested.clone(),
+        };
+        let boxed_callback = Box::new(move |mut event: Result<StreamEvent, StreamError>| {
+            let kind = StreamEventKind::of(&event);
+            match gate.admit(kind, Instant::now()) {
+                GateAction::Deliver { start } => {
+                    if start && !deliver(Ok(StreamEvent::Started), &mut gate.ended) {
+                        callback_frame_counts.drop_event(kind);
+                        return;
+                    }
+                },
+                GateAction::Drop => {
+                    callback_frame_counts.drop_event(kind);
+                    return;
+                },
+                GateAction::Ignore => return,
+                GateAction::End => {
+                    callback_frame_counts.drop_event(kind);
+                    deliver(Ok(StreamEvent::End { reason: StreamEndReason::Stopped }), &mut gate.ended);
+                    return;
+                },
+            }
+            if let Ok(StreamEvent::Video(frame)) = &mut event {
+                if let Some(clock_origin) = clock_origin {
+                    frame.clock_origin = Some(clock_origin);
+                }
+                callback_frame_counts.deliver_frame(frame.capture_time());
+            }
+            deliver(event, &mut gate.ended);
+        });
+        let impl_capture_stream = ImplCaptureStream::new(token.impl_capture_access_token, config, boxed_callback)?;
+        let stop_handle = StopHandle {
+            stop_requested,
+            impl_stop_handle: impl_capture_stream.stop_handle(),
+        };
         Ok(Self {
-            impl_capture_stream: ImplCaptureStream::new(token.impl_capture_access_token, config, boxed_callback)?
+            impl_capture_stream,
+            first_frame,
+            pixel_format,
+            start_gate,
+            paused,
+            frame_counts,
+            target,
+            source_rect,
+            scaling_quality,
+            stop_handle,
         })
     }
 
+    /// Get statistics about the stream so far
+    pub fn statistics(&self) -> StreamStatistics {
+        StreamStatistics {
+            start_skew: start_skew(&self.first_frame),
+            frames_delivered: self.frame_counts.delivered.load(atomic::Ordering::Relaxed),
+            frames_dropped: self.frame_counts.dropped.load(atomic::Ordering::Relaxed) + self.impl_capture_stream.dropped_frame_count(),
+            last_frame_interval: self.frame_counts.last_frame_interval(),
+        }
+    }
+
+    /// Get the settings the stream is actually running with, after applying its power profile
+    pub fn effective_config(&self) -> EffectiveCaptureConfig {
+        self.impl_capture_stream.effective_config()
+    }
+
+    /// Wait until the stream has delivered its first video frame, to check that capture is actually working
+    /// 
+    /// Resolves immediately if a frame was already delivered. Returns `StreamError::Timeout` with diagnostic information
+    /// (capture access, pixel format and capture backend) if no frame arrives within the timeout,
+    /// or `StreamError::Other` if the stream ends first.
+    /// 
+    /// Note that on MacOS, frames are only produced when the captured content changes
+    pub async fn await_first_frame(&self, timeout: Duration) -> Result<(), StreamError> {
+        let first_frame_rx = {
+            let mut first_frame = self.first_frame.lock();
+            if first_frame.delivered || first_frame.end_reason.is_some() {
+                None
+            } else {
+                let (tx, rx) = oneshot::channel();
+                first_frame.waiters.push(tx);
+                Some(rx)
+            }
+        };
+        if let Some(first_frame_rx) = first_frame_rx {
+            with_timeout(first_frame_rx, Some(timeout)).await;
+        }
+        let first_frame = self.first_frame.lock();
+        if first_frame.delivered {
+            return Ok(());
+        }
+        if let Some(end_reason) = &first_frame.end_reason {
+            return Err(StreamError::Other(format!("Stream ended before delivering a frame: {:?}", end_reason)));
+        }
+        let access = if ImplCaptureStream::check_access(false).is_some() { "granted" } else { "not granted" };
+        Err(StreamError::Timeout(format!(
+            "No frame within {:?} [capture access: {}, pixel format: {:?}, backend: {}]",
+            timeout, access, self.pixel_format, self.impl_capture_stream.backend_name()
+        )))
+    }
+
+    /// Pause delivery of frames, without tearing down the stream
+    /// 
+    /// While paused, video frames, audio frames and idle events are dropped as they arrive, while errors and `StreamEvent::End` are still delivered.
+    /// The OS keeps capturing in the meantime, so resuming is immediate - but a paused stream uses nearly as much power as a running one.
+    /// Returns an error if the stream has already ended.
+    pub fn pause(&mut self) -> Result<(), StreamError> {
+        self.set_paused(true)
+    }
+
+    /// Resume delivery of frames after `pause()`
+    /// 
+    /// The first frame after resuming is the next one the OS produces - on MacOS, that may not be until the captured content changes.
+    /// Returns an error if the stream has already ended.
+    pub fn resume(&mut self) -> Result<(), StreamError> {
+        self.set_paused(false)
+    }
+
+    /// Whether frame delivery is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(atomic::Ordering::Acquire)
+    }
+
+    fn set_paused(&mut self, paused: bool) -> Result<(), StreamError> {
+        set_paused(&self.first_frame, &self.paused, paused)
+    }
+
+    /// Change the capture target of the running stream, without tearing it down
+    /// 
+    /// The stream keeps its config, callback, frame ids and output size - the new target is fit into the output size the same way a resized target would be.
+    /// No `StreamEvent::End` is delivered for the old target, and frames of the old target still in flight when the target changes are dropped,
+    /// so every frame delivered after this returns is of the new target.
+    /// 
+    /// On MacOS, the CGDisplayStream backend can't change targets, and a stream capturing application-only audio can't switch to a display
+    /// (see `MacosCaptureConfigExt::with_application_only_audio`) - both return `StreamUpdateError::Unsupported`.
+    /// 
+    /// The crop set with `set_source_rect` is reset, since it was relative to the old target.
+    pub fn update_target(&mut self, target: Capturable) -> Result<(), StreamUpdateError> {
+        if self.first_frame.lock().end_reason.is_some() {
+            return Err(StreamUpdateError::AlreadyStopped);
+        }
+        if self.source_rect.is_some() {
+            self.set_source_rect(None)
+                .map_err(|error| StreamUpdateError::Other(format!("Failed to reset the source rect: {}", error)))?;
+        }
+        self.impl_capture_stream.update_target(target.clone())?;
+        self.target = target;
+        Ok(())
+    }
+
+    /// Apply the settings of a new config which can change while the stream runs, without restarting it
+    /// 
+    /// The output size, maximum dimensions, buffer count and maximum frame rate (see `CaptureConfig::with_maximum_fps`) are applied. Changing the target, pixel format or scaling quality returns
+    /// `StreamUpdateError::RequiresRestart` - use `update_target` to change the target. The rest of the config keeps the values the stream was created with,
+    /// and a crop set with `set_source_rect` is kept.
+    /// 
+    /// The new settings are checked as `CaptureConfig::validate()` checks a new stream's, returning `StreamUpdateError::InvalidConfig` if any are rejected.
+    /// 
+    /// On MacOS, the CGDisplayStream backend can't change its config while running, and returns `StreamUpdateError::Unsupported`.
+    pub fn update_config(&mut self, config: CaptureConfig) -> Result<(), StreamUpdateError> {
+        if self.first_frame.lock().end_reason.is_some() {
+            return Err(StreamUpdateError::AlreadyStopped);
+        }
+        if config.target != self.target {
+            return Err(StreamUpdateError::RequiresRestart("target".into()));
+        }
+        if config.pixel_format != self.pixel_format {
+            return Err(StreamUpdateError::RequiresRestart("pixel format".into()));
+        }
+        if config.scaling_quality != self.scaling_quality {
+            return Err(StreamUpdateError::RequiresRestart("scaling quality".into()));
+        }
+        let limits = ConfigLimits {
+            pixel_formats: Self::supported_pixel_formats(),
+            buffer_counts: Self::supported_buffer_counts(),
+            target_size: self.target.rect().size,
+        };
+        // The config's own source rect isn't applied, so only the crop set with `set_source_rect` has to fit the target
+        validate_settings(config.pixel_format, config.buffer_count, config.output_size, None, config.maximum_fps, &limits)
+            .map_err(StreamUpdateError::InvalidConfig)?;
+        self.impl_capture_stream.update_config(&config)
+    }
+
+    /// Crop the running stream to a rectangle of the captured content, or pass `None` to capture the full content again
+    /// 
+    /// The rectangle is relative to the top-left of the capture target, in the units of the target's `rect()`.
+    /// As with `CaptureConfig::with_source_rect`, it's clamped to the target's bounds, and must overlap them. Frames shrink with the crop, keeping the pixel density of the full capture -
+    /// except with a scaling quality other than `ScalingQuality::Smooth`, where the cropped region is fit into the output size.
+    /// 
+    /// On MacOS, the CGDisplayStream backend can't crop a running stream.
+    pub fn set_source_rect(&mut self, rect: Option<Rect>) -> Result<(), StreamError> {
+        if let Some(end_reason) = &self.first_frame.lock().end_reason {
+            return Err(StreamError::Other(format!("Stream has ended: {:?}", end_reason)));
+        }
+        let content_size = self.target.rect().size;
+        let rect = match rect {
+            Some(rect) => Some(clamp_source_rect(rect, content_size)
+                .ok_or_else(|| StreamError::Other(format!("Source rect {:?} is outside the content bounds {:?}", rect, content_size)))?),
+            None => None,
+        };
+        self.impl_capture_stream.set_source_rect(rect, content_size)?;
+        self.source_rect = rect;
+        Ok(())
+    }
+
+    /// Get the crop set with `set_source_rect`, if any, as clamped to the target's bounds
+    pub fn source_rect(&self) -> Option<Rect> {
+        self.source_rect
+    }
+
+    /// Get the size of the video frames the stream delivers, as configured with the OS - this can differ from the requested output size,
+    /// which is capped to the maximum dimensions, rounded to whole pixels, and shrunk with a crop
+    /// 
+    /// This follows changes made by `update_config` and `set_source_rect`. Frames captured at their native size on MacOS
+    /// (see `CaptureConfig::with_scaling_quality`) have GPU textures at the native size, and bitmaps of this size.
+    /// On Windows, streams with maximum dimensions and `ScalingQuality::Smooth` follow the size of the content, so their frames may be smaller.
+    pub fn output_size(&self) -> Size {
+        self.impl_capture_stream.output_size()
+    }
+
+    /// Get the pixel format of the video frames the stream delivers
+    pub fn pixel_format(&self) -> CapturePixelFormat {
+        self.pixel_format
+    }
+
+    /// Get the current capture target - either the target the stream was created with, or the last target given to `update_target`
+    pub fn target(&self) -> &Capturable {
+        &self.target
+    }
+
     /// Stop the capture
     pub fn stop(&mut self) -> Result<(), StreamStopError> {
-        self.impl_capture_stream.stop()
+        self.stop_handle.stop()
+    }
+
+    /// Get a handle which can stop the stream from any thread, or from inside the stream's callback - see `StopHandle`
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop_handle.clone()
+    }
+
+    /// Stop the capture, and wait for the OS to confirm that the stream has been torn down
+    /// 
+    /// `stop()` and dropping the stream only request teardown, so the OS may still hold onto the capture resources for a short time afterwards.
+    /// Await this before starting a new capture (E.G. when rapidly switching capture targets) to avoid "device busy" errors.
+    /// 
+    /// On MacOS this waits for the ScreenCaptureKit stop completion handler (or the CGDisplayStream stopped status),
+    /// and on Windows it waits for the capture session and frame pool to close and the stream thread to exit.
+    pub async fn close(self) -> Result<(), StreamStopError> {
+        self.impl_capture_stream.close().await
     }
 }
 
 
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{self, AtomicBool};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use parking_lot::Mutex;
+
+    use crate::util::{Point, Rect, Size};
+
+    use super::{clamp_source_rect, release_streams, set_paused, FrameIntervalLimiter, FrameLayout, start_skew, validate_settings, CaptureClock, CaptureConfigError, CapturePixelFormat, ConfigLimits, FirstFrameState, FrameCounts, GateAction, PixelFormatPurpose, ScalingQuality, StreamEndReason, StreamEventKind, StreamGate, MACOS_PIXEL_FORMATS, WINDOWS_PIXEL_FORMATS};
+
+    // A stream's gate and bookkeeping, doing what its callback does with each event - platform frames can't be created in tests,
+    // so frames are given as the time they're captured
+    struct StreamHarness {
+        gate: StreamGate,
+        first_frame: Arc<Mutex<FirstFrameState>>,
+        frame_counts: FrameCounts,
+        delivered: Vec<Instant>,
+        events: Vec<&'static str>,
+    }
+
+    impl StreamHarness {
+        fn new(start_time: Instant, shared_clock: bool) -> Self {
+            Self {
+                gate: StreamGate {
+                    ended: false,
+                    started: false,
+                    start_time,
+                    capture_video: true,
+                    start_gate: shared_clock.then(|| Arc::new(AtomicBool::new(false))),
+                    paused: Arc::new(AtomicBool::new(false)),
+                    stop_requested: Arc::new(AtomicBool::new(false)),
+                },
+                first_frame: Arc::new(Mutex::new(FirstFrameState::default())),
+                frame_counts: FrameCounts::default(),
+                delivered: Vec::new(),
+                events: Vec::new(),
+            }
+        }
+
+        fn event(&mut self, kind: StreamEventKind, now: Instant) -> GateAction {
+            let action = self.gate.admit(kind, now);
+            match action {
+                GateAction::Deliver { start } => {
+                    if start {
+                        self.events.push("started");
+                    }
+                    if kind == StreamEventKind::Video {
+                        self.frame_counts.deliver_frame(now);
+                        self.first_frame.lock().observe_frame(now);
+                        self.delivered.push(now);
+                    }
+                },
+                GateAction::Drop => self.frame_counts.drop_event(kind),
+                GateAction::Ignore => {},
+                GateAction::End => {
+                    self.frame_counts.drop_event(kind);
+                    self.end_delivered(&StreamEndReason::Stopped);
+                },
+            }
+            action
+        }
+
+        fn frame(&mut self, now: Instant) -> GateAction {
+            self.event(StreamEventKind::Video, now)
+        }
+
+        // The platform ends the stream
+        fn end(&mut self, reason: StreamEndReason, now: Instant) -> GateAction {
+            let action = self.event(StreamEventKind::Other, now);
+            if let GateAction::Deliver { .. } = action {
+                self.end_delivered(&reason);
+            }
+            action
+        }
+
+        fn end_delivered(&mut self, reason: &StreamEndReason) {
+            self.gate.ended = true;
+            self.first_frame.lock().observe_end(reason);
+            self.events.push("end");
+        }
+
+        fn dropped(&self) -> u64 {
+            self.frame_counts.dropped.load(atomic::Ordering::Relaxed)
+        }
+    }
+
+    #[test]
+    fn streams_on_a_shared_clock_start_on_the_same_frame() {
+        let clock = CaptureClock::new();
+        let origin = clock.origin();
+        let mut streams = [StreamHarness::new(origin, true), StreamHarness::new(origin, true)];
+        // Both streams capture the same blinking pattern, each seeing its frames a little after they're presented
+        let frame_interval = Duration::from_millis(16);
+        let latencies = [Duration::from_millis(1), Duration::from_millis(4)];
+        for frame in 0..30u32 {
+            if frame == 10 {
+                assert_eq!(start_skew(&streams[0].first_frame), None);
+                release_streams(streams.iter().map(|stream| (&stream.first_frame, stream.gate.start_gate.as_ref())));
+            }
+            for (stream, latency) in streams.iter_mut().zip(latencies) {
+                stream.frame(origin + frame_interval * frame + latency);
+            }
+        }
+        for stream in streams.iter() {
+            assert_eq!(stream.dropped(), 10);
+            assert_eq!(stream.delivered.len(), 20);
+            assert_eq!(stream.events, ["started"]);
+        }
+        // Matching frames of the pattern are only apart by the difference in latency - well within half a frame interval
+        let tolerance = frame_interval / 2;
+        for (first, second) in streams[0].delivered.iter().zip(streams[1].delivered.iter()) {
+            let (first, second) = (first.duration_since(origin), second.duration_since(origin));
+            assert!(first.abs_diff(second) < tolerance, "Frames at {:?} and {:?} don't match", first, second);
+        }
+        assert_eq!(start_skew(&streams[0].first_frame), Some(Duration::ZERO));
+        assert_eq!(start_skew(&streams[1].first_frame), Some(Duration::from_millis(3)));
+    }
+
+    fn limits() -> ConfigLimits {
+        ConfigLimits {
+            pixel_formats: &[CapturePixelFormat::Bgra8888, CapturePixelFormat::V420],
+            buffer_counts: 1..=8,
+            target_size: Size { width: 1920.0, height: 1080.0 },
+        }
+    }
+
+    fn size(width: f64, height: f64) -> Size {
+        Size { width, height }
+    }
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect { origin: Point { x, y }, size: size(width, height) }
+    }
+
+    #[test]
+    fn valid_settings_pass() {
+        assert!(validate_settings(CapturePixelFormat::Bgra8888, 3, size(1920.0, 1080.0), None, None, &limits()).is_ok());
+        // A source rect only has to overlap the target, since it's clamped to it
+        assert!(validate_settings(CapturePixelFormat::V420, 8, size(1.0, 1.0), Some(rect(-10.0, -10.0, 20.0, 20.0)), Some(0.5), &limits()).is_ok());
+    }
+
+    #[test]
+    fn unsupported_pixel_formats_are_rejected() {
+        let result = validate_settings(CapturePixelFormat::F420, 3, size(1920.0, 1080.0), None, None, &limits());
+        assert!(matches!(result, Err(CaptureConfigError::UnsupportedPixelFormat)));
+    }
+
+    #[test]
+    fn buffer_counts_outside_the_supported_range_are_rejected() {
+        for buffer_count in [0, 9] {
+            let result = validate_settings(CapturePixelFormat::Bgra8888, buffer_count, size(1920.0, 1080.0), None, None, &limits());
+            assert!(matches!(result, Err(CaptureConfigError::InvalidBufferCount)), "buffer count {buffer_count}");
+        }
+    }
+
+    #[test]
+    fn output_sizes_under_a_pixel_or_not_finite_are_rejected() {
+        for output_size in [size(0.0, 1080.0), size(1920.0, 0.5), size(-1.0, -1.0), size(f64::NAN, 1080.0), size(1920.0, f64::INFINITY)] {
+            let result = validate_settings(CapturePixelFormat::Bgra8888, 3, output_size, None, None, &limits());
+            assert!(matches!(result, Err(CaptureConfigError::InvalidOutputSize)), "output size {output_size:?}");
+        }
+    }
+
+    #[test]
+    fn source_rects_outside_the_target_are_rejected() {
+        for source_rect in [rect(1920.0, 0.0, 100.0, 100.0), rect(-100.0, -100.0, 100.0, 100.0), rect(10.0, 10.0, 0.0, 0.0)] {
+            let result = validate_settings(CapturePixelFormat::Bgra8888, 3, size(1920.0, 1080.0), Some(source_rect), None, &limits());
+            assert!(matches!(result, Err(CaptureConfigError::SourceRectOutOfBounds)), "source rect {source_rect:?}");
+        }
+    }
+
+    #[test]
+    fn source_rects_are_clamped_to_the_target() {
+        let target_size = size(1920.0, 1080.0);
+        let parts = |rect: Rect| (rect.origin.x, rect.origin.y, rect.size.width, rect.size.height);
+        let clamped = |source_rect: Rect, target_size: Size| clamp_source_rect(source_rect, target_size).map(parts);
+        assert_eq!(clamped(rect(100.0, 100.0, 200.0, 100.0), target_size), Some(parts(rect(100.0, 100.0, 200.0, 100.0))));
+        assert_eq!(clamped(rect(-10.0, -20.0, 110.0, 120.0), target_size), Some(parts(rect(0.0, 0.0, 100.0, 100.0))));
+        assert_eq!(clamped(rect(1820.0, 980.0, 200.0, 200.0), target_size), Some(parts(rect(1820.0, 980.0, 100.0, 100.0))));
+        assert_eq!(clamped(rect(1920.0, 0.0, 100.0, 100.0), target_size), None);
+        // Whatever clamps to a rect passes validation, and whatever doesn't is rejected, so `set_source_rect` and `with_source_rect` agree
+        for source_rect in [rect(-10.0, -20.0, 110.0, 120.0), rect(1920.0, 0.0, 100.0, 100.0), rect(10.0, 10.0, 0.0, 0.0)] {
+            let result = validate_settings(CapturePixelFormat::Bgra8888, 3, target_size, Some(source_rect), None, &limits());
+            assert_eq!(result.is_ok(), clamp_source_rect(source_rect, target_size).is_some(), "source rect {source_rect:?}");
+        }
+    }
+
+    #[test]
+    fn maximum_frame_rates_that_arent_positive_are_rejected() {
+        for maximum_fps in [0.0, -0.0, -30.0, f32::NAN, f32::NEG_INFINITY] {
+            let result = validate_settings(CapturePixelFormat::Bgra8888, 3, size(1920.0, 1080.0), None, Some(maximum_fps), &limits());
+            assert!(matches!(result, Err(CaptureConfigError::InvalidMaximumFps)), "maximum fps {maximum_fps}");
+        }
+        for maximum_fps in [0.01, 60.0, f32::INFINITY] {
+            assert!(validate_settings(CapturePixelFormat::Bgra8888, 3, size(1920.0, 1080.0), None, Some(maximum_fps), &limits()).is_ok(), "maximum fps {maximum_fps}");
+        }
+    }
+
+    const PURPOSES: [PixelFormatPurpose; 3] = [PixelFormatPurpose::CpuBitmap, PixelFormatPurpose::GpuTexture, PixelFormatPurpose::VideoEncode];
+
+    // Windows without R16G16B16A16 frame pools, where `Rgba16` isn't listed
+    const WINDOWS_PIXEL_FORMATS_WITHOUT_RGBA16: &[CapturePixelFormat] = &[CapturePixelFormat::Bgra8888, CapturePixelFormat::Argb2101010, CapturePixelFormat::RgbaF16];
+
+    fn backend_limits(pixel_formats: &'static [CapturePixelFormat]) -> ConfigLimits {
+        ConfigLimits { pixel_formats, ..limits() }
+    }
+
+    #[test]
+    fn every_format_a_purpose_recommends_passes_validation_on_some_backend() {
+        for purpose in PURPOSES {
+            for &pixel_format in purpose.preferred_pixel_formats() {
+                let passes = [MACOS_PIXEL_FORMATS, WINDOWS_PIXEL_FORMATS].into_iter()
+                    .any(|pixel_formats| validate_settings(pixel_format, 3, size(1920.0, 1080.0), None, None, &backend_limits(pixel_formats)).is_ok());
+                assert!(passes, "{purpose:?} recommends {pixel_format:?}, which no backend supports");
+            }
+        }
+    }
+
+    #[test]
+    fn the_preferred_format_for_every_purpose_passes_validation() {
+        for pixel_formats in [MACOS_PIXEL_FORMATS, WINDOWS_PIXEL_FORMATS, WINDOWS_PIXEL_FORMATS_WITHOUT_RGBA16] {
+            for purpose in PURPOSES {
+                let pixel_format = purpose.preferred_pixel_format(pixel_formats).expect("no preferred pixel format");
+                assert!(validate_settings(pixel_format, 3, size(1920.0, 1080.0), None, None, &backend_limits(pixel_formats)).is_ok(), "{purpose:?} with {pixel_formats:?}");
+            }
+        }
+        assert_eq!(PixelFormatPurpose::VideoEncode.preferred_pixel_format(MACOS_PIXEL_FORMATS), Some(CapturePixelFormat::V420));
+        assert_eq!(PixelFormatPurpose::VideoEncode.preferred_pixel_format(WINDOWS_PIXEL_FORMATS), Some(CapturePixelFormat::Bgra8888));
+        assert_eq!(PixelFormatPurpose::CpuBitmap.preferred_pixel_format(&[]), None);
+    }
+
+    fn content_rect(scaling_quality: ScalingQuality, content_size: (f64, f64), output_size: (f64, f64)) -> (f64, f64, f64, f64) {
+        let rect = scaling_quality.content_rect(Size { width: content_size.0, height: content_size.1 }, Size { width: output_size.0, height: output_size.1 });
+        (rect.origin.x, rect.origin.y, rect.size.width, rect.size.height)
+    }
+
+    #[test]
+    fn smooth_content_fills_the_output_keeping_its_aspect_ratio() {
+        assert_eq!(content_rect(ScalingQuality::Smooth, (100.0, 50.0), (300.0, 300.0)), (0.0, 75.0, 300.0, 150.0));
+        assert_eq!(content_rect(ScalingQuality::Smooth, (100.0, 50.0), (350.0, 200.0)), (0.0, 12.0, 350.0, 175.0));
+    }
+
+    #[test]
+    fn nearest_neighbor_content_is_scaled_by_whole_number_factors() {
+        assert_eq!(content_rect(ScalingQuality::NearestNeighbor, (100.0, 50.0), (350.0, 200.0)), (25.0, 25.0, 300.0, 150.0));
+        assert_eq!(content_rect(ScalingQuality::NearestNeighbor, (100.0, 50.0), (199.0, 99.0)), (49.0, 24.0, 100.0, 50.0));
+        // Content larger than the output is scaled down to fit
+        assert_eq!(content_rect(ScalingQuality::NearestNeighbor, (400.0, 200.0), (100.0, 100.0)), (0.0, 25.0, 100.0, 50.0));
+    }
+
+    #[test]
+    fn unscaled_content_is_centered_and_may_extend_past_the_output() {
+        assert_eq!(content_rect(ScalingQuality::None, (100.0, 50.0), (300.0, 300.0)), (100.0, 125.0, 100.0, 50.0));
+        assert_eq!(content_rect(ScalingQuality::None, (100.0, 50.0), (80.0, 80.0)), (-10.0, 15.0, 100.0, 50.0));
+    }
+
+    #[test]
+    fn empty_content_still_covers_a_pixel() {
+        assert_eq!(content_rect(ScalingQuality::None, (0.0, 0.0), (3.0, 3.0)), (1.0, 1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn placed_outputs_are_whole_pixels() {
+        let size = ScalingQuality::placed_output_size(Size { width: 1279.6, height: 0.4 });
+        assert_eq!((size.width, size.height), (1279.0, 1.0));
+    }
+
+    #[test]
+    fn a_target_going_away_ends_the_stream_once() {
+        let start_time = Instant::now();
+        let mut stream = StreamHarness::new(start_time, false);
+        stream.frame(start_time);
+        assert_eq!(stream.end(StreamEndReason::TargetGone, start_time), GateAction::Deliver { start: false });
+        // Frames already on their way are dropped, and stopping the stream afterwards doesn't end it again
+        assert_eq!(stream.frame(start_time), GateAction::Drop);
+        stream.gate.stop_requested.store(true, atomic::Ordering::Release);
+        assert_eq!(stream.end(StreamEndReason::Stopped, start_time), GateAction::Drop);
+        assert_eq!(stream.events, ["started", "end"]);
+        assert_eq!((stream.delivered.len(), stream.dropped()), (1, 1));
+        assert_eq!(stream.first_frame.lock().end_reason, Some(StreamEndReason::TargetGone));
+    }
+
+    #[test]
+    fn a_stop_requested_while_an_event_is_on_its_way_ends_the_stream_in_its_place() {
+        let start_time = Instant::now();
+        let mut stream = StreamHarness::new(start_time, false);
+        stream.frame(start_time);
+        stream.gate.stop_requested.store(true, atomic::Ordering::Release);
+        assert_eq!(stream.frame(start_time), GateAction::End);
+        assert_eq!(stream.end(StreamEndReason::Stopped, start_time), GateAction::Drop);
+        assert_eq!(stream.events, ["started", "end"]);
+        assert_eq!(stream.first_frame.lock().end_reason, Some(StreamEndReason::Stopped));
+    }
+
+    // The delivered size of each frame and whether it was scaled, as a window is resized
+    fn resize(content_sizes: &[(usize, usize)], source_rect: Option<Rect>, scaling_quality: ScalingQuality, max_dimensions: Size, output_size: Size) -> Vec<((usize, usize), bool)> {
+        content_sizes.iter()
+            .map(|&content_size| FrameLayout::new(content_size, source_rect, scaling_quality, Some(max_dimensions), output_size.fit_within(max_dimensions)))
+            .map(|layout| (layout.target_size, !layout.unscaled))
+            .collect()
+    }
+
+    #[test]
+    fn windows_resized_across_the_maximum_dimensions_are_scaled_only_past_them() {
+        // The window grows past the cap in one dimension, then both, then shrinks back under it
+        let content_sizes = [(1280, 720), (1920, 1080), (3840, 1200), (2560, 1440), (1920, 1080), (800, 600)];
+        let delivered = resize(&content_sizes, None, ScalingQuality::Smooth, size(1920.0, 1080.0), size(1920.0, 1080.0));
+        assert_eq!(delivered, [((1280, 720), false), ((1920, 1080), false), ((1920, 600), true), ((1920, 1080), true), ((1920, 1080), false), ((800, 600), false)]);
+    }
+
+    #[test]
+    fn cropped_windows_are_capped_after_cropping() {
+        // The crop is the left half of the window, so the window can be twice as wide as the cap before it's scaled
+        let left_half = Some(rect(0.0, 0.0, 0.5, 1.0));
+        let content_sizes = [(2000, 500), (4000, 1000), (4002, 1000), (1000, 250)];
+        let delivered = resize(&content_sizes, left_half, ScalingQuality::Smooth, size(2000.0, 1000.0), size(2000.0, 1000.0));
+        assert_eq!(delivered, [((1000, 500), true), ((2000, 1000), true), ((2000, 999), true), ((500, 250), true)]);
+        let layout = FrameLayout::new((4002, 1000), left_half, ScalingQuality::Smooth, Some(size(2000.0, 1000.0)), size(2000.0, 1000.0));
+        assert_eq!((layout.crop_rect.size.width, layout.crop_rect.size.height), (2001.0, 1000.0));
+    }
+
+    #[test]
+    fn placed_windows_keep_the_capped_output_size_across_the_maximum_dimensions() {
+        let content_sizes = [(640, 360), (2560, 1440), (640, 360)];
+        let delivered = resize(&content_sizes, None, ScalingQuality::NearestNeighbor, size(1280.0, 720.0), size(3840.0, 2160.0));
+        assert_eq!(delivered, [((1280, 720), true), ((1280, 720), true), ((1280, 720), true)]);
+        let content_rect = |content_size| {
+            let layout = FrameLayout::new(content_size, None, ScalingQuality::NearestNeighbor, Some(size(1280.0, 720.0)), size(1280.0, 720.0));
+            (layout.content_rect.origin.x, layout.content_rect.origin.y, layout.content_rect.size.width, layout.content_rect.size.height)
+        };
+        // Smaller content is scaled up by a whole number factor, larger content down to fit
+        assert_eq!(content_rect((640, 360)), (0.0, 0.0, 1280.0, 720.0));
+        assert_eq!(content_rect((2560, 1440)), (0.0, 0.0, 1280.0, 720.0));
+        assert_eq!(content_rect((500, 300)), (140.0, 60.0, 1000.0, 600.0));
+    }
+
+    // Wait for the harness to deliver more than the given number of frames
+    fn await_delivered(stream: &Mutex<StreamHarness>, count: usize) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            if stream.lock().delivered.len() > count {
+                return;
+            }
+            assert!(Instant::now() < deadline, "Expected frames to be delivered");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn paused_streams_drop_frames_until_resumed() {
+        let stream = Arc::new(Mutex::new(StreamHarness::new(Instant::now(), false)));
+        let (first_frame, paused) = {
+            let stream = stream.lock();
+            (stream.first_frame.clone(), stream.gate.paused.clone())
+        };
+        // Frames keep arriving from the platform's thread throughout
+        let producing = Arc::new(AtomicBool::new(true));
+        let producer = {
+            let (stream, producing) = (stream.clone(), producing.clone());
+            std::thread::spawn(move || {
+                while producing.load(atomic::Ordering::Acquire) {
+                    stream.lock().frame(Instant::now());
+                    std::thread::sleep(Duration::from_millis(2));
+                }
+            })
+        };
+
+        await_delivered(&stream, 0);
+        set_paused(&first_frame, &paused, true).unwrap();
+        let paused_at = stream.lock().delivered.len();
+        let dropped_at = stream.lock().dropped();
+        std::thread::sleep(Duration::from_millis(100));
+        {
+            let stream = stream.lock();
+            assert_eq!(stream.delivered.len(), paused_at, "Expected no frames while paused");
+            assert!(stream.dropped() > dropped_at, "Expected frames to be dropped while paused");
+        }
+
+        set_paused(&first_frame, &paused, false).unwrap();
+        await_delivered(&stream, paused_at);
+        set_paused(&first_frame, &paused, true).unwrap();
+
+        // The end of the stream is still delivered while paused, after which the stream can't be resumed
+        let now = Instant::now();
+        assert_eq!(stream.lock().end(StreamEndReason::TargetGone, now), GateAction::Deliver { start: false });
+        producing.store(false, atomic::Ordering::Release);
+        producer.join().unwrap();
+        assert!(set_paused(&first_frame, &paused, false).is_err());
+        assert_eq!(stream.lock().events, ["started", "end"]);
+    }
+
+    #[test]
+    fn throttled_and_paused_frames_are_counted_as_dropped() {
+        let start_time = Instant::now();
+        let mut stream = StreamHarness::new(start_time, false);
+        // Frames arrive at 60fps for two seconds, and are throttled to 2fps by the platform before the callback sees them
+        let frame_interval = Duration::from_micros(16_667);
+        let mut limiter = FrameIntervalLimiter::new(Some(Duration::from_millis(500)), Duration::from_millis(20));
+        let mut platform_dropped = 0;
+        let mut callback_saw = 0;
+        for frame in 0..120u32 {
+            stream.gate.paused.store((50..80).contains(&frame), atomic::Ordering::Release);
+            let now = start_time + frame_interval * frame;
+            if !limiter.should_deliver(now) {
+                platform_dropped += 1;
+                continue;
+            }
+            if let GateAction::Deliver { .. } = stream.frame(now) {
+                callback_saw += 1;
+            }
+        }
+        // Frames up to 20ms early are let through, so every 29th frame passes the throttle - and the one while paused is dropped
+        let frames_delivered = stream.frame_counts.delivered.load(atomic::Ordering::Relaxed);
+        let frames_dropped = stream.dropped() + platform_dropped;
+        assert!(frames_dropped > 0);
+        assert_eq!(frames_delivered, callback_saw);
+        assert_eq!((frames_delivered, stream.dropped(), frames_delivered + frames_dropped), (4, 1, 120));
+        assert_eq!(stream.frame_counts.last_frame_interval(), Some(frame_interval * 29));
+    }
+
+    #[test]
+    fn unthrottled_frames_are_all_delivered() {
+        let start_time = Instant::now();
+        let mut limiter = FrameIntervalLimiter::new(None, Duration::from_millis(20));
+        assert!((0..10).all(|_| limiter.should_deliver(start_time)));
+        // Changing the interval measures it from the last delivered frame
+        limiter.set_interval(Some(Duration::from_millis(100)));
+        assert!(!limiter.should_deliver(start_time + Duration::from_millis(50)));
+        assert!(limiter.should_deliver(start_time + Duration::from_millis(80)));
+    }
+}
@@ -0,0 +1,100 @@
+use std::sync::{atomic::{self, AtomicBool}, Arc};
+
+use parking_lot::Mutex;
+
+use crate::capture_stream::{CaptureAccessToken, CaptureClock, CaptureConfig, CaptureStream, StreamCreateError, StreamError, StreamEvent, StreamStopError};
+
+/// A group of capture streams sharing a clock and a callback, E.G. for recording every display at once
+/// 
+/// Every stream in the group uses the group's `CaptureClock`, so the `origin_time()` of each video frame is measured from the same instant,
+/// and the streams are released together to begin delivering frames as close to simultaneously as the platforms allow - see `CaptureClock`
+/// for the synchronization bounds. Events from all of the streams are delivered to one callback, along with the index of the stream's
+/// configuration. Each stream delivers `StreamEvent::End` exactly once, as an individual stream does.
+/// 
+/// The callback is locked while it runs, so events from different streams are delivered one at a time. Stop the group from outside the
+/// callback - stopping another stream of the group from inside it would wait on the callback itself.
+pub struct CaptureStreamGroup {
+    clock: CaptureClock,
+    streams: Vec<CaptureStream>,
+}
+
+impl CaptureStreamGroup {
+    /// Create a stream for each of the given configurations, on a new shared clock, and start them together
+    /// 
+    /// Any shared clock already set on the configurations is replaced with the group's. If a stream fails to be created, the streams
+    /// created before it are stopped without delivering any more events, and the error is returned.
+    pub fn new(token: CaptureAccessToken, configs: impl IntoIterator<Item = CaptureConfig>, callback: impl FnMut(usize, Result<StreamEvent, StreamError>) + Send + 'static) -> Result<Self, StreamCreateError> {
+        let clock = CaptureClock::new();
+        let callback = Arc::new(Mutex::new(callback));
+        // Set if the group fails to be created, so that the streams created so far don't deliver their ends
+        let abandoned = Arc::new(AtomicBool::new(false));
+        let mut streams = Vec::new();
+        for (stream_index, config) in configs.into_iter().enumerate() {
+            let stream_callback = callback.clone();
+            let stream_abandoned = abandoned.clone();
+            let result = CaptureStream::new(token, config.with_shared_clock(&clock), move |event| {
+                if stream_abandoned.load(atomic::Ordering::Acquire) {
+                    return;
+                }
+                let mut callback = stream_callback.lock();
+                (*callback)(stream_index, event);
+            });
+            match result {
+                Ok(stream) => streams.push(stream),
+                Err(error) => {
+                    abandoned.store(true, atomic::Ordering::Release);
+                    return Err(error);
+                }
+            }
+        }
+        CaptureClock::start_all(&streams);
+        Ok(Self {
+            clock,
+            streams,
+        })
+    }
+
+    /// Get the clock shared by the streams of the group
+    pub fn clock(&self) -> CaptureClock {
+        self.clock
+    }
+
+    /// Get the streams of the group, in the order of their configurations
+    pub fn streams(&self) -> &[CaptureStream] {
+        &self.streams
+    }
+
+    /// Get the streams of the group mutably, E.G. to pause one of them
+    pub fn streams_mut(&mut self) -> &mut [CaptureStream] {
+        &mut self.streams
+    }
+
+    /// Stop every stream of the group - see `CaptureStream::stop()`
+    /// 
+    /// All of the streams are stopped even if some fail to stop, and the first error is returned.
+    pub fn stop(&mut self) -> Result<(), StreamStopError> {
+        let mut result = Ok(());
+        for stream in self.streams.iter_mut() {
+            let stream_result = stream.stop();
+            if result.is_ok() {
+                result = stream_result;
+            }
+        }
+        result
+    }
+
+    /// Stop every stream of the group, and wait for the OS to tear each of them down - see `CaptureStream::close()`
+    /// 
+    /// All of the streams are closed even if some fail to close, and the first error is returned.
+    pub async fn close(mut self) -> Result<(), StreamStopError> {
+        // Stop all of the streams before waiting on any of them, so they end together
+        let mut result = self.stop();
+        for stream in self.streams {
+            let stream_result = stream.close().await;
+            if result.is_ok() {
+                result = stream_result;
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,194 @@
+use std::{fmt::Debug, panic::{catch_unwind, AssertUnwindSafe}, sync::Arc};
+
+use parking_lot::Mutex;
+
+use crate::capture_stream::{handle_callback_panic, CapturePixelFormat};
+
+/// A plane of a frame's pixels, mapped for writing on the CPU
+#[non_exhaustive]
+pub struct CompositorPlane<'a> {
+    /// The pixel data of the plane, `bytes_per_row * height` bytes long
+    pub data: &'a mut [u8],
+    /// The width of the plane in pixels
+    pub width: usize,
+    /// The height of the plane in pixels
+    pub height: usize,
+    /// The stride of the plane in bytes, which may be larger than the width times the pixel size
+    pub bytes_per_row: usize,
+}
+
+/// A frame about to be delivered, in the form the capture path holds it
+///
+/// Drawing into the target modifies the frame itself - the overlay is visible to every consumer of the frame, including bitmap and texture copies
+#[non_exhaustive]
+pub enum CompositorTarget<'a> {
+    /// The frame's pixels on the CPU - one plane for RGBA formats, or a luminance and a chroma plane for YCbCr formats
+    Cpu {
+        /// The pixel format of the frame
+        pixel_format: CapturePixelFormat,
+        /// The planes of the frame
+        planes: Vec<CompositorPlane<'a>>,
+    },
+    /// The frame's texture, and the immediate context of the stream's device
+    ///
+    /// When the frame isn't scaled the texture belongs to the capture frame pool, and may not be bindable as a render target -
+    /// check its description, or draw with copies.
+    #[cfg(target_os = "windows")]
+    D3D11 {
+        /// The pixel format of the frame
+        pixel_format: CapturePixelFormat,
+        /// The frame's texture
+        texture: &'a windows::Win32::Graphics::Direct3D11::ID3D11Texture2D,
+        /// The immediate context of the stream's device, which is flushed after compositing
+        context: &'a windows::Win32::Graphics::Direct3D11::ID3D11DeviceContext,
+    },
+    /// The frame's textures, and a command buffer on the stream's metal device
+    ///
+    /// The command buffer is committed after compositing, and the frame is delivered once it completes
+    #[cfg(all(target_os = "macos", feature = "metal"))]
+    Metal {
+        /// The pixel format of the frame
+        pixel_format: CapturePixelFormat,
+        /// The frame's textures - one for RGBA formats, or a luminance and a chroma texture for YCbCr formats
+        textures: Vec<metal::Texture>,
+        /// The command buffer to encode drawing into
+        command_buffer: &'a metal::CommandBufferRef,
+    },
+}
+
+/// Draws into frames before they're delivered, such as for picture-in-picture overlays or watermarks
+///
+/// The compositor runs on the stream's capture thread, drawing directly into the captured frame, so it adds no extra copies of the frame -
+/// but it does add its own time to the latency of every frame.
+/// It's called at most once for each frame delivered to the stream callback, after frame rate limiting and scaling,
+/// and never for frames that are dropped. Which target it receives depends on the platform:
+///
+/// - On Windows, frames are always composited on the GPU, with `CompositorTarget::D3D11`
+/// - On MacOS, frames are composited on the GPU with `CompositorTarget::Metal` when the `metal` feature is enabled and `prefers_gpu()` returns true,
+///   and otherwise on the CPU with `CompositorTarget::Cpu`
+///
+/// Panics in the compositor are caught and reported to the handler set with `CaptureStream::set_callback_panic_handler`,
+/// after which the compositor isn't called again and frames are delivered without it.
+pub trait FrameCompositor: Send + 'static {
+    /// Whether to composite on the GPU when the capture path supports it - by default, true
+    fn prefers_gpu(&self) -> bool {
+        true
+    }
+
+    /// Draw into a frame before it's delivered
+    fn composite(&mut self, target: CompositorTarget<'_>);
+}
+
+// The compositor shared by a config and the streams created from it - it's dropped if it panics
+#[derive(Clone)]
+pub(crate) struct SharedFrameCompositor(Arc<Mutex<Option<Box<dyn FrameCompositor>>>>);
+
+impl Debug for SharedFrameCompositor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SharedFrameCompositor")
+    }
+}
+
+impl SharedFrameCompositor {
+    pub(crate) fn new(compositor: Box<dyn FrameCompositor>) -> Self {
+        Self(Arc::new(Mutex::new(Some(compositor))))
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.0.lock().is_some()
+    }
+
+    pub(crate) fn prefers_gpu(&self) -> bool {
+        self.0.lock().as_ref().is_some_and(|compositor| compositor.prefers_gpu())
+    }
+
+    // Run the compositor on a frame, catching panics - returns false if there was no compositor to run
+    pub(crate) fn composite(&self, target: CompositorTarget<'_>) -> bool {
+        let mut compositor = self.0.lock();
+        let Some(active_compositor) = compositor.as_mut() else {
+            return false;
+        };
+        if let Err(payload) = catch_unwind(AssertUnwindSafe(|| active_compositor.composite(target))) {
+            handle_callback_panic(payload.as_ref());
+            *compositor = None;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+
+    use parking_lot::Mutex;
+
+    use crate::capture_stream::{CaptureStream, CapturePixelFormat};
+
+    use super::{CompositorPlane, CompositorTarget, FrameCompositor, SharedFrameCompositor};
+
+    // Fills the frame with its call count, panicking on the call given
+    struct CountingCompositor {
+        calls: Arc<AtomicUsize>,
+        panic_on: Option<usize>,
+    }
+
+    impl FrameCompositor for CountingCompositor {
+        fn composite(&mut self, target: CompositorTarget<'_>) {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            if Some(call) == self.panic_on {
+                panic!("compositor panicked on call {call}");
+            }
+            // The GPU targets only exist on Windows and MacOS
+            #[allow(irrefutable_let_patterns)]
+            if let CompositorTarget::Cpu { planes, .. } = target {
+                for plane in planes {
+                    plane.data.fill(call as u8);
+                }
+            }
+        }
+    }
+
+    fn composite_frame(compositor: &SharedFrameCompositor) -> (bool, Vec<u8>) {
+        let mut data = vec![0u8; 4 * 2 * 2];
+        let composited = compositor.composite(CompositorTarget::Cpu {
+            pixel_format: CapturePixelFormat::Bgra8888,
+            planes: vec![CompositorPlane { data: &mut data, width: 2, height: 2, bytes_per_row: 8 }],
+        });
+        (composited, data)
+    }
+
+    #[test]
+    fn compositors_draw_into_each_frame_once() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let compositor = SharedFrameCompositor::new(Box::new(CountingCompositor { calls: calls.clone(), panic_on: None }));
+        for frame in 1..=3 {
+            let (composited, data) = composite_frame(&compositor);
+            assert!(composited);
+            assert_eq!(calls.load(Ordering::SeqCst), frame);
+            assert!(data.iter().all(|byte| *byte as usize == frame));
+        }
+        assert!(compositor.is_active());
+        assert!(compositor.prefers_gpu());
+    }
+
+    #[test]
+    fn compositors_which_panic_are_reported_and_not_called_again() {
+        static PANICS: Mutex<Vec<String>> = parking_lot::const_mutex(Vec::new());
+        CaptureStream::set_callback_panic_handler(|panic| PANICS.lock().push(panic.message().unwrap_or_default().to_string()));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let compositor = SharedFrameCompositor::new(Box::new(CountingCompositor { calls: calls.clone(), panic_on: Some(2) }));
+        let shared_with_another_stream = compositor.clone();
+        assert!(composite_frame(&compositor).0);
+        // The panic is caught, and the frame is left as it was when the compositor panicked
+        let (composited, data) = composite_frame(&compositor);
+        assert!(composited);
+        assert!(data.iter().all(|byte| *byte == 0));
+        assert_eq!(PANICS.lock().iter().filter(|message| *message == "compositor panicked on call 2").count(), 1);
+        // After which the compositor is dropped for every stream sharing it
+        assert!(!shared_with_another_stream.is_active());
+        assert!(!shared_with_another_stream.prefers_gpu());
+        assert!(!composite_frame(&compositor).0);
+        assert!(!composite_frame(&shared_with_another_stream).0);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
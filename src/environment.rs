@@ -0,0 +1,128 @@
+use std::fmt::Display;
+
+use crate::capture_stream::CaptureStream;
+use crate::platform::platform_impl::probe_environment;
+
+/// Whether an optional platform API is available at runtime
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PlatformApiAvailability {
+    /// The name of the API
+    pub name: &'static str,
+    /// Whether the API is available on this system
+    pub available: bool,
+}
+
+/// A summary of the environment crabgrab is running in, intended to be included in bug reports
+///
+/// Use `environment_report()` to create one, and its `Display` impl to format it as a single line
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct EnvironmentReport {
+    /// The version of crabgrab
+    pub crate_version: &'static str,
+    /// The enabled crate features
+    pub features: Vec<&'static str>,
+    /// The operating system (see `std::env::consts::OS`)
+    pub os: &'static str,
+    /// The operating system version, if it could be determined
+    pub os_version: Option<String>,
+    /// The capture backend that would be automatically selected for display capture
+    pub capture_backend: Option<&'static str>,
+    /// Whether capture access has already been granted
+    pub capture_access: bool,
+    /// Whether borderless capture access has already been granted
+    pub borderless_capture_access: bool,
+    /// A description of the default GPU adapter, if it could be determined
+    pub gpu_adapter: Option<String>,
+    /// The availability of each optional platform API crabgrab checks for at runtime
+    pub platform_apis: Vec<PlatformApiAvailability>,
+}
+
+pub(crate) struct PlatformEnvironment {
+    pub os_version: Option<String>,
+    pub capture_backend: Option<&'static str>,
+    pub gpu_adapter: Option<String>,
+    pub platform_apis: Vec<PlatformApiAvailability>,
+}
+
+// Every feature in the [features] table of Cargo.toml, in the same order, with whether it's enabled
+const FEATURES: &[(&str, bool)] = &[
+    ("iosurface", cfg!(feature = "iosurface")),
+    ("metal", cfg!(feature = "metal")),
+    ("dxgi", cfg!(feature = "dxgi")),
+    ("dx11", cfg!(feature = "dx11")),
+    ("bitmap", cfg!(feature = "bitmap")),
+    ("screenshot", cfg!(feature = "screenshot")),
+    ("png", cfg!(feature = "png")),
+    ("sink", cfg!(feature = "sink")),
+    ("content_picker", cfg!(feature = "content_picker")),
+    ("wgpu", cfg!(feature = "wgpu")),
+    ("diagnostic", cfg!(feature = "diagnostic")),
+    ("stub-unsupported", cfg!(feature = "stub-unsupported")),
+    ("serde", cfg!(feature = "serde")),
+];
+
+fn enabled_features() -> Vec<&'static str> {
+    FEATURES.iter()
+        .filter_map(|(name, enabled)| enabled.then_some(*name))
+        .collect()
+}
+
+/// Gather a report on the environment crabgrab is running in
+///
+/// This only performs cheap checks, and never prompts the user for permission - access is checked with `CaptureStream::test_access`
+pub fn environment_report() -> EnvironmentReport {
+    let platform_environment = probe_environment();
+    EnvironmentReport {
+        crate_version: env!("CARGO_PKG_VERSION"),
+        features: enabled_features(),
+        os: std::env::consts::OS,
+        os_version: platform_environment.os_version,
+        capture_backend: platform_environment.capture_backend,
+        capture_access: CaptureStream::test_access(false).is_some(),
+        borderless_capture_access: CaptureStream::test_access(true).is_some(),
+        gpu_adapter: platform_environment.gpu_adapter,
+        platform_apis: platform_environment.platform_apis,
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+impl Display for EnvironmentReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("crabgrab {} [{}]", self.crate_version, self.features.join(", ")))?;
+        f.write_fmt(format_args!(" | os: {} {}", self.os, self.os_version.as_deref().unwrap_or("(unknown version)")))?;
+        f.write_fmt(format_args!(" | backend: {}", self.capture_backend.unwrap_or("none")))?;
+        f.write_fmt(format_args!(" | access: {}, borderless: {}", yes_no(self.capture_access), yes_no(self.borderless_capture_access)))?;
+        f.write_fmt(format_args!(" | gpu: {}", self.gpu_adapter.as_deref().unwrap_or("unknown")))?;
+        f.write_str(" | apis:")?;
+        if self.platform_apis.is_empty() {
+            f.write_str(" none")?;
+        }
+        for api in self.platform_apis.iter() {
+            f.write_fmt(format_args!(" {}={}", api.name, yes_no(api.available)))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FEATURES;
+
+    #[test]
+    fn every_cargo_feature_is_reported() {
+        let manifest = include_str!("../Cargo.toml");
+        let features_table = manifest.split("\n[features]\n").nth(1).expect("Cargo.toml has a [features] table");
+        let cargo_features: Vec<&str> = features_table.lines()
+            .take_while(|line| !line.starts_with('['))
+            .filter_map(|line| line.split_once('=').map(|(name, _)| name.trim()))
+            .collect();
+        let reported_features: Vec<&str> = FEATURES.iter().map(|(name, _)| *name).collect();
+        assert_eq!(reported_features, cargo_features);
+    }
+}
@@ -0,0 +1,329 @@
+// Code allocation policy:
+// * Each error type owns a block of 100 codes, starting at 1000 - see the comments in `ErrorKind`
+// * New variants are given the next unused code in their type's block, and new error types take the next unused block
+// * Codes are never reused or renumbered, even if the variant they belong to is removed
+// Since the codes are the discriminants of `ErrorKind`, the compiler rejects any duplicates.
+
+/// The stable kind of an error, identifying the error type and variant it came from
+///
+/// Every public error type has a `kind()` method returning its `ErrorKind`, and a `code()` method returning its numeric code.
+/// Kinds and codes are stable across versions of the crate, so they're suitable for aggregating errors in telemetry,
+/// while the `Display` strings of errors may change at any time.
+///
+/// The discriminant of each kind is its numeric code - see `ErrorKind::code()`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+#[repr(u32)]
+pub enum ErrorKind {
+    // CapturableContentError: 1000-1099
+    /// `CapturableContentError::Other`
+    CapturableContentOther = 1000,
+    /// `CapturableContentError::UnsupportedPlatform`
+    CapturableContentUnsupportedPlatform = 1001,
+    /// `CapturableContentError::Platform`
+    CapturableContentPlatform = 1002,
+    /// `CapturableContentError::Timeout`
+    CapturableContentTimeout = 1003,
+
+    // CaptureConfigError: 1100-1199
+    /// `CaptureConfigError::UnsupportedPixelFormat`
+    CaptureConfigUnsupportedPixelFormat = 1100,
+    /// `CaptureConfigError::InvalidBufferCount`
+    CaptureConfigInvalidBufferCount = 1101,
+    /// `CaptureConfigError::InvalidOutputSize`
+    CaptureConfigInvalidOutputSize = 1102,
+    /// `CaptureConfigError::SourceRectOutOfBounds`
+    CaptureConfigSourceRectOutOfBounds = 1103,
+    /// `CaptureConfigError::InvalidMaximumFps`
+    CaptureConfigInvalidMaximumFps = 1104,
+
+    // StreamCreateError: 1200-1299
+    /// `StreamCreateError::Other`
+    StreamCreateOther = 1200,
+    /// `StreamCreateError::UnsupportedPixelFormat`
+    StreamCreateUnsupportedPixelFormat = 1201,
+    /// `StreamCreateError::UnauthorizedFeature`
+    StreamCreateUnauthorizedFeature = 1202,
+    /// `StreamCreateError::UnsupportedPlatform`
+    StreamCreateUnsupportedPlatform = 1203,
+    /// `StreamCreateError::Platform`
+    StreamCreatePlatform = 1204,
+    /// `StreamCreateError::InvalidConfig`
+    StreamCreateInvalidConfig = 1205,
+
+    // StreamError: 1300-1399
+    /// `StreamError::Other`
+    StreamOther = 1300,
+    /// `StreamError::Platform`
+    StreamPlatform = 1301,
+    /// `StreamError::Timeout`
+    StreamTimeout = 1302,
+    /// `StreamError::EmptyFrame`
+    StreamEmptyFrame = 1303,
+
+    // StreamStopError: 1400-1499
+    /// `StreamStopError::Other`
+    StreamStopOther = 1400,
+    /// `StreamStopError::AlreadyStopped`
+    StreamStopAlreadyStopped = 1401,
+
+    // VideoFrameBitmapError: 1500-1599
+    /// `VideoFrameBitmapError::Other`
+    VideoFrameBitmapOther = 1500,
+
+    // ScreenshotError: 1600-1699
+    /// `ScreenshotError::Other`
+    ScreenshotOther = 1600,
+    /// `ScreenshotError::InsufficientAccess`
+    ScreenshotInsufficientAccess = 1601,
+    /// `ScreenshotError::PermissionDenied`
+    ScreenshotPermissionDenied = 1602,
+    /// `ScreenshotError::TargetGone`
+    ScreenshotTargetGone = 1603,
+    /// `ScreenshotError::Timeout`
+    ScreenshotTimeout = 1604,
+
+    // OpenSettingsError: 1700-1799
+    /// `OpenSettingsError::Other`
+    OpenSettingsOther = 1700,
+    /// `OpenSettingsError::Unavailable`
+    OpenSettingsUnavailable = 1701,
+    /// `OpenSettingsError::Platform`
+    OpenSettingsPlatform = 1702,
+
+    // StreamUpdateError: 1800-1899
+    /// `StreamUpdateError::Other`
+    StreamUpdateOther = 1800,
+    /// `StreamUpdateError::Unsupported`
+    StreamUpdateUnsupported = 1801,
+    /// `StreamUpdateError::AlreadyStopped`
+    StreamUpdateAlreadyStopped = 1802,
+    /// `StreamUpdateError::Platform`
+    StreamUpdatePlatform = 1803,
+    /// `StreamUpdateError::RequiresRestart`
+    StreamUpdateRequiresRestart = 1804,
+    /// `StreamUpdateError::InvalidConfig`
+    StreamUpdateInvalidConfig = 1805,
+
+    // VideoFrameMapError: 1900-1999
+    /// `VideoFrameMapError::Other`
+    VideoFrameMapOther = 1900,
+    /// `VideoFrameMapError::UnsupportedPixelFormat`
+    VideoFrameMapUnsupportedPixelFormat = 1901,
+
+    // SinkError: 2000-2099
+    /// `SinkError::Other`
+    SinkOther = 2000,
+    /// `SinkError::Frame`
+    SinkFrame = 2001,
+    /// `SinkError::Io`
+    SinkIo = 2002,
+
+    // SharableContentPickerError: 2100-2199
+    /// `SharableContentPickerError::Other`
+    SharableContentPickerOther = 2100,
+    /// `SharableContentPickerError::EmptyConfig`
+    SharableContentPickerEmptyConfig = 2101,
+    /// `SharableContentPickerError::ConfigFilteringUnsupported`
+    SharableContentPickerConfigFilteringUnsupported = 2102,
+    /// `SharableContentPickerError::AmbiguousPick`
+    SharableContentPickerAmbiguousPick = 2103,
+
+    // AudioBufferError: 2200-2299
+    /// `AudioBufferError::UnsupportedFormat`
+    AudioBufferUnsupportedFormat = 2200,
+    /// `AudioBufferError::InvalidChannel`
+    AudioBufferInvalidChannel = 2201,
+    /// `AudioBufferError::Other`
+    AudioBufferOther = 2202,
+
+    // WgpuVideoFrameError: 2300-2399
+    /// `WgpuVideoFrameError::NoBackendTexture`
+    WgpuVideoFrameNoBackendTexture = 2300,
+    /// `WgpuVideoFrameError::InvalidVideoPlaneTexture`
+    WgpuVideoFrameInvalidVideoPlaneTexture = 2301,
+    /// `WgpuVideoFrameError::NoWgpuDevice`
+    WgpuVideoFrameNoWgpuDevice = 2302,
+    /// `WgpuVideoFrameError::DeviceLost`
+    WgpuVideoFrameDeviceLost = 2303,
+    /// `WgpuVideoFrameError::DestinationMismatch`
+    WgpuVideoFrameDestinationMismatch = 2304,
+    /// `WgpuVideoFrameError::Other`
+    WgpuVideoFrameOther = 2305,
+    /// `WgpuVideoFrameError::BackendMismatch`
+    WgpuVideoFrameBackendMismatch = 2306,
+
+    // WindowsDx11VideoFrameError: 2400-2499
+    /// `WindowsDx11VideoFrameError::Other`
+    WindowsDx11VideoFrameOther = 2400,
+
+    // WindowsDxgiVideoFrameError: 2500-2599
+    /// `WindowsDxgiVideoFrameError::Other`
+    WindowsDxgiVideoFrameOther = 2500,
+
+    // WindowsDxgiCaptureStreamError: 2600-2699
+    /// `WindowsDxgiCaptureStreamError::NoAdapter`
+    WindowsDxgiCaptureStreamNoAdapter = 2600,
+
+    // MacosVideoFrameError: 2700-2799
+    /// `MacosVideoFrameError::NoIoSurface`
+    MacosVideoFrameNoIoSurface = 2700,
+    /// `MacosVideoFrameError::NoImageBuffer`
+    MacosVideoFrameNoImageBuffer = 2701,
+    /// `MacosVideoFrameError::InvalidVideoPlaneTexture`
+    MacosVideoFrameInvalidVideoPlaneTexture = 2702,
+    /// `MacosVideoFrameError::Other`
+    MacosVideoFrameOther = 2703,
+
+    // IoSurfaceLockError: 2800-2899
+    /// `IoSurfaceLockError::CannotLock`
+    IoSurfaceLockCannotLock = 2800,
+    /// `IoSurfaceLockError::Other`
+    IoSurfaceLockOther = 2801,
+
+    // GetIoSurfaceError: 2900-2999
+    /// `GetIoSurfaceError::NoImageBuffer`
+    GetIoSurfaceNoImageBuffer = 2900,
+    /// `GetIoSurfaceError::NoIoSurface`
+    GetIoSurfaceNoIoSurface = 2901,
+}
+
+impl ErrorKind {
+    /// Get the stable numeric code for this kind of error
+    pub fn code(&self) -> u32 {
+        *self as u32
+    }
+}
+
+/// An error code reported by an OS API
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PlatformErrorCode {
+    /// A Windows `HRESULT`
+    HResult(i32),
+    /// A MacOS `NSError`, identified by its domain and code
+    NSError {
+        domain: String,
+        code: isize,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use super::*;
+
+    // Every kind, in declaration order - `error_type` fails to compile when a kind is added, as a reminder to list it here too
+    const ALL_KINDS: &[ErrorKind] = &[
+        ErrorKind::CapturableContentOther,
+        ErrorKind::CapturableContentUnsupportedPlatform,
+        ErrorKind::CapturableContentPlatform,
+        ErrorKind::CapturableContentTimeout,
+        ErrorKind::CaptureConfigUnsupportedPixelFormat,
+        ErrorKind::CaptureConfigInvalidBufferCount,
+        ErrorKind::CaptureConfigInvalidOutputSize,
+        ErrorKind::CaptureConfigSourceRectOutOfBounds,
+        ErrorKind::CaptureConfigInvalidMaximumFps,
+        ErrorKind::StreamCreateOther,
+        ErrorKind::StreamCreateUnsupportedPixelFormat,
+        ErrorKind::StreamCreateUnauthorizedFeature,
+        ErrorKind::StreamCreateUnsupportedPlatform,
+        ErrorKind::StreamCreatePlatform,
+        ErrorKind::StreamCreateInvalidConfig,
+        ErrorKind::StreamOther,
+        ErrorKind::StreamPlatform,
+        ErrorKind::StreamTimeout,
+        ErrorKind::StreamEmptyFrame,
+        ErrorKind::StreamStopOther,
+        ErrorKind::StreamStopAlreadyStopped,
+        ErrorKind::VideoFrameBitmapOther,
+        ErrorKind::ScreenshotOther,
+        ErrorKind::ScreenshotInsufficientAccess,
+        ErrorKind::ScreenshotPermissionDenied,
+        ErrorKind::ScreenshotTargetGone,
+        ErrorKind::ScreenshotTimeout,
+        ErrorKind::OpenSettingsOther,
+        ErrorKind::OpenSettingsUnavailable,
+        ErrorKind::OpenSettingsPlatform,
+        ErrorKind::StreamUpdateOther,
+        ErrorKind::StreamUpdateUnsupported,
+        ErrorKind::StreamUpdateAlreadyStopped,
+        ErrorKind::StreamUpdatePlatform,
+        ErrorKind::StreamUpdateRequiresRestart,
+        ErrorKind::StreamUpdateInvalidConfig,
+        ErrorKind::VideoFrameMapOther,
+        ErrorKind::VideoFrameMapUnsupportedPixelFormat,
+        ErrorKind::SinkOther,
+        ErrorKind::SinkFrame,
+        ErrorKind::SinkIo,
+        ErrorKind::SharableContentPickerOther,
+        ErrorKind::SharableContentPickerEmptyConfig,
+        ErrorKind::SharableContentPickerConfigFilteringUnsupported,
+        ErrorKind::SharableContentPickerAmbiguousPick,
+        ErrorKind::AudioBufferUnsupportedFormat,
+        ErrorKind::AudioBufferInvalidChannel,
+        ErrorKind::AudioBufferOther,
+        ErrorKind::WgpuVideoFrameNoBackendTexture,
+        ErrorKind::WgpuVideoFrameInvalidVideoPlaneTexture,
+        ErrorKind::WgpuVideoFrameNoWgpuDevice,
+        ErrorKind::WgpuVideoFrameDeviceLost,
+        ErrorKind::WgpuVideoFrameDestinationMismatch,
+        ErrorKind::WgpuVideoFrameOther,
+        ErrorKind::WgpuVideoFrameBackendMismatch,
+        ErrorKind::WindowsDx11VideoFrameOther,
+        ErrorKind::WindowsDxgiVideoFrameOther,
+        ErrorKind::WindowsDxgiCaptureStreamNoAdapter,
+        ErrorKind::MacosVideoFrameNoIoSurface,
+        ErrorKind::MacosVideoFrameNoImageBuffer,
+        ErrorKind::MacosVideoFrameInvalidVideoPlaneTexture,
+        ErrorKind::MacosVideoFrameOther,
+        ErrorKind::IoSurfaceLockCannotLock,
+        ErrorKind::IoSurfaceLockOther,
+        ErrorKind::GetIoSurfaceNoImageBuffer,
+        ErrorKind::GetIoSurfaceNoIoSurface,
+    ];
+
+    fn error_type(kind: ErrorKind) -> &'static str {
+        match kind {
+            ErrorKind::CapturableContentOther | ErrorKind::CapturableContentUnsupportedPlatform | ErrorKind::CapturableContentPlatform | ErrorKind::CapturableContentTimeout => "CapturableContentError",
+            ErrorKind::CaptureConfigUnsupportedPixelFormat | ErrorKind::CaptureConfigInvalidBufferCount | ErrorKind::CaptureConfigInvalidOutputSize | ErrorKind::CaptureConfigSourceRectOutOfBounds | ErrorKind::CaptureConfigInvalidMaximumFps => "CaptureConfigError",
+            ErrorKind::StreamCreateOther | ErrorKind::StreamCreateUnsupportedPixelFormat | ErrorKind::StreamCreateUnauthorizedFeature | ErrorKind::StreamCreateUnsupportedPlatform | ErrorKind::StreamCreatePlatform | ErrorKind::StreamCreateInvalidConfig => "StreamCreateError",
+            ErrorKind::StreamOther | ErrorKind::StreamPlatform | ErrorKind::StreamTimeout | ErrorKind::StreamEmptyFrame => "StreamError",
+            ErrorKind::StreamStopOther | ErrorKind::StreamStopAlreadyStopped => "StreamStopError",
+            ErrorKind::VideoFrameBitmapOther => "VideoFrameBitmapError",
+            ErrorKind::ScreenshotOther | ErrorKind::ScreenshotInsufficientAccess | ErrorKind::ScreenshotPermissionDenied | ErrorKind::ScreenshotTargetGone | ErrorKind::ScreenshotTimeout => "ScreenshotError",
+            ErrorKind::OpenSettingsOther | ErrorKind::OpenSettingsUnavailable | ErrorKind::OpenSettingsPlatform => "OpenSettingsError",
+            ErrorKind::StreamUpdateOther | ErrorKind::StreamUpdateUnsupported | ErrorKind::StreamUpdateAlreadyStopped | ErrorKind::StreamUpdatePlatform | ErrorKind::StreamUpdateRequiresRestart | ErrorKind::StreamUpdateInvalidConfig => "StreamUpdateError",
+            ErrorKind::VideoFrameMapOther | ErrorKind::VideoFrameMapUnsupportedPixelFormat => "VideoFrameMapError",
+            ErrorKind::SinkOther | ErrorKind::SinkFrame | ErrorKind::SinkIo => "SinkError",
+            ErrorKind::SharableContentPickerOther | ErrorKind::SharableContentPickerEmptyConfig | ErrorKind::SharableContentPickerConfigFilteringUnsupported | ErrorKind::SharableContentPickerAmbiguousPick => "SharableContentPickerError",
+            ErrorKind::AudioBufferUnsupportedFormat | ErrorKind::AudioBufferInvalidChannel | ErrorKind::AudioBufferOther => "AudioBufferError",
+            ErrorKind::WgpuVideoFrameNoBackendTexture | ErrorKind::WgpuVideoFrameInvalidVideoPlaneTexture | ErrorKind::WgpuVideoFrameNoWgpuDevice | ErrorKind::WgpuVideoFrameDeviceLost | ErrorKind::WgpuVideoFrameDestinationMismatch | ErrorKind::WgpuVideoFrameOther | ErrorKind::WgpuVideoFrameBackendMismatch => "WgpuVideoFrameError",
+            ErrorKind::WindowsDx11VideoFrameOther => "WindowsDx11VideoFrameError",
+            ErrorKind::WindowsDxgiVideoFrameOther => "WindowsDxgiVideoFrameError",
+            ErrorKind::WindowsDxgiCaptureStreamNoAdapter => "WindowsDxgiCaptureStreamError",
+            ErrorKind::MacosVideoFrameNoIoSurface | ErrorKind::MacosVideoFrameNoImageBuffer | ErrorKind::MacosVideoFrameInvalidVideoPlaneTexture | ErrorKind::MacosVideoFrameOther => "MacosVideoFrameError",
+            ErrorKind::IoSurfaceLockCannotLock | ErrorKind::IoSurfaceLockOther => "IoSurfaceLockError",
+            ErrorKind::GetIoSurfaceNoImageBuffer | ErrorKind::GetIoSurfaceNoIoSurface => "GetIoSurfaceError",
+        }
+    }
+
+    #[test]
+    fn every_kind_has_a_distinct_code() {
+        let codes: HashSet<u32> = ALL_KINDS.iter().map(ErrorKind::code).collect();
+        assert_eq!(codes.len(), ALL_KINDS.len());
+    }
+
+    #[test]
+    fn every_error_type_owns_one_block_of_codes() {
+        let mut blocks: HashMap<&'static str, u32> = HashMap::new();
+        for kind in ALL_KINDS {
+            let block = kind.code() / 100;
+            assert!(block >= 10, "{:?} is below the first block", kind);
+            assert_eq!(*blocks.entry(error_type(*kind)).or_insert(block), block, "{:?} is outside its type's block", kind);
+        }
+        let distinct_blocks: HashSet<u32> = blocks.values().copied().collect();
+        assert_eq!(distinct_blocks.len(), blocks.len());
+    }
+}
@@ -2,26 +2,43 @@
 
 use std::error::Error;
 use std::fmt::Display;
+use std::future::Future;
 use std::os::raw::c_void;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc;
 use bytemuck::Pod;
 use bytemuck::Zeroable;
 use parking_lot::Mutex;
 use parking_lot::Condvar;
 use std::sync::Arc;
 
+use futures::channel::oneshot;
 use half::f16;
 
+use crate::capture_stream::handle_callback_panic;
+use crate::error::ErrorKind;
 use crate::prelude::CapturePixelFormat;
 use crate::prelude::VideoFrame;
+use crate::util::{Rect, Size};
 
 #[cfg(target_os = "macos")]
-use crate::platform::macos::frame::MacosVideoFrame;
+use crate::frame::VideoCaptureFrame;
 #[cfg(target_os = "macos")]
-use crate::platform::platform_impl::objc_wrap::CVPixelFormat;
+use crate::platform::macos::frame::{MacosSCStreamVideoFrame, MacosVideoFrame};
+#[cfg(target_os = "macos")]
+use crate::platform::platform_impl::objc_wrap::{CVPixelFormat, IOSurface};
+#[cfg(target_os = "macos")]
+use crate::platform::macos::frame_scaler::{MacosFrameScaler, MacosFrameScalerPlane};
+#[cfg(target_os = "macos")]
+use crate::platform::macos::MacosColorMatrix;
 
 #[cfg(target_os = "windows")]
 use crate::feature::dx11::{WindowsDx11VideoFrame, WindowsDx11VideoFrameError};
 #[cfg(target_os = "windows")]
+use crate::platform::windows::frame_scaler::WindowsFrameScaler;
+#[cfg(target_os = "windows")]
+use crate::util::Point;
+#[cfg(target_os = "windows")]
 use windows::Win32::Graphics::Direct3D11::ID3D11Texture2D;
 #[cfg(target_os = "windows")]
 use windows::Graphics::DirectX::DirectXPixelFormat;
@@ -30,21 +47,38 @@ use windows::core::ComInterface;
 #[cfg(target_os = "windows")]
 use windows::Win32::Graphics::Direct3D11::{D3D11_CPU_ACCESS_READ, D3D11_MAPPED_SUBRESOURCE, D3D11_MAP_READ, D3D11_TEXTURE2D_DESC, D3D11_USAGE_STAGING};
 #[cfg(target_os = "windows")]
-use windows::Win32::Graphics::Dxgi::Common::{DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_R10G10B10A2_UNORM};
+use windows::Win32::Graphics::Dxgi::Common::{DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_R10G10B10A2_UNORM, DXGI_FORMAT_R16G16B16A16_FLOAT, DXGI_FORMAT_R16G16B16A16_UNORM};
 #[cfg(target_os = "windows")]
 use windows::Win32::System::WinRT::Direct3D11::IDirect3DDxgiInterfaceAccess;
 #[cfg(target_os = "windows")]
 use windows::Win32::Graphics::Direct3D11::D3D11_USAGE_DYNAMIC;
+#[cfg(target_os = "windows")]
+use windows::Win32::Graphics::Direct3D11::{ID3D11ShaderResourceView, D3D11_BIND_RENDER_TARGET, D3D11_BIND_SHADER_RESOURCE, D3D11_RESOURCE_MISC_GENERATE_MIPS, D3D11_USAGE_DEFAULT};
+
+/// How a bitmap pool reuses pooled bitmaps when frames change resolution, E.G. when a captured window is resized
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum BitmapPoolResizePolicy {
+    /// Pooled bitmaps of a different resolution are dropped, and a new bitmap is allocated in their place
+    #[default]
+    Reallocate,
+    /// Pooled bitmaps which are too small are grown to the requested resolution, and larger ones are reused as-is,
+    /// so the pool never drops bitmaps - at the cost of holding onto the memory of the largest resolution seen
+    Grow,
+}
 
 #[derive(Clone)]
 struct BitmapPool<T: Sized + Zeroable + Copy> {
+    // The free bitmaps, and the number of bitmaps belonging to the pool (free or checked out)
     free_bitmaps_and_count: Arc<Mutex<(Vec<Box<[T]>>, usize)>>,
     free_condition: Arc<Condvar>,
     max: usize,
+    resize_policy: BitmapPoolResizePolicy,
+    // The address and resolution of the bitmap the last change metric was measured in, while it still holds that frame
+    last_measured: Arc<Mutex<Option<(usize, (usize, usize))>>>,
 }
 
 impl<T: Sized + Zeroable + Copy> BitmapPool<T> {
-    pub fn new(initial_count: usize, max: usize, initial_resolution: (usize, usize)) -> Arc<Self> {
+    pub fn new(initial_count: usize, max: usize, initial_resolution: (usize, usize), resize_policy: BitmapPoolResizePolicy) -> Arc<Self> {
         let mut free_bitmaps = Vec::new();
         for _ in 0..initial_count {
             free_bitmaps.push(
@@ -55,18 +89,20 @@ impl<T: Sized + Zeroable + Copy> BitmapPool<T> {
             free_bitmaps_and_count: Arc::new(Mutex::new((free_bitmaps, initial_count))),
             free_condition: Arc::new(Condvar::new()),
             max,
+            resize_policy,
+            last_measured: Arc::new(Mutex::new(None)),
         })
     }
 
-    fn make_new_bitmap(self: &Arc<Self>, resolution: (usize, usize)) -> Option<PooledBitmap<T>> {
-        Some(PooledBitmap {
+    fn make_pooled_bitmap(self: &Arc<Self>, data: Box<[T]>, resolution: (usize, usize)) -> PooledBitmap<T> {
+        PooledBitmap {
             data: PooledBitmapData {
-                data: Some(vec![T::zeroed(); resolution.0 * resolution.1].into_boxed_slice()),
+                data: Some(data),
                 pool: self.clone()
             },
             width: resolution.0,
             height: resolution.1
-        })
+        }
     }
 
     pub fn try_get_bitmap(self: &Arc<Self>, resolution: (usize, usize)) -> Option<PooledBitmap<T>> {
@@ -85,24 +121,62 @@ impl<T: Sized + Zeroable + Copy> BitmapPool<T> {
         }
     }
 
+    // Get a bitmap to copy a frame into while measuring how it changed - the bitmap the previous frame was measured in is preferred,
+    // so the frame can be compared against it in place. Also returns whether the bitmap still holds the previous frame
+    fn get_measured_bitmap(self: &Arc<Self>, resolution: (usize, usize), wait: bool) -> Option<(PooledBitmap<T>, bool)> {
+        let mut free_bitmaps_and_count = self.free_bitmaps_and_count.lock();
+        loop {
+            let last_measured = *self.last_measured.lock();
+            let previous_index = last_measured
+                .filter(|(_, previous_resolution)| *previous_resolution == resolution)
+                .and_then(|(address, _)| free_bitmaps_and_count.0.iter().position(|bitmap_data| bitmap_data.as_ptr() as usize == address));
+            let pooled_bitmap = match previous_index {
+                Some(index) => {
+                    let bitmap_data = free_bitmaps_and_count.0.swap_remove(index);
+                    Some((self.make_pooled_bitmap(bitmap_data, resolution), true))
+                },
+                None => self.try_get_bitmap_internal(resolution, &mut free_bitmaps_and_count).map(|pooled_bitmap| (pooled_bitmap, false)),
+            };
+            if let Some((pooled_bitmap, holds_previous)) = pooled_bitmap {
+                *self.last_measured.lock() = pooled_bitmap.data.data.as_ref().map(|bitmap_data| (bitmap_data.as_ptr() as usize, resolution));
+                return Some((pooled_bitmap, holds_previous));
+            }
+            if !wait {
+                return None;
+            }
+            self.free_condition.wait(&mut free_bitmaps_and_count);
+        }
+    }
+
     fn try_get_bitmap_internal(self: &Arc<Self>, resolution: (usize, usize), free_bitmaps_and_count: &mut (Vec<Box<[T]>>, usize)) -> Option<PooledBitmap<T>> {
+        let pixel_count = resolution.0 * resolution.1;
         if let Some(bitmap_data) = free_bitmaps_and_count.0.pop() {
-            if bitmap_data.len() <= resolution.0 * resolution.1 {
-                return Some(
-                    PooledBitmap {
-                        data: PooledBitmapData {
-                            data: Some(bitmap_data),
-                            pool: self.clone()
-                        },
-                        width: resolution.0,
-                        height: resolution.1
-                    }
-                );
+            // The bitmap is about to be overwritten or dropped, so it no longer holds the last measured frame
+            let mut last_measured = self.last_measured.lock();
+            if matches!(*last_measured, Some((address, _)) if address == bitmap_data.as_ptr() as usize) {
+                *last_measured = None;
             }
+            drop(last_measured);
+            let reusable = match self.resize_policy {
+                BitmapPoolResizePolicy::Reallocate => bitmap_data.len() == pixel_count,
+                BitmapPoolResizePolicy::Grow => true,
+            };
+            if reusable {
+                let bitmap_data = if bitmap_data.len() < pixel_count {
+                    let mut bitmap_data = bitmap_data.into_vec();
+                    bitmap_data.resize(pixel_count, T::zeroed());
+                    bitmap_data.into_boxed_slice()
+                } else {
+                    bitmap_data
+                };
+                return Some(self.make_pooled_bitmap(bitmap_data, resolution));
+            }
+            // Drop the mismatched bitmap, making room for a new one
             free_bitmaps_and_count.1 -= 1;
         }
         if free_bitmaps_and_count.1 < self.max {
-            return self.make_new_bitmap(resolution);
+            free_bitmaps_and_count.1 += 1;
+            return Some(self.make_pooled_bitmap(vec![T::zeroed(); pixel_count].into_boxed_slice(), resolution));
         }
         None
     }
@@ -112,6 +186,7 @@ impl<T: Sized + Zeroable + Copy> BitmapPool<T> {
         let count = free_bitmaps_and_count.0.len();
         free_bitmaps_and_count.0.clear();
         free_bitmaps_and_count.1 -= count;
+        *self.last_measured.lock() = None;
     }
 }
 
@@ -137,38 +212,159 @@ pub struct PooledBitmap<T: Sized + Copy + Zeroable> {
     pub height: usize,
 }
 
+// Pooled bitmaps may be larger than their resolution (see `BitmapPoolResizePolicy::Grow`), so only the pixels in use are exposed
 impl<T: Sized + Zeroable + Copy> AsRef<[T]> for PooledBitmap<T> {
     fn as_ref(&self) -> &[T] {
-        &self.data.data.as_ref().unwrap()[..]
+        &self.data.data.as_ref().unwrap()[..(self.width * self.height)]
     }
 }
 
 impl<T: Sized + Zeroable + Copy> AsMut<[T]> for PooledBitmap<T> {
     fn as_mut(&mut self) -> &mut [T] {
-        &mut self.data.data.as_mut().unwrap()[..]
+        &mut self.data.data.as_mut().unwrap()[..(self.width * self.height)]
+    }
+}
+
+// The pixel bounds `(x, y, width, height)` of a crop rect within a plane, covering every pixel the rect touches, clamped to the plane.
+// Crops that cover no pixels are 0 by 0
+fn crop_bounds(rect: Rect, width: usize, height: usize) -> (usize, usize, usize, usize) {
+    let clamp = |value: f64, max: usize| value.clamp(0.0, max as f64) as usize;
+    let x = clamp(rect.origin.x.floor(), width);
+    let y = clamp(rect.origin.y.floor(), height);
+    let end_x = clamp((rect.origin.x + rect.size.width).ceil(), width);
+    let end_y = clamp((rect.origin.y + rect.size.height).ceil(), height);
+    if end_x <= x || end_y <= y {
+        return (x, y, 0, 0);
+    }
+    (x, y, end_x - x, end_y - y)
+}
+
+// Copy a region, given as `(x, y, width, height)`, out of a plane with rows `width` elements long
+fn crop_plane<T: Copy>(data: &[T], width: usize, (x, y, crop_width, crop_height): (usize, usize, usize, usize)) -> Box<[T]> {
+    let mut cropped = Vec::with_capacity(crop_width * crop_height);
+    for row in y..y + crop_height {
+        let start = width * row + x;
+        cropped.extend_from_slice(&data[start..start + crop_width]);
     }
+    cropped.into_boxed_slice()
 }
 
 /// Bitmap data in the Bgra8888 format
-pub trait BitmapDataBgra8x4: Sized + AsRef<[[u8; 4]]> + AsMut<[[u8; 4]]> {}
-impl<T: Sized + AsRef<[[u8; 4]]> + AsMut<[[u8; 4]]>> BitmapDataBgra8x4 for T {}
+/// 
+/// Only read access is required, so borrowed data is included
+pub trait BitmapDataBgra8x4: Sized + AsRef<[[u8; 4]]> {}
+impl<T: Sized + AsRef<[[u8; 4]]>> BitmapDataBgra8x4 for T {}
 
 /// A Bgra8888 format bitmap
 pub struct FrameBitmapBgraUnorm8x4<Data: BitmapDataBgra8x4> {
     pub data: Data,
     pub width:  usize,
     pub height: usize,
+    pub change_metric: Option<BitmapChangeMetric>,
+}
+
+impl<Data: BitmapDataBgra8x4> FrameBitmapBgraUnorm8x4<Data> {
+    /// Copy the pixels covered by `rect` into a new bitmap
+    /// 
+    /// The rect is in pixels, and is clamped to the bounds of this bitmap - pixels it partly covers are included, and a rect
+    /// entirely outside the bitmap gives an empty one. Crops have no change metric.
+    pub fn crop(&self, rect: Rect) -> FrameBitmapBgraUnorm8x4<Box<[[u8; 4]]>> {
+        let bounds = crop_bounds(rect, self.width, self.height);
+        FrameBitmapBgraUnorm8x4 {
+            data: crop_plane(self.data.as_ref(), self.width, bounds),
+            width: bounds.2,
+            height: bounds.3,
+            change_metric: None,
+        }
+    }
+}
+
+/// A Bgra8888 format bitmap borrowed from a video frame, whose rows may be padded - see `VideoFrame::with_bitmap_view()`
+pub struct FrameBitmapBgraUnorm8x4View<'a> {
+    /// The pixels, with each row starting `bytes_per_row` bytes after the previous one - use `row()` to skip the padding
+    pub data: &'a [[u8; 4]],
+    pub width:  usize,
+    pub height: usize,
+    pub bytes_per_row: usize,
+}
+
+impl<'a> FrameBitmapBgraUnorm8x4View<'a> {
+    /// Get the `width` pixels of row `y`, without its padding
+    pub fn row(&self, y: usize) -> &'a [[u8; 4]] {
+        let start = y * self.bytes_per_row / 4;
+        &self.data[start..start + self.width]
+    }
+
+    /// Iterate over the rows of the bitmap, top to bottom, without their padding
+    pub fn rows(&self) -> impl Iterator<Item = &'a [[u8; 4]]> + '_ {
+        (0..self.height).map(|y| self.row(y))
+    }
+}
+
+/// A Rgba8888 format bitmap, produced by converting other formats with `FrameBitmap::to_rgba_unorm8()`
+/// 
+/// This is the channel order expected by most image libraries, such as the `image` crate's `RgbaImage`
+pub struct FrameBitmapRgbaUnorm8x4<Data: BitmapDataBgra8x4> {
+    pub data: Data,
+    pub width:  usize,
+    pub height: usize,
+    pub change_metric: Option<BitmapChangeMetric>,
+}
+
+impl<Data: BitmapDataBgra8x4> FrameBitmapRgbaUnorm8x4<Data> {
+    /// Copy the pixels covered by `rect` into a new bitmap - see `FrameBitmapBgraUnorm8x4::crop()`
+    pub fn crop(&self, rect: Rect) -> FrameBitmapRgbaUnorm8x4<Box<[[u8; 4]]>> {
+        let bounds = crop_bounds(rect, self.width, self.height);
+        FrameBitmapRgbaUnorm8x4 {
+            data: crop_plane(self.data.as_ref(), self.width, bounds),
+            width: bounds.2,
+            height: bounds.3,
+            change_metric: None,
+        }
+    }
 }
 
 /// Bitmap data in the Argb2101010 format
 pub trait BitmapDataArgbUnormPacked2101010: Sized + AsRef<[u32]> {}
 impl<T: Sized + AsRef<[u32]> + AsMut<[u32]>> BitmapDataArgbUnormPacked2101010 for T {}
 
-/// A Rgba1010102 format bitmap
+/// An Argb2101010 format bitmap - each pixel packs alpha into its top two bits, followed by ten bits each of red, green and blue
+/// 
+/// This is the layout of CoreVideo's `l10r` format on MacOS. On Windows, DXGI's `R10G10B10A2_UNORM` packs red into the low bits,
+/// so its red and blue are swapped when the bitmap is copied, and bitmaps have the same layout on both platforms.
 pub struct FrameBitmapArgbUnormPacked2101010<Data: BitmapDataArgbUnormPacked2101010> {
     pub data: Data,
     pub width:  usize,
     pub height: usize,
+    pub change_metric: Option<BitmapChangeMetric>,
+}
+
+impl<Data: BitmapDataArgbUnormPacked2101010> FrameBitmapArgbUnormPacked2101010<Data> {
+    /// Get the `[alpha, red, green, blue]` components of the pixel at `x`, `y` - alpha is in [0, 3], and the colors in [0, 1023]
+    pub fn pixel_at(&self, x: usize, y: usize) -> [u16; 4] {
+        unpack_argb2101010(self.data.as_ref()[self.width * y + x])
+    }
+
+    /// Copy the pixels covered by `rect` into a new bitmap - see `FrameBitmapBgraUnorm8x4::crop()`
+    pub fn crop(&self, rect: Rect) -> FrameBitmapArgbUnormPacked2101010<Box<[u32]>> {
+        let bounds = crop_bounds(rect, self.width, self.height);
+        FrameBitmapArgbUnormPacked2101010 {
+            data: crop_plane(self.data.as_ref(), self.width, bounds),
+            width: bounds.2,
+            height: bounds.3,
+            change_metric: None,
+        }
+    }
+}
+
+// Split a packed Argb2101010 pixel into its `[alpha, red, green, blue]` components
+fn unpack_argb2101010(pixel: u32) -> [u16; 4] {
+    [
+        ((pixel >> 30) & 0x3) as u16,
+        ((pixel >> 20) & 0x3FF) as u16,
+        ((pixel >> 10) & 0x3FF) as u16,
+        (pixel & 0x3FF) as u16,
+    ]
 }
 
 /// Bitmap data in the RgbaF16x4 format
@@ -180,16 +376,197 @@ pub struct FrameBitmapRgbaF16x4<Data: BitmapDataRgbaF16x4> {
     pub data: Data,
     pub width:  usize,
     pub height: usize,
+    pub change_metric: Option<BitmapChangeMetric>,
+}
+
+/// The curve used to map high dynamic range color values into [0, 1]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToneMapCurve {
+    /// Clamp values to [0, 1] - anything brighter than 1.0 is blown out
+    LinearClamp,
+    /// The Reinhard operator, `x / (1 + x)`
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve
+    AcesFilmic,
+}
+
+/// Tone mapping settings for converting high dynamic range bitmaps to 8 bits per channel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToneMap {
+    /// The curve to map color values with
+    pub curve: ToneMapCurve,
+    /// A linear multiplier applied to color values before the curve
+    pub exposure: f32,
+}
+
+impl ToneMap {
+    /// Clamp color values to [0, 1], with no exposure adjustment
+    pub const LINEAR_CLAMP: Self = Self { curve: ToneMapCurve::LinearClamp, exposure: 1.0 };
+    /// The Reinhard operator, with no exposure adjustment
+    pub const REINHARD: Self = Self { curve: ToneMapCurve::Reinhard, exposure: 1.0 };
+    /// The ACES filmic curve, with no exposure adjustment
+    pub const ACES_FILMIC: Self = Self { curve: ToneMapCurve::AcesFilmic, exposure: 1.0 };
+
+    /// Set the exposure multiplier
+    pub fn with_exposure(self, exposure: f32) -> Self {
+        Self {
+            exposure,
+            ..self
+        }
+    }
+
+    /// Map a linear color value into [0, 1]
+    pub fn map(&self, value: f32) -> f32 {
+        // NaN and negative (out of gamut) values map to black
+        let x = (value * self.exposure).max(0.0);
+        let mapped = match self.curve {
+            ToneMapCurve::LinearClamp => x,
+            ToneMapCurve::Reinhard => x / (1.0 + x),
+            ToneMapCurve::AcesFilmic => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+        };
+        mapped.clamp(0.0, 1.0)
+    }
+}
+
+fn linear_to_srgb_unorm8(value: f32) -> u8 {
+    let encoded = if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+// Tone map and encode a linear pixel, returning it in rgba order
+fn linear_f16_to_rgba_unorm8([r, g, b, a]: [f16; 4], tone_map: ToneMap) -> [u8; 4] {
+    [
+        linear_to_srgb_unorm8(tone_map.map(r.to_f32())),
+        linear_to_srgb_unorm8(tone_map.map(g.to_f32())),
+        linear_to_srgb_unorm8(tone_map.map(b.to_f32())),
+        (a.to_f32().clamp(0.0, 1.0) * 255.0).round() as u8,
+    ]
+}
+
+impl<Data: BitmapDataRgbaF16x4> FrameBitmapRgbaF16x4<Data> {
+    /// Copy the pixels covered by `rect` into a new bitmap - see `FrameBitmapBgraUnorm8x4::crop()`
+    pub fn crop(&self, rect: Rect) -> FrameBitmapRgbaF16x4<Box<[[f16; 4]]>> {
+        let bounds = crop_bounds(rect, self.width, self.height);
+        FrameBitmapRgbaF16x4 {
+            data: crop_plane(self.data.as_ref(), self.width, bounds),
+            width: bounds.2,
+            height: bounds.3,
+            change_metric: None,
+        }
+    }
+
+    /// Convert this bitmap to a Bgra8888 bitmap for display
+    /// 
+    /// Color values are treated as linear, tone mapped into [0, 1] with the given settings, and then encoded with the sRGB transfer function.
+    /// Alpha is clamped to [0, 1], and isn't tone mapped.
+    pub fn to_bgra_unorm8(&self, tone_map: ToneMap) -> FrameBitmapBgraUnorm8x4<Box<[[u8; 4]]>> {
+        let data = self.data.as_ref().iter().map(|pixel| {
+            let [r, g, b, a] = linear_f16_to_rgba_unorm8(*pixel, tone_map);
+            [b, g, r, a]
+        }).collect::<Vec<_>>().into_boxed_slice();
+        FrameBitmapBgraUnorm8x4 {
+            data,
+            width: self.width,
+            height: self.height,
+            change_metric: self.change_metric,
+        }
+    }
+
+    /// Convert this bitmap to a Rgba8888 bitmap for display, in the same way as `to_bgra_unorm8()`
+    pub fn to_rgba_unorm8(&self, tone_map: ToneMap) -> FrameBitmapRgbaUnorm8x4<Box<[[u8; 4]]>> {
+        let data = self.data.as_ref().iter().map(|pixel| linear_f16_to_rgba_unorm8(*pixel, tone_map)).collect::<Vec<_>>().into_boxed_slice();
+        FrameBitmapRgbaUnorm8x4 {
+            data,
+            width: self.width,
+            height: self.height,
+            change_metric: self.change_metric,
+        }
+    }
+}
+
+/// Bitmap data in the Rgba16x4 format
+pub trait BitmapDataRgba16x4: Sized + AsRef<[[u16; 4]]> {}
+impl<T: Sized + AsRef<[[u16; 4]]> + AsMut<[[u16; 4]]>> BitmapDataRgba16x4 for T {}
+
+/// A Rgba16x4 format bitmap
+pub struct FrameBitmapRgba16x4<Data: BitmapDataRgba16x4> {
+    pub data: Data,
+    pub width:  usize,
+    pub height: usize,
+    pub change_metric: Option<BitmapChangeMetric>,
+}
+
+impl<Data: BitmapDataRgba16x4> FrameBitmapRgba16x4<Data> {
+    /// Copy the pixels covered by `rect` into a new bitmap - see `FrameBitmapBgraUnorm8x4::crop()`
+    pub fn crop(&self, rect: Rect) -> FrameBitmapRgba16x4<Box<[[u16; 4]]>> {
+        let bounds = crop_bounds(rect, self.width, self.height);
+        FrameBitmapRgba16x4 {
+            data: crop_plane(self.data.as_ref(), self.width, bounds),
+            width: bounds.2,
+            height: bounds.3,
+            change_metric: None,
+        }
+    }
+}
+
+/// A cheap measure of how much a pooled bitmap changed since the previous frame copied into the same pool
+/// 
+/// Only produced for frames from a stream configured with `CaptureConfig::with_change_metric(true)`
+/// that are copied with `get_pooled_bitmap()` or `try_get_pooled_bitmap()`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BitmapChangeMetric {
+    /// The number of rows which differ at all from the previous frame
+    pub changed_rows: u32,
+    /// The mean absolute difference of each channel from the previous frame, normalized to [0, 1]
+    pub mean_abs_delta: f32,
 }
 
-/// The video range for a YCbCr format bitmap
+/// The video range for a YCbCr format bitmap - V420 frames are video range, and F420 frames are full range
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum VideoRange {
-    /// Luma: [16, 240], Chroma: [0, 255]
+    /// Luma: [16, 235], Chroma: [16, 240]
     Video,
     /// Luma: [0, 255], Chroma: [0, 255]
     Full,
 }
 
+/// The matrix used to convert RGB content to a YCbCr bitmap, and so to convert it back
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum YCbCrColorMatrix {
+    /// ITU-R BT.709, for HD video - the default
+    ItuR709,
+    /// ITU-R BT.601, for SD video
+    ItuR601,
+    /// SMPTE 240M
+    Smpte240M,
+}
+
+impl YCbCrColorMatrix {
+    // The red and blue luma coefficients (Kr, Kb) of the matrix
+    fn luma_coefficients(self) -> (f32, f32) {
+        match self {
+            Self::ItuR709   => (0.2126, 0.0722),
+            Self::ItuR601   => (0.299,  0.114),
+            Self::Smpte240M => (0.212,  0.087),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl From<MacosColorMatrix> for YCbCrColorMatrix {
+    fn from(color_matrix: MacosColorMatrix) -> Self {
+        match color_matrix {
+            MacosColorMatrix::ItuR709_2 => Self::ItuR709,
+            MacosColorMatrix::ItuR601_4 => Self::ItuR601,
+            MacosColorMatrix::Smpte240M1995 => Self::Smpte240M,
+        }
+    }
+}
+
 /// Bitmap data in the Luma/u8 format
 pub trait BitmapDataLuma: Sized + AsRef<[u8]> {}
 impl<T: Sized + AsRef<[u8]> + AsMut<[u8]>> BitmapDataLuma for T {}
@@ -203,6 +580,11 @@ impl<T: Sized + AsRef<[[u8; 2]]> + AsMut<[[u8; 2]]>> BitmapDataChroma for T {}
 /// Dual-planar, with luminance (Y) in one plane, and chrominance (CbCr) in another.
 /// Note that each plane may have a different size, as with V420 format, where
 /// the chroma plane is 2 by 2 blocks, but luma is per-pixel
+/// 
+/// The size of each plane comes from the frame itself rather than being derived - for 4:2:0 formats the chroma plane is
+/// half the luma size rounded up, so with odd dimensions the last chroma column or row covers a single luma column or row.
+/// Chroma samples are left-sited, as ScreenCaptureKit produces them: horizontally co-sited with even luma columns,
+/// and vertically centered between pairs of luma rows. Use `chroma_at()` to find the chroma sample for a luma pixel.
 pub struct FrameBitmapYCbCr<LumaData: BitmapDataLuma, ChromaData: BitmapDataChroma> {
     pub luma_data: LumaData,
     pub luma_width: usize,
@@ -211,14 +593,208 @@ pub struct FrameBitmapYCbCr<LumaData: BitmapDataLuma, ChromaData: BitmapDataChro
     pub chroma_width: usize,
     pub chroma_height: usize,
     pub range: VideoRange,
+    /// The matrix the frame's content was converted to YCbCr with, as configured for the capture stream
+    pub color_matrix: YCbCrColorMatrix,
+    /// Measured on the luma plane only
+    pub change_metric: Option<BitmapChangeMetric>,
+}
+
+impl<LumaData: BitmapDataLuma, ChromaData: BitmapDataChroma> FrameBitmapYCbCr<LumaData, ChromaData> {
+    /// Get the chroma (CbCr) sample covering the luma pixel at `x`, `y`, clamped to the edges of the chroma plane
+    pub fn chroma_at(&self, x: usize, y: usize) -> [u8; 2] {
+        if self.chroma_width == 0 || self.chroma_height == 0 {
+            return [128, 128];
+        }
+        let chroma_x = (x * self.chroma_width / self.luma_width.max(1)).min(self.chroma_width - 1);
+        let chroma_y = (y * self.chroma_height / self.luma_height.max(1)).min(self.chroma_height - 1);
+        self.chroma_data.as_ref()[self.chroma_width * chroma_y + chroma_x]
+    }
+
+    /// Copy the pixels covered by `rect` into a new bitmap, cropping both planes - see `FrameBitmapBgraUnorm8x4::crop()`
+    /// 
+    /// With subsampled chroma, the crop is widened to start on an even luma column and row, so that each chroma sample
+    /// covers the same luma pixels in the crop as it does in this bitmap
+    pub fn crop(&self, rect: Rect) -> FrameBitmapYCbCr<Box<[u8]>, Box<[[u8; 2]]>> {
+        let (x, y, width, height) = crop_bounds(rect, self.luma_width, self.luma_height);
+        // Empty crops stay empty, rather than being widened onto a pixel
+        let subsampling_x = if width > 0 && self.chroma_width < self.luma_width { 2 } else { 1 };
+        let subsampling_y = if height > 0 && self.chroma_height < self.luma_height { 2 } else { 1 };
+        let luma_x = x - x % subsampling_x;
+        let luma_y = y - y % subsampling_y;
+        let luma_bounds = (luma_x, luma_y, width + x - luma_x, height + y - luma_y);
+        let chroma_x = luma_x / subsampling_x;
+        let chroma_y = luma_y / subsampling_y;
+        let chroma_bounds = (
+            chroma_x,
+            chroma_y,
+            luma_bounds.2.div_ceil(subsampling_x).min(self.chroma_width.saturating_sub(chroma_x)),
+            luma_bounds.3.div_ceil(subsampling_y).min(self.chroma_height.saturating_sub(chroma_y)),
+        );
+        FrameBitmapYCbCr {
+            luma_data: crop_plane(self.luma_data.as_ref(), self.luma_width, luma_bounds),
+            luma_width: luma_bounds.2,
+            luma_height: luma_bounds.3,
+            chroma_data: crop_plane(self.chroma_data.as_ref(), self.chroma_width, chroma_bounds),
+            chroma_width: chroma_bounds.2,
+            chroma_height: chroma_bounds.3,
+            range: self.range,
+            color_matrix: self.color_matrix,
+            change_metric: None,
+        }
+    }
+
+    // Convert every pixel to RGB into `dest` (one pixel per luma sample), in bgra order if `bgra` is set and rgba order otherwise
+    fn convert_into(&self, dest: &mut [[u8; 4]], bgra: bool) {
+        // The offset and scale mapping each plane's stored range to [0, 1] for luma, and [-0.5, 0.5] for chroma
+        let (luma_offset, luma_scale, chroma_scale) = match self.range {
+            VideoRange::Video => (16.0, 219.0, 224.0),
+            VideoRange::Full  => ( 0.0, 255.0, 255.0),
+        };
+        let (kr, kb) = self.color_matrix.luma_coefficients();
+        let kg = 1.0 - kr - kb;
+        let (r_cr, b_cb) = (2.0 * (1.0 - kr), 2.0 * (1.0 - kb));
+        let (g_cb, g_cr) = (b_cb * kb / kg, r_cr * kr / kg);
+        let to_unorm8 = |x: f32| (x.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let luma_data = self.luma_data.as_ref();
+        for y in 0..self.luma_height {
+            for x in 0..self.luma_width {
+                let luma = (luma_data[self.luma_width * y + x] as f32 - luma_offset) / luma_scale;
+                let [cb, cr] = self.chroma_at(x, y).map(|c| (c as f32 - 128.0) / chroma_scale);
+                let r = to_unorm8(luma + r_cr * cr);
+                let g = to_unorm8(luma - g_cb * cb - g_cr * cr);
+                let b = to_unorm8(luma + b_cb * cb);
+                dest[self.luma_width * y + x] = if bgra { [b, g, r, 255] } else { [r, g, b, 255] };
+            }
+        }
+    }
+
+    /// Convert this bitmap to a Rgba8888 bitmap, with the bitmap's `color_matrix`
+    /// 
+    /// Luma and chroma are expanded from the bitmap's `range` first, and each pixel takes the chroma sample given by `chroma_at()`.
+    /// The result is opaque, and keeps the transfer function of the captured content.
+    pub fn to_rgba_unorm8(&self) -> FrameBitmapRgbaUnorm8x4<Box<[[u8; 4]]>> {
+        let mut data = vec![[0u8; 4]; self.luma_width * self.luma_height].into_boxed_slice();
+        self.convert_into(&mut data, false);
+        FrameBitmapRgbaUnorm8x4 {
+            data,
+            width: self.luma_width,
+            height: self.luma_height,
+            change_metric: self.change_metric,
+        }
+    }
+
+    /// Convert this bitmap to a Bgra8888 bitmap, in the same way as `to_rgba_unorm8()`
+    pub fn to_bgra_unorm8(&self) -> FrameBitmapBgraUnorm8x4<Box<[[u8; 4]]>> {
+        let mut data = vec![[0u8; 4]; self.luma_width * self.luma_height].into_boxed_slice();
+        self.convert_into(&mut data, true);
+        FrameBitmapBgraUnorm8x4 {
+            data,
+            width: self.luma_width,
+            height: self.luma_height,
+            change_metric: self.change_metric,
+        }
+    }
+
+    /// Convert this bitmap to a Bgra8888 bitmap from the given pool, in the same way as `to_rgba_unorm8()`
+    /// 
+    /// Like `VideoFrameBitmap::get_pooled_bitmap()`, this waits for a pooled bitmap to become available if `max` pooled bitmaps are checked out
+    pub fn to_pooled_bgra_unorm8(&self, bitmap_pool: &FrameBitmapPool) -> FrameBitmapBgraUnorm8x4<PooledBitmap<[u8; 4]>> {
+        let mut data = bitmap_pool.bgra_u8x4.get_bitmap((self.luma_width, self.luma_height));
+        self.convert_into(data.as_mut(), true);
+        FrameBitmapBgraUnorm8x4 {
+            data,
+            width: self.luma_width,
+            height: self.luma_height,
+            change_metric: self.change_metric,
+        }
+    }
 }
 
 /// A bitmap image of the selected format
-pub enum FrameBitmap<DataBgra: BitmapDataBgra8x4, DataArgbPacked: BitmapDataArgbUnormPacked2101010, DataRgbaF16: BitmapDataRgbaF16x4, DataLuma: BitmapDataLuma, DataChroma: BitmapDataChroma> {
+#[non_exhaustive]
+pub enum FrameBitmap<DataBgra: BitmapDataBgra8x4, DataArgbPacked: BitmapDataArgbUnormPacked2101010, DataRgbaF16: BitmapDataRgbaF16x4, DataLuma: BitmapDataLuma, DataChroma: BitmapDataChroma, DataRgba16: BitmapDataRgba16x4> {
     BgraUnorm8x4(FrameBitmapBgraUnorm8x4<DataBgra>),
     ArgbUnormPacked2101010(FrameBitmapArgbUnormPacked2101010<DataArgbPacked>),
     RgbaF16x4(FrameBitmapRgbaF16x4<DataRgbaF16>),
     YCbCr(FrameBitmapYCbCr<DataLuma, DataChroma>),
+    RgbaUnorm16x4(FrameBitmapRgba16x4<DataRgba16>),
+}
+
+impl<DataBgra: BitmapDataBgra8x4, DataArgbPacked: BitmapDataArgbUnormPacked2101010, DataRgbaF16: BitmapDataRgbaF16x4, DataLuma: BitmapDataLuma, DataChroma: BitmapDataChroma, DataRgba16: BitmapDataRgba16x4> FrameBitmap<DataBgra, DataArgbPacked, DataRgbaF16, DataLuma, DataChroma, DataRgba16> {
+    /// Gets how much this bitmap changed since the previous frame copied into the same bitmap pool
+    /// 
+    /// Each frame is copied over the pooled bitmap the previous frame was copied into, comparing each row before it's overwritten,
+    /// so no extra copy of the previous frame is kept - frames from different streams shouldn't share a pool when using change metrics.
+    /// That needs the previous frame's bitmap to be dropped (returned to the pool) before the next frame is copied: while it's still in use,
+    /// there's nothing to compare against, and the frame reports every row as changed with a delta of 1.0 - as does the first frame
+    /// copied into a pool, or the first after a resize
+    pub fn change_metric(&self) -> Option<BitmapChangeMetric> {
+        match self {
+            Self::BgraUnorm8x4(bitmap) => bitmap.change_metric,
+            Self::ArgbUnormPacked2101010(bitmap) => bitmap.change_metric,
+            Self::RgbaF16x4(bitmap) => bitmap.change_metric,
+            Self::YCbCr(bitmap) => bitmap.change_metric,
+            Self::RgbaUnorm16x4(bitmap) => bitmap.change_metric,
+        }
+    }
+
+    /// Copy the pixels covered by `rect` into a new bitmap of the same format - see `FrameBitmapBgraUnorm8x4::crop()`
+    /// and `FrameBitmapYCbCr::crop()`
+    pub fn crop(&self, rect: Rect) -> BoxedSliceFrameBitmap {
+        match self {
+            Self::BgraUnorm8x4(bitmap) => FrameBitmap::BgraUnorm8x4(bitmap.crop(rect)),
+            Self::ArgbUnormPacked2101010(bitmap) => FrameBitmap::ArgbUnormPacked2101010(bitmap.crop(rect)),
+            Self::RgbaF16x4(bitmap) => FrameBitmap::RgbaF16x4(bitmap.crop(rect)),
+            Self::YCbCr(bitmap) => FrameBitmap::YCbCr(bitmap.crop(rect)),
+            Self::RgbaUnorm16x4(bitmap) => FrameBitmap::RgbaUnorm16x4(bitmap.crop(rect)),
+        }
+    }
+
+    /// Convert this bitmap to a Rgba8888 bitmap, for use with image libraries that expect that channel order
+    /// 
+    /// * Bgra8888 bitmaps have their channels swapped
+    /// * YCbCr bitmaps are converted to RGB - see `FrameBitmapYCbCr::to_rgba_unorm8()`
+    /// * Argb2101010 and Rgba16x4 bitmaps are rounded to the nearest 8 bit value
+    /// * RgbaF16x4 bitmaps are clamped and encoded as sRGB - use `FrameBitmapRgbaF16x4::to_rgba_unorm8()` to tone map them instead
+    pub fn to_rgba_unorm8(&self) -> FrameBitmapRgbaUnorm8x4<Box<[[u8; 4]]>> {
+        // Round an n bit unorm value to the nearest 8 bit value
+        fn round_unorm(value: u32, max: u32) -> u8 {
+            ((value * 255 + max / 2) / max) as u8
+        }
+        let (data, width, height): (Box<[[u8; 4]]>, _, _) = match self {
+            Self::BgraUnorm8x4(bitmap) => (
+                bitmap.data.as_ref().iter().map(|[b, g, r, a]| [*r, *g, *b, *a]).collect(),
+                bitmap.width,
+                bitmap.height,
+            ),
+            Self::ArgbUnormPacked2101010(bitmap) => (
+                bitmap.data.as_ref().iter().map(|pixel| {
+                    let [a, r, g, b] = unpack_argb2101010(*pixel);
+                    [
+                        round_unorm(r as u32, 1023),
+                        round_unorm(g as u32, 1023),
+                        round_unorm(b as u32, 1023),
+                        round_unorm(a as u32, 3),
+                    ]
+                }).collect(),
+                bitmap.width,
+                bitmap.height,
+            ),
+            Self::RgbaF16x4(bitmap) => return bitmap.to_rgba_unorm8(ToneMap::LINEAR_CLAMP),
+            Self::YCbCr(bitmap) => return bitmap.to_rgba_unorm8(),
+            Self::RgbaUnorm16x4(bitmap) => (
+                bitmap.data.as_ref().iter().map(|pixel| pixel.map(|x| round_unorm(x as u32, 65535))).collect(),
+                bitmap.width,
+                bitmap.height,
+            ),
+        };
+        FrameBitmapRgbaUnorm8x4 {
+            data,
+            width,
+            height,
+            change_metric: self.change_metric(),
+        }
+    }
 }
 
 /// A Bitmap with boxed-slice image data
@@ -232,7 +808,9 @@ pub type BoxedSliceFrameBitmap = FrameBitmap<
     // Luma
     Box<[u8]>,
     // Chroma
-    Box<[[u8; 2]]>
+    Box<[[u8; 2]]>,
+    // Rgba16x4
+    Box<[[u16; 4]]>,
 >;
 
 /// A bitmap with booled images as bitmap data
@@ -247,6 +825,8 @@ pub type PooledFrameBitmap = FrameBitmap<
     PooledBitmap<u8>,
     // Chroma
     PooledBitmap<[u8; 2]>,
+    // Rgba16x4
+    PooledBitmap<[u16; 4]>,
 >;
 
 /// A pool of frame bitmaps
@@ -256,6 +836,7 @@ pub struct FrameBitmapPool {
     rgba_f16x4: Arc<BitmapPool<[f16; 4]>>,
     luma: Arc<BitmapPool<u8>>,
     chroma: Arc<BitmapPool<[u8; 2]>>,
+    rgba_u16x4: Arc<BitmapPool<[u16; 4]>>,
 }
 
 impl FrameBitmapPool {
@@ -265,39 +846,57 @@ impl FrameBitmapPool {
             bgra_u8x4: BitmapPool::new(
                 if format == CapturePixelFormat::Bgra8888 { capacity } else { 0 },
                 max,
-                initial_resolution
+                initial_resolution,
+                BitmapPoolResizePolicy::default()
             ),
             argb_packed_2101010: BitmapPool::new(
                 if format == CapturePixelFormat::Argb2101010 { capacity } else { 0 },
                 max,
-                initial_resolution
+                initial_resolution,
+                BitmapPoolResizePolicy::default()
             ),
             rgba_f16x4: BitmapPool::new(
-                0,
+                if format == CapturePixelFormat::RgbaF16 { capacity } else { 0 },
                 max,
-                initial_resolution
+                initial_resolution,
+                BitmapPoolResizePolicy::default()
             ),
             luma: BitmapPool::new(
                 if format == CapturePixelFormat::F420 || format == CapturePixelFormat::V420 { capacity } else { 0 },
                 max,
-                initial_resolution
+                initial_resolution,
+                BitmapPoolResizePolicy::default()
             ),
             chroma: BitmapPool::new(
                 if format == CapturePixelFormat::F420 || format == CapturePixelFormat::V420 { capacity } else { 0 },
                 max,
-                initial_resolution
-            )
+                initial_resolution,
+                BitmapPoolResizePolicy::default()
+            ),
+            rgba_u16x4: BitmapPool::new(
+                if format == CapturePixelFormat::Rgba16 { capacity } else { 0 },
+                max,
+                initial_resolution,
+                BitmapPoolResizePolicy::default()
+            ),
         }
     }
 
     /// Create a new frame bitmap pool, limited to `max` pooled bitmaps
     pub fn new(max: usize) -> Self {
+        Self::new_with_resize_policy(max, BitmapPoolResizePolicy::default())
+    }
+
+    /// Create a new frame bitmap pool, limited to `max` pooled bitmaps, which reuses its bitmaps across changes in resolution
+    /// according to `resize_policy`
+    pub fn new_with_resize_policy(max: usize, resize_policy: BitmapPoolResizePolicy) -> Self {
         Self {
-            bgra_u8x4: BitmapPool::new(0, max, (0, 0)),
-            argb_packed_2101010: BitmapPool::new(0, max, (0, 0)),
-            rgba_f16x4: BitmapPool::new(0, max, (0, 0)),
-            luma: BitmapPool::new(0, max, (0, 0)),
-            chroma: BitmapPool::new(0, max, (0, 0)),
+            bgra_u8x4: BitmapPool::new(0, max, (0, 0), resize_policy),
+            argb_packed_2101010: BitmapPool::new(0, max, (0, 0), resize_policy),
+            rgba_f16x4: BitmapPool::new(0, max, (0, 0), resize_policy),
+            luma: BitmapPool::new(0, max, (0, 0), resize_policy),
+            chroma: BitmapPool::new(0, max, (0, 0), resize_policy),
+            rgba_u16x4: BitmapPool::new(0, max, (0, 0), resize_policy),
         }
     }
 
@@ -308,9 +907,78 @@ impl FrameBitmapPool {
         self.rgba_f16x4.free_pooled();
         self.luma.free_pooled();
         self.chroma.free_pooled();
+        self.rgba_u16x4.free_pooled();
+    }
+}
+
+type ConversionJob = Box<dyn FnOnce() + Send + 'static>;
+
+/// A pool of worker threads used for offloaded bitmap conversions, shared between all streams
+/// 
+/// By default, a pool with one worker per available CPU core is created the first time an asynchronous conversion is requested.
+/// Panics during conversions are caught and reported to the handler set with `CaptureStream::set_callback_panic_handler`,
+/// without taking down the worker.
+#[derive(Clone)]
+pub struct ConversionThreadPool {
+    sender: Arc<Mutex<mpsc::Sender<ConversionJob>>>,
+    worker_count: usize,
+}
+
+impl ConversionThreadPool {
+    /// Create a new conversion thread pool with `worker_count` worker threads (at least one)
+    pub fn new(worker_count: usize) -> Self {
+        let worker_count = worker_count.max(1);
+        let (sender, receiver) = mpsc::channel::<ConversionJob>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..worker_count {
+            let receiver = receiver.clone();
+            let _ = std::thread::Builder::new()
+                .name(format!("crabgrab-bitmap-conversion-{}", i))
+                .spawn(move || {
+                    loop {
+                        let job = receiver.lock().recv();
+                        match job {
+                            // A panicking conversion is reported like a panicking stream callback, and the worker carries on
+                            Ok(job) => if let Err(payload) = catch_unwind(AssertUnwindSafe(job)) {
+                                handle_callback_panic(&*payload);
+                            },
+                            Err(_) => break,
+                        }
+                    }
+                });
+        }
+        Self {
+            sender: Arc::new(Mutex::new(sender)),
+            worker_count,
+        }
+    }
+
+    /// The number of worker threads in this pool
+    pub fn worker_count(&self) -> usize {
+        self.worker_count
+    }
+
+    fn spawn(&self, job: impl FnOnce() + Send + 'static) {
+        let _ = self.sender.lock().send(Box::new(job));
     }
 }
 
+static CONVERSION_THREAD_POOL: Mutex<Option<ConversionThreadPool>> = parking_lot::const_mutex(None);
+
+/// Set the thread pool used for asynchronous bitmap conversions by all streams, capping the CPU used by conversion work.
+/// 
+/// Conversions already queued on a previous pool will still complete on that pool.
+pub fn set_conversion_thread_pool(thread_pool: ConversionThreadPool) {
+    *CONVERSION_THREAD_POOL.lock() = Some(thread_pool);
+}
+
+fn conversion_thread_pool() -> ConversionThreadPool {
+    CONVERSION_THREAD_POOL.lock().get_or_insert_with(|| {
+        let worker_count = std::thread::available_parallelism().map_or(1, |count| count.get());
+        ConversionThreadPool::new(worker_count)
+    }).clone()
+}
+
 /// A video frame which can produce a bitmap
 pub trait VideoFrameBitmap {
     /// Create a bitmap image from this frame. This usually involves a memory transfer from VRAM to system RAM,
@@ -323,14 +991,49 @@ pub trait VideoFrameBitmap {
 
     /// Get a pooled bitmap, waiting for one to become available if `max` pooled bitmaps are checked out
     fn get_pooled_bitmap(&self, bitmap_pool: &FrameBitmapPool) -> Result<PooledFrameBitmap, VideoFrameBitmapError>;
+
+    /// Create a bitmap image from this frame, downscaled to at most `target_size`. This is much cheaper than
+    /// `get_bitmap` followed by resizing on the CPU, as the downscale happens on the GPU before readback
+    /// (from generated mips on Windows, with a Metal Performance Shaders Lanczos scale on MacOS).
+    /// 
+    /// Frames are never upscaled - if the frame is smaller than `target_size` it is returned at its own size.
+    fn downscale_to_bitmap(&self, target_size: Size, mode: DownscaleMode) -> Result<BoxedSliceFrameBitmap, VideoFrameBitmapError>;
+
+    /// Create a bitmap image from this frame on the conversion thread pool (see `set_conversion_thread_pool`),
+    /// rather than on the calling thread
+    fn get_bitmap_async(self) -> impl Future<Output = Result<BoxedSliceFrameBitmap, VideoFrameBitmapError>> + Send + 'static where Self: Sized;
+}
+
+/// How a frame is fit into the target size by `VideoFrameBitmap::downscale_to_bitmap`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DownscaleMode {
+    /// Stretch the frame to the target size
+    Stretch,
+    /// Fit the frame within the target size, preserving its aspect ratio
+    PreserveAspect,
 }
 
 #[derive(Clone, Debug)]
 /// Represents an error while generating a frame bitmap
+#[non_exhaustive]
 pub enum VideoFrameBitmapError {
     Other(String),
 }
 
+impl VideoFrameBitmapError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Other(_) => ErrorKind::VideoFrameBitmapOther,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+}
+
 impl Display for VideoFrameBitmapError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -365,16 +1068,45 @@ enum VideoFrameDataCopyPtrs {
     Bgra8888(VideoFramePlanePtr),
     ArgbPacked2101010(VideoFramePlanePtr),
     RgbaF16x4(VideoFramePlanePtr),
+    Rgba16x4(VideoFramePlanePtr),
     F420{luma: VideoFramePlanePtr, chroma: VideoFramePlanePtr},
     V420{luma: VideoFramePlanePtr, chroma: VideoFramePlanePtr},
 }
 
 trait VideoFrameBitmapInternal {
-    fn get_bitmap_internal<T>(&self, output_mapping: &impl Fn(VideoFrameDataCopyPtrs) -> Result<T, VideoFrameBitmapError>) -> Result<T, VideoFrameBitmapError>; 
+    fn get_bitmap_internal<T>(&self, downscale: Option<(Size, DownscaleMode)>, output_mapping: &impl Fn(VideoFrameDataCopyPtrs) -> Result<T, VideoFrameBitmapError>) -> Result<T, VideoFrameBitmapError>; 
+    fn ycbcr_color_matrix(&self) -> YCbCrColorMatrix;
+}
+
+// The smallest mip level of a texture of the given size which is still at least the minimum size
+#[cfg(any(target_os = "windows", test))]
+fn mip_level_for_size(size: (usize, usize), minimum_size: (usize, usize)) -> u32 {
+    let mut level = 0;
+    let (mut width, mut height) = size;
+    while width / 2 >= minimum_size.0.max(1) && height / 2 >= minimum_size.1.max(1) {
+        width /= 2;
+        height /= 2;
+        level += 1;
+    }
+    level
+}
+
+// The size of a mip level of a texture of the given size
+#[cfg(any(target_os = "windows", test))]
+fn mip_size(size: (usize, usize), mip_level: u32) -> (usize, usize) {
+    ((size.0 >> mip_level).max(1), (size.1 >> mip_level).max(1))
+}
+
+// How a texture is read back downscaled to the given size: the mip level to generate mips down to, and whether that
+// level is exactly the downscaled size, so it can be read back directly rather than sampled down to the size
+#[cfg(any(target_os = "windows", test))]
+fn downscale_mip_level(size: (usize, usize), downscaled_size: (usize, usize)) -> (u32, bool) {
+    let mip_level = mip_level_for_size(size, downscaled_size);
+    (mip_level, mip_size(size, mip_level) == downscaled_size)
 }
 
 impl VideoFrameBitmapInternal for VideoFrame {
-    fn get_bitmap_internal<T>(&self, output_mapping: &impl Fn(VideoFrameDataCopyPtrs) -> Result<T, VideoFrameBitmapError>) -> Result<T, VideoFrameBitmapError> {
+    fn get_bitmap_internal<T>(&self, downscale: Option<(Size, DownscaleMode)>, output_mapping: &impl Fn(VideoFrameDataCopyPtrs) -> Result<T, VideoFrameBitmapError>) -> Result<T, VideoFrameBitmapError> {
         #[cfg(target_os = "windows")]
         {
             let (width, height) = self.impl_video_frame.frame_size;
@@ -384,18 +1116,34 @@ impl VideoFrameBitmapInternal for VideoFrame {
                     let dxgi_format = match pixel_format {
                         DirectXPixelFormat::B8G8R8A8UIntNormalized => DXGI_FORMAT_B8G8R8A8_UNORM,
                         DirectXPixelFormat::R10G10B10A2UIntNormalized => DXGI_FORMAT_R10G10B10A2_UNORM,
+                        DirectXPixelFormat::R16G16B16A16UIntNormalized => DXGI_FORMAT_R16G16B16A16_UNORM,
+                        DirectXPixelFormat::R16G16B16A16Float => DXGI_FORMAT_R16G16B16A16_FLOAT,
                         _ => return Err(VideoFrameBitmapError::Other("Unknown or unsupported pixel format on DXGISurface".to_string())),
                     };
                     
                     unsafe {
                         let surface_desc = surface.Description()
                             .map_err(|_| VideoFrameBitmapError::Other("Couldn't get description of frame surface".to_string()))?;
+                        // When downscaling, mips are generated down to the downscaled size - a mip level of exactly that size
+                        // is read back as-is, otherwise the mips are sampled down to it, so the downscale happens entirely on the GPU
+                        let downscaled_size = downscale.map(|(target_size, mode)| downscaled_size((width, height), target_size, mode));
+                        let (mip_level, read_mip_level) = match downscaled_size {
+                            Some(downscaled_size) => downscale_mip_level((width, height), downscaled_size),
+                            None => (0, true),
+                        };
+                        let full_size = (width, height);
+                        let (width, height) = downscaled_size.unwrap_or(full_size);
+                        let (staging_width, staging_height) = if read_mip_level {
+                            mip_size((surface_desc.Width as usize, surface_desc.Height as usize), mip_level)
+                        } else {
+                            (width, height)
+                        };
                         let mut new_texture_desc = D3D11_TEXTURE2D_DESC::default();
                         new_texture_desc.CPUAccessFlags = D3D11_CPU_ACCESS_READ.0 as u32;
                         new_texture_desc.ArraySize = 1;
                         new_texture_desc.BindFlags = 0;
-                        new_texture_desc.Width = surface_desc.Width as u32;
-                        new_texture_desc.Height = surface_desc.Height as u32;
+                        new_texture_desc.Width = staging_width as u32;
+                        new_texture_desc.Height = staging_height as u32;
                         new_texture_desc.MipLevels = 1;
                         new_texture_desc.SampleDesc.Count = 1;
                         new_texture_desc.SampleDesc.Quality = 0;
@@ -411,7 +1159,44 @@ impl VideoFrameBitmapInternal for VideoFrame {
                         let device = self.impl_video_frame.device.GetImmediateContext()
                             .map_err(|_| VideoFrameBitmapError::Other("Couldn't get immediate d3d11 context".to_string()))?;
                         let staging_texture = staging_texture.unwrap();
-                        device.CopyResource(&staging_texture, &surface_texture);
+                        if downscaled_size.is_none() {
+                            device.CopyResource(&staging_texture, &surface_texture);
+                        } else {
+                            let mut mip_texture_desc = D3D11_TEXTURE2D_DESC::default();
+                            mip_texture_desc.CPUAccessFlags = 0;
+                            mip_texture_desc.ArraySize = 1;
+                            mip_texture_desc.BindFlags = (D3D11_BIND_SHADER_RESOURCE.0 | D3D11_BIND_RENDER_TARGET.0) as u32;
+                            mip_texture_desc.MiscFlags = D3D11_RESOURCE_MISC_GENERATE_MIPS.0 as u32;
+                            mip_texture_desc.Width = surface_desc.Width as u32;
+                            mip_texture_desc.Height = surface_desc.Height as u32;
+                            // Sampling down to a size between two levels reads from both, so the whole mip chain is generated then
+                            mip_texture_desc.MipLevels = if read_mip_level { mip_level + 1 } else { 0 };
+                            mip_texture_desc.SampleDesc.Count = 1;
+                            mip_texture_desc.SampleDesc.Quality = 0;
+                            mip_texture_desc.Usage = D3D11_USAGE_DEFAULT;
+                            mip_texture_desc.Format = dxgi_format;
+                            let mut mip_texture = Option::<ID3D11Texture2D>::None;
+                            self.impl_video_frame.device.CreateTexture2D(&mip_texture_desc as *const _, None, Some(&mut mip_texture as *mut _))
+                                .map_err(|error| VideoFrameBitmapError::Other(format!("Failed to create mip texture: {}", error.to_string())))?;
+                            let mip_texture = mip_texture.unwrap();
+                            let mut mip_texture_view = Option::<ID3D11ShaderResourceView>::None;
+                            self.impl_video_frame.device.CreateShaderResourceView(&mip_texture, None, Some(&mut mip_texture_view as *mut _))
+                                .map_err(|error| VideoFrameBitmapError::Other(format!("Failed to create mip texture view: {}", error.to_string())))?;
+                            let mip_texture_view = mip_texture_view.unwrap();
+                            device.CopySubresourceRegion(&mip_texture, 0, 0, 0, 0, &surface_texture, 0, None);
+                            device.GenerateMips(&mip_texture_view);
+                            if read_mip_level {
+                                device.CopySubresourceRegion(&staging_texture, 0, 0, 0, 0, &mip_texture, mip_level, None);
+                            } else {
+                                let frame_scaler = WindowsFrameScaler::new(self.impl_video_frame.device.clone())
+                                    .map_err(VideoFrameBitmapError::Other)?;
+                                let source_rect = Rect { origin: Point::ZERO, size: Size { width: full_size.0 as f64, height: full_size.1 as f64 } };
+                                let output_rect = Rect { origin: Point::ZERO, size: Size { width: width as f64, height: height as f64 } };
+                                let scaled_texture = frame_scaler.scale_texture(&mip_texture, dxgi_format, source_rect, (width, height), output_rect, false)
+                                    .map_err(VideoFrameBitmapError::Other)?;
+                                device.CopyResource(&staging_texture, &scaled_texture);
+                            }
+                        }
                         let mut mapped_resource = D3D11_MAPPED_SUBRESOURCE::default();
                         let map_result = device.Map(&staging_texture, 0, D3D11_MAP_READ, 0, Some(&mut mapped_resource as *mut _));
                         map_result.map_err(|_| VideoFrameBitmapError::Other("Couldn't map staging texture".to_string()))?;
@@ -442,12 +1227,45 @@ impl VideoFrameBitmapInternal for VideoFrame {
                                     bytes_per_row: bpr
                                 };
         
-                                let mapping_result = output_mapping(VideoFrameDataCopyPtrs::ArgbPacked2101010(plane_ptr));
+                                // DXGI packs red into the low bits, so repack to the Argb2101010 layout the bitmap shares with MacOS
+                                let image_data = repack_rgba1010102_to_argb2101010(plane_ptr);
+
+                                let _ = device.Unmap(&staging_texture, 0);
+
+                                output_mapping(VideoFrameDataCopyPtrs::ArgbPacked2101010(placed_plane_ptr(&image_data, width, height)))
+                            },
+                            DirectXPixelFormat::R16G16B16A16UIntNormalized => {
+                                let bpr = mapped_resource.RowPitch as usize;
+
+                                let plane_ptr = VideoFramePlanePtr {
+                                    ptr: mapped_resource.pData as *const c_void,
+                                    width,
+                                    height,
+                                    bytes_per_row: bpr
+                                };
+        
+                                let mapping_result = output_mapping(VideoFrameDataCopyPtrs::Rgba16x4(plane_ptr));
                                 
                                 let _ = device.Unmap(&staging_texture, 0);
                                 
                                 mapping_result
                             },
+                            DirectXPixelFormat::R16G16B16A16Float => {
+                                let bpr = mapped_resource.RowPitch as usize;
+
+                                let plane_ptr = VideoFramePlanePtr {
+                                    ptr: mapped_resource.pData as *const c_void,
+                                    width,
+                                    height,
+                                    bytes_per_row: bpr
+                                };
+
+                                let mapping_result = output_mapping(VideoFrameDataCopyPtrs::RgbaF16x4(plane_ptr));
+
+                                let _ = device.Unmap(&staging_texture, 0);
+
+                                mapping_result
+                            },
                             _ => {
                                 Err(VideoFrameBitmapError::Other("Unknown or unsupported pixel format on DXGISurface".to_string()))
                             }
@@ -458,6 +1276,17 @@ impl VideoFrameBitmapInternal for VideoFrame {
         }
         #[cfg(target_os = "macos")]
         {
+            let output_placement = match &self.impl_video_frame {
+                MacosVideoFrame::SCStream(MacosSCStreamVideoFrame { output_placement: Some((scaling_quality, output_size)), .. }) => {
+                    let native_size = self.impl_video_frame.native_content_size();
+                    Some(OutputPlacement {
+                        source_size: (native_size.width.round() as usize, native_size.height.round() as usize),
+                        content_rect: scaling_quality.content_rect(native_size, *output_size),
+                        output_size: ((output_size.width as usize).max(1), (output_size.height as usize).max(1)),
+                    })
+                },
+                _ => None,
+            };
             let iosurface = match &self.impl_video_frame {
                 MacosVideoFrame::SCStream(sc_frame) => {
                     match sc_frame.sample_buffer.get_image_buffer().map(|image_buffer| image_buffer.get_iosurface()).flatten() {
@@ -469,6 +1298,13 @@ impl VideoFrameBitmapInternal for VideoFrame {
                     cg_display_frame.io_surface.clone()
                 }
             };
+            if let Some((target_size, mode)) = downscale {
+                return downscale_iosurface(&self.impl_video_frame, &iosurface, output_placement, target_size, mode, output_mapping);
+            }
+            let output_mapping = &|copy_ptrs: VideoFrameDataCopyPtrs| match output_placement {
+                Some(output_placement) => placed_output_mapping(copy_ptrs, output_placement, output_mapping),
+                None => output_mapping(copy_ptrs),
+            };
             if let Ok(lock_gaurd) = iosurface.lock(true, false) {
                 let pixel_format = iosurface.get_pixel_format();
                 match pixel_format {
@@ -487,6 +1323,21 @@ impl VideoFrameBitmapInternal for VideoFrame {
 
                         output_mapping(VideoFrameDataCopyPtrs::Bgra8888(plane_ptr))
                     },
+                    Some(CVPixelFormat::ARGB2101010LE) => {
+                        let bpr = iosurface.get_bytes_per_row();
+                        let height = iosurface.get_height();
+                        let width = iosurface.get_width();
+                        let base_address = lock_gaurd.get_base_address().ok_or(VideoFrameBitmapError::Other("Failed to get base address of iosurface".into()))?;
+
+                        let plane_ptr = VideoFramePlanePtr {
+                            ptr: base_address,
+                            width,
+                            height,
+                            bytes_per_row: bpr
+                        };
+
+                        output_mapping(VideoFrameDataCopyPtrs::ArgbPacked2101010(plane_ptr))
+                    },
                     Some(CVPixelFormat::V420) |
                     Some(CVPixelFormat::F420) => {
 
@@ -526,167 +1377,574 @@ impl VideoFrameBitmapInternal for VideoFrame {
                 Err(VideoFrameBitmapError::Other("Failed to lock iosurface".to_string()))
             }
         }
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        {
+            let _ = (downscale, output_mapping);
+            Err(VideoFrameBitmapError::Other("Bitmaps are unsupported on this platform".to_string()))
+        }
     }
-}
 
-fn copy_boxed_slice_plane<T: Sized + Copy + Pod + Zeroable>(plane_ptr: VideoFramePlanePtr) -> Box<[T]> {
-    let mut image_data = vec![T::zeroed(); plane_ptr.width * plane_ptr.height];
-    let src_slice = unsafe { std::slice::from_raw_parts(plane_ptr.ptr as *const u8, plane_ptr.bytes_per_row * plane_ptr.height) };
-    for y in 0..plane_ptr.height {
-        let source_slice = bytemuck::cast_slice::<_, T>(&src_slice[(plane_ptr.bytes_per_row * y)..(plane_ptr.bytes_per_row * y + std::mem::size_of::<T>() * plane_ptr.width)]);
-        image_data[(plane_ptr.width * y)..(plane_ptr.width * y + plane_ptr.width)].copy_from_slice(source_slice);
+    // Only MacOS captures YCbCr frames, with the stream's configured matrix
+    fn ycbcr_color_matrix(&self) -> YCbCrColorMatrix {
+        #[cfg(target_os = "macos")]
+        {
+            match &self.impl_video_frame {
+                MacosVideoFrame::SCStream(frame) => frame.color_matrix.into(),
+                MacosVideoFrame::CGDisplayStream(frame) => frame.color_matrix.into(),
+            }
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            YCbCrColorMatrix::ItuR709
+        }
     }
-    image_data.into_boxed_slice()
 }
 
-fn copy_pooled_plane<T: Sized + Copy + Pod + Zeroable>(plane_ptr: VideoFramePlanePtr, pool: &Arc<BitmapPool<T>>) -> PooledBitmap<T> {
-    let mut bitmap = pool.get_bitmap((plane_ptr.width, plane_ptr.height));
+// Downscale the planes of a frame's IOSurface on the GPU, then map them to a bitmap - content placed into the output
+// (see `OutputPlacement`) is scaled to its downscaled content rect, then placed into the downscaled output
+#[cfg(target_os = "macos")]
+fn downscale_iosurface<T>(frame: &MacosVideoFrame, iosurface: &IOSurface, output_placement: Option<OutputPlacement>, target_size: Size, mode: DownscaleMode, output_mapping: &impl Fn(VideoFrameDataCopyPtrs) -> Result<T, VideoFrameBitmapError>) -> Result<T, VideoFrameBitmapError> {
+    let pixel_format = iosurface.get_pixel_format();
+    let (metal_pixel_format, bytes_per_pixel, planar) = match pixel_format {
+        Some(CVPixelFormat::BGRA8888) => (metal::MTLPixelFormat::BGRA8Unorm, 4, false),
+        Some(CVPixelFormat::ARGB2101010LE) => (metal::MTLPixelFormat::BGR10A2Unorm, 4, false),
+        Some(CVPixelFormat::V420) | Some(CVPixelFormat::F420) => (metal::MTLPixelFormat::R8Unorm, 1, true),
+        _ => return Err(VideoFrameBitmapError::Other("Unknown pixel format on iosurface".to_string())),
+    };
+    let plane_size = if planar {
+        (iosurface.get_width_of_plane(0), iosurface.get_height_of_plane(0))
+    } else {
+        (iosurface.get_width(), iosurface.get_height())
+    };
+    let output_size = downscaled_size(output_placement.map_or(plane_size, |placement| placement.output_size), target_size, mode);
+    let placement = output_placement.map(|output_placement| output_placement.downscaled(output_size));
+    let source_size = output_placement.map_or(plane_size, |output_placement| (output_placement.source_size.0.min(plane_size.0), output_placement.source_size.1.min(plane_size.1)));
+    let scaled_size = placement.map_or(output_size, |placement| placement.source_size);
+    let mut planes = vec![MacosFrameScalerPlane {
+        plane: 0,
+        pixel_format: metal_pixel_format,
+        bytes_per_pixel,
+        plane_size,
+        source_size,
+        target_size: scaled_size,
+    }];
+    if planar {
+        let chroma_plane_size = (iosurface.get_width_of_plane(1), iosurface.get_height_of_plane(1));
+        planes.push(MacosFrameScalerPlane {
+            plane: 1,
+            pixel_format: metal::MTLPixelFormat::RG8Unorm,
+            bytes_per_pixel: 2,
+            plane_size: chroma_plane_size,
+            source_size: scaled_chroma_size(plane_size, chroma_plane_size, source_size),
+            target_size: scaled_chroma_size(plane_size, chroma_plane_size, scaled_size),
+        });
+    }
+    let metal_device = match frame {
+        MacosVideoFrame::SCStream(frame) => frame.metal_device.clone(),
+        MacosVideoFrame::CGDisplayStream(frame) => Some(frame.metal_device.clone()),
+    }.or_else(metal::Device::system_default)
+        .ok_or_else(|| VideoFrameBitmapError::Other("No metal device to downscale the frame with".to_string()))?;
+    let scaled_planes = MacosFrameScaler::new(metal_device)
+        .and_then(|frame_scaler| frame_scaler.scale(iosurface, &planes))
+        .map_err(VideoFrameBitmapError::Other)?;
+    let plane_ptrs: Vec<VideoFramePlanePtr> = scaled_planes.iter().map(|scaled_plane| VideoFramePlanePtr {
+        ptr: scaled_plane.contents(),
+        width: scaled_plane.width,
+        height: scaled_plane.height,
+        bytes_per_row: scaled_plane.bytes_per_row,
+    }).collect();
+    let copy_ptrs = match pixel_format {
+        Some(CVPixelFormat::BGRA8888) => VideoFrameDataCopyPtrs::Bgra8888(plane_ptrs[0]),
+        Some(CVPixelFormat::ARGB2101010LE) => VideoFrameDataCopyPtrs::ArgbPacked2101010(plane_ptrs[0]),
+        Some(CVPixelFormat::V420) => VideoFrameDataCopyPtrs::V420 { luma: plane_ptrs[0], chroma: plane_ptrs[1] },
+        _ => VideoFrameDataCopyPtrs::F420 { luma: plane_ptrs[0], chroma: plane_ptrs[1] },
+    };
+    match placement {
+        Some(placement) => placed_output_mapping(copy_ptrs, placement, output_mapping),
+        None => output_mapping(copy_ptrs),
+    }
+}
+
+// Where the native size content of a frame goes in its output, for scaling qualities other than smooth
+#[cfg(any(target_os = "macos", test))]
+#[derive(Copy, Clone)]
+struct OutputPlacement {
+    // The size of the content in the top-left of the plane
+    source_size: (usize, usize),
+    content_rect: Rect,
+    output_size: (usize, usize),
+}
+
+#[cfg(any(target_os = "macos", test))]
+impl OutputPlacement {
+    // The placement for a subsampled plane, such as a chroma plane
+    fn subsampled(&self, plane: VideoFramePlanePtr, full_plane: VideoFramePlanePtr) -> Self {
+        let scale_x = plane.width as f64 / full_plane.width.max(1) as f64;
+        let scale_y = plane.height as f64 / full_plane.height.max(1) as f64;
+        Self {
+            source_size: ((self.source_size.0 as f64 * scale_x).ceil() as usize, (self.source_size.1 as f64 * scale_y).ceil() as usize),
+            content_rect: self.content_rect.scaled_2d((scale_x, scale_y)),
+            output_size: (((self.output_size.0 as f64 * scale_x).ceil() as usize).max(1), ((self.output_size.1 as f64 * scale_y).ceil() as usize).max(1)),
+        }
+    }
+
+    // The placement in an output downscaled to the given size, of content which is already scaled to the size of its downscaled content rect
+    fn downscaled(&self, output_size: (usize, usize)) -> Self {
+        let scale_x = output_size.0 as f64 / self.output_size.0.max(1) as f64;
+        let scale_y = output_size.1 as f64 / self.output_size.1.max(1) as f64;
+        let content_rect = self.content_rect.scaled_2d((scale_x, scale_y));
+        Self {
+            source_size: ((content_rect.size.width.round() as usize).max(1), (content_rect.size.height.round() as usize).max(1)),
+            content_rect,
+            output_size,
+        }
+    }
+}
+
+// Copy the content of a plane into content_rect of a new plane of the output size with nearest-neighbor sampling, padding the rest
+#[cfg(any(target_os = "macos", test))]
+fn place_plane<T: BitmapPixel>(plane_ptr: VideoFramePlanePtr, placement: OutputPlacement, padding: T) -> Vec<T> {
+    let (output_width, output_height) = placement.output_size;
+    let mut image_data = vec![padding; output_width * output_height];
+    let source_width = placement.source_size.0.min(plane_ptr.width);
+    let source_height = placement.source_size.1.min(plane_ptr.height);
+    let rect = placement.content_rect;
+    if source_width == 0 || source_height == 0 || rect.size.width <= 0.0 || rect.size.height <= 0.0 {
+        return image_data;
+    }
+    let src_slice = unsafe { std::slice::from_raw_parts(plane_ptr.ptr as *const u8, plane_ptr.bytes_per_row * plane_ptr.height) };
+    let x_start = rect.origin.x.max(0.0) as usize;
+    let x_end = (rect.origin.x + rect.size.width).clamp(0.0, output_width as f64) as usize;
+    let y_start = rect.origin.y.max(0.0) as usize;
+    let y_end = (rect.origin.y + rect.size.height).clamp(0.0, output_height as f64) as usize;
+    for y in y_start..y_end {
+        // Sample at pixel centers, so whole number scale factors repeat each source pixel exactly
+        let src_y = (((y as f64 + 0.5 - rect.origin.y) * source_height as f64 / rect.size.height) as usize).min(source_height - 1);
+        let row = bytemuck::cast_slice::<_, T>(&src_slice[(plane_ptr.bytes_per_row * src_y)..(plane_ptr.bytes_per_row * src_y + std::mem::size_of::<T>() * plane_ptr.width)]);
+        for x in x_start..x_end {
+            let src_x = (((x as f64 + 0.5 - rect.origin.x) * source_width as f64 / rect.size.width) as usize).min(source_width - 1);
+            image_data[output_width * y + x] = row[src_x];
+        }
+    }
+    image_data
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn placed_plane_ptr<T>(image_data: &[T], width: usize, height: usize) -> VideoFramePlanePtr {
+    VideoFramePlanePtr {
+        ptr: image_data.as_ptr() as *const c_void,
+        width,
+        height,
+        bytes_per_row: std::mem::size_of::<T>() * width,
+    }
+}
+
+// Swap the red and blue channels of each R10G10B10A2 pixel of a plane, giving tightly packed Argb2101010 rows
+#[cfg(any(target_os = "windows", test))]
+fn repack_rgba1010102_to_argb2101010(plane_ptr: VideoFramePlanePtr) -> Vec<u32> {
+    let mut image_data = Vec::with_capacity(plane_ptr.width * plane_ptr.height);
     let src_slice = unsafe { std::slice::from_raw_parts(plane_ptr.ptr as *const u8, plane_ptr.bytes_per_row * plane_ptr.height) };
     for y in 0..plane_ptr.height {
-        let source_slice = bytemuck::cast_slice::<_, T>(&src_slice[(plane_ptr.bytes_per_row * y)..(plane_ptr.bytes_per_row * y + std::mem::size_of::<T>() * plane_ptr.width)]);
-        AsMut::as_mut(&mut bitmap)[(plane_ptr.width * y)..(plane_ptr.width * y + plane_ptr.width)].copy_from_slice(source_slice);
+        let row = &src_slice[(plane_ptr.bytes_per_row * y)..(plane_ptr.bytes_per_row * y + 4 * plane_ptr.width)];
+        image_data.extend(row.chunks_exact(4).map(|bytes| {
+            let pixel = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            (pixel & 0xC00FFC00) | ((pixel & 0x3FF) << 20) | ((pixel >> 20) & 0x3FF)
+        }));
+    }
+    image_data
+}
+
+// Place the content of each plane into the output (padding with opaque black) before mapping it to a bitmap
+#[cfg(target_os = "macos")]
+fn placed_output_mapping<T>(copy_ptrs: VideoFrameDataCopyPtrs, placement: OutputPlacement, output_mapping: &impl Fn(VideoFrameDataCopyPtrs) -> Result<T, VideoFrameBitmapError>) -> Result<T, VideoFrameBitmapError> {
+    let (width, height) = placement.output_size;
+    match copy_ptrs {
+        VideoFrameDataCopyPtrs::Bgra8888(plane_ptr) => {
+            let image_data = place_plane(plane_ptr, placement, [0u8, 0, 0, 255]);
+            output_mapping(VideoFrameDataCopyPtrs::Bgra8888(placed_plane_ptr(&image_data, width, height)))
+        },
+        VideoFrameDataCopyPtrs::ArgbPacked2101010(plane_ptr) => {
+            let image_data = place_plane(plane_ptr, placement, 0x3u32 << 30);
+            output_mapping(VideoFrameDataCopyPtrs::ArgbPacked2101010(placed_plane_ptr(&image_data, width, height)))
+        },
+        VideoFrameDataCopyPtrs::RgbaF16x4(plane_ptr) => {
+            let image_data = place_plane(plane_ptr, placement, [f16::ZERO, f16::ZERO, f16::ZERO, f16::ONE]);
+            output_mapping(VideoFrameDataCopyPtrs::RgbaF16x4(placed_plane_ptr(&image_data, width, height)))
+        },
+        VideoFrameDataCopyPtrs::Rgba16x4(plane_ptr) => {
+            let image_data = place_plane(plane_ptr, placement, [0u16, 0, 0, u16::MAX]);
+            output_mapping(VideoFrameDataCopyPtrs::Rgba16x4(placed_plane_ptr(&image_data, width, height)))
+        },
+        VideoFrameDataCopyPtrs::F420 { luma, chroma } |
+        VideoFrameDataCopyPtrs::V420 { luma, chroma } => {
+            let video_range = matches!(copy_ptrs, VideoFrameDataCopyPtrs::V420 { .. });
+            let chroma_placement = placement.subsampled(chroma, luma);
+            let luma_data = place_plane(luma, placement, if video_range { 16u8 } else { 0u8 });
+            let chroma_data = place_plane(chroma, chroma_placement, [128u8, 128]);
+            let luma = placed_plane_ptr(&luma_data, width, height);
+            let chroma = placed_plane_ptr(&chroma_data, chroma_placement.output_size.0, chroma_placement.output_size.1);
+            if video_range {
+                output_mapping(VideoFrameDataCopyPtrs::V420 { luma, chroma })
+            } else {
+                output_mapping(VideoFrameDataCopyPtrs::F420 { luma, chroma })
+            }
+        },
     }
-    bitmap
 }
 
-fn try_copy_pooled_plane<T: Sized + Copy + Pod + Zeroable>(plane_ptr: VideoFramePlanePtr, pool: &Arc<BitmapPool<T>>) -> Option<PooledBitmap<T>> {
-    let mut bitmap = pool.try_get_bitmap((plane_ptr.width, plane_ptr.height))?;
+fn copy_boxed_slice_plane<T: Sized + Copy + Pod + Zeroable>(plane_ptr: VideoFramePlanePtr) -> Box<[T]> {
+    let mut image_data = vec![T::zeroed(); plane_ptr.width * plane_ptr.height];
     let src_slice = unsafe { std::slice::from_raw_parts(plane_ptr.ptr as *const u8, plane_ptr.bytes_per_row * plane_ptr.height) };
     for y in 0..plane_ptr.height {
         let source_slice = bytemuck::cast_slice::<_, T>(&src_slice[(plane_ptr.bytes_per_row * y)..(plane_ptr.bytes_per_row * y + std::mem::size_of::<T>() * plane_ptr.width)]);
-        AsMut::as_mut(&mut bitmap)[(plane_ptr.width * y)..(plane_ptr.width * y + plane_ptr.width)].copy_from_slice(source_slice);
+        image_data[(plane_ptr.width * y)..(plane_ptr.width * y + plane_ptr.width)].copy_from_slice(source_slice);
+    }
+    image_data.into_boxed_slice()
+}
+
+fn copy_pooled_plane<T: BitmapPixel>(plane_ptr: VideoFramePlanePtr, pool: &Arc<BitmapPool<T>>, change_metric: bool) -> (PooledBitmap<T>, Option<BitmapChangeMetric>) {
+    let resolution = (plane_ptr.width, plane_ptr.height);
+    let (mut bitmap, holds_previous) = match change_metric {
+        true => pool.get_measured_bitmap(resolution, true).expect("Waiting for a measured bitmap always returns one"),
+        false => (pool.get_bitmap(resolution), false),
+    };
+    let metric = copy_plane_rows(plane_ptr, AsMut::as_mut(&mut bitmap), change_metric.then_some(holds_previous));
+    (bitmap, metric)
+}
+
+fn try_copy_pooled_plane<T: BitmapPixel>(plane_ptr: VideoFramePlanePtr, pool: &Arc<BitmapPool<T>>, change_metric: bool) -> Option<(PooledBitmap<T>, Option<BitmapChangeMetric>)> {
+    let resolution = (plane_ptr.width, plane_ptr.height);
+    let (mut bitmap, holds_previous) = match change_metric {
+        true => pool.get_measured_bitmap(resolution, false)?,
+        false => (pool.try_get_bitmap(resolution)?, false),
+    };
+    let metric = copy_plane_rows(plane_ptr, AsMut::as_mut(&mut bitmap), change_metric.then_some(holds_previous));
+    Some((bitmap, metric))
+}
+
+// Copy the rows of a plane into a bitmap - when measuring a change metric, `holds_previous` is whether the bitmap
+// still holds the previous frame, which each row is compared against before it's overwritten
+fn copy_plane_rows<T: BitmapPixel>(plane_ptr: VideoFramePlanePtr, dest: &mut [T], holds_previous: Option<bool>) -> Option<BitmapChangeMetric> {
+    let (width, height) = (plane_ptr.width, plane_ptr.height);
+    let src_slice = unsafe { std::slice::from_raw_parts(plane_ptr.ptr as *const u8, plane_ptr.bytes_per_row * height) };
+    let comparable = holds_previous == Some(true);
+    let mut changed_rows = 0;
+    let mut delta_sum = 0.0f64;
+    for y in 0..height {
+        let source_slice = bytemuck::cast_slice::<_, T>(&src_slice[(plane_ptr.bytes_per_row * y)..(plane_ptr.bytes_per_row * y + std::mem::size_of::<T>() * width)]);
+        let dest_row = &mut dest[(width * y)..(width * y + width)];
+        // Only rows that differ are compared per-channel
+        if comparable && bytemuck::cast_slice::<_, u8>(&*dest_row) != bytemuck::cast_slice::<_, u8>(source_slice) {
+            changed_rows += 1;
+            delta_sum += row_abs_delta(dest_row, source_slice);
+        }
+        dest_row.copy_from_slice(source_slice);
+    }
+    holds_previous?;
+    if !comparable {
+        return Some(BitmapChangeMetric {
+            changed_rows: height as u32,
+            mean_abs_delta: 1.0,
+        });
+    }
+    let channel_count = (width * height * T::CHANNELS) as f64;
+    Some(BitmapChangeMetric {
+        changed_rows,
+        mean_abs_delta: if channel_count > 0.0 { (delta_sum / channel_count) as f32 } else { 0.0 },
+    })
+}
+
+fn row_abs_delta<T: BitmapPixel>(previous_row: &[T], row: &[T]) -> f64 {
+    let mut sum = 0.0f64;
+    for (previous_pixel, pixel) in previous_row.iter().zip(row.iter()) {
+        let previous_components = previous_pixel.to_components();
+        let components = pixel.to_components();
+        for i in 0..T::CHANNELS {
+            sum += ((components[i] - previous_components[i]).abs() / T::COMPONENT_MAX[i]) as f64;
+        }
+    }
+    sum
+}
+
+// The size a frame of the given size is downscaled to by `downscale_to_bitmap` - never larger than the frame
+fn downscaled_size(size: (usize, usize), target_size: Size, mode: DownscaleMode) -> (usize, usize) {
+    let target_width = target_size.width.max(1.0);
+    let target_height = target_size.height.max(1.0);
+    let (width, height) = match mode {
+        DownscaleMode::Stretch => (target_width.min(size.0 as f64), target_height.min(size.1 as f64)),
+        DownscaleMode::PreserveAspect => {
+            let scale = (target_width / size.0 as f64).min(target_height / size.1 as f64).min(1.0);
+            (size.0 as f64 * scale, size.1 as f64 * scale)
+        }
+    };
+    ((width.round() as usize).max(1), (height.round() as usize).max(1))
+}
+
+// The size of a subsampled chroma plane once its luma plane is scaled to the given size, rounding partial chroma samples up
+#[cfg(any(target_os = "macos", test))]
+fn scaled_chroma_size(luma_size: (usize, usize), chroma_size: (usize, usize), scaled_luma_size: (usize, usize)) -> (usize, usize) {
+    (
+        ((scaled_luma_size.0 * chroma_size.0).div_ceil(luma_size.0.max(1))).max(1),
+        ((scaled_luma_size.1 * chroma_size.1).div_ceil(luma_size.1.max(1))).max(1),
+    )
+}
+
+trait BitmapPixel: Sized + Copy + Pod + Zeroable {
+    /// The number of meaningful components returned by `to_components()`
+    const CHANNELS: usize;
+    /// The largest value of each component returned by `to_components()`
+    const COMPONENT_MAX: [f32; 4];
+
+    fn to_components(self) -> [f32; 4];
+}
+
+impl BitmapPixel for u8 {
+    const CHANNELS: usize = 1;
+    const COMPONENT_MAX: [f32; 4] = [255.0, 1.0, 1.0, 1.0];
+
+    fn to_components(self) -> [f32; 4] {
+        [self as f32, 0.0, 0.0, 0.0]
+    }
+}
+
+impl BitmapPixel for [u8; 2] {
+    const CHANNELS: usize = 2;
+    const COMPONENT_MAX: [f32; 4] = [255.0, 255.0, 1.0, 1.0];
+
+    fn to_components(self) -> [f32; 4] {
+        [self[0] as f32, self[1] as f32, 0.0, 0.0]
+    }
+}
+
+impl BitmapPixel for [u8; 4] {
+    const CHANNELS: usize = 4;
+    const COMPONENT_MAX: [f32; 4] = [255.0; 4];
+
+    fn to_components(self) -> [f32; 4] {
+        self.map(|x| x as f32)
+    }
+}
+
+impl BitmapPixel for u32 {
+    const CHANNELS: usize = 4;
+    const COMPONENT_MAX: [f32; 4] = [3.0, 1023.0, 1023.0, 1023.0];
+
+    fn to_components(self) -> [f32; 4] {
+        [
+            ((self >> 30) & 0x3) as f32,
+            ((self >> 20) & 0x3FF) as f32,
+            ((self >> 10) & 0x3FF) as f32,
+            (self & 0x3FF) as f32,
+        ]
+    }
+}
+
+impl BitmapPixel for [u16; 4] {
+    const CHANNELS: usize = 4;
+    const COMPONENT_MAX: [f32; 4] = [65535.0; 4];
+
+    fn to_components(self) -> [f32; 4] {
+        self.map(|x| x as f32)
+    }
+}
+
+impl BitmapPixel for [f16; 4] {
+    const CHANNELS: usize = 4;
+    const COMPONENT_MAX: [f32; 4] = [1.0; 4];
+
+    fn to_components(self) -> [f32; 4] {
+        self.map(|x| x.to_f32())
+    }
+}
+
+// Copy the planes of a frame into a new bitmap
+fn boxed_slice_bitmap(copy_ptrs: VideoFrameDataCopyPtrs, color_matrix: YCbCrColorMatrix) -> Result<BoxedSliceFrameBitmap, VideoFrameBitmapError> {
+    match copy_ptrs {
+        VideoFrameDataCopyPtrs::Bgra8888(bgra_plane_ptr) => {
+            Ok(BoxedSliceFrameBitmap::BgraUnorm8x4(FrameBitmapBgraUnorm8x4 {
+                data: copy_boxed_slice_plane(bgra_plane_ptr),
+                width: bgra_plane_ptr.width,
+                height: bgra_plane_ptr.height,
+                change_metric: None,
+            }))
+        },
+        VideoFrameDataCopyPtrs::ArgbPacked2101010(argb_plane_ptr) => {
+            Ok(BoxedSliceFrameBitmap::ArgbUnormPacked2101010(FrameBitmapArgbUnormPacked2101010 {
+                data: copy_boxed_slice_plane(argb_plane_ptr),
+                width: argb_plane_ptr.width,
+                height: argb_plane_ptr.height,
+                change_metric: None,
+            }))
+        },
+        VideoFrameDataCopyPtrs::F420 { luma: luma_plane_ptr, chroma: chroma_plane_ptr } => {
+            Ok(BoxedSliceFrameBitmap::YCbCr(FrameBitmapYCbCr {
+                luma_data: copy_boxed_slice_plane(luma_plane_ptr),
+                luma_width: luma_plane_ptr.width,
+                luma_height: luma_plane_ptr.height,
+                chroma_data: copy_boxed_slice_plane(chroma_plane_ptr),
+                chroma_width: chroma_plane_ptr.width,
+                chroma_height: chroma_plane_ptr.height,
+                range: VideoRange::Full,
+                color_matrix,
+                change_metric: None,
+            }))
+        },
+        VideoFrameDataCopyPtrs::V420 { luma: luma_plane_ptr, chroma: chroma_plane_ptr } => {
+            Ok(BoxedSliceFrameBitmap::YCbCr(FrameBitmapYCbCr {
+                luma_data: copy_boxed_slice_plane(luma_plane_ptr),
+                luma_width: luma_plane_ptr.width,
+                luma_height: luma_plane_ptr.height,
+                chroma_data: copy_boxed_slice_plane(chroma_plane_ptr),
+                chroma_width: chroma_plane_ptr.width,
+                chroma_height: chroma_plane_ptr.height,
+                range: VideoRange::Video,
+                color_matrix,
+                change_metric: None,
+            }))
+        },
+        VideoFrameDataCopyPtrs::RgbaF16x4(rgba_plane_ptr) => {
+            Ok(BoxedSliceFrameBitmap::RgbaF16x4(FrameBitmapRgbaF16x4 {
+                data: copy_boxed_slice_plane(rgba_plane_ptr),
+                width: rgba_plane_ptr.width,
+                height: rgba_plane_ptr.height,
+                change_metric: None,
+            }))
+        },
+        VideoFrameDataCopyPtrs::Rgba16x4(rgba_plane_ptr) => {
+            Ok(BoxedSliceFrameBitmap::RgbaUnorm16x4(FrameBitmapRgba16x4 {
+                data: copy_boxed_slice_plane(rgba_plane_ptr),
+                width: rgba_plane_ptr.width,
+                height: rgba_plane_ptr.height,
+                change_metric: None,
+            }))
+        }
     }
-    Some(bitmap)
 }
 
 impl VideoFrameBitmap for VideoFrame {
+    fn downscale_to_bitmap(&self, target_size: Size, mode: DownscaleMode) -> Result<BoxedSliceFrameBitmap, VideoFrameBitmapError> {
+        self.get_bitmap_internal::<BoxedSliceFrameBitmap>(Some((target_size, mode)), &|copy_ptrs| boxed_slice_bitmap(copy_ptrs, self.ycbcr_color_matrix()))
+    }
+
     fn get_bitmap(&self) -> Result<BoxedSliceFrameBitmap, VideoFrameBitmapError> {
-        self.get_bitmap_internal::<BoxedSliceFrameBitmap>(&|copy_ptrs| {
-            match copy_ptrs {
-                VideoFrameDataCopyPtrs::Bgra8888(bgra_plane_ptr) => {
-                    Ok(BoxedSliceFrameBitmap::BgraUnorm8x4(FrameBitmapBgraUnorm8x4 {
-                        data: copy_boxed_slice_plane(bgra_plane_ptr),
-                        width: bgra_plane_ptr.width,
-                        height: bgra_plane_ptr.height,
-                    }))
-                },
-                VideoFrameDataCopyPtrs::ArgbPacked2101010(argb_plane_ptr) => {
-                    Ok(BoxedSliceFrameBitmap::ArgbUnormPacked2101010(FrameBitmapArgbUnormPacked2101010 {
-                        data: copy_boxed_slice_plane(argb_plane_ptr),
-                        width: argb_plane_ptr.width,
-                        height: argb_plane_ptr.height,
-                    }))
-                },
-                VideoFrameDataCopyPtrs::F420 { luma: luma_plane_ptr, chroma: chroma_plane_ptr } => {
-                    Ok(BoxedSliceFrameBitmap::YCbCr(FrameBitmapYCbCr {
-                        luma_data: copy_boxed_slice_plane(luma_plane_ptr),
-                        luma_width: luma_plane_ptr.width,
-                        luma_height: luma_plane_ptr.height,
-                        chroma_data: copy_boxed_slice_plane(chroma_plane_ptr),
-                        chroma_width: chroma_plane_ptr.width,
-                        chroma_height: chroma_plane_ptr.height,
-                        range: VideoRange::Full
-                    }))
-                },
-                VideoFrameDataCopyPtrs::V420 { luma: luma_plane_ptr, chroma: chroma_plane_ptr } => {
-                    Ok(BoxedSliceFrameBitmap::YCbCr(FrameBitmapYCbCr {
-                        luma_data: copy_boxed_slice_plane(luma_plane_ptr),
-                        luma_width: luma_plane_ptr.width,
-                        luma_height: luma_plane_ptr.height,
-                        chroma_data: copy_boxed_slice_plane(chroma_plane_ptr),
-                        chroma_width: chroma_plane_ptr.width,
-                        chroma_height: chroma_plane_ptr.height,
-                        range: VideoRange::Video
-                    }))
-                },
-                VideoFrameDataCopyPtrs::RgbaF16x4(rgba_plane_ptr) => {
-                    Ok(BoxedSliceFrameBitmap::RgbaF16x4(FrameBitmapRgbaF16x4 {
-                        data: copy_boxed_slice_plane(rgba_plane_ptr),
-                        width: rgba_plane_ptr.width,
-                        height: rgba_plane_ptr.height,
-                    }))
-                }
-            }
-        })
+        self.get_bitmap_internal::<BoxedSliceFrameBitmap>(None, &|copy_ptrs| boxed_slice_bitmap(copy_ptrs, self.ycbcr_color_matrix()))
     }
 
     fn get_pooled_bitmap(&self, bitmap_pool: &FrameBitmapPool) -> Result<PooledFrameBitmap, VideoFrameBitmapError> {
-        self.get_bitmap_internal::<PooledFrameBitmap>(&|copy_ptrs| {
+        self.get_bitmap_internal::<PooledFrameBitmap>(None, &|copy_ptrs| {
             match copy_ptrs {
                 VideoFrameDataCopyPtrs::Bgra8888(bgra_plane_ptr) => {
+                    let (data, change_metric) = copy_pooled_plane(bgra_plane_ptr, &bitmap_pool.bgra_u8x4, self.change_metric);
                     Ok(PooledFrameBitmap::BgraUnorm8x4(FrameBitmapBgraUnorm8x4 {
-                        data: copy_pooled_plane(bgra_plane_ptr, &bitmap_pool.bgra_u8x4),
+                        data,
                         width: bgra_plane_ptr.width,
                         height: bgra_plane_ptr.height,
+                        change_metric,
                     }))
                 },
                 VideoFrameDataCopyPtrs::ArgbPacked2101010(argb_plane_ptr) => {
+                    let (data, change_metric) = copy_pooled_plane(argb_plane_ptr, &bitmap_pool.argb_packed_2101010, self.change_metric);
                     Ok(PooledFrameBitmap::ArgbUnormPacked2101010(FrameBitmapArgbUnormPacked2101010 {
-                        data: copy_pooled_plane(argb_plane_ptr, &bitmap_pool.argb_packed_2101010),
+                        data,
                         width: argb_plane_ptr.width,
                         height: argb_plane_ptr.height,
+                        change_metric,
                     }))
                 },
                 VideoFrameDataCopyPtrs::F420 { luma: luma_plane_ptr, chroma: chroma_plane_ptr } => {
+                    let (luma_data, change_metric) = copy_pooled_plane(luma_plane_ptr, &bitmap_pool.luma, self.change_metric);
+                    let (chroma_data, _) = copy_pooled_plane(chroma_plane_ptr, &bitmap_pool.chroma, false);
                     Ok(PooledFrameBitmap::YCbCr(FrameBitmapYCbCr {
-                        luma_data: copy_pooled_plane(luma_plane_ptr, &bitmap_pool.luma),
+                        luma_data,
                         luma_width: luma_plane_ptr.width,
                         luma_height: luma_plane_ptr.height,
-                        chroma_data: copy_pooled_plane(chroma_plane_ptr, &bitmap_pool.chroma),
+                        chroma_data,
                         chroma_width: chroma_plane_ptr.width,
                         chroma_height: chroma_plane_ptr.height,
-                        range: VideoRange::Full
+                        range: VideoRange::Full,
+                        color_matrix: self.ycbcr_color_matrix(),
+                        change_metric,
                     }))
                 },
                 VideoFrameDataCopyPtrs::V420 { luma: luma_plane_ptr, chroma: chroma_plane_ptr } => {
+                    let (luma_data, change_metric) = copy_pooled_plane(luma_plane_ptr, &bitmap_pool.luma, self.change_metric);
+                    let (chroma_data, _) = copy_pooled_plane(chroma_plane_ptr, &bitmap_pool.chroma, false);
                     Ok(PooledFrameBitmap::YCbCr(FrameBitmapYCbCr {
-                        luma_data: copy_pooled_plane(luma_plane_ptr, &bitmap_pool.luma),
+                        luma_data,
                         luma_width: luma_plane_ptr.width,
                         luma_height: luma_plane_ptr.height,
-                        chroma_data: copy_pooled_plane(chroma_plane_ptr, &bitmap_pool.chroma),
+                        chroma_data,
                         chroma_width: chroma_plane_ptr.width,
                         chroma_height: chroma_plane_ptr.height,
-                        range: VideoRange::Video
+                        range: VideoRange::Video,
+                        color_matrix: self.ycbcr_color_matrix(),
+                        change_metric,
                     }))
                 },
                 VideoFrameDataCopyPtrs::RgbaF16x4(rgba_plane_ptr) => {
+                    let (data, change_metric) = copy_pooled_plane(rgba_plane_ptr, &bitmap_pool.rgba_f16x4, self.change_metric);
                     Ok(PooledFrameBitmap::RgbaF16x4(FrameBitmapRgbaF16x4 {
-                        data: copy_pooled_plane(rgba_plane_ptr, &bitmap_pool.rgba_f16x4),
+                        data,
                         width: rgba_plane_ptr.width,
                         height: rgba_plane_ptr.height,
+                        change_metric,
+                    }))
+                },
+                VideoFrameDataCopyPtrs::Rgba16x4(rgba_plane_ptr) => {
+                    let (data, change_metric) = copy_pooled_plane(rgba_plane_ptr, &bitmap_pool.rgba_u16x4, self.change_metric);
+                    Ok(PooledFrameBitmap::RgbaUnorm16x4(FrameBitmapRgba16x4 {
+                        data,
+                        width: rgba_plane_ptr.width,
+                        height: rgba_plane_ptr.height,
+                        change_metric,
                     }))
                 }
             }
         })
     }
 
+    fn get_bitmap_async(self) -> impl Future<Output = Result<BoxedSliceFrameBitmap, VideoFrameBitmapError>> + Send + 'static {
+        let (tx, rx) = oneshot::channel();
+        conversion_thread_pool().spawn(move || {
+            let _ = tx.send(self.get_bitmap());
+        });
+        async move {
+            match rx.await {
+                Ok(result) => result,
+                Err(_) => Err(VideoFrameBitmapError::Other("Bitmap conversion was dropped by the conversion thread pool".to_string())),
+            }
+        }
+    }
+
     fn try_get_pooled_bitmap(&self, bitmap_pool: &FrameBitmapPool) -> Result<Option<PooledFrameBitmap>, VideoFrameBitmapError> {
-        self.get_bitmap_internal::<Option<PooledFrameBitmap>>(&|copy_ptrs| {
+        self.get_bitmap_internal::<Option<PooledFrameBitmap>>(None, &|copy_ptrs| {
             match copy_ptrs {
                 VideoFrameDataCopyPtrs::Bgra8888(bgra_plane_ptr) => {
-                    if let Some(data) = try_copy_pooled_plane(bgra_plane_ptr, &bitmap_pool.bgra_u8x4) {
+                    if let Some((data, change_metric)) = try_copy_pooled_plane(bgra_plane_ptr, &bitmap_pool.bgra_u8x4, self.change_metric) {
                         Ok(Some(PooledFrameBitmap::BgraUnorm8x4(FrameBitmapBgraUnorm8x4 {
                             data,
                             width: bgra_plane_ptr.width,
                             height: bgra_plane_ptr.height,
+                            change_metric,
                         })))
                     } else {
                         Ok(None)
                     }
                 },
                 VideoFrameDataCopyPtrs::ArgbPacked2101010(argb_plane_ptr) => {
-                    if let Some(data) = try_copy_pooled_plane(argb_plane_ptr, &bitmap_pool.argb_packed_2101010) {
+                    if let Some((data, change_metric)) = try_copy_pooled_plane(argb_plane_ptr, &bitmap_pool.argb_packed_2101010, self.change_metric) {
                         Ok(Some(PooledFrameBitmap::ArgbUnormPacked2101010(FrameBitmapArgbUnormPacked2101010 {
                             data,
                             width: argb_plane_ptr.width,
                             height: argb_plane_ptr.height,
+                            change_metric,
                         })))
                     } else {
                         Ok(None)
                     }
                 },
                 VideoFrameDataCopyPtrs::F420 { luma: luma_plane_ptr, chroma: chroma_plane_ptr } => {
-                    if let (Some(luma_data), Some(chroma_data)) = (try_copy_pooled_plane(luma_plane_ptr, &bitmap_pool.luma), try_copy_pooled_plane(chroma_plane_ptr, &bitmap_pool.chroma)) {
+                    if let (Some((luma_data, change_metric)), Some((chroma_data, _))) = (try_copy_pooled_plane(luma_plane_ptr, &bitmap_pool.luma, self.change_metric), try_copy_pooled_plane(chroma_plane_ptr, &bitmap_pool.chroma, false)) {
                         Ok(Some(PooledFrameBitmap::YCbCr(FrameBitmapYCbCr {
                             luma_data,
                             luma_width: luma_plane_ptr.width,
@@ -694,7 +1952,9 @@ impl VideoFrameBitmap for VideoFrame {
                             chroma_data,
                             chroma_width: chroma_plane_ptr.width,
                             chroma_height: chroma_plane_ptr.height,
-                            range: VideoRange::Full
+                            range: VideoRange::Full,
+                            color_matrix: self.ycbcr_color_matrix(),
+                            change_metric,
                         })))
                     } else {
                         Ok(None)
@@ -702,7 +1962,7 @@ impl VideoFrameBitmap for VideoFrame {
                     
                 },
                 VideoFrameDataCopyPtrs::V420 { luma: luma_plane_ptr, chroma: chroma_plane_ptr } => {
-                    if let (Some(luma_data), Some(chroma_data)) = (try_copy_pooled_plane(luma_plane_ptr, &bitmap_pool.luma), try_copy_pooled_plane(chroma_plane_ptr, &bitmap_pool.chroma)) {
+                    if let (Some((luma_data, change_metric)), Some((chroma_data, _))) = (try_copy_pooled_plane(luma_plane_ptr, &bitmap_pool.luma, self.change_metric), try_copy_pooled_plane(chroma_plane_ptr, &bitmap_pool.chroma, false)) {
                         Ok(Some(PooledFrameBitmap::YCbCr(FrameBitmapYCbCr {
                             luma_data,
                             luma_width: luma_plane_ptr.width,
@@ -710,18 +1970,33 @@ impl VideoFrameBitmap for VideoFrame {
                             chroma_data,
                             chroma_width: chroma_plane_ptr.width,
                             chroma_height: chroma_plane_ptr.height,
-                            range: VideoRange::Video
+                            range: VideoRange::Video,
+                            color_matrix: self.ycbcr_color_matrix(),
+                            change_metric,
                         })))
                     } else {
                         Ok(None)
                     }
                 },
                 VideoFrameDataCopyPtrs::RgbaF16x4(rgba_plane_ptr) => {
-                    if let Some(data) = try_copy_pooled_plane(rgba_plane_ptr, &bitmap_pool.rgba_f16x4) {
+                    if let Some((data, change_metric)) = try_copy_pooled_plane(rgba_plane_ptr, &bitmap_pool.rgba_f16x4, self.change_metric) {
                         Ok(Some(PooledFrameBitmap::RgbaF16x4(FrameBitmapRgbaF16x4 {
                             data,
                             width: rgba_plane_ptr.width,
                             height: rgba_plane_ptr.height,
+                            change_metric,
+                        })))
+                    } else {
+                        Ok(None)
+                    }
+                },
+                VideoFrameDataCopyPtrs::Rgba16x4(rgba_plane_ptr) => {
+                    if let Some((data, change_metric)) = try_copy_pooled_plane(rgba_plane_ptr, &bitmap_pool.rgba_u16x4, self.change_metric) {
+                        Ok(Some(PooledFrameBitmap::RgbaUnorm16x4(FrameBitmapRgba16x4 {
+                            data,
+                            width: rgba_plane_ptr.width,
+                            height: rgba_plane_ptr.height,
+                            change_metric,
                         })))
                     } else {
                         Ok(None)
@@ -732,4 +2007,851 @@ impl VideoFrameBitmap for VideoFrame {
     }
 }
 
+impl VideoFrame {
+    /// Borrow this frame's pixels as a Bgra8888 bitmap for the duration of `view`, rather than copying them into an owned bitmap
+    /// 
+    /// The pixels are mapped into system memory as with `VideoFrame::map_planes()` - on MacOS the IOSurface is locked read-only,
+    /// and on Windows the frame is copied to a staging texture which is mapped - and are unmapped once `view` returns,
+    /// so the borrowed bitmap is only valid inside it.
+    /// 
+    /// Rows may be padded past the bitmap's width, so read them with `FrameBitmapBgraUnorm8x4View::row()`, or with `bytes_per_row`.
+    /// 
+    /// The pixels are copied rather than borrowed:
+    /// * Always on Windows, where they're copied once into the staging texture - though not again into an owned bitmap
+    /// * On MacOS, when the content is placed into the output size when copying (a `ScalingQuality` other than `Smooth`), in which case
+    ///   they're copied as with `get_bitmap()`
+    /// 
+    /// Only `CapturePixelFormat::Bgra8888` frames are supported.
+    pub fn with_bitmap_view<R>(&self, view: impl FnOnce(&FrameBitmapBgraUnorm8x4View<'_>) -> R) -> Result<R, VideoFrameBitmapError> {
+        #[cfg(target_os = "macos")]
+        let placed = matches!(&self.impl_video_frame, MacosVideoFrame::SCStream(MacosSCStreamVideoFrame { output_placement: Some(_), .. }));
+        #[cfg(not(target_os = "macos"))]
+        let placed = false;
+        let mut view = Some(view);
+        if !placed {
+            let result = self.map_planes(|planes| {
+                let [plane] = planes else {
+                    return None;
+                };
+                if plane.format != CapturePixelFormat::Bgra8888 || plane.bytes_per_row % 4 != 0 || plane.bytes_per_row < plane.width * 4 {
+                    return None;
+                }
+                let bitmap = FrameBitmapBgraUnorm8x4View {
+                    data: bytemuck::cast_slice::<u8, [u8; 4]>(&plane.data[..(plane.bytes_per_row * plane.height)]),
+                    width: plane.width,
+                    height: plane.height,
+                    bytes_per_row: plane.bytes_per_row,
+                };
+                view.take().map(|view| view(&bitmap))
+            }).map_err(|error| VideoFrameBitmapError::Other(format!("Failed to map frame: {}", error)))?;
+            if let Some(result) = result {
+                return Ok(result);
+            }
+        }
+        match (self.get_bitmap()?, view) {
+            (FrameBitmap::BgraUnorm8x4(bitmap), Some(view)) => Ok(view(&FrameBitmapBgraUnorm8x4View {
+                data: bitmap.data.as_ref(),
+                width: bitmap.width,
+                height: bitmap.height,
+                bytes_per_row: bitmap.width * 4,
+            })),
+            _ => Err(VideoFrameBitmapError::Other("Bitmap views are only supported for Bgra8888 frames".to_string())),
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use half::f16;
+
+    use crate::prelude::ScalingQuality;
+
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-5, "expected {expected}, got {actual}");
+    }
+
+    fn f16x4(components: [f32; 4]) -> [f16; 4] {
+        components.map(f16::from_f32)
+    }
+
+    #[test]
+    fn linear_clamp_tone_map() {
+        let tone_map = ToneMap::LINEAR_CLAMP;
+        assert_close(tone_map.map(0.0), 0.0);
+        assert_close(tone_map.map(0.25), 0.25);
+        assert_close(tone_map.map(1.0), 1.0);
+        assert_close(tone_map.map(4.0), 1.0);
+    }
+
+    #[test]
+    fn reinhard_tone_map() {
+        let tone_map = ToneMap::REINHARD;
+        assert_close(tone_map.map(0.0), 0.0);
+        assert_close(tone_map.map(1.0), 0.5);
+        assert_close(tone_map.map(3.0), 0.75);
+        assert!(tone_map.map(1000.0) < 1.0);
+    }
+
+    #[test]
+    fn aces_filmic_tone_map() {
+        let tone_map = ToneMap::ACES_FILMIC;
+        assert_close(tone_map.map(0.0), 0.0);
+        assert_close(tone_map.map(0.18), (0.18 * (2.51 * 0.18 + 0.03)) / (0.18 * (2.43 * 0.18 + 0.59) + 0.14));
+        assert_close(tone_map.map(1.0), 2.54 / 3.16);
+        // The curve's asymptote is above 1.0, so bright values are clamped
+        assert_close(tone_map.map(100.0), 1.0);
+    }
+
+    #[test]
+    fn tone_maps_send_negative_and_nan_values_to_black() {
+        for tone_map in [ToneMap::LINEAR_CLAMP, ToneMap::REINHARD, ToneMap::ACES_FILMIC] {
+            assert_close(tone_map.map(-1.0), 0.0);
+            assert_close(tone_map.map(f32::NAN), 0.0);
+        }
+    }
+
+    #[test]
+    fn exposure_scales_values_before_the_curve() {
+        assert_close(ToneMap::LINEAR_CLAMP.with_exposure(2.0).map(0.25), 0.5);
+        assert_close(ToneMap::LINEAR_CLAMP.with_exposure(0.5).map(4.0), 1.0);
+        assert_close(ToneMap::REINHARD.with_exposure(0.5).map(2.0), 0.5);
+        assert_close(ToneMap::ACES_FILMIC.with_exposure(2.0).map(0.5), ToneMap::ACES_FILMIC.map(1.0));
+    }
+
+    #[test]
+    fn f16_pixels_are_tone_mapped_then_srgb_encoded() {
+        // sRGB encodes linear 0.5 as 0.7354, and 0.0031308 is the end of the linear segment
+        assert_eq!(linear_f16_to_rgba_unorm8(f16x4([1.0, 0.0, 0.5, 0.5]), ToneMap::LINEAR_CLAMP), [255, 0, 188, 128]);
+        assert_eq!(linear_f16_to_rgba_unorm8(f16x4([0.0031308, 2.0, -1.0, 2.0]), ToneMap::LINEAR_CLAMP), [10, 255, 0, 255]);
+        // Alpha isn't tone mapped
+        assert_eq!(linear_f16_to_rgba_unorm8(f16x4([1.0, 1.0, 1.0, 1.0]), ToneMap::REINHARD), [188, 188, 188, 255]);
+    }
+
+    #[test]
+    fn f16_conversions_swizzle_and_keep_the_change_metric() {
+        let change_metric = Some(BitmapChangeMetric { changed_rows: 1, mean_abs_delta: 0.25 });
+        let bitmap = FrameBitmapRgbaF16x4 {
+            data: vec![f16x4([1.0, 0.0, 0.0, 1.0]), f16x4([0.0, 0.0, 1.0, 0.0])].into_boxed_slice(),
+            width: 2,
+            height: 1,
+            change_metric,
+        };
+        let rgba = bitmap.to_rgba_unorm8(ToneMap::LINEAR_CLAMP);
+        let bgra = bitmap.to_bgra_unorm8(ToneMap::LINEAR_CLAMP);
+        assert_eq!(&*rgba.data, &[[255, 0, 0, 255], [0, 0, 255, 0]]);
+        assert_eq!(&*bgra.data, &[[0, 0, 255, 255], [255, 0, 0, 0]]);
+        assert_eq!((bgra.width, bgra.height), (2, 1));
+        assert_eq!(rgba.change_metric, change_metric);
+        assert_eq!(bgra.change_metric, change_metric);
+    }
+
+    // The number of bitmaps belonging to a pool, free or checked out
+    fn pooled_count<T: Sized + Zeroable + Copy>(pool: &BitmapPool<T>) -> usize {
+        pool.free_bitmaps_and_count.lock().1
+    }
+
+    #[test]
+    fn pools_reuse_bitmaps_at_a_fixed_resolution() {
+        for resize_policy in [BitmapPoolResizePolicy::Reallocate, BitmapPoolResizePolicy::Grow] {
+            let pool = BitmapPool::<u32>::new(0, 3, (0, 0), resize_policy);
+            // Distinct allocations, told apart by address - two are checked out at once, so only two are ever needed
+            let mut allocations = HashSet::new();
+            for _ in 0..100 {
+                let first = pool.get_bitmap((16, 8));
+                let second = pool.get_bitmap((16, 8));
+                allocations.insert(first.as_ref().as_ptr());
+                allocations.insert(second.as_ref().as_ptr());
+            }
+            assert_eq!(allocations.len(), 2, "{resize_policy:?}");
+            assert_eq!(pooled_count(&pool), 2, "{resize_policy:?}");
+        }
+    }
+
+    #[test]
+    fn grow_policy_stops_allocating_once_bitmaps_fit() {
+        let pool = BitmapPool::<u32>::new(0, 2, (0, 0), BitmapPoolResizePolicy::Grow);
+        let mut allocations = HashSet::new();
+        for i in 0..100 {
+            let resolution = if i % 2 == 0 { (16, 8) } else { (32, 16) };
+            let bitmap = pool.get_bitmap(resolution);
+            assert_eq!(bitmap.as_ref().len(), resolution.0 * resolution.1);
+            // The first bitmap is grown once, to the larger resolution
+            if i >= 1 {
+                allocations.insert(bitmap.as_ref().as_ptr());
+            }
+        }
+        assert_eq!(allocations.len(), 1);
+        assert_eq!(pooled_count(&pool), 1);
+    }
+
+    #[test]
+    fn reallocate_policy_stays_within_max_when_resolution_changes() {
+        let pool = BitmapPool::<u32>::new(2, 2, (16, 8), BitmapPoolResizePolicy::Reallocate);
+        for i in 0..100 {
+            let resolution = if i % 2 == 0 { (32, 16) } else { (16, 8) };
+            // Each mismatched free bitmap is dropped before its replacement is allocated, so both fit under the max
+            let first = pool.try_get_bitmap(resolution).expect("pool exhausted");
+            let second = pool.try_get_bitmap(resolution).expect("pool exhausted");
+            assert!(pool.try_get_bitmap(resolution).is_none());
+            assert_eq!(first.as_ref().len(), resolution.0 * resolution.1);
+            assert_eq!(second.as_ref().len(), resolution.0 * resolution.1);
+            assert_eq!(pooled_count(&pool), 2);
+        }
+        pool.free_pooled();
+        assert_eq!(pooled_count(&pool), 0);
+    }
+
+    #[test]
+    fn bitmap_view_rows_skip_padding() {
+        // Two rows of two pixels, each padded by a pixel
+        let data = [[1, 1, 1, 1], [2, 2, 2, 2], [0, 0, 0, 0], [3, 3, 3, 3], [4, 4, 4, 4], [0, 0, 0, 0]];
+        let view = FrameBitmapBgraUnorm8x4View { data: &data, width: 2, height: 2, bytes_per_row: 12 };
+        assert_eq!(view.row(1), &[[3, 3, 3, 3], [4, 4, 4, 4]]);
+        assert_eq!(view.rows().collect::<Vec<_>>(), [&data[0..2], &data[3..5]]);
+    }
+
+    #[test]
+    fn r10g10b10a2_planes_repack_to_argb2101010_without_padding() {
+        // DXGI's R10G10B10A2 packs red into the low bits, then green, blue, and alpha in the top two bits
+        let pack = |r: u32, g: u32, b: u32, a: u32| r | (g << 10) | (b << 20) | (a << 30);
+        // Two rows of two pixels, each padded by a pixel which mustn't be copied
+        let padding = 0xDEADBEEF;
+        let pixels = [
+            pack(1023, 0, 0, 3), pack(1, 2, 3, 0), padding,
+            pack(0, 1023, 512, 1), pack(1000, 500, 10, 2), padding,
+        ];
+        let bytes: Vec<u8> = pixels.iter().flat_map(|pixel| pixel.to_le_bytes()).collect();
+        let plane_ptr = VideoFramePlanePtr { ptr: bytes.as_ptr() as *const c_void, width: 2, height: 2, bytes_per_row: 12 };
+        let repacked: Vec<[u16; 4]> = repack_rgba1010102_to_argb2101010(plane_ptr).into_iter().map(unpack_argb2101010).collect();
+        assert_eq!(repacked, [
+            [3, 1023, 0, 0], [0, 1, 2, 3],
+            [1, 0, 1023, 512], [2, 1000, 500, 10],
+        ]);
+    }
+
+    // Known R16G16B16A16_FLOAT values, including HDR values above 1, negative values and the largest finite f16
+    fn known_f16_pixels() -> [[f16; 4]; 6] {
+        [
+            f16x4([0.0, 0.5, 1.0, 1.0]), f16x4([2.0, 12.5, -0.25, 1.0]), f16x4([65504.0, 1.0 / 16384.0, f32::NEG_INFINITY, 1.0]),
+            f16x4([0.1, 0.2, 0.3, 0.75]), f16x4([-1.0, 4.0, 100.0, 0.0]), f16x4([1.0 / 3.0, 2.0 / 3.0, 1000.0, 0.5]),
+        ]
+    }
+
+    #[test]
+    fn rgba_f16_planes_copy_rows_honoring_row_pitch() {
+        // Mapped staging textures pad each row out to the row pitch, here by two pixels which mustn't be copied
+        let pixels = known_f16_pixels();
+        let padding = f16x4([-7.0; 4]);
+        let mut mapped = Vec::new();
+        for row in pixels.chunks(3) {
+            mapped.extend_from_slice(row);
+            mapped.extend_from_slice(&[padding, padding]);
+        }
+        let plane_ptr = VideoFramePlanePtr { ptr: mapped.as_ptr() as *const c_void, width: 3, height: 2, bytes_per_row: 5 * 8 };
+        let copied: Box<[[f16; 4]]> = copy_boxed_slice_plane(plane_ptr);
+        let bits = |pixels: &[[f16; 4]]| pixels.iter().flatten().map(|component| component.to_bits()).collect::<Vec<_>>();
+        assert_eq!(bits(&copied), bits(&pixels));
+        let pool = FrameBitmapPool::new(1);
+        let (pooled, _) = copy_pooled_plane(plane_ptr, &pool.rgba_f16x4, false);
+        assert_eq!((pooled.width, pooled.height), (3, 2));
+        assert_eq!(bits(pooled.as_ref()), bits(&pixels));
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn rgba_f16_staging_texture_reads_back_known_values() {
+        use windows::Win32::Graphics::Direct3D::{D3D_DRIVER_TYPE_WARP, D3D_FEATURE_LEVEL_11_0};
+        use windows::Win32::Graphics::Direct3D11::{D3D11CreateDevice, ID3D11Device, D3D11_CREATE_DEVICE_FLAG, D3D11_SDK_VERSION, D3D11_SUBRESOURCE_DATA};
+        use windows::Win32::Graphics::Dxgi::IDXGIAdapter;
+        let pixels = known_f16_pixels();
+        unsafe {
+            // WARP, so the test doesn't need a GPU
+            let mut device = Option::<ID3D11Device>::None;
+            D3D11CreateDevice(None::<&IDXGIAdapter>, D3D_DRIVER_TYPE_WARP, None, D3D11_CREATE_DEVICE_FLAG(0), Some(&[D3D_FEATURE_LEVEL_11_0]), D3D11_SDK_VERSION, Some(&mut device as *mut _), None, None)
+                .expect("Expected a WARP device");
+            let device = device.unwrap();
+            let mut texture_desc = D3D11_TEXTURE2D_DESC::default();
+            texture_desc.CPUAccessFlags = D3D11_CPU_ACCESS_READ.0 as u32;
+            texture_desc.ArraySize = 1;
+            texture_desc.Width = 3;
+            texture_desc.Height = 2;
+            texture_desc.MipLevels = 1;
+            texture_desc.SampleDesc.Count = 1;
+            texture_desc.Usage = D3D11_USAGE_STAGING;
+            texture_desc.Format = DXGI_FORMAT_R16G16B16A16_FLOAT;
+            let initial_data = D3D11_SUBRESOURCE_DATA { pSysMem: pixels.as_ptr() as *const c_void, SysMemPitch: 3 * 8, SysMemSlicePitch: 0 };
+            let mut staging_texture = Option::<ID3D11Texture2D>::None;
+            device.CreateTexture2D(&texture_desc as *const _, Some(&initial_data as *const _), Some(&mut staging_texture as *mut _))
+                .expect("Expected a staging texture");
+            let staging_texture = staging_texture.unwrap();
+            let context = device.GetImmediateContext().expect("Expected an immediate context");
+            let mut mapped_resource = D3D11_MAPPED_SUBRESOURCE::default();
+            context.Map(&staging_texture, 0, D3D11_MAP_READ, 0, Some(&mut mapped_resource as *mut _)).expect("Expected to map the staging texture");
+            let plane_ptr = VideoFramePlanePtr {
+                ptr: mapped_resource.pData as *const c_void,
+                width: 3,
+                height: 2,
+                bytes_per_row: mapped_resource.RowPitch as usize,
+            };
+            let copied: Box<[[f16; 4]]> = copy_boxed_slice_plane(plane_ptr);
+            context.Unmap(&staging_texture, 0);
+            let bits = |pixels: &[[f16; 4]]| pixels.iter().flatten().map(|component| component.to_bits()).collect::<Vec<_>>();
+            assert_eq!(bits(&copied), bits(&pixels));
+        }
+    }
+
+    #[test]
+    fn bgra_bitmaps_convert_to_rgba_by_swapping_channels() {
+        let change_metric = Some(BitmapChangeMetric { changed_rows: 2, mean_abs_delta: 0.5 });
+        let bitmap: BoxedSliceFrameBitmap = FrameBitmap::BgraUnorm8x4(FrameBitmapBgraUnorm8x4 {
+            data: vec![[1, 2, 3, 4], [255, 0, 128, 64]].into_boxed_slice(),
+            width: 1,
+            height: 2,
+            change_metric,
+        });
+        let rgba = bitmap.to_rgba_unorm8();
+        assert_eq!(&*rgba.data, &[[3, 2, 1, 4], [128, 0, 255, 64]]);
+        assert_eq!((rgba.width, rgba.height), (1, 2));
+        assert_eq!(rgba.change_metric, change_metric);
+    }
+
+    #[test]
+    fn packed_2101010_bitmaps_round_to_8_bits() {
+        let pack = |a: u32, r: u32, g: u32, b: u32| (a << 30) | (r << 20) | (g << 10) | b;
+        let bitmap: BoxedSliceFrameBitmap = FrameBitmap::ArgbUnormPacked2101010(FrameBitmapArgbUnormPacked2101010 {
+            data: vec![pack(3, 1023, 512, 0), pack(1, 3, 1, 1021)].into_boxed_slice(),
+            width: 2,
+            height: 1,
+            change_metric: None,
+        });
+        // 512 / 1023 * 255 = 127.6, 3 / 1023 * 255 = 0.75, and 1 / 3 alpha is 85
+        assert_eq!(&*bitmap.to_rgba_unorm8().data, &[[255, 128, 0, 255], [1, 0, 255, 85]]);
+    }
+
+    #[test]
+    fn rgba16_bitmaps_round_to_8_bits() {
+        let bitmap: BoxedSliceFrameBitmap = FrameBitmap::RgbaUnorm16x4(FrameBitmapRgba16x4 {
+            data: vec![[65535, 32768, 0, 65535], [257, 128, 129, 32639]].into_boxed_slice(),
+            width: 2,
+            height: 1,
+            change_metric: None,
+        });
+        assert_eq!(&*bitmap.to_rgba_unorm8().data, &[[255, 128, 0, 255], [1, 0, 1, 127]]);
+    }
+
+    // A YCbCr bitmap with a chroma sample for every luma sample
+    fn ycbcr_444(pixels: &[[u8; 3]], range: VideoRange) -> FrameBitmapYCbCr<Box<[u8]>, Box<[[u8; 2]]>> {
+        FrameBitmapYCbCr {
+            luma_data: pixels.iter().map(|[y, _, _]| *y).collect(),
+            luma_width: pixels.len(),
+            luma_height: 1,
+            chroma_data: pixels.iter().map(|[_, cb, cr]| [*cb, *cr]).collect(),
+            chroma_width: pixels.len(),
+            chroma_height: 1,
+            range,
+            color_matrix: YCbCrColorMatrix::ItuR709,
+            change_metric: None,
+        }
+    }
+
+    #[test]
+    fn bt709_video_range_golden_pixels() {
+        // BT.709 video range encodings of black, white, gray, red, green and blue, rounded to 8 bits - so the primaries decode to within 1
+        let bitmap = ycbcr_444(&[[16, 128, 128], [235, 128, 128], [126, 128, 128], [63, 102, 240], [173, 42, 26], [32, 240, 118]], VideoRange::Video);
+        assert_eq!(&*bitmap.to_rgba_unorm8().data, &[
+            [0, 0, 0, 255],
+            [255, 255, 255, 255],
+            [128, 128, 128, 255],
+            [255, 1, 0, 255],
+            [0, 255, 1, 255],
+            [1, 0, 255, 255],
+        ]);
+        // Values outside the video range are clamped
+        let bitmap = ycbcr_444(&[[0, 128, 128], [255, 128, 128]], VideoRange::Video);
+        assert_eq!(&*bitmap.to_rgba_unorm8().data, &[[0, 0, 0, 255], [255, 255, 255, 255]]);
+    }
+
+    #[test]
+    fn bt709_full_range_golden_pixels() {
+        let bitmap = ycbcr_444(&[[0, 128, 128], [255, 128, 128], [128, 128, 128], [54, 99, 255], [182, 30, 12], [18, 255, 116]], VideoRange::Full);
+        assert_eq!(&*bitmap.to_rgba_unorm8().data, &[
+            [0, 0, 0, 255],
+            [255, 255, 255, 255],
+            [128, 128, 128, 255],
+            [254, 0, 0, 255],
+            [0, 255, 0, 255],
+            [0, 0, 254, 255],
+        ]);
+    }
+
+    #[test]
+    fn bt601_video_range_golden_pixels() {
+        // BT.601 video range encodings of gray, red, green and blue, rounded to 8 bits
+        let pixels = [[126, 128, 128], [81, 90, 240], [145, 54, 34], [41, 240, 110]];
+        let bitmap = FrameBitmapYCbCr { color_matrix: YCbCrColorMatrix::ItuR601, ..ycbcr_444(&pixels, VideoRange::Video) };
+        assert_eq!(&*bitmap.to_rgba_unorm8().data, &[
+            [128, 128, 128, 255],
+            [254, 0, 0, 255],
+            [0, 255, 1, 255],
+            [0, 0, 255, 255],
+        ]);
+        // Decoding them with the wrong matrix shifts the primaries
+        let bt709 = ycbcr_444(&pixels, VideoRange::Video).to_rgba_unorm8();
+        assert_eq!(bt709.data[0], [128, 128, 128, 255]);
+        assert!(bt709.data[1..] != bitmap.to_rgba_unorm8().data[1..]);
+    }
+
+    #[test]
+    fn subsampled_chroma_covers_2x2_luma_blocks_with_odd_sizes() {
+        // A 3x3 luma plane has a 2x2 chroma plane, whose last column and row each cover a single luma column or row
+        let bitmap = FrameBitmapYCbCr {
+            luma_data: vec![126; 9].into_boxed_slice(),
+            luma_width: 3,
+            luma_height: 3,
+            chroma_data: vec![[128, 128], [128, 240], [240, 128], [16, 16]].into_boxed_slice(),
+            chroma_width: 2,
+            chroma_height: 2,
+            range: VideoRange::Video,
+            color_matrix: YCbCrColorMatrix::ItuR709,
+            change_metric: None,
+        };
+        let gray = [128, 128, 128, 255];
+        let red = ycbcr_444(&[[126, 128, 240]], VideoRange::Video).to_rgba_unorm8().data[0];
+        let blue = ycbcr_444(&[[126, 240, 128]], VideoRange::Video).to_rgba_unorm8().data[0];
+        let green = ycbcr_444(&[[126, 16, 16]], VideoRange::Video).to_rgba_unorm8().data[0];
+        assert_eq!(&*bitmap.to_rgba_unorm8().data, &[
+            gray, gray, red,
+            gray, gray, red,
+            blue, blue, green,
+        ]);
+    }
+
+    #[test]
+    fn ycbcr_bgra_conversions_match_rgba() {
+        let change_metric = Some(BitmapChangeMetric { changed_rows: 1, mean_abs_delta: 0.125 });
+        let mut bitmap = ycbcr_444(&[[63, 102, 240], [32, 240, 118]], VideoRange::Video);
+        bitmap.change_metric = change_metric;
+        let bgra = bitmap.to_bgra_unorm8();
+        assert_eq!(&*bgra.data, &[[0, 1, 255, 255], [255, 0, 1, 255]]);
+        assert_eq!(bgra.change_metric, change_metric);
+        let pool = FrameBitmapPool::new(1);
+        let pooled = bitmap.to_pooled_bgra_unorm8(&pool);
+        assert_eq!(pooled.data.as_ref(), &*bgra.data);
+        assert_eq!((pooled.width, pooled.height), (2, 1));
+    }
+
+    #[test]
+    fn ycbcr_bitmaps_convert_to_opaque_rgba() {
+        let bitmap: BoxedSliceFrameBitmap = FrameBitmap::YCbCr(FrameBitmapYCbCr {
+            luma_data: vec![16, 235].into_boxed_slice(),
+            luma_width: 2,
+            luma_height: 1,
+            chroma_data: vec![[128, 128]].into_boxed_slice(),
+            chroma_width: 1,
+            chroma_height: 1,
+            range: VideoRange::Video,
+            color_matrix: YCbCrColorMatrix::ItuR709,
+            change_metric: None,
+        });
+        assert_eq!(&*bitmap.to_rgba_unorm8().data, &[[0, 0, 0, 255], [255, 255, 255, 255]]);
+    }
 
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect { origin: crate::util::Point { x, y }, size: Size { width, height } }
+    }
+
+    // A bitmap of [x, y, 0, 255] at each pixel
+    fn coordinate_bitmap(width: usize, height: usize) -> FrameBitmapBgraUnorm8x4<Box<[[u8; 4]]>> {
+        FrameBitmapBgraUnorm8x4 {
+            data: (0..width * height).map(|i| [(i % width) as u8, (i / width) as u8, 0, 255]).collect(),
+            width,
+            height,
+            change_metric: Some(BitmapChangeMetric { changed_rows: height as u32, mean_abs_delta: 1.0 }),
+        }
+    }
+
+    fn coordinates(x: std::ops::Range<u8>, y: std::ops::Range<u8>) -> Vec<[u8; 4]> {
+        y.flat_map(|y| x.clone().map(move |x| [x, y, 0, 255])).collect()
+    }
+
+    #[test]
+    fn crops_at_odd_offsets_include_partly_covered_pixels() {
+        let bitmap = coordinate_bitmap(7, 5);
+        let cropped = bitmap.crop(rect(1.0, 3.0, 3.0, 1.0));
+        assert_eq!((cropped.width, cropped.height), (3, 1));
+        assert_eq!(&*cropped.data, &coordinates(1..4, 3..4)[..]);
+        // Fractional edges round outwards, onto every pixel the rect touches
+        let cropped = bitmap.crop(rect(1.5, 0.5, 2.0, 1.0));
+        assert_eq!((cropped.width, cropped.height), (3, 2));
+        assert_eq!(&*cropped.data, &coordinates(1..4, 0..2)[..]);
+        assert!(cropped.change_metric.is_none());
+    }
+
+    #[test]
+    fn crops_are_clamped_to_the_bitmap() {
+        let bitmap = coordinate_bitmap(7, 5);
+        let cropped = bitmap.crop(rect(5.0, 3.0, 10.0, 10.0));
+        assert_eq!((cropped.width, cropped.height), (2, 2));
+        assert_eq!(&*cropped.data, &coordinates(5..7, 3..5)[..]);
+        let cropped = bitmap.crop(rect(-2.0, -2.0, 100.0, 100.0));
+        assert_eq!(&*cropped.data, &*bitmap.data);
+    }
+
+    #[test]
+    fn crops_outside_the_bitmap_or_empty_are_empty() {
+        let bitmap = coordinate_bitmap(7, 5);
+        let empty_rects = [
+            rect(7.0, 0.0, 2.0, 2.0),
+            rect(0.0, 20.0, 2.0, 2.0),
+            rect(-10.0, -10.0, 5.0, 5.0),
+            rect(2.0, 2.0, 0.0, 0.0),
+            rect(3.0, 1.0, -2.0, 2.0),
+            rect(f64::NAN, 0.0, 2.0, 2.0),
+        ];
+        for crop_rect in empty_rects {
+            let cropped = bitmap.crop(crop_rect);
+            assert_eq!((cropped.width, cropped.height, cropped.data.len()), (0, 0, 0), "crop rect {crop_rect:?}");
+        }
+    }
+
+    // A 4:2:0 bitmap with luma of x + 16 * y, and chroma of [x, y] for each 2x2 block
+    fn coordinate_ycbcr_420(luma_width: usize, luma_height: usize) -> FrameBitmapYCbCr<Box<[u8]>, Box<[[u8; 2]]>> {
+        let (chroma_width, chroma_height) = (luma_width.div_ceil(2), luma_height.div_ceil(2));
+        FrameBitmapYCbCr {
+            luma_data: (0..luma_width * luma_height).map(|i| ((i % luma_width) + 16 * (i / luma_width)) as u8).collect(),
+            luma_width,
+            luma_height,
+            chroma_data: (0..chroma_width * chroma_height).map(|i| [(i % chroma_width) as u8, (i / chroma_width) as u8]).collect(),
+            chroma_width,
+            chroma_height,
+            range: VideoRange::Full,
+            color_matrix: YCbCrColorMatrix::ItuR601,
+            change_metric: None,
+        }
+    }
+
+    #[test]
+    fn ycbcr_crops_widen_odd_offsets_to_even_luma() {
+        let bitmap = coordinate_ycbcr_420(9, 7);
+        let cropped = bitmap.crop(rect(3.0, 1.0, 3.0, 3.0));
+        assert_eq!((cropped.luma_width, cropped.luma_height), (4, 4));
+        assert_eq!(&*cropped.luma_data, &(0..4).flat_map(|y| (2..6).map(move |x| x + 16 * y)).collect::<Vec<u8>>()[..]);
+        assert_eq!((cropped.chroma_width, cropped.chroma_height), (2, 2));
+        assert_eq!(&*cropped.chroma_data, &[[1, 0], [2, 0], [1, 1], [2, 1]]);
+        // Every luma pixel keeps the chroma sample it had before cropping
+        for (x, y) in (0..4).flat_map(|y| (0..4).map(move |x| (x, y))) {
+            assert_eq!(cropped.chroma_at(x, y), bitmap.chroma_at(x + 2, y), "luma pixel {x}, {y}");
+        }
+        assert_eq!((cropped.range, cropped.color_matrix), (VideoRange::Full, YCbCrColorMatrix::ItuR601));
+    }
+
+    #[test]
+    fn ycbcr_crops_with_odd_sizes_take_the_last_chroma_sample() {
+        let bitmap = coordinate_ycbcr_420(9, 7);
+        // The last luma column and row of a 9x7 bitmap each have chroma to themselves
+        let cropped = bitmap.crop(rect(7.0, 5.0, 5.0, 5.0));
+        assert_eq!((cropped.luma_width, cropped.luma_height), (3, 3));
+        assert_eq!((cropped.chroma_width, cropped.chroma_height), (2, 2));
+        assert_eq!(&*cropped.chroma_data, &[[3, 2], [4, 2], [3, 3], [4, 3]]);
+        assert_eq!(cropped.chroma_at(2, 2), [4, 3]);
+    }
+
+    #[test]
+    fn ycbcr_crops_outside_the_bitmap_are_empty() {
+        let bitmap = coordinate_ycbcr_420(9, 7);
+        for crop_rect in [rect(20.0, 20.0, 2.0, 2.0), rect(3.0, 3.0, 0.0, 0.0), rect(-4.0, 1.0, 3.0, 3.0)] {
+            let cropped = bitmap.crop(crop_rect);
+            assert_eq!((cropped.luma_width, cropped.luma_height, cropped.luma_data.len()), (0, 0, 0), "crop rect {crop_rect:?}");
+            assert_eq!((cropped.chroma_width, cropped.chroma_height, cropped.chroma_data.len()), (0, 0, 0), "crop rect {crop_rect:?}");
+        }
+    }
+
+    #[test]
+    fn downscaled_sizes_stretch_or_fit_without_upscaling() {
+        let target_size = Size { width: 640.0, height: 480.0 };
+        assert_eq!(downscaled_size((1920, 1080), target_size, DownscaleMode::Stretch), (640, 480));
+        assert_eq!(downscaled_size((1920, 1080), target_size, DownscaleMode::PreserveAspect), (640, 360));
+        assert_eq!(downscaled_size((1080, 1920), target_size, DownscaleMode::PreserveAspect), (270, 480));
+        // Frames smaller than the target keep their own size, per axis when stretching
+        assert_eq!(downscaled_size((320, 200), target_size, DownscaleMode::PreserveAspect), (320, 200));
+        assert_eq!(downscaled_size((1920, 200), target_size, DownscaleMode::Stretch), (640, 200));
+        // Sizes round to whole pixels, and never reach zero
+        assert_eq!(downscaled_size((1001, 1001), Size { width: 100.0, height: 1000.0 }, DownscaleMode::PreserveAspect), (100, 100));
+        assert_eq!(downscaled_size((4000, 10), Size { width: 100.0, height: 100.0 }, DownscaleMode::PreserveAspect), (100, 1));
+        assert_eq!(downscaled_size((1920, 1080), Size { width: 0.0, height: -5.0 }, DownscaleMode::Stretch), (1, 1));
+    }
+
+    #[test]
+    fn downscales_read_back_exact_mip_levels_and_sample_the_rest() {
+        // The smallest level still at least the downscaled size is generated
+        assert_eq!(mip_level_for_size((1920, 1080), (1920, 1080)), 0);
+        assert_eq!(mip_level_for_size((1920, 1080), (960, 540)), 1);
+        assert_eq!(mip_level_for_size((1920, 1080), (640, 360)), 1);
+        assert_eq!(mip_level_for_size((1920, 1080), (480, 270)), 2);
+        assert_eq!(mip_level_for_size((1920, 1080), (1, 1)), 10);
+        assert_eq!(mip_size((1920, 1080), 2), (480, 270));
+        assert_eq!(mip_size((1920, 1080), 11), (1, 1));
+        // Halvings are read back directly, other sizes are sampled down from the mips
+        assert_eq!(downscale_mip_level((1920, 1080), (960, 540)), (1, true));
+        assert_eq!(downscale_mip_level((1920, 1080), (240, 135)), (3, true));
+        assert_eq!(downscale_mip_level((1920, 1080), (640, 360)), (1, false));
+        assert_eq!(downscale_mip_level((1920, 1080), (640, 480)), (1, false));
+        assert_eq!(downscale_mip_level((1921, 1081), (960, 540)), (1, true));
+    }
+
+    #[test]
+    fn scaled_chroma_planes_cover_the_scaled_luma() {
+        assert_eq!(scaled_chroma_size((1920, 1080), (960, 540), (640, 360)), (320, 180));
+        // Odd luma sizes round partial chroma samples up
+        assert_eq!(scaled_chroma_size((1920, 1080), (960, 540), (641, 361)), (321, 181));
+        assert_eq!(scaled_chroma_size((1283, 717), (642, 359), (1283, 717)), (642, 359));
+        assert_eq!(scaled_chroma_size((1920, 1080), (960, 540), (1, 1)), (1, 1));
+    }
+
+    #[test]
+    fn downscaled_placements_scale_the_content_rect_with_the_output() {
+        let placement = OutputPlacement {
+            source_size: (800, 600),
+            content_rect: rect(160.0, 60.0, 1600.0, 1200.0),
+            output_size: (1920, 1320),
+        };
+        let downscaled = placement.downscaled((960, 660));
+        assert_eq!(downscaled.output_size, (960, 660));
+        assert_eq!(downscaled.source_size, (800, 600));
+        let rect = downscaled.content_rect;
+        assert_eq!((rect.origin.x, rect.origin.y, rect.size.width, rect.size.height), (80.0, 30.0, 800.0, 600.0));
+        // Content scaled below a pixel still covers a pixel
+        assert_eq!(placement.downscaled((1, 1)).source_size, (1, 1));
+    }
+
+    const PADDING: [u8; 4] = [0, 0, 0, 255];
+
+    fn placed(bitmap: &FrameBitmapBgraUnorm8x4<Box<[[u8; 4]]>>, scaling_quality: ScalingQuality, output_size: (usize, usize)) -> Vec<[u8; 4]> {
+        let content_size = Size { width: bitmap.width as f64, height: bitmap.height as f64 };
+        let placement = OutputPlacement {
+            source_size: (bitmap.width, bitmap.height),
+            content_rect: scaling_quality.content_rect(content_size, Size { width: output_size.0 as f64, height: output_size.1 as f64 }),
+            output_size,
+        };
+        let plane_ptr = VideoFramePlanePtr { ptr: bitmap.data.as_ptr() as *const c_void, width: bitmap.width, height: bitmap.height, bytes_per_row: bitmap.width * 4 };
+        place_plane(plane_ptr, placement, PADDING)
+    }
+
+    #[test]
+    fn nearest_neighbor_placement_repeats_source_pixels_without_intermediate_colors() {
+        let bitmap = coordinate_bitmap(3, 2);
+        // A whole number scale of 3, centered in a 10x7 output
+        let output = placed(&bitmap, ScalingQuality::NearestNeighbor, (10, 7));
+        let source_colors: HashSet<[u8; 4]> = bitmap.data.iter().copied().collect();
+        for (i, pixel) in output.iter().enumerate() {
+            let (x, y) = (i % 10, i / 10);
+            if x < 9 && y < 6 {
+                assert_eq!(*pixel, [(x / 3) as u8, (y / 3) as u8, 0, 255], "pixel at {x}, {y}");
+            } else {
+                assert_eq!(*pixel, PADDING, "pixel at {x}, {y}");
+            }
+            assert!(*pixel == PADDING || source_colors.contains(pixel));
+        }
+    }
+
+    #[test]
+    fn nearest_neighbor_placement_samples_pixel_centers_when_scaling_down() {
+        let bitmap = coordinate_bitmap(4, 2);
+        // Scaled by a half into the top of a 2x2 output
+        let output = placed(&bitmap, ScalingQuality::NearestNeighbor, (2, 2));
+        assert_eq!(output, vec![[1, 1, 0, 255], [3, 1, 0, 255], PADDING, PADDING]);
+    }
+
+    #[test]
+    fn unscaled_placement_crops_content_larger_than_the_output() {
+        let bitmap = coordinate_bitmap(4, 4);
+        let output = placed(&bitmap, ScalingQuality::None, (2, 2));
+        assert_eq!(output, vec![[1, 1, 0, 255], [2, 1, 0, 255], [1, 2, 0, 255], [2, 2, 0, 255]]);
+        // And pads around content smaller than the output
+        let output = placed(&coordinate_bitmap(1, 1), ScalingQuality::None, (3, 1));
+        assert_eq!(output, vec![PADDING, [0, 0, 0, 255], PADDING]);
+    }
+
+    #[test]
+    fn placement_only_reads_the_source_size_of_the_plane() {
+        let bitmap = coordinate_bitmap(4, 4);
+        let placement = OutputPlacement {
+            source_size: (2, 2),
+            content_rect: rect(0.0, 0.0, 4.0, 4.0),
+            output_size: (4, 4),
+        };
+        let plane_ptr = VideoFramePlanePtr { ptr: bitmap.data.as_ptr() as *const c_void, width: 4, height: 4, bytes_per_row: 16 };
+        let output = place_plane(plane_ptr, placement, PADDING);
+        assert!(output.iter().all(|pixel| pixel[0] < 2 && pixel[1] < 2));
+    }
+
+    #[test]
+    fn subsampled_placements_scale_with_the_plane() {
+        let placement = OutputPlacement {
+            source_size: (9, 5),
+            content_rect: rect(1.0, 2.0, 18.0, 10.0),
+            output_size: (21, 15),
+        };
+        let luma = VideoFramePlanePtr { ptr: std::ptr::null(), width: 10, height: 6, bytes_per_row: 10 };
+        let chroma = VideoFramePlanePtr { ptr: std::ptr::null(), width: 5, height: 3, bytes_per_row: 10 };
+        let subsampled = placement.subsampled(chroma, luma);
+        // Partial chroma samples round up
+        assert_eq!(subsampled.source_size, (5, 3));
+        assert_eq!(subsampled.output_size, (11, 8));
+        let rect = subsampled.content_rect;
+        assert_eq!((rect.origin.x, rect.origin.y, rect.size.width, rect.size.height), (0.5, 1.0, 9.0, 5.0));
+    }
+
+    #[test]
+    fn odd_sized_chroma_planes_cover_the_last_luma_column_and_row() {
+        let bitmap = FrameBitmapYCbCr {
+            luma_data: vec![128; 9].into_boxed_slice(),
+            luma_width: 3,
+            luma_height: 3,
+            chroma_data: vec![[128, 128], [128, 240], [240, 128], [240, 240]].into_boxed_slice(),
+            chroma_width: 2,
+            chroma_height: 2,
+            range: VideoRange::Full,
+            color_matrix: YCbCrColorMatrix::ItuR709,
+            change_metric: None,
+        };
+        assert_eq!(bitmap.chroma_at(1, 1), [128, 128]);
+        assert_eq!(bitmap.chroma_at(2, 0), [128, 240]);
+        assert_eq!(bitmap.chroma_at(0, 2), [240, 128]);
+        assert_eq!(bitmap.chroma_at(2, 2), [240, 240]);
+    }
+
+    #[test]
+    fn odd_sized_420_frames_copy_every_chroma_column() {
+        let (luma_width, luma_height) = (1283, 717);
+        let (chroma_width, chroma_height) = (642, 359);
+        // Rows padded to 16 bytes, as IOSurfaces are
+        let (luma_bytes_per_row, chroma_bytes_per_row) = (1296, 1296);
+        let luma: Vec<u8> = (0..(luma_bytes_per_row * luma_height)).map(|i| (i % luma_bytes_per_row) as u8).collect();
+        // Each chroma sample holds its column and row, modulo 256
+        let chroma: Vec<u8> = (0..(chroma_bytes_per_row * chroma_height)).map(|i| {
+            let (x, y) = ((i % chroma_bytes_per_row) / 2, i / chroma_bytes_per_row);
+            if i % 2 == 0 { x as u8 } else { y as u8 }
+        }).collect();
+        let luma_ptr = VideoFramePlanePtr { ptr: luma.as_ptr() as *const c_void, width: luma_width, height: luma_height, bytes_per_row: luma_bytes_per_row };
+        let chroma_ptr = VideoFramePlanePtr { ptr: chroma.as_ptr() as *const c_void, width: chroma_width, height: chroma_height, bytes_per_row: chroma_bytes_per_row };
+        for (copy_ptrs, range) in [
+            (VideoFrameDataCopyPtrs::V420 { luma: luma_ptr, chroma: chroma_ptr }, VideoRange::Video),
+            (VideoFrameDataCopyPtrs::F420 { luma: luma_ptr, chroma: chroma_ptr }, VideoRange::Full),
+        ] {
+            let Ok(FrameBitmap::YCbCr(bitmap)) = boxed_slice_bitmap(copy_ptrs, YCbCrColorMatrix::ItuR709) else {
+                panic!("expected a YCbCr bitmap");
+            };
+            assert_eq!(bitmap.range, range);
+            assert_eq!((bitmap.luma_width, bitmap.luma_height), (luma_width, luma_height));
+            assert_eq!((bitmap.chroma_width, bitmap.chroma_height), (chroma_width, chroma_height));
+            assert_eq!(bitmap.luma_data.len(), luma_width * luma_height);
+            assert_eq!(bitmap.chroma_data.len(), chroma_width * chroma_height);
+            // The row padding isn't copied
+            assert_eq!(bitmap.luma_data[luma_width], 0);
+            // The last luma column is the only one covered by the last chroma column
+            assert_eq!(bitmap.chroma_at(luma_width - 1, 0), [(641 % 256) as u8, 0]);
+            assert_eq!(bitmap.chroma_at(luma_width - 2, 0), [(640 % 256) as u8, 0]);
+            assert_eq!(bitmap.chroma_at(luma_width - 1, luma_height - 1), [(641 % 256) as u8, (358 % 256) as u8]);
+            assert_eq!(bitmap.chroma_data[chroma_width * chroma_height - 1], [(641 % 256) as u8, (358 % 256) as u8]);
+        }
+    }
+
+    fn measured_copy(pool: &Arc<BitmapPool<[u8; 4]>>, frame: &[[u8; 4]], width: usize) -> (PooledBitmap<[u8; 4]>, BitmapChangeMetric) {
+        let plane_ptr = VideoFramePlanePtr { ptr: frame.as_ptr() as *const c_void, width, height: frame.len() / width, bytes_per_row: width * 4 };
+        let (bitmap, metric) = copy_pooled_plane(plane_ptr, pool, true);
+        assert!(bitmap.as_ref() == frame);
+        (bitmap, metric.expect("expected a change metric"))
+    }
+
+    const FULL_CHANGE: BitmapChangeMetric = BitmapChangeMetric { changed_rows: 4, mean_abs_delta: 1.0 };
+
+    #[test]
+    fn change_metrics_compare_against_the_previous_pooled_bitmap() {
+        let pool = BitmapPool::new(0, 4, (0, 0), BitmapPoolResizePolicy::Reallocate);
+        let frame = coordinate_bitmap(4, 4).data;
+        let (bitmap, metric) = measured_copy(&pool, &frame, 4);
+        assert_eq!(metric, FULL_CHANGE);
+        let previous_address = bitmap.as_ref().as_ptr();
+        drop(bitmap);
+
+        // The previous frame's bitmap is copied over in place
+        let (bitmap, metric) = measured_copy(&pool, &frame, 4);
+        assert_eq!(metric, BitmapChangeMetric { changed_rows: 0, mean_abs_delta: 0.0 });
+        assert_eq!(bitmap.as_ref().as_ptr(), previous_address);
+        drop(bitmap);
+
+        let mut changed_frame = frame.clone();
+        changed_frame[5][2] = 255;
+        changed_frame[14][2] = 51;
+        let (bitmap, metric) = measured_copy(&pool, &changed_frame, 4);
+        assert_eq!(metric.changed_rows, 2);
+        assert!((metric.mean_abs_delta - 1.2 / 64.0).abs() < 1e-6);
+        drop(bitmap);
+
+        // No extra copy of the previous frame is allocated
+        assert_eq!(pool.free_bitmaps_and_count.lock().1, 1);
+    }
+
+    #[test]
+    fn change_metrics_report_a_full_change_without_the_previous_frame() {
+        let pool = BitmapPool::new(0, 4, (0, 0), BitmapPoolResizePolicy::Reallocate);
+        let frame = coordinate_bitmap(4, 4).data;
+        // While the previous bitmap is still in use, there's nothing to compare against
+        let (held_bitmap, _) = measured_copy(&pool, &frame, 4);
+        let (bitmap, metric) = measured_copy(&pool, &frame, 4);
+        assert_eq!(metric, FULL_CHANGE);
+        drop(held_bitmap);
+        drop(bitmap);
+        assert_eq!(measured_copy(&pool, &frame, 4).1, BitmapChangeMetric { changed_rows: 0, mean_abs_delta: 0.0 });
+
+        // Or once it's been overwritten by an unmeasured copy
+        let plane_ptr = VideoFramePlanePtr { ptr: frame.as_ptr() as *const c_void, width: 4, height: 4, bytes_per_row: 16 };
+        let (bitmap, metric) = copy_pooled_plane(plane_ptr, &pool, false);
+        assert!(metric.is_none());
+        drop(bitmap);
+        assert_eq!(measured_copy(&pool, &frame, 4).1, FULL_CHANGE);
+
+        // Or after a resize
+        let resized_frame = coordinate_bitmap(8, 4).data;
+        assert_eq!(measured_copy(&pool, &resized_frame, 8).1, FULL_CHANGE);
+    }
+
+    #[test]
+    fn measured_copies_only_wait_when_asked_to() {
+        let pool = BitmapPool::new(0, 1, (0, 0), BitmapPoolResizePolicy::Reallocate);
+        let frame = coordinate_bitmap(4, 4).data;
+        let plane_ptr = VideoFramePlanePtr { ptr: frame.as_ptr() as *const c_void, width: 4, height: 4, bytes_per_row: 16 };
+        let (bitmap, _) = measured_copy(&pool, &frame, 4);
+        assert!(try_copy_pooled_plane(plane_ptr, &pool, true).is_none());
+        drop(bitmap);
+        let (_, metric) = try_copy_pooled_plane(plane_ptr, &pool, true).expect("expected a free bitmap");
+        assert_eq!(metric, Some(BitmapChangeMetric { changed_rows: 0, mean_abs_delta: 0.0 }));
+    }
+
+    #[test]
+    fn panicking_conversions_dont_take_down_their_worker() {
+        let thread_pool = ConversionThreadPool::new(2);
+        for _ in 0..4 {
+            thread_pool.spawn(|| panic!("conversion panicked"));
+        }
+        // Both workers are still around to run conversions side by side
+        let barrier = Arc::new(std::sync::Barrier::new(2));
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..2 {
+            let (barrier, tx) = (barrier.clone(), tx.clone());
+            thread_pool.spawn(move || {
+                barrier.wait();
+                let _ = tx.send(std::thread::current().name().map(str::to_string));
+            });
+        }
+        let names: HashSet<Option<String>> = (0..2).map(|_| rx.recv_timeout(std::time::Duration::from_secs(10)).expect("a worker stopped running conversions")).collect();
+        assert_eq!(names.len(), 2);
+    }
+}
@@ -1,43 +1,75 @@
-use std::time::Duration;
-
-use crate::platform::platform_impl::objc_wrap::{debug_objc_object, CGMainDisplayID, SCContentSharingPicker, SCContentSharingPickerConfiguration, SCContentSharingPickerEvent, SCContentSharingPickerModeSingleDisplay, SCContentSharingPickerModeSingleWindow, SCContentSharingPickerObserver, SCShareableContentStyle};
+use crate::platform::platform_impl::{objc_wrap::{CGMainDisplayID, SCContentFilter, SCContentSharingPicker, SCContentSharingPickerConfiguration, SCContentSharingPickerEvent, SCContentSharingPickerModeSingleDisplay, SCContentSharingPickerModeSingleWindow, SCContentSharingPickerObserver, SCShareableContentStyle}, ImplCapturableDisplay, ImplCapturableWindow};
+use crate::prelude::{CapturableDisplay, CapturableWindow};
 
 use super::{PickedSharableContent, SharableContentPickerError, SharableContentPickerConfig};
 use futures::channel::oneshot;
 
+/// Show the system content picker, and get the window or display the user picked - or `None` if they cancelled
+///
+/// The picker needs MacOS 14, and identifying the picked content needs MacOS 15.2.
+/// The excluded applications are hidden from the picker.
 pub async fn pick_sharable_content(config: SharableContentPickerConfig) -> Result<Option<PickedSharableContent>, SharableContentPickerError> {
+    if !config.display && !config.window {
+        return Err(SharableContentPickerError::EmptyConfig);
+    }
+    if !SCContentSharingPicker::class_exists() {
+        return Err(SharableContentPickerError::Other("SCContentSharingPicker is not available".into()));
+    }
     unsafe { CGMainDisplayID(); }
     let configuration = SCContentSharingPickerConfiguration::new();
-    let allowed_picker_modes = 
+    let allowed_picker_modes =
         if config.display { SCContentSharingPickerModeSingleDisplay } else { 0 } |
         if config.window { SCContentSharingPickerModeSingleWindow } else { 0 };
     configuration.set_allowed_picker_modes(allowed_picker_modes);
+    let excluded_bundle_ids: Vec<String> = config.excluded_apps.iter()
+        .map(|application| application.identifier())
+        .collect();
+    configuration.set_excluded_bundle_ids(&excluded_bundle_ids);
 
     let picker = SCContentSharingPicker::shared();
     picker.set_configuration_for_stream(configuration, None);
     let (tx, rx) = oneshot::channel();
-    let mut tx_opt = Some(tx);
+    let mut tx = Some(tx);
     let observer = SCContentSharingPickerObserver::new(move |event| {
-        if let Some(tx_opt) = tx_opt.take() {
-            tx_opt.send(event).expect("SCContentSharingPickerObserver callback send");
+        if let Some(tx) = tx.take() {
+            let _ = tx.send(event);
         }
     });
 
-    picker.add(observer);
+    picker.add(&observer);
     picker.set_active(true);
-    picker.present_using_content_style(SCShareableContentStyle::None);
+    match (config.display, config.window) {
+        (true, false) => picker.present_using_content_style(SCShareableContentStyle::Display),
+        (false, true) => picker.present_using_content_style(SCShareableContentStyle::Window),
+        _ => picker.present(),
+    }
+    let event = rx.await;
+    picker.remove(&observer);
+    picker.set_active(false);
 
-    match rx.await {
-        Ok(event) => {
-            match event {
-                Ok(SCContentSharingPickerEvent::Cancelled) => Ok(None),
-                Ok(SCContentSharingPickerEvent::DidUpdate { filter, stream }) => {
-                    debug_objc_object(filter.0);
-                    todo!()
-                }
-                Err(e) => Err(SharableContentPickerError::Other(format!("Failed to receive sharable content from picker: {}", e.description())))
-            }
-        }
-        Err(e) => Err(SharableContentPickerError::Other(format!("Failed to receive event from future")))
+    match event {
+        Ok(Ok(SCContentSharingPickerEvent::Cancelled)) => Ok(None),
+        Ok(Ok(SCContentSharingPickerEvent::DidUpdate { filter, .. })) => picked_content_from_filter(&filter).map(Some),
+        Ok(Err(error)) => Err(SharableContentPickerError::Other(format!("Failed to start the picker: {}", error.description()))),
+        Err(_) => Err(SharableContentPickerError::Other("The picker stopped without picking content".into())),
+    }
+}
+
+fn picked_content_from_filter(filter: &SCContentFilter) -> Result<PickedSharableContent, SharableContentPickerError> {
+    let unidentified = || SharableContentPickerError::Other("Identifying the picked content needs MacOS 15.2".into());
+    match filter.style() {
+        SCShareableContentStyle::Window => {
+            let window = filter.included_windows().ok_or_else(unidentified)?
+                .into_iter().next()
+                .ok_or_else(|| SharableContentPickerError::Other("The picked filter doesn't include a window".into()))?;
+            Ok(PickedSharableContent::Window(CapturableWindow::from_impl(ImplCapturableWindow::from_impl(window))))
+        },
+        SCShareableContentStyle::Display => {
+            let display = filter.included_displays().ok_or_else(unidentified)?
+                .into_iter().next()
+                .ok_or_else(|| SharableContentPickerError::Other("The picked filter doesn't include a display".into()))?;
+            Ok(PickedSharableContent::Display(CapturableDisplay { impl_capturable_display: ImplCapturableDisplay::from_impl(display) }))
+        },
+        _ => Err(SharableContentPickerError::Other("The picked content isn't a single window or display".into())),
     }
 }
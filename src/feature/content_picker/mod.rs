@@ -8,7 +8,9 @@ mod windows;
 #[cfg(target_os = "windows")]
 pub use windows::pick_sharable_content;
 
-use crate::prelude::{CapturableApplication, CapturableWindow, CapturableDisplay};
+use std::{error::Error, fmt::Display};
+
+use crate::{error::ErrorKind, prelude::{CapturableApplication, CapturableWindow, CapturableDisplay}};
 
 /// Configuration for the content picker
 /// 
@@ -32,15 +34,64 @@ impl Default for SharableContentPickerConfig {
     }
 }
 
+/// Represents an error picking content with the system content picker
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum SharableContentPickerError {
     /// The picker config doesn't allow for any selectable content
     EmptyConfig,
     /// The backend doesn't support filtering content as specified by the config
     ConfigFilteringUnsupported,
+    /// The picked content couldn't be told apart from other windows or displays (Windows only) - see `pick_sharable_content`
+    AmbiguousPick,
     Other(String),
 }
 
+unsafe impl Send for SharableContentPickerError {}
+unsafe impl Sync for SharableContentPickerError {}
+
+impl SharableContentPickerError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::EmptyConfig => ErrorKind::SharableContentPickerEmptyConfig,
+            Self::ConfigFilteringUnsupported => ErrorKind::SharableContentPickerConfigFilteringUnsupported,
+            Self::AmbiguousPick => ErrorKind::SharableContentPickerAmbiguousPick,
+            Self::Other(_) => ErrorKind::SharableContentPickerOther,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+}
+
+impl Display for SharableContentPickerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EmptyConfig => f.write_str("SharableContentPickerError::EmptyConfig"),
+            Self::ConfigFilteringUnsupported => f.write_str("SharableContentPickerError::ConfigFilteringUnsupported"),
+            Self::AmbiguousPick => f.write_str("SharableContentPickerError::AmbiguousPick"),
+            Self::Other(message) => f.write_fmt(format_args!("SharableContentPickerError::Other(\"{}\")", message)),
+        }
+    }
+}
+
+impl Error for SharableContentPickerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+
+    fn description(&self) -> &str {
+        "description() is deprecated; use Display"
+    }
+
+    fn cause(&self) -> Option<&dyn Error> {
+        self.source()
+    }
+}
+
 /// Content picked by the picker
 pub enum PickedSharableContent {
     Window(CapturableWindow),
@@ -1,19 +1,77 @@
-use std::time::Duration;
+use windows::{core::ComInterface, Graphics::Capture::{GraphicsCaptureItem, GraphicsCapturePicker}, Win32::{System::Com::{CoInitializeEx, COINIT_MULTITHREADED}, UI::{Shell::IInitializeWithWindow, WindowsAndMessaging::GetForegroundWindow}}};
 
-use windows::{ApplicationModel::Core::CoreApplication, Graphics::Capture::GraphicsCapturePicker, Win32::System::Com::{CoInitializeEx, COINIT_MULTITHREADED}, UI::Core::{CoreDispatcher, CoreDispatcherPriority, DispatchedHandler}};
+use crate::{capturable_content::Capturable, platform::platform_impl::capture_stream::create_graphics_capture_item, prelude::{CapturableContent, CapturableContentFilter}};
 
 use super::{PickedSharableContent, SharableContentPickerError, SharableContentPickerConfig};
 
+/// Show the system content picker, and get the window or display the user picked - or `None` if they cancelled
+/// 
+/// The picker is presented over the foreground window. It always offers both windows and displays, and can't exclude applications,
+/// so other configs return `SharableContentPickerError::ConfigFilteringUnsupported`.
+/// 
+/// The system picker doesn't report which window or monitor was picked, only its name and size, so it's matched against
+/// the capturable content by those. If several windows or displays match - E.G. two windows with the same title and size -
+/// `SharableContentPickerError::AmbiguousPick` is returned.
 pub async fn pick_sharable_content(config: SharableContentPickerConfig) -> Result<Option<PickedSharableContent>, SharableContentPickerError> {
-    let close_clr = unsafe { CoInitializeEx(None, COINIT_MULTITHREADED) }.is_ok();
-    if !config.display || !config.window || config.excluded_apps.len() != 0 {
+    if !config.display && !config.window {
+        return Err(SharableContentPickerError::EmptyConfig);
+    }
+    // The system picker always offers both windows and displays, and can't exclude applications
+    if !config.display || !config.window || !config.excluded_apps.is_empty() {
         return Err(SharableContentPickerError::ConfigFilteringUnsupported);
     }
+    // Left initialized, since the rest of the pick may resume on another thread
+    let _ = unsafe { CoInitializeEx(None, COINIT_MULTITHREADED) };
     let picker = GraphicsCapturePicker::new()
-        .map_err(|error| SharableContentPickerError::Other(format!("Faild to create picker instance: {}", error.to_string())))?;
-    let item = picker.PickSingleItemAsync()
-        .map_err(|error| SharableContentPickerError::Other(format!("Failed to start pick dialogue: {}", error.to_string())))?.await;
-    //println!("item: {:?}", item);
-    std::thread::sleep(Duration::from_secs(10));
-    todo!()
+        .map_err(|error| SharableContentPickerError::Other(format!("Failed to create picker instance: {}", error)))?;
+    // Outside of UWP apps, the picker needs a window to be presented over
+    let owner_window = unsafe { GetForegroundWindow() };
+    if owner_window.0 == 0 {
+        return Err(SharableContentPickerError::Other("No foreground window to present the picker over".into()));
+    }
+    picker.cast::<IInitializeWithWindow>()
+        .and_then(|initialize_with_window| unsafe { initialize_with_window.Initialize(owner_window) })
+        .map_err(|error| SharableContentPickerError::Other(format!("Failed to set the picker's owner window: {}", error)))?;
+    let pick_operation = picker.PickSingleItemAsync()
+        .map_err(|error| SharableContentPickerError::Other(format!("Failed to start pick dialogue: {}", error)))?;
+    // A cancelled pick completes with a null item
+    let item = match pick_operation.await {
+        Ok(item) => item,
+        Err(error) if error.code().is_ok() => return Ok(None),
+        Err(error) => return Err(SharableContentPickerError::Other(format!("Failed to pick content: {}", error))),
+    };
+    match_picked_item(&item).await
+}
+
+// The picked item doesn't expose the window or monitor it was created for, so find the enumerated content
+// whose capture item has the same name and size. That isn't unique - two windows with the same title and size,
+// or two identical monitors, can't be told apart - so the pick fails with `AmbiguousPick` if several match.
+async fn match_picked_item(item: &GraphicsCaptureItem) -> Result<Option<PickedSharableContent>, SharableContentPickerError> {
+    let item_name = item.DisplayName()
+        .map_err(|error| SharableContentPickerError::Other(format!("Failed to get the picked item's name: {}", error)))?;
+    let item_size = item.Size()
+        .map_err(|error| SharableContentPickerError::Other(format!("Failed to get the picked item's size: {}", error)))?;
+    let matches_item = |capturable: Capturable| {
+        create_graphics_capture_item(&capturable).is_ok_and(|candidate| {
+            candidate.DisplayName().ok().as_ref() == Some(&item_name) &&
+            candidate.Size().ok() == Some(item_size)
+        })
+    };
+    let content = CapturableContent::new(CapturableContentFilter::EVERYTHING).await
+        .map_err(|error| SharableContentPickerError::Other(format!("Failed to enumerate content to match the picked item: {}", error)))?;
+    let mut matches = content.displays()
+        .filter(|display| matches_item(Capturable::Display(display.clone())))
+        .map(PickedSharableContent::Display)
+        .collect::<Vec<_>>();
+    // The name of a window's capture item is its title, so only windows with the picked title need a capture item to compare sizes
+    let item_title = item_name.to_string_lossy();
+    matches.extend(content.windows()
+        .filter(|window| window.title() == item_title)
+        .filter(|window| matches_item(Capturable::Window(window.clone())))
+        .map(PickedSharableContent::Window));
+    match matches.len() {
+        0 => Err(SharableContentPickerError::Other(format!("Couldn't find the picked item \"{}\" in the capturable content", item_name))),
+        1 => Ok(matches.pop()),
+        _ => Err(SharableContentPickerError::AmbiguousPick),
+    }
 }
@@ -147,8 +147,12 @@ impl FrameDiagnosticExt for crate::prelude::VideoFrame {
                         }
                     }
                 },
-                crate::platform::platform_impl::ImplVideoFrame::CGDisplayStream(_) =>  {
-                    todo!()
+                crate::platform::platform_impl::ImplVideoFrame::CGDisplayStream(cg_display_stream_frame) =>  {
+                    // CGDisplayStream frames carry no ScreenCaptureKit info dictionary
+                    FrameDiagnostic {
+                        iosurface_info: Some(get_iosurface_info(&cg_display_stream_frame.io_surface)),
+                        info_dictionary: Vec::new(),
+                    }
                 },
             }
         }
@@ -157,6 +161,8 @@ impl FrameDiagnosticExt for crate::prelude::VideoFrame {
             let pixel_format = match self.impl_video_frame.pixel_format {
                 DirectXPixelFormat::B8G8R8A8UIntNormalized => "B8G8R8A8UIntNormalized",
                 DirectXPixelFormat::R10G10B10A2UIntNormalized => "R10G10B10A2UIntNormalized",
+                DirectXPixelFormat::R16G16B16A16UIntNormalized => "R16G16B16A16UIntNormalized",
+                DirectXPixelFormat::R16G16B16A16Float => "R16G16B16A16Float",
                 _ => "unknown"
             }.to_string();
             let (width, height) = self.impl_video_frame.frame_size;
@@ -6,13 +6,29 @@ use windows::{core::ComInterface, Graphics::DirectX::{Direct3D11::IDirect3DSurfa
 use std::error::Error;
 use std::fmt::Display;
 
+use crate::error::ErrorKind;
 use crate::prelude::{CaptureStream, VideoFrame};
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum WindowsDx11VideoFrameError {
     Other(String),
 }
 
+impl WindowsDx11VideoFrameError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Other(_) => ErrorKind::WindowsDx11VideoFrameOther,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+}
+
 impl Display for WindowsDx11VideoFrameError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -44,7 +60,7 @@ pub trait WindowsDx11VideoFrame {
 
 impl WindowsDx11VideoFrame for VideoFrame {
     fn get_dx11_surface(&self) -> Result<(IDirect3DSurface, DirectXPixelFormat), WindowsDx11VideoFrameError> {
-        self.impl_video_frame.frame.Surface()
+        self.impl_video_frame.surface()
             .map_err(|e| WindowsDx11VideoFrameError::Other(format!("Failed to get frame surface: {}", e.to_string())))
             .map(|surface| (surface, self.impl_video_frame.pixel_format))
     }
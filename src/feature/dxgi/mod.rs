@@ -1,6 +1,7 @@
 #![cfg(target_os = "windows")]
 #![cfg(feature = "dxgi")]
 
+use crate::error::ErrorKind;
 use crate::prelude::{CaptureStream, VideoFrame};
 
 use std::error::Error;
@@ -12,10 +13,25 @@ use windows::Win32::System::WinRT::Direct3D11::IDirect3DDxgiInterfaceAccess;
 use windows::Win32::Graphics::Direct3D11::ID3D11Texture2D;
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum WindowsDxgiVideoFrameError {
     Other(String),
 }
 
+impl WindowsDxgiVideoFrameError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Other(_) => ErrorKind::WindowsDxgiVideoFrameOther,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+}
+
 impl Display for WindowsDxgiVideoFrameError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -46,7 +62,7 @@ pub trait WindowsDxgiVideoFrame {
 
 impl WindowsDxgiVideoFrame for VideoFrame {
     fn get_dxgi_surface(&self) -> Result<(windows::Win32::Graphics::Dxgi::IDXGISurface, DirectXPixelFormat), WindowsDxgiVideoFrameError> {
-        let d3d11_surface = self.impl_video_frame.frame.Surface()
+        let d3d11_surface = self.impl_video_frame.surface()
             .map_err(|e| WindowsDxgiVideoFrameError::Other(format!("Failed to get frame surface: {}", e.to_string())))?;
         let interface_access: IDirect3DDxgiInterfaceAccess = d3d11_surface.cast()
             .map_err(|e| WindowsDxgiVideoFrameError::Other(format!("Failed to cast d3d11 surface to dxgi interface access: {}", e.to_string())))?;
@@ -59,10 +75,25 @@ impl WindowsDxgiVideoFrame for VideoFrame {
 }
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum WindowsDxgiCaptureStreamError {
     NoAdapter(String)
 }
 
+impl WindowsDxgiCaptureStreamError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::NoAdapter(_) => ErrorKind::WindowsDxgiCaptureStreamNoAdapter,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+}
+
 impl Display for WindowsDxgiCaptureStreamError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -1,38 +1,212 @@
 #![cfg(target_os = "macos")]
 #![cfg(feature = "iosurface")]
 
+use std::marker::PhantomData;
 use std::os::raw::c_void;
 
 use std::error::Error;
 use std::fmt::Display;
 
-use crate::{platform::{macos::{frame::MacosVideoFrame, objc_wrap::IOSurfaceRef}, platform_impl::objc_wrap::{IOSurfaceDecrementUseCount, IOSurfaceIncrementUseCount}}, prelude::VideoFrame};
+use crate::{error::ErrorKind, platform::macos::{frame::MacosVideoFrame, objc_wrap::{IOSurface, IOSurfaceLockError, IOSurfaceLockGaurd, IOSurfaceRef}}, prelude::VideoFrame};
 
 /// A MacOS IOSurface instance
-pub struct IoSurface(IOSurfaceRef);
+/// 
+/// The surface is retained and marked in use while this (or any clone of it) is alive, so its pool won't reuse it
+#[derive(Clone)]
+pub struct IoSurface(IOSurface);
+
+unsafe impl Send for IoSurface {}
+unsafe impl Sync for IoSurface {}
 
 impl IoSurface {
     /// Gets the raw IOSurfaceRef
     pub fn get_raw(&self) -> *const c_void {
-        self.0
+        self.0.0
     }
 
     pub(crate) fn from_ref_unretained(r: IOSurfaceRef) -> Self {
-        unsafe { IOSurfaceIncrementUseCount(r); }
-        IoSurface(r)
+        IoSurface(IOSurface::from_ref_unretained(r))
+    }
+
+    /// Gets the width of the surface in pixels
+    pub fn get_width(&self) -> usize {
+        self.0.get_width()
+    }
+
+    /// Gets the height of the surface in pixels
+    pub fn get_height(&self) -> usize {
+        self.0.get_height()
+    }
+
+    /// Gets the pixel format of the surface
+    pub fn get_pixel_format(&self) -> IoSurfacePixelFormat {
+        IoSurfacePixelFormat::from_code(self.0.get_pixel_format_code())
+    }
+
+    /// Gets the number of planes in the surface - zero for surfaces which aren't planar, such as BGRA surfaces
+    pub fn get_plane_count(&self) -> usize {
+        self.0.get_plane_count()
+    }
+
+    /// Gets the number of bytes in each row of a surface which isn't planar, including any padding
+    pub fn get_bytes_per_row(&self) -> usize {
+        self.0.get_bytes_per_row()
+    }
+
+    /// Gets the number of bytes in each row of the given plane, including any padding
+    pub fn get_bytes_per_row_of_plane(&self, plane: usize) -> usize {
+        self.0.get_bytes_per_row_of_plane(plane)
+    }
+
+    /// Gets the width of the given plane in pixels, E.G. half the surface's width for the chroma plane of a 4:2:0 surface
+    pub fn get_width_of_plane(&self, plane: usize) -> usize {
+        self.0.get_width_of_plane(plane)
+    }
+
+    /// Gets the height of the given plane in pixels
+    pub fn get_height_of_plane(&self, plane: usize) -> usize {
+        self.0.get_height_of_plane(plane)
+    }
+
+    /// Lock the surface for access to its pixel data from the CPU, until the returned guard is dropped
+    /// 
+    /// Locking synchronizes with any GPU work on the surface. A read only lock can't write to the surface,
+    /// and doesn't mark the surface's contents as changed.
+    pub fn lock(&self, read_only: bool) -> Result<IoSurfaceLockGuard<'_>, IoSurfaceLockError> {
+        match self.0.lock(read_only, false) {
+            Ok(lock) => Ok(IoSurfaceLockGuard {
+                lock,
+                surface: self,
+                read_only,
+                phantom: PhantomData,
+            }),
+            Err(IOSurfaceLockError::CannotLock) => Err(IoSurfaceLockError::CannotLock),
+            Err(IOSurfaceLockError::Other) => Err(IoSurfaceLockError::Other),
+        }
     }
 }
 
-impl Clone for IoSurface {
-    fn clone(&self) -> Self {
-        unsafe { IOSurfaceIncrementUseCount(self.0); }
-        IoSurface(self.0)
+/// The pixel format of an IOSurface
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IoSurfacePixelFormat {
+    /// 'BGRA' - 8 bit blue, green, red and alpha channels, matching `CapturePixelFormat::Bgra8888`
+    Bgra8888,
+    /// 'l10r' - little endian packed 2 bit alpha and 10 bit red, green and blue channels, matching `CapturePixelFormat::Argb2101010`
+    Argb2101010,
+    /// '420v' - video range YCbCr 4:2:0, with a luminance plane and an interleaved chroma plane, matching `CapturePixelFormat::V420`
+    V420,
+    /// '420f' - full range YCbCr 4:2:0, with a luminance plane and an interleaved chroma plane, matching `CapturePixelFormat::F420`
+    F420,
+    /// Another pixel format, by its four character code
+    Other(u32),
+}
+
+impl IoSurfacePixelFormat {
+    fn from_code(code: u32) -> Self {
+        match code {
+            0x42475241 => Self::Bgra8888,
+            0x6C313072 => Self::Argb2101010,
+            0x34323076 => Self::V420,
+            0x34323066 => Self::F420,
+            _ => Self::Other(code),
+        }
     }
 }
 
-impl Drop for IoSurface {
-    fn drop(&mut self) {
-        unsafe { IOSurfaceDecrementUseCount(self.0); }
+/// Access to the pixel data of a locked IOSurface, which is unlocked when this is dropped - see `IoSurface::lock()`
+pub struct IoSurfaceLockGuard<'surface> {
+    lock: IOSurfaceLockGaurd,
+    surface: &'surface IoSurface,
+    read_only: bool,
+    // The pixel data may only be accessed from the thread which locked the surface
+    phantom: PhantomData<*const ()>,
+}
+
+impl IoSurfaceLockGuard<'_> {
+    // The address and length of the given plane, or of the whole surface for plane 0 of a surface which isn't planar
+    fn plane_range(&self, plane: usize) -> Option<(*const c_void, usize)> {
+        if self.surface.get_plane_count() == 0 {
+            if plane != 0 {
+                return None;
+            }
+            let address = self.lock.get_base_address()?;
+            Some((address, self.surface.get_bytes_per_row() * self.surface.get_height()))
+        } else {
+            if plane >= self.surface.get_plane_count() {
+                return None;
+            }
+            let address = self.lock.get_base_address_of_plane(plane)?;
+            Some((address, self.surface.get_bytes_per_row_of_plane(plane) * self.surface.get_height_of_plane(plane)))
+        }
+    }
+
+    /// Gets the bytes of the given plane, row by row with `IoSurface::get_bytes_per_row_of_plane()` bytes in each row
+    /// 
+    /// For a surface which isn't planar, plane 0 is the whole surface, with `IoSurface::get_bytes_per_row()` bytes in each row.
+    /// Returns `None` if the plane doesn't exist.
+    pub fn plane_data(&self, plane: usize) -> Option<&[u8]> {
+        let (address, length) = self.plane_range(plane)?;
+        Some(unsafe { std::slice::from_raw_parts(address as *const u8, length) })
+    }
+
+    /// Gets the bytes of the given plane for writing, as for `plane_data()`
+    /// 
+    /// Returns `None` if the plane doesn't exist, or the surface was locked read only.
+    pub fn plane_data_mut(&mut self, plane: usize) -> Option<&mut [u8]> {
+        if self.read_only {
+            return None;
+        }
+        let (address, length) = self.plane_range(plane)?;
+        Some(unsafe { std::slice::from_raw_parts_mut(address as *mut u8, length) })
+    }
+}
+
+#[derive(Debug)]
+/// Represents an error when locking an IOSurface
+#[non_exhaustive]
+pub enum IoSurfaceLockError {
+    /// The surface couldn't be locked, E.G. because it's in use by the GPU in a way that prevents CPU access
+    CannotLock,
+    /// Locking failed for another reason
+    Other,
+}
+
+impl IoSurfaceLockError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::CannotLock => ErrorKind::IoSurfaceLockCannotLock,
+            Self::Other => ErrorKind::IoSurfaceLockOther,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+}
+
+impl Display for IoSurfaceLockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CannotLock => f.write_str("IoSurfaceLockError::CannotLock"),
+            Self::Other => f.write_str("IoSurfaceLockError::Other"),
+        }
+    }
+}
+
+impl Error for IoSurfaceLockError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+
+    fn description(&self) -> &str {
+        "description() is deprecated; use Display"
+    }
+
+    fn cause(&self) -> Option<&dyn Error> {
+        self.source()
     }
 }
 
@@ -44,6 +218,7 @@ pub trait MacosIoSurfaceVideoFrameExt {
 
 #[derive(Debug)]
 /// Represents an error when getting the IOSurface behind this video frame
+#[non_exhaustive]
 pub enum GetIoSurfaceError{
     /// There was no image buffer in this frame
     NoImageBuffer,
@@ -51,6 +226,21 @@ pub enum GetIoSurfaceError{
     NoIoSurface
 }
 
+impl GetIoSurfaceError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::NoImageBuffer => ErrorKind::GetIoSurfaceNoImageBuffer,
+            Self::NoIoSurface => ErrorKind::GetIoSurfaceNoIoSurface,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+}
+
 impl Display for GetIoSurfaceError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -7,8 +7,9 @@ use objc2::runtime::AnyObject;
 use objc2::Encode;
 use objc2::Encoding;
 
-use crate::platform::platform_impl::objc_wrap::CVPixelFormat;
-use crate::prelude::{CaptureStream, VideoFrame};
+use crate::error::ErrorKind;
+use crate::platform::platform_impl::objc_wrap::{CVPixelFormat, IOSurface};
+use crate::prelude::{Capturable, CapturableDisplay, CaptureStream, VideoFrame};
 
 use std::error::Error;
 use std::fmt::Display;
@@ -29,6 +30,7 @@ pub enum MetalVideoFramePlaneTexture {
 
 /// Represents an error getting the texture from a video frame
 #[derive(Clone, Debug)]
+#[non_exhaustive]
 pub enum MacosVideoFrameError {
     // Could not retreive the IOSurface for this frame
     NoIoSurface,
@@ -40,6 +42,23 @@ pub enum MacosVideoFrameError {
 }
 
 
+impl MacosVideoFrameError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::NoIoSurface => ErrorKind::MacosVideoFrameNoIoSurface,
+            Self::NoImageBuffer => ErrorKind::MacosVideoFrameNoImageBuffer,
+            Self::InvalidVideoPlaneTexture => ErrorKind::MacosVideoFrameInvalidVideoPlaneTexture,
+            Self::Other(_) => ErrorKind::MacosVideoFrameOther,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+}
+
 impl Display for MacosVideoFrameError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -81,80 +100,75 @@ unsafe impl Encode for IOSurfacePtrEncoded {
 #[cfg(feature="metal")]
 impl MetalVideoFrameExt for VideoFrame {
     fn get_metal_texture(&self, plane: MetalVideoFramePlaneTexture) -> Result<metal::Texture, MacosVideoFrameError> {
-        let iosurface_and_metal_device = match &self.impl_video_frame {
-            MacosVideoFrame::SCStream(frame) => {
-                match frame.sample_buffer.get_image_buffer() {
-                    Some(image_buffer) => {
-                        match image_buffer.get_iosurface() {
-                            Some(iosurface) => {
-                                Ok((iosurface, frame.metal_device.clone()))
-                            },
-                            None => Err(MacosVideoFrameError::NoIoSurface)
-                        }
-                    },
-                    None => Err(MacosVideoFrameError::NoImageBuffer)
-                }
-            },
-            MacosVideoFrame::CGDisplayStream(frame) => {
-                Ok((frame.io_surface.clone(), Some(frame.metal_device.clone())))
-            }
-        }?;
-        let (iosurface, metal_device) = iosurface_and_metal_device;
-        let pixel_format = match iosurface.get_pixel_format() {
-            None => return Err(MacosVideoFrameError::Other("Unable to get pixel format from iosurface".to_string())),
-            Some(format) => format
-        };
-        match pixel_format {
-            CVPixelFormat::BGRA8888 => {
-                match plane {
-                    MetalVideoFramePlaneTexture::Rgba => {},
-                    _ => return Err(MacosVideoFrameError::InvalidVideoPlaneTexture),
-                }
-                unsafe {
-                    let device_ref = metal_device.as_ref().unwrap().as_ptr();
-                    let texture_descriptor = metal::TextureDescriptor::new();
-                    texture_descriptor.set_texture_type(metal::MTLTextureType::D2);
-                    texture_descriptor.set_pixel_format(metal::MTLPixelFormat::BGRA8Unorm);
-                    texture_descriptor.set_width(iosurface.get_width() as u64);
-                    texture_descriptor.set_height(iosurface.get_height() as u64);
-                    texture_descriptor.set_sample_count(1);
-                    texture_descriptor.set_mipmap_level_count(1);
-                    texture_descriptor.set_storage_mode(metal::MTLStorageMode::Shared);
-                    texture_descriptor.set_cpu_cache_mode(metal::MTLCPUCacheMode::DefaultCache);
-                    let texture_ptr: *mut AnyObject = msg_send![device_ref as *mut AnyObject, newTextureWithDescriptor: texture_descriptor.as_ptr() as *mut AnyObject, iosurface: IOSurfacePtrEncoded(iosurface.0), plane: 0usize];
-                    if texture_ptr.is_null() {
-                        Err(MacosVideoFrameError::Other("Failed to create metal texture".to_string()))
-                    } else {
-                        Ok((metal::Texture::from_ptr(texture_ptr as *mut metal::MTLTexture)).to_owned())
-                    }
-                }
-            },
-            CVPixelFormat::V420 | CVPixelFormat::F420 => {
-                let (plane, pixel_format) = match plane {
-                    MetalVideoFramePlaneTexture::Luminance => (0, metal::MTLPixelFormat::R8Uint),
-                    MetalVideoFramePlaneTexture::Chroma => (1, metal::MTLPixelFormat::RG8Uint),
-                    _ => return Err(MacosVideoFrameError::InvalidVideoPlaneTexture),
-                };
-                unsafe {
-                    let device_ref = metal_device.as_ref().unwrap().as_ptr();
-                    let texture_descriptor = metal::TextureDescriptor::new();
-                    texture_descriptor.set_texture_type(metal::MTLTextureType::D2);
-                    texture_descriptor.set_pixel_format(pixel_format);
-                    texture_descriptor.set_width(iosurface.get_width() as u64);
-                    texture_descriptor.set_height(iosurface.get_height_of_plane(plane) as u64);
-                    texture_descriptor.set_sample_count(1);
-                    texture_descriptor.set_mipmap_level_count(1);
-                    texture_descriptor.set_storage_mode(metal::MTLStorageMode::Shared);
-                    texture_descriptor.set_cpu_cache_mode(metal::MTLCPUCacheMode::DefaultCache);
-                    let texture_ptr: *mut AnyObject = msg_send![device_ref as *mut AnyObject, newTextureWithDescriptor: texture_descriptor.as_ptr() as *mut AnyObject, iosurface: iosurface.0, plane: plane];
-                    if texture_ptr.is_null() {
-                        Err(MacosVideoFrameError::Other("Failed to create metal texture".to_string()))
-                    } else {
-                        Ok((metal::Texture::from_ptr(texture_ptr as *mut metal::MTLTexture)).to_owned())
+        let metal_device = match &self.impl_video_frame {
+            MacosVideoFrame::SCStream(frame) => frame.metal_device.clone(),
+            MacosVideoFrame::CGDisplayStream(frame) => Some(frame.metal_device.clone()),
+        }.ok_or(MacosVideoFrameError::Other("No metal device for this frame".to_string()))?;
+        get_metal_texture_with_device(self, plane, &metal_device, metal::MTLTextureUsage::ShaderRead)
+    }
+}
+
+// Create a metal texture with the given usage for a plane of a video frame on the given device, rather than the frame's own
+pub(crate) fn get_metal_texture_with_device(frame: &VideoFrame, plane: MetalVideoFramePlaneTexture, metal_device: &metal::Device, usage: metal::MTLTextureUsage) -> Result<metal::Texture, MacosVideoFrameError> {
+    let iosurface = match &frame.impl_video_frame {
+        MacosVideoFrame::SCStream(frame) => {
+            match frame.sample_buffer.get_image_buffer() {
+                Some(image_buffer) => {
+                    match image_buffer.get_iosurface() {
+                        Some(iosurface) => Ok(iosurface),
+                        None => Err(MacosVideoFrameError::NoIoSurface)
                     }
-                }
-            },
-            _ => Err(MacosVideoFrameError::Other("Unknown pixel format on iosurface".to_string())),
+                },
+                None => Err(MacosVideoFrameError::NoImageBuffer)
+            }
+        },
+        MacosVideoFrame::CGDisplayStream(frame) => Ok(frame.io_surface.clone()),
+    }?;
+    let pixel_format = match iosurface.get_pixel_format() {
+        None => return Err(MacosVideoFrameError::Other("Unable to get pixel format from iosurface".to_string())),
+        Some(format) => format
+    };
+    match pixel_format {
+        CVPixelFormat::BGRA8888 => {
+            match plane {
+                MetalVideoFramePlaneTexture::Rgba => {},
+                _ => return Err(MacosVideoFrameError::InvalidVideoPlaneTexture),
+            }
+            new_iosurface_texture(metal_device, &iosurface, 0, metal::MTLPixelFormat::BGRA8Unorm, (iosurface.get_width(), iosurface.get_height()), usage)
+                .map_err(MacosVideoFrameError::Other)
+        },
+        CVPixelFormat::V420 | CVPixelFormat::F420 => {
+            let (plane, pixel_format) = match plane {
+                MetalVideoFramePlaneTexture::Luminance => (0, metal::MTLPixelFormat::R8Uint),
+                MetalVideoFramePlaneTexture::Chroma => (1, metal::MTLPixelFormat::RG8Uint),
+                _ => return Err(MacosVideoFrameError::InvalidVideoPlaneTexture),
+            };
+            new_iosurface_texture(metal_device, &iosurface, plane, pixel_format, (iosurface.get_width_of_plane(plane), iosurface.get_height_of_plane(plane)), usage)
+                .map_err(MacosVideoFrameError::Other)
+        },
+        _ => Err(MacosVideoFrameError::Other("Unknown pixel format on iosurface".to_string())),
+    }
+}
+
+// Create a metal texture of the given format and size backed by a plane of an IOSurface
+pub(crate) fn new_iosurface_texture(metal_device: &metal::Device, iosurface: &IOSurface, plane: usize, pixel_format: metal::MTLPixelFormat, size: (usize, usize), usage: metal::MTLTextureUsage) -> Result<metal::Texture, String> {
+    unsafe {
+        let device_ref = metal_device.as_ptr();
+        let texture_descriptor = metal::TextureDescriptor::new();
+        texture_descriptor.set_texture_type(metal::MTLTextureType::D2);
+        texture_descriptor.set_pixel_format(pixel_format);
+        texture_descriptor.set_width(size.0 as u64);
+        texture_descriptor.set_height(size.1 as u64);
+        texture_descriptor.set_sample_count(1);
+        texture_descriptor.set_mipmap_level_count(1);
+        texture_descriptor.set_storage_mode(metal::MTLStorageMode::Shared);
+        texture_descriptor.set_cpu_cache_mode(metal::MTLCPUCacheMode::DefaultCache);
+        texture_descriptor.set_usage(usage);
+        let texture_ptr: *mut AnyObject = msg_send![device_ref as *mut AnyObject, newTextureWithDescriptor: texture_descriptor.as_ptr() as *mut AnyObject, iosurface: IOSurfacePtrEncoded(iosurface.0), plane: plane];
+        if texture_ptr.is_null() {
+            Err("Failed to create metal texture".to_string())
+        } else {
+            Ok(metal::Texture::from_ptr(texture_ptr as *mut metal::MTLTexture))
         }
     }
 }
@@ -170,3 +184,46 @@ impl MetalCaptureStreamExt for CaptureStream {
         self.impl_capture_stream.metal_device.clone()
     }
 }
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGDirectDisplayCopyCurrentMetalDevice(display: u32) -> *mut c_void;
+}
+
+// Get the metal device for the GPU currently driving the given display
+pub(crate) fn get_display_metal_device(display_id: u32) -> Option<metal::Device> {
+    unsafe {
+        let device_ptr = CGDirectDisplayCopyCurrentMetalDevice(display_id);
+        if device_ptr.is_null() {
+            None
+        } else {
+            // The device is returned retained, so this takes ownership of it
+            Some(metal::Device::from_ptr(device_ptr as *mut metal::MTLDevice))
+        }
+    }
+}
+
+// The metal device to capture the target with when none is configured - the GPU driving a captured display, otherwise the system default
+pub(crate) fn default_metal_device(target: &Capturable) -> Option<metal::Device> {
+    let display_metal_device = match target {
+        Capturable::Display(display) => get_display_metal_device(display.impl_capturable_display.display.raw_id()),
+        _ => None,
+    };
+    display_metal_device.or_else(metal::Device::system_default)
+}
+
+/// A capturable display which inter-operates with Metal
+pub trait MetalCapturableDisplayExt {
+    /// Get the metal device for the GPU currently driving this display
+    /// 
+    /// On Macs with more than one GPU, using this device avoids copying frames between GPUs. Capture streams of a display
+    /// use this device by default, unless one is given with `MacosCaptureConfigExt::with_metal_device`.
+    /// Note that the GPU driving a display may change, E.G. when automatic graphics switching is enabled.
+    fn preferred_metal_device(&self) -> Option<metal::Device>;
+}
+
+impl MetalCapturableDisplayExt for CapturableDisplay {
+    fn preferred_metal_device(&self) -> Option<metal::Device> {
+        get_display_metal_device(self.impl_capturable_display.display.raw_id())
+    }
+}
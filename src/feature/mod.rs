@@ -1,37 +1,61 @@
 #[cfg(feature = "metal")]
 #[cfg(target_os="macos")]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "metal", target_os = "macos"))))]
 /// Frame -> Metal Texture conversion
 /// (requires `metal` feature)
 pub mod metal;
 #[cfg(feature = "dxgi")]
 #[cfg(target_os="windows")]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "dxgi", target_os = "windows"))))]
 /// Frame -> DXGI Surface conversion
 /// (requires `dxgi` feature)
 pub mod dxgi;
 #[cfg(feature = "dx11")]
 #[cfg(target_os="windows")]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "dx11", target_os = "windows"))))]
 /// Frame -> DX11 Surface/Texture conversion
 /// (requires `dx11` feature)
 pub mod dx11;
 #[cfg(feature = "iosurface")]
 #[cfg(target_os="macos")]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "iosurface", target_os = "macos"))))]
 /// Frame -> IOSurface conversion
 /// (requires `iosurface` feature)
 pub mod iosurface;
 #[cfg(feature = "bitmap")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bitmap")))]
 /// Frame to Bitmap conversion
 /// (requires `bitmap` feature)
 pub mod bitmap;
 #[cfg(feature = "wgpu")]
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "wgpu", any(target_os = "macos", target_os = "windows")))))]
 /// Frame -> Wgpu Texture conversion
 /// (requires `wgpu` feature)
 pub mod wgpu;
 #[cfg(feature = "screenshot")]
+#[cfg_attr(docsrs, doc(cfg(feature = "screenshot")))]
 /// Screenshot utility function
 /// (requires `screenshot` feature)
 pub mod screenshot;
-//#[cfg(feature = "content_picker")]
-//pub mod content_picker;
+#[cfg(feature = "png")]
+#[cfg_attr(docsrs, doc(cfg(feature = "png")))]
+/// Frame -> PNG file encoding
+/// (requires `png` feature)
+pub mod png;
+#[cfg(feature = "sink")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sink")))]
+/// Frame sinks, for passing a stream's video frames to an encoder
+/// (requires `sink` feature)
+pub mod sink;
+#[cfg(feature = "content_picker")]
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "content_picker", any(target_os = "macos", target_os = "windows")))))]
+/// System content picker
+/// (requires `content_picker` feature)
+pub mod content_picker;
 
 #[cfg(feature = "diagnostic")]
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "diagnostic", any(target_os = "macos", target_os = "windows")))))]
 pub mod diagnostic;
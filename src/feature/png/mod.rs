@@ -0,0 +1,220 @@
+use std::path::Path;
+
+use crate::feature::bitmap::{BitmapDataBgra8x4, FrameBitmapRgbaUnorm8x4, VideoFrameBitmap, VideoFrameBitmapError};
+use crate::prelude::VideoFrame;
+
+// The largest payload of a single stored (uncompressed) deflate block
+const MAX_STORED_BLOCK_LEN: usize = 0xFFFF;
+
+impl VideoFrame {
+    /// Save this frame to a PNG file at the given path, replacing any existing file
+    /// 
+    /// The frame is copied to a bitmap with `VideoFrameBitmap::get_bitmap()` and converted to Rgba8888
+    /// with `FrameBitmap::to_rgba_unorm8()`, so every pixel format is supported - see there for how each is converted.
+    /// The image data is stored uncompressed, so files are roughly the size of the raw bitmap.
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), VideoFrameBitmapError> {
+        let png = self.get_bitmap()?.to_rgba_unorm8().encode_png();
+        std::fs::write(path.as_ref(), png)
+            .map_err(|error| VideoFrameBitmapError::Other(format!("Failed to write png file \"{}\": {}", path.as_ref().display(), error)))
+    }
+}
+
+impl<Data: BitmapDataBgra8x4> FrameBitmapRgbaUnorm8x4<Data> {
+    /// Encode this bitmap as a PNG file, returning the file's bytes
+    /// 
+    /// The image data is stored uncompressed, so the result is roughly the size of the raw bitmap.
+    pub fn encode_png(&self) -> Vec<u8> {
+        // Each row of the image data is prefixed with its filter type - always 0 (None) here
+        let row_len = self.width * 4 + 1;
+        let mut image_data = Vec::with_capacity(row_len * self.height);
+        for row in self.data.as_ref().chunks_exact(self.width.max(1)).take(self.height) {
+            image_data.push(0);
+            image_data.extend(row.iter().flatten());
+        }
+
+        let mut header = Vec::with_capacity(13);
+        header.extend((self.width as u32).to_be_bytes());
+        header.extend((self.height as u32).to_be_bytes());
+        // 8 bit depth, truecolor with alpha, deflate compression, adaptive filtering, no interlacing
+        header.extend([8, 6, 0, 0, 0]);
+
+        let mut png = Vec::with_capacity(image_data.len() + image_data.len() / MAX_STORED_BLOCK_LEN * 5 + 64);
+        png.extend(b"\x89PNG\r\n\x1a\n");
+        write_chunk(&mut png, b"IHDR", &header);
+        write_chunk(&mut png, b"IDAT", &zlib_stored(&image_data));
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
+
+// Write a PNG chunk - the length, type, data and CRC of the type and data
+fn write_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    png.extend(chunk_type);
+    png.extend(data);
+    let crc = !crc32(crc32(!0, chunk_type), data);
+    png.extend(crc.to_be_bytes());
+}
+
+// Wrap the data in a zlib stream of stored (uncompressed) deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut stream = Vec::with_capacity(data.len() + data.len() / MAX_STORED_BLOCK_LEN * 5 + 11);
+    // Deflate with a 32K window, no preset dictionary, and a check value making the header a multiple of 31
+    stream.extend([0x78, 0x01]);
+    let mut blocks = data.chunks(MAX_STORED_BLOCK_LEN).peekable();
+    if blocks.peek().is_none() {
+        // An empty final block
+        stream.extend([1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        let len = block.len() as u16;
+        stream.push(is_final as u8);
+        stream.extend(len.to_le_bytes());
+        stream.extend((!len).to_le_bytes());
+        stream.extend(block);
+    }
+    stream.extend(adler32(data).to_be_bytes());
+    stream
+}
+
+// The CRC-32 (as used by PNG) of each byte value
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { 0xEDB88320 ^ (crc >> 1) } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+// Update a running CRC-32 with the given bytes
+fn crc32(crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |crc, byte| CRC32_TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8))
+}
+
+// The Adler-32 checksum of the given bytes (as used by zlib)
+fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the most bytes that can be summed before `b` could overflow
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_crc32(data: &[u8]) -> u32 {
+        !crc32(!0, data)
+    }
+
+    // Reassemble the data of a zlib stream of stored deflate blocks, checking each block header and the final Adler-32
+    fn unstore(stream: &[u8]) -> Vec<u8> {
+        assert_eq!(&stream[..2], &[0x78, 0x01]);
+        assert_eq!(u16::from_be_bytes([stream[0], stream[1]]) % 31, 0);
+        let mut data = Vec::new();
+        let mut offset = 2;
+        loop {
+            let is_final = stream[offset];
+            let len = u16::from_le_bytes([stream[offset + 1], stream[offset + 2]]);
+            let nlen = u16::from_le_bytes([stream[offset + 3], stream[offset + 4]]);
+            assert_eq!(nlen, !len);
+            offset += 5;
+            data.extend(&stream[offset..(offset + len as usize)]);
+            offset += len as usize;
+            if is_final == 1 {
+                break;
+            }
+            assert_eq!(is_final, 0);
+        }
+        assert_eq!(&stream[offset..], &adler32(&data).to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn crc32_matches_known_values() {
+        assert_eq!(png_crc32(b"IEND"), 0xAE426082);
+        assert_eq!(png_crc32(b"123456789"), 0xCBF43926);
+        assert_eq!(png_crc32(b""), 0);
+    }
+
+    #[test]
+    fn adler32_matches_known_values() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+        // Long enough to need the sums reduced partway through
+        assert_eq!(adler32(&[0xFF; 100000]), 0x149A302C);
+    }
+
+    #[test]
+    fn empty_data_is_stored_as_a_single_empty_block() {
+        assert_eq!(zlib_stored(&[]), vec![0x78, 0x01, 1, 0, 0, 0xFF, 0xFF, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn data_longer_than_a_block_is_split_into_stored_blocks() {
+        let data: Vec<u8> = (0..(MAX_STORED_BLOCK_LEN * 2 + 10)).map(|i| (i % 251) as u8).collect();
+        let stream = zlib_stored(&data);
+        // Two full blocks and a final block of the remainder, each with a five byte header
+        assert_eq!(stream.len(), 2 + data.len() + 3 * 5 + 4);
+        assert_eq!(&stream[2..7], &[0, 0xFF, 0xFF, 0, 0]);
+        assert_eq!(&stream[(7 + MAX_STORED_BLOCK_LEN)..(12 + MAX_STORED_BLOCK_LEN)], &[0, 0xFF, 0xFF, 0, 0]);
+        assert_eq!(unstore(&stream), data);
+    }
+
+    #[test]
+    fn tiny_bitmaps_encode_to_exact_bytes() {
+        let bitmap = FrameBitmapRgbaUnorm8x4 {
+            data: vec![[255u8, 0, 0, 255], [0, 0, 255, 128]].into_boxed_slice(),
+            width: 2,
+            height: 1,
+            change_metric: None,
+        };
+        assert_eq!(bitmap.encode_png(), vec![
+            0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A,
+            // IHDR
+            0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52,
+            0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00,
+            0xF4, 0x22, 0x7F, 0x8A,
+            // IDAT
+            0x00, 0x00, 0x00, 0x14, 0x49, 0x44, 0x41, 0x54,
+            0x78, 0x01, 0x01, 0x09, 0x00, 0xF6, 0xFF,
+            0x00, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF, 0x80,
+            0x0F, 0x7A, 0x03, 0x7E,
+            0x4A, 0x6E, 0x70, 0x0C,
+            // IEND
+            0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82,
+        ]);
+    }
+
+    #[test]
+    fn every_row_is_prefixed_with_its_filter_type() {
+        let bitmap = FrameBitmapRgbaUnorm8x4 {
+            data: vec![[1u8, 2, 3, 4]; 6].into_boxed_slice(),
+            width: 2,
+            height: 3,
+            change_metric: None,
+        };
+        let png = bitmap.encode_png();
+        // The IDAT chunk's data follows the signature, the 25 byte IHDR chunk and the IDAT length and type
+        let idat_len = u32::from_be_bytes([png[33], png[34], png[35], png[36]]) as usize;
+        let image_data = unstore(&png[41..(41 + idat_len)]);
+        assert_eq!(image_data, [0, 1, 2, 3, 4, 1, 2, 3, 4].repeat(3));
+    }
+}
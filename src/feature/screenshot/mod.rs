@@ -1,21 +1,118 @@
 mod platform;
-use std::{error::Error, fmt::Display};
+use std::{error::Error, fmt::Display, time::Duration};
 
-pub use platform::take_screenshot;
+use platform::take_screenshot_impl;
+
+use crate::error::ErrorKind;
+use crate::frame::VideoFrame;
+use crate::prelude::{CaptureAccessToken, CaptureConfig};
+use crate::util::sleep;
+
+/// Take a screenshot of the capturable content given a configuration
+/// 
+/// This waits for the first frame of the content, however long it takes - a minimized window may never produce one,
+/// so prefer `take_screenshot_with_timeout` when the target isn't known to be visible.
+/// 
+/// The screenshot is a full `VideoFrame` that stays on the GPU until asked otherwise, so it suits thumbnailing many windows -
+/// the metal, dx11 and wgpu extensions work on it as they do on stream frames, and no bitmap is made unless one is requested
+/// with the `bitmap` extensions. On MacOS 14+, it's taken with `SCScreenshotManager` rather than a stream; elsewhere, a
+/// one-shot capture is started for the frame, and torn down before returning.
+/// 
+/// On Windows, the token must allow every capability the configuration uses - borderless window capture
+/// (`WindowsCaptureConfigExt::with_borderless`) requires a token from `test_access(true)` or `request_access(true)`,
+/// otherwise `ScreenshotError::InsufficientAccess` is returned. On MacOS, every access token grants all capture
+/// capabilities (including borderless capture), so only screen recording permission is checked.
+pub async fn take_screenshot(token: CaptureAccessToken, config: CaptureConfig) -> Result<VideoFrame, ScreenshotError> {
+    take_screenshot_impl(token, screenshot_config(config), None).await
+}
+
+/// Take a screenshot that stays on the GPU - the same as `take_screenshot`, named for callers that want a texture rather than a bitmap
+/// 
+/// Use the metal, dx11 or wgpu extensions on the returned frame to get its texture.
+pub async fn take_screenshot_texture(token: CaptureAccessToken, config: CaptureConfig) -> Result<VideoFrame, ScreenshotError> {
+    take_screenshot(token, config).await
+}
+
+/// Take a screenshot of the capturable content given a configuration, failing with `ScreenshotError::Timeout`
+/// if no frame arrives within the timeout - see `take_screenshot`
+/// 
+/// On timeout, the capture started for the screenshot is stopped before returning.
+pub async fn take_screenshot_with_timeout(token: CaptureAccessToken, config: CaptureConfig, timeout: Duration) -> Result<VideoFrame, ScreenshotError> {
+    take_screenshot_impl(token, screenshot_config(config), Some(timeout)).await
+}
+
+/// Take a screenshot of the capturable content given a configuration, after waiting for the given delay
+/// 
+/// Useful for "capture in 5 seconds" countdowns, so the user can set up the screen. The delay is timed internally,
+/// without blocking the calling task's executor.
+pub async fn take_screenshot_after(token: CaptureAccessToken, config: CaptureConfig, delay: Duration) -> Result<VideoFrame, ScreenshotError> {
+    if !delay.is_zero() {
+        sleep(delay).await.map_err(|_| ScreenshotError::Other("Failed to wait for screenshot delay".into()))?;
+    }
+    take_screenshot(token, config).await
+}
+
+/// Take a screenshot of the capturable content given a configuration, blocking the calling thread until the frame arrives
+/// 
+/// For one-off grabs such as thumbnails, without an async runtime - the frame is a full `VideoFrame`, so the bitmap and
+/// GPU interop extensions work on it, using the configured metal or wgpu device where there is one. Like `take_screenshot`,
+/// this waits however long the first frame takes. Don't call it from an async task, as it blocks the task's executor.
+pub fn take_frame(token: CaptureAccessToken, config: CaptureConfig) -> Result<VideoFrame, ScreenshotError> {
+    futures::executor::block_on(take_screenshot(token, config))
+}
+
+// Screenshots are always of the target's video, even when taken with an audio-only config
+fn screenshot_config(config: CaptureConfig) -> CaptureConfig {
+    CaptureConfig {
+        capture_video: true,
+        ..config
+    }
+}
 
 #[derive(Debug)]
 /// Represents an error while taking a screenshot
+#[non_exhaustive]
 pub enum ScreenshotError {
-    Other(String)
+    Other(String),
+    /// The access token lacks a capability required by the screenshot configuration, E.G. borderless capture
+    InsufficientAccess(String),
+    /// The application doesn't (or no longer) have permission to capture content
+    PermissionDenied,
+    /// The window or display to capture no longer exists
+    TargetGone,
+    /// The screenshot wasn't produced in time
+    Timeout,
 }
 
 unsafe impl Send for ScreenshotError {}
 unsafe impl Sync for ScreenshotError {}
 
+impl ScreenshotError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Other(_) => ErrorKind::ScreenshotOther,
+            Self::InsufficientAccess(_) => ErrorKind::ScreenshotInsufficientAccess,
+            Self::PermissionDenied => ErrorKind::ScreenshotPermissionDenied,
+            Self::TargetGone => ErrorKind::ScreenshotTargetGone,
+            Self::Timeout => ErrorKind::ScreenshotTimeout,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+}
+
 impl Display for ScreenshotError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Other(error) => f.write_fmt(format_args!("ScreenshotError::Other({})", error)),
+            Self::InsufficientAccess(capability) => f.write_fmt(format_args!("ScreenshotError::InsufficientAccess({})", capability)),
+            Self::PermissionDenied => f.write_str("ScreenshotError::PermissionDenied"),
+            Self::TargetGone => f.write_str("ScreenshotError::TargetGone"),
+            Self::Timeout => f.write_str("ScreenshotError::Timeout"),
         }
     }
 }
@@ -1,47 +1,99 @@
 use std::cell::RefCell;
-use std::time::Instant;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::feature::screenshot::ScreenshotError;
+use parking_lot::Mutex;
+
+use crate::{feature::screenshot::ScreenshotError, util::with_timeout};
+#[cfg(feature = "metal")]
+use crate::feature::metal::default_metal_device;
 use crate::frame::VideoFrame;
+use crate::platform::macos::capture_stream::{sc_content_filter_for_target, stop_sc_stream};
 use crate::platform::macos::frame::{MacosSCStreamVideoFrame, MacosVideoFrame};
-use crate::platform::macos::objc_wrap::{CGSize, NSArray, SCContentFilter, SCScreenshotManager, SCStreamColorMatrix, SCStreamConfiguration, SCStreamPixelFormat};
-use crate::platform::platform_impl::objc_wrap::{CGMainDisplayID, CMTime, DispatchQueue, SCStream, SCStreamCallbackError, SCStreamHandler, SCStreamOutputType};
-use crate::prelude::{Capturable, CaptureAccessToken, CaptureConfig, CapturePixelFormat};
+use crate::platform::macos::objc_wrap::{cursor_is_visible, cursor_location, NSCursor, CGPoint, CGRect, CGSize, SCScreenshotManager, SCStreamConfiguration, SCStreamPixelFormat};
+use crate::platform::platform_impl::objc_wrap::{CGMainDisplayID, CMTime, DispatchQueue, NSError, SCStream, SCStreamCallbackError, SCStreamHandler, SCStreamOutputType};
+use crate::prelude::{CaptureAccessToken, CaptureConfig, CapturePixelFormat, CaptureStream, CaptureTarget};
+
+// SCStreamErrorCode values
+const SC_STREAM_ERROR_USER_DECLINED: isize = -3801;
+const SC_STREAM_ERROR_NO_WINDOW_LIST: isize = -3813;
+const SC_STREAM_ERROR_NO_DISPLAY_LIST: isize = -3814;
+const SC_STREAM_ERROR_NO_CAPTURE_SOURCE: isize = -3815;
+
+fn screenshot_error_from_callback_error(error: SCStreamCallbackError) -> ScreenshotError {
+    match error {
+        SCStreamCallbackError::Other(error) => screenshot_error_from_ns_error(error),
+        SCStreamCallbackError::MissingSampleBuffer => ScreenshotError::Other("Failed to capture screenshot: No sample buffer".to_string()),
+        SCStreamCallbackError::StreamStopped(error) => screenshot_error_from_ns_error(error),
+        SCStreamCallbackError::HandlerPanicked { message, .. } => ScreenshotError::Other(format!("Failed to capture screenshot: Stream handler panicked: {}", message)),
+    }
+}
+
+fn screenshot_error_from_ns_error(error: NSError) -> ScreenshotError {
+    match error.code() {
+        SC_STREAM_ERROR_USER_DECLINED => ScreenshotError::PermissionDenied,
+        SC_STREAM_ERROR_NO_WINDOW_LIST |
+        SC_STREAM_ERROR_NO_DISPLAY_LIST |
+        SC_STREAM_ERROR_NO_CAPTURE_SOURCE => ScreenshotError::TargetGone,
+        _ => ScreenshotError::Other(format!("Failed to capture screenshot: {}", error.description())),
+    }
+}
 
-/// Take a screenshot of the capturable content given a configuration
-pub async fn take_screenshot(token: CaptureAccessToken, config: CaptureConfig) -> Result<VideoFrame, ScreenshotError> {
+pub(crate) async fn take_screenshot_impl(token: CaptureAccessToken, config: CaptureConfig, timeout: Option<Duration>) -> Result<VideoFrame, ScreenshotError> {
     let _ = token;
+    if CaptureStream::test_access(false).is_none() {
+        return Err(ScreenshotError::PermissionDenied);
+    }
     // Force core graphics initialization
     unsafe { CGMainDisplayID() };
     let mut stream_config = SCStreamConfiguration::new();
-    let filter = match &config.target {
-        Capturable::Window(window) => SCContentFilter::new_with_desktop_independent_window(&window.impl_capturable_window.window),
-        Capturable::Display(display) => SCContentFilter::new_with_display_excluding_apps_excepting_windows(display.impl_capturable_display.display.clone(), NSArray::new(), NSArray::new())
-    };
-    stream_config.set_scales_to_fit(false);
+    let filter = sc_content_filter_for_target(&config.target, &config.impl_capture_config.excluded_windows, &config.impl_capture_config.excluded_applications)
+        .map_err(ScreenshotError::Other)?;
+    stream_config.set_scales_to_fit(config.max_dimensions.is_some());
     let (pixel_format, set_color_matrix) = match config.pixel_format {
         CapturePixelFormat::Bgra8888 =>    (SCStreamPixelFormat::BGRA8888, false),
         CapturePixelFormat::Argb2101010 => (SCStreamPixelFormat::L10R, false),
         CapturePixelFormat::V420 =>        (SCStreamPixelFormat::V420, true),
         CapturePixelFormat::F420 =>        (SCStreamPixelFormat::F420, true),
+        _ => return Err(ScreenshotError::Other("Unsupported pixel format".to_string())),
     };
     if set_color_matrix {
-        stream_config.set_color_matrix(SCStreamColorMatrix::ItuR709_2);
+        stream_config.set_color_matrix(config.impl_capture_config.color_matrix.into());
     }
     stream_config.set_pixel_format(pixel_format);
-    stream_config.set_size(CGSize {
-        x: config.output_size.width,
-        y: config.output_size.height,
-    });
+    let output_size = config.cap_size(config.output_size);
+    match config.clamped_source_rect().map_err(ScreenshotError::Other)? {
+        Some(source_rect) => {
+            let content_size = config.target.rect().size;
+            stream_config.set_source_rect(CGRect {
+                origin: CGPoint { x: source_rect.origin.x, y: source_rect.origin.y },
+                size: CGSize { x: source_rect.size.width, y: source_rect.size.height },
+            });
+            // Shrink the screenshot with the crop, as streams do
+            stream_config.set_size(CGSize {
+                x: (output_size.width * source_rect.size.width / content_size.width).ceil().max(1.0),
+                y: (output_size.height * source_rect.size.height / content_size.height).ceil().max(1.0),
+            });
+        },
+        None => {
+            stream_config.set_size(CGSize {
+                x: output_size.width,
+                y: output_size.height,
+            });
+        }
+    }
     stream_config.set_show_cursor(config.show_cursor);
     stream_config.set_capture_audio(false);
     stream_config.set_minimum_time_interval(CMTime::new_with_seconds(0.0, 100));
     let (tx, rx) = futures::channel::oneshot::channel();
     let mut tx = Some(tx);
+    // Frames carry a metal device even when none is configured, as stream frames do, so the metal extensions work on them
     #[cfg(feature = "metal")]
-    let callback_metal_device = config.impl_capture_config.metal_device.clone();
+    let callback_metal_device = config.impl_capture_config.metal_device.clone()
+        .or_else(|| default_metal_device(&config.target));
     #[cfg(feature = "wgpu")]
     let callback_wgpu_device = config.impl_capture_config.wgpu_device.clone();
+    let color_matrix = config.impl_capture_config.color_matrix;
     let mut persist_scstream = None;
     if SCScreenshotManager::class_exists() {
         SCScreenshotManager::capture_samplebuffer_with_filter_and_configuration(filter, stream_config, move |result| {
@@ -53,21 +105,38 @@ pub async fn take_screenshot(token: CaptureAccessToken, config: CaptureConfig) -
                             MacosSCStreamVideoFrame {
                                 sample_buffer,
                                 capture_time,
+                                origin_time: Duration::ZERO,
+                                duration: Duration::ZERO,
                                 dictionary: RefCell::new(None),
                                 frame_id: 0,
+                                output_placement: None,
+                                cursor_location: cursor_location(),
+                                cursor_visible: cursor_is_visible(),
+                                cursor: NSCursor::current_system_cursor(),
+                                dirty_rects: None,
+                                color_matrix,
                                 #[cfg(feature = "metal")]
                                 metal_device: callback_metal_device.clone(),
                                 #[cfg(feature = "wgpu")]
                                 wgpu_device: callback_wgpu_device.clone(),
                             }
-                        )
+                        ),
+                        clock_origin: None,
+                        #[cfg(feature = "bitmap")]
+                        change_metric: false,
                     })
                 },
-                Err(error) => Err(ScreenshotError::Other(format!("Failed to capture screenshot: {}", error)))
+                Err(error) => Err(screenshot_error_from_callback_error(error))
             };
-            tx.take().unwrap().send(screenshot_result).unwrap();
+            // The screenshot may have timed out, dropping the receiver
+            if let Some(tx) = tx.take() {
+                let _ = tx.send(screenshot_result);
+            }
         });
     } else {
+        // The stream may fail to start rather than delivering a frame
+        let tx = Arc::new(Mutex::new(tx));
+        let start_tx = tx.clone();
         let handler = SCStreamHandler::new(move |stream_result| {
             let screenshot_result = match stream_result {
                 Ok((sample_buffer, SCStreamOutputType::Screen)) => {
@@ -77,28 +146,32 @@ pub async fn take_screenshot(token: CaptureAccessToken, config: CaptureConfig) -
                             MacosSCStreamVideoFrame {
                                 sample_buffer,
                                 capture_time,
+                                origin_time: Duration::ZERO,
+                                duration: Duration::ZERO,
                                 dictionary: RefCell::new(None),
                                 frame_id: 0,
+                                output_placement: None,
+                                cursor_location: cursor_location(),
+                                cursor_visible: cursor_is_visible(),
+                                cursor: NSCursor::current_system_cursor(),
+                                dirty_rects: None,
+                                color_matrix,
                                 #[cfg(feature = "metal")]
                                 metal_device: callback_metal_device.clone(),
                                 #[cfg(feature = "wgpu")]
                                 wgpu_device: callback_wgpu_device.clone(),
                             }
-                        )
+                        ),
+                        clock_origin: None,
+                        #[cfg(feature = "bitmap")]
+                        change_metric: false,
                     }))
                 },
-                Err(error) => {
-                    let description = match error {
-                        SCStreamCallbackError::Other(error) => error.description(),
-                        SCStreamCallbackError::SampleBufferCopyFailed => "Failed to copy sample buffer".to_string(),
-                        SCStreamCallbackError::StreamStopped => "Stream stopped early".to_string(),
-                    };
-                    Some(Err(ScreenshotError::Other(format!("Failed to capture screenshot: {}", description))))
-                },
+                Err(error) => Some(Err(screenshot_error_from_callback_error(error))),
                 _ => None
             };
-            if let (Some(screenshot_result), Some(tx)) = (screenshot_result, tx.take()) {
-                tx.send(screenshot_result).unwrap();
+            if let (Some(screenshot_result), Some(tx)) = (screenshot_result, tx.lock().take()) {
+                let _ = tx.send(screenshot_result);
             }
         });
         let mut stream = match SCStream::new(
@@ -110,13 +183,19 @@ pub async fn take_screenshot(token: CaptureAccessToken, config: CaptureConfig) -
             Ok(stream) => stream,
             Err(error) => Err(ScreenshotError::Other(format!("Failed to build SCStream: {}", error)))?,
         };
-        stream.start();
+        stream.start_with_completion_handler(move |result| {
+            if let (Err(error), Some(tx)) = (result, start_tx.lock().take()) {
+                let _ = tx.send(Err(screenshot_error_from_ns_error(error)));
+            }
+        });
         persist_scstream = Some(stream);
     }
-    let result = rx.await
-        .map_err(|_| ScreenshotError::Other("Failed to await callback future".into()))?;
-    if let Some(sc_stream) = persist_scstream {
-        drop(sc_stream);
+    let result = with_timeout(rx, timeout).await;
+    // Stop the capture whether or not a frame arrived, so the screen recording indicator goes away, and wait for
+    // ScreenCaptureKit to tear the stream down before returning
+    if let Some(mut sc_stream) = persist_scstream {
+        let _ = stop_sc_stream(&mut sc_stream).await;
     }
-    result
+    result.ok_or(ScreenshotError::Timeout)?
+        .map_err(|_| ScreenshotError::Other("Failed to await callback future".into()))?
 }
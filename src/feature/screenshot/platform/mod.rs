@@ -1,9 +1,14 @@
 #[cfg(target_os = "macos")]
 mod macos;
 #[cfg(target_os = "macos")]
-pub use macos::take_screenshot;
+pub(crate) use macos::take_screenshot_impl;
 
 #[cfg(target_os = "windows")]
 mod windows;
 #[cfg(target_os = "windows")]
-pub use windows::take_screenshot;
+pub(crate) use windows::take_screenshot_impl;
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod stub;
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub(crate) use stub::take_screenshot_impl;
@@ -0,0 +1,11 @@
+use std::time::Duration;
+
+use crate::feature::screenshot::ScreenshotError;
+use crate::frame::VideoFrame;
+use crate::prelude::{CaptureAccessToken, CaptureConfig};
+
+// Screenshots always fail on unsupported platforms
+pub(crate) async fn take_screenshot_impl(token: CaptureAccessToken, config: CaptureConfig, timeout: Option<Duration>) -> Result<VideoFrame, ScreenshotError> {
+    let _ = (token, config, timeout);
+    Err(ScreenshotError::Other("Screenshots are unsupported on this platform".to_string()))
+}
@@ -1,14 +1,27 @@
+use std::time::Duration;
+
 use futures::channel::oneshot;
+use windows::Win32::UI::WindowsAndMessaging::IsWindow;
 
-use crate::feature::screenshot::ScreenshotError;
+use crate::{feature::screenshot::ScreenshotError, util::with_timeout};
 use crate::frame::VideoFrame;
-use crate::prelude::{CaptureConfig, CaptureStream, StreamEvent, CaptureAccessToken};
+use crate::prelude::{Capturable, CaptureAccessToken, CaptureConfig, CaptureStream, StreamCreateError, StreamEvent};
 
-/// Take a screenshot of the capturable content given a configuration
-pub async fn take_screenshot(token: CaptureAccessToken, config: CaptureConfig) -> Result<VideoFrame, ScreenshotError> {
+pub(crate) async fn take_screenshot_impl(token: CaptureAccessToken, config: CaptureConfig, timeout: Option<Duration>) -> Result<VideoFrame, ScreenshotError> {
+    if CaptureStream::test_access(false).is_none() {
+        return Err(ScreenshotError::PermissionDenied);
+    }
+    if config.impl_capture_config.borderless && !token.allows_borderless() {
+        return Err(ScreenshotError::InsufficientAccess("Borderless Capture".to_string()));
+    }
+    if let Capturable::Window(window) = &config.target {
+        if !unsafe { IsWindow(window.impl_capturable_window.0).as_bool() } {
+            return Err(ScreenshotError::TargetGone);
+        }
+    }
     let (tx, rx) = oneshot::channel();
     let mut tx = Some(tx);
-    let mut capture_stream = CaptureStream::new(token, config, move |event_result| {
+    let capture_stream = CaptureStream::new(token, config, move |event_result| {
         match event_result {
             Ok(StreamEvent::Video(frame)) => {
                 if let Some(tx) = tx.take() {
@@ -23,9 +36,15 @@ pub async fn take_screenshot(token: CaptureAccessToken, config: CaptureConfig) -
             _ => {}
         }
     }).map_err(|error| {
-        ScreenshotError::Other(format!("Failed to create capture stream: {}", error.to_string()))
+        match error {
+            StreamCreateError::UnauthorizedFeature(feature) => ScreenshotError::InsufficientAccess(feature),
+            error => ScreenshotError::Other(format!("Failed to create capture stream: {}", error.to_string())),
+        }
     })?;
-    let result = rx.await.map_err(|_| ScreenshotError::Other("Failed to wait for result from callback".into()))?;
-    let _ = capture_stream.stop();
+    let result = with_timeout(rx, timeout).await;
+    // Close the capture whether or not a frame arrived, and wait for the session and frame pool to be torn down before returning
+    let _ = capture_stream.close().await;
+    let result = result.ok_or(ScreenshotError::Timeout)?
+        .map_err(|_| ScreenshotError::Other("Failed to wait for result from callback".into()))?;
     result.map_err(|error| ScreenshotError::Other(format!("Capture failed: {}", error.to_string())))
 }
\ No newline at end of file
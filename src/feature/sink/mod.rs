@@ -0,0 +1,198 @@
+use std::borrow::Borrow;
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::Arc;
+use std::{error::Error, fmt::Display};
+#[cfg(feature = "png")]
+use std::path::{Path, PathBuf};
+
+use parking_lot::Mutex;
+
+#[cfg(feature = "png")]
+use crate::feature::bitmap::VideoFrameBitmap;
+use crate::error::ErrorKind;
+use crate::prelude::{CaptureAccessToken, CaptureConfig, CaptureStream, StopHandle, StreamCreateError, StreamError, StreamEvent, VideoFrame};
+
+/// Represents an error in a video frame sink
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SinkError {
+    Other(String),
+    /// The frame couldn't be converted into the form the sink consumes, with the reason
+    Frame(String),
+    /// Writing the sink's output failed, with the reason
+    Io(String),
+}
+
+unsafe impl Send for SinkError {}
+unsafe impl Sync for SinkError {}
+
+impl SinkError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Other(_) => ErrorKind::SinkOther,
+            Self::Frame(_) => ErrorKind::SinkFrame,
+            Self::Io(_) => ErrorKind::SinkIo,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+}
+
+impl Display for SinkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Other(message) => f.write_fmt(format_args!("SinkError::Other(\"{}\")", message)),
+            Self::Frame(message) => f.write_fmt(format_args!("SinkError::Frame(\"{}\")", message)),
+            Self::Io(message) => f.write_fmt(format_args!("SinkError::Io(\"{}\")", message)),
+        }
+    }
+}
+
+impl Error for SinkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+
+    fn description(&self) -> &str {
+        "description() is deprecated; use Display"
+    }
+
+    fn cause(&self) -> Option<&dyn Error> {
+        self.source()
+    }
+}
+
+/// A consumer of the video frames of a capture stream, such as an encoder - see `CaptureStream::new_with_sink()`
+///
+/// The sink's methods are called from the stream's callback, so they should return quickly to avoid holding up capture
+pub trait VideoFrameSink: Send {
+    /// Consume the next video frame of the stream
+    ///
+    /// Returning an error stops the stream and ends the sink, so no more frames are passed to it
+    fn on_frame(&mut self, frame: &VideoFrame) -> Result<(), SinkError>;
+
+    /// Handle an error reported by the stream - the stream may go on delivering frames afterwards
+    ///
+    /// Does nothing by default
+    fn on_error(&mut self, _error: &StreamError) {}
+
+    /// Finish consuming frames, E.G. to flush an encoder - called exactly once, after the last frame
+    fn on_end(&mut self);
+}
+
+// Owns the sink in the stream callback, and ends it exactly once - at the end of the stream, after a sink error,
+// or when the callback is dropped along with the stream
+struct SinkState<Sink: VideoFrameSink> {
+    sink: Sink,
+    ended: bool,
+}
+
+impl<Sink: VideoFrameSink> SinkState<Sink> {
+    fn end(&mut self) {
+        if !self.ended {
+            self.ended = true;
+            self.sink.on_end();
+        }
+    }
+}
+
+impl<Sink: VideoFrameSink> Drop for SinkState<Sink> {
+    fn drop(&mut self) {
+        self.end();
+    }
+}
+
+impl CaptureStream {
+    /// Start a new capture stream which passes its video frames to a sink, rather than a callback
+    ///
+    /// The sink receives the stream's video frames in order, along with its errors - audio frames and idle events aren't passed on.
+    /// Its `on_end()` is called exactly once: when the stream ends (including when it's stopped, or dropped mid-capture),
+    /// or when `on_frame()` returns an error, in which case the stream is also stopped.
+    pub fn new_with_sink(token: CaptureAccessToken, config: impl Borrow<CaptureConfig>, sink: impl VideoFrameSink + 'static) -> Result<CaptureStream, StreamCreateError> {
+        let stop_handle = Arc::new(Mutex::new(None::<StopHandle>));
+        let callback_stop_handle = stop_handle.clone();
+        let sink_failed = Arc::new(AtomicBool::new(false));
+        let callback_sink_failed = sink_failed.clone();
+        let mut sink_state = SinkState {
+            sink,
+            ended: false,
+        };
+        let stream = CaptureStream::new(token, config, move |event| {
+            if sink_state.ended {
+                return;
+            }
+            match event {
+                Ok(StreamEvent::Video(frame)) if sink_state.sink.on_frame(&frame).is_err() => {
+                    sink_state.end();
+                    callback_sink_failed.store(true, atomic::Ordering::SeqCst);
+                    if let Some(stop_handle) = callback_stop_handle.lock().as_ref() {
+                        let _ = stop_handle.stop();
+                    }
+                },
+                Ok(StreamEvent::End { .. }) => sink_state.end(),
+                Err(error) => sink_state.sink.on_error(&error),
+                _ => {}
+            }
+        })?;
+        *stop_handle.lock() = Some(stream.stop_handle());
+        // The sink may have failed on a frame delivered before the stop handle was available
+        if sink_failed.load(atomic::Ordering::SeqCst) {
+            let _ = stream.stop_handle().stop();
+        }
+        Ok(stream)
+    }
+}
+
+/// A sink which saves each video frame as a numbered PNG file in a directory - `frame_000000.png`, `frame_000001.png` and so on
+/// (requires the `png` feature)
+///
+/// This is the reference implementation of `VideoFrameSink`. Frames are converted the same way as `VideoFrame::save_png()`,
+/// so every pixel format is supported, and each file is roughly the size of the frame's raw bitmap.
+#[cfg(feature = "png")]
+#[cfg_attr(docsrs, doc(cfg(feature = "png")))]
+pub struct PngSequenceSink {
+    directory: PathBuf,
+    frame_count: usize,
+}
+
+#[cfg(feature = "png")]
+impl PngSequenceSink {
+    /// Create a sink which saves frames into the given directory, creating it if it doesn't exist
+    ///
+    /// Existing files with the same names are replaced.
+    pub fn new(directory: impl AsRef<Path>) -> Result<Self, SinkError> {
+        let directory = directory.as_ref().to_path_buf();
+        std::fs::create_dir_all(&directory)
+            .map_err(|error| SinkError::Io(format!("Failed to create directory \"{}\": {}", directory.display(), error)))?;
+        Ok(Self {
+            directory,
+            frame_count: 0,
+        })
+    }
+
+    /// Get the path the frame with the given index in the sequence is saved to
+    pub fn frame_path(&self, index: usize) -> PathBuf {
+        self.directory.join(format!("frame_{:06}.png", index))
+    }
+}
+
+#[cfg(feature = "png")]
+impl VideoFrameSink for PngSequenceSink {
+    fn on_frame(&mut self, frame: &VideoFrame) -> Result<(), SinkError> {
+        let png = frame.get_bitmap()
+            .map_err(|error| SinkError::Frame(error.to_string()))?
+            .to_rgba_unorm8()
+            .encode_png();
+        let path = self.frame_path(self.frame_count);
+        std::fs::write(&path, png)
+            .map_err(|error| SinkError::Io(format!("Failed to write png file \"{}\": {}", path.display(), error)))?;
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    fn on_end(&mut self) {}
+}
@@ -1,6 +1,13 @@
+use std::collections::HashMap;
+use std::sync::atomic::{self, AtomicBool};
 use std::sync::Arc;
+#[cfg(target_os = "windows")]
+use std::sync::Weak;
 use std::{error::Error, fmt::Display};
 
+use parking_lot::Mutex;
+
+use crate::error::ErrorKind;
 use crate::prelude::{CaptureConfig, CaptureStream, VideoFrame};
 
 #[cfg(target_os = "macos")]
@@ -18,13 +25,11 @@ use wgpu::hal::Device;
 #[cfg(target_os = "windows")]
 use windows::core::PCWSTR;
 #[cfg(target_os = "windows")]
-use windows::Win32::Foundation::WAIT_OBJECT_0;
-#[cfg(target_os = "windows")]
-use windows::Win32::Foundation::{CloseHandle, GENERIC_ALL};
+use windows::Win32::Foundation::{LUID, WAIT_OBJECT_0};
 #[cfg(target_os = "windows")]
-use windows::Win32::Graphics::Direct3D::{D3D_DRIVER_TYPE_UNKNOWN, D3D_FEATURE_LEVEL_11_0};
+use windows::Win32::Foundation::GENERIC_ALL;
 #[cfg(target_os = "windows")]
-use windows::Win32::Graphics::Direct3D11::{D3D11CreateDevice, ID3D11Device5, ID3D11DeviceContext4, ID3D11Fence, D3D11_CREATE_DEVICE_DEBUG, D3D11_SDK_VERSION, D3D11_TEXTURE2D_DESC};
+use windows::Win32::Graphics::Direct3D11::{ID3D11Device5, ID3D11DeviceContext4, ID3D11Fence, D3D11_TEXTURE2D_DESC};
 #[cfg(target_os = "windows")]
 use windows::Win32::Graphics::Direct3D12::{D3D12_CLEAR_VALUE, D3D12_FENCE_FLAG_NONE, D3D12_FENCE_FLAG_SHARED};
 #[cfg(target_os = "windows")]
@@ -32,6 +37,8 @@ use windows::Win32::Graphics::Direct3D12::{ID3D12Fence, D3D12_CPU_PAGE_PROPERTY_
 #[cfg(target_os = "windows")]
 use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT_B8G8R8A8_TYPELESS;
 #[cfg(target_os = "windows")]
+use windows::Win32::Graphics::Dxgi::Common::{DXGI_FORMAT, DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_B8G8R8A8_UNORM_SRGB, DXGI_FORMAT_R10G10B10A2_UINT, DXGI_FORMAT_R10G10B10A2_UNORM, DXGI_FORMAT_R16G16B16A16_FLOAT, DXGI_FORMAT_R16G16B16A16_UNORM, DXGI_SAMPLE_DESC};
+#[cfg(target_os = "windows")]
 use windows::Win32::Graphics::Dxgi::{CreateDXGIFactory, IDXGIAdapter4, IDXGIFactory5};
 #[cfg(target_os = "windows")]
 use windows::Win32::System::Threading::{CreateEventA, WaitForSingleObjectEx, CREATE_EVENT, INFINITE, PROCESS_DELETE, PROCESS_SYNCHRONIZE};
@@ -43,9 +50,442 @@ use crate::platform::windows::capture_stream::WindowsCaptureConfig;
 #[cfg(target_os = "windows")]
 use crate::feature::dx11::*;
 #[cfg(target_os = "windows")]
-use windows::{core::{Interface, ComInterface}, Graphics::DirectX::DirectXPixelFormat, Win32::Graphics::{Direct3D11::ID3D11Texture2D, Direct3D11::D3D11_CREATE_DEVICE_BGRA_SUPPORT, Direct3D12::{ID3D12CommandQueue, ID3D12Device, ID3D12Resource, D3D12_RESOURCE_FLAG_ALLOW_RENDER_TARGET, D3D12_RESOURCE_FLAG_DENY_SHADER_RESOURCE}}};
+use windows::{core::{Interface, ComInterface}, Graphics::DirectX::DirectXPixelFormat, Win32::Graphics::{Direct3D11::ID3D11Texture2D, Direct3D12::{ID3D12CommandQueue, ID3D12Device, ID3D12Resource, D3D12_RESOURCE_FLAG_ALLOW_RENDER_TARGET, D3D12_RESOURCE_FLAG_DENY_SHADER_RESOURCE}}};
 #[cfg(target_os = "windows")]
 use std::ffi::c_void;
+#[cfg(target_os = "windows")]
+use crate::platform::windows::AutoHandle;
+
+// The wgpu device given to new frames, shared with a stream's callback so that it can be replaced while the stream runs
+pub(crate) type SharedWgpuDevice = Arc<Mutex<Option<Arc<dyn AsRef<wgpu::Device> + Send + Sync + 'static>>>>;
+
+// Whether each wgpu device supplied to a stream has been lost, by its id - set from the device lost callback installed by `watch_wgpu_device`
+static WATCHED_WGPU_DEVICES: Mutex<Option<HashMap<wgpu::Id<wgpu::Device>, Arc<AtomicBool>>>> = parking_lot::const_mutex(None);
+
+// Detect when a wgpu device supplied to a stream is lost, through its device lost callback - this replaces any callback set before
+fn watch_wgpu_device(wgpu_device: &wgpu::Device) {
+    let device_id = wgpu_device.global_id();
+    let lost = Arc::new(AtomicBool::new(false));
+    {
+        let mut watched_devices = WATCHED_WGPU_DEVICES.lock();
+        let watched_devices = watched_devices.get_or_insert_with(HashMap::new);
+        if watched_devices.contains_key(&device_id) {
+            return;
+        }
+        watched_devices.insert(device_id, lost.clone());
+    }
+    wgpu_device.set_device_lost_callback(move |reason, _message| {
+        // Replacing the callback doesn't lose the device, though it's no longer watched - see `mark_wgpu_device_lost`
+        if !matches!(reason, wgpu::DeviceLostReason::ReplacedCallback) {
+            lost.store(true, atomic::Ordering::Release);
+        }
+    });
+}
+
+/// Tell capture streams that a Wgpu device was lost
+/// 
+/// Streams detect the loss of devices supplied with `WgpuCaptureConfigExt::with_wgpu_device` or `WgpuCaptureStreamExt::replace_wgpu_device`
+/// by setting the device's lost callback (see `wgpu::Device::set_device_lost_callback`). If you set your own lost callback after supplying
+/// the device, call this from it so that streams still see the loss.
+pub fn mark_wgpu_device_lost(wgpu_device: &wgpu::Device) {
+    let mut watched_devices = WATCHED_WGPU_DEVICES.lock();
+    watched_devices.get_or_insert_with(HashMap::new)
+        .entry(wgpu_device.global_id())
+        .or_insert_with(|| Arc::new(AtomicBool::new(false)))
+        .store(true, atomic::Ordering::Release);
+}
+
+// Whether a wgpu device is known to have been lost - see `watch_wgpu_device` and `mark_wgpu_device_lost`
+pub(crate) fn wgpu_device_lost(wgpu_device: &wgpu::Device) -> bool {
+    WATCHED_WGPU_DEVICES.lock().as_ref()
+        .and_then(|watched_devices| watched_devices.get(&wgpu_device.global_id()))
+        .is_some_and(|lost| lost.load(atomic::Ordering::Acquire))
+}
+
+// Get the metal device underneath a wgpu device, or None if it isn't a metal device
+#[cfg(target_os = "macos")]
+pub(crate) fn wgpu_metal_device(wgpu_device: &wgpu::Device) -> Option<metal::Device> {
+    unsafe {
+        wgpu_device.as_hal::<wgpu::hal::api::Metal, _, _>(|device| {
+            device.map(|device| device.raw_device().lock().clone())
+        }).flatten()
+    }
+}
+
+// Get the LUID of the adapter underneath a wgpu device, or None if it isn't a dx12 device
+#[cfg(target_os = "windows")]
+pub(crate) fn wgpu_adapter_luid(wgpu_device: &wgpu::Device) -> Option<LUID> {
+    unsafe {
+        wgpu_device.as_hal::<wgpu::hal::api::Dx12, _, _>(|device| {
+            device.and_then(|device| {
+                let raw_device_ptr = device.raw_device().as_mut_ptr() as *mut c_void;
+                ID3D12Device::from_raw_borrowed(&raw_device_ptr).map(|d3d12_device| d3d12_device.GetAdapterLuid())
+            })
+        }).flatten()
+    }
+}
+
+// The shared textures which a stream's frames are copied into for wgpu, and the fence ordering those copies - kept per stream,
+// so that they're reused across frames rather than created for every frame
+#[cfg(target_os = "windows")]
+pub(crate) type SharedWgpuTextureCache = Arc<Mutex<WgpuTextureCache>>;
+
+// The most textures kept for reuse - when they're all still in use, a frame gets a texture of its own
+#[cfg(target_os = "windows")]
+const MAX_CACHED_WGPU_TEXTURES: usize = 4;
+
+// Held by a frame for each shared texture handed out for it, so that the texture isn't reused for another frame until the frame is dropped
+#[cfg(target_os = "windows")]
+pub(crate) type WgpuTextureLease = Arc<()>;
+
+#[cfg(target_os = "windows")]
+#[derive(Default)]
+pub(crate) struct WgpuTextureCache {
+    // The device the textures and fence were created on, which changes if the stream's wgpu device is replaced
+    d3d12_device: Option<ID3D12Device>,
+    textures: Vec<WgpuSharedTexture>,
+    // Caller-provided textures opened on the stream's d3d11 device by `copy_to_wgpu_texture`, most recently used last
+    destination_textures: Vec<WgpuSharedTexture>,
+    fence: Option<WgpuSharedFence>,
+    pub(crate) allocation_count: u64,
+}
+
+#[cfg(target_os = "windows")]
+unsafe impl Send for WgpuTextureCache {}
+
+#[cfg(target_os = "windows")]
+struct WgpuSharedTexture {
+    d3d12_texture: ID3D12Resource,
+    d3d11_texture: ID3D11Texture2D,
+    desc: D3D11_TEXTURE2D_DESC,
+    // The lease of the frame the texture was last handed out for
+    lease: Weak<()>,
+}
+
+#[cfg(target_os = "windows")]
+struct WgpuSharedFence {
+    d3d12_fence: ID3D12Fence,
+    d3d11_fence: ID3D11Fence,
+    event: AutoHandle,
+    value: u64,
+}
+
+#[cfg(target_os = "windows")]
+impl WgpuTextureCache {
+    pub(crate) fn new_shared() -> SharedWgpuTextureCache {
+        Arc::new(Mutex::new(Self::default()))
+    }
+
+    // Drop everything created on another device
+    fn use_device(&mut self, d3d12_device: &ID3D12Device) {
+        if self.d3d12_device.as_ref().map(Interface::as_raw) != Some(d3d12_device.as_raw()) {
+            self.textures.clear();
+            self.destination_textures.clear();
+            self.fence = None;
+            self.d3d12_device = Some(d3d12_device.clone());
+        }
+    }
+
+    // Get a texture for a frame with the given description which isn't leased to a live frame, along with the lease for the frame to hold
+    unsafe fn shared_texture(&mut self, d3d12_device: &ID3D12Device, d3d11_device: &ID3D11Device5, frame_desc: &D3D11_TEXTURE2D_DESC) -> Result<(ID3D12Resource, ID3D11Texture2D, WgpuTextureLease), WgpuVideoFrameError> {
+        // Textures for frames of an old size are dropped once their frames are
+        self.textures.retain(|texture| texture.matches(frame_desc) || texture.leased());
+        let lease = WgpuTextureLease::new(());
+        if let Some(texture) = self.textures.iter_mut().find(|texture| texture.matches(frame_desc) && !texture.leased()) {
+            texture.lease = Arc::downgrade(&lease);
+            return Ok((texture.d3d12_texture.clone(), texture.d3d11_texture.clone(), lease));
+        }
+        let mut texture = WgpuSharedTexture::new(d3d12_device, d3d11_device, frame_desc)?;
+        self.allocation_count += 1;
+        texture.lease = Arc::downgrade(&lease);
+        let textures = (texture.d3d12_texture.clone(), texture.d3d11_texture.clone(), lease);
+        if self.textures.len() < MAX_CACHED_WGPU_TEXTURES {
+            self.textures.push(texture);
+        }
+        Ok(textures)
+    }
+
+    // Open a caller-provided texture on the stream's d3d11 device, or None if it wasn't created as shareable - the least recently
+    // used texture is dropped once there are too many, since there's no telling when the caller drops theirs
+    unsafe fn destination_texture(&mut self, d3d12_device: &ID3D12Device, d3d11_device: &ID3D11Device5, d3d12_texture: &ID3D12Resource) -> Option<ID3D11Texture2D> {
+        // Holding the texture keeps its address from being reused for another, so it can be found by address
+        if let Some(index) = self.destination_textures.iter().position(|texture| texture.d3d12_texture.as_raw() == d3d12_texture.as_raw()) {
+            let texture = self.destination_textures.remove(index);
+            let d3d11_texture = texture.d3d11_texture.clone();
+            self.destination_textures.push(texture);
+            return Some(d3d11_texture);
+        }
+        let texture = WgpuSharedTexture::open(d3d12_device, d3d11_device, d3d12_texture).ok()?;
+        let d3d11_texture = texture.d3d11_texture.clone();
+        if self.destination_textures.len() == MAX_CACHED_WGPU_TEXTURES {
+            self.destination_textures.remove(0);
+        }
+        self.destination_textures.push(texture);
+        Some(d3d11_texture)
+    }
+
+    unsafe fn fence(&mut self, d3d12_device: &ID3D12Device, d3d11_device: &ID3D11Device5) -> Result<&mut WgpuSharedFence, WgpuVideoFrameError> {
+        if self.fence.is_none() {
+            self.fence = Some(WgpuSharedFence::new(d3d12_device, d3d11_device)?);
+        }
+        Ok(self.fence.as_mut().unwrap())
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl WgpuSharedTexture {
+    unsafe fn new(d3d12_device: &ID3D12Device, d3d11_device: &ID3D11Device5, frame_desc: &D3D11_TEXTURE2D_DESC) -> Result<Self, WgpuVideoFrameError> {
+        let d3d12_texture_desc = D3D12_RESOURCE_DESC {
+            Dimension: D3D12_RESOURCE_DIMENSION_TEXTURE2D,
+            Alignment: 0,
+            Width: frame_desc.Width as u64,
+            Height: frame_desc.Height,
+            DepthOrArraySize: frame_desc.ArraySize as u16,
+            MipLevels: frame_desc.MipLevels as u16,
+            Format: frame_desc.Format,
+            SampleDesc: frame_desc.SampleDesc,
+            Layout: D3D12_TEXTURE_LAYOUT_UNKNOWN,
+            Flags: D3D12_RESOURCE_FLAG_ALLOW_RENDER_TARGET | D3D12_RESOURCE_FLAG_ALLOW_SIMULTANEOUS_ACCESS | D3D12_RESOURCE_FLAG_ALLOW_UNORDERED_ACCESS
+        };
+        let d3d12_texture_heap_properties = D3D12_HEAP_PROPERTIES {
+            Type: D3D12_HEAP_TYPE_DEFAULT,
+            CPUPageProperty: D3D12_CPU_PAGE_PROPERTY_UNKNOWN,
+            MemoryPoolPreference: D3D12_MEMORY_POOL_UNKNOWN,
+            CreationNodeMask: 0,
+            VisibleNodeMask: 0,
+        };
+        let d3d12_texture_clear_value = D3D12_CLEAR_VALUE {
+            Format: frame_desc.Format,
+            Anonymous: windows::Win32::Graphics::Direct3D12::D3D12_CLEAR_VALUE_0 {
+                Color: [0.0, 0.0, 0.0, 0.0]
+            }
+        };
+
+        let mut d3d12_texture = None;
+        d3d12_device.CreateCommittedResource(
+            &d3d12_texture_heap_properties as *const _,
+            D3D12_HEAP_FLAG_SHARED,
+            &d3d12_texture_desc as *const _,
+            D3D12_RESOURCE_STATE_COMMON,
+            Some(&d3d12_texture_clear_value),
+            &mut d3d12_texture as *mut _
+        ).map_err(|error| WgpuVideoFrameError::Other(format!("Failed to create d3d12 texture: {}", error.to_string())))?;
+        let d3d12_texture: ID3D12Resource = d3d12_texture
+            .ok_or_else(|| WgpuVideoFrameError::Other("Failed to create d3d12 texture".to_string()))?;
+        Ok(Self {
+            desc: *frame_desc,
+            ..Self::open(d3d12_device, d3d11_device, &d3d12_texture)?
+        })
+    }
+
+    // Open a d3d12 texture created on a shared heap on the d3d11 device
+    unsafe fn open(d3d12_device: &ID3D12Device, d3d11_device: &ID3D11Device5, d3d12_texture: &ID3D12Resource) -> Result<Self, WgpuVideoFrameError> {
+        let dxgi_shared_texture_handle = d3d12_device.CreateSharedHandle(
+            d3d12_texture,
+            None,
+            GENERIC_ALL.0,
+            None
+        ).map_err(|error| WgpuVideoFrameError::Other(format!("Failed to share d3d12 texture: {}", error.to_string())))?;
+        let dxgi_shared_texture_handle = AutoHandle(dxgi_shared_texture_handle);
+
+        let d3d11_texture: ID3D11Texture2D = d3d11_device.OpenSharedResource1(dxgi_shared_texture_handle.0)
+            .map_err(|error| WgpuVideoFrameError::Other(format!("Failed to use dxgi shared texture in d3d11: {}", error.to_string())))?;
+
+        let mut desc = D3D11_TEXTURE2D_DESC::default();
+        d3d11_texture.GetDesc(&mut desc as *mut _);
+
+        Ok(Self {
+            d3d12_texture: d3d12_texture.clone(),
+            d3d11_texture,
+            desc,
+            lease: Weak::new(),
+        })
+    }
+
+    fn matches(&self, frame_desc: &D3D11_TEXTURE2D_DESC) -> bool {
+        (self.desc.Width, self.desc.Height, self.desc.ArraySize, self.desc.MipLevels, self.desc.Format, self.desc.SampleDesc.Count, self.desc.SampleDesc.Quality) ==
+        (frame_desc.Width, frame_desc.Height, frame_desc.ArraySize, frame_desc.MipLevels, frame_desc.Format, frame_desc.SampleDesc.Count, frame_desc.SampleDesc.Quality)
+    }
+
+    // Whether the frame the texture was last handed out for is still alive
+    fn leased(&self) -> bool {
+        self.lease.strong_count() > 0
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl WgpuSharedFence {
+    unsafe fn new(d3d12_device: &ID3D12Device, d3d11_device: &ID3D11Device5) -> Result<Self, WgpuVideoFrameError> {
+        let d3d12_fence: ID3D12Fence = d3d12_device.CreateFence(0, D3D12_FENCE_FLAG_SHARED)
+            .map_err(|error|  WgpuVideoFrameError::Other(format!("Failed to create fence: {}", error)))?;
+        let event = CreateEventA(None, false, false, None)
+            .map_err(|error|  WgpuVideoFrameError::Other(format!("Failed to create fence event: {}", error)))?;
+        let event = AutoHandle(event);
+
+        let dxgi_shared_fence_handle = d3d12_device.CreateSharedHandle(
+            &d3d12_fence,
+            None,
+            GENERIC_ALL.0,
+            None
+        ).map_err(|error| WgpuVideoFrameError::Other(format!("Failed to share fence with dxgi: {}", error.to_string())))?;
+        let dxgi_shared_fence_handle = AutoHandle(dxgi_shared_fence_handle);
+
+        let mut d3d11_fence = None;
+        d3d11_device.OpenSharedFence(dxgi_shared_fence_handle.0, &mut d3d11_fence)
+            .map_err(|error| WgpuVideoFrameError::Other(format!("Failed to use dxgi shared fence: {}", error.to_string())))?;
+        let d3d11_fence: ID3D11Fence = d3d11_fence
+            .ok_or_else(|| WgpuVideoFrameError::Other("Failed to use dxgi shared fence".to_string()))?;
+
+        Ok(Self {
+            d3d12_fence,
+            d3d11_fence,
+            event,
+            value: 0,
+        })
+    }
+}
+
+// Copy a frame's surface into a texture shared with the wgpu device, and have the wgpu device's queue wait for the copy
+#[cfg(target_os = "windows")]
+unsafe fn copy_frame_to_shared_texture(frame: &VideoFrame, frame_texture: ID3D11Texture2D, d3d11_shared_texture: ID3D11Texture2D, d3d12_queue: &ID3D12CommandQueue, fence: &mut WgpuSharedFence) -> Result<(), WgpuVideoFrameError> {
+    // Work already submitted to wgpu's queue may still be reading the texture for an earlier frame, so the copy waits for it
+    fence.value += 1;
+    d3d12_queue.Signal(&fence.d3d12_fence, fence.value)
+        .map_err(|error| WgpuVideoFrameError::Other(format!("Failed to enqueue fence signal: {}", error.to_string())))?;
+    let queue_idle_value = fence.value;
+    fence.value += 1;
+
+    {
+        let device_context: ID3D11DeviceContext4 = frame.impl_video_frame.device.GetImmediateContext()
+            .map_err(|error| WgpuVideoFrameError::Other(format!("Failed to get d3d11 device context: {}", error.to_string())))?
+            .cast()
+            .map_err(|error| WgpuVideoFrameError::Other(format!("Failed to get d3d11 device context v4: {}", error.to_string())))?;
+        device_context.Wait(&fence.d3d11_fence, queue_idle_value)
+            .map_err(|error| WgpuVideoFrameError::Other(format!("Failed to queue fence wait: {}", error.to_string())))?;
+        device_context.CopyResource(&d3d11_shared_texture, &frame_texture);
+        device_context.Signal(&fence.d3d11_fence, fence.value)
+            .map_err(|error| WgpuVideoFrameError::Other(format!("Failed to queue fence signal: {}", error.to_string())))?;
+        drop(frame_texture);
+        drop(d3d11_shared_texture);
+        device_context.Flush();
+    }
+
+    d3d12_queue.Wait(&fence.d3d12_fence, fence.value)
+        .map_err(|error| WgpuVideoFrameError::Other(format!("Failed to enqueue wait on fence: {}", error.to_string())))?;
+
+    // The frame's surface goes back to the frame pool once the frame is dropped, so the copy out of it has to finish first
+    fence.d3d12_fence.SetEventOnCompletion(fence.value, fence.event.0)
+        .map_err(|error|  WgpuVideoFrameError::Other(format!("Failed to set fence completion event: {}", error.to_string())))?;
+    if WaitForSingleObjectEx(fence.event.0, INFINITE, false) != WAIT_OBJECT_0 {
+        Err(WgpuVideoFrameError::Other(format!("Failed wait on completion fence")))?
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn wgpu_format_for_pixel_format(pixel_format: DirectXPixelFormat) -> Result<wgpu::TextureFormat, WgpuVideoFrameError> {
+    match pixel_format {
+        DirectXPixelFormat::B8G8R8A8Typeless => Ok(wgpu::TextureFormat::Bgra8Unorm),
+        DirectXPixelFormat::B8G8R8A8UIntNormalized => Ok(wgpu::TextureFormat::Bgra8Unorm),
+        DirectXPixelFormat::B8G8R8A8UIntNormalizedSrgb => Ok(wgpu::TextureFormat::Bgra8UnormSrgb),
+        DirectXPixelFormat::R10G10B10A2Typeless => Ok(wgpu::TextureFormat::Rgb10a2Uint),
+        DirectXPixelFormat::R10G10B10A2UInt => Ok(wgpu::TextureFormat::Rgb10a2Uint),
+        DirectXPixelFormat::R10G10B10A2UIntNormalized => Ok(wgpu::TextureFormat::Rgb10a2Unorm),
+        DirectXPixelFormat::R16G16B16A16Float => Ok(wgpu::TextureFormat::Rgba16Float),
+        DirectXPixelFormat::R16G16B16A16UIntNormalized => Ok(wgpu::TextureFormat::Rgba16Unorm),
+        _ => Err(WgpuVideoFrameError::Other("Unsupported DirectXPixelFormat".to_string()))
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn dxgi_format_for_wgpu_format(format: wgpu::TextureFormat) -> Option<DXGI_FORMAT> {
+    match format {
+        wgpu::TextureFormat::Bgra8Unorm => Some(DXGI_FORMAT_B8G8R8A8_UNORM),
+        wgpu::TextureFormat::Bgra8UnormSrgb => Some(DXGI_FORMAT_B8G8R8A8_UNORM_SRGB),
+        wgpu::TextureFormat::Rgb10a2Uint => Some(DXGI_FORMAT_R10G10B10A2_UINT),
+        wgpu::TextureFormat::Rgb10a2Unorm => Some(DXGI_FORMAT_R10G10B10A2_UNORM),
+        wgpu::TextureFormat::Rgba16Float => Some(DXGI_FORMAT_R16G16B16A16_FLOAT),
+        wgpu::TextureFormat::Rgba16Unorm => Some(DXGI_FORMAT_R16G16B16A16_UNORM),
+        _ => None
+    }
+}
+
+/// Create a Wgpu texture which frames can be copied into directly by `WgpuVideoFrameExt::copy_to_wgpu_texture`
+/// 
+/// On Windows, the texture is created on a shared heap, so that the capture stream's Direct3D 11 device can copy frames straight
+/// out of the frame pool into it. Only the formats frames are captured in are supported, and the descriptor's dimension must be 2d.
+/// On MacOS, this is the same as `wgpu::Device::create_texture`.
+pub fn create_shareable_wgpu_texture(wgpu_device: &wgpu::Device, descriptor: &wgpu::TextureDescriptor) -> Result<wgpu::Texture, WgpuVideoFrameError> {
+    if wgpu_device_lost(wgpu_device) {
+        return Err(WgpuVideoFrameError::DeviceLost);
+    }
+    #[cfg(target_os = "macos")]
+    {
+        Ok(wgpu_device.create_texture(descriptor))
+    }
+    #[cfg(target_os = "windows")]
+    {
+        if descriptor.dimension != wgpu::TextureDimension::D2 {
+            return Err(WgpuVideoFrameError::Other("Shareable textures must be 2d".to_string()));
+        }
+        let format = dxgi_format_for_wgpu_format(descriptor.format)
+            .ok_or_else(|| WgpuVideoFrameError::Other(format!("Unsupported format for shareable texture: {:?}", descriptor.format)))?;
+        unsafe {
+            wgpu_device.as_hal::<wgpu::hal::api::Dx12, _, _>(|wgpu_dx12_device| {
+                let wgpu_dx12_device = wgpu_dx12_device.ok_or(WgpuVideoFrameError::BackendMismatch)?;
+                let d3d12_device_ptr = wgpu_dx12_device.raw_device().as_ptr() as *mut c_void;
+                let d3d12_device = ID3D12Device::from_raw_borrowed(&d3d12_device_ptr).ok_or(WgpuVideoFrameError::BackendMismatch)?;
+
+                let mut flags = D3D12_RESOURCE_FLAG_ALLOW_RENDER_TARGET | D3D12_RESOURCE_FLAG_ALLOW_SIMULTANEOUS_ACCESS;
+                if descriptor.usage.contains(wgpu::TextureUsages::STORAGE_BINDING) {
+                    flags |= D3D12_RESOURCE_FLAG_ALLOW_UNORDERED_ACCESS;
+                }
+                let d3d12_texture_desc = D3D12_RESOURCE_DESC {
+                    Dimension: D3D12_RESOURCE_DIMENSION_TEXTURE2D,
+                    Alignment: 0,
+                    Width: descriptor.size.width as u64,
+                    Height: descriptor.size.height,
+                    DepthOrArraySize: descriptor.size.depth_or_array_layers as u16,
+                    MipLevels: descriptor.mip_level_count as u16,
+                    Format: format,
+                    SampleDesc: DXGI_SAMPLE_DESC { Count: descriptor.sample_count, Quality: 0 },
+                    Layout: D3D12_TEXTURE_LAYOUT_UNKNOWN,
+                    Flags: flags,
+                };
+                let d3d12_texture_heap_properties = D3D12_HEAP_PROPERTIES {
+                    Type: D3D12_HEAP_TYPE_DEFAULT,
+                    CPUPageProperty: D3D12_CPU_PAGE_PROPERTY_UNKNOWN,
+                    MemoryPoolPreference: D3D12_MEMORY_POOL_UNKNOWN,
+                    CreationNodeMask: 0,
+                    VisibleNodeMask: 0,
+                };
+                let mut d3d12_texture = None;
+                d3d12_device.CreateCommittedResource(
+                    &d3d12_texture_heap_properties as *const _,
+                    D3D12_HEAP_FLAG_SHARED,
+                    &d3d12_texture_desc as *const _,
+                    D3D12_RESOURCE_STATE_COMMON,
+                    None,
+                    &mut d3d12_texture as *mut _
+                ).map_err(|error| WgpuVideoFrameError::Other(format!("Failed to create d3d12 texture: {}", error.to_string())))?;
+                let d3d12_texture: ID3D12Resource = d3d12_texture
+                    .ok_or_else(|| WgpuVideoFrameError::Other("Failed to create d3d12 texture".to_string()))?;
+
+                // wgpu takes its own reference to the texture, as in `get_wgpu_texture`
+                let texture_ptr: ComPtr<winapi::um::d3d12::ID3D12Resource> = d3d12::ComPtr::from_raw(d3d12_texture.as_raw() as *mut _);
+                let hal_texture = wgpu::hal::dx12::Device::texture_from_raw(
+                    texture_ptr,
+                    descriptor.format,
+                    descriptor.dimension,
+                    descriptor.size,
+                    descriptor.mip_level_count,
+                    descriptor.sample_count
+                );
+                Ok(wgpu_device.create_texture_from_hal::<wgpu::hal::api::Dx12>(hal_texture, descriptor))
+            }).unwrap_or(Err(WgpuVideoFrameError::BackendMismatch))
+        }
+    }
+}
 
 /// A capture config which can be supplied with a Wgpu device
 pub trait WgpuCaptureConfigExt: Sized {
@@ -57,66 +497,32 @@ impl WgpuCaptureConfigExt for CaptureConfig {
     fn with_wgpu_device(self, wgpu_device: Arc<dyn AsRef<wgpu::Device> + Send + Sync + 'static>) -> Result<Self, String> {
         #[cfg(target_os = "macos")]
         {
-            unsafe {
-                let device = AsRef::<wgpu::Device>::as_ref(&*wgpu_device).as_hal::<wgpu::hal::api::Metal, _, _>(move |device| {
-                    if let Some(device) = device {
-                        Some(device.raw_device().lock().clone())
-                    } else {
-                        None
-                    }
-                }).expect("Expected metal device underneath wgpu");
-                Ok(Self {
-                    impl_capture_config: MacosCaptureConfig {
-                        metal_device: device,
-                        wgpu_device: Some(wgpu_device.clone()),
-                        ..self.impl_capture_config
-                    },
-                    ..self
-                })
-            }
+            let device = wgpu_metal_device(AsRef::<wgpu::Device>::as_ref(&*wgpu_device))
+                .ok_or_else(|| "Expected metal device underneath wgpu".to_string())?;
+            watch_wgpu_device(AsRef::<wgpu::Device>::as_ref(&*wgpu_device));
+            Ok(Self {
+                impl_capture_config: MacosCaptureConfig {
+                    metal_device: Some(device),
+                    wgpu_device: Some(wgpu_device.clone()),
+                    ..self.impl_capture_config
+                },
+                ..self
+            })
         }
         #[cfg(target_os = "windows")]
         {
             unsafe {
-                let mut dxgi_adapter_result = Err("Unimplemented for this wgpu backend".to_string());
-                AsRef::<wgpu::Device>::as_ref(&*wgpu_device).as_hal::<wgpu::hal::api::Dx12, _, _>(|device| {
-                    device.map(|device| {
-                        //device.raw_device().AddRef();
-                        let raw_device_ptr = device.raw_device().as_mut_ptr() as *mut c_void;
-                        let raw_queue_ptr = device.raw_queue().as_mut_ptr() as *mut c_void;
-                        let d3d12_device = ID3D12Device::from_raw(raw_device_ptr);
-                        let d3d12_queue = ID3D12CommandQueue::from_raw(raw_queue_ptr);
-                        let adapter_luid = d3d12_device.GetAdapterLuid();
-                        let dxgi_factory: IDXGIFactory5 = match CreateDXGIFactory() {
-                            Err(error) => {
-                                dxgi_adapter_result = Err(format!("Failed to create dxgi factory: {}", error.to_string()));
-                                return;
-                            },
-                            Ok(factory) => factory,
-                        };
-                        dxgi_adapter_result = dxgi_factory.EnumAdapterByLuid(adapter_luid)
-                            .map_err(|error| format!("Failed to find matching dxgi adapter for wgpu device: {}", error.to_string()))
-                            .map(|dxgi_adapter: IDXGIAdapter4| (dxgi_adapter, d3d12_device, d3d12_queue));
-                    })
-                });
-                let (dxgi_adapter, _d3d12_device, _d3d12_queue) = dxgi_adapter_result?;
-                let dxgi_adapter = dxgi_adapter.cast::<IDXGIAdapter4>().unwrap();
-                let mut d3d11_device = None;
-                D3D11CreateDevice (
-                    &dxgi_adapter,
-                    D3D_DRIVER_TYPE_UNKNOWN,
-                    None,
-                    D3D11_CREATE_DEVICE_BGRA_SUPPORT | D3D11_CREATE_DEVICE_DEBUG,
-                    Some(&[D3D_FEATURE_LEVEL_11_0]),
-                    D3D11_SDK_VERSION,
-                    Some(&mut d3d11_device),
-                    None,
-                    None
-                ).map_err(|error| format!("Failed to create d3d11 device from dxgi adapter: {}", error.to_string()))?;
-                let d3d11_device = d3d11_device.unwrap();
+                let adapter_luid = wgpu_adapter_luid(AsRef::<wgpu::Device>::as_ref(&*wgpu_device))
+                    .ok_or_else(|| "Unimplemented for this wgpu backend".to_string())?;
+                let dxgi_factory: IDXGIFactory5 = CreateDXGIFactory()
+                    .map_err(|error| format!("Failed to create dxgi factory: {}", error.to_string()))?;
+                let dxgi_adapter: IDXGIAdapter4 = dxgi_factory.EnumAdapterByLuid(adapter_luid)
+                    .map_err(|error| format!("Failed to find matching dxgi adapter for wgpu device: {}", error.to_string()))?;
+                watch_wgpu_device(AsRef::<wgpu::Device>::as_ref(&*wgpu_device));
+                // The d3d11 device is created on the wgpu device's adapter when the stream starts
                 Ok(Self {
                     impl_capture_config: WindowsCaptureConfig {
-                        d3d11_device: Some(d3d11_device),
+                        d3d11_device: None,
                         wgpu_device: Some(wgpu_device),
                         dxgi_adapter: Some(dxgi_adapter),
                         ..self.impl_capture_config
@@ -142,6 +548,7 @@ pub enum WgpuVideoFramePlaneTexture {
 
 /// Represents an error getting the texture from a video frame
 #[derive(Clone, Debug)]
+#[non_exhaustive]
 pub enum WgpuVideoFrameError {
     /// the backend texture couldn't be fetched
     NoBackendTexture,
@@ -149,16 +556,47 @@ pub enum WgpuVideoFrameError {
     InvalidVideoPlaneTexture,
     /// No Wgpu device was supplied to the capture stream
     NoWgpuDevice,
+    /// The Wgpu device used for this frame was lost or destroyed - supply a new one with `WgpuCaptureStreamExt::replace_wgpu_device`
+    /// 
+    /// See `WgpuCaptureStreamExt::replace_wgpu_device` for how loss is detected
+    DeviceLost,
+    /// The Wgpu device isn't on the backend frames are shared with - Metal on MacOS, or DX12 on Windows
+    BackendMismatch,
+    /// The destination texture's size or format doesn't match the frame
+    DestinationMismatch,
     Other(String)
 }
 
 
+impl WgpuVideoFrameError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::NoBackendTexture => ErrorKind::WgpuVideoFrameNoBackendTexture,
+            Self::InvalidVideoPlaneTexture => ErrorKind::WgpuVideoFrameInvalidVideoPlaneTexture,
+            Self::NoWgpuDevice => ErrorKind::WgpuVideoFrameNoWgpuDevice,
+            Self::DeviceLost => ErrorKind::WgpuVideoFrameDeviceLost,
+            Self::BackendMismatch => ErrorKind::WgpuVideoFrameBackendMismatch,
+            Self::DestinationMismatch => ErrorKind::WgpuVideoFrameDestinationMismatch,
+            Self::Other(_) => ErrorKind::WgpuVideoFrameOther,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+}
+
 impl Display for WgpuVideoFrameError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::NoBackendTexture => f.write_str("WgpuVideoFrameError::NoBackendTexture"),
             Self::InvalidVideoPlaneTexture => f.write_str("WgpuVideoFrameError::InvalidVideoPlaneTexture"),
             Self::NoWgpuDevice => f.write_str("WgpuVideoFrameError::NoWgpuDevice"),
+            Self::DeviceLost => f.write_str("WgpuVideoFrameError::DeviceLost"),
+            Self::BackendMismatch => f.write_str("WgpuVideoFrameError::BackendMismatch"),
+            Self::DestinationMismatch => f.write_str("WgpuVideoFrameError::DestinationMismatch"),
             Self::Other(error) => f.write_fmt(format_args!("WgpuVideoFrameError::Other(\"{}\")", error)),
         }
     }
@@ -181,7 +619,22 @@ impl Error for WgpuVideoFrameError {
 /// A video frame which can be used to create Wgpu textures
 pub trait WgpuVideoFrameExt {
     /// Get the texture for the given plane of the video frame
+    /// 
+    /// On Windows, the texture is a copy of the frame, which the stream may reuse for a later frame once this frame is dropped - so keep
+    /// the frame while the texture is in use, and copy the texture to keep its contents past that. Work submitted to the device's queue
+    /// before the texture is reused finishes first.
     fn get_wgpu_texture(&self, plane: WgpuVideoFramePlaneTexture, label: Option<&'static str>) -> Result<wgpu::Texture, WgpuVideoFrameError>;
+    /// Copy the given plane of the video frame into a texture of the same size and format, on the stream's Wgpu device
+    /// 
+    /// On Windows, when the destination was created with `create_shareable_wgpu_texture`, the frame is copied straight out of the
+    /// frame pool into it, and nothing is recorded into the encoder. Otherwise - or when the adapter doesn't support sharing the
+    /// destination - the frame goes through the same copy as `get_wgpu_texture`, and a copy from that into the destination is
+    /// recorded into the encoder, so the destination needs `wgpu::TextureUsages::COPY_DST`.
+    /// 
+    /// The frame's surface is reused by the frame pool once the frame is dropped. On Windows, when the destination is shareable, the copy
+    /// out of it has finished by the time this returns, so the frame can be dropped straight away, and work submitted to the device's queue
+    /// afterwards sees the copied frame. Otherwise the copy is recorded into the encoder, so keep the frame until the encoder's commands have been submitted.
+    fn copy_to_wgpu_texture(&self, plane: WgpuVideoFramePlaneTexture, encoder: &mut wgpu::CommandEncoder, destination: &wgpu::Texture) -> Result<(), WgpuVideoFrameError>;
 }
 
 impl WgpuVideoFrameExt for VideoFrame {
@@ -192,12 +645,18 @@ impl WgpuVideoFrameExt for VideoFrame {
                 MacosVideoFrame::SCStream(sc_stream_frame) => sc_stream_frame.wgpu_device.clone(),
                 MacosVideoFrame::CGDisplayStream(cg_display_stream_frame) => cg_display_stream_frame.wgpu_device.clone(),
             }.ok_or(WgpuVideoFrameError::NoWgpuDevice)?;
+            if wgpu_device_lost((*wgpu_device).as_ref()) {
+                return Err(WgpuVideoFrameError::DeviceLost);
+            }
+            // The texture is created on the wgpu device's own metal device, which may differ from the stream's if the wgpu device was replaced
+            let metal_device = wgpu_metal_device((&*wgpu_device).as_ref())
+                .ok_or(WgpuVideoFrameError::BackendMismatch)?;
             let metal_plane = match plane {
                 WgpuVideoFramePlaneTexture::Rgba => MetalVideoFramePlaneTexture::Rgba,
                 WgpuVideoFramePlaneTexture::Chroma => MetalVideoFramePlaneTexture::Chroma,
                 WgpuVideoFramePlaneTexture::Luminance => MetalVideoFramePlaneTexture::Luminance,
             };
-            match MetalVideoFrameExt::get_metal_texture(self, metal_plane) {
+            match get_metal_texture_with_device(self, metal_plane, &metal_device, metal::MTLTextureUsage::ShaderRead) {
                 Ok(metal_texture) => {
                     unsafe {
                         let descriptor = wgpu::TextureDescriptor {
@@ -274,28 +733,22 @@ impl WgpuVideoFrameExt for VideoFrame {
             }
             let wgpu_device = self.impl_video_frame.wgpu_device.as_ref()
                 .ok_or(WgpuVideoFrameError::NoWgpuDevice)?.clone();
+            if wgpu_device_lost((*wgpu_device).as_ref()) {
+                return Err(WgpuVideoFrameError::DeviceLost);
+            }
             let d3d11_5_device = self.impl_video_frame.device.cast::<ID3D11Device5>()
                 .map_err(|error| WgpuVideoFrameError::Other(format!("Device is incompatible with resource sharing interface: {}", error)))?;
             let (frame_texture, pixel_format) = WindowsDx11VideoFrame::get_dx11_texture(self)
                 .map_err(|_| WgpuVideoFrameError::NoBackendTexture)?;
             
-            let wgpu_format = match pixel_format {
-                DirectXPixelFormat::B8G8R8A8Typeless => wgpu::TextureFormat::Bgra8Unorm,
-                DirectXPixelFormat::B8G8R8A8UIntNormalized => wgpu::TextureFormat::Bgra8Unorm,
-                DirectXPixelFormat::B8G8R8A8UIntNormalizedSrgb => wgpu::TextureFormat::Bgra8UnormSrgb,
-                DirectXPixelFormat::R10G10B10A2Typeless => wgpu::TextureFormat::Rgb10a2Uint,
-                DirectXPixelFormat::R10G10B10A2UInt => wgpu::TextureFormat::Rgb10a2Uint,
-                DirectXPixelFormat::R10G10B10A2UIntNormalized => wgpu::TextureFormat::Rgb10a2Unorm,
-                DirectXPixelFormat::R16G16B16A16Float => wgpu::TextureFormat::Rgba16Float,
-                _ => return Err(WgpuVideoFrameError::Other("Unsupported DirectXPixelFormat".to_string()))
-            };
+            let wgpu_format = wgpu_format_for_pixel_format(pixel_format)?;
             unsafe {
                 AsRef::as_ref(&*wgpu_device).as_hal::<wgpu::hal::api::Dx12, _, _>(|wgpu_dx12_device| {
-                    let wgpu_dx12_device = wgpu_dx12_device.unwrap();
+                    let wgpu_dx12_device = wgpu_dx12_device.ok_or(WgpuVideoFrameError::BackendMismatch)?;
                     let d3d12_device_ptr = wgpu_dx12_device.raw_device().as_ptr() as *mut c_void;
-                    let d3d12_device = ID3D12Device::from_raw_borrowed(&d3d12_device_ptr).unwrap();
+                    let d3d12_device = ID3D12Device::from_raw_borrowed(&d3d12_device_ptr).ok_or(WgpuVideoFrameError::BackendMismatch)?;
                     let d3d12_queue_ptr = wgpu_dx12_device.raw_queue().as_ptr() as *mut c_void;
-                    let d3d12_queue = ID3D12CommandQueue::from_raw_borrowed(&d3d12_queue_ptr).unwrap();
+                    let d3d12_queue = ID3D12CommandQueue::from_raw_borrowed(&d3d12_queue_ptr).ok_or(WgpuVideoFrameError::BackendMismatch)?;
 
                     let mut frame_desc = D3D11_TEXTURE2D_DESC::default();
                     frame_texture.GetDesc(&mut frame_desc as *mut _);
@@ -306,93 +759,19 @@ impl WgpuVideoFrameExt for VideoFrame {
                         depth_or_array_layers: frame_desc.ArraySize,
                     };
 
-                    let d3d12_texture_desc = D3D12_RESOURCE_DESC {
-                        Dimension: D3D12_RESOURCE_DIMENSION_TEXTURE2D,
-                        Alignment: 0,
-                        Width: frame_desc.Width as u64,
-                        Height: frame_desc.Height,
-                        DepthOrArraySize: frame_desc.ArraySize as u16,
-                        MipLevels: frame_desc.MipLevels as u16,
-                        Format: frame_desc.Format,
-                        SampleDesc: frame_desc.SampleDesc,
-                        Layout: D3D12_TEXTURE_LAYOUT_UNKNOWN,
-                        Flags: D3D12_RESOURCE_FLAG_ALLOW_RENDER_TARGET | D3D12_RESOURCE_FLAG_ALLOW_SIMULTANEOUS_ACCESS | D3D12_RESOURCE_FLAG_ALLOW_UNORDERED_ACCESS
-                    };
-                    let d3d12_texture_heap_properties = D3D12_HEAP_PROPERTIES {
-                        Type: D3D12_HEAP_TYPE_DEFAULT,
-                        CPUPageProperty: D3D12_CPU_PAGE_PROPERTY_UNKNOWN,
-                        MemoryPoolPreference: D3D12_MEMORY_POOL_UNKNOWN,
-                        CreationNodeMask: 0,
-                        VisibleNodeMask: 0,
-                    };  
-                    let d3d12_texture_clear_value = D3D12_CLEAR_VALUE {
-                        Format: frame_desc.Format,
-                        Anonymous: windows::Win32::Graphics::Direct3D12::D3D12_CLEAR_VALUE_0 {
-                            Color: [0.0, 0.0, 0.0, 0.0]
-                        }
-                    };
-
-                    let mut d3d12_texture = None;
-                    d3d12_device.CreateCommittedResource(
-                        &d3d12_texture_heap_properties as *const _,
-                        D3D12_HEAP_FLAG_SHARED,
-                        &d3d12_texture_desc as *const _,
-                        D3D12_RESOURCE_STATE_COMMON,
-                        Some(&d3d12_texture_clear_value),
-                        &mut d3d12_texture as *mut _
-                    ).map_err(|error| WgpuVideoFrameError::Other(format!("Failed to create d3d12 texture: {}", error.to_string())))?;
-                    let d3d12_texture: ID3D12Resource = d3d12_texture.unwrap();
-
-                    let dxgi_shared_texture_handle = d3d12_device.CreateSharedHandle(
-                        &d3d12_texture,
-                        None,
-                        GENERIC_ALL.0,
-                        None
-                    ).map_err(|error| WgpuVideoFrameError::Other(format!("Failed to share d3d12 texture: {}", error.to_string())))?;
-
-                    let d3d11_shared_texture: ID3D11Texture2D = d3d11_5_device.OpenSharedResource1(dxgi_shared_texture_handle)
-                    .map_err(|error| WgpuVideoFrameError::Other(format!("Failed to use dxgi shared texture in d3d11: {}", error.to_string())))?;
-
-                    let d3d12_fence: ID3D12Fence = d3d12_device.CreateFence(0, D3D12_FENCE_FLAG_SHARED)
-                        .map_err(|error|  WgpuVideoFrameError::Other(format!("Failed to create fence: {}", error)))?;
-                    let fence_event = CreateEventA(None, false, false, None)
-                        .map_err(|error|  WgpuVideoFrameError::Other(format!("Failed to create fence event: {}", error)))?;
-                    d3d12_fence.SetEventOnCompletion(1, fence_event)
-                        .map_err(|error|  WgpuVideoFrameError::Other(format!("Failed to set fence completion event: {}", error.to_string())))?;
-
-                    let dxgi_shared_fence_handle = d3d12_device.CreateSharedHandle(
-                        &d3d12_fence,
-                        None,
-                        GENERIC_ALL.0,
-                        None
-                    ).map_err(|error| WgpuVideoFrameError::Other(format!("Failed to share fence with dxgi: {}", error.to_string())))?;
-
-                    let mut d3d11_shared_fence = None;
-                    d3d11_5_device.OpenSharedFence(dxgi_shared_fence_handle, &mut d3d11_shared_fence)
-                        .map_err(|error| WgpuVideoFrameError::Other(format!("Failed to use dxgi shared fence: {}", error.to_string())))?;
-                    let d3d11_shared_fence: ID3D11Fence = d3d11_shared_fence.unwrap();
-
-                    {
-                        let device_context: ID3D11DeviceContext4 = self.impl_video_frame.device.GetImmediateContext()
-                            .map_err(|error| WgpuVideoFrameError::Other(format!("Failed to get d3d11 device context: {}", error.to_string())))?
-                            .cast()
-                            .map_err(|error| WgpuVideoFrameError::Other(format!("Failed to get d3d11 device context v4: {}", error.to_string())))?;
-                        device_context.CopyResource(&d3d11_shared_texture, &frame_texture);
-                        device_context.Signal(&d3d11_shared_fence, 1)
-                            .map_err(|error| WgpuVideoFrameError::Other(format!("Failed to queue fence signal: {}", error.to_string())))?;
-                        drop(frame_texture);
-                        drop(d3d11_shared_texture);
-                        drop(d3d11_shared_fence);
-                        device_context.Flush();
-                    }
-
-                    CloseHandle(dxgi_shared_texture_handle)
-                        .map_err(|error| WgpuVideoFrameError::Other(format!("Failed to close shared texture handle: {}", error.to_string())))?;
+                    let mut texture_cache = self.impl_video_frame.wgpu_texture_cache.lock();
+                    texture_cache.use_device(d3d12_device);
+                    let (d3d12_texture, d3d11_shared_texture, lease) = texture_cache.shared_texture(d3d12_device, &d3d11_5_device, &frame_desc)?;
+                    let fence = texture_cache.fence(d3d12_device, &d3d11_5_device)?;
+                    copy_frame_to_shared_texture(self, frame_texture, d3d11_shared_texture, d3d12_queue, fence)?;
+                    drop(texture_cache);
+                    self.impl_video_frame.wgpu_texture_leases.lock().push(lease);
 
-                    let texture_ptr: ComPtr<winapi::um::d3d12::ID3D12Resource> = d3d12::ComPtr::from_raw(d3d12_texture.into_raw() as *mut _);
+                    // wgpu takes its own reference to the texture, and releases it once the returned texture is dropped and no longer in use
+                    let texture_ptr: ComPtr<winapi::um::d3d12::ID3D12Resource> = d3d12::ComPtr::from_raw(d3d12_texture.as_raw() as *mut _);
 
                     let hal_texture = wgpu::hal::dx12::Device::texture_from_raw(
-                        texture_ptr.clone(),
+                        texture_ptr,
                         wgpu_format,
                         wgpu::TextureDimension::D2,
                         wgpu_size,
@@ -400,18 +779,6 @@ impl WgpuVideoFrameExt for VideoFrame {
                         frame_desc.SampleDesc.Count
                     );
 
-                    d3d12_queue.Wait(&d3d12_fence, 1)
-                        .map_err(|error| WgpuVideoFrameError::Other(format!("Failed to enqueue wait on fence: {}", error.to_string())))?;
-
-                    if WaitForSingleObjectEx(fence_event, INFINITE, false) != WAIT_OBJECT_0 {
-                        Err(WgpuVideoFrameError::Other(format!("Failed wait on completion fence")))?
-                    }
-
-                    CloseHandle(dxgi_shared_fence_handle)
-                        .map_err(|error| WgpuVideoFrameError::Other(format!("Failed to close shared fence handle: {}", error.to_string())))?;
-                    CloseHandle(fence_event)
-                        .map_err(|error| WgpuVideoFrameError::Other(format!("Failed to close fence event handle: {}", error.to_string())))?;
-                    
                     let desc = wgpu::TextureDescriptor {
                         label,
                         size: wgpu_size,
@@ -422,15 +789,76 @@ impl WgpuVideoFrameExt for VideoFrame {
                         usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
                         view_formats: &[wgpu_format]
                     };
-                    let result = Ok((*wgpu_device).as_ref().create_texture_from_hal::<wgpu::hal::api::Dx12>(hal_texture, &desc));
+                    Ok((*wgpu_device).as_ref().create_texture_from_hal::<wgpu::hal::api::Dx12>(hal_texture, &desc))
+                }).unwrap_or(Err(WgpuVideoFrameError::BackendMismatch))
+            }
+        }
+    }
 
-                    // dirty hack to reduce the refount
-                    std::mem::drop(std::mem::transmute_copy::<_, ComPtr<winapi::um::d3d12::ID3D12Resource>>(&texture_ptr));
+    fn copy_to_wgpu_texture(&self, plane: WgpuVideoFramePlaneTexture, encoder: &mut wgpu::CommandEncoder, destination: &wgpu::Texture) -> Result<(), WgpuVideoFrameError> {
+        #[cfg(target_os = "windows")]
+        {
+            if plane != WgpuVideoFramePlaneTexture::Rgba {
+                return Err(WgpuVideoFrameError::InvalidVideoPlaneTexture);
+            }
+            let wgpu_device = self.impl_video_frame.wgpu_device.as_ref()
+                .ok_or(WgpuVideoFrameError::NoWgpuDevice)?.clone();
+            if wgpu_device_lost((*wgpu_device).as_ref()) {
+                return Err(WgpuVideoFrameError::DeviceLost);
+            }
+            let d3d11_5_device = self.impl_video_frame.device.cast::<ID3D11Device5>()
+                .map_err(|error| WgpuVideoFrameError::Other(format!("Device is incompatible with resource sharing interface: {}", error)))?;
+            let (frame_texture, pixel_format) = WindowsDx11VideoFrame::get_dx11_texture(self)
+                .map_err(|_| WgpuVideoFrameError::NoBackendTexture)?;
+            let wgpu_format = wgpu_format_for_pixel_format(pixel_format)?;
+            let mut frame_desc = D3D11_TEXTURE2D_DESC::default();
+            unsafe { frame_texture.GetDesc(&mut frame_desc as *mut _); }
+            if (destination.width(), destination.height(), destination.sample_count(), destination.format().remove_srgb_suffix()) !=
+                (frame_desc.Width, frame_desc.Height, frame_desc.SampleDesc.Count, wgpu_format.remove_srgb_suffix()) {
+                return Err(WgpuVideoFrameError::DestinationMismatch);
+            }
+
+            let copied = unsafe {
+                let d3d12_destination_texture = destination.as_hal::<wgpu::hal::api::Dx12, _, _>(|hal_texture| {
+                    hal_texture.and_then(|hal_texture| {
+                        let d3d12_texture_ptr = hal_texture.raw_resource().as_mut_ptr() as *mut c_void;
+                        ID3D12Resource::from_raw_borrowed(&d3d12_texture_ptr).cloned()
+                    })
+                });
+                AsRef::as_ref(&*wgpu_device).as_hal::<wgpu::hal::api::Dx12, _, _>(|wgpu_dx12_device| {
+                    let wgpu_dx12_device = wgpu_dx12_device.ok_or(WgpuVideoFrameError::BackendMismatch)?;
+                    let d3d12_device_ptr = wgpu_dx12_device.raw_device().as_ptr() as *mut c_void;
+                    let d3d12_device = ID3D12Device::from_raw_borrowed(&d3d12_device_ptr).ok_or(WgpuVideoFrameError::BackendMismatch)?;
+                    let d3d12_queue_ptr = wgpu_dx12_device.raw_queue().as_ptr() as *mut c_void;
+                    let d3d12_queue = ID3D12CommandQueue::from_raw_borrowed(&d3d12_queue_ptr).ok_or(WgpuVideoFrameError::BackendMismatch)?;
+                    let Some(d3d12_destination_texture) = d3d12_destination_texture else {
+                        return Ok(false);
+                    };
 
-                    result
-                }).unwrap()
+                    let mut texture_cache = self.impl_video_frame.wgpu_texture_cache.lock();
+                    texture_cache.use_device(d3d12_device);
+                    // Textures which weren't created on a shared heap can't be opened on the stream's device
+                    let Some(d3d11_destination_texture) = texture_cache.destination_texture(d3d12_device, &d3d11_5_device, &d3d12_destination_texture) else {
+                        return Ok(false);
+                    };
+                    let fence = texture_cache.fence(d3d12_device, &d3d11_5_device)?;
+                    copy_frame_to_shared_texture(self, frame_texture, d3d11_destination_texture, d3d12_queue, fence)?;
+                    Ok(true)
+                }).unwrap_or(Err(WgpuVideoFrameError::BackendMismatch))?
+            };
+            if copied {
+                return Ok(());
             }
         }
+
+        let source = self.get_wgpu_texture(plane, None)?;
+        if (source.size(), source.sample_count(), source.format().remove_srgb_suffix()) !=
+            (destination.size(), destination.sample_count(), destination.format().remove_srgb_suffix()) {
+            return Err(WgpuVideoFrameError::DestinationMismatch);
+        }
+        // wgpu keeps the source texture alive until the encoder's commands have run
+        encoder.copy_texture_to_texture(source.as_image_copy(), destination.as_image_copy(), source.size());
+        Ok(())
     }
 }
 
@@ -440,6 +868,24 @@ pub trait WgpuCaptureStreamExt {
     fn get_wgpu_device_wrapper(&self) -> Option<Arc<dyn AsRef<wgpu::Device> + Send + Sync + 'static>>;
     /// Gets the Wgpu device referenced by device wrapper supplied to `CaptureConfig::with_wgpu_device(..)`
     fn get_wgpu_device(&self) -> Option<&wgpu::Device>;
+    /// Replace the Wgpu device used for frames captured from now on, E.G. after the previous device was lost, without restarting the stream
+    /// 
+    /// Frames captured before the replacement keep using the previous device - if it was lost, `get_wgpu_texture` returns `WgpuVideoFrameError::DeviceLost` for them.
+    /// On Windows, the new device must be on the same adapter as the capture stream.
+    /// 
+    /// Loss is detected through the lost callback of each device supplied to a stream, which the stream sets when the device is supplied
+    /// (replacing any set before - see `mark_wgpu_device_lost` for setting your own). Once it fires, for any reason other than the callback being replaced,
+    /// frames on the device return `WgpuVideoFrameError::DeviceLost`, and the device can't be supplied again. Loss is only seen once wgpu reports it,
+    /// which may be some time after the GPU was reset or removed, so textures handed out just before may still fail to be used.
+    /// A device on the wrong backend is reported as `WgpuVideoFrameError::BackendMismatch` instead.
+    fn replace_wgpu_device(&mut self, wgpu_device: Arc<dyn AsRef<wgpu::Device> + Send + Sync + 'static>) -> Result<(), String>;
+    /// Get the number of backend textures created so far to hold this stream's frames for `WgpuVideoFrameExt::get_wgpu_texture`
+    /// 
+    /// On Windows, frames are copied into a few cached textures, which are reused once wgpu is done with the textures returned
+    /// for earlier frames - so this stays small while returned textures are dropped promptly. Frames copied straight into shareable
+    /// textures by `WgpuVideoFrameExt::copy_to_wgpu_texture` don't need any. On MacOS, textures wrap the
+    /// frames' own surfaces, so this is always zero.
+    fn wgpu_texture_allocation_count(&self) -> u64;
 }
 
 impl WgpuCaptureStreamExt for CaptureStream {
@@ -456,4 +902,37 @@ impl WgpuCaptureStreamExt for CaptureStream {
         #[cfg(target_os = "windows")]
         { self.impl_capture_stream.wgpu_device.clone() }
     }
+
+    fn replace_wgpu_device(&mut self, wgpu_device: Arc<dyn AsRef<wgpu::Device> + Send + Sync + 'static>) -> Result<(), String> {
+        if wgpu_device_lost(AsRef::<wgpu::Device>::as_ref(&*wgpu_device)) {
+            return Err("Wgpu device was lost".to_string());
+        }
+        #[cfg(target_os = "macos")]
+        {
+            wgpu_metal_device(AsRef::<wgpu::Device>::as_ref(&*wgpu_device))
+                .ok_or_else(|| "Expected metal device underneath wgpu".to_string())?;
+        }
+        #[cfg(target_os = "windows")]
+        {
+            let adapter_luid = wgpu_adapter_luid(AsRef::<wgpu::Device>::as_ref(&*wgpu_device))
+                .ok_or_else(|| "Unimplemented for this wgpu backend".to_string())?;
+            let stream_adapter_luid = unsafe { self.impl_capture_stream.dxgi_device.GetAdapter().and_then(|adapter| adapter.GetDesc()) }
+                .map_err(|error| format!("Failed to get the capture stream's dxgi adapter: {}", error.to_string()))?
+                .AdapterLuid;
+            if (adapter_luid.LowPart, adapter_luid.HighPart) != (stream_adapter_luid.LowPart, stream_adapter_luid.HighPart) {
+                return Err("Wgpu device is on a different adapter than the capture stream".to_string());
+            }
+        }
+        watch_wgpu_device(AsRef::<wgpu::Device>::as_ref(&*wgpu_device));
+        *self.impl_capture_stream.frame_wgpu_device.lock() = Some(wgpu_device.clone());
+        self.impl_capture_stream.wgpu_device = Some(wgpu_device);
+        Ok(())
+    }
+
+    fn wgpu_texture_allocation_count(&self) -> u64 {
+        #[cfg(target_os = "macos")]
+        { 0 }
+        #[cfg(target_os = "windows")]
+        { self.impl_capture_stream.wgpu_texture_cache.lock().allocation_count }
+    }
 }
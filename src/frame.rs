@@ -1,7 +1,7 @@
 #![allow(unused)]
-use std::{marker::PhantomData, time::{Duration, Instant}, fmt::Debug};
+use std::{error::Error, fmt::{Debug, Display}, marker::PhantomData, sync::Arc, time::{Duration, Instant}};
 
-use crate::{platform::platform_impl::{ImplAudioFrame, ImplVideoFrame}, util::*};
+use crate::{capture_stream::CapturePixelFormat, error::ErrorKind, platform::platform_impl::{ImplAudioFrame, ImplVideoFrame}, util::*};
 
 /// The rate to capture audio samples
 #[derive(Copy, Clone, Debug)]
@@ -48,6 +48,8 @@ impl<T: Copy> AudioChannelDataSamples<'_, T> {
 }
 
 /// Represents an error getting the data for an audio channel
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum AudioBufferError {
     // The audio sample format was not supported
     UnsupportedFormat,
@@ -56,12 +58,54 @@ pub enum AudioBufferError {
     Other(String)
 }
 
+impl AudioBufferError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::UnsupportedFormat => ErrorKind::AudioBufferUnsupportedFormat,
+            Self::InvalidChannel => ErrorKind::AudioBufferInvalidChannel,
+            Self::Other(_) => ErrorKind::AudioBufferOther,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+}
+
+impl Display for AudioBufferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedFormat => f.write_str("AudioBufferError::UnsupportedFormat"),
+            Self::InvalidChannel => f.write_str("AudioBufferError::InvalidChannel"),
+            Self::Other(error) => f.write_fmt(format_args!("AudioBufferError::Other(\"{}\")", error)),
+        }
+    }
+}
+
+impl Error for AudioBufferError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+
+    fn description(&self) -> &str {
+        "description() is deprecated; use Display"
+    }
+
+    fn cause(&self) -> Option<&dyn Error> {
+        self.source()
+    }
+}
+
 pub(crate) trait AudioCaptureFrame {
     fn sample_rate(&self) -> AudioSampleRate;
     fn channel_count(&self) -> AudioChannelCount;
     fn audio_channel_buffer(&mut self, channel: usize) -> Result<AudioChannelData<'_>, AudioBufferError>;
     fn duration(&self) -> Duration;
     fn origin_time(&self) -> Duration;
+    fn silent(&self) -> bool;
+    fn discontinuity(&self) -> bool;
     fn frame_id(&self) -> u64;
 }
 
@@ -102,7 +146,22 @@ impl AudioFrame {
 
     /// Get the time since the start of the stream that this audio frame begins at
     pub fn origin_time(&self) -> Duration {
-        self.impl_audio_frame.duration()
+        self.impl_audio_frame.origin_time()
+    }
+
+    /// Get whether the system reported this frame as silent - its samples are all zero
+    /// 
+    /// Only reported on Windows - on MacOS, silent audio is delivered as ordinary frames of zeroed samples
+    pub fn silent(&self) -> bool {
+        self.impl_audio_frame.silent()
+    }
+
+    /// Get whether samples were lost between the previous audio frame and this one, such as after a glitch in the system audio engine
+    /// 
+    /// Unlike a silent frame, which holds samples of silence, a discontinuity marks audio that was never delivered.
+    /// Only reported on Windows.
+    pub fn discontinuity(&self) -> bool {
+        self.impl_audio_frame.discontinuity()
     }
 
     /// Get the sequence id of this frame (monotonically increasing)
@@ -113,6 +172,55 @@ impl AudioFrame {
     }
 }
 
+/// The color space of a video frame's pixels - the color primaries and transfer function needed to display or tone map them
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ColorSpace {
+    /// sRGB primaries and transfer function - standard dynamic range content
+    Srgb,
+    /// Display P3 primaries, with the sRGB transfer function
+    DisplayP3,
+    /// Rec. 2020 primaries, with the PQ (SMPTE ST 2084) transfer function - HDR10 content
+    Rec2020Pq,
+    /// Rec. 2020 primaries, with the HLG (hybrid log-gamma) transfer function
+    Rec2020Hlg,
+    /// sRGB primaries with a linear transfer function, extended past 1.0 (SDR white) for HDR content - scRGB, as in `CapturePixelFormat::RgbaF16` frames
+    LinearExtendedSrgb,
+    /// A color space the crate doesn't recognize
+    Unknown,
+}
+
+impl ColorSpace {
+    /// Checks whether this is a high dynamic range color space, which needs tone mapping for SDR output
+    pub fn is_hdr(&self) -> bool {
+        matches!(self, Self::Rec2020Pq | Self::Rec2020Hlg | Self::LinearExtendedSrgb)
+    }
+}
+
+/// The cursor as a video frame was captured, for drawing it over the frame yourself - see `VideoFrame::cursor()`
+#[derive(Clone, Debug)]
+pub struct CursorInfo {
+    /// The position of the cursor's hotspot, in the same coordinate space as `VideoFrame::content_rect()`
+    pub position: Point,
+    /// Whether the cursor was shown, rather than hidden by an application
+    pub visible: bool,
+    /// The cursor's image, or `None` if it couldn't be read
+    pub image: Option<Arc<CursorImage>>,
+}
+
+/// The image of a cursor, at the resolution it's shown on screen
+/// 
+/// When the frame was scaled (see `VideoFrame::native_content_size()`), scale the image by the same amount to match the frame.
+#[derive(Debug)]
+pub struct CursorImage {
+    /// BGRA pixels with straight (not premultiplied) alpha, row by row from the top-left
+    pub data: Box<[[u8; 4]]>,
+    pub width: usize,
+    pub height: usize,
+    /// The offset of the cursor's hotspot from the top-left of the image, in pixels - draw the image at `CursorInfo::position` minus this offset
+    pub hotspot: Point,
+}
+
 pub(crate) trait VideoCaptureFrame {
     fn size(&self) -> Size;
     fn dpi(&self) -> f64;
@@ -121,11 +229,109 @@ pub(crate) trait VideoCaptureFrame {
     fn capture_time(&self) -> Instant;
     fn frame_id(&self) -> u64;
     fn content_rect(&self) -> Rect;
+    fn native_content_size(&self) -> Size;
+    fn cursor_position(&self) -> Option<Point>;
+    fn cursor_visible(&self) -> bool;
+    fn cursor_image(&self) -> Option<Arc<CursorImage>>;
+    fn dirty_rects(&self) -> Option<Vec<Rect>>;
+    fn color_space(&self) -> ColorSpace;
+    fn map_planes<T>(&self, map: impl FnOnce(&[PlaneData<'_>]) -> T) -> Result<T, VideoFrameMapError>;
+}
+
+// The rect of a CGDisplayStream frame covered by its source rect, which the stream fits into the frame preserving its aspect ratio
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn display_stream_content_rect(source_size: Size, frame_size: Size) -> Rect {
+    crate::capture_stream::ScalingQuality::Smooth.content_rect(source_size, frame_size)
+}
+
+// The dpi of a CGDisplayStream frame's content, from the dpi of the display's pixels - the source rect is in points,
+// and covers `backing_scale_factor` display pixels per point before it's scaled into the content rect
+#[cfg(any(target_os = "macos", test))]
+pub(crate) fn display_stream_dpi(display_dpi: f64, backing_scale_factor: f64, source_size: Size, content_size: Size) -> f64 {
+    let source_pixels = source_size.width * backing_scale_factor;
+    if source_pixels > 0.0 {
+        display_dpi * content_size.width / source_pixels
+    } else {
+        display_dpi
+    }
+}
+
+/// A plane of a video frame's pixel data, mapped into system memory - see `VideoFrame::map_planes()`
+pub struct PlaneData<'data> {
+    /// The bytes of the plane, `bytes_per_row * height` long
+    pub data: &'data [u8],
+    pub width: usize,
+    pub height: usize,
+    /// The stride between the starts of rows, which may be more than the bytes of pixels in a row
+    pub bytes_per_row: usize,
+    /// The pixel format of the frame - see `CapturePixelFormat` for the layout of each plane
+    pub format: CapturePixelFormat,
+}
+
+impl<'data> PlaneData<'data> {
+    /// Get the bytes of row `y` of the plane, including any padding at the end of the row
+    pub fn row(&self, y: usize) -> &'data [u8] {
+        &self.data[(self.bytes_per_row * y)..(self.bytes_per_row * (y + 1))]
+    }
+}
+
+/// Represents an error mapping a video frame's pixel data into system memory
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum VideoFrameMapError {
+    Other(String),
+    /// The frame's pixel data is in a format the crate doesn't know the layout of
+    UnsupportedPixelFormat,
+}
+
+unsafe impl Send for VideoFrameMapError {}
+unsafe impl Sync for VideoFrameMapError {}
+
+impl VideoFrameMapError {
+    /// Get the stable kind of this error - see `ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Other(_) => ErrorKind::VideoFrameMapOther,
+            Self::UnsupportedPixelFormat => ErrorKind::VideoFrameMapUnsupportedPixelFormat,
+        }
+    }
+
+    /// Get the stable numeric code of this error - see `ErrorKind`
+    pub fn code(&self) -> u32 {
+        self.kind().code()
+    }
+}
+
+impl Display for VideoFrameMapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Other(error) => f.write_fmt(format_args!("VideoFrameMapError::Other(\"{}\")", error)),
+            Self::UnsupportedPixelFormat => f.write_str("VideoFrameMapError::UnsupportedPixelFormat"),
+        }
+    }
+}
+
+impl Error for VideoFrameMapError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+
+    fn description(&self) -> &str {
+        "description() is deprecated; use Display"
+    }
+
+    fn cause(&self) -> Option<&dyn Error> {
+        self.source()
+    }
 }
 
 /// A frame of captured video
 pub struct VideoFrame {
     pub(crate) impl_video_frame: ImplVideoFrame,
+    // The origin of the stream's shared clock, if it has one - see `CaptureClock`
+    pub(crate) clock_origin: Option<Instant>,
+    #[cfg(feature = "bitmap")]
+    pub(crate) change_metric: bool,
 }
 
 unsafe impl Send for VideoFrame {}
@@ -145,13 +351,27 @@ impl VideoFrame {
     }
 
     /// Get the time since the start of the stream that this frame was generated
+    /// 
+    /// For streams using a shared clock, this is instead the time since the clock's origin that the frame was delivered - see `CaptureClock`
     pub fn origin_time(&self) -> Duration {
-        self.impl_video_frame.origin_time()
+        match self.clock_origin {
+            Some(clock_origin) => self.capture_time().saturating_duration_since(clock_origin),
+            None => self.impl_video_frame.origin_time(),
+        }
+    }
+
+    /// Get the time between the previous frame delivered by the stream and this one, or zero for the first frame
+    /// 
+    /// Frames are only delivered when their contents change, so this may be much longer than the stream's frame interval
+    pub fn duration(&self) -> Duration {
+        self.impl_video_frame.duration()
     }
 
     /// Get the raw size of the frame
     /// 
-    /// For planar image formats, this is the size of the largest plane
+    /// For planar image formats, this is the size of the largest plane. This is the size of the bitmaps copied from the frame -
+    /// on MacOS, content placed into the output (a `ScalingQuality` other than `Smooth`) is delivered at its native size,
+    /// and this is the whole pixel output size it's placed into, with `content_rect()` in the output's pixels.
     pub fn size(&self) -> Size {
         self.impl_video_frame.size()
     }
@@ -165,6 +385,72 @@ impl VideoFrame {
     pub fn content_rect(&self) -> Rect {
         self.impl_video_frame.content_rect()
     }
+
+    /// Get the size of the captured content before it was scaled into the frame, E.G. by `CaptureConfig::with_max_dimensions`
+    /// 
+    /// Compare with `size()` to find out whether scaling occurred
+    pub fn native_content_size(&self) -> Size {
+        self.impl_video_frame.native_content_size()
+    }
+
+    /// Get the position of the cursor when this frame was captured, in the same coordinate space as `content_rect()`
+    /// 
+    /// Returns `None` if the cursor was outside the captured content, or its position couldn't be determined.
+    /// The cursor is sampled as the frame arrives, so it may lead the frame's contents slightly.
+    /// On Windows, positions are only exact for per-monitor DPI aware processes.
+    pub fn cursor_position(&self) -> Option<Point> {
+        self.impl_video_frame.cursor_position()
+    }
+
+    /// Get whether the cursor was shown when this frame was captured - it may still be outside the captured content
+    /// 
+    /// This is independent of `CaptureConfig::with_show_cursor` - use it to decide whether to draw your own cursor at `cursor_position()`
+    pub fn cursor_visible(&self) -> bool {
+        self.impl_video_frame.cursor_visible()
+    }
+
+    /// Get the cursor when this frame was captured, with its image, for drawing it separately from the frame
+    /// 
+    /// Returns `None` if the cursor was outside the captured content, or its position couldn't be determined.
+    /// The position is in the same coordinate space as `content_rect()`, as for `cursor_position()`. Capture with
+    /// `CaptureConfig::with_show_cursor(false)` to keep the cursor out of the frame itself.
+    /// On Windows, the image is read as the frame arrives, only when the cursor's shape changes. On MacOS, the system
+    /// cursor is sampled as the frame arrives, and its image is converted each time this is called.
+    pub fn cursor(&self) -> Option<CursorInfo> {
+        Some(CursorInfo {
+            position: self.cursor_position()?,
+            visible: self.cursor_visible(),
+            image: self.impl_video_frame.cursor_image(),
+        })
+    }
+
+    /// Get the regions of the frame that changed since the previous frame delivered by the stream, in the same coordinate space as `content_rect()`
+    /// 
+    /// Returns `None` if the changed regions aren't known, in which case the whole frame should be treated as changed -
+    /// this is always the case on Windows, and for screenshots. Regions that changed in frames dropped by frame rate limiting
+    /// are included in the next delivered frame, and drawing by a `FrameCompositor` isn't included.
+    pub fn dirty_rects(&self) -> Option<Vec<Rect>> {
+        self.impl_video_frame.dirty_rects()
+    }
+
+    /// Get the color space of the frame's pixels, for tone mapping HDR content
+    /// 
+    /// On MacOS this is read from the frame's color attachments. On Windows, 8 bit frames are always converted to sRGB,
+    /// and other formats take the color space of the display showing the content. `ColorSpace::Srgb` is returned
+    /// when the color space isn't known.
+    pub fn color_space(&self) -> ColorSpace {
+        self.impl_video_frame.color_space()
+    }
+
+    /// Map the frame's pixel data into system memory and pass its planes to `map`, without copying them into a bitmap
+    /// 
+    /// The planes are only mapped for the duration of the call - copy out whatever is needed before returning.
+    /// On MacOS this locks the frame's IOSurface, and on Windows the frame is copied to a staging texture which is mapped,
+    /// as the `bitmap` feature does. Planes hold the frame as the OS delivered it - on MacOS, streams with a `ScalingQuality`
+    /// other than `Smooth` hold their content unscaled in the top-left of the planes (see `native_content_size()`).
+    pub fn map_planes<T>(&self, map: impl FnOnce(&[PlaneData<'_>]) -> T) -> Result<T, VideoFrameMapError> {
+        self.impl_video_frame.map_planes(map)
+    }
 }
 
 impl Debug for VideoFrame {
@@ -172,3 +458,41 @@ impl Debug for VideoFrame {
         f.debug_struct("VideoFrame").finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::util::{Rect, Size};
+
+    use super::{display_stream_content_rect, display_stream_dpi};
+
+    fn parts(rect: Rect) -> (f64, f64, f64, f64) {
+        (rect.origin.x, rect.origin.y, rect.size.width, rect.size.height)
+    }
+
+    #[test]
+    fn display_stream_content_fills_frames_of_its_aspect_ratio() {
+        // A 1440x900 point display captured at its 2x backing resolution
+        let content_rect = display_stream_content_rect(Size { width: 1440.0, height: 900.0 }, Size { width: 2880.0, height: 1800.0 });
+        assert_eq!(parts(content_rect), (0.0, 0.0, 2880.0, 1800.0));
+        assert_eq!(display_stream_dpi(220.0, 2.0, Size { width: 1440.0, height: 900.0 }, content_rect.size), 220.0);
+    }
+
+    #[test]
+    fn display_stream_content_is_letterboxed_into_other_aspect_ratios() {
+        let content_rect = display_stream_content_rect(Size { width: 1600.0, height: 900.0 }, Size { width: 1600.0, height: 1200.0 });
+        assert_eq!(parts(content_rect), (0.0, 150.0, 1600.0, 900.0));
+        let content_rect = display_stream_content_rect(Size { width: 400.0, height: 400.0 }, Size { width: 1000.0, height: 500.0 });
+        assert_eq!(parts(content_rect), (250.0, 0.0, 500.0, 500.0));
+    }
+
+    #[test]
+    fn display_stream_dpi_follows_the_scale_of_the_content() {
+        // A crop of a 2x display, captured at half the display's pixel density
+        let source_size = Size { width: 800.0, height: 600.0 };
+        assert_eq!(display_stream_dpi(220.0, 2.0, source_size, Size { width: 800.0, height: 600.0 }), 110.0);
+        // A 1x display captured at twice its size
+        assert_eq!(display_stream_dpi(110.0, 1.0, source_size, Size { width: 1600.0, height: 1200.0 }), 220.0);
+        // An empty source rect keeps the display's dpi
+        assert_eq!(display_stream_dpi(110.0, 1.0, Size { width: 0.0, height: 0.0 }, Size { width: 1.0, height: 1.0 }), 110.0);
+    }
+}
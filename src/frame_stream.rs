@@ -0,0 +1,232 @@
+use std::{borrow::Borrow, collections::VecDeque, pin::Pin, sync::Arc, task::{Context, Poll, Waker}};
+
+use futures::Stream;
+use parking_lot::Mutex;
+
+use crate::capture_stream::{CaptureAccessToken, CaptureConfig, CaptureStream, StreamCreateError, StreamError, StreamEvent};
+
+// The events buffered between the stream callback and the consumer of a frame stream
+struct FrameStreamState {
+    events: VecDeque<Result<StreamEvent, StreamError>>,
+    depth: usize,
+    dropped_count: u64,
+    // Set once the stream has delivered `StreamEvent::End`, so later events are ignored
+    ended: bool,
+    // Set when the frame stream is dropped, so the callback stops buffering events
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+fn is_frame_event(event: &Result<StreamEvent, StreamError>) -> bool {
+    matches!(event, Ok(StreamEvent::Video(_) | StreamEvent::Audio(_) | StreamEvent::Idle))
+}
+
+impl FrameStreamState {
+    // Buffer an event, returning the waker of the consumer waiting for it, if any
+    fn push(&mut self, event: Result<StreamEvent, StreamError>) -> Option<Waker> {
+        if self.ended || self.closed {
+            return None;
+        }
+        self.ended = matches!(event, Ok(StreamEvent::End { .. }));
+        if self.events.len() >= self.depth {
+            // Only frames are dropped, so that errors and the end of the stream are always seen
+            match self.events.iter().position(is_frame_event) {
+                Some(oldest_frame) => {
+                    self.events.remove(oldest_frame);
+                    self.dropped_count += 1;
+                },
+                // With only errors buffered, a new frame is dropped instead, while errors and the end of the stream go in past the depth
+                None if is_frame_event(&event) => {
+                    self.dropped_count += 1;
+                    return None;
+                },
+                None => {},
+            }
+        }
+        self.events.push_back(event);
+        self.waker.take()
+    }
+}
+
+/// The events of a capture stream, as a `futures::Stream` - see `CaptureStream::new_async`
+/// 
+/// Events are yielded in the order the stream delivered them, and buffered up to the stream's depth. When the buffer is full,
+/// the oldest buffered frame (video, audio or idle) is dropped to make room, so a slow consumer never blocks the OS capture thread
+/// and memory use stays bounded - see `dropped_count()`. Errors and `StreamEvent::End` are never dropped, even if they take the buffer past its depth.
+/// The stream ends after yielding `StreamEvent::End`.
+pub struct FrameStream {
+    state: Arc<Mutex<FrameStreamState>>,
+}
+
+impl FrameStream {
+    /// Get the number of frames dropped so far because the buffer was full
+    pub fn dropped_count(&self) -> u64 {
+        self.state.lock().dropped_count
+    }
+}
+
+impl Stream for FrameStream {
+    type Item = Result<StreamEvent, StreamError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut state = self.state.lock();
+        match state.events.pop_front() {
+            Some(event) => Poll::Ready(Some(event)),
+            None if state.ended => Poll::Ready(None),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for FrameStream {
+    fn drop(&mut self) {
+        let mut state = self.state.lock();
+        state.closed = true;
+        state.events.clear();
+    }
+}
+
+impl CaptureStream {
+    /// Start a new capture stream, delivering its events through a `FrameStream` rather than a callback
+    /// 
+    /// Up to `buffer_depth` events (at least one) are buffered while they wait to be consumed, after which the oldest frames are dropped.
+    /// Buffered frames hold onto their capture buffers, so keep the depth below the config's buffer count (see `CaptureConfig::with_buffer_count`)
+    /// to leave the OS free buffers to capture into.
+    pub fn new_async(token: CaptureAccessToken, config: impl Borrow<CaptureConfig>, buffer_depth: usize) -> Result<(CaptureStream, FrameStream), StreamCreateError> {
+        let state = Arc::new(Mutex::new(FrameStreamState {
+            events: VecDeque::new(),
+            depth: buffer_depth.max(1),
+            dropped_count: 0,
+            ended: false,
+            closed: false,
+            waker: None,
+        }));
+        let callback_state = state.clone();
+        let stream = CaptureStream::new(token, config, move |event| {
+            let waker = callback_state.lock().push(event);
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        })?;
+        Ok((stream, FrameStream { state }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{self, AtomicUsize};
+    use std::task::Wake;
+
+    use futures::StreamExt;
+
+    use crate::capture_stream::StreamEndReason;
+
+    use super::*;
+
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, atomic::Ordering::SeqCst);
+        }
+    }
+
+    fn frame_stream(depth: usize) -> FrameStream {
+        FrameStream {
+            state: Arc::new(Mutex::new(FrameStreamState {
+                events: VecDeque::new(),
+                depth,
+                dropped_count: 0,
+                ended: false,
+                closed: false,
+                waker: None,
+            })),
+        }
+    }
+
+    fn push(stream: &FrameStream, event: Result<StreamEvent, StreamError>) {
+        if let Some(waker) = stream.state.lock().push(event) {
+            waker.wake();
+        }
+    }
+
+    fn poll(stream: &mut FrameStream, waker: &Waker) -> Poll<Option<Result<StreamEvent, StreamError>>> {
+        stream.poll_next_unpin(&mut Context::from_waker(waker))
+    }
+
+    fn error_message(event: Poll<Option<Result<StreamEvent, StreamError>>>) -> String {
+        match event {
+            Poll::Ready(Some(Err(StreamError::Other(message)))) => message,
+            _ => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn the_oldest_frame_is_dropped_when_the_buffer_is_full() {
+        let mut stream = frame_stream(2);
+        let waker = Waker::noop().clone();
+        push(&stream, Ok(StreamEvent::Idle));
+        push(&stream, Err(StreamError::Other("first".into())));
+        push(&stream, Err(StreamError::Other("second".into())));
+        assert_eq!(stream.dropped_count(), 1);
+        assert_eq!(error_message(poll(&mut stream, &waker)), "first");
+        assert_eq!(error_message(poll(&mut stream, &waker)), "second");
+        assert!(poll(&mut stream, &waker).is_pending());
+    }
+
+    #[test]
+    fn errors_and_the_end_are_kept_past_the_depth() {
+        let mut stream = frame_stream(1);
+        let waker = Waker::noop().clone();
+        push(&stream, Err(StreamError::Other("first".into())));
+        // A new frame is dropped rather than an error
+        push(&stream, Ok(StreamEvent::Idle));
+        push(&stream, Err(StreamError::Other("second".into())));
+        push(&stream, Ok(StreamEvent::End { reason: StreamEndReason::Stopped }));
+        assert_eq!(stream.dropped_count(), 1);
+        assert_eq!(error_message(poll(&mut stream, &waker)), "first");
+        assert_eq!(error_message(poll(&mut stream, &waker)), "second");
+        assert!(matches!(poll(&mut stream, &waker), Poll::Ready(Some(Ok(StreamEvent::End { reason: StreamEndReason::Stopped })))));
+    }
+
+    #[test]
+    fn the_stream_ends_after_the_end_event() {
+        let mut stream = frame_stream(4);
+        let waker = Waker::noop().clone();
+        push(&stream, Ok(StreamEvent::End { reason: StreamEndReason::TargetGone }));
+        // Events after the end are ignored
+        push(&stream, Ok(StreamEvent::Idle));
+        assert!(matches!(poll(&mut stream, &waker), Poll::Ready(Some(Ok(StreamEvent::End { .. })))));
+        assert!(matches!(poll(&mut stream, &waker), Poll::Ready(None)));
+        assert!(matches!(poll(&mut stream, &waker), Poll::Ready(None)));
+    }
+
+    #[test]
+    fn a_pending_consumer_is_woken_by_the_next_event() {
+        let mut stream = frame_stream(4);
+        let counter = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(counter.clone());
+        assert!(poll(&mut stream, &waker).is_pending());
+        push(&stream, Ok(StreamEvent::Idle));
+        assert_eq!(counter.0.load(atomic::Ordering::SeqCst), 1);
+        // The waker is only taken once per pending poll
+        push(&stream, Ok(StreamEvent::Idle));
+        assert_eq!(counter.0.load(atomic::Ordering::SeqCst), 1);
+        assert!(matches!(poll(&mut stream, &waker), Poll::Ready(Some(Ok(StreamEvent::Idle)))));
+    }
+
+    #[test]
+    fn dropping_the_frame_stream_stops_buffering() {
+        let stream = frame_stream(4);
+        let state = stream.state.clone();
+        state.lock().push(Ok(StreamEvent::Idle));
+        drop(stream);
+        assert!(state.lock().events.is_empty());
+        assert!(state.lock().push(Ok(StreamEvent::Idle)).is_none());
+        assert!(state.lock().events.is_empty());
+        assert_eq!(state.lock().dropped_count, 0);
+    }
+}
@@ -1,9 +1,9 @@
 //! A cross-platform screen/window/audio capture library
 //! 
-//! ## MacOS Docs
+//! ## Platform Docs
 //! 
-//! Since we depend on the metal crate, our docs won't build for macos under docs.rs's linux containers. As a workaround, you can see our build of the docs for MacOS here:
-//! [MacOS Documentation](https://augmendtech.github.io/CrabGrab/macos_docs/crabgrab/index.html)
+//! These docs are built for both Windows and MacOS - use the platform menu on docs.rs to switch between them.
+//! Platform-specific and feature-gated items are labelled with their requirements.
 //! 
 //! ## Feature flags
 //! 
@@ -18,11 +18,25 @@
 //! ### Bitmap output
 //! 
 //! - **`bitmap`** - enables creating raw bitmap copies of frames in system memory
+//! - **`png`** - enables saving frames as PNG files, without any image library dependency
 //! 
 //! ### Screenshots
 //! 
 //! - **`screenshot`** - provides an easy-to-use function wrapping `CaptureStream` for single-frame capture
 //! 
+//! ### Frame sinks
+//! 
+//! - **`sink`** - enables passing a stream's video frames to a `VideoFrameSink`, such as an encoder, with a PNG sequence sink when `png` is also enabled
+//! 
+//! ### Other
+//! 
+//! - **`content_picker`** - enables picking a window or display to capture with the system content picker
+//! - **`serde`** - derives `serde::Serialize` for `EnvironmentReport`
+//! 
+//! ### Unsupported targets
+//! 
+//! - **`stub-unsupported`** - on targets other than MacOS and Windows (such as wasm), compiles against a stub backend where enumeration and stream creation return `UnsupportedPlatform` errors, instead of failing to compile
+//! 
 //! ## Example
 //! 
 //! ```
@@ -50,8 +64,9 @@
 //!     match window {
 //!         Some(window) => {
 //!             println!("capturing window: {}", window.title()); 
-//!             // create a captuere config using the first supported pixel format
-//!             let config = CaptureConfig::with_window(window, CaptureStream::supported_pixel_formats()[0]).unwrap();
+//!             // create a capture config using the supported pixel format best suited to reading pixels on the CPU
+//!             let pixel_format = CaptureStream::preferred_pixel_format(PixelFormatPurpose::CpuBitmap).unwrap();
+//!             let config = CaptureConfig::with_window(window, pixel_format).unwrap();
 //!             // create a capture stream with an event handler callback
 //!             let mut stream = CaptureStream::new(token, config, |stream_event| {
 //!                 match stream_event {
@@ -68,6 +83,8 @@
 //!                     }
 //!                 }
 //!             }).unwrap();
+//!             // check that frames are actually flowing
+//!             stream.await_first_frame(Duration::from_secs(5)).await.expect("Expected a frame");
 //!             // wait for a while to capture some frames
 //!             tokio::task::block_in_place(|| std::thread::sleep(Duration::from_millis(4000)));
 //!             stream.stop().unwrap();
@@ -82,6 +99,9 @@
 //! ````
 //! 
 
+#![cfg_attr(not(any(target_os = "macos", target_os = "windows")), allow(dead_code))]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
 /// Platform-specific extensions
 pub mod platform;
 /// Extension features
@@ -95,6 +115,18 @@ pub mod frame;
 pub mod capture_stream;
 /// Enumeration of capturable items
 pub mod capturable_content;
+/// Stable error codes
+pub mod error;
+/// Environment reports for bug reports
+pub mod environment;
+/// Compositing overlays into frames before delivery
+pub mod compositor;
+/// Consuming capture streams asynchronously
+pub mod frame_stream;
+/// Capturing several targets together on a shared clock
+pub mod capture_stream_group;
+
+pub use environment::{environment_report, EnvironmentReport};
 
 /// Everything
 pub mod prelude;
\ No newline at end of file
@@ -0,0 +1,124 @@
+// Signature-only copies of the public Mac OS extensions, so documentation built on other hosts (docs.rs's windows target,
+// or `cargo doc` on linux) still renders them. Keep these in step with src/platform/macos - `with_metal_device` is left
+// out, since the `metal` crate is only available on Mac OS
+
+use crate::capturable_content::{CapturableApplication, CapturableContentError, CapturableWindow};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// The "resolution type" of the capture
+pub enum MacosCaptureResolutionType {
+    /// Automatically select the resolution type
+    Automatic,
+    /// Select the highest available capture resolution (usually the physical resolution being rendered)
+    Best,
+    /// One linear screen unit per pixel, IE the "virtual resolution"
+    Nominal,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// The OS API used to capture displays
+pub enum MacosDisplayCaptureBackend {
+    /// Use ScreenCaptureKit where available, falling back to CGDisplayStream
+    Automatic,
+    /// Use ScreenCaptureKit, as window capture does. Supports the same frame metadata, but requires MacOS 12.3
+    SCStream,
+    /// Use the deprecated CGDisplayStream API
+    CGDisplayStream,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// The matrix used to convert captured RGB content to YCbCr, for the `V420` and `F420` pixel formats
+pub enum MacosColorMatrix {
+    /// ITU-R BT.709-2, for HD video - the default
+    ItuR709_2,
+    /// ITU-R BT.601-4, for SD video
+    ItuR601_4,
+    /// SMPTE 240M-1995
+    Smpte240M1995,
+}
+
+/// Mac OS specific extensions for capture configs
+pub trait MacosCaptureConfigExt {
+    /// Set whether or not to scale content to the output size
+    fn with_scale_to_fit(self, scale_to_fit: bool) -> Self;
+    /// Set the maximum capture frame-rate - forwards to `CaptureConfig::with_maximum_fps`
+    #[deprecated(note = "use CaptureConfig::with_maximum_fps")]
+    fn with_maximum_fps(self, maximum_fps: Option<f32>) -> Self;
+    /// Set the resolution type of the capture. Does nothing on macos before OS 14.0
+    fn with_resolution_type(self, resolution_type: MacosCaptureResolutionType) -> Self;
+    /// Set the OS API used for display capture. Defaults to `MacosDisplayCaptureBackend::Automatic`
+    fn with_display_capture_backend(self, display_capture_backend: MacosDisplayCaptureBackend) -> Self;
+    /// Set the matrix used to convert content to YCbCr for `V420` and `F420` frames. Defaults to `MacosColorMatrix::ItuR709_2`
+    /// 
+    /// YCbCr bitmaps (`bitmap` feature) record the matrix as their `color_matrix`, and convert back to RGB with it
+    fn with_color_matrix(self, color_matrix: MacosColorMatrix) -> Self;
+    /// Set the windows left out of a display capture, E.G. the recording application's own windows
+    /// 
+    /// Only applies to display capture with ScreenCaptureKit - stream creation fails when capturing with CGDisplayStream,
+    /// or when both windows and applications are excluded. There's no equivalent on Windows, where a window can hide
+    /// itself from all captures with `SetWindowDisplayAffinity(WDA_EXCLUDEFROMCAPTURE)`
+    fn with_excluded_windows(self, excluded_windows: Vec<CapturableWindow>) -> Self;
+    /// Set the applications whose windows are left out of a display capture, including windows they open later
+    /// 
+    /// Only applies to display capture with ScreenCaptureKit - see `with_excluded_windows`
+    fn with_excluded_apps(self, excluded_applications: Vec<CapturableApplication>) -> Self;
+}
+
+/// Mac OS specific extensions for audio capture configs
+pub trait MacosAudioCaptureConfigExt {
+    fn set_exclude_current_process_audio(self, exclude_current_process_audio: bool) -> Self;
+    /// Only capture the audio played by the application that owns the captured window, rather than all system audio
+    /// 
+    /// Only valid for window capture - stream creation fails for display capture with this set
+    fn set_application_audio_only(self, application_audio_only: bool) -> Self;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// Represents the "window level" of a native Mac OS window. Windows within the same level are ordered above or below levels that are above below or above this level respectively.
+pub enum MacosWindowLevel {
+    BelowDesktop      =  0,
+    Desktop           =  1,
+    DesktopIcon       =  2,
+    Backstop          =  3,
+    Normal            =  4,
+    Floating          =  5,
+    TornOffMenu       =  6,
+    Dock              =  7,
+    MainMenu          =  8,
+    Status            =  9,
+    ModalPanel        = 10,
+    PopupMenu         = 11,
+    Dragging          = 12,
+    ScreenSaver       = 13,
+    Overlay           = 14,
+    Help              = 15,
+    Utility           = 16,
+    Cursor            = 17,
+    AssistiveTechHigh = 18,
+}
+
+/// A capturable window with mac-os specific features
+pub trait MacosCapturableWindowExt {
+    /// Get the window layer of this window
+    fn get_window_layer(&self) -> Result<i32, CapturableContentError>;
+
+    /// Get the window level of this window
+    fn get_window_level(&self) -> Result<MacosWindowLevel, CapturableContentError>;
+
+    /// Get the native window id for this capturable window.
+    /// This is the `CGWindowID` for this window.
+    fn get_window_id(&self) -> u32;
+
+    /// Try and convert the given CGWindowID to a capturable window.
+    fn from_window_id(window_id: u32) -> impl std::future::Future<Output = Result<CapturableWindow, CapturableContentError>>;
+}
+
+/// A capturable content filter with Mac OS specific options
+pub trait MacosCapturableContentFilterExt: Sized {
+    /// Set the range of "window levels" to filter to (inclusive)
+    fn with_window_level_range(self, min: Option<MacosWindowLevel>, max: Option<MacosWindowLevel>) -> Result<Self, CapturableContentError>;
+    /// Exclude windows who's applications have the provided bundle ids
+    fn with_exclude_bundle_ids(self, bundle_id: &[&str]) -> Self;
+    /// Exclude windows with the given CGWindowIDs
+    fn with_exclude_window_ids(self, window_ids: &[u32]) -> Self;
+}
@@ -0,0 +1,46 @@
+// Signature-only copies of the public Windows extensions, so documentation built on other hosts (docs.rs's Mac OS target,
+// or `cargo doc` on linux) still renders them. Keep these in step with src/platform/windows - the `windows` crate is only
+// available on Windows, so its types are stood in for by the placeholders in `win32`
+
+use std::time::Duration;
+
+use crate::capturable_content::{CapturableContentError, CapturableWindow};
+
+#[doc(hidden)]
+pub mod win32 {
+    pub struct IDXGIAdapter(());
+    pub struct ID3D11Device(());
+}
+
+use win32::{IDXGIAdapter, ID3D11Device};
+
+/// Re-exported from the `windows` crate
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct HWND(pub isize);
+
+pub trait WindowsCaptureConfigExt {
+    fn with_dxgi_adapter(self, dxgi_adapter: IDXGIAdapter) -> Self;
+    fn with_d3d11_device(self, d3d11_device: ID3D11Device) -> Self;
+    fn with_borderless(self, borderless: bool) -> Self;
+    /// Set the maximum capture frame-rate - forwards to `CaptureConfig::with_maximum_fps`
+    #[deprecated(note = "use CaptureConfig::with_maximum_fps")]
+    fn with_maximum_fps(self, maximum_fps: Option<f32>) -> Self;
+    /// Set how long the stream goes without a new frame before delivering `StreamEvent::Idle`, or `None` to never deliver it - by default, one second
+    /// 
+    /// Windows.Graphics.Capture only produces frames when the content changes, so static content goes idle, and `StreamEvent::Idle`
+    /// is delivered once each time it does. Audio-only streams never deliver it.
+    fn with_idle_timeout(self, idle_timeout: Option<Duration>) -> Self;
+}
+
+/// Windows-specific extensions for capturable windows
+pub trait WindowsCapturableWindowExt {
+    /// Get the HWND for this capturable window.
+    fn get_window_handle(&self) -> HWND;
+    /// Get a capturable window from an HWND
+    fn from_window_handle(window_handle: HWND) -> Result<CapturableWindow, CapturableContentError>;
+}
+
+/// Windows-specific extensions to capturable content filters
+pub trait WindowsCapturableContentFilterExt: Sized {
+    fn with_exclude_window_handles(self, window_handles: &[HWND]) -> Self;
+}
@@ -1,23 +1,34 @@
-use std::{cell::Cell, fmt::Debug, hash::Hash, sync::Arc};
+use std::{cell::Cell, collections::HashMap, fmt::Debug, hash::Hash, sync::Arc, time::Duration};
 
 use futures::channel::oneshot;
 use libc::getpid;
 use parking_lot::Mutex;
 
-use crate::{capturable_content::{CapturableContentError, CapturableContentFilter}, prelude::{CapturableContent, CapturableWindow}, util::{Point, Rect, Size}};
+use crate::{capturable_content::{CapturableContentError, CapturableContentFilter, WindowLayer}, error::PlatformErrorCode, prelude::{CapturableContent, CapturableWindow}, util::{Point, Rect, Size}};
 
-use super::objc_wrap::{get_window_description, get_window_levels, CGMainDisplayID, CGWindowID, SCDisplay, SCRunningApplication, SCShareableContent, SCWindow};
+use super::objc_wrap::{get_on_screen_window_ids, get_window_description, get_window_levels, CGDisplayIsInMirrorSet, CGDisplayMode, CGDisplayPrimaryDisplay, CGDisplayReconfigurationObserver, CGMainDisplayID, CGWindowID, NSScreen, NSWorkspaceActiveSpaceDidChangeNotification, NSWorkspaceDidHideApplicationNotification, NSWorkspaceDidLaunchApplicationNotification, NSWorkspaceDidTerminateApplicationNotification, NSWorkspaceDidUnhideApplicationNotification, NSWorkspaceNotificationObserver, SCDisplay, SCRunningApplication, SCShareableContent, SCWindow};
 
 pub struct MacosCapturableContent {
     pub windows: Vec<SCWindow>,
     pub displays: Vec<SCDisplay>,
+    pub applications: Vec<SCRunningApplication>,
 }
 
 impl MacosCapturableContent {
+    // Get the z-order of every window, from a single enumeration - see `MacosCapturableWindow::z_order()`
+    pub fn window_z_orders(&self) -> Vec<Option<usize>> {
+        let z_orders: HashMap<u32, usize> = get_on_screen_window_ids().unwrap_or_default()
+            .into_iter()
+            .enumerate()
+            .map(|(z_order, window_id)| (window_id.0, z_order))
+            .collect();
+        self.windows.iter().map(|window| z_orders.get(&window.id().0).copied()).collect()
+    }
+
     pub async fn new(filter: CapturableContentFilter) -> Result<Self, CapturableContentError> {
         // Force core graphics initialization
         unsafe { CGMainDisplayID() };
-        let (exclude_desktop, onscreen_only) = filter.windows.map_or((false, true), |filter| (!filter.desktop_windows, filter.onscreen_only));
+        let (exclude_desktop, onscreen_only) = filter.windows.as_ref().map_or((false, true), |filter| (!filter.desktop_windows, filter.onscreen_only));
         let (tx, rx) = oneshot::channel();
         let mut tx = Mutex::new(Some(tx));
         SCShareableContent::get_shareable_content_with_completion_handler(exclude_desktop, onscreen_only, move |result| {
@@ -31,24 +42,59 @@ impl MacosCapturableContent {
                 let windows = content.windows()
                     .into_iter()
                     .filter(|window| filter.impl_capturable_content_filter.filter_scwindow(window))
+                    .filter(|window| filter.filter_window_rect(&MacosCapturableWindow::from_impl(window.clone()).rect()))
                     .collect();
                 let displays = content.displays()
                     .into_iter()
                     .filter(|display| filter.impl_capturable_content_filter.filter_scdisplay(display))
                     .collect();
+                let applications = content.applications();
                 Ok(Self {
                     windows,
                     displays,
+                    applications,
                 })
             },
             Ok(Err(error)) => {
-                Err(CapturableContentError::Other(format!("SCShareableContent returned error code: {}", error.code())))
+                Err(CapturableContentError::Platform(format!("SCShareableContent returned error: {}", error.description()), PlatformErrorCode::NSError { domain: error.domain(), code: error.code() }))
             }
             Err(error) => Err(CapturableContentError::Other(format!("Failed to receive SCSharableContent result from completion handler future: {}", error.to_string()))),
         }
     }
 }
 
+/// Wakes a callback when displays are reconfigured or applications launch, quit, hide or unhide - until dropped
+/// 
+/// There are no notifications for windows opening, closing or moving, so content changes are also polled for.
+/// Note that these notifications are only delivered while the main run loop is running.
+pub(crate) struct MacosContentChangeNotifier {
+    _display_reconfiguration_observer: CGDisplayReconfigurationObserver,
+    _workspace_notification_observer: NSWorkspaceNotificationObserver,
+}
+
+impl MacosContentChangeNotifier {
+    pub(crate) const POLL_INTERVAL: Option<Duration> = Some(Duration::from_secs(1));
+
+    // Window geometry changes are only picked up by polling, so `window_geometry` changes nothing
+    pub(crate) fn new(wake: impl Fn() + Send + Sync + 'static, _window_geometry: bool) -> Result<Self, CapturableContentError> {
+        let wake = Arc::new(wake);
+        let display_wake = wake.clone();
+        let display_reconfiguration_observer = CGDisplayReconfigurationObserver::new(move |_display_id, _flags| (display_wake)());
+        let notification_names = unsafe {[
+            NSWorkspaceDidLaunchApplicationNotification,
+            NSWorkspaceDidTerminateApplicationNotification,
+            NSWorkspaceDidHideApplicationNotification,
+            NSWorkspaceDidUnhideApplicationNotification,
+            NSWorkspaceActiveSpaceDidChangeNotification,
+        ]};
+        let workspace_notification_observer = NSWorkspaceNotificationObserver::new(&notification_names, move || (wake)());
+        Ok(Self {
+            _display_reconfiguration_observer: display_reconfiguration_observer,
+            _workspace_notification_observer: workspace_notification_observer,
+        })
+    }
+}
+
 #[derive(Clone)]
 pub struct MacosCapturableWindow {
     pub(crate) window: SCWindow
@@ -61,6 +107,10 @@ impl MacosCapturableWindow {
         }
     }
 
+    pub fn id(&self) -> u64 {
+        self.window.id().0 as u64
+    }
+
     pub fn title(&self) -> String {
         self.window.title()
     }
@@ -88,6 +138,37 @@ impl MacosCapturableWindow {
     pub fn is_visible(&self) -> bool {
         self.window.on_screen()
     }
+
+    pub fn is_on_screen(&self) -> bool {
+        get_window_description(self.window.id())
+            .map(|description| description.on_screen)
+            .unwrap_or(false)
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        // The window server doesn't report minimization, so off-screen windows at the normal window layer are taken to be minimized
+        get_window_description(self.window.id())
+            .map(|description| description.window_layer == 0 && !description.on_screen)
+            .unwrap_or(false)
+    }
+
+    pub fn z_order(&self) -> Option<usize> {
+        let id = self.window.id();
+        get_on_screen_window_ids().ok()?
+            .into_iter()
+            .position(|window_id| window_id == id)
+    }
+
+    pub fn z_order_hint(&self) -> WindowLayer {
+        match get_window_level(self.window.id().0) {
+            Ok(MacosWindowLevel::Normal) | Err(_) => WindowLayer::Normal,
+            Ok(MacosWindowLevel::Floating | MacosWindowLevel::TornOffMenu | MacosWindowLevel::ModalPanel | MacosWindowLevel::Utility) => WindowLayer::AboveNormal,
+            Ok(MacosWindowLevel::PopupMenu | MacosWindowLevel::Dragging | MacosWindowLevel::Overlay | MacosWindowLevel::Help) => WindowLayer::Overlay,
+            Ok(MacosWindowLevel::BelowDesktop | MacosWindowLevel::Desktop | MacosWindowLevel::DesktopIcon | MacosWindowLevel::Backstop |
+                MacosWindowLevel::Dock | MacosWindowLevel::MainMenu | MacosWindowLevel::Status | MacosWindowLevel::ScreenSaver |
+                MacosWindowLevel::Cursor | MacosWindowLevel::AssistiveTechHigh) => WindowLayer::System,
+        }
+    }
 }
 
 impl Debug for MacosCapturableWindow {
@@ -135,6 +216,64 @@ impl MacosCapturableDisplay {
             }
         }
     }
+
+    pub fn id(&self) -> u64 {
+        self.display.raw_id() as u64
+    }
+
+    fn screen(&self) -> Option<NSScreen> {
+        let display_id = self.display.raw_id();
+        NSScreen::screens()
+            .into_iter()
+            .find(|screen| screen.display_id() == Some(display_id))
+    }
+
+    pub fn name(&self) -> Option<String> {
+        self.screen()
+            .and_then(|screen| screen.localized_name())
+            .filter(|name| !name.is_empty())
+    }
+
+    pub fn pixel_size(&self) -> Size {
+        match CGDisplayMode::for_display(self.display.raw_id()) {
+            Some(mode) => Size {
+                width: mode.pixel_width() as f64,
+                height: mode.pixel_height() as f64,
+            },
+            None => {
+                let size = self.rect().size;
+                let scale_factor = self.scale_factor();
+                Size {
+                    width: size.width * scale_factor,
+                    height: size.height * scale_factor,
+                }
+            }
+        }
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        match CGDisplayMode::for_display(self.display.raw_id()) {
+            Some(mode) if mode.width() > 0 => mode.pixel_width() as f64 / mode.width() as f64,
+            _ => self.screen().map_or(1.0, |screen| screen.backing_scale_factor()),
+        }
+    }
+
+    pub fn refresh_rate(&self) -> Option<f64> {
+        CGDisplayMode::for_display(self.display.raw_id())
+            .map(|mode| mode.refresh_rate())
+            .filter(|refresh_rate| *refresh_rate > 0.0)
+    }
+
+    pub fn mirror_group(&self) -> Option<u64> {
+        let display_id = self.display.raw_id();
+        unsafe {
+            if CGDisplayIsInMirrorSet(display_id) == 0 {
+                return None;
+            }
+            // All members of a mirror set share the same primary display
+            Some(CGDisplayPrimaryDisplay(display_id) as u64)
+        }
+    }
 }
 
 impl PartialEq for MacosCapturableDisplay {
@@ -157,12 +296,18 @@ impl Debug for MacosCapturableDisplay {
     }
 }
 
-#[derive()]
+#[derive(Clone)]
 pub struct MacosCapturableApplication {
     pub(crate) running_application: SCRunningApplication,
 }
 
 impl MacosCapturableApplication {
+    pub fn from_impl(running_application: SCRunningApplication) -> Self {
+        Self {
+            running_application
+        }
+    }
+
     pub fn identifier(&self) -> String {
         self.running_application.bundle_identifier()
     }
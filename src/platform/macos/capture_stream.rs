@@ -1,11 +1,16 @@
-use std::{borrow::{Borrow, BorrowMut}, cell::{Cell, RefCell}, sync::{atomic::{self, AtomicBool, AtomicU64}, Arc}, time::{Duration, Instant}, fmt::Debug};
+use std::{borrow::{Borrow, BorrowMut}, cell::{Cell, RefCell}, panic::{catch_unwind, AssertUnwindSafe}, sync::{atomic::{self, AtomicBool, AtomicU64}, Arc}, time::{Duration, Instant}, fmt::Debug, future::Future, ops::RangeInclusive};
 
-use futures::executor::block_on;
+use futures::{channel::oneshot, executor::block_on};
 use objc2::runtime::AnyObject;
 use parking_lot::Mutex;
 
-use crate::{capture_stream::{CaptureConfig, StreamCreateError, StreamError, StreamEvent}, platform::platform_impl::{frame::MacosSCStreamVideoFrame, objc_wrap::NSNumber}, prelude::{AudioCaptureConfig, AudioFrame, Capturable, CaptureConfigError, CapturePixelFormat, Point, StreamStopError, VideoFrame}, util::{Rect, Size}};
-use super::{frame::{MacosAudioFrame, MacosCGDisplayStreamVideoFrame, MacosVideoFrame}, objc_wrap::{kCFBooleanFalse, kCFBooleanTrue, kCGDisplayStreamDestinationRect, kCGDisplayStreamMinimumFrameTime, kCGDisplayStreamPreserveAspectRatio, kCGDisplayStreamQueueDepth, kCGDisplayStreamShowCursor, kCGDisplayStreamSourceRect, CFNumber, CGDisplayStream, CGDisplayStreamFrameStatus, CGPoint, CGRect, CGSize, CMSampleBuffer, CMTime, DispatchQueue, IOSurface, NSArray, NSDictionary, NSString, SCCaptureResolutionType, SCContentFilter, SCFrameStatus, SCStream, SCStreamCallbackError, SCStreamColorMatrix, SCStreamConfiguration, SCStreamFrameInfoStatus, SCStreamHandler, SCStreamOutputType, SCStreamPixelFormat, SCStreamSampleRate}};
+use crate::{capture_stream::{CallbackPanic, FrameIntervalLimiter, MACOS_PIXEL_FORMATS, CaptureConfig, EffectiveCaptureConfig, OpenSettingsError, OpenSettingsOutcome, StreamCreateError, StreamEndReason, StreamError, StreamEvent}, error::PlatformErrorCode, platform::platform_impl::{frame::MacosSCStreamVideoFrame, objc_wrap::NSNumber}, prelude::{AudioBufferError, AudioCaptureConfig, AudioFrame, Capturable, CapturableApplication, CapturableWindow, CaptureConfigError, CapturePixelFormat, CaptureTarget, Point, PowerProfile, ScalingQuality, StreamStopError, StreamUpdateError, VideoFrame}, util::{Rect, Size}};
+#[cfg(feature = "wgpu")]
+use crate::feature::wgpu::SharedWgpuDevice;
+#[cfg(feature = "metal")]
+use crate::feature::metal::{default_metal_device, get_metal_texture_with_device, MetalVideoFramePlaneTexture};
+use crate::compositor::{CompositorPlane, CompositorTarget, SharedFrameCompositor};
+use super::{frame::{rect_from_cg_rect, sample_buffer_presentation_time, sc_stream_frame_dirty_rects, MacosAudioFrame, MacosCGDisplayStreamVideoFrame, MacosVideoFrame}, objc_wrap::{cursor_is_visible, cursor_location, NSCursor, kCFBooleanFalse, kCFBooleanTrue, kCGDisplayStreamDestinationRect, kCGDisplayStreamMinimumFrameTime, kCGDisplayStreamPreserveAspectRatio, kCGDisplayStreamQueueDepth, kCGDisplayStreamShowCursor, kCGDisplayStreamSourceRect, kCGDisplayStreamYCbCrMatrix, CFNumber, CGDisplayIsOnline, CGDisplayReconfigurationObserver, CGDisplayStream, CGDisplayStreamFrameStatus, K_CG_DISPLAY_REMOVE_FLAG, CGPoint, CGRect, CGSize, CMSampleBuffer, CMTime, DispatchQoSClass, DispatchQueue, IOSurface, NSDictionary, NSError, NSProcessInfo, NSString, NSURL, NSWorkspace, SCCaptureResolutionType, SCContentFilter, SCDisplay, SCFrameStatus, SCShareableContent, SCStream, SCStreamCallbackError, SCStreamColorMatrix, SCStreamConfiguration, SCStreamFrameInfoStatus, SCStreamHandler, SCStreamOutputType, SCStreamPixelFormat, NSScreen, SCStreamSampleRate, SCWindow}};
 
 pub type MacosPixelFormat = SCStreamPixelFormat;
 
@@ -24,18 +29,115 @@ impl TryFrom<CapturePixelFormat> for SCStreamPixelFormat {
 }
 
 enum MacosCaptureStreamInternal {
-    Window(SCStream),
-    Display(CGDisplayStream),
+    SCStream(SCStream),
+    CGDisplayStream(CGDisplayStream, CGDisplayReconfigurationObserver),
 }
 
+// SCStreamErrorCode values
+const SC_STREAM_ERROR_NO_WINDOW_LIST: isize = -3813;
+const SC_STREAM_ERROR_NO_DISPLAY_LIST: isize = -3814;
+const SC_STREAM_ERROR_NO_CAPTURE_SOURCE: isize = -3815;
+const SC_STREAM_ERROR_USER_STOPPED: isize = -3817;
+
+fn stream_end_reason_from_ns_error(error: &NSError) -> StreamEndReason {
+    stream_end_reason_from_error_code(error.code(), || format!("[description: {}, reason: {}, code: {}, domain: {}]", error.description(), error.reason(), error.code(), error.domain()))
+}
+
+fn stream_end_reason_from_error_code(code: isize, describe_error: impl FnOnce() -> String) -> StreamEndReason {
+    match code {
+        SC_STREAM_ERROR_NO_WINDOW_LIST |
+        SC_STREAM_ERROR_NO_DISPLAY_LIST |
+        SC_STREAM_ERROR_NO_CAPTURE_SOURCE => StreamEndReason::TargetGone,
+        SC_STREAM_ERROR_USER_STOPPED => StreamEndReason::Stopped,
+        _ => StreamEndReason::Other(format!("Stream stopped by the OS: {}", describe_error())),
+    }
+}
+
+// Whether a display reconfiguration removed the captured display
+fn display_was_removed(display_id: u32, changed_display_id: u32, flags: u32) -> bool {
+    changed_display_id == display_id && (flags & K_CG_DISPLAY_REMOVE_FLAG) != 0
+}
+
+pub(crate) const SC_STREAM_BACKEND_NAME: &str = "ScreenCaptureKit (SCStream)";
+pub(crate) const CG_DISPLAY_STREAM_BACKEND_NAME: &str = "CoreGraphics (CGDisplayStream)";
+
 pub(crate) struct MacosCaptureStream {
     stream: MacosCaptureStreamInternal,
+    // Captures application-only audio alongside the video stream
+    audio_stream: Option<SCStream>,
+    // Resolves once a CGDisplayStream reports that it has stopped
+    display_stream_stopped_rx: Option<oneshot::Receiver<()>>,
+    effective_config: EffectiveCaptureConfig,
     stopped_flag: Arc<AtomicBool>,
+    stop_handle: MacosStopHandle,
+    // Set while the content filter is being swapped by `update_target`, to drop frames of the old target
+    updating_target_flag: Arc<AtomicBool>,
+    // Frames dropped by the frame interval limiter, or because the stream was stopped or changing target
+    dropped_frames: Arc<AtomicU64>,
+    // The size of the stream's frames, which `update_config` and `set_source_rect` can change for SCStreams
+    output_size: Size,
+    sc_stream_config: Option<SCStreamUpdatableConfig>,
     shared_callback: Arc<Mutex<Box<dyn FnMut(Result<StreamEvent, StreamError>) + Send + 'static>>>,
     #[cfg(feature = "metal")]
     pub(crate) metal_device: metal::Device,
     #[cfg(feature = "wgpu")]
     pub(crate) wgpu_device: Option<Arc<dyn AsRef<wgpu::Device> + Send + Sync + 'static>>,
+    #[cfg(feature = "wgpu")]
+    pub(crate) frame_wgpu_device: SharedWgpuDevice,
+}
+
+// The OS stream of a `MacosCaptureStream`, as held by its stop handle
+enum MacosStopTarget {
+    SCStream(SCStream),
+    CGDisplayStream(CGDisplayStream),
+}
+
+// Stops a `MacosCaptureStream` from any thread, including from inside its callback - see `StopHandle`
+#[derive(Clone)]
+pub(crate) struct MacosStopHandle(Arc<MacosStopHandleInner>);
+
+struct MacosStopHandleInner {
+    stopped_flag: Arc<AtomicBool>,
+    shared_callback: Arc<Mutex<Box<dyn FnMut(Result<StreamEvent, StreamError>) + Send + 'static>>>,
+    // The video stream, and the application-only audio stream if there is one
+    streams: Mutex<(MacosStopTarget, Option<SCStream>)>,
+}
+
+unsafe impl Send for MacosStopHandleInner {}
+unsafe impl Sync for MacosStopHandleInner {}
+
+impl MacosStopHandle {
+    fn new(stopped_flag: Arc<AtomicBool>, shared_callback: Arc<Mutex<Box<dyn FnMut(Result<StreamEvent, StreamError>) + Send + 'static>>>, stream: MacosStopTarget, audio_stream: Option<SCStream>) -> Self {
+        Self(Arc::new(MacosStopHandleInner {
+            stopped_flag,
+            shared_callback,
+            streams: Mutex::new((stream, audio_stream)),
+        }))
+    }
+
+    // Stop the OS streams - the end of the stream is delivered first, unless the caller is inside the callback and delivers it itself
+    pub(crate) fn stop(&self, deliver_end: bool) -> Result<(), StreamStopError> {
+        if deliver_end {
+            let mut callback = self.0.shared_callback.lock();
+            if self.0.stopped_flag.fetch_or(true, atomic::Ordering::AcqRel) {
+                return Ok(());
+            }
+            (callback)(Ok(StreamEvent::End { reason: StreamEndReason::Stopped }));
+        } else if self.0.stopped_flag.fetch_or(true, atomic::Ordering::AcqRel) {
+            return Ok(());
+        }
+        // The end of the stream has already been delivered, so failures to stop can't be reported through the callback -
+        // use `close()` to wait for the OS streams to stop and get their errors
+        let mut streams = self.0.streams.lock();
+        let (stream, audio_stream) = &mut *streams;
+        if let Some(audio_stream) = audio_stream {
+            audio_stream.stop_with_completion_handler(|_| {});
+        }
+        match stream {
+            MacosStopTarget::SCStream(stream) => { stream.stop_with_completion_handler(|_| {}); Ok(()) },
+            MacosStopTarget::CGDisplayStream(stream) => stream.stop().map_err(|_| StreamStopError::Other("Failed to stop CGDisplayStream".into())),
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -49,23 +151,282 @@ pub enum MacosCaptureResolutionType {
     Nominal,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// The OS API used to capture displays
+pub enum MacosDisplayCaptureBackend {
+    /// Use ScreenCaptureKit where available, falling back to CGDisplayStream
+    Automatic,
+    /// Use ScreenCaptureKit, as window capture does. Supports the same frame metadata, but requires MacOS 12.3
+    SCStream,
+    /// Use the deprecated CGDisplayStream API
+    CGDisplayStream,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// The matrix used to convert captured RGB content to YCbCr, for the `V420` and `F420` pixel formats
+pub enum MacosColorMatrix {
+    /// ITU-R BT.709-2, for HD video - the default
+    ItuR709_2,
+    /// ITU-R BT.601-4, for SD video
+    ItuR601_4,
+    /// SMPTE 240M-1995
+    Smpte240M1995,
+}
+
+impl From<MacosColorMatrix> for SCStreamColorMatrix {
+    fn from(color_matrix: MacosColorMatrix) -> Self {
+        match color_matrix {
+            MacosColorMatrix::ItuR709_2 => SCStreamColorMatrix::ItuR709_2,
+            MacosColorMatrix::ItuR601_4 => SCStreamColorMatrix::ItuR601_4,
+            MacosColorMatrix::Smpte240M1995 => SCStreamColorMatrix::Smpte240M1995,
+        }
+    }
+}
+
+/// The longest minimum frame interval requested from ScreenCaptureKit, in seconds
+const MAX_SC_STREAM_FRAME_INTERVAL: f64 = 1.0;
+/// Timescale for frame interval CMTimes, giving microsecond precision for long intervals
+const FRAME_INTERVAL_TIMESCALE: i32 = 1_000_000;
+
+// Frames from ScreenCaptureKit and CGDisplayStream may arrive up to a quarter of the longest requested interval early
+fn new_frame_interval_limiter(interval: Option<f64>) -> FrameIntervalLimiter {
+    FrameIntervalLimiter::new(interval.map(Duration::from_secs_f64), Duration::from_secs_f64(MAX_SC_STREAM_FRAME_INTERVAL) / 4)
+}
+
+fn maximum_fps_interval(maximum_fps: Option<f32>) -> Option<f64> {
+    maximum_fps
+        .filter(|maximum_fps| maximum_fps.is_finite() && *maximum_fps > 0.0)
+        .map(|maximum_fps| 1.0 / maximum_fps as f64)
+}
+
+// The settings of an SCStream's handler which `update_config` can change
+struct SCStreamLiveSettings {
+    frame_interval_limiter: FrameIntervalLimiter,
+    output_placement: Option<(ScalingQuality, Size)>,
+}
+
+// The configuration of a running SCStream, for `set_source_rect` and `update_config`
+struct SCStreamUpdatableConfig {
+    config: SCStreamConfiguration,
+    // The frame size without a crop
+    full_size: CGSize,
+    // Whether frames are captured at the native size of the content and placed into the output size later, rather than captured at the output size
+    native_size: bool,
+    scale_to_fit: bool,
+    // The crop set with `set_source_rect`, and the size of the content it's relative to
+    source_rect: Option<(Rect, Size)>,
+    live_settings: Arc<Mutex<SCStreamLiveSettings>>,
+    // The content left out of display capture, which `update_target` keeps excluding
+    excluded_windows: Vec<CapturableWindow>,
+    excluded_applications: Vec<CapturableApplication>,
+}
+
+impl SCStreamUpdatableConfig {
+    fn apply_source_rect(&mut self) {
+        match self.source_rect {
+            Some((source_rect, content_size)) => {
+                self.config.set_source_rect(CGRect {
+                    origin: CGPoint { x: source_rect.origin.x, y: source_rect.origin.y },
+                    size: CGSize { x: source_rect.size.width, y: source_rect.size.height },
+                });
+                // Shrink the frames with the crop, so it's captured at the same density as the full content
+                self.config.set_size(CGSize {
+                    x: (self.full_size.x * source_rect.size.width / content_size.width).ceil().max(1.0),
+                    y: (self.full_size.y * source_rect.size.height / content_size.height).ceil().max(1.0),
+                });
+            },
+            None => {
+                self.config.set_source_rect(CGRect::NULL);
+                self.config.set_size(self.full_size);
+            }
+        }
+    }
+
+    // The size of delivered frames - frames captured at their native size are placed into the output size when copying bitmaps
+    fn output_size(&self) -> Size {
+        if let Some((_, output_size)) = self.live_settings.lock().output_placement {
+            return output_size;
+        }
+        let size = self.config.size();
+        Size { width: size.x, height: size.y }
+    }
+}
+
+// The minimum frame interval for a stream, in seconds, after applying its power profile
+// Changes in frames dropped by the frame interval limiter, which are merged into the dirty rects of the next delivered frame
+#[derive(Default)]
+struct PendingDirtyRects {
+    rects: Vec<Rect>,
+    unknown: bool,
+}
+
+impl PendingDirtyRects {
+    fn drop_frame(&mut self, dirty_rects: Option<Vec<Rect>>) {
+        match dirty_rects {
+            Some(dirty_rects) => self.rects.extend(dirty_rects),
+            None => self.unknown = true,
+        }
+    }
+
+    fn deliver_frame(&mut self, dirty_rects: Option<Vec<Rect>>) -> Option<Vec<Rect>> {
+        let pending = std::mem::take(self);
+        let mut dirty_rects = dirty_rects?;
+        if pending.unknown {
+            return None;
+        }
+        dirty_rects.extend(pending.rects);
+        Some(dirty_rects)
+    }
+}
+
+// Presentation timestamps of delivered frames, which are reported relative to the first delivered frame
+#[derive(Default)]
+struct PresentationClock {
+    first: Option<Duration>,
+    last: Option<Duration>,
+}
+
+impl PresentationClock {
+    // Returns the time since the first delivered frame, and the time since the previous delivered frame
+    fn deliver_frame(&mut self, presentation_time: Duration) -> (Duration, Duration) {
+        let first = *self.first.get_or_insert(presentation_time);
+        let duration = self.last.map_or(Duration::ZERO, |last| presentation_time.saturating_sub(last));
+        self.last = Some(presentation_time);
+        (presentation_time.saturating_sub(first), duration)
+    }
+}
+
+fn stream_frame_interval(capture_config: &CaptureConfig) -> Option<f64> {
+    let configured_interval = maximum_fps_interval(capture_config.maximum_fps).map(Duration::from_secs_f64);
+    capture_config.power_profile.frame_interval(configured_interval).map(|interval| interval.as_secs_f64())
+}
+
+/// The queue depth of CGDisplayStreams, which aren't given one explicitly
+const CG_DISPLAY_STREAM_DEFAULT_QUEUE_DEPTH: usize = 3;
+
+// Create a queue for stream callbacks, scheduled according to the power profile - returns the applied setting, if any
+fn make_handler_queue(name: &str, power_profile: PowerProfile) -> (DispatchQueue, Option<&'static str>) {
+    match power_profile {
+        PowerProfile::HighPerformance => (DispatchQueue::make_concurrent_with_qos(name.into(), DispatchQoSClass::UserInteractive), Some("dispatch qos: user-interactive")),
+        PowerProfile::Balanced => (DispatchQueue::make_concurrent(name.into()), None),
+        PowerProfile::Efficiency => (DispatchQueue::make_concurrent_with_qos(name.into(), DispatchQoSClass::Utility), Some("dispatch qos: utility")),
+    }
+}
+
+// A stream's frame compositor, along with what it needs to composite frames on the GPU
+struct StreamFrameCompositor {
+    frame_compositor: SharedFrameCompositor,
+    pixel_format: CapturePixelFormat,
+    #[cfg(feature = "metal")]
+    metal_device: metal::Device,
+    #[cfg(feature = "metal")]
+    command_queue: metal::CommandQueue,
+}
+
+impl StreamFrameCompositor {
+    fn new(capture_config: &CaptureConfig, #[cfg(feature = "metal")] metal_device: &metal::Device) -> Option<Self> {
+        capture_config.frame_compositor.clone().map(|frame_compositor| Self {
+            frame_compositor,
+            pixel_format: capture_config.pixel_format,
+            #[cfg(feature = "metal")]
+            metal_device: metal_device.clone(),
+            #[cfg(feature = "metal")]
+            command_queue: metal_device.new_command_queue(),
+        })
+    }
+
+    // Draw into a frame in place before it's delivered - on the GPU with the metal feature if the compositor prefers it,
+    // otherwise (and for 10-bit frames, which have no metal textures) on the CPU with the frame's IOSurface locked
+    fn composite(&self, video_frame: &VideoFrame) -> Result<(), String> {
+        if !self.frame_compositor.is_active() {
+            return Ok(());
+        }
+        #[cfg(feature = "metal")]
+        if self.frame_compositor.prefers_gpu() && self.pixel_format != CapturePixelFormat::Argb2101010 {
+            let planes: &[MetalVideoFramePlaneTexture] = match self.pixel_format {
+                CapturePixelFormat::V420 | CapturePixelFormat::F420 => &[MetalVideoFramePlaneTexture::Luminance, MetalVideoFramePlaneTexture::Chroma],
+                _ => &[MetalVideoFramePlaneTexture::Rgba],
+            };
+            let usage = metal::MTLTextureUsage::ShaderRead | metal::MTLTextureUsage::ShaderWrite | metal::MTLTextureUsage::RenderTarget;
+            let textures = planes.iter()
+                .map(|plane| get_metal_texture_with_device(video_frame, *plane, &self.metal_device, usage))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|error| error.to_string())?;
+            let command_buffer = self.command_queue.new_command_buffer();
+            self.frame_compositor.composite(CompositorTarget::Metal { pixel_format: self.pixel_format, textures, command_buffer });
+            command_buffer.commit();
+            command_buffer.wait_until_completed();
+            return Ok(());
+        }
+        let io_surface = match &video_frame.impl_video_frame {
+            MacosVideoFrame::SCStream(frame) => frame.sample_buffer.get_image_buffer().and_then(|image_buffer| image_buffer.get_iosurface()),
+            MacosVideoFrame::CGDisplayStream(frame) => Some(frame.io_surface.clone()),
+        }.ok_or_else(|| "No IOSurface for this frame".to_string())?;
+        let lock = io_surface.lock(false, false)
+            .map_err(|_| "Couldn't lock the frame's IOSurface".to_string())?;
+        let plane_layouts = match io_surface.get_plane_count() {
+            0 => vec![(lock.get_base_address(), io_surface.get_width(), io_surface.get_height(), io_surface.get_bytes_per_row())],
+            plane_count => (0..plane_count).map(|plane| (
+                lock.get_base_address_of_plane(plane),
+                io_surface.get_width_of_plane(plane),
+                io_surface.get_height_of_plane(plane),
+                io_surface.get_bytes_per_row_of_plane(plane),
+            )).collect(),
+        };
+        let mut planes = Vec::with_capacity(plane_layouts.len());
+        for (base_address, width, height, bytes_per_row) in plane_layouts {
+            let base_address = base_address.ok_or_else(|| "Couldn't get the base address of the frame's IOSurface".to_string())?;
+            planes.push(CompositorPlane {
+                // The surface is locked for writing until the compositor returns
+                data: unsafe { std::slice::from_raw_parts_mut(base_address as *mut u8, bytes_per_row * height) },
+                width,
+                height,
+                bytes_per_row,
+            });
+        }
+        self.frame_compositor.composite(CompositorTarget::Cpu { pixel_format: self.pixel_format, planes });
+        drop(lock);
+        Ok(())
+    }
+}
+
 pub trait MacosCaptureConfigExt {
     /// Set whether or not to scale content to the output size
     fn with_scale_to_fit(self, scale_to_fit: bool) -> Self;
-    /// Set the maximum capture frame-rate
+    /// Set the maximum capture frame-rate - forwards to `CaptureConfig::with_maximum_fps`
+    #[deprecated(note = "use CaptureConfig::with_maximum_fps")]
     fn with_maximum_fps(self, maximum_fps: Option<f32>) -> Self;
     #[cfg(feature = "metal")]
     /// Set the metal device to use for texture creation
     fn with_metal_device(self, metal_device: metal::Device) -> Self;
     /// Set the resolution type of the capture. Does nothing on macos before OS 14.0
     fn with_resolution_type(self, resolution_type: MacosCaptureResolutionType) -> Self;
+    /// Set the OS API used for display capture. Defaults to `MacosDisplayCaptureBackend::Automatic`
+    fn with_display_capture_backend(self, display_capture_backend: MacosDisplayCaptureBackend) -> Self;
+    /// Set the matrix used to convert content to YCbCr for `V420` and `F420` frames. Defaults to `MacosColorMatrix::ItuR709_2`
+    /// 
+    /// YCbCr bitmaps (`bitmap` feature) record the matrix as their `color_matrix`, and convert back to RGB with it
+    fn with_color_matrix(self, color_matrix: MacosColorMatrix) -> Self;
+    /// Set the windows left out of a display capture, E.G. the recording application's own windows
+    /// 
+    /// Only applies to display capture with ScreenCaptureKit - stream creation fails when capturing with CGDisplayStream,
+    /// or when both windows and applications are excluded. There's no equivalent on Windows, where a window can hide
+    /// itself from all captures with `SetWindowDisplayAffinity(WDA_EXCLUDEFROMCAPTURE)`
+    fn with_excluded_windows(self, excluded_windows: Vec<CapturableWindow>) -> Self;
+    /// Set the applications whose windows are left out of a display capture, including windows they open later
+    /// 
+    /// Only applies to display capture with ScreenCaptureKit - see `with_excluded_windows`
+    fn with_excluded_apps(self, excluded_applications: Vec<CapturableApplication>) -> Self;
 }
 
 #[derive(Clone)]
 pub(crate) struct MacosCaptureConfig {
     pub(crate) scale_to_fit: bool,
-    pub(crate) maximum_fps: Option<f32>,
     pub(crate) resolution_type: MacosCaptureResolutionType,
+    pub(crate) display_capture_backend: MacosDisplayCaptureBackend,
+    pub(crate) color_matrix: MacosColorMatrix,
+    pub(crate) excluded_windows: Vec<CapturableWindow>,
+    pub(crate) excluded_applications: Vec<CapturableApplication>,
     #[cfg(feature = "metal")]
     pub(crate) metal_device: Option<metal::Device>,
     #[cfg(feature = "wgpu")]
@@ -74,7 +435,7 @@ pub(crate) struct MacosCaptureConfig {
 
 impl Debug for MacosCaptureConfig {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("MacosCaptureConfig").field("scale_to_fit", &self.scale_to_fit).field("maximum_fps", &self.maximum_fps).finish()
+        f.debug_struct("MacosCaptureConfig").field("scale_to_fit", &self.scale_to_fit).finish()
     }
 }
 
@@ -82,8 +443,11 @@ impl MacosCaptureConfig {
     pub fn new() -> Self {
         Self {
             scale_to_fit: true,
-            maximum_fps: None,
             resolution_type: MacosCaptureResolutionType::Nominal,
+            display_capture_backend: MacosDisplayCaptureBackend::Automatic,
+            color_matrix: MacosColorMatrix::ItuR709_2,
+            excluded_windows: Vec::new(),
+            excluded_applications: Vec::new(),
             #[cfg(feature = "metal")]
             metal_device: None,
             #[cfg(feature = "wgpu")]
@@ -104,30 +468,64 @@ impl MacosCaptureConfigExt for CaptureConfig {
     }
 
     fn with_maximum_fps(self, maximum_fps: Option<f32>) -> Self {
+        CaptureConfig::with_maximum_fps(self, maximum_fps)
+    }
+
+    #[cfg(feature = "metal")]
+    fn with_metal_device(self, metal_device: metal::Device) -> Self {
         Self {
             impl_capture_config: MacosCaptureConfig {
-                maximum_fps,
+                metal_device: Some(metal_device),
                 ..self.impl_capture_config
             },
             ..self
         }
     }
 
-    #[cfg(feature = "metal")]
-    fn with_metal_device(self, metal_device: metal::Device) -> Self {
+    fn with_resolution_type(self, resolution_type: MacosCaptureResolutionType) -> Self {
         Self {
             impl_capture_config: MacosCaptureConfig {
-                metal_device: Some(metal_device),
+                resolution_type,
                 ..self.impl_capture_config
             },
             ..self
         }
     }
 
-    fn with_resolution_type(self, resolution_type: MacosCaptureResolutionType) -> Self {
+    fn with_display_capture_backend(self, display_capture_backend: MacosDisplayCaptureBackend) -> Self {
         Self {
             impl_capture_config: MacosCaptureConfig {
-                resolution_type,
+                display_capture_backend,
+                ..self.impl_capture_config
+            },
+            ..self
+        }
+    }
+
+    fn with_color_matrix(self, color_matrix: MacosColorMatrix) -> Self {
+        Self {
+            impl_capture_config: MacosCaptureConfig {
+                color_matrix,
+                ..self.impl_capture_config
+            },
+            ..self
+        }
+    }
+
+    fn with_excluded_windows(self, excluded_windows: Vec<CapturableWindow>) -> Self {
+        Self {
+            impl_capture_config: MacosCaptureConfig {
+                excluded_windows,
+                ..self.impl_capture_config
+            },
+            ..self
+        }
+    }
+
+    fn with_excluded_apps(self, excluded_applications: Vec<CapturableApplication>) -> Self {
+        Self {
+            impl_capture_config: MacosCaptureConfig {
+                excluded_applications,
                 ..self.impl_capture_config
             },
             ..self
@@ -137,17 +535,23 @@ impl MacosCaptureConfigExt for CaptureConfig {
 
 pub trait MacosAudioCaptureConfigExt {
     fn set_exclude_current_process_audio(self, exclude_current_process_audio: bool) -> Self;
+    /// Only capture the audio played by the application that owns the captured window, rather than all system audio
+    /// 
+    /// Only valid for window capture - stream creation fails for display capture with this set
+    fn set_application_audio_only(self, application_audio_only: bool) -> Self;
 }
 
 #[derive(Copy, Clone, Debug)]
 pub(crate) struct MacosAudioCaptureConfig {
     exclude_current_process_audio: bool,
+    application_audio_only: bool,
 }
 
 impl MacosAudioCaptureConfig {
     pub fn new() -> Self {
         Self {
             exclude_current_process_audio: false,
+            application_audio_only: false,
         }
     }
 }
@@ -162,6 +566,179 @@ impl MacosAudioCaptureConfigExt for AudioCaptureConfig {
             ..self
         }
     }
+
+    fn set_application_audio_only(self, application_audio_only: bool) -> Self {
+        Self {
+            impl_capture_audio_config: MacosAudioCaptureConfig {
+                application_audio_only,
+                ..self.impl_capture_audio_config
+            },
+            ..self
+        }
+    }
+}
+
+// The stream event for an audio sample buffer
+fn audio_frame_event(sample_buffer: CMSampleBuffer, capture_time: Instant, frame_id: u64) -> Result<StreamEvent, StreamError> {
+    match MacosAudioFrame::new(sample_buffer, capture_time, frame_id) {
+        Ok(impl_audio_frame) => Ok(StreamEvent::Audio(AudioFrame { impl_audio_frame })),
+        Err(AudioBufferError::Other(error)) => Err(StreamError::Other(format!("Failed to get audio frame: {}", error))),
+        Err(_) => Err(StreamError::Other("Failed to get audio frame".into())),
+    }
+}
+
+fn configure_sc_stream_audio(config: &mut SCStreamConfiguration, audio_config: &AudioCaptureConfig) {
+    config.set_capture_audio(true);
+    let channel_count = match audio_config.channel_count {
+        crate::prelude::AudioChannelCount::Mono => 1,
+        crate::prelude::AudioChannelCount::Stereo => 2,
+    };
+    config.set_channel_count(channel_count);
+    config.set_exclude_current_process_audio(audio_config.impl_capture_audio_config.exclude_current_process_audio);
+    let sample_rate = match audio_config.sample_rate {
+        crate::prelude::AudioSampleRate::Hz8000 =>  SCStreamSampleRate::R8000,
+        crate::prelude::AudioSampleRate::Hz16000 => SCStreamSampleRate::R16000,
+        crate::prelude::AudioSampleRate::Hz24000 => SCStreamSampleRate::R24000,
+        crate::prelude::AudioSampleRate::Hz48000 => SCStreamSampleRate::R48000,
+    };
+    config.set_sample_rate(sample_rate);
+}
+
+// The backing scale factor of the screen containing a point, for finding the native pixel size of content
+fn backing_scale_factor_at(point: CGPoint) -> f64 {
+    NSScreen::screens()
+        .into_iter()
+        .find(|screen| screen.frame().contains(point))
+        .map_or(1.0, |screen| screen.backing_scale_factor())
+}
+
+const SYSTEM_SETTINGS_SCREEN_CAPTURE_URL: &str = "x-apple.systempreferences:com.apple.settings.PrivacySecurity.extension?Privacy_ScreenCapture";
+const SYSTEM_PREFERENCES_SCREEN_CAPTURE_URL: &str = "x-apple.systempreferences:com.apple.preference.security?Privacy_ScreenCapture";
+
+// System Settings replaced System Preferences in MacOS 13, changing the URLs of the privacy panes
+pub(crate) fn has_system_settings() -> bool {
+    NSProcessInfo::is_operating_system_at_least_version(13, 0, 0)
+}
+
+// Start an SCStream - ScreenCaptureKit reports failures to start asynchronously, so they're delivered
+// through the stream's callback as an error, followed by the end of the stream
+fn start_sc_stream(stream: &mut SCStream, shared_callback: Arc<Mutex<Box<dyn FnMut(Result<StreamEvent, StreamError>) + Send + 'static>>>, stopped_flag: Arc<AtomicBool>) {
+    stream.start_with_completion_handler(move |result| {
+        let Err(error) = result else {
+            return;
+        };
+        let mut callback = shared_callback.lock();
+        if stopped_flag.fetch_or(true, atomic::Ordering::AcqRel) {
+            return;
+        }
+        (callback)(Err(StreamError::Platform(format!("Failed to start stream: [description: {}, reason: {}]", error.description(), error.reason()), PlatformErrorCode::NSError { domain: error.domain(), code: error.code() })));
+        (callback)(Ok(StreamEvent::End { reason: stream_end_reason_from_ns_error(&error) }));
+    });
+}
+
+// Stop an SCStream, resolving once ScreenCaptureKit has finished tearing it down
+pub(crate) fn stop_sc_stream(stream: &mut SCStream) -> impl Future<Output = Result<(), StreamStopError>> {
+    let (tx, rx) = oneshot::channel();
+    let tx = Mutex::new(Some(tx));
+    stream.stop_with_completion_handler(move |result| {
+        if let Some(tx) = tx.lock().take() {
+            let _ = tx.send(result);
+        }
+    });
+    async move {
+        match rx.await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(error)) => Err(StreamStopError::Other(format!("Failed to stop stream: [description: {}, reason: {}]", error.description(), error.reason()))),
+            Err(_) => Err(StreamStopError::Other("Stream stop completion was never called".into())),
+        }
+    }
+}
+
+// Wait for ScreenCaptureKit to swap the content filter of a stream
+fn update_sc_stream_content_filter(stream: &mut SCStream, filter: SCContentFilter) -> Result<(), StreamUpdateError> {
+    let (tx, rx) = oneshot::channel();
+    let tx = Mutex::new(Some(tx));
+    stream.update_content_filter_with_completion_handler(filter, move |result| {
+        if let Some(tx) = tx.lock().take() {
+            let _ = tx.send(result);
+        }
+    });
+    match block_on(rx) {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(error)) => Err(StreamUpdateError::Platform(format!("Failed to update content filter: [description: {}, reason: {}]", error.description(), error.reason()), PlatformErrorCode::NSError { domain: error.domain(), code: error.code() })),
+        Err(_) => Err(StreamUpdateError::Other("Content filter update completion was never called".into())),
+    }
+}
+
+// Wait for ScreenCaptureKit to apply a new configuration to a stream - fails with the error it reported, or None if it never completed
+fn update_sc_stream_configuration(stream: &mut SCStream, config: &SCStreamConfiguration) -> Result<(), Option<NSError>> {
+    let (tx, rx) = oneshot::channel();
+    let tx = Mutex::new(Some(tx));
+    stream.update_configuration_with_completion_handler(config, move |result| {
+        if let Some(tx) = tx.lock().take() {
+            let _ = tx.send(result);
+        }
+    });
+    match block_on(rx) {
+        Ok(result) => result.map_err(Some),
+        Err(_) => Err(None),
+    }
+}
+
+// The content filter for a target, leaving the configured windows or applications out of display capture - ScreenCaptureKit
+// can't exclude both at once
+pub(crate) fn sc_content_filter_for_target(target: &Capturable, excluded_windows: &[CapturableWindow], excluded_applications: &[CapturableApplication]) -> Result<SCContentFilter, String> {
+    let display = match target {
+        Capturable::Window(window) => return Ok(SCContentFilter::new_with_desktop_independent_window(&window.impl_capturable_window.window)),
+        Capturable::Display(display) => display.impl_capturable_display.display.clone(),
+    };
+    match (excluded_windows, excluded_applications) {
+        ([], excluded_applications) => {
+            let excluded_applications = excluded_applications.iter()
+                .map(|application| application.impl_capturable_application.running_application.clone())
+                .collect::<Vec<_>>();
+            Ok(SCContentFilter::new_with_display_excluding_applications(display, &excluded_applications))
+        },
+        (excluded_windows, []) => {
+            let excluded_windows = excluded_windows.iter()
+                .map(|window| window.impl_capturable_window.window.clone())
+                .collect::<Vec<_>>();
+            Ok(SCContentFilter::new_with_display_excluding_windows(display, &excluded_windows))
+        },
+        _ => Err("Display capture can't exclude both windows and applications".into()),
+    }
+}
+
+// The filter of the stream capturing application-only audio, covering the window's application on the window's display
+fn sc_application_audio_filter_for_window(window: &SCWindow) -> Result<SCContentFilter, StreamCreateError> {
+    Ok(SCContentFilter::new_with_display_including_applications(display_for_window(window)?, &[window.owning_application()]))
+}
+
+// Find the display showing the largest part of a window
+fn display_for_window(window: &SCWindow) -> Result<SCDisplay, StreamCreateError> {
+    let (tx, rx) = oneshot::channel();
+    let tx = Mutex::new(Some(tx));
+    SCShareableContent::get_shareable_content_with_completion_handler(false, false, move |result| {
+        if let Some(tx) = tx.lock().take() {
+            let _ = tx.send(result);
+        }
+    });
+    let content = match block_on(rx) {
+        Ok(Ok(content)) => content,
+        Ok(Err(error)) => return Err(StreamCreateError::Platform(format!("Failed to get shareable content: [description: {}, reason: {}]", error.description(), error.reason()), PlatformErrorCode::NSError { domain: error.domain(), code: error.code() })),
+        Err(_) => return Err(StreamCreateError::Other("Failed to get shareable content".into())),
+    };
+    let window_frame = window.frame();
+    let overlap_area = |display: &SCDisplay| {
+        let display_frame = display.frame();
+        let width = (window_frame.origin.x + window_frame.size.x).min(display_frame.origin.x + display_frame.size.x) - window_frame.origin.x.max(display_frame.origin.x);
+        let height = (window_frame.origin.y + window_frame.size.y).min(display_frame.origin.y + display_frame.size.y) - window_frame.origin.y.max(display_frame.origin.y);
+        width.max(0.0) * height.max(0.0)
+    };
+    content.displays()
+        .into_iter()
+        .max_by(|a, b| overlap_area(a).total_cmp(&overlap_area(b)))
+        .ok_or_else(|| StreamCreateError::Other("No display found for application audio capture".into()))
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -178,12 +755,12 @@ impl MacosCaptureAccessToken {
 
 impl MacosCaptureStream {
     pub fn supported_pixel_formats() -> &'static [CapturePixelFormat] {
-        &[
-            CapturePixelFormat::V420,
-            CapturePixelFormat::F420,
-            CapturePixelFormat::Bgra8888,
-            CapturePixelFormat::Argb2101010,
-        ]
+        MACOS_PIXEL_FORMATS
+    }
+
+    pub fn supported_buffer_counts() -> RangeInclusive<usize> {
+        // SCStreamConfiguration rejects queue depths over 8
+        1..=8
     }
 
     pub fn check_access(_borderless: bool) -> Option<MacosCaptureAccessToken> {
@@ -202,15 +779,35 @@ impl MacosCaptureStream {
         }
     }
 
+    pub fn permission_settings_url() -> Option<&'static str> {
+        if has_system_settings() {
+            Some(SYSTEM_SETTINGS_SCREEN_CAPTURE_URL)
+        } else {
+            Some(SYSTEM_PREFERENCES_SCREEN_CAPTURE_URL)
+        }
+    }
+
+    pub fn open_permission_settings() -> Result<OpenSettingsOutcome, OpenSettingsError> {
+        let url = Self::permission_settings_url().ok_or(OpenSettingsError::Unavailable)?;
+        let ns_url = NSURL::from_string(url).ok_or_else(|| OpenSettingsError::Other(format!("Invalid settings URL: {}", url)))?;
+        if NSWorkspace::open_url(&ns_url) {
+            // Screen recording permission is only picked up when the application launches
+            Ok(OpenSettingsOutcome::NeedsRestart)
+        } else {
+            Err(OpenSettingsError::Other(format!("Failed to open settings URL: {}", url)))
+        }
+    }
+
     pub fn new(token: MacosCaptureAccessToken, capture_config: CaptureConfig, mut callback: Box<impl FnMut(Result<StreamEvent, StreamError>) + Send + 'static>) -> Result<Self, StreamCreateError> {
         let _ = token;
         let shared_callback = Arc::new(Mutex::new(callback as Box<dyn FnMut(Result<StreamEvent, StreamError>) + Send + 'static>));
         let stream_shared_callback = shared_callback.clone();
         #[cfg(feature = "metal")]
-        let mut metal_device = match capture_config.impl_capture_config.metal_device {
+        let mut metal_device = match capture_config.impl_capture_config.metal_device.clone() {
             Some(metal_device) => metal_device,
             None => {
-                match metal::Device::system_default() {
+                // Prefer the GPU driving the captured display, so that frames aren't copied between GPUs
+                match default_metal_device(&capture_config.target) {
                     Some(device) => device,
                     None => return Err(StreamCreateError::Other("Failed to create system default metal device".into()))
                 }
@@ -218,78 +815,155 @@ impl MacosCaptureStream {
         };
         #[cfg(feature = "metal")]
         let callback_metal_device = metal_device.clone();
+        #[cfg(feature = "metal")]
+        let frame_compositor = StreamFrameCompositor::new(&capture_config, &metal_device);
+        #[cfg(not(feature = "metal"))]
+        let frame_compositor = StreamFrameCompositor::new(&capture_config);
         #[cfg(feature = "wgpu")]
         let wgpu_device = capture_config.impl_capture_config.wgpu_device.clone();
         #[cfg(feature = "wgpu")]
-        let callback_wgpu_device = wgpu_device.clone();
-        match capture_config.target {
-            Capturable::Window(window) => {
+        let frame_wgpu_device: SharedWgpuDevice = Arc::new(Mutex::new(wgpu_device.clone()));
+        #[cfg(feature = "wgpu")]
+        let callback_wgpu_device = frame_wgpu_device.clone();
+        #[cfg(feature = "bitmap")]
+        let callback_change_metric = capture_config.change_metric;
+        let callback_color_matrix = capture_config.impl_capture_config.color_matrix;
+        let callback_capture_video = capture_config.capture_video;
+        let application_audio_config = capture_config.capture_audio.clone()
+            .filter(|audio_config| audio_config.impl_capture_audio_config.application_audio_only);
+        if application_audio_config.is_some() {
+            if let Capturable::Display(_) = &capture_config.target {
+                return Err(StreamCreateError::Other("Application-only audio requires a window capture target".into()));
+            }
+        }
+        // CGDisplayStream can't capture audio, so audio-only streams always use ScreenCaptureKit
+        let use_sc_stream = !capture_config.capture_video || match &capture_config.target {
+            Capturable::Window(_) => true,
+            Capturable::Display(_) => match capture_config.impl_capture_config.display_capture_backend {
+                MacosDisplayCaptureBackend::Automatic => SCStream::class_exists(),
+                MacosDisplayCaptureBackend::SCStream => true,
+                MacosDisplayCaptureBackend::CGDisplayStream => false,
+            },
+        };
+        let excludes_content = !capture_config.impl_capture_config.excluded_windows.is_empty() || !capture_config.impl_capture_config.excluded_applications.is_empty();
+        if excludes_content && !use_sc_stream {
+            return Err(StreamCreateError::Other("Excluding windows or applications requires ScreenCaptureKit display capture".into()));
+        }
+        match capture_config.target.clone() {
+            target if use_sc_stream => {
                 let mut config = SCStreamConfiguration::new();
                 let (pixel_format, set_color_matrix) = match capture_config.pixel_format {
                     CapturePixelFormat::Bgra8888 =>    (SCStreamPixelFormat::BGRA8888, false),
                     CapturePixelFormat::Argb2101010 => (SCStreamPixelFormat::L10R, false),
                     CapturePixelFormat::V420 =>        (SCStreamPixelFormat::V420, true),
                     CapturePixelFormat::F420 =>        (SCStreamPixelFormat::F420, true),
+                    _ => return Err(StreamCreateError::UnsupportedPixelFormat),
                 };
                 if set_color_matrix {
-                    config.set_color_matrix(SCStreamColorMatrix::ItuR709_2);
+                    config.set_color_matrix(capture_config.impl_capture_config.color_matrix.into());
                 }
                 config.set_pixel_format(pixel_format);
-                config.set_minimum_time_interval(CMTime::new_with_seconds(capture_config.impl_capture_config.maximum_fps.map(|x| 1.0 / x).unwrap_or(1.0 / 120.0) as f64, 240));
-                /*config.set_source_rect(CGRect {
-                    origin: CGPoint {
-                        x: capture_config.source_rect.origin.x,
-                        y: capture_config.source_rect.origin.y,
-                    },
-                    size: CGSize {
-                        x: capture_config.source_rect.size.width,
-                        y: capture_config.source_rect.size.height
-                    }
-                });*/
+                let power_profile = capture_config.power_profile;
+                let mut platform_settings = Vec::new();
+                let frame_interval = stream_frame_interval(&capture_config);
+                let sc_frame_interval = frame_interval.unwrap_or(1.0 / 120.0).min(MAX_SC_STREAM_FRAME_INTERVAL);
+                config.set_minimum_time_interval(CMTime::new_with_seconds(sc_frame_interval, FRAME_INTERVAL_TIMESCALE));
+                let frame_interval_limiter = new_frame_interval_limiter(frame_interval.filter(|interval| *interval > MAX_SC_STREAM_FRAME_INTERVAL));
+                let mut pending_dirty_rects = PendingDirtyRects::default();
+                let mut presentation_clock = PresentationClock::default();
                 let resolution_type = match capture_config.impl_capture_config.resolution_type {
                     MacosCaptureResolutionType::Automatic => SCCaptureResolutionType::SCCaptureResolutionAutomatic,
                     MacosCaptureResolutionType::Best => SCCaptureResolutionType::SCCaptureResolutionBest,
                     MacosCaptureResolutionType::Nominal => SCCaptureResolutionType::SCCaptureResolutionNominal,
                 };
                 _ = config.set_resolution_type(resolution_type);
-                config.set_size(CGSize {
-                    x: capture_config.output_size.width,
-                    y: capture_config.output_size.height,
-                });
-                config.set_scales_to_fit(capture_config.impl_capture_config.scale_to_fit);
-                config.set_queue_depth(capture_config.buffer_count as isize);
+                let output_size = capture_config.cap_size(capture_config.output_size);
+                let output_placement = match capture_config.scaling_quality {
+                    ScalingQuality::Smooth => None,
+                    scaling_quality => Some((scaling_quality, ScalingQuality::placed_output_size(output_size))),
+                };
+                let config_size = if output_placement.is_some() {
+                    // Capture the content at its native size, and leave placing it in the output to bitmap copies
+                    let target_rect = match &target {
+                        Capturable::Window(window) => window.rect(),
+                        Capturable::Display(display) => display.rect(),
+                    };
+                    let scale_factor = backing_scale_factor_at(CGPoint { x: target_rect.origin.x, y: target_rect.origin.y });
+                    config.set_scales_to_fit(false);
+                    CGSize {
+                        x: (target_rect.size.width * scale_factor).ceil(),
+                        y: (target_rect.size.height * scale_factor).ceil(),
+                    }
+                } else {
+                    // With maximum dimensions, content larger than the output has to be scaled down into it rather than cropped
+                    config.set_scales_to_fit(capture_config.impl_capture_config.scale_to_fit || capture_config.max_dimensions.is_some());
+                    CGSize {
+                        x: output_size.width,
+                        y: output_size.height,
+                    }
+                };
+                let config_size = if capture_config.capture_video {
+                    config_size
+                } else {
+                    // The video of audio-only streams is never delivered, so keep it as small and infrequent as possible
+                    config.set_minimum_time_interval(CMTime::new_with_seconds(MAX_SC_STREAM_FRAME_INTERVAL, FRAME_INTERVAL_TIMESCALE));
+                    CGSize { x: 2.0, y: 2.0 }
+                };
+                config.set_size(config_size);
+                let buffer_count = power_profile.buffer_count(capture_config.buffer_count);
+                config.set_queue_depth(buffer_count as isize);
                 config.set_show_cursor(capture_config.show_cursor);
-                match capture_config.capture_audio {
-                    Some(audio_config) => {
-                        config.set_capture_audio(true);
-                        let channel_count = match audio_config.channel_count {
-                            crate::prelude::AudioChannelCount::Mono => 1,
-                            crate::prelude::AudioChannelCount::Stereo => 2,
-                        };
-                        config.set_channel_count(channel_count);
-                        config.set_exclude_current_process_audio(audio_config.impl_capture_audio_config.exclude_current_process_audio);
-                        let sample_rate = match audio_config.sample_rate {
-                            crate::prelude::AudioSampleRate::Hz8000 =>  SCStreamSampleRate::R8000,
-                            crate::prelude::AudioSampleRate::Hz16000 => SCStreamSampleRate::R16000,
-                            crate::prelude::AudioSampleRate::Hz24000 => SCStreamSampleRate::R24000,
-                            crate::prelude::AudioSampleRate::Hz48000 => SCStreamSampleRate::R48000,
-                        };
-                        config.set_sample_rate(sample_rate);
+                // Skipping the shadow saves the compositor from rendering it into the capture (MacOS 14+)
+                if power_profile == PowerProfile::Efficiency && matches!(target, Capturable::Window(_)) {
+                    if config.set_ignore_shadows_single_window(true).is_ok() {
+                        platform_settings.push("ignore window shadow");
+                    }
+                }
+                // Application-only audio comes from a separate stream, see below
+                match &capture_config.capture_audio {
+                    Some(audio_config) if application_audio_config.is_none() => {
+                        configure_sc_stream_audio(&mut config, audio_config);
                     },
-                    None => {
+                    _ => {
                         config.set_capture_audio(false);
                     }
                 }
 
-                let filter = SCContentFilter::new_with_desktop_independent_window(&window.impl_capturable_window.window);
+                let filter = sc_content_filter_for_target(&target, &capture_config.impl_capture_config.excluded_windows, &capture_config.impl_capture_config.excluded_applications)
+                    .map_err(StreamCreateError::Other)?;
+                let live_settings = Arc::new(Mutex::new(SCStreamLiveSettings {
+                    frame_interval_limiter,
+                    output_placement,
+                }));
+                let callback_live_settings = live_settings.clone();
+                let mut sc_stream_config = SCStreamUpdatableConfig {
+                    config: config.clone(),
+                    full_size: config_size,
+                    native_size: output_placement.is_some(),
+                    scale_to_fit: capture_config.impl_capture_config.scale_to_fit,
+                    source_rect: capture_config.source_rect.map(|source_rect| (source_rect, target.rect().size)),
+                    live_settings,
+                    excluded_windows: capture_config.impl_capture_config.excluded_windows.clone(),
+                    excluded_applications: capture_config.impl_capture_config.excluded_applications.clone(),
+                };
+                // The updatable config shares the configuration the stream is created with, so this crops from the first frame
+                if sc_stream_config.source_rect.is_some() {
+                    sc_stream_config.apply_source_rect();
+                }
 
-                let handler_queue = DispatchQueue::make_concurrent("com.augmend.crabgrab.window_capture".into());
+                let (handler_queue, queue_setting) = make_handler_queue("com.augmend.crabgrab.window_capture", power_profile);
+                platform_settings.extend(queue_setting);
 
-                let mut audio_frame_id_counter = AtomicU64::new(0);
+                let audio_frame_id_counter = Arc::new(AtomicU64::new(0));
+                let callback_audio_frame_id_counter = audio_frame_id_counter.clone();
                 let mut video_frame_id_counter = AtomicU64::new(0);
 
                 let stopped_flag = Arc::new(AtomicBool::new(false));
                 let callback_stopped_flag = stopped_flag.clone();
+                let updating_target_flag = Arc::new(AtomicBool::new(false));
+                let callback_updating_target_flag = updating_target_flag.clone();
+                let dropped_frames = Arc::new(AtomicU64::new(0));
+                let callback_dropped_frames = dropped_frames.clone();
                 
                 let handler = SCStreamHandler::new(Box::new(move |stream_result: Result<(CMSampleBuffer, SCStreamOutputType), SCStreamCallbackError>| {
                     let mut callback = stream_shared_callback.lock();
@@ -298,8 +972,11 @@ impl MacosCaptureStream {
                         Ok((sample_buffer, output_type)) => {
                             match output_type {
                                 SCStreamOutputType::Audio => {
-                                    let frame_id = audio_frame_id_counter.fetch_add(1, atomic::Ordering::AcqRel);
-                                    // TODO...
+                                    if callback_stopped_flag.load(atomic::Ordering::Acquire) {
+                                        return;
+                                    }
+                                    let frame_id = callback_audio_frame_id_counter.fetch_add(1, atomic::Ordering::AcqRel);
+                                    (callback)(audio_frame_event(sample_buffer, capture_time, frame_id));
                                 },
                                 SCStreamOutputType::Screen => {
                                     let attachments = sample_buffer.get_sample_attachment_array();
@@ -317,27 +994,65 @@ impl MacosCaptureStream {
                                     }
                                     match status_opt.unwrap() {
                                         SCFrameStatus::Complete => {
-                                            if callback_stopped_flag.load(atomic::Ordering::Acquire) {
+                                            if !callback_capture_video {
                                                 return;
                                             }
+                                            if callback_stopped_flag.load(atomic::Ordering::Acquire) || callback_updating_target_flag.load(atomic::Ordering::Acquire) {
+                                                callback_dropped_frames.fetch_add(1, atomic::Ordering::Relaxed);
+                                                return;
+                                            }
+                                            // ScreenCaptureKit occasionally reports complete frames without any pixels
+                                            if sample_buffer.get_image_buffer().is_none() {
+                                                (callback)(Err(StreamError::EmptyFrame));
+                                                return;
+                                            }
+                                            let dirty_rects = sc_stream_frame_dirty_rects(&attachments[0]);
+                                            let output_placement = {
+                                                let mut live_settings = callback_live_settings.lock();
+                                                if !live_settings.frame_interval_limiter.should_deliver(capture_time) {
+                                                    pending_dirty_rects.drop_frame(dirty_rects);
+                                                    callback_dropped_frames.fetch_add(1, atomic::Ordering::Relaxed);
+                                                    return;
+                                                }
+                                                live_settings.output_placement
+                                            };
+                                            let dirty_rects = pending_dirty_rects.deliver_frame(dirty_rects);
+                                            let (origin_time, duration) = presentation_clock.deliver_frame(sample_buffer_presentation_time(&sample_buffer));
                                             let frame_id = video_frame_id_counter.fetch_add(1, atomic::Ordering::AcqRel);
                                             let video_frame = VideoFrame {
                                                 impl_video_frame: MacosVideoFrame::SCStream(MacosSCStreamVideoFrame {
                                                     sample_buffer,
                                                     capture_time,
+                                                    origin_time,
+                                                    duration,
                                                     dictionary: RefCell::new(None),
                                                     frame_id,
+                                                    output_placement,
+                                                    cursor_location: cursor_location(),
+                                                    cursor_visible: cursor_is_visible(),
+                                                    cursor: NSCursor::current_system_cursor(),
+                                                    dirty_rects,
+                                                    color_matrix: callback_color_matrix,
                                                     #[cfg(feature = "metal")]
                                                     metal_device: Some(callback_metal_device.clone()),
                                                     #[cfg(feature = "wgpu")]
-                                                    wgpu_device: callback_wgpu_device.clone(),
-                                                })
+                                                    wgpu_device: callback_wgpu_device.lock().clone(),
+                                                }),
+                                                clock_origin: None,
+                                                #[cfg(feature = "bitmap")]
+                                                change_metric: callback_change_metric,
                                             };
+                                            if let Some(frame_compositor) = &frame_compositor {
+                                                if let Err(error) = frame_compositor.composite(&video_frame) {
+                                                    (callback)(Err(StreamError::Other(format!("Failed to composite frame: {}", error))));
+                                                    return;
+                                                }
+                                            }
                                             (callback)(Ok(StreamEvent::Video(video_frame)));
                                         },
                                         SCFrameStatus::Suspended |
                                         SCFrameStatus::Idle => {
-                                            if callback_stopped_flag.load(atomic::Ordering::Acquire) {
+                                            if !callback_capture_video || callback_stopped_flag.load(atomic::Ordering::Acquire) || callback_updating_target_flag.load(atomic::Ordering::Acquire) {
                                                 return;
                                             }
                                             (callback)(Ok(StreamEvent::Idle));
@@ -346,7 +1061,7 @@ impl MacosCaptureStream {
                                             if callback_stopped_flag.fetch_or(true, atomic::Ordering::AcqRel) {
                                                 return;
                                             }
-                                            (callback)(Ok(StreamEvent::End));
+                                            (callback)(Ok(StreamEvent::End { reason: StreamEndReason::Stopped }));
                                         }
                                         _ => {}
                                     }
@@ -357,68 +1072,188 @@ impl MacosCaptureStream {
                         },
                         Err(err) => {
                             let event = match err {
-                                SCStreamCallbackError::StreamStopped => {
+                                SCStreamCallbackError::StreamStopped(error) => {
                                     if callback_stopped_flag.fetch_or(true, atomic::Ordering::AcqRel) {
                                         return;
                                     }
-                                    Ok(StreamEvent::End)
+                                    Ok(StreamEvent::End { reason: stream_end_reason_from_ns_error(&error) })
+                                },
+                                SCStreamCallbackError::MissingSampleBuffer => Err(StreamError::Other("Stream delivered no sample buffer".into())),
+                                SCStreamCallbackError::Other(e) => Err(StreamError::Platform(format!("Internal stream failure: [description: {}, reason: {}]", e.description(), e.reason()), PlatformErrorCode::NSError { domain: e.domain(), code: e.code() })),
+                                SCStreamCallbackError::HandlerPanicked { message, stopped } => {
+                                    // The stream has stopped either way, so don't report its end again
+                                    if stopped && callback_stopped_flag.fetch_or(true, atomic::Ordering::AcqRel) {
+                                        return;
+                                    }
+                                    Err(StreamError::Other(format!("Stream handler panicked: {}", message)))
                                 },
-                                SCStreamCallbackError::SampleBufferCopyFailed => Err(StreamError::Other("Failed to copy sample buffer".into())),
-                                SCStreamCallbackError::Other(e) => Err(StreamError::Other(format!("Internal stream failure: [description: {}, reason: {}, code: {}, domain: {}]", e.description(), e.reason(), e.code(), e.domain()))),
                             };
                             (callback)(event);
                         }
                     }
                 }));
 
-                let mut sc_stream = SCStream::new(filter, config, handler_queue, handler)
+                // Audio samples are only delivered to streams with an audio output added
+                let output_types: &[SCStreamOutputType] = match &capture_config.capture_audio {
+                    Some(_) if application_audio_config.is_none() => &[SCStreamOutputType::Screen, SCStreamOutputType::Audio],
+                    _ => &[SCStreamOutputType::Screen],
+                };
+                let mut sc_stream = SCStream::new_with_output_types(filter, config, handler_queue, handler, output_types)
                     .map_err(|error| StreamCreateError::Other(error))?;
 
-                sc_stream.start();
+                // ScreenCaptureKit filters audio by the applications in the content filter, so application-only audio
+                // is captured by a second stream filtered to the window's application, which only outputs audio
+                let mut audio_stream = match (&application_audio_config, &target) {
+                    (Some(audio_config), Capturable::Window(window)) => {
+                        let window = &window.impl_capturable_window.window;
+                        let audio_filter = sc_application_audio_filter_for_window(window)?;
+                        let mut audio_stream_config = SCStreamConfiguration::new();
+                        // The video of this stream is never delivered, so keep it as small and infrequent as possible
+                        audio_stream_config.set_size(CGSize { x: 2.0, y: 2.0 });
+                        audio_stream_config.set_minimum_time_interval(CMTime::new_with_seconds(MAX_SC_STREAM_FRAME_INTERVAL, FRAME_INTERVAL_TIMESCALE));
+                        configure_sc_stream_audio(&mut audio_stream_config, audio_config);
+                        let audio_shared_callback = shared_callback.clone();
+                        let audio_stopped_flag = stopped_flag.clone();
+                        let audio_handler = SCStreamHandler::new(Box::new(move |stream_result: Result<(CMSampleBuffer, SCStreamOutputType), SCStreamCallbackError>| {
+                            if audio_stopped_flag.load(atomic::Ordering::Acquire) {
+                                return;
+                            }
+                            let error = match stream_result {
+                                Ok((sample_buffer, SCStreamOutputType::Audio)) => {
+                                    let frame_id = audio_frame_id_counter.fetch_add(1, atomic::Ordering::AcqRel);
+                                    (audio_shared_callback.lock())(audio_frame_event(sample_buffer, Instant::now(), frame_id));
+                                    return;
+                                },
+                                Ok((_, SCStreamOutputType::Screen)) => return,
+                                // The end of the capture is reported by the video stream
+                                Err(SCStreamCallbackError::StreamStopped(_)) => return,
+                                Err(SCStreamCallbackError::MissingSampleBuffer) => StreamError::Other("Audio stream delivered no sample buffer".into()),
+                                Err(SCStreamCallbackError::Other(e)) => StreamError::Platform(format!("Internal audio stream failure: [description: {}, reason: {}]", e.description(), e.reason()), PlatformErrorCode::NSError { domain: e.domain(), code: e.code() }),
+                                Err(SCStreamCallbackError::HandlerPanicked { message, .. }) => StreamError::Other(format!("Audio stream handler panicked: {}", message)),
+                            };
+                            (audio_shared_callback.lock())(Err(error));
+                        }));
+                        let (audio_handler_queue, _) = make_handler_queue("com.augmend.crabgrab.application_audio_capture", power_profile);
+                        let audio_stream = SCStream::new_with_output_types(audio_filter, audio_stream_config, audio_handler_queue, audio_handler, &[SCStreamOutputType::Audio])
+                            .map_err(|error| StreamCreateError::Other(error))?;
+                        Some(audio_stream)
+                    },
+                    _ => None,
+                };
+
+                start_sc_stream(&mut sc_stream, shared_callback.clone(), stopped_flag.clone());
+                if let Some(audio_stream) = &mut audio_stream {
+                    start_sc_stream(audio_stream, shared_callback.clone(), stopped_flag.clone());
+                }
+
+                let stop_handle = MacosStopHandle::new(stopped_flag.clone(), shared_callback.clone(), MacosStopTarget::SCStream(sc_stream.clone()), audio_stream.clone());
 
                 Ok(MacosCaptureStream {
                     stopped_flag,
+                    stop_handle,
+                    updating_target_flag,
+                    dropped_frames,
+                    output_size: sc_stream_config.output_size(),
+                    sc_stream_config: Some(sc_stream_config),
                     shared_callback,
-                    stream: MacosCaptureStreamInternal::Window(sc_stream),
+                    stream: MacosCaptureStreamInternal::SCStream(sc_stream),
+                    audio_stream,
+                    display_stream_stopped_rx: None,
+                    effective_config: EffectiveCaptureConfig {
+                        power_profile,
+                        minimum_frame_interval: frame_interval.map(Duration::from_secs_f64),
+                        buffer_count,
+                        platform_settings,
+                    },
                     #[cfg(feature = "metal")]
                     metal_device,
                     #[cfg(feature = "wgpu")]
-                    wgpu_device
+                    wgpu_device,
+                    #[cfg(feature = "wgpu")]
+                    frame_wgpu_device,
                 })
             },
             Capturable::Display(display) => {
-                let options_dict = NSDictionary::new_mutable();
+                let mut options_dict = NSDictionary::new_mutable();
 
                 #[cfg(feature = "metal")]
                 let callback_metal_device = metal_device.clone();
                 
                 let display_id = display.impl_capturable_display.display.raw_id();
 
-                let size = (capture_config.output_size.width.ceil() as usize, capture_config.output_size.height.ceil() as usize);
+                let output_size = capture_config.cap_size(capture_config.output_size);
+                let size = match capture_config.source_rect {
+                    Some(source_rect) => {
+                        // The source rect is in points relative to the display's origin, as it's given
+                        let source_rect_dict = CGRect {
+                            origin: CGPoint { x: source_rect.origin.x, y: source_rect.origin.y },
+                            size: CGSize { x: source_rect.size.width, y: source_rect.size.height },
+                        }.create_dictionary_representation();
+                        options_dict.set_object_for_key(source_rect_dict.0, unsafe { kCGDisplayStreamSourceRect } as *mut AnyObject);
+                        // Shrink the frames with the crop, so it's captured at the same density as the full display
+                        let display_size = display.rect().size;
+                        (
+                            (output_size.width * source_rect.size.width / display_size.width).ceil().max(1.0) as usize,
+                            (output_size.height * source_rect.size.height / display_size.height).ceil().max(1.0) as usize,
+                        )
+                    },
+                    None => (output_size.width.ceil() as usize, output_size.height.ceil() as usize),
+                };
+                let callback_source_rect = capture_config.source_rect;
 
                 let (pixel_format, set_color_matrix) = match capture_config.pixel_format {
                     CapturePixelFormat::Bgra8888 =>    (SCStreamPixelFormat::BGRA8888, false),
                     CapturePixelFormat::Argb2101010 => (SCStreamPixelFormat::L10R, false),
                     CapturePixelFormat::V420 =>        (SCStreamPixelFormat::V420, true),
                     CapturePixelFormat::F420 =>        (SCStreamPixelFormat::F420, true),
+                    _ => return Err(StreamCreateError::UnsupportedPixelFormat),
                 };
+                if set_color_matrix {
+                    let color_matrix = SCStreamColorMatrix::from(capture_config.impl_capture_config.color_matrix);
+                    options_dict.set_object_for_key(color_matrix.to_cfstringref() as *mut AnyObject, unsafe { kCGDisplayStreamYCbCrMatrix } as *mut AnyObject);
+                }
 
-                let dispatch_queue = DispatchQueue::make_concurrent("crabgrab.capture".into());
+                let power_profile = capture_config.power_profile;
+                let (dispatch_queue, queue_setting) = make_handler_queue("crabgrab.capture", power_profile);
                 
                 let mut audio_frame_id_counter = AtomicU64::new(0);
                 let mut video_frame_id_counter = AtomicU64::new(0);
 
                 let stopped_flag = Arc::new(AtomicBool::new(false));
                 let callback_stopped_flag = stopped_flag.clone();
+                let dropped_frames = Arc::new(AtomicU64::new(0));
+                let callback_dropped_frames = dropped_frames.clone();
+
+                let frame_interval = stream_frame_interval(&capture_config);
+                let frame_interval_limiter = Mutex::new(new_frame_interval_limiter(frame_interval));
+
+                let (display_stream_stopped_tx, display_stream_stopped_rx) = oneshot::channel();
+                let display_stream_stopped_tx = Mutex::new(Some(display_stream_stopped_tx));
 
-                let capture_time = Instant::now();
+                let pending_dirty_rects = Mutex::new(PendingDirtyRects::default());
+                let presentation_clock = Mutex::new(PresentationClock::default());
 
-                let stream_callback = move |status, duration, io_surface: IOSurface| {
+                let stream_callback = move |status, display_time, io_surface: IOSurface, dirty_rects: Option<Vec<CGRect>>| {
                     let now = Instant::now();
                     match status {
                         CGDisplayStreamFrameStatus::Complete => {
+                            let dirty_rects = dirty_rects.map(|dirty_rects| dirty_rects.into_iter().map(rect_from_cg_rect).collect());
+                            if !frame_interval_limiter.lock().should_deliver(now) {
+                                pending_dirty_rects.lock().drop_frame(dirty_rects);
+                                callback_dropped_frames.fetch_add(1, atomic::Ordering::Relaxed);
+                                return;
+                            }
+                            let dirty_rects = pending_dirty_rects.lock().deliver_frame(dirty_rects);
+                            let (origin_time, duration) = presentation_clock.lock().deliver_frame(display_time);
                             let frame_id = video_frame_id_counter.fetch_add(1, atomic::Ordering::AcqRel);
-                            let rect = display.impl_capturable_display.display.frame();
+                            let display_rect = display.impl_capturable_display.display.frame();
+                            let rect = match callback_source_rect {
+                                Some(source_rect) => CGRect {
+                                    origin: CGPoint { x: display_rect.origin.x + source_rect.origin.x, y: display_rect.origin.y + source_rect.origin.y },
+                                    size: CGSize { x: source_rect.size.width, y: source_rect.size.height },
+                                },
+                                None => display_rect,
+                            };
                             let w = io_surface.get_width();
                             let h = io_surface.get_height();
                             let video_frame = VideoFrame{
@@ -427,25 +1262,42 @@ impl MacosCaptureStream {
                                         io_surface,
                                         duration,
                                         capture_timestamp: now,
-                                        capture_time: now - capture_time,
+                                        capture_time: origin_time,
                                         frame_id,
+                                        display_id,
                                         source_rect: Rect {
                                             origin: Point { x: rect.origin.x, y: rect.origin.y },
                                             size: Size { width: rect.size.x, height: rect.size.y },
                                         },
                                         dest_size: Size { width: w as f64, height: h as f64 },
+                                        cursor_location: cursor_location(),
+                                        cursor_visible: cursor_is_visible(),
+                                        cursor: NSCursor::current_system_cursor(),
+                                        dirty_rects,
+                                        color_matrix: callback_color_matrix,
                                         #[cfg(feature = "metal")]
                                         metal_device: callback_metal_device.clone(),
                                         #[cfg(feature = "wgpu")]
-                                        wgpu_device: callback_wgpu_device.clone(),
+                                        wgpu_device: callback_wgpu_device.lock().clone(),
                                     }
-                                )
+                                ),
+                                clock_origin: None,
+                                #[cfg(feature = "bitmap")]
+                                change_metric: callback_change_metric,
                             };
                             
                             let mut callback = stream_shared_callback.lock();
-                            if !callback_stopped_flag.load(atomic::Ordering::Acquire) {
-                                (callback)(Ok(StreamEvent::Video(video_frame)));
+                            if callback_stopped_flag.load(atomic::Ordering::Acquire) {
+                                callback_dropped_frames.fetch_add(1, atomic::Ordering::Relaxed);
+                                return;
                             }
+                            if let Some(frame_compositor) = &frame_compositor {
+                                if let Err(error) = frame_compositor.composite(&video_frame) {
+                                    (callback)(Err(StreamError::Other(format!("Failed to composite frame: {}", error))));
+                                    return;
+                                }
+                            }
+                            (callback)(Ok(StreamEvent::Video(video_frame)));
                         },
                         CGDisplayStreamFrameStatus::Idle => {
                             let mut callback = stream_shared_callback.lock();
@@ -456,43 +1308,225 @@ impl MacosCaptureStream {
                         CGDisplayStreamFrameStatus::Stopped => {
                             let mut callback = stream_shared_callback.lock();
                             if !callback_stopped_flag.fetch_or(true, atomic::Ordering::AcqRel) {
-                                (callback)(Ok(StreamEvent::End));
+                                let reason = if unsafe { CGDisplayIsOnline(display_id) } == 0 {
+                                    StreamEndReason::TargetGone
+                                } else {
+                                    StreamEndReason::Stopped
+                                };
+                                (callback)(Ok(StreamEvent::End { reason }));
+                            }
+                            if let Some(display_stream_stopped_tx) = display_stream_stopped_tx.lock().take() {
+                                let _ = display_stream_stopped_tx.send(());
                             }
                         },
                         _ => {}
                     }
                 };
+                // Report panics in the frame handler to the stream, as SCStream handlers do, rather than letting them unwind into CoreGraphics
+                let panic_shared_callback = shared_callback.clone();
+                let panic_stopped_flag = stopped_flag.clone();
+                let stream_callback = move |status, duration, io_surface: IOSurface, dirty_rects: Option<Vec<CGRect>>| {
+                    let Err(payload) = catch_unwind(AssertUnwindSafe(|| stream_callback(status, duration, io_surface, dirty_rects))) else {
+                        return;
+                    };
+                    let message = CallbackPanic::from_payload(payload.as_ref()).message().unwrap_or("unknown panic").to_string();
+                    let mut callback = panic_shared_callback.lock();
+                    if !panic_stopped_flag.load(atomic::Ordering::Acquire) {
+                        (callback)(Err(StreamError::Other(format!("Stream handler panicked: {}", message))));
+                    }
+                };
 
                 let display_stream = CGDisplayStream::new(stream_callback, display_id, size, pixel_format, options_dict, dispatch_queue);
 
+                // CGDisplayStream doesn't reliably stop when its display is disconnected, so watch for the display being removed.
+                // Note that reconfiguration callbacks are only delivered while the main run loop is running.
+                let reconfiguration_shared_callback = shared_callback.clone();
+                let reconfiguration_stopped_flag = stopped_flag.clone();
+                let display_reconfiguration_observer = CGDisplayReconfigurationObserver::new(move |changed_display_id, flags| {
+                    if !display_was_removed(display_id, changed_display_id, flags) {
+                        return;
+                    }
+                    let mut callback = reconfiguration_shared_callback.lock();
+                    if !reconfiguration_stopped_flag.fetch_or(true, atomic::Ordering::AcqRel) {
+                        (callback)(Ok(StreamEvent::End { reason: StreamEndReason::TargetGone }));
+                    }
+                });
+
                 display_stream.start().map_err(|_| StreamCreateError::Other("Stream failed to start".into()))?;
 
+                let stop_handle = MacosStopHandle::new(stopped_flag.clone(), shared_callback.clone(), MacosStopTarget::CGDisplayStream(display_stream.clone()), None);
+
                 Ok(MacosCaptureStream {
-                    stream: MacosCaptureStreamInternal::Display(display_stream),
+                    stream: MacosCaptureStreamInternal::CGDisplayStream(display_stream, display_reconfiguration_observer),
+                    audio_stream: None,
+                    display_stream_stopped_rx: Some(display_stream_stopped_rx),
+                    effective_config: EffectiveCaptureConfig {
+                        power_profile,
+                        minimum_frame_interval: frame_interval.map(Duration::from_secs_f64),
+                        buffer_count: CG_DISPLAY_STREAM_DEFAULT_QUEUE_DEPTH,
+                        platform_settings: queue_setting.into_iter().collect(),
+                    },
                     stopped_flag,
+                    stop_handle,
+                    updating_target_flag: Arc::new(AtomicBool::new(false)),
+                    dropped_frames,
+                    output_size: Size { width: size.0 as f64, height: size.1 as f64 },
+                    sc_stream_config: None,
                     shared_callback,
                     #[cfg(feature = "metal")]
                     metal_device,
                     #[cfg(feature = "wgpu")]
-                    wgpu_device
+                    wgpu_device,
+                    #[cfg(feature = "wgpu")]
+                    frame_wgpu_device,
                 }) 
-            }
+            },
+            Capturable::Window(_) => unreachable!("Window capture always uses SCStream"),
         }
 
     }
 
-    pub(crate) fn stop(&mut self) -> Result<(), StreamStopError> {
-        {
+    pub(crate) fn stop(&self) -> Result<(), StreamStopError> {
+        self.stop_handle.stop(true)
+    }
+
+    pub(crate) fn stop_handle(&self) -> MacosStopHandle {
+        self.stop_handle.clone()
+    }
+
+    pub(crate) async fn close(mut self) -> Result<(), StreamStopError> {
+        let already_stopped = {
             let mut callback = self.shared_callback.lock();
-            if !self.stopped_flag.fetch_or(true, atomic::Ordering::AcqRel) {
-                (callback)(Ok(StreamEvent::End));
-            } else {
-                return Ok(());
+            let already_stopped = self.stopped_flag.fetch_or(true, atomic::Ordering::AcqRel);
+            if !already_stopped {
+                (callback)(Ok(StreamEvent::End { reason: StreamEndReason::Stopped }));
             }
+            already_stopped
+        };
+        // Stop the OS streams even if the stream already ended, so their teardown can be awaited
+        let audio_stream_stopped = self.audio_stream.as_mut().map(stop_sc_stream);
+        let result = match &mut self.stream {
+            MacosCaptureStreamInternal::SCStream(stream) => stop_sc_stream(stream).await,
+            MacosCaptureStreamInternal::CGDisplayStream(stream, _) => {
+                let result = stream.stop().map_err(|_| StreamStopError::Other("Failed to stop CGDisplayStream".into()));
+                if let (Ok(()), Some(display_stream_stopped_rx)) = (&result, self.display_stream_stopped_rx.take()) {
+                    let _ = display_stream_stopped_rx.await;
+                }
+                result
+            },
+        };
+        let audio_result = match audio_stream_stopped {
+            Some(audio_stream_stopped) => audio_stream_stopped.await,
+            None => Ok(()),
+        };
+        // A stream which already ended may refuse to stop again, but it's still torn down
+        if already_stopped {
+            return Ok(());
+        }
+        result.and(audio_result)
+    }
+
+    pub(crate) fn update_target(&mut self, target: Capturable) -> Result<(), StreamUpdateError> {
+        if self.stopped_flag.load(atomic::Ordering::Acquire) {
+            return Err(StreamUpdateError::AlreadyStopped);
         }
-        match &mut self.stream {
-            MacosCaptureStreamInternal::Window(stream) => { stream.stop(); Ok(()) },
-            MacosCaptureStreamInternal::Display(stream) => stream.stop().map_err(|_| StreamStopError::Other("Unkown".into())),
+        let (MacosCaptureStreamInternal::SCStream(stream), Some(updatable_config)) = (&mut self.stream, &self.sc_stream_config) else {
+            return Err(StreamUpdateError::Unsupported);
+        };
+        let filter = sc_content_filter_for_target(&target, &updatable_config.excluded_windows, &updatable_config.excluded_applications)
+            .map_err(StreamUpdateError::Other)?;
+        // The application-only audio stream follows the application of the captured window
+        let audio_filter = match (&self.audio_stream, &target) {
+            (None, _) => None,
+            (Some(_), Capturable::Window(window)) => Some(sc_application_audio_filter_for_window(&window.impl_capturable_window.window)
+                .map_err(|error| match error {
+                    StreamCreateError::Platform(message, platform_code) => StreamUpdateError::Platform(message, platform_code),
+                    error => StreamUpdateError::Other(error.to_string()),
+                })?),
+            (Some(_), Capturable::Display(_)) => return Err(StreamUpdateError::Unsupported),
+        };
+        // Frames delivered while the filter is swapped may be of either target, so they're dropped
+        self.updating_target_flag.store(true, atomic::Ordering::Release);
+        let result = update_sc_stream_content_filter(stream, filter);
+        self.updating_target_flag.store(false, atomic::Ordering::Release);
+        result?;
+        if let (Some(audio_stream), Some(audio_filter)) = (&mut self.audio_stream, audio_filter) {
+            update_sc_stream_content_filter(audio_stream, audio_filter)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn update_config(&mut self, capture_config: &CaptureConfig) -> Result<(), StreamUpdateError> {
+        if self.stopped_flag.load(atomic::Ordering::Acquire) {
+            return Err(StreamUpdateError::AlreadyStopped);
+        }
+        let (MacosCaptureStreamInternal::SCStream(stream), Some(updatable_config)) = (&mut self.stream, &mut self.sc_stream_config) else {
+            return Err(StreamUpdateError::Unsupported);
+        };
+        // The power profile can't change, so it's applied to the new maximum frame rate the same way as when the stream was created
+        let power_profile = self.effective_config.power_profile;
+        let configured_interval = maximum_fps_interval(capture_config.maximum_fps).map(Duration::from_secs_f64);
+        let frame_interval = power_profile.frame_interval(configured_interval).map(|interval| interval.as_secs_f64());
+        let sc_frame_interval = frame_interval.unwrap_or(1.0 / 120.0).min(MAX_SC_STREAM_FRAME_INTERVAL);
+        updatable_config.config.set_minimum_time_interval(CMTime::new_with_seconds(sc_frame_interval, FRAME_INTERVAL_TIMESCALE));
+        let output_size = capture_config.cap_size(capture_config.output_size);
+        if !updatable_config.native_size {
+            updatable_config.full_size = CGSize {
+                x: output_size.width,
+                y: output_size.height,
+            };
+            updatable_config.config.set_scales_to_fit(updatable_config.scale_to_fit || capture_config.max_dimensions.is_some());
+        }
+        let buffer_count = power_profile.buffer_count(capture_config.buffer_count);
+        updatable_config.config.set_queue_depth(buffer_count as isize);
+        updatable_config.apply_source_rect();
+        update_sc_stream_configuration(stream, &updatable_config.config)
+            .map_err(|error| match error {
+                Some(error) => StreamUpdateError::Platform(format!("Failed to update stream configuration: [description: {}, reason: {}]", error.description(), error.reason()), PlatformErrorCode::NSError { domain: error.domain(), code: error.code() }),
+                None => StreamUpdateError::Other("Stream configuration update completion was never called".into()),
+            })?;
+        {
+            let mut live_settings = updatable_config.live_settings.lock();
+            live_settings.frame_interval_limiter = new_frame_interval_limiter(frame_interval.filter(|interval| *interval > MAX_SC_STREAM_FRAME_INTERVAL));
+            live_settings.output_placement = live_settings.output_placement.map(|(scaling_quality, _)| (scaling_quality, ScalingQuality::placed_output_size(output_size)));
+        }
+        self.effective_config.minimum_frame_interval = frame_interval.map(Duration::from_secs_f64);
+        self.effective_config.buffer_count = buffer_count;
+        self.output_size = updatable_config.output_size();
+        Ok(())
+    }
+
+    pub(crate) fn set_source_rect(&mut self, source_rect: Option<Rect>, content_size: Size) -> Result<(), StreamError> {
+        let (MacosCaptureStreamInternal::SCStream(stream), Some(updatable_config)) = (&mut self.stream, &mut self.sc_stream_config) else {
+            return Err(StreamError::Other("The CGDisplayStream backend can't change the source rect of a running stream".into()));
+        };
+        updatable_config.source_rect = source_rect.map(|source_rect| (source_rect, content_size));
+        updatable_config.apply_source_rect();
+        update_sc_stream_configuration(stream, &updatable_config.config)
+            .map_err(|error| match error {
+                Some(error) => StreamError::Platform(format!("Failed to update stream configuration: [description: {}, reason: {}]", error.description(), error.reason()), PlatformErrorCode::NSError { domain: error.domain(), code: error.code() }),
+                None => StreamError::Other("Stream configuration update completion was never called".into()),
+            })?;
+        self.output_size = updatable_config.output_size();
+        Ok(())
+    }
+
+    pub(crate) fn effective_config(&self) -> EffectiveCaptureConfig {
+        self.effective_config.clone()
+    }
+
+    pub(crate) fn dropped_frame_count(&self) -> u64 {
+        self.dropped_frames.load(atomic::Ordering::Relaxed)
+    }
+
+    pub(crate) fn output_size(&self) -> Size {
+        self.output_size
+    }
+
+    pub(crate) fn backend_name(&self) -> &'static str {
+        match &self.stream {
+            MacosCaptureStreamInternal::SCStream(_) => SC_STREAM_BACKEND_NAME,
+            MacosCaptureStreamInternal::CGDisplayStream(..) => CG_DISPLAY_STREAM_BACKEND_NAME,
         }
     }
 }
@@ -502,3 +1536,30 @@ impl Drop for MacosCaptureStream {
         self.stop();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::StreamEndReason;
+
+    use super::{display_was_removed, stream_end_reason_from_error_code, K_CG_DISPLAY_REMOVE_FLAG, SC_STREAM_ERROR_NO_CAPTURE_SOURCE, SC_STREAM_ERROR_NO_DISPLAY_LIST, SC_STREAM_ERROR_NO_WINDOW_LIST, SC_STREAM_ERROR_USER_STOPPED};
+
+    #[test]
+    fn streams_stopped_for_a_missing_target_end_with_target_gone() {
+        for code in [SC_STREAM_ERROR_NO_WINDOW_LIST, SC_STREAM_ERROR_NO_DISPLAY_LIST, SC_STREAM_ERROR_NO_CAPTURE_SOURCE] {
+            assert!(matches!(stream_end_reason_from_error_code(code, String::new), StreamEndReason::TargetGone), "Expected code {} to end with TargetGone", code);
+        }
+        assert!(matches!(stream_end_reason_from_error_code(SC_STREAM_ERROR_USER_STOPPED, String::new), StreamEndReason::Stopped));
+        match stream_end_reason_from_error_code(-3801, || "test error".to_string()) {
+            StreamEndReason::Other(message) => assert!(message.contains("test error")),
+            other => panic!("Expected an unknown code to end with Other, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn only_removing_the_captured_display_ends_the_stream() {
+        // kCGDisplayAddFlag and kCGDisplayMovedFlag, for the captured display
+        assert!(!display_was_removed(1, 1, (1 << 4) | (1 << 1)));
+        assert!(!display_was_removed(1, 2, K_CG_DISPLAY_REMOVE_FLAG));
+        assert!(display_was_removed(1, 1, K_CG_DISPLAY_REMOVE_FLAG | (1 << 1)));
+    }
+}
@@ -0,0 +1,30 @@
+use crate::environment::{PlatformApiAvailability, PlatformEnvironment};
+
+use super::capture_stream::{has_system_settings, CG_DISPLAY_STREAM_BACKEND_NAME, SC_STREAM_BACKEND_NAME};
+use super::objc_wrap::{NSProcessInfo, SCContentSharingPicker, SCScreenshotManager, SCStream, SCStreamConfiguration};
+
+pub(crate) fn probe_environment() -> PlatformEnvironment {
+    let sc_stream_available = SCStream::class_exists();
+    // Mirrors the automatic display capture backend selection in MacosCaptureStream::new
+    let capture_backend = if sc_stream_available {
+        SC_STREAM_BACKEND_NAME
+    } else {
+        CG_DISPLAY_STREAM_BACKEND_NAME
+    };
+    #[cfg(feature = "metal")]
+    let gpu_adapter = metal::Device::system_default().map(|device| device.name().to_string());
+    #[cfg(not(feature = "metal"))]
+    let gpu_adapter = None;
+    PlatformEnvironment {
+        os_version: Some(NSProcessInfo::operating_system_version_string()),
+        capture_backend: Some(capture_backend),
+        gpu_adapter,
+        platform_apis: vec![
+            PlatformApiAvailability { name: "SCStream", available: sc_stream_available },
+            PlatformApiAvailability { name: "SCScreenshotManager", available: SCScreenshotManager::class_exists() },
+            PlatformApiAvailability { name: "SCContentSharingPicker", available: SCContentSharingPicker::class_exists() },
+            PlatformApiAvailability { name: "SCStreamConfiguration.captureResolution", available: SCStreamConfiguration::supports_resolution_type() },
+            PlatformApiAvailability { name: "System Settings", available: has_system_settings() },
+        ],
+    }
+}
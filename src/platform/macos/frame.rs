@@ -2,15 +2,31 @@ use std::{cell::{Ref, RefCell}, marker::PhantomData, sync::Arc, time::{Duration,
 
 use objc2::runtime::AnyObject;
 
-use crate::{frame::{AudioCaptureFrame, VideoCaptureFrame}, prelude::{AudioBufferError, AudioChannelCount, AudioChannelData, AudioChannelDataSamples, AudioSampleRate, Point}, util::{Rect, Size}};
+use crate::{frame::{display_stream_content_rect, display_stream_dpi, AudioCaptureFrame, ColorSpace, CursorImage, PlaneData, VideoCaptureFrame, VideoFrameMapError}, prelude::{AudioBufferError, CapturePixelFormat, AudioChannelCount, AudioChannelData, AudioChannelDataSamples, AudioSampleRate, Point, ScalingQuality}, util::{Rect, Size}};
 
-use super::objc_wrap::{kAudioFormatFlagIsBigEndian, kAudioFormatFlagIsPacked, kAudioFormatFlagsCanonical, kAudioFormatNativeEndian, AVAudioFormat, AVAudioPCMBuffer, AudioBufferList, AudioStreamBasicDescription, CFDictionary, CGRect, CGRectMakeWithDictionaryRepresentation, CMBlockBuffer, CMSampleBuffer, IOSurface, NSDictionary, NSNumber, NSScreen, SCStreamFrameInfoBoundingRect, SCStreamFrameInfoContentRect, SCStreamFrameInfoContentScale, SCStreamFrameInfoScaleFactor, SCStreamFrameInfoScreenRect};
+use super::capture_stream::MacosColorMatrix;
+use super::objc_wrap::{kAudioFormatFlagIsBigEndian, kAudioFormatFlagIsNonInterleaved, kAudioFormatFlagIsPacked, kAudioFormatFlagsCanonical, kAudioFormatNativeEndian, AVAudioFormat, AVAudioPCMBuffer, AudioBufferListError, AudioBufferListStorage, AudioStreamBasicDescription, CFDictionary, CGPoint, CGRect, CGRectMakeWithDictionaryRepresentation, CMBlockBuffer, CMSampleBuffer, CVColorPrimaries, CVPixelFormat, CVTransferFunction, IOSurface, NSArray, NSCursor, NSDictionary, NSNumber, NSScreen, SCStreamFrameInfoBoundingRect, SCStreamFrameInfoContentRect, SCStreamFrameInfoContentScale, SCStreamFrameInfoDirtyRects, SCStreamFrameInfoScaleFactor, SCStreamFrameInfoScreenRect};
 
 pub(crate) struct MacosSCStreamVideoFrame {
     pub(crate) sample_buffer: CMSampleBuffer,
     pub(crate) capture_time: Instant,
+    // Relative to the stream's first delivered frame
+    pub(crate) origin_time: Duration,
+    // The time since the stream's previous delivered frame
+    pub(crate) duration: Duration,
     pub(crate) dictionary: RefCell<Option<CFDictionary>>,
     pub(crate) frame_id: u64,
+    // With a scaling quality other than smooth, the frame holds the content at its native size,
+    // and it's placed into an output of this size when copying bitmaps
+    pub(crate) output_placement: Option<(ScalingQuality, Size)>,
+    // The cursor as the frame arrived, in global display coordinates
+    pub(crate) cursor_location: Option<CGPoint>,
+    pub(crate) cursor_visible: bool,
+    pub(crate) cursor: Option<NSCursor>,
+    // The regions that changed since the previous delivered frame, in frame pixels
+    pub(crate) dirty_rects: Option<Vec<Rect>>,
+    // The matrix the stream was configured to convert YCbCr content with
+    pub(crate) color_matrix: MacosColorMatrix,
     #[cfg(feature = "metal")]
     pub(crate) metal_device: Option<metal::Device>,
     #[cfg(feature = "wgpu")]
@@ -23,8 +39,19 @@ pub(crate) struct MacosCGDisplayStreamVideoFrame {
     pub(crate) capture_time: Duration,
     pub(crate) capture_timestamp: Instant,
     pub(crate) frame_id: u64,
+    // The captured display, and the part of it captured in global display coordinates (points)
+    pub(crate) display_id: u32,
     pub(crate) source_rect: Rect,
+    // The size of the frame's surface, in pixels
     pub(crate) dest_size: Size,
+    // The cursor as the frame arrived, in global display coordinates
+    pub(crate) cursor_location: Option<CGPoint>,
+    pub(crate) cursor_visible: bool,
+    pub(crate) cursor: Option<NSCursor>,
+    // The regions that changed since the previous delivered frame, in frame pixels
+    pub(crate) dirty_rects: Option<Vec<Rect>>,
+    // The matrix the stream was configured to convert YCbCr content with
+    pub(crate) color_matrix: MacosColorMatrix,
     #[cfg(feature = "metal")]
     pub(crate) metal_device: metal::Device,
     #[cfg(feature = "wgpu")]
@@ -42,6 +69,43 @@ impl MacosSCStreamVideoFrame {
     }
 }
 
+// Sample buffers can carry an invalid presentation timestamp, which is treated as zero
+pub(crate) fn sample_buffer_presentation_time(sample_buffer: &CMSampleBuffer) -> Duration {
+    Duration::try_from_secs_f64(sample_buffer.get_presentation_timestamp().seconds_f64()).unwrap_or_default()
+}
+
+pub(crate) fn rect_from_cg_rect(rect: CGRect) -> Rect {
+    Rect {
+        origin: Point { x: rect.origin.x, y: rect.origin.y },
+        size: Size { width: rect.size.x, height: rect.size.y },
+    }
+}
+
+// The regions of a ScreenCaptureKit frame that changed since the previous frame, in frame pixels
+pub(crate) fn sc_stream_frame_dirty_rects(info_dict: &CFDictionary) -> Option<Vec<Rect>> {
+    let dirty_rects_ptr = unsafe { info_dict.get_value(SCStreamFrameInfoDirtyRects) };
+    if dirty_rects_ptr.is_null() {
+        return None;
+    }
+    let dirty_rects_array = NSArray::from_id_unretained(dirty_rects_ptr as *mut AnyObject);
+    Some((0..dirty_rects_array.count()).map(|i| {
+        let rect_dict = unsafe { NSDictionary::from_id_unretained(dirty_rects_array.obj_at_index::<*mut AnyObject>(i)) };
+        rect_from_cg_rect(CGRect::create_from_dictionary_representation(&rect_dict))
+    }).collect())
+}
+
+// Frames without tagged color primaries are treated as sRGB, and a missing transfer function is assumed to be sRGB's
+fn color_space_from_attachments(primaries: Option<CVColorPrimaries>, transfer_function: Option<CVTransferFunction>) -> ColorSpace {
+    match (primaries, transfer_function) {
+        (None, _) => ColorSpace::Srgb,
+        (Some(CVColorPrimaries::Rec709), None | Some(CVTransferFunction::Rec709 | CVTransferFunction::Srgb)) => ColorSpace::Srgb,
+        (Some(CVColorPrimaries::P3D65), None | Some(CVTransferFunction::Rec709 | CVTransferFunction::Srgb)) => ColorSpace::DisplayP3,
+        (Some(CVColorPrimaries::Rec2020), Some(CVTransferFunction::Pq)) => ColorSpace::Rec2020Pq,
+        (Some(CVColorPrimaries::Rec2020), Some(CVTransferFunction::Hlg)) => ColorSpace::Rec2020Hlg,
+        _ => ColorSpace::Unknown,
+    }
+}
+
 pub(crate) enum MacosVideoFrame {
     SCStream(MacosSCStreamVideoFrame),
     CGDisplayStream(MacosCGDisplayStreamVideoFrame),
@@ -50,6 +114,7 @@ pub(crate) enum MacosVideoFrame {
 impl VideoCaptureFrame for MacosVideoFrame {
     fn size(&self) -> Size {
         match self {
+            MacosVideoFrame::SCStream(MacosSCStreamVideoFrame { output_placement: Some((_, output_size)), .. }) => *output_size,
             MacosVideoFrame::SCStream(sc_frame) => {
                 sc_frame.sample_buffer.get_image_buffer().map(|image_buffer| {
                     Size {
@@ -81,20 +146,27 @@ impl VideoCaptureFrame for MacosVideoFrame {
                 }
                 dpi
             },
-            MacosVideoFrame::CGDisplayStream(cgd_frame) => todo!()
+            MacosVideoFrame::CGDisplayStream(cgd_frame) => {
+                let screen = NSScreen::screens().into_iter()
+                    .find(|screen| screen.display_id() == Some(cgd_frame.display_id));
+                match screen {
+                    Some(screen) => display_stream_dpi(screen.dpi(), screen.backing_scale_factor(), cgd_frame.source_rect.size, self.content_rect().size),
+                    None => 72.0,
+                }
+            },
         }
     }
 
     fn duration(&self) -> Duration {
         match self {
-            MacosVideoFrame::SCStream(sc_frame) => std::time::Duration::from_secs_f64(sc_frame.sample_buffer.get_duration().seconds_f64()),
+            MacosVideoFrame::SCStream(sc_frame) => sc_frame.duration,
             MacosVideoFrame::CGDisplayStream(cgd_frame) => cgd_frame.duration
         }
     }
 
     fn origin_time(&self) -> Duration {
         match self {
-            MacosVideoFrame::SCStream(sc_frame) => std::time::Duration::from_secs_f64(sc_frame.sample_buffer.get_presentation_timestamp().seconds_f64()),
+            MacosVideoFrame::SCStream(sc_frame) => sc_frame.origin_time,
             MacosVideoFrame::CGDisplayStream(cgd_frame) => cgd_frame.capture_time
         }
     }
@@ -115,6 +187,10 @@ impl VideoCaptureFrame for MacosVideoFrame {
 
     fn content_rect(&self) -> Rect {
         match self {
+            // Content placed into the output covers the same rect as in its bitmaps
+            MacosVideoFrame::SCStream(MacosSCStreamVideoFrame { output_placement: Some((scaling_quality, output_size)), .. }) => {
+                scaling_quality.content_rect(self.native_content_size(), *output_size)
+            },
             MacosVideoFrame::SCStream(sc_frame) => {
                 let info_dict = sc_frame.get_info_dict();
                 let content_rect_ptr = unsafe { info_dict.get_value(SCStreamFrameInfoContentRect) };
@@ -133,11 +209,145 @@ impl VideoCaptureFrame for MacosVideoFrame {
                     }
                 }
             },
-            MacosVideoFrame::CGDisplayStream(sc_fame) => {
-                todo!()
-            }
+            MacosVideoFrame::CGDisplayStream(cgd_frame) => display_stream_content_rect(cgd_frame.source_rect.size, cgd_frame.dest_size),
+        }
+    }
+
+    fn native_content_size(&self) -> Size {
+        match self {
+            MacosVideoFrame::SCStream(sc_frame) => {
+                let info_dict = sc_frame.get_info_dict();
+                let content_rect_ptr = unsafe { info_dict.get_value(SCStreamFrameInfoContentRect) };
+                let content_scale_ptr = unsafe { info_dict.get_value(SCStreamFrameInfoContentScale) };
+                let scale_factor_ptr = unsafe { info_dict.get_value(SCStreamFrameInfoScaleFactor) };
+                let content_rect_dict = unsafe { NSDictionary::from_id_unretained(content_rect_ptr as *mut AnyObject) };
+                let content_scale = unsafe { NSNumber::from_id_unretained(content_scale_ptr as *mut AnyObject).as_f64() };
+                let scale_factor = unsafe { NSNumber::from_id_unretained(scale_factor_ptr as *mut AnyObject).as_f64() };
+                let frame_content_rect = unsafe { CGRect::create_from_dictionary_representation(&content_rect_dict) };
+                // The content scale is how much the content was shrunk to fit into the frame
+                let content_scale = if content_scale > 0.0 { content_scale } else { 1.0 };
+                Size {
+                    width: frame_content_rect.size.x * scale_factor / content_scale,
+                    height: frame_content_rect.size.y * scale_factor / content_scale,
+                }
+            },
+            MacosVideoFrame::CGDisplayStream(cgd_frame) => cgd_frame.source_rect.size,
+        }
+    }
+
+    fn cursor_position(&self) -> Option<Point> {
+        match self {
+            MacosVideoFrame::SCStream(sc_frame) => {
+                let cursor_location = sc_frame.cursor_location?;
+                let screen_rect = {
+                    let info_dict = sc_frame.get_info_dict();
+                    let screen_rect_ptr = unsafe { info_dict.get_value(SCStreamFrameInfoScreenRect) };
+                    if screen_rect_ptr.is_null() {
+                        return None;
+                    }
+                    let screen_rect_dict = unsafe { NSDictionary::from_id_unretained(screen_rect_ptr as *mut AnyObject) };
+                    unsafe { CGRect::create_from_dictionary_representation(&screen_rect_dict) }
+                };
+                // The screen rect is in points, while the content rect is in frame pixels
+                let screen_rect = Rect {
+                    origin: Point { x: screen_rect.origin.x, y: screen_rect.origin.y },
+                    size: Size { width: screen_rect.size.x, height: screen_rect.size.y },
+                };
+                screen_rect.map_point_to(Point { x: cursor_location.x, y: cursor_location.y }, &self.content_rect())
+            },
+            MacosVideoFrame::CGDisplayStream(cgd_frame) => {
+                let cursor_location = cgd_frame.cursor_location?;
+                cgd_frame.source_rect.map_point_to(Point { x: cursor_location.x, y: cursor_location.y }, &self.content_rect())
+            },
+        }
+    }
+
+    fn cursor_visible(&self) -> bool {
+        match self {
+            MacosVideoFrame::SCStream(sc_frame) => sc_frame.cursor_visible,
+            MacosVideoFrame::CGDisplayStream(cgd_frame) => cgd_frame.cursor_visible,
+        }
+    }
+
+    fn cursor_image(&self) -> Option<Arc<CursorImage>> {
+        let cursor = match self {
+            MacosVideoFrame::SCStream(sc_frame) => sc_frame.cursor.as_ref(),
+            MacosVideoFrame::CGDisplayStream(cgd_frame) => cgd_frame.cursor.as_ref(),
+        }?;
+        let (data, width, height, hotspot) = cursor.image_bgra()?;
+        Some(Arc::new(CursorImage {
+            data,
+            width,
+            height,
+            hotspot: Point { x: hotspot.x, y: hotspot.y },
+        }))
+    }
+
+    fn dirty_rects(&self) -> Option<Vec<Rect>> {
+        match self {
+            MacosVideoFrame::SCStream(sc_frame) => sc_frame.dirty_rects.clone(),
+            MacosVideoFrame::CGDisplayStream(cgd_frame) => cgd_frame.dirty_rects.clone(),
+        }
+    }
+
+    fn color_space(&self) -> ColorSpace {
+        match self {
+            MacosVideoFrame::SCStream(sc_frame) => match sc_frame.sample_buffer.get_image_buffer() {
+                Some(image_buffer) => color_space_from_attachments(image_buffer.get_color_primaries(), image_buffer.get_transfer_function()),
+                None => ColorSpace::Srgb,
+            },
+            // CGDisplayStream surfaces aren't tagged with their color space
+            MacosVideoFrame::CGDisplayStream(_) => ColorSpace::Srgb,
         }
     }
+
+    fn map_planes<T>(&self, map: impl FnOnce(&[PlaneData<'_>]) -> T) -> Result<T, VideoFrameMapError> {
+        let iosurface = match self {
+            MacosVideoFrame::SCStream(sc_frame) => sc_frame.sample_buffer.get_image_buffer().and_then(|image_buffer| image_buffer.get_iosurface())
+                .ok_or(VideoFrameMapError::Other("Failed to get iosurface".to_string()))?,
+            MacosVideoFrame::CGDisplayStream(cgd_frame) => cgd_frame.io_surface.clone(),
+        };
+        let lock_gaurd = iosurface.lock(true, false)
+            .map_err(|_| VideoFrameMapError::Other("Failed to lock iosurface".to_string()))?;
+        let base_address_error = || VideoFrameMapError::Other("Failed to get base address of iosurface".to_string());
+        // The planes borrow the surface's memory, which stays mapped until the lock guard is dropped after `map` returns
+        let planes = match iosurface.get_pixel_format() {
+            Some(pixel_format @ (CVPixelFormat::BGRA8888 | CVPixelFormat::ARGB2101010LE)) => {
+                let base_address = lock_gaurd.get_base_address().ok_or_else(base_address_error)?;
+                let bytes_per_row = iosurface.get_bytes_per_row();
+                let height = iosurface.get_height();
+                vec![PlaneData {
+                    data: unsafe { std::slice::from_raw_parts(base_address as *const u8, bytes_per_row * height) },
+                    width: iosurface.get_width(),
+                    height,
+                    bytes_per_row,
+                    format: if pixel_format == CVPixelFormat::BGRA8888 { CapturePixelFormat::Bgra8888 } else { CapturePixelFormat::Argb2101010 },
+                }]
+            },
+            Some(pixel_format @ (CVPixelFormat::V420 | CVPixelFormat::F420)) => {
+                let format = if pixel_format == CVPixelFormat::V420 { CapturePixelFormat::V420 } else { CapturePixelFormat::F420 };
+                let mut planes = Vec::with_capacity(2);
+                for plane in 0..2 {
+                    let base_address = lock_gaurd.get_base_address_of_plane(plane).ok_or_else(base_address_error)?;
+                    let bytes_per_row = iosurface.get_bytes_per_row_of_plane(plane);
+                    let height = iosurface.get_height_of_plane(plane);
+                    planes.push(PlaneData {
+                        data: unsafe { std::slice::from_raw_parts(base_address as *const u8, bytes_per_row * height) },
+                        width: iosurface.get_width_of_plane(plane),
+                        height,
+                        bytes_per_row,
+                        format,
+                    });
+                }
+                planes
+            },
+            _ => return Err(VideoFrameMapError::UnsupportedPixelFormat),
+        };
+        let result = map(&planes);
+        drop(planes);
+        drop(lock_gaurd);
+        Ok(result)
+    }
 }
 
 pub struct MacosAudioFrame {
@@ -145,11 +355,47 @@ pub struct MacosAudioFrame {
     pub(crate) audio_format_description: AudioStreamBasicDescription,
     pub(crate) pcm_audio_buffer: Option<AVAudioPCMBuffer>,
     pub(crate) block_buffer: Option<CMBlockBuffer>,
-    pub(crate) buffer_list: Option<AudioBufferList>,
+    pub(crate) buffer_list: Option<AudioBufferListStorage>,
     pub(crate) capture_time: Instant,
     pub(crate) frame_id: u64,
 }
 
+impl MacosAudioFrame {
+    pub(crate) fn new(sample_buffer: CMSampleBuffer, capture_time: Instant, frame_id: u64) -> Result<Self, AudioBufferError> {
+        let audio_format_description = sample_buffer.get_format_description()
+            .as_audio_format_description()
+            .map(|audio_format_description| *audio_format_description.get_basic_stream_description())
+            .ok_or(AudioBufferError::Other("CMSampleBuffer had no audio format description".into()))?;
+        let mut audio_frame = Self {
+            sample_buffer,
+            audio_format_description,
+            pcm_audio_buffer: None,
+            block_buffer: None,
+            buffer_list: None,
+            capture_time,
+            frame_id,
+        };
+        audio_frame.retain_audio_buffer_list()?;
+        Ok(audio_frame)
+    }
+
+    // Get the audio buffer list of the sample buffer, retaining its block buffer so the samples stay valid for the life of the frame
+    fn retain_audio_buffer_list(&mut self) -> Result<(), AudioBufferError> {
+        if self.buffer_list.is_some() {
+            return Ok(());
+        }
+        let (audio_buffer_list, block_buffer) = match unsafe { self.sample_buffer.get_audio_buffer_list_with_block_buffer() } {
+            Ok(x) => x,
+            Err(AudioBufferListError::ArrayTooSmall) => return Err(AudioBufferError::Other("CMSampleBuffer audio buffer list didn't fit in the allocated storage".into())),
+            Err(AudioBufferListError::NoBlockBuffer) => return Err(AudioBufferError::Other("CMSampleBuffer audio buffer list had no block buffer".into())),
+            Err(AudioBufferListError::Other(status)) => return Err(AudioBufferError::Other(format!("CMSampleBuffer::get_audio_buffer_list_with_block_buffer() failed with status {}", status))),
+        };
+        self.buffer_list = Some(audio_buffer_list);
+        self.block_buffer = Some(block_buffer);
+        Ok(())
+    }
+}
+
 impl AudioCaptureFrame for MacosAudioFrame {
     fn sample_rate(&self) -> crate::prelude::AudioSampleRate {
         if self.audio_format_description.sample_rate >= 15500.0 && self.audio_format_description.sample_rate <= 16500.0 {
@@ -172,42 +418,39 @@ impl AudioCaptureFrame for MacosAudioFrame {
     }
 
     fn audio_channel_buffer(&mut self, channel: usize) -> Result<AudioChannelData<'_>, AudioBufferError> {
-        let pcm_audio_buffer_ref = if self.pcm_audio_buffer.is_some() {
-            self.pcm_audio_buffer.as_ref().unwrap()
-        } else {
-            if self.audio_format_description.format_flags == kAudioFormatFlagsCanonical {
-                let (audio_buffer_list, block_buffer) = match unsafe { self.sample_buffer.get_audio_buffer_list_with_block_buffer() } {
-                    Ok(x) => x,
-                    Err(()) => return Err(AudioBufferError::Other("CMSampleBuffer::get_audio_buffer_list_with_block_buffer() failed".into()))
-                };
-                self.buffer_list = Some(audio_buffer_list);
-                self.block_buffer = Some(block_buffer);
+        if self.pcm_audio_buffer.is_none() {
+            // ScreenCaptureKit delivers multichannel audio non-interleaved - the buffer list handles either layout
+            if (self.audio_format_description.format_flags & !kAudioFormatFlagIsNonInterleaved) == kAudioFormatFlagsCanonical {
+                self.retain_audio_buffer_list()?;
                 let audio_buffer_list = self.buffer_list.as_ref().unwrap();
                 let av_audio_format = AVAudioFormat::new_with_standard_format_sample_rate_channels(self.audio_format_description.sample_rate, self.audio_format_description.channels_per_frame);
-                if let Ok(pcm_audio_buffer) = AVAudioPCMBuffer::new_with_format_buffer_list_no_copy_deallocator(av_audio_format, audio_buffer_list as *const _) {
-                    self.pcm_audio_buffer = Some(pcm_audio_buffer);
-                    self.pcm_audio_buffer.as_ref().unwrap()
-                } else {
-                    return Err(AudioBufferError::Other("Failed to build PCM audio buffer".into()));
+                // Building the PCM buffer checks that the buffer list actually matches the stream format
+                match AVAudioPCMBuffer::new_with_format_buffer_list_no_copy_deallocator(av_audio_format, audio_buffer_list.as_ptr()) {
+                    Ok(pcm_audio_buffer) => self.pcm_audio_buffer = Some(pcm_audio_buffer),
+                    Err(()) => return Err(AudioBufferError::Other("Failed to build PCM audio buffer".into())),
                 }
             } else {
                 return Err(AudioBufferError::UnsupportedFormat);
             }
+        }
+        let Some(audio_buffer_list) = self.buffer_list.as_ref() else {
+            return Err(AudioBufferError::Other("Failed to get audio buffer".into()));
         };
-        if channel >= pcm_audio_buffer_ref.channel_count() {
+        let Some((audio_buffer, channel_offset)) = audio_buffer_list.channel_buffer(channel) else {
             return Err(AudioBufferError::InvalidChannel);
-        }
-        let stride = pcm_audio_buffer_ref.stride();
-        if let Some(f32_ptr) = pcm_audio_buffer_ref.f32_buffer(channel) {
-            let data_samples = AudioChannelDataSamples {
-                data: f32_ptr as *const u8,
-                stride,
-                length: pcm_audio_buffer_ref.frame_capacity(),
-                phantom_lifetime: PhantomData
-            };
-            return Ok(AudioChannelData::F32(data_samples));
-        }
-        return Err(AudioBufferError::Other("Failed to get audio buffer".into()))
+        };
+        // Lengths come from the buffer itself, so interleaved and non-interleaved layouts are both handled
+        let sample_size = std::mem::size_of::<f32>();
+        let stride = sample_size * audio_buffer.number_channels();
+        let data = audio_buffer.data();
+        let length = data.len() / stride;
+        let data_samples = AudioChannelDataSamples {
+            data: if length > 0 { data[channel_offset * sample_size..].as_ptr() } else { data.as_ptr() },
+            stride,
+            length,
+            phantom_lifetime: PhantomData
+        };
+        Ok(AudioChannelData::F32(data_samples))
     }
 
     fn duration(&self) -> std::time::Duration {
@@ -218,6 +461,14 @@ impl AudioCaptureFrame for MacosAudioFrame {
         std::time::Duration::from_secs_f64(self.sample_buffer.get_presentation_timestamp().seconds_f64())
     }
 
+    fn silent(&self) -> bool {
+        false
+    }
+
+    fn discontinuity(&self) -> bool {
+        false
+    }
+
     fn frame_id(&self) -> u64 {
         self.frame_id
     }
@@ -0,0 +1,152 @@
+#![cfg(feature = "bitmap")]
+
+use std::os::raw::c_void;
+
+use metal::foreign_types::ForeignType;
+use objc2::{class, msg_send, rc::autoreleasepool, runtime::AnyObject, Encode, Encoding, RefEncode};
+
+use crate::feature::metal::new_iosurface_texture;
+
+use super::objc_wrap::IOSurface;
+
+#[link(name = "MetalPerformanceShaders", kind = "framework")]
+extern "C" {}
+
+#[repr(C)]
+struct MPSScaleTransform {
+    scale_x: f64,
+    scale_y: f64,
+    translate_x: f64,
+    translate_y: f64,
+}
+
+unsafe impl Encode for MPSScaleTransform {
+    const ENCODING: Encoding = Encoding::Struct("MPSScaleTransform", &[Encoding::Double, Encoding::Double, Encoding::Double, Encoding::Double]);
+}
+
+unsafe impl RefEncode for MPSScaleTransform {
+    const ENCODING_REF: Encoding = Encoding::Pointer(&Self::ENCODING);
+}
+
+// A plane of an IOSurface to scale: the plane's index, metal pixel format and size, the size of the content in its top-left, and the size to scale that content to
+#[derive(Copy, Clone)]
+pub(crate) struct MacosFrameScalerPlane {
+    pub(crate) plane: usize,
+    pub(crate) pixel_format: metal::MTLPixelFormat,
+    pub(crate) bytes_per_pixel: usize,
+    pub(crate) plane_size: (usize, usize),
+    pub(crate) source_size: (usize, usize),
+    pub(crate) target_size: (usize, usize),
+}
+
+// A scaled plane, read back into shared memory with tightly packed rows
+pub(crate) struct MacosScaledPlane {
+    buffer: metal::Buffer,
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) bytes_per_row: usize,
+}
+
+impl MacosScaledPlane {
+    pub(crate) fn contents(&self) -> *const c_void {
+        self.buffer.contents() as *const c_void
+    }
+}
+
+// Scales the planes of captured IOSurfaces on the GPU with Metal Performance Shaders, since reading back full size frames
+// to downscale them on the CPU costs far more than the downscale itself
+pub(crate) struct MacosFrameScaler {
+    device: metal::Device,
+    command_queue: metal::CommandQueue,
+    lanczos_scale: *mut AnyObject,
+}
+
+impl MacosFrameScaler {
+    pub(crate) fn new(device: metal::Device) -> Result<Self, String> {
+        let command_queue = device.new_command_queue();
+        unsafe {
+            let lanczos_scale: *mut AnyObject = msg_send![class!(MPSImageLanczosScale), alloc];
+            let lanczos_scale: *mut AnyObject = msg_send![lanczos_scale, initWithDevice: device.as_ptr() as *mut AnyObject];
+            if lanczos_scale.is_null() {
+                return Err("Failed to create MPSImageLanczosScale - the metal device may not support Metal Performance Shaders".to_string());
+            }
+            Ok(Self {
+                device,
+                command_queue,
+                lanczos_scale,
+            })
+        }
+    }
+
+    // Scale the content of each plane to its target size, in a single command buffer
+    pub(crate) fn scale(&self, iosurface: &IOSurface, planes: &[MacosFrameScalerPlane]) -> Result<Vec<MacosScaledPlane>, String> {
+        autoreleasepool(|_| {
+            let command_buffer = self.command_queue.new_command_buffer();
+            let mut scaled_planes = Vec::with_capacity(planes.len());
+            for plane in planes {
+                let source_texture = new_iosurface_texture(&self.device, iosurface, plane.plane, plane.pixel_format, plane.plane_size, metal::MTLTextureUsage::ShaderRead)?;
+                let (width, height) = (plane.target_size.0.max(1), plane.target_size.1.max(1));
+                let texture_descriptor = metal::TextureDescriptor::new();
+                texture_descriptor.set_texture_type(metal::MTLTextureType::D2);
+                texture_descriptor.set_pixel_format(plane.pixel_format);
+                texture_descriptor.set_width(width as u64);
+                texture_descriptor.set_height(height as u64);
+                texture_descriptor.set_mipmap_level_count(1);
+                texture_descriptor.set_storage_mode(metal::MTLStorageMode::Private);
+                texture_descriptor.set_usage(metal::MTLTextureUsage::ShaderRead | metal::MTLTextureUsage::ShaderWrite);
+                let scaled_texture = self.device.new_texture(&texture_descriptor);
+                // Only the content in the top-left of the plane is mapped onto the scaled texture
+                let transform = MPSScaleTransform {
+                    scale_x: width as f64 / plane.source_size.0.max(1) as f64,
+                    scale_y: height as f64 / plane.source_size.1.max(1) as f64,
+                    translate_x: 0.0,
+                    translate_y: 0.0,
+                };
+                unsafe {
+                    let _: () = msg_send![self.lanczos_scale, setScaleTransform: &transform as *const MPSScaleTransform];
+                    let _: () = msg_send![self.lanczos_scale,
+                        encodeToCommandBuffer: command_buffer.as_ptr() as *mut AnyObject,
+                        sourceTexture: source_texture.as_ptr() as *mut AnyObject,
+                        destinationTexture: scaled_texture.as_ptr() as *mut AnyObject
+                    ];
+                }
+                let bytes_per_row = width * plane.bytes_per_pixel;
+                let buffer = self.device.new_buffer((bytes_per_row * height) as u64, metal::MTLResourceOptions::StorageModeShared);
+                let blit_encoder = command_buffer.new_blit_command_encoder();
+                blit_encoder.copy_from_texture_to_buffer(
+                    &scaled_texture,
+                    0,
+                    0,
+                    metal::MTLOrigin { x: 0, y: 0, z: 0 },
+                    metal::MTLSize { width: width as u64, height: height as u64, depth: 1 },
+                    &buffer,
+                    0,
+                    bytes_per_row as u64,
+                    (bytes_per_row * height) as u64,
+                    metal::MTLBlitOption::empty()
+                );
+                blit_encoder.end_encoding();
+                scaled_planes.push(MacosScaledPlane {
+                    buffer,
+                    width,
+                    height,
+                    bytes_per_row,
+                });
+            }
+            command_buffer.commit();
+            command_buffer.wait_until_completed();
+            if command_buffer.status() == metal::MTLCommandBufferStatus::Error {
+                return Err("Failed to scale frame with Metal Performance Shaders".to_string());
+            }
+            Ok(scaled_planes)
+        })
+    }
+}
+
+impl Drop for MacosFrameScaler {
+    fn drop(&mut self) {
+        unsafe {
+            let _: () = msg_send![self.lanczos_scale, release];
+        }
+    }
+}
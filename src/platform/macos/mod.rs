@@ -2,15 +2,20 @@
 
 pub(crate) mod capture_stream;
 pub(crate) mod frame;
+pub(crate) mod frame_scaler;
 pub(crate) mod capturable_content;
 pub(crate) mod objc_wrap;
+pub(crate) mod environment;
 
 pub(crate) use capture_stream::MacosCaptureStream as ImplCaptureStream;
+pub(crate) use capture_stream::MacosStopHandle as ImplStopHandle;
 pub(crate) use capture_stream::MacosAudioCaptureConfig as ImplAudioCaptureConfig;
 pub(crate) use capture_stream::MacosCaptureConfig as ImplCaptureConfig;
 pub(crate) use capture_stream::MacosPixelFormat as ImplPixelFormat;
 pub(crate) use capture_stream::MacosCaptureAccessToken as ImplCaptureAccessToken;
 
+pub(crate) use environment::probe_environment;
+
 pub(crate) use frame::MacosAudioFrame as ImplAudioFrame;
 pub(crate) use frame::MacosVideoFrame as ImplVideoFrame;
 
@@ -19,6 +24,7 @@ pub(crate) use capturable_content::MacosCapturableWindow as ImplCapturableWindow
 pub(crate) use capturable_content::MacosCapturableDisplay as ImplCapturableDisplay;
 pub(crate) use capturable_content::MacosCapturableContentFilter as ImplCapturableContentFilter;
 pub(crate) use capturable_content::MacosCapturableApplication as ImplCapturableApplication;
+pub(crate) use capturable_content::MacosContentChangeNotifier as ImplContentChangeNotifier;
 
 /// Mac OS specific extensions for audio capture configs
 pub use capture_stream::MacosAudioCaptureConfigExt;
@@ -26,6 +32,10 @@ pub use capture_stream::MacosAudioCaptureConfigExt;
 pub use capture_stream::MacosCaptureConfigExt;
 /// Mac OS "resolution type"
 pub use capture_stream::MacosCaptureResolutionType;
+/// Mac OS display capture API selection
+pub use capture_stream::MacosDisplayCaptureBackend;
+/// Mac OS YCbCr color matrix selection
+pub use capture_stream::MacosColorMatrix;
 
 /// Mac OS specific extensions for capturable windows
 pub use capturable_content::MacosCapturableWindowExt;
@@ -13,14 +13,14 @@
 #[link(name = "AVFoundation", kind = "framework")]
 extern "C" {}
 
-use std::{cell::RefCell, collections::HashMap, ffi::CString, ops::{Add, Mul, Sub}, ptr::{addr_of_mut, null, null_mut, NonNull}, sync::Arc, time::{Duration, Instant}};
+use std::{any::Any, cell::RefCell, collections::HashMap, ffi::CString, ops::{Add, Mul, Sub}, panic::{catch_unwind, AssertUnwindSafe}, ptr::{addr_of_mut, null, null_mut, NonNull}, sync::Arc, time::{Duration, Instant}};
 
 use block2::{ffi::Class, Block, RcBlock, StackBlock};
 use libc::{c_void, strlen};
 use objc2::{class, declare::ClassBuilder, ffi::{objc_getClass, objc_getProtocol}, msg_send, rc::Id, runtime::{AnyClass, AnyObject, AnyProtocol, Bool, Ivar, Sel}, sel, Encode, Encoding, RefEncode};
 use mach2::mach_time::{mach_timebase_info, mach_timebase_info_data_t};
 
-use crate::{prelude::{AudioSampleRate, StreamCreateError, StreamError, StreamEvent, StreamStopError}};
+use crate::{capture_stream::{handle_callback_panic, CallbackPanic}, prelude::{AudioSampleRate, StreamCreateError, StreamError, StreamEvent, StreamStopError}};
 
 use lazy_static::lazy_static;
 use parking_lot::Mutex;
@@ -44,6 +44,9 @@ type CFBooleanRef = CFTypeRef;
 type CFNumberRef = CFTypeRef;
 type CVPixelBufferRef = CFTypeRef;
 type CGImageRef = CFTypeRef;
+type CGColorSpaceRef = CFTypeRef;
+type CGContextRef = CFTypeRef;
+type CGDisplayModeRef = CFTypeRef;
 type CGDataProviderRef = CFTypeRef;
 type CFDataRef = CFTypeRef;
 
@@ -61,6 +64,7 @@ extern "C" {
 
     fn CFRetain(x: CFTypeRef) -> CFTypeRef;
     fn CFRelease(x: CFTypeRef);
+    fn CFEqual(a: CFTypeRef, b: CFTypeRef) -> bool;
 
     pub(crate) static kCFBooleanTrue: CFBooleanRef;
     pub(crate) static kCFBooleanFalse: CFBooleanRef;
@@ -96,7 +100,6 @@ extern "C" {
     static kCMTimeNegativeInfinity: CMTime;
     static kCMTimeZero: CMTime;
 
-    fn CMSampleBufferCreateCopy(allocator: CFAllocatorRef, original: CMSampleBufferRef, new: *mut CMSampleBufferRef) -> OSStatus;
     fn CMSampleBufferIsValid(sbuf: CMSampleBufferRef) -> Bool;
     fn CMSampleBufferGetNumSamples(sbuf: CMSampleBufferRef) -> isize;
     fn CMSampleBufferGetPresentationTimeStamp(sbuf: CMSampleBufferRef) -> CMTime;
@@ -113,6 +116,7 @@ extern "C" {
     fn CVPixelBufferGetHeight(pixel_buffer: CVPixelBufferRef) -> usize;
     fn CVBufferRetain(buffer: CVPixelBufferRef) -> CVPixelBufferRef;
     fn CVBufferRelease(buffer: CVPixelBufferRef) -> CVPixelBufferRef;
+    fn CVBufferGetAttachment(buffer: CVPixelBufferRef, key: CFStringRef, attachment_mode: *mut u32) -> CFTypeRef;
 
     fn CFArrayCreateMutable(allocator: CFAllocatorRef, capacity: isize, callbacks: *const CFArrayCallBacks) -> CFArrayRef;
     fn CFArrayGetCount(array: CFArrayRef) -> i32;
@@ -130,18 +134,35 @@ extern "C" {
     fn CGDisplayStreamCreateWithDispatchQueue(display_id: u32, output_width: usize, output_height: usize, pixel_format: i32, properties: CFDictionaryRef, dispatch_queue: *mut AnyObject, handler: *const c_void) -> CGDisplayStreamRef;
     fn CGDisplayStreamStart(stream: CGDisplayStreamRef) -> i32;
     fn CGDisplayStreamStop(stream: CGDisplayStreamRef) -> i32;
+    fn CGDisplayStreamUpdateGetRects(update: CGDisplayStreamUpdateRef, rect_type: i32, rect_count: *mut usize) -> *const CGRect;
 
     pub(crate) fn CGMainDisplayID() -> u32;
     
     fn CGDisplayScreenSize(display: u32) -> CGSize;
 
+    pub(crate) fn CGDisplayIsInMirrorSet(display: u32) -> u32;
+    pub(crate) fn CGDisplayIsOnline(display: u32) -> u32;
+    fn CGDisplayRegisterReconfigurationCallback(callback: CGDisplayReconfigurationCallBack, user_info: *mut c_void) -> i32;
+    fn CGDisplayRemoveReconfigurationCallback(callback: CGDisplayReconfigurationCallBack, user_info: *mut c_void) -> i32;
+    pub(crate) fn CGDisplayPrimaryDisplay(display: u32) -> u32;
+
+    fn CGDisplayCopyDisplayMode(display: u32) -> CGDisplayModeRef;
+    fn CGDisplayModeRelease(mode: CGDisplayModeRef);
+    fn CGDisplayModeGetWidth(mode: CGDisplayModeRef) -> usize;
+    fn CGDisplayModeGetPixelWidth(mode: CGDisplayModeRef) -> usize;
+    fn CGDisplayModeGetPixelHeight(mode: CGDisplayModeRef) -> usize;
+    fn CGDisplayModeGetRefreshRate(mode: CGDisplayModeRef) -> f64;
+
     fn CGRectCreateDictionaryRepresentation(rect: CGRect) -> CFDictionaryRef;
 
     pub(crate) fn CGWindowListCreateImage(screen_bounds: CGRect, options: u32, window_id: u32, image_options: u32) -> CGImageRef;
 
     static kCGWindowLayer: CFStringRef;
+    static kCGWindowIsOnscreen: CFStringRef;
+    static kCGWindowNumber: CFStringRef;
 
     fn CGWindowListCreateDescriptionFromArray(window_array: CFArrayRef) -> CFArrayRef;
+    fn CGWindowListCopyWindowInfo(option: u32, relative_to_window: u32) -> CFArrayRef;
 
     fn CGImageRetain(image: CGImageRef);
     fn CGImageRelease(image: CGImageRef);
@@ -154,12 +175,22 @@ extern "C" {
     fn CGImageGetPixelFormatInfo(image: CGImageRef) -> u32;
     fn CGImageGetBitmapInfo(image: CGImageRef) -> u32;
 
+    fn CGColorSpaceCreateDeviceRGB() -> CGColorSpaceRef;
+    fn CGColorSpaceRelease(color_space: CGColorSpaceRef);
+    fn CGBitmapContextCreate(data: *mut c_void, width: usize, height: usize, bits_per_component: usize, bytes_per_row: usize, color_space: CGColorSpaceRef, bitmap_info: u32) -> CGContextRef;
+    fn CGContextDrawImage(context: CGContextRef, rect: CGRect, image: CGImageRef);
+    fn CGContextRelease(context: CGContextRef);
+
     fn CGDataProviderRetain(data_provider: CGDataProviderRef);
     fn CGDataProviderRelease(data_provider: CGDataProviderRef);
     fn CGDataProviderCopyData(data_provider: CGDataProviderRef) -> CFDataRef;
 
     fn CGWindowLevelForKey(key: i32) -> i32;
 
+    fn CGEventCreate(source: *const c_void) -> CFTypeRef;
+    fn CGEventGetLocation(event: CFTypeRef) -> CGPoint;
+    fn CGCursorIsVisible() -> u32;
+
     pub(crate) fn IOSurfaceIncrementUseCount(r: IOSurfaceRef);
     pub(crate) fn IOSurfaceDecrementUseCount(r: IOSurfaceRef);
 
@@ -193,6 +224,7 @@ extern "C" {
     static mut _dispatch_queue_attr_concurrent: c_void;
 
     fn dispatch_queue_create(label: *const std::ffi::c_char, attr: DispatchQueueAttr) -> DispatchQueue;
+    fn dispatch_queue_attr_make_with_qos_class(attr: DispatchQueueAttr, qos_class: u32, relative_priority: i32) -> DispatchQueueAttr;
     fn dispatch_retain(AnyObject: *mut AnyObject);
     fn dispatch_release(AnyObject: *mut AnyObject);
 
@@ -224,6 +256,22 @@ extern "C" {
     pub(crate) static CGRectInfinite : CGRect;
 
     pub(crate) static kIOSurfaceCacheMode: CFStringRef;
+
+    static kCVImageBufferColorPrimariesKey            : CFStringRef;
+    static kCVImageBufferColorPrimaries_ITU_R_709_2   : CFStringRef;
+    static kCVImageBufferColorPrimaries_P3_D65        : CFStringRef;
+    static kCVImageBufferColorPrimaries_ITU_R_2020    : CFStringRef;
+    static kCVImageBufferTransferFunctionKey          : CFStringRef;
+    static kCVImageBufferTransferFunction_ITU_R_709_2 : CFStringRef;
+    static kCVImageBufferTransferFunction_sRGB        : CFStringRef;
+    static kCVImageBufferTransferFunction_SMPTE_ST_2084_PQ : CFStringRef;
+    static kCVImageBufferTransferFunction_ITU_R_2100_HLG   : CFStringRef;
+
+    pub(crate) static NSWorkspaceDidLaunchApplicationNotification    : CFStringRef;
+    pub(crate) static NSWorkspaceDidTerminateApplicationNotification : CFStringRef;
+    pub(crate) static NSWorkspaceDidHideApplicationNotification      : CFStringRef;
+    pub(crate) static NSWorkspaceDidUnhideApplicationNotification    : CFStringRef;
+    pub(crate) static NSWorkspaceActiveSpaceDidChangeNotification    : CFStringRef;
 }
 
 const SCSTREAM_ERROR_CODE_USER_STOPPED: isize = -3817;
@@ -299,6 +347,127 @@ impl NSString {
     }
 }
 
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct NSOperatingSystemVersion {
+    major_version: isize,
+    minor_version: isize,
+    patch_version: isize,
+}
+
+unsafe impl Encode for NSOperatingSystemVersion {
+    const ENCODING: Encoding = Encoding::Struct("?", &[isize::ENCODING, isize::ENCODING, isize::ENCODING]);
+}
+
+pub(crate) struct NSProcessInfo;
+
+impl NSProcessInfo {
+    pub(crate) fn operating_system_version_string() -> String {
+        unsafe {
+            let process_info: *mut AnyObject = msg_send![class!(NSProcessInfo), processInfo];
+            let version_string: *mut AnyObject = msg_send![process_info, operatingSystemVersionString];
+            NSString(version_string).as_string()
+        }
+    }
+
+    pub(crate) fn is_operating_system_at_least_version(major_version: isize, minor_version: isize, patch_version: isize) -> bool {
+        let version = NSOperatingSystemVersion { major_version, minor_version, patch_version };
+        unsafe {
+            let process_info: *mut AnyObject = msg_send![class!(NSProcessInfo), processInfo];
+            let at_least: Bool = msg_send![process_info, isOperatingSystemAtLeastVersion: version];
+            at_least.as_bool()
+        }
+    }
+}
+
+pub(crate) struct NSURL(*mut AnyObject);
+
+impl NSURL {
+    pub(crate) fn from_string(string: &str) -> Option<Self> {
+        let string = NSString::new(string);
+        unsafe {
+            let url: *mut AnyObject = msg_send![class!(NSURL), URLWithString: string.0];
+            if url.is_null() {
+                None
+            } else {
+                let _: *mut AnyObject = msg_send![url, retain];
+                Some(Self(url))
+            }
+        }
+    }
+}
+
+impl Drop for NSURL {
+    fn drop(&mut self) {
+        unsafe {
+            let _: () = msg_send![self.0, release];
+        }
+    }
+}
+
+pub(crate) struct NSWorkspace;
+
+impl NSWorkspace {
+    pub(crate) fn open_url(url: &NSURL) -> bool {
+        unsafe {
+            let workspace: *mut AnyObject = msg_send![class!(NSWorkspace), sharedWorkspace];
+            let opened: Bool = msg_send![workspace, openURL: url.0];
+            opened.as_bool()
+        }
+    }
+}
+
+/// Calls a callback whenever the shared workspace posts one of the given notifications, until dropped
+pub(crate) struct NSWorkspaceNotificationObserver {
+    notification_center: *mut AnyObject,
+    observers: Vec<*mut AnyObject>,
+}
+
+unsafe impl Send for NSWorkspaceNotificationObserver {}
+
+impl NSWorkspaceNotificationObserver {
+    pub(crate) fn new(notification_names: &[CFStringRef], callback: impl Fn() + Send + Sync + 'static) -> Self {
+        let callback = Arc::new(callback);
+        unsafe {
+            let workspace: *mut AnyObject = msg_send![class!(NSWorkspace), sharedWorkspace];
+            let notification_center: *mut AnyObject = msg_send![workspace, notificationCenter];
+            let _: *mut AnyObject = msg_send![notification_center, retain];
+            let observers = notification_names.iter().map(|notification_name| {
+                let callback = callback.clone();
+                let handler_block = RcBlock::new(move |_notification: *mut AnyObject| {
+                    catch_os_callback_panic(|| (callback)());
+                });
+                // The notification center copies the block, and keeps the returned observer until it's removed
+                let observer: *mut AnyObject = msg_send![
+                    notification_center,
+                    addObserverForName: *notification_name as *mut AnyObject
+                    object: std::ptr::null_mut::<AnyObject>()
+                    queue: std::ptr::null_mut::<AnyObject>()
+                    usingBlock: &*handler_block
+                ];
+                let _: *mut AnyObject = msg_send![observer, retain];
+                observer
+            }).collect();
+            Self {
+                notification_center,
+                observers,
+            }
+        }
+    }
+}
+
+impl Drop for NSWorkspaceNotificationObserver {
+    fn drop(&mut self) {
+        unsafe {
+            for observer in self.observers.drain(..) {
+                let _: () = msg_send![self.notification_center, removeObserver: observer];
+                let _: () = msg_send![observer, release];
+            }
+            let _: () = msg_send![self.notification_center, release];
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Debug)]
 pub(crate) struct NSError(*mut AnyObject);
@@ -398,7 +567,7 @@ impl NSArray {
 
     pub(crate) fn add_object<T: 'static + Encode>(&mut self, object: T) {
         unsafe {
-            let _: () = msg_send![self.0, addAnyObject: object];
+            let _: () = msg_send![self.0, addObject: object];
         }
     }
 
@@ -478,7 +647,7 @@ impl NSDictionary {
 
     pub(crate) fn set_object_for_key(&mut self, object: *mut AnyObject, key: *mut AnyObject) {
         unsafe {
-            let _: () = msg_send![self.0, setAnyObject: object, forKey: key];
+            let _: () = msg_send![self.0, setObject: object, forKey: key];
         }
     }
 }
@@ -513,6 +682,103 @@ impl CGPoint {
     pub(crate) const INF: CGPoint = CGPoint { x: std::f64::INFINITY, y: std::f64::INFINITY };
 }
 
+// The current location of the cursor in global display coordinates (top-left origin, in points)
+pub(crate) fn cursor_location() -> Option<CGPoint> {
+    unsafe {
+        let event = CGEventCreate(std::ptr::null());
+        if event.is_null() {
+            return None;
+        }
+        let location = CGEventGetLocation(event);
+        CFRelease(event);
+        Some(location)
+    }
+}
+
+// Whether the cursor is currently shown, rather than hidden by an application
+pub(crate) fn cursor_is_visible() -> bool {
+    unsafe { CGCursorIsVisible() != 0 }
+}
+
+#[repr(C)]
+struct CGImageRefEncoded(CGImageRef);
+
+unsafe impl Encode for CGImageRefEncoded {
+    const ENCODING: Encoding = Encoding::Pointer(&Encoding::Struct("CGImage", &[]));
+}
+
+// The cursor shown on screen, whichever application set it
+pub(crate) struct NSCursor(*mut AnyObject);
+
+unsafe impl Send for NSCursor {}
+unsafe impl Sync for NSCursor {}
+
+impl NSCursor {
+    pub(crate) fn current_system_cursor() -> Option<Self> {
+        unsafe {
+            let cursor: *mut AnyObject = msg_send![class!(NSCursor), currentSystemCursor];
+            if cursor.is_null() {
+                return None;
+            }
+            let _: *mut AnyObject = msg_send![cursor, retain];
+            Some(Self(cursor))
+        }
+    }
+
+    // The cursor's image as BGRA pixels with straight alpha at its full resolution, with its width, height, and the offset of its hotspot in pixels
+    pub(crate) fn image_bgra(&self) -> Option<(Box<[[u8; 4]]>, usize, usize, CGPoint)> {
+        unsafe {
+            let image: *mut AnyObject = msg_send![self.0, image];
+            if image.is_null() {
+                return None;
+            }
+            let image_size: CGSize = msg_send![image, size];
+            let hotspot: CGPoint = msg_send![self.0, hotSpot];
+            let cg_image: CGImageRefEncoded = msg_send![image, CGImageForProposedRect: std::ptr::null_mut::<CGRect>(), context: std::ptr::null_mut::<AnyObject>(), hints: std::ptr::null_mut::<AnyObject>()];
+            if cg_image.0.is_null() {
+                return None;
+            }
+            let cg_image = CGImage::from_ref_unretained(cg_image.0);
+            let (width, height) = (cg_image.width(), cg_image.height());
+            if width == 0 || height == 0 || image_size.x <= 0.0 || image_size.y <= 0.0 {
+                return None;
+            }
+
+            let mut data = vec![[0u8; 4]; width * height];
+            let color_space = CGColorSpaceCreateDeviceRGB();
+            let context = CGBitmapContextCreate(data.as_mut_ptr() as *mut c_void, width, height, 8, width * 4, color_space, kCGBitmapInfoByteOrder32Little | kCGImageAlphaPremultipliedFirst);
+            CGColorSpaceRelease(color_space);
+            if context.is_null() {
+                return None;
+            }
+            CGContextDrawImage(context, CGRect { origin: CGPoint::ZERO, size: CGSize { x: width as f64, y: height as f64 } }, cg_image.0);
+            CGContextRelease(context);
+
+            // Bitmap contexts only hold premultiplied alpha
+            for pixel in data.iter_mut() {
+                let alpha = pixel[3] as u32;
+                if alpha != 0 && alpha != 255 {
+                    for channel in &mut pixel[0..3] {
+                        *channel = ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+                    }
+                }
+            }
+            // The hotspot is in the image's points
+            let hotspot = CGPoint {
+                x: hotspot.x * width as f64 / image_size.x,
+                y: hotspot.y * height as f64 / image_size.y,
+            };
+            Some((data.into_boxed_slice(), width, height, hotspot))
+        }
+    }
+}
+
+impl Drop for NSCursor {
+    fn drop(&mut self) {
+        unsafe { let _: () = msg_send![self.0, release]; }
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Default)]
 pub(crate) struct CGSize {
@@ -539,6 +805,10 @@ unsafe impl Encode for CGRect {
     const ENCODING: Encoding = Encoding::Struct("CGRect", &[CGPoint::ENCODING, CGSize::ENCODING]);
 }
 
+unsafe impl RefEncode for CGRect {
+    const ENCODING_REF: Encoding = Encoding::Pointer(&Self::ENCODING);
+}
+
 impl CGRect {
     pub(crate) const ZERO: CGRect = CGRect {
         origin: CGPoint::ZERO,
@@ -557,9 +827,10 @@ impl CGRect {
         p.y <= (self.origin.y + self.size.y)
     }
 
-    pub(crate) fn create_dicitonary_representation(&self) -> NSDictionary {
+    pub(crate) fn create_dictionary_representation(&self) -> NSDictionary {
         unsafe {
-            NSDictionary::from_ref_unretained(CGRectCreateDictionaryRepresentation(*self))
+            // The dictionary is created, so it's already retained
+            NSDictionary::from_id_retained(CGRectCreateDictionaryRepresentation(*self) as *mut AnyObject)
         }
     }
 
@@ -699,6 +970,13 @@ impl Drop for SCDisplay {
 }
 
 #[repr(C)]
+// Panics must not unwind into the OS frameworks which call blocks and C callbacks, so they're reported to the callback panic handler and swallowed
+fn catch_os_callback_panic(callback: impl FnOnce()) {
+    if let Err(payload) = catch_unwind(AssertUnwindSafe(callback)) {
+        handle_callback_panic(payload.as_ref());
+    }
+}
+
 pub(crate) struct SCShareableContent(*mut AnyObject);
 unsafe impl Send for SCShareableContent {}
 unsafe impl Sync for SCShareableContent {}
@@ -711,7 +989,7 @@ impl SCShareableContent {
     ) {
         let completion_handler = Arc::new(completion_handler);
         let handler_block = RcBlock::new(move |sc_shareable_content: Option<NonNull<AnyObject>>, error: Option<NonNull<AnyObject>>| {
-            unsafe {
+            catch_os_callback_panic(|| unsafe {
                 if let Some(mut error) = error {
                     (completion_handler)(Err(NSError::from_id_unretained(error.as_mut())));
                 } else {
@@ -720,7 +998,7 @@ impl SCShareableContent {
                     let sc_shareable_content = SCShareableContent(sc_shareable_content.as_mut());
                     (completion_handler)(Ok(sc_shareable_content));
                 }
-            }
+            });
         });
         unsafe {
             let _: () = msg_send![
@@ -848,7 +1126,7 @@ impl SCStreamColorMatrix {
         unsafe {
             match self {
                 Self::ItuR709_2 => kCGDisplayStreamYCbCrMatrix_ITU_R_709_2,
-                Self::ItuR601_4 => kCGDisplayStreamYCbCrMatrix_ITU_R_709_2,
+                Self::ItuR601_4 => kCGDisplayStreamYCbCrMatrix_ITU_R_601_4,
                 Self::Smpte240M1995 => kCGDisplayStreamYCbCrMatrix_SMPTE_240M_1995,
             }
         }
@@ -894,6 +1172,14 @@ impl SCStreamConfiguration {
         }
     }
 
+    pub(crate) fn size(&self) -> CGSize {
+        unsafe {
+            let width: usize = msg_send![self.0, width];
+            let height: usize = msg_send![self.0, height];
+            CGSize { x: width as f64, y: height as f64 }
+        }
+    }
+
     pub(crate) fn set_source_rect(&mut self, source_rect: CGRect) {
         unsafe {
             let _: () = msg_send![self.0, setSourceRect: source_rect];
@@ -919,15 +1205,23 @@ impl SCStreamConfiguration {
         }
     }
 
+    pub(crate) fn supports_resolution_type() -> bool {
+        match AnyClass::get("SCStreamConfiguration") {
+            Some(class) => unsafe {
+                let has_property: Bool = msg_send![class, instancesRespondToSelector: sel!(setCaptureResolution:)];
+                has_property.as_bool()
+            },
+            None => false,
+        }
+    }
+
     pub(crate) fn set_resolution_type(&mut self, resolution_type: SCCaptureResolutionType) -> Result<(), ()> {
+        if !Self::supports_resolution_type() {
+            return Err(())
+        }
         unsafe {
-            let has_property: Bool = msg_send![self.0, respondsToSelector: sel!(setCaptureResolution:)];
-            if !has_property.as_bool() {
-                return Err(())
-            } else {
-                let _: () = msg_send![self.0, setCaptureResolution: resolution_type.to_isize()];
-                Ok(())
-            }
+            let _: () = msg_send![self.0, setCaptureResolution: resolution_type.to_isize()];
+            Ok(())
         }
     }
 
@@ -944,6 +1238,26 @@ impl SCStreamConfiguration {
         }
     }
 
+    pub(crate) fn supports_ignore_shadows_single_window() -> bool {
+        match AnyClass::get("SCStreamConfiguration") {
+            Some(class) => unsafe {
+                let has_property: Bool = msg_send![class, instancesRespondToSelector: sel!(setIgnoreShadowsSingleWindow:)];
+                has_property.as_bool()
+            },
+            None => false,
+        }
+    }
+
+    pub(crate) fn set_ignore_shadows_single_window(&mut self, ignore_shadows: bool) -> Result<(), ()> {
+        if !Self::supports_ignore_shadows_single_window() {
+            return Err(())
+        }
+        unsafe {
+            let _: () = msg_send![self.0, setIgnoreShadowsSingleWindow: Bool::new(ignore_shadows)];
+            Ok(())
+        }
+    }
+
     pub(crate) fn set_queue_depth(&mut self, queue_depth: isize) {
         unsafe {
             let _: () = msg_send![self.0, setQueueDepth: queue_depth];
@@ -1219,13 +1533,78 @@ impl SCContentFilter {
         }
     }
 
-    pub(crate) fn new_with_display_excluding_apps_excepting_windows(display: SCDisplay, excluded_applications: NSArray, excepting_windows: NSArray) -> Self {
+    pub(crate) fn new_with_display_including_applications(display: SCDisplay, applications: &[SCRunningApplication]) -> Self {
+        let mut included_applications = NSArray::new_mutable();
+        for application in applications {
+            included_applications.add_object(application.0);
+        }
+        let excepting_windows = NSArray::new();
+        unsafe {
+            let id: *mut AnyObject = msg_send![class!(SCContentFilter), alloc];
+            let id: *mut AnyObject = msg_send![id, initWithDisplay: display.0 includingApplications: included_applications.0 exceptingWindows: excepting_windows.0];
+            Self(id)
+        }
+    }
+
+    pub(crate) fn new_with_display_excluding_applications(display: SCDisplay, applications: &[SCRunningApplication]) -> Self {
+        let mut excluded_applications = NSArray::new_mutable();
+        for application in applications {
+            excluded_applications.add_object(application.0);
+        }
+        let excepting_windows = NSArray::new();
         unsafe {
             let id: *mut AnyObject = msg_send![class!(SCContentFilter), alloc];
             let id: *mut AnyObject = msg_send![id, initWithDisplay: display.0 excludingApplications: excluded_applications.0 exceptingWindows: excepting_windows.0];
             Self(id)
         }
     }
+
+    pub(crate) fn new_with_display_excluding_windows(display: SCDisplay, windows: &[SCWindow]) -> Self {
+        let mut excluded_windows = NSArray::new_mutable();
+        for window in windows {
+            excluded_windows.add_object(window.0);
+        }
+        unsafe {
+            let id: *mut AnyObject = msg_send![class!(SCContentFilter), alloc];
+            let id: *mut AnyObject = msg_send![id, initWithDisplay: display.0 excludingWindows: excluded_windows.0];
+            Self(id)
+        }
+    }
+
+    // Only available from MacOS 14
+    pub(crate) fn style(&self) -> SCShareableContentStyle {
+        let style: isize = unsafe { msg_send![self.0, style] };
+        match style {
+            1 => SCShareableContentStyle::Window,
+            2 => SCShareableContentStyle::Display,
+            3 => SCShareableContentStyle::Application,
+            _ => SCShareableContentStyle::None,
+        }
+    }
+
+    // Only available from MacOS 15.2
+    pub(crate) fn included_windows(&self) -> Option<Vec<SCWindow>> {
+        unsafe {
+            let has_included_windows: Bool = msg_send![self.0, respondsToSelector: sel!(includedWindows)];
+            if !has_included_windows.as_bool() {
+                return None;
+            }
+            let windows = NSArray::from_id_unretained(msg_send![self.0, includedWindows]);
+            Some((0..windows.count()).map(|i| SCWindow::from_id_unretained(windows.obj_at_index(i))).collect())
+        }
+    }
+
+    // Only available from MacOS 15.2
+    pub(crate) fn included_displays(&self) -> Option<Vec<SCDisplay>> {
+        unsafe {
+            let has_included_displays: Bool = msg_send![self.0, respondsToSelector: sel!(includedDisplays)];
+            if !has_included_displays.as_bool() {
+                return None;
+            }
+            let displays = NSArray::from_id_unretained(msg_send![self.0, includedDisplays]);
+            Some((0..displays.count()).map(|i| SCDisplay::from_id_unretained(displays.obj_at_index(i))).collect())
+        }
+    }
 }
 
 impl Clone for SCContentFilter {
@@ -1242,9 +1621,11 @@ impl Drop for SCContentFilter {
 }
 
 pub(crate) enum SCStreamCallbackError {
-    SampleBufferCopyFailed,
-    StreamStopped,
-    Other(NSError)
+    MissingSampleBuffer,
+    StreamStopped(NSError),
+    Other(NSError),
+    // A stream handler method panicked - `stopped` is set if it was handling the stream stopping
+    HandlerPanicked { message: String, stopped: bool },
 }
 
 #[repr(C)]
@@ -1305,37 +1686,58 @@ unsafe impl Encode for SCStreamOutputTypeEncoded {
 #[repr(C)]
 struct SCStreamEncoded(*mut AnyObject);
 
+// Panics must not unwind into ScreenCaptureKit, so handler methods report them to the stream callback instead
+unsafe fn sc_stream_handler_report_panic(this: *mut AnyObject, payload: Box<dyn Any + Send>, stopped: bool) {
+    let message = CallbackPanic::from_payload(payload.as_ref()).message().unwrap_or("unknown panic").to_string();
+    let _ = catch_unwind(AssertUnwindSafe(|| {
+        let callback_container_ivar = SCStreamHandler::get_class().instance_variable("callback_container_ptr").expect("Expected callback_container_ptr ivar on SCStreamHandler");
+        let callback_container: *mut SCStreamCallbackContainer = *callback_container_ivar.load(&mut *this);
+        (&mut *callback_container).call_error(SCStreamCallbackError::HandlerPanicked { message, stopped });
+    }));
+}
+
 extern fn sc_stream_output_did_output_sample_buffer_of_type(this: *mut AnyObject, _sel: Sel, stream: SCStream, buffer: CMSampleBufferRef, output_type: SCStreamOutputTypeEncoded) {
     unsafe {
-        let callback_container_ivar = SCStreamHandler::get_class().instance_variable("callback_container_ptr").expect("Expected callback_container_ptr ivar on SCStreamHandler");
-        let callback_container: *mut SCStreamCallbackContainer = *callback_container_ivar.load::<*mut c_void>(&mut *this) as *mut _;
-        if let Ok(sample_buffer) = CMSampleBuffer::copy_from_ref(buffer) {
-            let output_type = SCStreamOutputType::from_encoded(output_type.0).unwrap();
-            (&mut *callback_container).call_output(sample_buffer, output_type);
-        } else {
-            (&mut *callback_container).call_error(SCStreamCallbackError::SampleBufferCopyFailed);
-        }
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let callback_container_ivar = SCStreamHandler::get_class().instance_variable("callback_container_ptr").expect("Expected callback_container_ptr ivar on SCStreamHandler");
+            let callback_container: *mut SCStreamCallbackContainer = *callback_container_ivar.load::<*mut c_void>(&mut *this) as *mut _;
+            if let Some(sample_buffer) = CMSampleBuffer::retain_from_ref(buffer) {
+                let output_type = SCStreamOutputType::from_encoded(output_type.0).expect("Expected a known SCStreamOutputType");
+                (&mut *callback_container).call_output(sample_buffer, output_type);
+            } else {
+                (&mut *callback_container).call_error(SCStreamCallbackError::MissingSampleBuffer);
+            }
+        }));
         std::mem::forget(stream);
+        if let Err(payload) = result {
+            sc_stream_handler_report_panic(this, payload, false);
+        }
     }
 }
 
 extern fn sc_stream_handler_did_stop_with_error(this: *mut AnyObject, _sel: Sel, stream: SCStream, error: NSError) -> () {
     unsafe {
-        let callback_container_ivar = SCStreamHandler::get_class().instance_variable("callback_container_ptr").expect("Expected callback_container_ptr ivar on SCStreamHandler");
-        let callback_container: *mut SCStreamCallbackContainer = *callback_container_ivar.load(&mut *this);
-        (&mut *callback_container).call_error(SCStreamCallbackError::StreamStopped);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let callback_container_ivar = SCStreamHandler::get_class().instance_variable("callback_container_ptr").expect("Expected callback_container_ptr ivar on SCStreamHandler");
+            let callback_container: *mut SCStreamCallbackContainer = *callback_container_ivar.load(&mut *this);
+            (&mut *callback_container).call_error(SCStreamCallbackError::StreamStopped(NSError::from_id_unretained(error.0)));
+        }));
         std::mem::forget(error);
         std::mem::forget(stream);
+        if let Err(payload) = result {
+            sc_stream_handler_report_panic(this, payload, true);
+        }
     }
 }
 
 extern fn sc_stream_handler_dealloc(this: *mut AnyObject, _sel: Sel) {
-    unsafe {
+    // Dropping the callback drops whatever it captured, which may panic
+    catch_os_callback_panic(|| unsafe {
         let callback_container_ivar = SCStreamHandler::get_class().instance_variable("callback_container_ptr").expect("Expected callback_container_ptr ivar on SCStreamHandler");
         let callback_container: *mut SCStreamCallbackContainer = *callback_container_ivar.load(&mut *this);
         let callback_container: Box<SCStreamCallbackContainer> = Box::from_raw(callback_container);
         drop(callback_container);
-    }
+    });
 }
 
 #[repr(C)]
@@ -1383,6 +1785,12 @@ unsafe impl Encode for SCStream {
 unsafe impl Sync for SCStream {}
 unsafe impl Send for SCStream {}
 
+impl Clone for SCStream {
+    fn clone(&self) -> Self {
+        Self::from_id(self.0)
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
 struct SCStreamDelegate(*mut AnyObject);
@@ -1400,6 +1808,10 @@ unsafe impl Encode for SCStreamOutput {
 }
 
 impl SCStream {
+    pub fn class_exists() -> bool {
+        AnyClass::get("SCStream").is_some()
+    }
+
     pub fn preflight_access() -> bool {
         unsafe { CGPreflightScreenCaptureAccess() }
     }
@@ -1420,43 +1832,75 @@ impl SCStream {
     }
 
     pub fn new(filter: SCContentFilter, config: SCStreamConfiguration, handler_queue: DispatchQueue, handler: SCStreamHandler) -> Result<Self, String> {
+        Self::new_with_output_types(filter, config, handler_queue, handler, &[SCStreamOutputType::Screen])
+    }
+
+    pub fn new_with_output_types(filter: SCContentFilter, config: SCStreamConfiguration, handler_queue: DispatchQueue, handler: SCStreamHandler, output_types: &[SCStreamOutputType]) -> Result<Self, String> {
         unsafe {
             let instance: *mut AnyObject = msg_send![class!(SCStream), alloc];
             let instance: *mut AnyObject = msg_send![instance, initWithFilter: filter.0 configuration: config.0 delegate: SCStreamDelegate(handler.0)];
-            let mut error: *mut AnyObject = std::ptr::null_mut();
-            let result: bool = msg_send![instance, addStreamOutput: SCStreamOutput(handler.0) type: SCStreamOutputType::Screen.to_encoded() sampleHandlerQueue: handler_queue error: &mut error as *mut _];
-            if !error.is_null() {
-                let error = NSError::from_id_retained(error);
-                let _: () = msg_send![instance, release];
-                return Err(format!("SCStream error: {}, reason: {}", error.description(), error.reason()));
+            for output_type in output_types {
+                let mut error: *mut AnyObject = std::ptr::null_mut();
+                let result: bool = msg_send![instance, addStreamOutput: SCStreamOutput(handler.0) type: output_type.to_encoded() sampleHandlerQueue: handler_queue.clone() error: &mut error as *mut _];
+                if !error.is_null() {
+                    let error = NSError::from_id_retained(error);
+                    let _: () = msg_send![instance, release];
+                    return Err(format!("SCStream error: {}, reason: {}", error.description(), error.reason()));
+                }
             }
             Ok(SCStream(instance))
         }
     }
 
-    pub fn start(&mut self) {
+    pub fn start_with_completion_handler(&mut self, completion_handler: impl Fn(Result<(), NSError>) + Send + 'static) {
+        let handler_block = RcBlock::new(move |error: *mut AnyObject| {
+            catch_os_callback_panic(|| if error.is_null() {
+                (completion_handler)(Ok(()));
+            } else {
+                (completion_handler)(Err(unsafe { NSError::from_id_unretained(error) }));
+            });
+        });
         unsafe {
-            let _: () = msg_send![self.0, startCaptureWithCompletionHandler: &*StackBlock::new(Box::new(
-                |error: *mut AnyObject| {
-                    if !error.is_null() {
-                        let error =  NSError::from_id_unretained(error);
-                        println!("startCaptureWithCompletionHandler error: {:?}, reason: {:?}", error.description(), error.reason());
-                    }
-                }
-            )).copy()];
+            let _: () = msg_send![self.0, startCaptureWithCompletionHandler: &*handler_block];
         }
     }
 
-    pub fn stop(&mut self) {
+    pub fn stop_with_completion_handler(&mut self, completion_handler: impl Fn(Result<(), NSError>) + Send + 'static) {
+        let handler_block = RcBlock::new(move |error: *mut AnyObject| {
+            catch_os_callback_panic(|| if error.is_null() {
+                (completion_handler)(Ok(()));
+            } else {
+                (completion_handler)(Err(unsafe { NSError::from_id_unretained(error) }));
+            });
+        });
         unsafe {
-            let _: () = msg_send![self.0, stopCaptureWithCompletionHandler: &*StackBlock::new(Box::new(
-                |error: *mut AnyObject| {
-                    if !error.is_null() {
-                        let error =  NSError::from_id_unretained(error);
-                        println!("stopCaptureWithCompletionHandler error: {:?}, reason: {:?}", error.description(), error.reason());
-                    }
-                }
-            )).copy()];
+            let _: () = msg_send![self.0, stopCaptureWithCompletionHandler: &*handler_block];
+        }
+    }
+
+    pub fn update_configuration_with_completion_handler(&mut self, config: &SCStreamConfiguration, completion_handler: impl Fn(Result<(), NSError>) + Send + 'static) {
+        let handler_block = RcBlock::new(move |error: *mut AnyObject| {
+            catch_os_callback_panic(|| if error.is_null() {
+                (completion_handler)(Ok(()));
+            } else {
+                (completion_handler)(Err(unsafe { NSError::from_id_unretained(error) }));
+            });
+        });
+        unsafe {
+            let _: () = msg_send![self.0, updateConfiguration: config.0 completionHandler: &*handler_block];
+        }
+    }
+
+    pub fn update_content_filter_with_completion_handler(&mut self, filter: SCContentFilter, completion_handler: impl Fn(Result<(), NSError>) + Send + 'static) {
+        let handler_block = RcBlock::new(move |error: *mut AnyObject| {
+            catch_os_callback_panic(|| if error.is_null() {
+                (completion_handler)(Ok(()));
+            } else {
+                (completion_handler)(Err(unsafe { NSError::from_id_unretained(error) }));
+            });
+        });
+        unsafe {
+            let _: () = msg_send![self.0, updateContentFilter: filter.0 completionHandler: &*handler_block];
         }
     }
 }
@@ -1468,16 +1912,13 @@ pub(crate) struct CMSampleBuffer(CMSampleBufferRef);
 unsafe impl Send for CMSampleBuffer {}
 
 impl CMSampleBuffer {
-    pub(crate) fn copy_from_ref(r: CMSampleBufferRef) -> Result<Self, ()> {
-        unsafe { 
-            let mut new_ref: CMSampleBufferRef = std::ptr::null();
-            let status = CMSampleBufferCreateCopy(kCFAllocatorDefault, r, &mut new_ref as *mut _);
-            if status != 0 {
-                Err(())
-            } else {
-                Ok(CMSampleBuffer(new_ref))
-            }
+    // Sample buffers are only ever read, so retaining the original is enough to keep it alive past the callback
+    pub(crate) fn retain_from_ref(r: CMSampleBufferRef) -> Option<Self> {
+        if r.is_null() {
+            return None;
         }
+        unsafe { CFRetain(r); }
+        Some(CMSampleBuffer(r))
     }
 
     pub(crate) fn get_presentation_timestamp(&self) -> CMTime {
@@ -1494,23 +1935,45 @@ impl CMSampleBuffer {
     }
 
     // CMSampleBufferGetAudioBufferListWithRetainedBlockBuffer
-    pub(crate) unsafe fn get_audio_buffer_list_with_block_buffer(&self) -> Result<(AudioBufferList, CMBlockBuffer), ()> {
-        let mut audio_buffer_list = AudioBufferList::default();
+    pub(crate) unsafe fn get_audio_buffer_list_with_block_buffer(&self) -> Result<(AudioBufferListStorage, CMBlockBuffer), AudioBufferListError> {
+        // The sample buffer may hold any number of AudioBuffers trailing the list, so ask for the required size first
+        let mut buffer_list_size = 0usize;
+        let status = CMSampleBufferGetAudioBufferListWithRetainedBlockBuffer(
+            self.0,
+            &mut buffer_list_size as *mut _,
+            std::ptr::null_mut(),
+            0,
+            kCFAllocatorNull,
+            kCFAllocatorNull,
+            kCMSampleBufferFlag_AudioBufferList_Assure16ByteAlignment,
+            std::ptr::null_mut()
+        );
+        if status != 0 {
+            return Err(AudioBufferListError::from_status(status));
+        }
+        let mut audio_buffer_list = AudioBufferListStorage::with_size(buffer_list_size);
         let mut block_buffer: CMBlockBufferRef = std::ptr::null();
         let status = CMSampleBufferGetAudioBufferListWithRetainedBlockBuffer(
             self.0,
             std::ptr::null_mut(),
-            &mut audio_buffer_list as *mut _,
-            std::mem::size_of::<AudioBufferList>(),
+            audio_buffer_list.as_mut_ptr(),
+            audio_buffer_list.size(),
             kCFAllocatorNull,
             kCFAllocatorNull,
             kCMSampleBufferFlag_AudioBufferList_Assure16ByteAlignment,
             &mut block_buffer as *mut _
         );
         if status != 0 {
-            return Err(());
+            return Err(AudioBufferListError::from_status(status));
+        }
+        if block_buffer.is_null() {
+            return Err(AudioBufferListError::NoBlockBuffer);
+        }
+        let block_buffer = CMBlockBuffer::from_ref_retained(block_buffer);
+        if audio_buffer_list.buffer_count() > audio_buffer_list.buffer_capacity() {
+            return Err(AudioBufferListError::ArrayTooSmall);
         }
-        Ok((audio_buffer_list, CMBlockBuffer::from_ref_retained(block_buffer)))
+        Ok((audio_buffer_list, block_buffer))
     }
 
     pub(crate) fn get_sample_attachment_array(&self) -> Vec<CFDictionary> {
@@ -1706,30 +2169,114 @@ unsafe impl Encode for AudioBuffer {
     ]);
 }
 
+impl AudioBuffer {
+    pub(crate) fn number_channels(&self) -> usize {
+        self.number_channels as usize
+    }
+
+    pub(crate) fn data(&self) -> &[u8] {
+        if self.data.is_null() || self.data_byte_size == 0 {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(self.data as *const u8, self.data_byte_size as usize) }
+    }
+}
+
+// The header of a variable length AudioBufferList - the AudioBuffers are stored inline, starting at `buffers`
 #[repr(C)]
 pub(crate) struct AudioBufferList {
     number_buffers: u32,
-    buffers: *mut AudioBuffer,
+    buffers: [AudioBuffer; 1],
 }
 
 unsafe impl Encode for AudioBufferList {
     const ENCODING: Encoding = Encoding::Struct("AudioBufferList", &[
         Encoding::UInt,
-        Encoding::Pointer(&AudioBuffer::ENCODING)
+        Encoding::Array(1, &AudioBuffer::ENCODING)
     ]);
 }
 
 unsafe impl RefEncode for AudioBufferList {
-    const ENCODING_REF: Encoding = Self::ENCODING;
+    const ENCODING_REF: Encoding = Encoding::Pointer(&Self::ENCODING);
 }
 
-impl Default for AudioBufferList {
-    fn default() -> Self {
+#[derive(Debug)]
+pub(crate) enum AudioBufferListError {
+    ArrayTooSmall,
+    NoBlockBuffer,
+    Other(OSStatus),
+}
+
+impl AudioBufferListError {
+    fn from_status(status: OSStatus) -> Self {
+        match status {
+            kCMSampleBufferError_ArrayTooSmall => Self::ArrayTooSmall,
+            _ => Self::Other(status),
+        }
+    }
+}
+
+// Owned, correctly aligned storage for an AudioBufferList along with all of its trailing AudioBuffers
+pub(crate) struct AudioBufferListStorage {
+    storage: Box<[u64]>,
+    size: usize,
+}
+
+impl AudioBufferListStorage {
+    pub(crate) fn with_size(size: usize) -> Self {
+        let size = size.max(std::mem::size_of::<AudioBufferList>());
+        let words = size.div_ceil(std::mem::size_of::<u64>());
         Self {
-            number_buffers: 0,
-            buffers: std::ptr::null_mut()
+            storage: vec![0u64; words].into_boxed_slice(),
+            size,
         }
     }
+
+    pub(crate) fn size(&self) -> usize {
+        self.size
+    }
+
+    pub(crate) fn as_ptr(&self) -> *const AudioBufferList {
+        self.storage.as_ptr() as *const AudioBufferList
+    }
+
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut AudioBufferList {
+        self.storage.as_mut_ptr() as *mut AudioBufferList
+    }
+
+    // The number of AudioBuffers that fit in the allocated storage
+    pub(crate) fn buffer_capacity(&self) -> usize {
+        let header_size = std::mem::offset_of!(AudioBufferList, buffers);
+        (self.size - header_size) / std::mem::size_of::<AudioBuffer>()
+    }
+
+    pub(crate) fn buffer_count(&self) -> usize {
+        unsafe { (*self.as_ptr()).number_buffers as usize }
+    }
+
+    pub(crate) fn buffer(&self, index: usize) -> Option<&AudioBuffer> {
+        if index >= self.buffer_count().min(self.buffer_capacity()) {
+            return None;
+        }
+        unsafe {
+            let buffers = std::ptr::addr_of!((*self.as_ptr()).buffers) as *const AudioBuffer;
+            Some(&*buffers.add(index))
+        }
+    }
+
+    // Find the buffer holding the given channel, returning it along with the channel's offset within its interleaved frames
+    pub(crate) fn channel_buffer(&self, channel: usize) -> Option<(&AudioBuffer, usize)> {
+        let mut first_channel = 0;
+        for i in 0..self.buffer_count() {
+            let buffer = self.buffer(i)?;
+            let channels = buffer.number_channels();
+            if channel < first_channel + channels {
+                return Some((buffer, channel - first_channel));
+            }
+            first_channel += channels;
+        }
+        None
+    }
 }
 
 #[repr(C)]
@@ -1932,6 +2479,14 @@ impl DispatchQueue {
         unsafe { dispatch_queue_create(cstring_name.as_ptr(), DispatchQueueAttr(addr_of_mut!(_dispatch_queue_attr_concurrent))) }
     }
 
+    pub fn make_concurrent_with_qos(name: String, qos_class: DispatchQoSClass) -> Self {
+        let cstring_name = CString::new(name.as_str()).unwrap();
+        unsafe {
+            let attr = dispatch_queue_attr_make_with_qos_class(DispatchQueueAttr(addr_of_mut!(_dispatch_queue_attr_concurrent)), qos_class as u32, 0);
+            dispatch_queue_create(cstring_name.as_ptr(), attr)
+        }
+    }
+
     pub fn make_serial(name: String) -> Self {
         let cstring_name = CString::new(name.as_str()).unwrap();
         unsafe { dispatch_queue_create(cstring_name.as_ptr(), DispatchQueueAttr(0 as *mut c_void)) }
@@ -1964,6 +2519,17 @@ impl Clone for DispatchQueue {
 #[repr(C)]
 struct DispatchQueueAttr(*mut c_void);
 
+// qos_class_t values
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum DispatchQoSClass {
+    UserInteractive = 0x21,
+    UserInitiated = 0x19,
+    Default = 0x15,
+    Utility = 0x11,
+    Background = 0x09,
+}
+
 pub(crate) struct SCRunningApplication(pub(crate) *mut AnyObject);
 
 impl SCRunningApplication {
@@ -2029,16 +2595,33 @@ impl CGDisplayStreamFrameStatus {
     }
 }
 
+// kCGDisplayStreamUpdateDirtyRects
+const K_CG_DISPLAY_STREAM_UPDATE_DIRTY_RECTS: i32 = 2;
+
+// Copy the rects of a frame's update out of the update, which is only valid during the frame callback
+unsafe fn cg_display_stream_update_dirty_rects(update: CGDisplayStreamUpdateRef) -> Option<Vec<CGRect>> {
+    if update.is_null() {
+        return None;
+    }
+    let mut rect_count = 0usize;
+    let rects = CGDisplayStreamUpdateGetRects(update, K_CG_DISPLAY_STREAM_UPDATE_DIRTY_RECTS, &mut rect_count as *mut _);
+    if rects.is_null() {
+        return None;
+    }
+    Some(std::slice::from_raw_parts(rects, rect_count).to_vec())
+}
+
 pub(crate) struct CGDisplayStream{
     stream_ref: CGDisplayStreamRef,
     callback_block: RcBlock<dyn Fn(i32, u64, IOSurfaceRef, CGDisplayStreamUpdateRef)>,
 }
 
 impl CGDisplayStream {
-    pub fn new(callback: impl Fn(CGDisplayStreamFrameStatus, Duration, IOSurface) + 'static, display_id: u32, size: (usize, usize), pixel_format: SCStreamPixelFormat, options_dict: NSDictionary, dispatch_queue: DispatchQueue) -> Self {
+    // The callback receives the regions of the frame which changed since the previous frame, in frame pixels, if they're known
+    pub fn new(callback: impl Fn(CGDisplayStreamFrameStatus, Duration, IOSurface, Option<Vec<CGRect>>) + 'static, display_id: u32, size: (usize, usize), pixel_format: SCStreamPixelFormat, options_dict: NSDictionary, dispatch_queue: DispatchQueue) -> Self {
         let absolute_time_start = Arc::new(Mutex::new(None));
         let callback = Arc::new(callback);
-        let callback_block = StackBlock::new(move |status: i32, display_time: u64, iosurface_ref: IOSurfaceRef, stream_update_ref: CGDisplayStreamUpdateRef| {
+        let callback_block = StackBlock::new(move |status: i32, display_time: u64, iosurface_ref: IOSurfaceRef, stream_update_ref: CGDisplayStreamUpdateRef| catch_os_callback_panic(|| {
             if let Some(status) = CGDisplayStreamFrameStatus::from_i32(status) {
                 let abs_time_start_opt = { *absolute_time_start.lock() };
                 let relative_time = if let Some(absolute_time_start) = abs_time_start_opt {
@@ -2053,13 +2636,13 @@ impl CGDisplayStream {
                     let time_ns = ((relative_time as u128 * timebase_info.numer as u128) / timebase_info.denom as u128);
                     let time = Duration::from_nanos(time_ns as u64);
                     let io_surface = IOSurface::from_ref_unretained(iosurface_ref);
-                    (callback)(status, time, io_surface);
+                    let dirty_rects = cg_display_stream_update_dirty_rects(stream_update_ref);
+                    (callback)(status, time, io_surface, dirty_rects);
                 }
             }
-        }).copy();
+        })).copy();
         unsafe {
             let pixel_format = pixel_format.to_ostype();
-            let display_id = CGMainDisplayID();
             let stream_ref = CGDisplayStreamCreateWithDispatchQueue(display_id, size.0, size.1, pixel_format.as_i32(), std::ptr::null_mut(), dispatch_queue.0, &*callback_block as *const _ as *const c_void);
             Self {
                 stream_ref,
@@ -2107,6 +2690,45 @@ impl Drop for CGDisplayStream {
     }
 }
 
+type CGDisplayReconfigurationCallBack = extern "C" fn(display: u32, flags: u32, user_info: *mut c_void);
+
+pub(crate) const K_CG_DISPLAY_REMOVE_FLAG: u32 = 1 << 5;
+
+type CGDisplayReconfigurationCallbackFn = Box<dyn Fn(u32, u32) + Send + Sync + 'static>;
+
+extern "C" fn cg_display_reconfiguration_callback(display: u32, flags: u32, user_info: *mut c_void) {
+    let callback = unsafe { &*(user_info as *const CGDisplayReconfigurationCallbackFn) };
+    catch_os_callback_panic(|| callback(display, flags));
+}
+
+/// Calls a callback with the display id and change flags whenever a display is reconfigured, until dropped
+pub(crate) struct CGDisplayReconfigurationObserver {
+    callback: *mut CGDisplayReconfigurationCallbackFn,
+}
+
+unsafe impl Send for CGDisplayReconfigurationObserver {}
+
+impl CGDisplayReconfigurationObserver {
+    pub fn new(callback: impl Fn(u32, u32) + Send + Sync + 'static) -> Self {
+        let callback = Box::into_raw(Box::new(Box::new(callback) as CGDisplayReconfigurationCallbackFn));
+        unsafe {
+            CGDisplayRegisterReconfigurationCallback(cg_display_reconfiguration_callback, callback as *mut c_void);
+        }
+        Self {
+            callback
+        }
+    }
+}
+
+impl Drop for CGDisplayReconfigurationObserver {
+    fn drop(&mut self) {
+        unsafe {
+            CGDisplayRemoveReconfigurationCallback(cg_display_reconfiguration_callback, self.callback as *mut c_void);
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
 
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -2117,6 +2739,8 @@ pub enum CVPixelFormat {
     BGRA8888,
     ABGR8888,
     RGBA8888,
+    // 'l10r', little endian packed (a: u2, r: u10, g: u10, b: u10)
+    ARGB2101010LE,
     V420,
     F420,
     Other,
@@ -2133,7 +2757,9 @@ impl CVPixelFormat {
                 0x42475241 => Self::BGRA8888,
                 0x41424752 => Self::ABGR8888,
                 0x52474241 => Self::RGBA8888,
+                0x6C313072 => Self::ARGB2101010LE,
                 0x34323076 => Self::V420,
+                0x34323066 => Self::F420,
                 _ => {
                     return None;
                 }
@@ -2242,11 +2868,20 @@ pub enum IOSurfaceCacheMode {
 }
 
 impl IOSurface {
-    fn from_ref_unretained(r: IOSurfaceRef) -> Self {
-        unsafe { IOSurfaceIncrementUseCount(r); }
+    // Retains the surface so that it outlives its owner, and marks it in use so that its pool doesn't reuse it
+    pub(crate) fn from_ref_unretained(r: IOSurfaceRef) -> Self {
+        unsafe {
+            CFRetain(r);
+            IOSurfaceIncrementUseCount(r);
+        }
         Self(r)
     }
 
+    // The surface's pixel format as a four character code, E.G. 'BGRA'
+    pub(crate) fn get_pixel_format_code(&self) -> u32 {
+        unsafe { IOSurfaceGetPixelFormat(self.0).as_u32() }
+    }
+
     pub(crate) fn get_pixel_format(&self) -> Option<CVPixelFormat> {
         unsafe {
             let pixel_format_ostype = IOSurfaceGetPixelFormat(self.0);
@@ -2366,6 +3001,7 @@ impl Drop for IOSurface {
     fn drop(&mut self) {
         unsafe {
             IOSurfaceDecrementUseCount(self.0);
+            CFRelease(self.0);
         }
     }
 }
@@ -2544,6 +3180,12 @@ impl NSNumber {
         }
     }
 
+    pub(crate) fn as_bool(&self) -> bool {
+        unsafe {
+            msg_send![self.0, boolValue]
+        }
+    }
+
 }
 
 impl Clone for NSNumber {
@@ -2664,6 +3306,61 @@ impl CVPixelBuffer {
             CVPixelBufferGetHeight(self.0)
         }
     }
+
+    // The attachment is owned by the buffer, so it's only valid while the buffer is
+    fn get_attachment(&self, key: CFStringRef) -> Option<CFTypeRef> {
+        let attachment = unsafe { CVBufferGetAttachment(self.0, key, std::ptr::null_mut()) };
+        (!attachment.is_null()).then_some(attachment)
+    }
+
+    pub fn get_color_primaries(&self) -> Option<CVColorPrimaries> {
+        unsafe {
+            let primaries = self.get_attachment(kCVImageBufferColorPrimariesKey)?;
+            Some(if CFEqual(primaries, kCVImageBufferColorPrimaries_ITU_R_709_2) {
+                CVColorPrimaries::Rec709
+            } else if CFEqual(primaries, kCVImageBufferColorPrimaries_P3_D65) {
+                CVColorPrimaries::P3D65
+            } else if CFEqual(primaries, kCVImageBufferColorPrimaries_ITU_R_2020) {
+                CVColorPrimaries::Rec2020
+            } else {
+                CVColorPrimaries::Other
+            })
+        }
+    }
+
+    pub fn get_transfer_function(&self) -> Option<CVTransferFunction> {
+        unsafe {
+            let transfer_function = self.get_attachment(kCVImageBufferTransferFunctionKey)?;
+            Some(if CFEqual(transfer_function, kCVImageBufferTransferFunction_ITU_R_709_2) {
+                CVTransferFunction::Rec709
+            } else if CFEqual(transfer_function, kCVImageBufferTransferFunction_sRGB) {
+                CVTransferFunction::Srgb
+            } else if CFEqual(transfer_function, kCVImageBufferTransferFunction_SMPTE_ST_2084_PQ) {
+                CVTransferFunction::Pq
+            } else if CFEqual(transfer_function, kCVImageBufferTransferFunction_ITU_R_2100_HLG) {
+                CVTransferFunction::Hlg
+            } else {
+                CVTransferFunction::Other
+            })
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CVColorPrimaries {
+    Rec709,
+    P3D65,
+    Rec2020,
+    Other,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CVTransferFunction {
+    Rec709,
+    Srgb,
+    Pq,
+    Hlg,
+    Other,
 }
 
 impl Clone for CVPixelBuffer {
@@ -2756,6 +3453,74 @@ impl NSScreen {
     pub(crate) fn frame(&self) -> CGRect {
         unsafe { msg_send![self.0, frame] }
     }
+
+    pub(crate) fn backing_scale_factor(&self) -> f64 {
+        let backing_scale_factor: f64 = unsafe { msg_send![self.0, backingScaleFactor] };
+        if backing_scale_factor > 0.0 { backing_scale_factor } else { 1.0 }
+    }
+
+    pub(crate) fn display_id(&self) -> Option<u32> {
+        let ns_screen_number_string = NSString::new("NSScreenNumber");
+        let device_description = self.device_description();
+        let screen_number_ptr = device_description.value_for_key(ns_screen_number_string.0 as CFStringRef);
+        let display_id = if screen_number_ptr.is_null() {
+            None
+        } else {
+            Some(NSNumber::from_id_unretained(screen_number_ptr).as_i32() as u32)
+        };
+        std::mem::forget(device_description);
+        display_id
+    }
+
+    // Only available from MacOS 10.15
+    pub(crate) fn localized_name(&self) -> Option<String> {
+        unsafe {
+            let has_localized_name: Bool = msg_send![self.0, respondsToSelector: sel!(localizedName)];
+            if !has_localized_name.as_bool() {
+                return None;
+            }
+            let name: *mut AnyObject = msg_send![self.0, localizedName];
+            if name.is_null() {
+                return None;
+            }
+            Some(NSString::from_id_unretained(name).as_string())
+        }
+    }
+}
+
+pub struct CGDisplayMode(CGDisplayModeRef);
+
+impl CGDisplayMode {
+    pub(crate) fn for_display(display_id: u32) -> Option<Self> {
+        let mode = unsafe { CGDisplayCopyDisplayMode(display_id) };
+        if mode.is_null() {
+            None
+        } else {
+            Some(Self(mode))
+        }
+    }
+
+    pub(crate) fn width(&self) -> usize {
+        unsafe { CGDisplayModeGetWidth(self.0) }
+    }
+
+    pub(crate) fn pixel_width(&self) -> usize {
+        unsafe { CGDisplayModeGetPixelWidth(self.0) }
+    }
+
+    pub(crate) fn pixel_height(&self) -> usize {
+        unsafe { CGDisplayModeGetPixelHeight(self.0) }
+    }
+
+    pub(crate) fn refresh_rate(&self) -> f64 {
+        unsafe { CGDisplayModeGetRefreshRate(self.0) }
+    }
+}
+
+impl Drop for CGDisplayMode {
+    fn drop(&mut self) {
+        unsafe { CGDisplayModeRelease(self.0); }
+    }
 }
 
 #[derive(Debug)]
@@ -2995,6 +3760,16 @@ impl SCContentSharingPickerConfiguration {
         }
     }
 
+    pub fn set_excluded_bundle_ids(&self, bundle_ids: &[String]) {
+        let mut excluded_bundle_ids = NSArray::new_mutable();
+        for bundle_id in bundle_ids {
+            excluded_bundle_ids.add_object(NSString::new(bundle_id).0);
+        }
+        unsafe {
+            let _: () = msg_send![self.0, setExcludedBundleIDs: excluded_bundle_ids.0];
+        }
+    }
+
     pub fn set_allowed_picker_modes(&self, allowed_picker_modes: usize) {
         unsafe {
             let _: () = msg_send![self.0, setAllowedPickerModes: allowed_picker_modes];
@@ -3024,35 +3799,52 @@ unsafe impl Encode for SCContentSharingPickerObserver {
     const ENCODING: Encoding = Encoding::Object;
 }
 
-extern fn sc_content_sharing_picker_observer_did_cancel_for_stream(this: *mut AnyObject, _sel: Sel, picker: *mut AnyObject, stream: *mut AnyObject) {
-    //println!("sc_content_sharing_picker_observer_did_cancel_for_stream");
+// The callback container of an observer, which lives until the observer is deallocated
+unsafe fn sc_content_sharing_picker_callback_container<'a>(this: *mut AnyObject) -> &'a mut SCContentSharingPickerCallbackContainer {
+    let callback_container_ivar = SCContentSharingPickerObserver::get_class().instance_variable("callback_container_ptr").expect("Expected callback_container_ptr ivar on SCContentSharingPickerObserver");
+    let callback_container: *mut c_void = *callback_container_ivar.load(&*this);
+    &mut *(callback_container as *mut SCContentSharingPickerCallbackContainer)
+}
+
+extern fn sc_content_sharing_picker_observer_did_cancel_for_stream(this: *mut AnyObject, _sel: Sel, _picker: *mut AnyObject, _stream: *mut AnyObject) {
+    catch_os_callback_panic(|| unsafe {
+        sc_content_sharing_picker_callback_container(this).call_cancelled();
+    });
 }
 
-extern fn sc_content_sharing_picker_observer_did_update_filter_for_stream(this: *mut AnyObject, _sel: Sel, picker: *mut AnyObject, filter: *mut AnyObject, stream: *mut AnyObject) {
-    //println!("sc_content_sharing_picker_observer_did_update_filter_for_stream");
-    //debug_objc_AnyObject(filter);
+extern fn sc_content_sharing_picker_observer_did_update_filter_for_stream(this: *mut AnyObject, _sel: Sel, _picker: *mut AnyObject, filter: *mut AnyObject, stream: *mut AnyObject) {
+    catch_os_callback_panic(|| unsafe {
+        let _: *mut AnyObject = msg_send![filter, retain];
+        let filter = SCContentFilter(filter);
+        let stream = (!stream.is_null()).then(|| SCStream::from_id(stream));
+        sc_content_sharing_picker_callback_container(this).call_did_update_with_filter(filter, stream);
+    });
 }
 
 extern fn sc_content_sharing_picker_observer_start_did_fail_with_error(this: *mut AnyObject, _sel: Sel, error: *mut AnyObject) {
-    //println!("sc_content_sharing_picker_observer_start_did_fail_with_error");
+    catch_os_callback_panic(|| unsafe {
+        sc_content_sharing_picker_callback_container(this).call_error(NSError::from_id_unretained(error));
+    });
 }
 
 extern fn sc_content_sharing_picker_observer_dealloc(this: *mut AnyObject, _sel: Sel) {
-    unsafe {
-        
-        let callback_container: Box<SCContentSharingPickerCallbackContainer> = Box::from_raw(*(&*this).class().instance_variable("callback_container_ptr").unwrap().load::<*mut c_void>(&*this) as *mut SCContentSharingPickerCallbackContainer);
+    catch_os_callback_panic(|| unsafe {
+        let callback_container: Box<SCContentSharingPickerCallbackContainer> = Box::from_raw(sc_content_sharing_picker_callback_container(this));
         drop(callback_container);
-    }
+    });
 }
 
 impl SCContentSharingPickerObserver {
     fn get_class() -> &'static AnyClass {
         unsafe {
-            if let Some(mut class) = ClassBuilder::new("SCContentSharingPickerObserverImpl", class!(NSAnyObject)) {
+            if let Some(mut class) = ClassBuilder::new("SCContentSharingPickerObserverImpl", class!(NSObject)) {
                 class.add_method(sel!(contentSharingPicker:didCancelForStream:), sc_content_sharing_picker_observer_did_cancel_for_stream as extern fn (*mut AnyObject, Sel, *mut AnyObject, *mut AnyObject));
                 class.add_method(sel!(contentSharingPicker:didUpdateWithFilter:forStream:), sc_content_sharing_picker_observer_did_update_filter_for_stream as extern fn (*mut AnyObject, Sel, *mut AnyObject, *mut AnyObject, *mut AnyObject));
                 class.add_method(sel!(contentSharingPickerStartDidFailWithError:), sc_content_sharing_picker_observer_start_did_fail_with_error as extern fn (*mut AnyObject, Sel, *mut AnyObject));
                 class.add_method(sel!(dealloc), sc_content_sharing_picker_observer_dealloc as extern fn (*mut AnyObject, Sel));
+                if let Some(protocol) = AnyProtocol::get("SCContentSharingPickerObserver") {
+                    class.add_protocol(protocol);
+                }
 
                 class.add_ivar::<*mut c_void>("callback_container_ptr");
                 
@@ -3082,7 +3874,7 @@ impl SCContentSharingPickerObserver {
             let class = Self::get_class();
             let id: *mut AnyObject = msg_send![class, alloc];
             let id: *mut AnyObject = msg_send![id, init];
-            let callback_container_ptr_ivar = class!(SCShareableContent).instance_variable("callback_container_ptr").expect("Expected callback_container_ptr ivar on SCContentSharingPickerObserver");
+            let callback_container_ptr_ivar = class.instance_variable("callback_container_ptr").expect("Expected callback_container_ptr ivar on SCContentSharingPickerObserver");
             *callback_container_ptr_ivar.load_mut(&mut *id) = callback_container_ptr;
 
             Self(id)
@@ -3122,13 +3914,16 @@ impl SCContentSharingPickerCallbackContainer {
         }
     }
 
-    pub fn call_cancelled(&mut self, picker: SCContentSharingPicker, stream: Option<SCStream>) {
+    pub fn call_cancelled(&mut self) {
+        (self.callback)(Ok(SCContentSharingPickerEvent::Cancelled));
     }
 
-    pub fn call_did_update_with_filter(&mut self, picker: SCContentSharingPicker, filter: SCContentFilter, stream: Option<SCStream>) {
+    pub fn call_did_update_with_filter(&mut self, filter: SCContentFilter, stream: Option<SCStream>) {
+        (self.callback)(Ok(SCContentSharingPickerEvent::DidUpdate { filter, stream }));
     }
 
     pub fn call_error(&mut self, error: NSError) {
+        (self.callback)(Err(error));
     }
 }
 
@@ -3142,6 +3937,10 @@ pub enum SCShareableContentStyle {
 pub struct SCContentSharingPicker(*mut AnyObject);
 
 impl SCContentSharingPicker {
+    pub fn class_exists() -> bool {
+        AnyClass::get("SCContentSharingPicker").is_some()
+    }
+
     pub fn shared() -> Self {
         unsafe {
             let id: *mut AnyObject = msg_send![class!(SCContentSharingPicker), sharedPicker];
@@ -3166,15 +3965,15 @@ impl SCContentSharingPicker {
         }
     }
 
-    pub fn add(&self, observer: SCContentSharingPickerObserver) {
+    pub fn add(&self, observer: &SCContentSharingPickerObserver) {
         unsafe {
-            let _: () = msg_send![self.0, addObserver: observer];
+            let _: () = msg_send![self.0, addObserver: observer.0];
         }
     }
 
-    pub fn remove(&self, observer: SCContentSharingPickerObserver) {
+    pub fn remove(&self, observer: &SCContentSharingPickerObserver) {
         unsafe {
-            let _: () = msg_send![self.0, removeObserver: observer];
+            let _: () = msg_send![self.0, removeObserver: observer.0];
         }
     }
 
@@ -3233,11 +4032,11 @@ impl SCScreenshotManager {
     pub fn capture_image_with_filter_and_configuration(filter: &SCContentFilter, config: &SCStreamConfiguration, completion_handler: impl FnMut(Result<CGImage, NSError>) + Send + 'static) {
         let completion_handler = Mutex::new(completion_handler);
         let completion_block = RcBlock::new(move |image: CGImageRef, error: *mut AnyObject| {
-            if error.is_null() {
+            catch_os_callback_panic(|| if error.is_null() {
                 (completion_handler.lock())(Ok(CGImage::from_ref_unretained(image)));
             } else {
                 (completion_handler.lock())(Err(NSError::from_id_unretained(error)));
-            }
+            });
         });
         unsafe {
             let _: () = msg_send![
@@ -3249,21 +4048,21 @@ impl SCScreenshotManager {
         }
     }
 
-    pub fn capture_samplebuffer_with_filter_and_configuration(filter: SCContentFilter, config: SCStreamConfiguration, completion_handler: impl FnMut(Result<CMSampleBuffer, String>) + Send + 'static) {
+    pub fn capture_samplebuffer_with_filter_and_configuration(filter: SCContentFilter, config: SCStreamConfiguration, completion_handler: impl FnMut(Result<CMSampleBuffer, SCStreamCallbackError>) + Send + 'static) {
         unsafe {
             let completion_handler = Arc::new(Mutex::new(completion_handler));
             let completion_block = StackBlock::new(move |sample_buffer: CMSampleBufferRef, error: *mut AnyObject| {
-                if error.is_null() {
+                catch_os_callback_panic(|| if error.is_null() {
                     (completion_handler.lock())(
-                        CMSampleBuffer::copy_from_ref(sample_buffer)
-                            .map_err(|_| "Failed to copy sample buffer".to_string())
+                        CMSampleBuffer::retain_from_ref(sample_buffer)
+                            .ok_or(SCStreamCallbackError::MissingSampleBuffer)
                     );
                 } else {
-                    let error = NSError::from_id_unretained(error).description();
+                    let error = NSError::from_id_unretained(error);
                     (completion_handler.lock())(
-                        Err(error)
+                        Err(SCStreamCallbackError::Other(error))
                     );
-                }
+                });
             }).copy();
             let mut error: *mut AnyObject = std::ptr::null_mut();
             let _: () = msg_send![
@@ -3278,6 +4077,7 @@ impl SCScreenshotManager {
 
 pub(crate) struct WindowDescription {
     pub window_layer: i32,
+    pub on_screen: bool,
 }
 
 pub(crate) fn get_window_description(window: CGWindowID) -> Result<WindowDescription, ()> {
@@ -3302,13 +4102,46 @@ pub(crate) fn get_window_description(window: CGWindowID) -> Result<WindowDescrip
             return Err(());
         }
         let window_layer = NSNumber::from_id_unretained(window_layer_nsnumber as *mut AnyObject);
+        // The on-screen flag is left out of the descriptions of windows which aren't on screen
+        let on_screen_cfboolean = description.get_value(kCGWindowIsOnscreen);
+        let on_screen = !on_screen_cfboolean.is_null() && NSNumber::from_id_unretained(on_screen_cfboolean as *mut AnyObject).as_bool();
         
         Ok(WindowDescription {
             window_layer: window_layer.as_i32(),
+            on_screen,
         })
     }
 }
 
+const kCGWindowListOptionOnScreenOnly: u32 = 1 << 0;
+const kCGWindowListExcludeDesktopElements: u32 = 1 << 4;
+const kCGNullWindowID: u32 = 0;
+
+// Get the ids of the windows currently on screen, ordered front to back
+pub(crate) fn get_on_screen_window_ids() -> Result<Vec<CGWindowID>, ()> {
+    unsafe {
+        let description_array = CGWindowListCopyWindowInfo(kCGWindowListOptionOnScreenOnly | kCGWindowListExcludeDesktopElements, kCGNullWindowID);
+        if description_array.is_null() {
+            return Err(());
+        }
+        let descriptions = CFArray::from_ref_retained(description_array);
+        let mut window_ids = Vec::with_capacity(descriptions.get_count() as usize);
+        for i in 0..descriptions.get_count() {
+            let description_dictionary = descriptions.get_value_at_index(i);
+            if description_dictionary.is_null() {
+                continue;
+            }
+            let description = CFDictionary::from_ref_unretained(description_dictionary);
+            let window_number = description.get_value(kCGWindowNumber);
+            if window_number.is_null() {
+                continue;
+            }
+            window_ids.push(CGWindowID(NSNumber::from_id_unretained(window_number as *mut AnyObject).as_i32() as u32));
+        }
+        Ok(window_ids)
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct WindowLevels {
     pub base                : i32,
@@ -3391,3 +4224,100 @@ lazy_static! {
 pub(crate) fn get_window_levels() -> &'static WindowLevels {
     &*WINDOW_LEVELS
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::c_void;
+
+    use super::{kCMSampleBufferError_ArrayTooSmall, AudioBuffer, AudioBufferList, AudioBufferListError, AudioBufferListStorage};
+
+    // Lays out a synthetic AudioBufferList in storage with room for `capacity` buffers - the header reports every buffer given,
+    // but only those that fit are written
+    fn synthetic_buffer_list(capacity: usize, buffers: &mut [(u32, Vec<u8>)]) -> AudioBufferListStorage {
+        let size = std::mem::offset_of!(AudioBufferList, buffers) + capacity * std::mem::size_of::<AudioBuffer>();
+        let mut storage = AudioBufferListStorage::with_size(size);
+        unsafe {
+            let buffer_list = storage.as_mut_ptr();
+            (*buffer_list).number_buffers = buffers.len() as u32;
+            let first_buffer = std::ptr::addr_of_mut!((*buffer_list).buffers) as *mut AudioBuffer;
+            for (i, (number_channels, data)) in buffers.iter_mut().enumerate().take(capacity) {
+                first_buffer.add(i).write(AudioBuffer {
+                    number_channels: *number_channels,
+                    data_byte_size: data.len() as u32,
+                    data: data.as_mut_ptr() as *mut c_void,
+                });
+            }
+        }
+        storage
+    }
+
+    #[test]
+    fn storage_fits_at_least_one_buffer() {
+        let storage = AudioBufferListStorage::with_size(0);
+        assert_eq!(storage.size(), std::mem::size_of::<AudioBufferList>());
+        assert_eq!(storage.buffer_capacity(), 1);
+        assert_eq!(storage.as_ptr() as usize % std::mem::align_of::<AudioBufferList>(), 0);
+    }
+
+    #[test]
+    fn non_interleaved_buffers_hold_one_channel_each() {
+        let mut buffers = [(1, vec![1u8; 16]), (1, vec![2u8; 16])];
+        let storage = synthetic_buffer_list(2, &mut buffers);
+        assert_eq!(storage.buffer_count(), 2);
+        let (left, left_offset) = storage.channel_buffer(0).unwrap();
+        let (right, right_offset) = storage.channel_buffer(1).unwrap();
+        assert_eq!((left.data(), left_offset), (&[1u8; 16][..], 0));
+        assert_eq!((right.data(), right_offset), (&[2u8; 16][..], 0));
+        assert!(storage.channel_buffer(2).is_none());
+    }
+
+    #[test]
+    fn interleaved_buffer_holds_every_channel() {
+        let mut buffers = [(2, vec![0u8; 32])];
+        let storage = synthetic_buffer_list(1, &mut buffers);
+        let (buffer, offset) = storage.channel_buffer(1).unwrap();
+        assert_eq!((buffer.number_channels(), buffer.data().len(), offset), (2, 32, 1));
+        assert!(storage.channel_buffer(2).is_none());
+    }
+
+    #[test]
+    fn more_than_two_buffers_with_their_own_lengths() {
+        let mut buffers = [(1, vec![0u8; 4]), (2, vec![1u8; 24]), (1, vec![2u8; 8]), (1, vec![3u8; 12])];
+        let storage = synthetic_buffer_list(4, &mut buffers);
+        let lengths: Vec<usize> = (0..storage.buffer_count()).map(|i| storage.buffer(i).unwrap().data().len()).collect();
+        assert_eq!(lengths, [4, 24, 8, 12]);
+        // Channels 1 and 2 share the interleaved second buffer
+        let channels: Vec<(u8, usize)> = (0..5).map(|channel| {
+            let (buffer, offset) = storage.channel_buffer(channel).unwrap();
+            (buffer.data()[0], offset)
+        }).collect();
+        assert_eq!(channels, [(0, 0), (1, 0), (1, 1), (2, 0), (3, 0)]);
+        assert!(storage.channel_buffer(5).is_none());
+    }
+
+    #[test]
+    fn buffers_beyond_capacity_are_not_read() {
+        let mut buffers = [(1, vec![0u8; 4]), (1, vec![0u8; 4]), (1, vec![0u8; 4])];
+        let storage = synthetic_buffer_list(2, &mut buffers);
+        assert_eq!(storage.buffer_count(), 3);
+        assert_eq!(storage.buffer_capacity(), 2);
+        assert!(storage.buffer(1).is_some());
+        assert!(storage.buffer(2).is_none());
+        assert!(storage.channel_buffer(2).is_none());
+    }
+
+    #[test]
+    fn empty_buffer_has_no_data() {
+        let mut buffers = [(1, Vec::new())];
+        let storage = synthetic_buffer_list(1, &mut buffers);
+        assert!(storage.buffer(0).unwrap().data().is_empty());
+        let null_buffer = AudioBuffer { number_channels: 1, data_byte_size: 16, data: std::ptr::null_mut() };
+        assert!(null_buffer.data().is_empty());
+    }
+
+    #[test]
+    fn array_too_small_status_is_recognized() {
+        assert!(matches!(AudioBufferListError::from_status(kCMSampleBufferError_ArrayTooSmall), AudioBufferListError::ArrayTooSmall));
+        assert!(matches!(AudioBufferListError::from_status(-1), AudioBufferListError::Other(-1)));
+    }
+}
@@ -1,4 +1,5 @@
 #[cfg(target_os = "macos")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "macos")))]
 /// Macos-specific extensions
 pub mod macos;
 
@@ -6,10 +7,36 @@ pub mod macos;
 pub(crate) use macos as platform_impl;
 
 #[cfg(target_os = "windows")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "windows")))]
 /// Windows-specific extensions
 pub mod windows;
 
 #[cfg(target_os = "windows")]
 pub(crate)  use windows as platform_impl;
 
+// Documentation builds also render the other platform's extensions, from signature-only shims
+#[cfg(all(docsrs, not(target_os = "macos")))]
+#[cfg_attr(docsrs, doc(cfg(target_os = "macos")))]
+#[path = "docsrs/macos.rs"]
+/// Macos-specific extensions
+pub mod macos;
+
+#[cfg(all(docsrs, not(target_os = "windows")))]
+#[cfg_attr(docsrs, doc(cfg(target_os = "windows")))]
+#[path = "docsrs/windows.rs"]
+/// Windows-specific extensions
+pub mod windows;
+
+// Documentation builds on other hosts (such as docs.rs's linux containers) also use the stub
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+#[cfg(any(feature = "stub-unsupported", docsrs))]
+pub(crate) mod stub;
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+#[cfg(any(feature = "stub-unsupported", docsrs))]
+pub(crate) use stub as platform_impl;
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", feature = "stub-unsupported", docsrs)))]
+compile_error!("crabgrab supports only macOS and Windows; gate your dependency with a target cfg, or enable the `stub-unsupported` feature to compile against a stub which returns `UnsupportedPlatform` errors");
+
 
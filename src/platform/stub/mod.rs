@@ -0,0 +1,382 @@
+#![allow(unused)]
+
+use std::{ops::RangeInclusive, sync::Arc, time::{Duration, Instant}};
+
+use crate::capturable_content::{Capturable, CapturableContentError, CapturableContentFilter, WindowLayer};
+use crate::environment::PlatformEnvironment;
+use crate::capture_stream::{CaptureConfig, CapturePixelFormat, EffectiveCaptureConfig, OpenSettingsError, OpenSettingsOutcome, StreamCreateError, StreamError, StreamEvent, StreamStopError, StreamUpdateError};
+use crate::frame::{AudioBufferError, AudioCaptureFrame, AudioChannelCount, AudioChannelData, AudioSampleRate, ColorSpace, CursorImage, PlaneData, VideoCaptureFrame, VideoFrameMapError};
+use crate::util::{Point, Rect, Size};
+
+/// No instances of stub content, streams or frames can ever be created
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Unconstructible {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct StubCapturableWindow(Unconstructible);
+
+impl StubCapturableWindow {
+    pub fn from_impl(window: Self) -> Self {
+        window
+    }
+
+    pub fn id(&self) -> u64 {
+        match self.0 {}
+    }
+
+    pub fn title(&self) -> String {
+        match self.0 {}
+    }
+
+    pub fn rect(&self) -> Rect {
+        match self.0 {}
+    }
+
+    pub fn application(&self) -> StubCapturableApplication {
+        match self.0 {}
+    }
+
+    pub fn is_visible(&self) -> bool {
+        match self.0 {}
+    }
+
+    pub fn is_on_screen(&self) -> bool {
+        match self.0 {}
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        match self.0 {}
+    }
+
+    pub fn z_order(&self) -> Option<usize> {
+        match self.0 {}
+    }
+
+    pub fn z_order_hint(&self) -> WindowLayer {
+        match self.0 {}
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct StubCapturableDisplay(Unconstructible);
+
+impl StubCapturableDisplay {
+    pub fn from_impl(display: Self) -> Self {
+        display
+    }
+
+    pub fn rect(&self) -> Rect {
+        match self.0 {}
+    }
+
+    pub fn id(&self) -> u64 {
+        match self.0 {}
+    }
+
+    pub fn name(&self) -> Option<String> {
+        match self.0 {}
+    }
+
+    pub fn pixel_size(&self) -> Size {
+        match self.0 {}
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        match self.0 {}
+    }
+
+    pub fn refresh_rate(&self) -> Option<f64> {
+        match self.0 {}
+    }
+
+    pub fn mirror_group(&self) -> Option<u64> {
+        match self.0 {}
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct StubCapturableApplication(Unconstructible);
+
+impl StubCapturableApplication {
+    pub fn from_impl(application: Self) -> Self {
+        application
+    }
+
+    pub fn identifier(&self) -> String {
+        match self.0 {}
+    }
+
+    pub fn name(&self) -> String {
+        match self.0 {}
+    }
+
+    pub fn pid(&self) -> i32 {
+        match self.0 {}
+    }
+}
+
+pub(crate) struct StubCapturableContent {
+    pub(crate) windows: Vec<StubCapturableWindow>,
+    pub(crate) displays: Vec<StubCapturableDisplay>,
+    pub(crate) applications: Vec<StubCapturableApplication>,
+}
+
+impl StubCapturableContent {
+    pub fn window_z_orders(&self) -> Vec<Option<usize>> {
+        self.windows.iter().map(|window| window.z_order()).collect()
+    }
+
+    pub async fn new(filter: CapturableContentFilter) -> Result<Self, CapturableContentError> {
+        Err(CapturableContentError::UnsupportedPlatform)
+    }
+}
+
+pub(crate) struct StubContentChangeNotifier(Unconstructible);
+
+impl StubContentChangeNotifier {
+    pub(crate) const POLL_INTERVAL: Option<Duration> = None;
+
+    pub(crate) fn new(wake: impl Fn() + Send + Sync + 'static, window_geometry: bool) -> Result<Self, CapturableContentError> {
+        Err(CapturableContentError::UnsupportedPlatform)
+    }
+}
+
+// Braced rather than a unit struct - the shared filter code builds platform filters with `default()`, which clippy flags for unit structs
+#[derive(Clone, Default)]
+pub(crate) struct StubCapturableContentFilter {}
+
+impl StubCapturableContentFilter {
+    pub(crate) const DEFAULT: Self = Self {};
+    pub(crate) const NORMAL_WINDOWS: Self = Self {};
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct StubCaptureConfig;
+
+impl StubCaptureConfig {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct StubAudioCaptureConfig;
+
+impl StubAudioCaptureConfig {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct StubCaptureAccessToken(Unconstructible);
+
+impl StubCaptureAccessToken {
+    pub(crate) fn allows_borderless(&self) -> bool {
+        match self.0 {}
+    }
+}
+
+pub(crate) struct StubCaptureStream(Unconstructible);
+
+impl StubCaptureStream {
+    pub fn supported_pixel_formats() -> &'static [CapturePixelFormat] {
+        &[]
+    }
+
+    pub fn supported_buffer_counts() -> RangeInclusive<usize> {
+        1..=usize::MAX
+    }
+
+    pub fn check_access(borderless: bool) -> Option<StubCaptureAccessToken> {
+        None
+    }
+
+    pub async fn request_access(borderless: bool) -> Option<StubCaptureAccessToken> {
+        None
+    }
+
+    pub fn permission_settings_url() -> Option<&'static str> {
+        None
+    }
+
+    pub fn open_permission_settings() -> Result<OpenSettingsOutcome, OpenSettingsError> {
+        Err(OpenSettingsError::Unavailable)
+    }
+
+    // Takes the same boxed callback as the real backends, so `CaptureStream::new` calls every backend the same way
+    #[allow(clippy::boxed_local)]
+    pub fn new(token: StubCaptureAccessToken, config: CaptureConfig, callback: Box<impl FnMut(Result<StreamEvent, StreamError>) + Send + 'static>) -> Result<Self, StreamCreateError> {
+        match token.0 {}
+    }
+
+    pub fn stop(&self) -> Result<(), StreamStopError> {
+        match self.0 {}
+    }
+
+    pub fn stop_handle(&self) -> StubStopHandle {
+        match self.0 {}
+    }
+
+    pub async fn close(self) -> Result<(), StreamStopError> {
+        match self.0 {}
+    }
+
+    pub fn update_target(&mut self, target: Capturable) -> Result<(), StreamUpdateError> {
+        match self.0 {}
+    }
+
+    pub fn update_config(&mut self, config: &CaptureConfig) -> Result<(), StreamUpdateError> {
+        match self.0 {}
+    }
+
+    pub fn set_source_rect(&mut self, source_rect: Option<Rect>, content_size: Size) -> Result<(), StreamError> {
+        match self.0 {}
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        match self.0 {}
+    }
+
+    pub fn effective_config(&self) -> EffectiveCaptureConfig {
+        match self.0 {}
+    }
+
+    pub fn dropped_frame_count(&self) -> u64 {
+        match self.0 {}
+    }
+
+    pub fn output_size(&self) -> Size {
+        match self.0 {}
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct StubStopHandle(Unconstructible);
+
+impl StubStopHandle {
+    pub fn stop(&self, deliver_end: bool) -> Result<(), StreamStopError> {
+        match self.0 {}
+    }
+}
+
+pub(crate) struct StubVideoFrame(Unconstructible);
+
+impl VideoCaptureFrame for StubVideoFrame {
+    fn size(&self) -> Size {
+        match self.0 {}
+    }
+
+    fn dpi(&self) -> f64 {
+        match self.0 {}
+    }
+
+    fn duration(&self) -> Duration {
+        match self.0 {}
+    }
+
+    fn origin_time(&self) -> Duration {
+        match self.0 {}
+    }
+
+    fn capture_time(&self) -> Instant {
+        match self.0 {}
+    }
+
+    fn frame_id(&self) -> u64 {
+        match self.0 {}
+    }
+
+    fn content_rect(&self) -> Rect {
+        match self.0 {}
+    }
+
+    fn native_content_size(&self) -> Size {
+        match self.0 {}
+    }
+
+    fn cursor_position(&self) -> Option<Point> {
+        match self.0 {}
+    }
+
+    fn cursor_visible(&self) -> bool {
+        match self.0 {}
+    }
+
+    fn cursor_image(&self) -> Option<Arc<CursorImage>> {
+        match self.0 {}
+    }
+
+    fn dirty_rects(&self) -> Option<Vec<Rect>> {
+        match self.0 {}
+    }
+
+    fn color_space(&self) -> ColorSpace {
+        match self.0 {}
+    }
+
+    fn map_planes<T>(&self, _map: impl FnOnce(&[PlaneData<'_>]) -> T) -> Result<T, VideoFrameMapError> {
+        match self.0 {}
+    }
+}
+
+pub(crate) struct StubAudioFrame(Unconstructible);
+
+impl AudioCaptureFrame for StubAudioFrame {
+    fn sample_rate(&self) -> AudioSampleRate {
+        match self.0 {}
+    }
+
+    fn channel_count(&self) -> AudioChannelCount {
+        match self.0 {}
+    }
+
+    fn audio_channel_buffer(&mut self, channel: usize) -> Result<AudioChannelData<'_>, AudioBufferError> {
+        match self.0 {}
+    }
+
+    fn duration(&self) -> Duration {
+        match self.0 {}
+    }
+
+    fn origin_time(&self) -> Duration {
+        match self.0 {}
+    }
+
+    fn silent(&self) -> bool {
+        match self.0 {}
+    }
+
+    fn discontinuity(&self) -> bool {
+        match self.0 {}
+    }
+
+    fn frame_id(&self) -> u64 {
+        match self.0 {}
+    }
+}
+
+pub(crate) fn probe_environment() -> PlatformEnvironment {
+    PlatformEnvironment {
+        os_version: None,
+        capture_backend: None,
+        gpu_adapter: None,
+        platform_apis: vec![],
+    }
+}
+
+pub(crate) use StubCapturableApplication as ImplCapturableApplication;
+pub(crate) use StubCapturableDisplay as ImplCapturableDisplay;
+pub(crate) use StubCapturableWindow as ImplCapturableWindow;
+pub(crate) use StubCapturableContent as ImplCapturableContent;
+pub(crate) use StubCapturableContentFilter as ImplCapturableContentFilter;
+pub(crate) use StubContentChangeNotifier as ImplContentChangeNotifier;
+
+pub(crate) use StubCaptureStream as ImplCaptureStream;
+pub(crate) use StubStopHandle as ImplStopHandle;
+pub(crate) use StubCaptureConfig as ImplCaptureConfig;
+pub(crate) use StubAudioCaptureConfig as ImplAudioCaptureConfig;
+pub(crate) use StubCaptureAccessToken as ImplCaptureAccessToken;
+
+pub(crate) use StubVideoFrame as ImplVideoFrame;
+pub(crate) use StubAudioFrame as ImplAudioFrame;
@@ -1,12 +1,23 @@
-use std::{ffi::c_void, time::Duration};
+use std::{ffi::c_void, sync::{atomic::{self, AtomicBool}, Arc}, time::Duration};
 
-use windows::{core::Interface, Win32::{Media::Audio::{eConsole, eRender, IAudioCaptureClient, IAudioClient, IMMDeviceEnumerator, MMDeviceEnumerator, AUDCLNT_SHAREMODE_SHARED, AUDCLNT_STREAMFLAGS_LOOPBACK, WAVEFORMATEX, WAVE_FORMAT_PCM}, System::Com::{CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_ALL, COINIT_MULTITHREADED}}};
+use windows::{core::Interface, Win32::{Media::Audio::{eConsole, eRender, IAudioCaptureClient, IAudioClient, IMMDeviceEnumerator, MMDeviceEnumerator, AUDCLNT_BUFFERFLAGS_DATA_DISCONTINUITY, AUDCLNT_BUFFERFLAGS_SILENT, AUDCLNT_SHAREMODE_SHARED, AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM, AUDCLNT_STREAMFLAGS_LOOPBACK, AUDCLNT_STREAMFLAGS_SRC_DEFAULT_QUALITY, WAVEFORMATEX}, System::{Com::{CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_ALL, COINIT_MULTITHREADED}, Threading::THREAD_PRIORITY}}};
 
 use crate::prelude::{AudioCaptureConfig, AudioChannelCount, AudioSampleRate};
 
+use super::capture_stream::set_current_thread_priority;
+
+// WAVE_FORMAT_IEEE_FLOAT, from mmreg.h
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+
+// The length of the shared mode buffer, in 100ns units - long enough to ride out scheduling hiccups of the polling thread
+const AUDIO_BUFFER_DURATION: i64 = 1_000_000;
+// How often the capture thread drains packets from the buffer
+const AUDIO_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 pub struct WindowsAudioCaptureStream {
     should_couninit: bool,
     audio_client: IAudioClient,
+    stopped_flag: Arc<AtomicBool>,
 }
 
 pub enum WindowsAudioCaptureStreamCreateError {
@@ -23,13 +34,15 @@ pub enum WindowsAudioCaptureStreamError {
     GetBufferFailed,
 }
 
-#[allow(unused)]
 pub struct WindowsAudioCaptureStreamPacket<'a> {
-    pub(crate) data: &'a [i16],
-    pub(crate) channel_count: u32,
+    // Interleaved samples, `channel_count` per frame
+    pub(crate) data: &'a [f32],
     pub(crate) origin_time: Duration,
     pub(crate) duration: Duration,
-    pub(crate) sample_index: u64,
+    // The packet holds no sound - the audio engine doesn't fill the buffers of silent packets, so `data` is zeroed
+    pub(crate) silent: bool,
+    // Samples were lost between the previous packet and this one
+    pub(crate) discontinuity: bool,
 }
 
 struct SendCaptureClient(*mut c_void);
@@ -47,8 +60,21 @@ impl SendCaptureClient {
     }
 }
 
+fn sample_rate_hz(sample_rate: AudioSampleRate) -> u32 {
+    match sample_rate {
+        AudioSampleRate::Hz8000  =>  8000,
+        AudioSampleRate::Hz16000 => 16000,
+        AudioSampleRate::Hz24000 => 24000,
+        AudioSampleRate::Hz48000 => 48000,
+    }
+}
+
+fn frames_to_duration(frames: u64, sample_rate: u32) -> Duration {
+    Duration::from_nanos((frames as u128 * 1_000_000_000u128 / sample_rate as u128) as u64)
+}
+
 impl WindowsAudioCaptureStream {
-    pub fn new(config: AudioCaptureConfig, mut callback: Box<dyn for <'a> FnMut(Result<WindowsAudioCaptureStreamPacket<'a>, WindowsAudioCaptureStreamError>) + Send + 'static>) -> Result<Self, WindowsAudioCaptureStreamCreateError> {
+    pub fn new(config: AudioCaptureConfig, thread_priority: Option<THREAD_PRIORITY>, mut callback: Box<dyn for <'a> FnMut(Result<WindowsAudioCaptureStreamPacket<'a>, WindowsAudioCaptureStreamError>) + Send + 'static>) -> Result<Self, WindowsAudioCaptureStreamCreateError> {
         unsafe {
             let should_couninit = CoInitializeEx(None, COINIT_MULTITHREADED).is_ok();
 
@@ -56,36 +82,28 @@ impl WindowsAudioCaptureStream {
                 .map_err(|e| WindowsAudioCaptureStreamCreateError::Other(format!("Failed to create MMDeviceEnumerator: {}", e.to_string())))?;
             let device = mm_device_enumerator.GetDefaultAudioEndpoint(eRender, eConsole)
                 .map_err(|_| WindowsAudioCaptureStreamCreateError::EndpointEnumerationFailed)?;
-            
+
             let audio_client: IAudioClient = device.Activate(CLSCTX_ALL, None)
                 .map_err(|_| WindowsAudioCaptureStreamCreateError::AudioClientActivationFailed)?;
 
-            let mut format = WAVEFORMATEX::default();
-            format.wFormatTag = WAVE_FORMAT_PCM as u16;
-            format.nSamplesPerSec = match config.sample_rate {
-                AudioSampleRate::Hz8000  =>  8000,
-                AudioSampleRate::Hz16000 => 16000,
-                AudioSampleRate::Hz24000 => 24000,
-                AudioSampleRate::Hz48000 => 48000,
-            };
-            format.wBitsPerSample = 16;
-            format.nChannels = match config.channel_count {
+            let sample_rate = sample_rate_hz(config.sample_rate);
+            let channel_count: u16 = match config.channel_count {
                 AudioChannelCount::Mono   => 1,
                 AudioChannelCount::Stereo => 2,
             };
-            format.nBlockAlign = format.nChannels * 2;
-            format.nAvgBytesPerSec = format.nSamplesPerSec * format.nBlockAlign as u32;
-            format.cbSize = 0;
-
-            let callback_format = format.clone();
 
-            let buffer_size = 512;
-            let buffer_time = buffer_size as i64 * 10000000i64 / format.nSamplesPerSec as i64;
-
-            let buffer_duration = Duration::from_nanos(buffer_time as u64 * 100);
-            let half_buffer_duration = buffer_duration / 2;
+            let mut format = WAVEFORMATEX::default();
+            format.wFormatTag = WAVE_FORMAT_IEEE_FLOAT;
+            format.nSamplesPerSec = sample_rate;
+            format.wBitsPerSample = 32;
+            format.nChannels = channel_count;
+            format.nBlockAlign = channel_count * 4;
+            format.nAvgBytesPerSec = sample_rate * channel_count as u32 * 4;
+            format.cbSize = 0;
 
-            audio_client.Initialize(AUDCLNT_SHAREMODE_SHARED, AUDCLNT_STREAMFLAGS_LOOPBACK, buffer_time, buffer_time, &format as *const _, None)
+            // The shared mode engine only accepts its mix format unless it's asked to convert to the requested rate and channel count
+            let stream_flags = AUDCLNT_STREAMFLAGS_LOOPBACK | AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM | AUDCLNT_STREAMFLAGS_SRC_DEFAULT_QUALITY;
+            audio_client.Initialize(AUDCLNT_SHAREMODE_SHARED, stream_flags, AUDIO_BUFFER_DURATION, 0, &format as *const _, None)
                 .map_err(|_| WindowsAudioCaptureStreamCreateError::AudioClientInitializeFailed)?;
 
             let capture_client : IAudioCaptureClient = audio_client.GetService()
@@ -93,51 +111,65 @@ impl WindowsAudioCaptureStream {
 
             let capture_client_send = SendCaptureClient::from_iaudiocaptureclient(capture_client);
 
+            let stopped_flag = Arc::new(AtomicBool::new(false));
+            let thread_stopped_flag = stopped_flag.clone();
+
             std::thread::spawn(move || {
                 {
+                    set_current_thread_priority(thread_priority);
                     let should_couninit = CoInitializeEx(None, COINIT_MULTITHREADED).is_ok();
 
-                    let mut last_device_position = 0u64;
-                    let mut sample_count = 0u64;
+                    // The device position the next packet should start at, if no samples are lost
+                    let mut expected_device_position = None;
 
                     let capture_client = capture_client_send.into_iaudiocaptureclient();
-                    loop {
-                        std::thread::sleep(half_buffer_duration);
-
-                        let _buffered_count = match capture_client.GetNextPacketSize() {
-                            Ok(count) => count,
-                            Err(_) => {
-                                (callback)(Err(WindowsAudioCaptureStreamError::Other(format!("Stream failed - couldn't fetch packet size"))));
-                                break;
+                    'capture: while !thread_stopped_flag.load(atomic::Ordering::Acquire) {
+                        std::thread::sleep(AUDIO_POLL_INTERVAL);
+
+                        loop {
+                            match capture_client.GetNextPacketSize() {
+                                Ok(0) => break,
+                                Ok(_) => {},
+                                Err(_) => {
+                                    (callback)(Err(WindowsAudioCaptureStreamError::Other(format!("Stream failed - couldn't fetch packet size"))));
+                                    break 'capture;
+                                }
                             }
-                        };
-
-                        let mut data_ptr: *mut u8 = std::ptr::null_mut();
-
-                        let mut num_frames = 0u32;
-                        let mut flags = 0u32;
-                        let mut device_position = 0u64;
-
-                        match capture_client.GetBuffer(&mut data_ptr as *mut _, &mut num_frames as *mut _, &mut flags as *mut _, Some(&mut device_position as *mut _), None) {
-                            Ok(_) => {
-                                let packet = WindowsAudioCaptureStreamPacket {
-                                    data: std::slice::from_raw_parts(data_ptr as *const i16, num_frames as usize * 2),
-                                    channel_count: callback_format.nChannels as u32,
-                                    origin_time: Duration::from_nanos(device_position as u64 * 100),
-                                    duration: Duration::from_nanos((device_position - last_device_position) as u64),
-                                    sample_index: sample_count
-                                };
-                                (callback)(Ok(packet));
-                                let _ = capture_client.ReleaseBuffer(num_frames);
-                                last_device_position = device_position;
-                                sample_count += num_frames as u64;
-                            },
-                            Err(_) => {
+
+                            let mut data_ptr: *mut u8 = std::ptr::null_mut();
+
+                            let mut num_frames = 0u32;
+                            let mut flags = 0u32;
+                            let mut device_position = 0u64;
+
+                            if capture_client.GetBuffer(&mut data_ptr as *mut _, &mut num_frames as *mut _, &mut flags as *mut _, Some(&mut device_position as *mut _), None).is_err() {
                                 (callback)(Err(WindowsAudioCaptureStreamError::GetBufferFailed));
-                                break;
+                                break 'capture;
                             }
-                        }
 
+                            let sample_count = num_frames as usize * channel_count as usize;
+                            let silent = (flags & AUDCLNT_BUFFERFLAGS_SILENT.0 as u32) != 0;
+                            let silence;
+                            let data = if silent || data_ptr.is_null() {
+                                silence = vec![0.0f32; sample_count];
+                                &silence[..]
+                            } else {
+                                std::slice::from_raw_parts(data_ptr as *const f32, sample_count)
+                            };
+                            // The engine flags glitches it notices, but a jump in the device position also means samples were lost
+                            let discontinuity = (flags & AUDCLNT_BUFFERFLAGS_DATA_DISCONTINUITY.0 as u32) != 0 ||
+                                expected_device_position.is_some_and(|expected_device_position| device_position > expected_device_position);
+                            let packet = WindowsAudioCaptureStreamPacket {
+                                data,
+                                origin_time: frames_to_duration(device_position, sample_rate),
+                                duration: frames_to_duration(num_frames as u64, sample_rate),
+                                silent,
+                                discontinuity,
+                            };
+                            (callback)(Ok(packet));
+                            let _ = capture_client.ReleaseBuffer(num_frames);
+                            expected_device_position = Some(device_position + num_frames as u64);
+                        }
                     }
 
                     if should_couninit {
@@ -146,17 +178,26 @@ impl WindowsAudioCaptureStream {
                 }
             });
 
-            audio_client.Start()
-                .map_err(|_| WindowsAudioCaptureStreamCreateError::StreamStartFailed)?;
+            if audio_client.Start().is_err() {
+                stopped_flag.store(true, atomic::Ordering::Release);
+                return Err(WindowsAudioCaptureStreamCreateError::StreamStartFailed);
+            }
 
             Ok(WindowsAudioCaptureStream {
                 should_couninit,
-                audio_client
+                audio_client,
+                stopped_flag,
             })
         }
     }
 
+    // The flag which ends the stream's polling thread when set
+    pub fn stopped_flag(&self) -> Arc<AtomicBool> {
+        self.stopped_flag.clone()
+    }
+
     pub fn stop(&mut self) {
+        self.stopped_flag.store(true, atomic::Ordering::Release);
         unsafe {
             let _ = self.audio_client.Stop();
         }
@@ -165,6 +206,7 @@ impl WindowsAudioCaptureStream {
 
 impl Drop for WindowsAudioCaptureStream {
     fn drop(&mut self) {
+        self.stopped_flag.store(true, atomic::Ordering::Release);
         unsafe {
             let _ = self.audio_client.Stop();
             if self.should_couninit {
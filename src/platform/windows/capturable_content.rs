@@ -1,10 +1,10 @@
-use std::{ffi::OsString, hash::Hash, os::{raw::c_void, windows::ffi::OsStringExt}, sync::Arc};
+use std::{cell::RefCell, collections::HashMap, ffi::OsString, hash::Hash, os::{raw::c_void, windows::ffi::OsStringExt}, sync::Arc, thread::JoinHandle, time::Duration};
 
-use windows::Win32::{Foundation::{BOOL, LPARAM, RECT, TRUE}, Graphics::Gdi::{EnumDisplayMonitors, HDC, HMONITOR}, System::{ProcessStatus::GetModuleFileNameExW, Threading::{OpenProcess, PROCESS_QUERY_INFORMATION, PROCESS_VM_READ}}, UI::WindowsAndMessaging::{EnumWindows, GetWindowDisplayAffinity, GetWindowRect, GetWindowTextLengthW, GetWindowTextW, GetWindowThreadProcessId, IsWindow, IsWindowVisible, WDA_EXCLUDEFROMCAPTURE}};
+use windows::{core::{w, PCWSTR}, Win32::{Foundation::{BOOL, HINSTANCE, HMODULE, LPARAM, LRESULT, RECT, TRUE, WPARAM}, Graphics::Gdi::{EnumDisplayDevicesW, EnumDisplayMonitors, EnumDisplaySettingsW, GetMonitorInfoW, DEVMODEW, DISPLAY_DEVICEW, ENUM_CURRENT_SETTINGS, HDC, HMONITOR, MONITORINFO, MONITORINFOEXW}, System::{LibraryLoader::GetModuleHandleW, ProcessStatus::GetModuleFileNameExW, Threading::{GetCurrentThreadId, OpenProcess, PROCESS_QUERY_INFORMATION, PROCESS_VM_READ}}, UI::{Accessibility::{SetWinEventHook, UnhookWinEvent, HWINEVENTHOOK}, HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI}, WindowsAndMessaging::{CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, EnumWindows, GetAncestor, GetClassNameW, GetMessageW, GetWindowDisplayAffinity, GetWindowLongW, GetWindowRect, GetWindowTextLengthW, GetWindowTextW, GetWindowThreadProcessId, IsIconic, IsWindow, IsWindowVisible, PeekMessageW, PostThreadMessageW, RegisterClassW, TranslateMessage, CHILDID_SELF, EVENT_OBJECT_CREATE, EVENT_OBJECT_DESTROY, EVENT_OBJECT_HIDE, EVENT_SYSTEM_FOREGROUND, EVENT_SYSTEM_MINIMIZEEND, EVENT_SYSTEM_MINIMIZESTART, EVENT_SYSTEM_MOVESIZEEND, GA_ROOT, GWL_EXSTYLE, HMENU, MSG, OBJID_WINDOW, PM_NOREMOVE, WDA_EXCLUDEFROMCAPTURE, WINEVENT_OUTOFCONTEXT, WM_DISPLAYCHANGE, WM_QUIT, WM_USER, WNDCLASSW, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_OVERLAPPED}}}};
 
 pub use windows::Win32::Foundation::HWND;
 
-use crate::{prelude::{CapturableContentError, CapturableContentFilter, CapturableWindow}, util::{Point, Rect, Size}};
+use crate::{prelude::{CapturableContentError, CapturableContentFilter, CapturableWindow, WindowLayer}, util::{Point, Rect, Size}};
 
 use super::AutoHandle;
 
@@ -24,6 +24,10 @@ impl WindowsCapturableWindow {
         Self(hwnd)
     }
 
+    pub fn id(&self) -> u64 {
+        self.0.0 as u64
+    }
+
     pub fn title(&self) -> String {
         unsafe {
             let text_length = GetWindowTextLengthW(self.0);
@@ -63,6 +67,45 @@ impl WindowsCapturableWindow {
     pub fn is_visible(&self) -> bool {
         unsafe { IsWindowVisible(self.0).as_bool() }
     }
+
+    pub fn is_on_screen(&self) -> bool {
+        unsafe { IsWindowVisible(self.0).as_bool() && !IsIconic(self.0).as_bool() }
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        unsafe { IsIconic(self.0).as_bool() }
+    }
+
+    pub fn z_order(&self) -> Option<usize> {
+        if !self.is_on_screen() {
+            return None;
+        }
+        on_screen_windows().into_iter().position(|hwnd| hwnd == self.0)
+    }
+
+    pub fn z_order_hint(&self) -> WindowLayer {
+        if !unsafe { IsWindow(self.0).as_bool() } {
+            return WindowLayer::Normal;
+        }
+        let mut class_name = [0u16; 256];
+        let class_name_len = unsafe { GetClassNameW(self.0, &mut class_name) }.max(0) as usize;
+        match String::from_utf16_lossy(&class_name[..class_name_len]).as_str() {
+            // The taskbars and the desktop
+            "Shell_TrayWnd" | "Shell_SecondaryTrayWnd" | "Progman" | "WorkerW" => return WindowLayer::System,
+            // Menus
+            "#32768" => return WindowLayer::Overlay,
+            _ => {}
+        }
+        let ex_style = unsafe { GetWindowLongW(self.0, GWL_EXSTYLE) } as u32;
+        if ex_style & WS_EX_TOPMOST.0 == 0 {
+            WindowLayer::Normal
+        } else if ex_style & (WS_EX_TOOLWINDOW.0 | WS_EX_NOACTIVATE.0) != 0 {
+            // Topmost windows which never take focus or appear in the taskbar are popups or tooltips
+            WindowLayer::Overlay
+        } else {
+            WindowLayer::AboveNormal
+        }
+    }
 }
 
 impl Hash for WindowsCapturableWindow {
@@ -79,6 +122,12 @@ impl PartialEq for WindowsCapturableWindow {
 
 impl Eq for WindowsCapturableWindow {}
 
+// Decode a nul-terminated wide string from a fixed-size buffer
+fn wide_string(chars: &[u16]) -> String {
+    let length = chars.iter().position(|c| *c == 0).unwrap_or(chars.len());
+    OsString::from_wide(&chars[..length]).to_string_lossy().into_owned()
+}
+
 #[derive(Clone, Debug)]
 pub struct WindowsCapturableDisplay(pub(crate) HMONITOR, pub(crate) RECT);
 
@@ -87,6 +136,10 @@ impl WindowsCapturableDisplay {
         Self(monitor.0, monitor.1)
     }
 
+    pub fn id(&self) -> u64 {
+        self.0.0 as u64
+    }
+
     pub fn rect(&self) -> Rect {
         Rect {
             origin: Point {
@@ -99,6 +152,82 @@ impl WindowsCapturableDisplay {
             }
         }
     }
+
+    // The GDI device name of the monitor, such as `\\.\DISPLAY1`
+    fn device_name(&self) -> Option<[u16; 32]> {
+        unsafe {
+            let mut monitor_info = MONITORINFOEXW::default();
+            monitor_info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+            if !GetMonitorInfoW(self.0, &mut monitor_info as *mut MONITORINFOEXW as *mut MONITORINFO).as_bool() {
+                return None;
+            }
+            Some(monitor_info.szDevice)
+        }
+    }
+
+    fn display_mode(&self) -> Option<DEVMODEW> {
+        let device_name = self.device_name()?;
+        unsafe {
+            let mut display_mode = DEVMODEW::default();
+            display_mode.dmSize = std::mem::size_of::<DEVMODEW>() as u16;
+            if !EnumDisplaySettingsW(PCWSTR(device_name.as_ptr()), ENUM_CURRENT_SETTINGS, &mut display_mode as *mut _).as_bool() {
+                return None;
+            }
+            Some(display_mode)
+        }
+    }
+
+    pub fn name(&self) -> Option<String> {
+        let device_name = self.device_name()?;
+        unsafe {
+            // The first device attached to the adapter's output is the monitor itself
+            let mut display_device = DISPLAY_DEVICEW::default();
+            display_device.cb = std::mem::size_of::<DISPLAY_DEVICEW>() as u32;
+            if !EnumDisplayDevicesW(PCWSTR(device_name.as_ptr()), 0, &mut display_device as *mut _, 0).as_bool() {
+                return None;
+            }
+            let name = wide_string(&display_device.DeviceString);
+            if name.is_empty() {
+                None
+            } else {
+                Some(name)
+            }
+        }
+    }
+
+    pub fn pixel_size(&self) -> Size {
+        match self.display_mode() {
+            Some(display_mode) => Size {
+                width: display_mode.dmPelsWidth as f64,
+                height: display_mode.dmPelsHeight as f64,
+            },
+            None => self.rect().size,
+        }
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        let mut dpi_x = 0u32;
+        let mut dpi_y = 0u32;
+        unsafe {
+            match GetDpiForMonitor(self.0, MDT_EFFECTIVE_DPI, &mut dpi_x as *mut _, &mut dpi_y as *mut _) {
+                Ok(()) if dpi_x > 0 => dpi_x as f64 / 96.0,
+                _ => 1.0,
+            }
+        }
+    }
+
+    pub fn refresh_rate(&self) -> Option<f64> {
+        // 0 and 1 mean the hardware's default refresh rate
+        self.display_mode()
+            .map(|display_mode| display_mode.dmDisplayFrequency)
+            .filter(|refresh_rate| *refresh_rate > 1)
+            .map(|refresh_rate| refresh_rate as f64)
+    }
+
+    pub fn mirror_group(&self) -> Option<u64> {
+        // Duplicated ("cloned") outputs are enumerated as a single monitor
+        None
+    }
 }
 
 
@@ -121,6 +250,10 @@ impl Eq for WindowsCapturableDisplay {}
 pub struct WindowsCapturableApplication(pub(crate) u32);
 
 impl WindowsCapturableApplication {
+    pub fn from_impl(pid: u32) -> Self {
+        Self(pid)
+    }
+
     pub fn identifier(&self) -> String {
         unsafe {
             let process = OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, false, self.0);
@@ -169,6 +302,7 @@ impl WindowsCapturableApplication {
 pub struct WindowsCapturableContent {
     pub(crate) windows: Vec<HWND>,
     pub(crate) displays: Vec<(HMONITOR, RECT)>,
+    pub(crate) applications: Vec<u32>,
 }
 
 unsafe extern "system" fn enum_windows_callback(window: HWND, windows_ptr_raw: LPARAM) -> BOOL {
@@ -183,7 +317,27 @@ unsafe extern "system" fn enum_monitors_callback(monitor: HMONITOR, _: HDC, rect
     TRUE
 }
 
+// Get the top level windows which are shown and not minimized, front to back
+fn on_screen_windows() -> Vec<HWND> {
+    // Top level windows are enumerated front to back
+    let mut windows = Vec::<HWND>::new();
+    unsafe {
+        let _ = EnumWindows(Some(enum_windows_callback), LPARAM(&mut windows as *mut _ as *mut c_void as isize));
+    }
+    windows.retain(|hwnd| unsafe { IsWindowVisible(*hwnd).as_bool() && !IsIconic(*hwnd).as_bool() });
+    windows
+}
+
 impl WindowsCapturableContent {
+    // Get the z-order of every window, from a single enumeration - see `WindowsCapturableWindow::z_order()`
+    pub fn window_z_orders(&self) -> Vec<Option<usize>> {
+        let z_orders: HashMap<isize, usize> = on_screen_windows().into_iter()
+            .enumerate()
+            .map(|(z_order, hwnd)| (hwnd.0, z_order))
+            .collect();
+        self.windows.iter().map(|hwnd| z_orders.get(&hwnd.0).copied()).collect()
+    }
+
     pub async fn new(filter: CapturableContentFilter) -> Result<Self, CapturableContentError> {
         let mut displays = Vec::<(HMONITOR, RECT)>::new();
         let mut windows = Vec::<HWND>::new();
@@ -191,7 +345,7 @@ impl WindowsCapturableContent {
             if filter.displays {
                 EnumDisplayMonitors(HDC(0), None, Some(enum_monitors_callback), LPARAM(&mut displays as *mut _ as *mut c_void as isize));
             }
-            if let Some(window_filter) = filter.windows {
+            if let Some(window_filter) = &filter.windows {
                 let _ = EnumWindows(Some(enum_windows_callback), LPARAM(&mut windows as *mut _ as *mut c_void as isize));
                 windows = windows.iter().filter(|hwnd| {
                     if !IsWindow(**hwnd).as_bool() {
@@ -209,18 +363,144 @@ impl WindowsCapturableContent {
                     if !filter.impl_capturable_content_filter.filter_window_handle(hwnd) {
                         return false;
                     }
+                    if !filter.filter_window_rect(&WindowsCapturableWindow(**hwnd).rect()) {
+                        return false;
+                    }
                     // TODO: filter desktop windows
                     true
                 }).map(|hwnd| *hwnd).collect();
             }
         }
+        // There's no enumeration of applications on windows, so only applications owning an enumerated window are included
+        let mut applications = Vec::<u32>::new();
+        for hwnd in windows.iter() {
+            let pid = hwnd_pid(*hwnd);
+            if !applications.contains(&pid) {
+                applications.push(pid);
+            }
+        }
         Ok(WindowsCapturableContent {
             windows,
             displays,
+            applications,
         })
     }
 }
 
+thread_local! {
+    static CONTENT_CHANGE_WAKE: RefCell<Option<Arc<dyn Fn() + Send + Sync>>> = const { RefCell::new(None) };
+}
+
+fn wake_content_change_thread() {
+    CONTENT_CHANGE_WAKE.with(|wake| {
+        if let Some(wake) = wake.borrow().as_ref() {
+            (wake)();
+        }
+    });
+}
+
+unsafe extern "system" fn content_change_win_event_proc(_hook: HWINEVENTHOOK, event: u32, hwnd: HWND, id_object: i32, id_child: i32, _event_thread: u32, _event_time: u32) {
+    if hwnd.0 == 0 || id_object != OBJID_WINDOW.0 || id_child != CHILDID_SELF as i32 {
+        return;
+    }
+    // Only top level windows are enumerated, but a destroyed window no longer has an ancestor to check
+    if event != EVENT_OBJECT_DESTROY && GetAncestor(hwnd, GA_ROOT) != hwnd {
+        return;
+    }
+    wake_content_change_thread();
+}
+
+unsafe extern "system" fn content_change_window_proc(hwnd: HWND, message: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if message == WM_DISPLAYCHANGE {
+        wake_content_change_thread();
+    }
+    DefWindowProcW(hwnd, message, wparam, lparam)
+}
+
+// WM_DISPLAYCHANGE is only broadcast to top level windows, so a hidden one is created to receive it
+unsafe fn create_display_change_window() -> HWND {
+    let instance = HINSTANCE::from(GetModuleHandleW(None).unwrap_or_default());
+    let class_name = w!("CrabGrabDisplayChangeWindow");
+    let window_class = WNDCLASSW {
+        lpfnWndProc: Some(content_change_window_proc),
+        hInstance: instance,
+        lpszClassName: class_name,
+        ..Default::default()
+    };
+    // Registering the class again for another notifier fails harmlessly
+    let _ = RegisterClassW(&window_class as *const _);
+    CreateWindowExW(WS_EX_TOOLWINDOW, class_name, w!(""), WS_OVERLAPPED, 0, 0, 0, 0, HWND::default(), HMENU::default(), instance, None)
+}
+
+/// Wakes a callback when top level windows are created, destroyed, shown or hidden, or the display configuration changes - until dropped
+/// 
+/// With `window_geometry`, the callback is also woken when a window is activated, minimized or restored, or moved or resized by the user.
+pub(crate) struct WindowsContentChangeNotifier {
+    thread_id: u32,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WindowsContentChangeNotifier {
+    pub(crate) const POLL_INTERVAL: Option<Duration> = None;
+
+    pub(crate) fn new(wake: impl Fn() + Send + Sync + 'static, window_geometry: bool) -> Result<Self, CapturableContentError> {
+        let wake: Arc<dyn Fn() + Send + Sync> = Arc::new(wake);
+        let (init_tx, init_rx) = std::sync::mpsc::channel();
+        // Out of context WinEvents and window messages are both delivered through the hooking thread's message loop
+        let thread = std::thread::spawn(move || {
+            CONTENT_CHANGE_WAKE.with(|thread_wake| *thread_wake.borrow_mut() = Some(wake));
+            unsafe {
+                let hook = SetWinEventHook(EVENT_OBJECT_CREATE, EVENT_OBJECT_HIDE, HMODULE::default(), Some(content_change_win_event_proc), 0, 0, WINEVENT_OUTOFCONTEXT);
+                if hook.0 == 0 {
+                    let _ = init_tx.send(Err(CapturableContentError::Other("Failed to hook window events".into())));
+                    return;
+                }
+                // Programmatic moves are only reported by EVENT_OBJECT_LOCATIONCHANGE, which fires for every caret and cursor movement too
+                let geometry_hooks: Vec<HWINEVENTHOOK> = if window_geometry {
+                    [(EVENT_SYSTEM_FOREGROUND, EVENT_SYSTEM_FOREGROUND), (EVENT_SYSTEM_MOVESIZEEND, EVENT_SYSTEM_MOVESIZEEND), (EVENT_SYSTEM_MINIMIZESTART, EVENT_SYSTEM_MINIMIZEEND)]
+                        .into_iter()
+                        .map(|(event_min, event_max)| SetWinEventHook(event_min, event_max, HMODULE::default(), Some(content_change_win_event_proc), 0, 0, WINEVENT_OUTOFCONTEXT))
+                        .filter(|hook| hook.0 != 0)
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                let window = create_display_change_window();
+                // Make sure this thread has a message queue before anything is posted to it
+                let mut message = MSG::default();
+                let _ = PeekMessageW(&mut message as *mut _, HWND::default(), WM_USER, WM_USER, PM_NOREMOVE);
+                let _ = init_tx.send(Ok(GetCurrentThreadId()));
+                while GetMessageW(&mut message as *mut _, HWND::default(), 0, 0).as_bool() {
+                    TranslateMessage(&message as *const _);
+                    DispatchMessageW(&message as *const _);
+                }
+                if window.0 != 0 {
+                    let _ = DestroyWindow(window);
+                }
+                for geometry_hook in geometry_hooks {
+                    let _ = UnhookWinEvent(geometry_hook);
+                }
+                let _ = UnhookWinEvent(hook);
+            }
+        });
+        let thread_id = init_rx.recv()
+            .map_err(|error| CapturableContentError::Other(format!("Failed to receive content change thread start: {}", error)))??;
+        Ok(Self {
+            thread_id,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for WindowsContentChangeNotifier {
+    fn drop(&mut self) {
+        let _ = unsafe { PostThreadMessageW(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0)) };
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
 /// Windows-specific extensions for capturable windows
 pub trait WindowsCapturableWindowExt {
     /// Get the HWND for this capturable window.
@@ -244,9 +524,7 @@ impl WindowsCapturableWindowExt for CapturableWindow {
                 return Err(CapturableContentError::Other(format!("HWND {:016X} is not capturable a window", window_handle.0)));
             }
         }
-        return Ok(CapturableWindow {
-            impl_capturable_window: WindowsCapturableWindow(window_handle)
-        })
+        return Ok(CapturableWindow::from_impl(WindowsCapturableWindow(window_handle)))
     }
 }
 
@@ -1,11 +1,17 @@
-use std::{fmt::Debug, sync::{atomic::{self, AtomicBool, AtomicU64}, Arc}, time::{Duration, Instant}};
+use std::{ffi::c_void, fmt::Debug, ops::RangeInclusive, sync::{atomic::{self, AtomicBool, AtomicU64}, Arc, OnceLock}, time::{Duration, Instant}};
 
-use crate::prelude::{AudioFrame, Capturable, CaptureConfig, CapturePixelFormat, StreamCreateError, StreamError, StreamEvent, StreamStopError, VideoFrame};
+use crate::capture_stream::{FrameIntervalLimiter, FrameLayout, WINDOWS_PIXEL_FORMATS};
+use crate::compositor::{CompositorTarget, SharedFrameCompositor};
+use crate::frame::CursorImage;
+use crate::prelude::{AudioFrame, Capturable, CaptureTarget, Point, PowerProfile, Rect, ScalingQuality, Size, CaptureConfig, CapturePixelFormat, EffectiveCaptureConfig, OpenSettingsError, OpenSettingsOutcome, PlatformErrorCode, StreamCreateError, StreamEndReason, StreamError, StreamEvent, StreamStopError, StreamUpdateError, VideoFrame};
 
+use futures::channel::oneshot;
 use parking_lot::Mutex;
-use windows::{core::{ComInterface, IInspectable, HSTRING}, Foundation::TypedEventHandler, Graphics::{Capture::{Direct3D11CaptureFramePool, GraphicsCaptureAccess, GraphicsCaptureAccessKind, GraphicsCaptureItem, GraphicsCaptureSession}, DirectX::{Direct3D11::IDirect3DDevice, DirectXPixelFormat}, SizeInt32}, Security::Authorization::AppCapabilityAccess::{AppCapability, AppCapabilityAccessStatus}, Win32::{Foundation::HWND, Graphics::{Direct3D::{D3D_DRIVER_TYPE_UNKNOWN, D3D_FEATURE_LEVEL_11_0}, Direct3D11::{D3D11CreateDevice, ID3D11Device, D3D11_CREATE_DEVICE_BGRA_SUPPORT, D3D11_SDK_VERSION}, Dxgi::{CreateDXGIFactory, IDXGIAdapter, IDXGIAdapter4, IDXGIDevice, IDXGIFactory5}}, System::{Com::COINIT_APARTMENTTHREADED, WinRT::{CreateDispatcherQueueController, Direct3D11::CreateDirect3D11DeviceFromDXGIDevice, DispatcherQueueOptions, Graphics::Capture::IGraphicsCaptureItemInterop, DQTAT_COM_NONE, DQTYPE_THREAD_CURRENT}}, UI::{HiDpi::{GetDpiForMonitor, GetDpiForWindow, MDT_RAW_DPI}, WindowsAndMessaging::{DispatchMessageW, GetMessageW, TranslateMessage, MSG}}}};
+use windows::{core::{ComInterface, IInspectable, HSTRING}, Foundation::{EventRegistrationToken, Metadata::ApiInformation, TypedEventHandler, Uri}, Graphics::{Capture::{Direct3D11CaptureFramePool, GraphicsCaptureAccess, GraphicsCaptureAccessKind, GraphicsCaptureItem, GraphicsCaptureSession}, DirectX::{Direct3D11::{IDirect3DDevice, IDirect3DSurface}, DirectXPixelFormat}, SizeInt32}, Security::Authorization::AppCapabilityAccess::{AppCapability, AppCapabilityAccessStatus}, System::Launcher, Win32::{Foundation::{HWND, LPARAM, RECT, WPARAM}, Graphics::{Direct3D::{D3D_DRIVER_TYPE, D3D_DRIVER_TYPE_HARDWARE, D3D_DRIVER_TYPE_UNKNOWN, D3D_FEATURE_LEVEL_11_0}, Direct3D11::{D3D11CreateDevice, ID3D11Device, ID3D11Texture2D, D3D11_CREATE_DEVICE_BGRA_SUPPORT, D3D11_SDK_VERSION}, Dwm::{DwmGetWindowAttribute, DWMWA_EXTENDED_FRAME_BOUNDS}, Gdi::{DeleteObject, GetDC, GetDIBits, GetObjectW, MonitorFromWindow, ReleaseDC, BITMAP, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS, HBITMAP, MONITOR_DEFAULTTONEAREST}, Dxgi::{CreateDXGIFactory, IDXGIAdapter, IDXGIAdapter4, IDXGIDevice, IDXGIFactory5}}, System::{Com::COINIT_APARTMENTTHREADED, Threading::{GetCurrentThread, GetCurrentThreadId, SetThreadPriority, THREAD_PRIORITY, THREAD_PRIORITY_ABOVE_NORMAL, THREAD_PRIORITY_BELOW_NORMAL}, WinRT::{CreateDispatcherQueueController, Direct3D11::{CreateDirect3D11DeviceFromDXGIDevice, IDirect3DDxgiInterfaceAccess}, DispatcherQueueOptions, Graphics::Capture::IGraphicsCaptureItemInterop, DQTAT_COM_NONE, DQTYPE_THREAD_CURRENT}}, UI::{HiDpi::{GetDpiForMonitor, GetDpiForWindow, MDT_RAW_DPI}, WindowsAndMessaging::{DispatchMessageW, GetCursorInfo, GetIconInfo, GetMessageW, PostThreadMessageW, TranslateMessage, CURSORINFO, CURSOR_SHOWING, HCURSOR, HICON, ICONINFO, MSG, WM_QUIT}}}};
 
-use super::{audio_capture_stream::{WindowsAudioCaptureStream, WindowsAudioCaptureStreamError, WindowsAudioCaptureStreamPacket}, frame::{WindowsAudioFrame, WindowsVideoFrame}, AutoCom};
+#[cfg(feature = "wgpu")]
+use crate::feature::wgpu::{SharedWgpuDevice, SharedWgpuTextureCache, WgpuTextureCache};
+use super::{audio_capture_stream::{WindowsAudioCaptureStream, WindowsAudioCaptureStreamCreateError, WindowsAudioCaptureStreamError, WindowsAudioCaptureStreamPacket}, frame::{WindowsAudioFrame, WindowsVideoFrame}, frame_scaler::WindowsFrameScaler, AutoCom};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[allow(unused)]
@@ -34,6 +40,7 @@ impl WindowsAudioCaptureConfigExt for CaptureConfig {
 #[derive(Clone)]
 pub struct WindowsCaptureConfig {
     pub(crate) borderless: bool,
+    pub(crate) idle_timeout: Option<Duration>,
     pub(crate) dxgi_adapter: Option<IDXGIAdapter4>,
     pub(crate) d3d11_device: Option<ID3D11Device>,
     #[cfg(feature = "wgpu")]
@@ -42,7 +49,7 @@ pub struct WindowsCaptureConfig {
 
 impl Debug for WindowsCaptureConfig {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("WindowsCaptureConfig").field("dxgi_adapter", &self.dxgi_adapter).field("d3d11_device", &self.d3d11_device).finish()
+        f.debug_struct("WindowsCaptureConfig").field("idle_timeout", &self.idle_timeout).field("dxgi_adapter", &self.dxgi_adapter).field("d3d11_device", &self.d3d11_device).finish()
     }
 }
 
@@ -50,6 +57,7 @@ impl WindowsCaptureConfig {
     pub fn new() -> Self {
         Self {
             borderless: false,
+            idle_timeout: Some(DEFAULT_IDLE_TIMEOUT),
             dxgi_adapter: None,
             d3d11_device: None,
             #[cfg(feature = "wgpu")]
@@ -62,6 +70,14 @@ pub trait WindowsCaptureConfigExt {
     fn with_dxgi_adapter(self, dxgi_adapter: IDXGIAdapter) -> Self;
     fn with_d3d11_device(self, d3d11_device: ID3D11Device) -> Self;
     fn with_borderless(self, borderless: bool) -> Self;
+    /// Set the maximum capture frame-rate - forwards to `CaptureConfig::with_maximum_fps`
+    #[deprecated(note = "use CaptureConfig::with_maximum_fps")]
+    fn with_maximum_fps(self, maximum_fps: Option<f32>) -> Self;
+    /// Set how long the stream goes without a new frame before delivering `StreamEvent::Idle`, or `None` to never deliver it - by default, one second
+    /// 
+    /// Windows.Graphics.Capture only produces frames when the content changes, so static content goes idle, and `StreamEvent::Idle`
+    /// is delivered once each time it does. Audio-only streams never deliver it.
+    fn with_idle_timeout(self, idle_timeout: Option<Duration>) -> Self;
 }
 
 impl WindowsCaptureConfigExt for CaptureConfig {
@@ -94,6 +110,215 @@ impl WindowsCaptureConfigExt for CaptureConfig {
             ..self
         }
     }
+
+    fn with_maximum_fps(self, maximum_fps: Option<f32>) -> Self {
+        CaptureConfig::with_maximum_fps(self, maximum_fps)
+    }
+
+    fn with_idle_timeout(self, idle_timeout: Option<Duration>) -> Self {
+        Self {
+            impl_capture_config: WindowsCaptureConfig {
+                idle_timeout,
+                ..self.impl_capture_config
+            },
+            ..self
+        }
+    }
+}
+
+// How long a stream goes without a frame before it's idle, unless configured with `with_idle_timeout`
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+// The longest the idle detection thread sleeps, which bounds how long it outlives its stream
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// How early a frame may arrive and still be delivered, for long frame intervals
+const MAX_FRAME_INTERVAL_TOLERANCE: Duration = Duration::from_millis(20);
+
+// The minimum interval between delivered frames for a config, after applying its power profile
+fn config_frame_interval(config: &CaptureConfig, power_profile: PowerProfile) -> Option<Duration> {
+    let configured_interval = config.maximum_fps
+        .filter(|maximum_fps| maximum_fps.is_finite() && *maximum_fps > 0.0)
+        .map(|maximum_fps| Duration::from_secs_f64(1.0 / maximum_fps as f64));
+    power_profile.frame_interval(configured_interval)
+}
+
+pub(crate) const WINDOWS_GRAPHICS_CAPTURE_BACKEND_NAME: &str = "Windows.Graphics.Capture";
+
+pub(crate) const IS_BORDER_REQUIRED_PROPERTY: &str = "IsBorderRequired";
+pub(crate) const IS_CURSOR_CAPTURE_ENABLED_PROPERTY: &str = "IsCursorCaptureEnabled";
+
+// Older versions of Windows.Graphics.Capture lack some of the session's properties, so check for them before setting them
+pub(crate) fn graphics_capture_session_has_property(property: &str) -> bool {
+    ApiInformation::IsPropertyPresent(&HSTRING::from("Windows.Graphics.Capture.GraphicsCaptureSession"), &HSTRING::from(property))
+        .unwrap_or(false)
+}
+
+const GRAPHICS_CAPTURE_PRIVACY_SETTINGS_URI: &str = "ms-settings:privacy-graphicsCaptureProgrammatic";
+
+// The screen capture privacy settings page arrived alongside GraphicsCaptureAccess, so older versions of Windows have no page to open
+pub(crate) fn graphics_capture_access_is_present() -> bool {
+    ApiInformation::IsTypePresent(&HSTRING::from("Windows.Graphics.Capture.GraphicsCaptureAccess"))
+        .unwrap_or(false)
+}
+
+// Run the frame compositor on a frame's surface, before it's delivered
+fn composite_surface(frame_compositor: &SharedFrameCompositor, device: &ID3D11Device, surface: &IDirect3DSurface, pixel_format: CapturePixelFormat) -> Result<(), String> {
+    if !frame_compositor.is_active() {
+        return Ok(());
+    }
+    unsafe {
+        let interface_access: IDirect3DDxgiInterfaceAccess = surface.cast()
+            .map_err(|_| "Couldn't access the frame surface's dxgi interface".to_string())?;
+        let texture: ID3D11Texture2D = interface_access.GetInterface()
+            .map_err(|_| "Couldn't get the frame surface's texture".to_string())?;
+        let context = device.GetImmediateContext()
+            .map_err(|_| "Couldn't get immediate d3d11 context".to_string())?;
+        frame_compositor.composite(CompositorTarget::D3D11 { pixel_format, texture: &texture, context: &context });
+        context.Flush();
+    }
+    Ok(())
+}
+
+pub(crate) fn create_graphics_capture_item(target: &Capturable) -> windows::core::Result<GraphicsCaptureItem> {
+    let interop = windows::core::factory::<GraphicsCaptureItem, IGraphicsCaptureItemInterop>()?;
+    unsafe {
+        match target {
+            Capturable::Window(window) => interop.CreateForWindow(window.impl_capturable_window.0),
+            Capturable::Display(display) => interop.CreateForMonitor(display.impl_capturable_display.0),
+        }
+    }
+}
+
+fn configure_capture_session(capture_session: &GraphicsCaptureSession, borderless: bool, show_cursor: bool) {
+    if graphics_capture_session_has_property(IS_BORDER_REQUIRED_PROPERTY) {
+        let _ = capture_session.SetIsBorderRequired(!borderless);
+    }
+    if graphics_capture_session_has_property(IS_CURSOR_CAPTURE_ENABLED_PROPERTY) {
+        let _ = capture_session.SetIsCursorCaptureEnabled(show_cursor);
+    }
+}
+
+// Sample the cursor as a frame arrives - returns its offset from the top-left of the captured content, whether it's showing, and its shape
+fn sample_cursor(target: &Capturable) -> (Option<Point>, bool, HCURSOR) {
+    unsafe {
+        let mut cursor_info = CURSORINFO {
+            cbSize: std::mem::size_of::<CURSORINFO>() as u32,
+            ..Default::default()
+        };
+        if GetCursorInfo(&mut cursor_info as *mut _).is_err() {
+            return (None, false, HCURSOR::default());
+        }
+        let visible = (cursor_info.flags.0 & CURSOR_SHOWING.0) != 0;
+        // Window capture covers the window's extended frame bounds, which exclude its invisible resize borders
+        let content_rect = match target {
+            Capturable::Window(window) => {
+                let mut rect = RECT::default();
+                DwmGetWindowAttribute(window.impl_capturable_window.0, DWMWA_EXTENDED_FRAME_BOUNDS, &mut rect as *mut _ as *mut c_void, std::mem::size_of::<RECT>() as u32)
+                    .ok()
+                    .map(|_| rect)
+            },
+            Capturable::Display(display) => Some(display.impl_capturable_display.1),
+        };
+        let offset = content_rect.map(|rect| Point {
+            x: (cursor_info.ptScreenPos.x - rect.left) as f64,
+            y: (cursor_info.ptScreenPos.y - rect.top) as f64,
+        });
+        (offset, visible, cursor_info.hCursor)
+    }
+}
+
+// Read a cursor's image - monochrome cursors' inverting pixels come out black
+fn cursor_image(cursor: HCURSOR) -> Option<CursorImage> {
+    if cursor.is_invalid() {
+        return None;
+    }
+    unsafe {
+        let mut icon_info = ICONINFO::default();
+        GetIconInfo(HICON(cursor.0), &mut icon_info as *mut _).ok()?;
+        let mask = bitmap_bgra(icon_info.hbmMask);
+        let color = if icon_info.hbmColor.is_invalid() { None } else { bitmap_bgra(icon_info.hbmColor) };
+        let _ = DeleteObject(icon_info.hbmMask);
+        if !icon_info.hbmColor.is_invalid() {
+            let _ = DeleteObject(icon_info.hbmColor);
+        }
+        let (mask, mask_width, mask_height) = mask?;
+        let (data, width, height) = match color {
+            Some((mut color, width, height)) => {
+                // Cursors without an alpha channel take their transparency from the mask
+                if color.iter().all(|pixel| pixel[3] == 0) && mask.len() >= color.len() {
+                    for (pixel, mask_pixel) in color.iter_mut().zip(mask.iter()) {
+                        pixel[3] = if mask_pixel[0] == 0 { 255 } else { 0 };
+                    }
+                }
+                (color, width, height)
+            },
+            // Monochrome cursors hold their AND mask above their XOR mask
+            None => {
+                let height = mask_height / 2;
+                let (and_mask, xor_mask) = mask.split_at(mask_width * height);
+                let data = and_mask.iter().zip(xor_mask.iter()).map(|(and_pixel, xor_pixel)| {
+                    match (and_pixel[0] != 0, xor_pixel[0] != 0) {
+                        (false, false) => [0, 0, 0, 255],
+                        (false, true) => [255, 255, 255, 255],
+                        (true, false) => [0, 0, 0, 0],
+                        (true, true) => [0, 0, 0, 255],
+                    }
+                }).collect();
+                (data, mask_width, height)
+            },
+        };
+        Some(CursorImage {
+            data: data.into_boxed_slice(),
+            width,
+            height,
+            hotspot: Point { x: icon_info.xHotspot as f64, y: icon_info.yHotspot as f64 },
+        })
+    }
+}
+
+// Read a bitmap as top-down 32 bit BGRA pixels
+unsafe fn bitmap_bgra(bitmap: HBITMAP) -> Option<(Vec<[u8; 4]>, usize, usize)> {
+    let mut bitmap_desc = BITMAP::default();
+    if GetObjectW(bitmap, std::mem::size_of::<BITMAP>() as i32, Some(&mut bitmap_desc as *mut _ as *mut c_void)) == 0 {
+        return None;
+    }
+    let (width, height) = (bitmap_desc.bmWidth.unsigned_abs() as usize, bitmap_desc.bmHeight.unsigned_abs() as usize);
+    let mut bitmap_info = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: width as i32,
+            biHeight: -(height as i32),
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut data = vec![[0u8; 4]; width * height];
+    let dc = GetDC(HWND(0));
+    let line_count = GetDIBits(dc, bitmap, 0, height as u32, Some(data.as_mut_ptr() as *mut c_void), &mut bitmap_info as *mut _, DIB_RGB_COLORS);
+    ReleaseDC(HWND(0), dc);
+    if line_count == 0 {
+        return None;
+    }
+    Some((data, width, height))
+}
+
+// The priority of the crate's capture threads for a power profile, and a description of it
+fn power_profile_thread_priority(power_profile: PowerProfile) -> Option<(THREAD_PRIORITY, &'static str)> {
+    match power_profile {
+        PowerProfile::HighPerformance => Some((THREAD_PRIORITY_ABOVE_NORMAL, "thread priority: above normal")),
+        PowerProfile::Balanced => None,
+        PowerProfile::Efficiency => Some((THREAD_PRIORITY_BELOW_NORMAL, "thread priority: below normal")),
+    }
+}
+
+pub(crate) fn set_current_thread_priority(thread_priority: Option<THREAD_PRIORITY>) {
+    if let Some(thread_priority) = thread_priority {
+        let _ = unsafe { SetThreadPriority(GetCurrentThread(), thread_priority) };
+    }
 }
 
 #[allow(unused)]
@@ -104,20 +329,136 @@ pub struct WindowsCaptureStream {
     pub(crate) d3d11_device: ID3D11Device,
     #[cfg(feature = "wgpu")]
     pub(crate) wgpu_device: Option<Arc<dyn AsRef<wgpu::Device> + Send + Sync + 'static>>,
+    #[cfg(feature = "wgpu")]
+    pub(crate) frame_wgpu_device: SharedWgpuDevice,
+    #[cfg(feature = "wgpu")]
+    pub(crate) wgpu_texture_cache: SharedWgpuTextureCache,
     pub(crate) frame_pool: Direct3D11CaptureFramePool,
-    pub(crate) capture_session: GraphicsCaptureSession,
+    // Holds the capture session, which `update_target` replaces
+    stop_handle: WindowsStopHandle,
+    // The item being captured, and the registration of the close handler on it - both replaced by `update_target`
+    capture_item: GraphicsCaptureItem,
+    capture_item_closed_token: EventRegistrationToken,
+    close_handler: TypedEventHandler<GraphicsCaptureItem, IInspectable>,
+    borderless: bool,
+    show_cursor: bool,
+    // What `update_config` needs to recreate the frame pool
+    direct3d_device: IDirect3DDevice,
+    directx_pixel_format: DirectXPixelFormat,
+    scaling_quality: ScalingQuality,
     auto_com: AutoCom,
     shared_handler_data: Arc<SharedHandlerData>,
     audio_stream: Option<WindowsAudioCaptureStream>,
+    // Whether the capture session is started - audio-only streams only capture from the audio stream
+    capture_video: bool,
+    // The stream thread runs a message loop, and signals its exit on this channel
+    thread_id: u32,
+    thread_exit_rx: Option<oneshot::Receiver<()>>,
+    effective_config: EffectiveCaptureConfig,
 }
 
 unsafe impl Send for WindowsCaptureStream {}
 
+// Stops a `WindowsCaptureStream` from any thread, including from inside its callback - see `StopHandle`
+#[derive(Clone)]
+pub(crate) struct WindowsStopHandle {
+    shared_handler_data: Arc<SharedHandlerData>,
+    capture_session: Arc<Mutex<GraphicsCaptureSession>>,
+    audio_stopped_flag: Option<Arc<AtomicBool>>,
+}
+
+unsafe impl Send for WindowsStopHandle {}
+unsafe impl Sync for WindowsStopHandle {}
+
+impl WindowsStopHandle {
+    // Close the capture session - the end of the stream is delivered first, unless the caller is inside the callback and delivers it itself
+    pub(crate) fn stop(&self, deliver_end: bool) -> Result<(), StreamStopError> {
+        let already_closed = self.shared_handler_data.closed.fetch_or(true, atomic::Ordering::AcqRel);
+        if !already_closed && deliver_end {
+            (*self.shared_handler_data.callback.lock())(Ok(StreamEvent::End { reason: StreamEndReason::Stopped }));
+        }
+        // The audio stream's client is stopped when the stream is closed or dropped, but its polling ends here
+        if let Some(audio_stopped_flag) = &self.audio_stopped_flag {
+            audio_stopped_flag.store(true, atomic::Ordering::Release);
+        }
+        self.capture_session.lock().Close().map_err(|_| StreamStopError::Other("Failed to close capture session".into()))?;
+        Ok(())
+    }
+}
+
 pub(crate) struct SharedHandlerData {
     callback: Mutex<Box<dyn FnMut(Result<StreamEvent, StreamError>) + Send + 'static>>,
     closed: AtomicBool,
     frame_id_counter: AtomicU64,
     audio_frame_id_counter: AtomicU64,
+    // The current capture target, which `update_target` swaps while holding the callback lock
+    target: Mutex<Capturable>,
+    // FrameArrived events still queued for frames of an old target, which were discarded by `update_target`
+    stale_frame_events: AtomicU64,
+    // The crop set with `set_source_rect`, as a fraction of the content size
+    source_rect: Mutex<Option<Rect>>,
+    live_settings: Mutex<WindowsLiveSettings>,
+    // Frames dropped by the frame interval, or because the stream was closed
+    dropped_frames: AtomicU64,
+    idle_state: Mutex<WindowsIdleState>,
+}
+
+// When the frame pool last raised FrameArrived, and whether `StreamEvent::Idle` was delivered since
+struct WindowsIdleState {
+    last_frame_arrived: Instant,
+    idle: bool,
+}
+
+// Deliver `StreamEvent::Idle` once each time no frame arrives for the idle timeout, until the stream is closed
+fn run_idle_detection(shared_handler_data: Arc<SharedHandlerData>, idle_timeout: Duration) {
+    loop {
+        if shared_handler_data.closed.load(atomic::Ordering::Acquire) {
+            return;
+        }
+        let idle_at = {
+            let idle_state = shared_handler_data.idle_state.lock();
+            (!idle_state.idle).then(|| idle_state.last_frame_arrived + idle_timeout)
+        };
+        let now = Instant::now();
+        match idle_at {
+            Some(idle_at) if idle_at <= now => {
+                let mut callback = shared_handler_data.callback.lock();
+                // The end of the stream is delivered after it's marked closed, so checking while holding the callback means Idle never follows it
+                if shared_handler_data.closed.load(atomic::Ordering::Acquire) {
+                    return;
+                }
+                let mut idle_state = shared_handler_data.idle_state.lock();
+                // A frame may have arrived while waiting for the callback
+                if idle_state.idle || idle_state.last_frame_arrived + idle_timeout > Instant::now() {
+                    continue;
+                }
+                idle_state.idle = true;
+                drop(idle_state);
+                (*callback)(Ok(StreamEvent::Idle));
+            },
+            Some(idle_at) => std::thread::sleep((idle_at - now).min(IDLE_POLL_INTERVAL)),
+            None => std::thread::sleep(IDLE_POLL_INTERVAL),
+        }
+    }
+}
+
+// The settings of a stream's frame handler which `update_config` can change
+struct WindowsLiveSettings {
+    // The size of frames which aren't scaled
+    output_size: (usize, usize),
+    max_dimensions: Option<Size>,
+    // The output size capped to the maximum dimensions
+    scaled_output_size: Size,
+    buffer_count: usize,
+    frame_pool_size: (usize, usize),
+    // Throttles frames to the minimum interval between delivered frames
+    frame_interval_limiter: FrameIntervalLimiter,
+}
+
+impl WindowsLiveSettings {
+    fn scales_frames(&self, scaling_quality: ScalingQuality) -> bool {
+        self.max_dimensions.is_some() || scaling_quality != ScalingQuality::Smooth
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -141,19 +482,77 @@ struct StreamCreateOutput {
     d3d11_device: ID3D11Device,
     #[cfg(feature = "wgpu")]
     wgpu_device: Option<Arc<dyn AsRef<wgpu::Device> + Send + Sync + 'static>>,
+    #[cfg(feature = "wgpu")]
+    frame_wgpu_device: SharedWgpuDevice,
+    #[cfg(feature = "wgpu")]
+    wgpu_texture_cache: SharedWgpuTextureCache,
     frame_pool: Direct3D11CaptureFramePool,
     capture_session: GraphicsCaptureSession,
+    capture_item: GraphicsCaptureItem,
+    capture_item_closed_token: EventRegistrationToken,
+    close_handler: TypedEventHandler<GraphicsCaptureItem, IInspectable>,
+    borderless: bool,
+    show_cursor: bool,
+    // What `update_config` needs to recreate the frame pool
+    direct3d_device: IDirect3DDevice,
+    directx_pixel_format: DirectXPixelFormat,
+    scaling_quality: ScalingQuality,
     auto_com: AutoCom,
     shared_handler_data: Arc<SharedHandlerData>,
     audio_stream: Option<WindowsAudioCaptureStream>,
+    effective_config: EffectiveCaptureConfig,
+}
+
+// Every D3D11 device the platform creates goes through here, so that tests can count creations and make them fail
+#[cfg(test)]
+static D3D11_DEVICE_CREATIONS: AtomicU64 = AtomicU64::new(0);
+#[cfg(test)]
+static FAIL_D3D11_DEVICE_CREATION: AtomicBool = AtomicBool::new(false);
+
+unsafe fn new_d3d11_device(dxgi_adapter: Option<&IDXGIAdapter>, driver_type: D3D_DRIVER_TYPE) -> windows::core::Result<Option<ID3D11Device>> {
+    #[cfg(test)]
+    {
+        D3D11_DEVICE_CREATIONS.fetch_add(1, atomic::Ordering::SeqCst);
+        if FAIL_D3D11_DEVICE_CREATION.load(atomic::Ordering::SeqCst) {
+            return Err(windows::core::Error::from(windows::Win32::Foundation::E_FAIL));
+        }
+    }
+    let mut d3d11_device = None;
+    D3D11CreateDevice(dxgi_adapter, driver_type, None, D3D11_CREATE_DEVICE_BGRA_SUPPORT, Some(&[D3D_FEATURE_LEVEL_11_0]), D3D11_SDK_VERSION, Some(&mut d3d11_device as *mut _), None, None)?;
+    Ok(d3d11_device)
+}
+
+// Whether Windows.Graphics.Capture accepts frame pools of the given format - not every version of Windows accepts every DirectXPixelFormat.
+// Probed once on its own thread, so that the caller's COM apartment isn't touched
+fn frame_pool_supports_format(pixel_format: DirectXPixelFormat) -> bool {
+    std::thread::spawn(move || {
+        let _auto_com = AutoCom::new(COINIT_APARTMENTTHREADED);
+        unsafe {
+            let d3d11_device = new_d3d11_device(None, D3D_DRIVER_TYPE_HARDWARE).ok()?;
+            let dxgi_device: IDXGIDevice = d3d11_device?.cast().ok()?;
+            let direct3d_device: IDirect3DDevice = CreateDirect3D11DeviceFromDXGIDevice(&dxgi_device).ok()?.cast().ok()?;
+            let frame_pool = Direct3D11CaptureFramePool::CreateFreeThreaded(&direct3d_device, pixel_format, 1, SizeInt32 { Width: 16, Height: 16 }).ok()?;
+            let _ = frame_pool.Close();
+            Some(())
+        }
+    }).join().ok().flatten().is_some()
 }
 
 impl WindowsCaptureStream {
     pub fn supported_pixel_formats() -> &'static [CapturePixelFormat] {
-        &[
-            CapturePixelFormat::Bgra8888,
-            CapturePixelFormat::Argb2101010,
-        ]
+        static SUPPORTED_PIXEL_FORMATS: OnceLock<Vec<CapturePixelFormat>> = OnceLock::new();
+        SUPPORTED_PIXEL_FORMATS.get_or_init(|| {
+            let supports_rgba16 = frame_pool_supports_format(DirectXPixelFormat::R16G16B16A16UIntNormalized);
+            WINDOWS_PIXEL_FORMATS.iter()
+                .copied()
+                .filter(|&pixel_format| pixel_format != CapturePixelFormat::Rgba16 || supports_rgba16)
+                .collect()
+        })
+    }
+
+    pub fn supported_buffer_counts() -> RangeInclusive<usize> {
+        // Direct3D11CaptureFramePool takes the buffer count as an i32
+        1..=(i32::MAX as usize)
     }
 
     pub fn check_access(borderless: bool) -> Option<WindowsCaptureAccessToken> {
@@ -193,24 +592,55 @@ impl WindowsCaptureStream {
         }
     }
 
+    pub fn permission_settings_url() -> Option<&'static str> {
+        graphics_capture_access_is_present().then_some(GRAPHICS_CAPTURE_PRIVACY_SETTINGS_URI)
+    }
+
+    pub fn open_permission_settings() -> Result<OpenSettingsOutcome, OpenSettingsError> {
+        let uri = Self::permission_settings_url().ok_or(OpenSettingsError::Unavailable)?;
+        let uri = Uri::CreateUri(&HSTRING::from(uri))
+            .map_err(|error| OpenSettingsError::Platform(format!("Failed to create settings URI: {}", error.to_string()), PlatformErrorCode::HResult(error.code().0)))?;
+        let launched = Launcher::LaunchUriAsync(&uri)
+            .and_then(|launch_operation| launch_operation.get())
+            .map_err(|error| OpenSettingsError::Platform(format!("Failed to launch settings URI: {}", error.to_string()), PlatformErrorCode::HResult(error.code().0)))?;
+        if launched {
+            Ok(OpenSettingsOutcome::Opened)
+        } else {
+            Err(OpenSettingsError::Other("The Settings app failed to launch".into()))
+        }
+    }
+
     fn create_d3d11_device(dxgi_adapter: IDXGIAdapter4) -> Result<(Option<IDXGIAdapter4>, Option<String>, ID3D11Device), StreamCreateError> {
         unsafe {
-            let mut d3d11_device = None;
-            let d3d11_device_result = D3D11CreateDevice(
-                Some(&dxgi_adapter.cast().unwrap()),
-                D3D_DRIVER_TYPE_UNKNOWN,
-                None,
-                D3D11_CREATE_DEVICE_BGRA_SUPPORT,
-                Some(&[D3D_FEATURE_LEVEL_11_0]),
-                D3D11_SDK_VERSION,
-                Some(&mut d3d11_device as *mut _),
-                None,
-                None
-            );
-            match d3d11_device_result {
-                Ok(_) => d3d11_device.map_or_else(|| Err(StreamCreateError::Other("Failed to create ID3D11Device".into())), |x| Ok((Some(dxgi_adapter), None, x))),
-                Err(_) => Err(StreamCreateError::Other(format!("Failed to create d3d11 device")))
-                ,
+            match new_d3d11_device(Some(&dxgi_adapter.cast().unwrap()), D3D_DRIVER_TYPE_UNKNOWN) {
+                Ok(d3d11_device) => d3d11_device.map_or_else(|| Err(StreamCreateError::Other("Failed to create ID3D11Device".into())), |x| Ok((Some(dxgi_adapter), None, x))),
+                Err(error) => Err(StreamCreateError::Platform(format!("Failed to create ID3D11Device: {}", error.to_string()), PlatformErrorCode::HResult(error.code().0))),
+            }
+        }
+    }
+
+    // Devices are only created when a stream is created - enumerating content and building a config never touch D3D11 or DXGI,
+    // so broken GPU drivers only affect capture itself
+    fn create_devices(capture_config: &WindowsCaptureConfig) -> Result<(Option<IDXGIAdapter4>, Option<String>, ID3D11Device), StreamCreateError> {
+        match (&capture_config.dxgi_adapter, &capture_config.d3d11_device) {
+            (_, Some(d3d11_device)) => {
+                let dxgi_adapter = d3d11_device.cast::<IDXGIDevice>()
+                    .and_then(|dxgi_device| unsafe { dxgi_device.GetAdapter() })
+                    .and_then(|dxgi_adapter| dxgi_adapter.cast::<IDXGIAdapter4>())
+                    .map_err(|error| format!("Failed to get IDXGIAdapter from ID3D11Device: {}", error.to_string()));
+                match dxgi_adapter {
+                    Ok(dxgi_adapter) => Ok((Some(dxgi_adapter), None, d3d11_device.clone())),
+                    Err(dxgi_adapter_error) => Ok((None, Some(dxgi_adapter_error), d3d11_device.clone())),
+                }
+            },
+            (Some(dxgi_adapter), None) => Self::create_d3d11_device(dxgi_adapter.clone()),
+            (None, None) => {
+                let dxgi_factory: IDXGIFactory5 = unsafe { CreateDXGIFactory() }
+                    .map_err(|error| StreamCreateError::Platform(format!("Failed to create IDXGIFactory: {}", error.to_string()), PlatformErrorCode::HResult(error.code().0)))?;
+                let dxgi_adapter: IDXGIAdapter4 = unsafe { dxgi_factory.EnumAdapters(0) }
+                    .and_then(|dxgi_adapter| dxgi_adapter.cast())
+                    .map_err(|error| StreamCreateError::Platform(format!("Failed to enumerate IDXGIAdapter: {}", error.to_string()), PlatformErrorCode::HResult(error.code().0)))?;
+                Self::create_d3d11_device(dxgi_adapter)
             }
         }
     }
@@ -225,7 +655,7 @@ impl WindowsCaptureStream {
         dqco.dwSize = std::mem::size_of::<DispatcherQueueOptions>() as u32;
         match unsafe { CreateDispatcherQueueController(dqco) } {
             Ok(_) => {},
-            Err(error) => return Err(StreamCreateError::Other(format!("Failed to create dispatch queue controller: {}", error.to_string()))),
+            Err(error) => return Err(StreamCreateError::Platform(format!("Failed to create dispatch queue controller: {}", error.to_string()), PlatformErrorCode::HResult(error.code().0))),
         };
 
         if config.impl_capture_config.borderless && !token.borderless {
@@ -235,11 +665,11 @@ impl WindowsCaptureStream {
         let pixel_format = match config.pixel_format {
             CapturePixelFormat::Bgra8888 => DirectXPixelFormat::B8G8R8A8UIntNormalized,
             CapturePixelFormat::Argb2101010 => DirectXPixelFormat::R10G10B10A2UIntNormalized,
+            CapturePixelFormat::Rgba16 => DirectXPixelFormat::R16G16B16A16UIntNormalized,
+            CapturePixelFormat::RgbaF16 => DirectXPixelFormat::R16G16B16A16Float,
             _ => return Err(StreamCreateError::UnsupportedPixelFormat),
         };
 
-        let callback_target = config.target.clone();
-
         let interop: IGraphicsCaptureItemInterop = windows::core::factory::<GraphicsCaptureItem, IGraphicsCaptureItemInterop>()
             .map_err(|_| StreamCreateError::Other("Failed to create IGraphicsCaptureInterop factory".into()))?;
 
@@ -247,30 +677,14 @@ impl WindowsCaptureStream {
             match config.target {
                 Capturable::Window(window) =>
                     interop.CreateForWindow(window.impl_capturable_window.0)
-                        .map_err(|e| StreamCreateError::Other(format!("Failed to create graphics capture item from HWND: {}", e.to_string())))?,
+                        .map_err(|e| StreamCreateError::Platform(format!("Failed to create graphics capture item from HWND: {}", e.to_string()), PlatformErrorCode::HResult(e.code().0)))?,
                 Capturable::Display(display) => 
                     interop.CreateForMonitor(display.impl_capturable_display.0)
                         .map_err(|_| StreamCreateError::Other("Failed to create graphics capture item from HMONITOR".into()))?,
             }
         };
 
-        let (dxgi_adapter, dxgi_adapter_error, d3d11_device) = match (config.impl_capture_config.dxgi_adapter, config.impl_capture_config.d3d11_device) {
-            (_, Some(d3d11_device)) => {
-                let dxgi_adapter = d3d11_device.cast().map_err(|error| format!("Failed to create IDXGIAdapter from ID3D11Device: {}", error.to_string()));
-                match dxgi_adapter {
-                    Ok(dxgi_adapter) => (Some(dxgi_adapter), None, d3d11_device),
-                    Err(dxgi_adapter_error) => (None, Some(dxgi_adapter_error), d3d11_device)
-                }
-            },
-            (Some(dxgi_adapter), None) => Self::create_d3d11_device(dxgi_adapter)?,
-            (None, None) => {
-                let dxgi_factory: IDXGIFactory5 = unsafe { CreateDXGIFactory()
-                    .map_err(|_| StreamCreateError::Other("Failed to create IDXGIAdapter factory".into())) }?;
-                let dxgi_adapter = unsafe { dxgi_factory.EnumAdapters(0) }
-                    .map_err(|_| StreamCreateError::Other("Failed to enumerate IDXGIAdapter".into()))?;
-                Self::create_d3d11_device(dxgi_adapter.cast().unwrap())?
-            }
-        };
+        let (dxgi_adapter, dxgi_adapter_error, d3d11_device) = Self::create_devices(&config.impl_capture_config)?;
 
         let dxgi_device: IDXGIDevice = d3d11_device.clone().cast()
             .map_err(|_| StreamCreateError::Other("Failed to cast ID3D11Device to IDXGIDevice".into()))?;
@@ -283,12 +697,25 @@ impl WindowsCaptureStream {
 
         let (width, height) = ((config.output_size.width + 0.1) as usize, (config.output_size.height + 0.1) as usize);
 
+        let power_profile = config.power_profile;
+        let buffer_count = power_profile.buffer_count(config.buffer_count);
+        // Windows.Graphics.Capture produces frames whenever the content is presented, so frames are throttled as they arrive
+        let frame_interval = config_frame_interval(&config, power_profile);
+        let thread_priority = power_profile_thread_priority(power_profile);
+
         let frame_pool = Direct3D11CaptureFramePool::Create(
             &direct3d_device,
             pixel_format,
-            config.buffer_count as i32,
+            buffer_count as i32,
             SizeInt32 { Width: width as i32, Height: height as i32 },
-        ).map_err(|e| StreamCreateError::Other(format!("Failed to create Direct3D11CaptureFramePool: {}", e.to_string())))?;
+        ).map_err(|e| {
+            // Not every DirectXPixelFormat is accepted by every version of Windows.Graphics.Capture
+            if config.pixel_format == CapturePixelFormat::Rgba16 {
+                StreamCreateError::UnsupportedPixelFormat
+            } else {
+                StreamCreateError::Platform(format!("Failed to create Direct3D11CaptureFramePool: {}", e.to_string()), PlatformErrorCode::HResult(e.code().0))
+            }
+        })?;
 
         let shared_handler_data = Arc::new(
             SharedHandlerData {
@@ -296,6 +723,26 @@ impl WindowsCaptureStream {
                 closed: AtomicBool::new(false),
                 frame_id_counter: AtomicU64::new(0),
                 audio_frame_id_counter: AtomicU64::new(0),
+                target: Mutex::new(config.target.clone()),
+                stale_frame_events: AtomicU64::new(0),
+                // Normalized to the content size, as with `set_source_rect`
+                source_rect: Mutex::new(config.source_rect.map(|source_rect| {
+                    let content_size = config.target.rect().size;
+                    source_rect.scaled_2d((1.0 / content_size.width, 1.0 / content_size.height))
+                })),
+                live_settings: Mutex::new(WindowsLiveSettings {
+                    output_size: (width, height),
+                    max_dimensions: config.max_dimensions,
+                    scaled_output_size: config.cap_size(config.output_size),
+                    buffer_count,
+                    frame_pool_size: (width, height),
+                    frame_interval_limiter: FrameIntervalLimiter::new(frame_interval, MAX_FRAME_INTERVAL_TOLERANCE),
+                }),
+                dropped_frames: AtomicU64::new(0),
+                idle_state: Mutex::new(WindowsIdleState {
+                    last_frame_arrived: Instant::now(),
+                    idle: false,
+                }),
             }
         );
 
@@ -303,11 +750,12 @@ impl WindowsCaptureStream {
         let frame_handler_data = shared_handler_data.clone();
         let audio_handler_data = shared_handler_data.clone();
 
+        // Closed is raised both when a captured window closes and when a captured display is disconnected
         let close_handler = TypedEventHandler::new(move |_, _| {
-            let alread_closed = close_handler_data.closed.fetch_and(true, atomic::Ordering::AcqRel);
+            let alread_closed = close_handler_data.closed.fetch_or(true, atomic::Ordering::AcqRel);
             if !alread_closed {
                 let mut callback = close_handler_data.callback.lock();
-                (*callback)(Ok(StreamEvent::End));
+                (*callback)(Ok(StreamEvent::End { reason: StreamEndReason::TargetGone }));
             }
             Ok(())
         });
@@ -315,20 +763,58 @@ impl WindowsCaptureStream {
         let mut t_first_frame = None;
         let mut t_last_frame = None;
 
+        // Windows.Graphics.Capture can't scale, so with maximum dimensions or a scaling quality other than smooth, the frame pool
+        // follows the native size of the content, and frames are scaled on the GPU
+        let scaling_quality = config.scaling_quality;
+        // Streams which don't scale only create a scaler once they're cropped with `set_source_rect`, or given maximum dimensions by `update_config`
+        let mut frame_scaler = if shared_handler_data.live_settings.lock().scales_frames(scaling_quality) {
+            Some(WindowsFrameScaler::new(d3d11_device.clone()).map_err(StreamCreateError::Other)?)
+        } else {
+            None
+        };
+        let callback_direct3d_device_winrt = direct3d_device.clone();
+        let frame_compositor = config.frame_compositor.clone();
+        let callback_pixel_format = config.pixel_format;
+
+        #[cfg(feature = "wgpu")]
+        let frame_wgpu_device: SharedWgpuDevice = Arc::new(Mutex::new(config.impl_capture_config.wgpu_device.clone()));
         #[cfg(feature = "wgpu")]
-        let callback_wgpu_device = config.impl_capture_config.wgpu_device.clone();
+        let callback_wgpu_device = frame_wgpu_device.clone();
+        #[cfg(feature = "wgpu")]
+        let wgpu_texture_cache = WgpuTextureCache::new_shared();
+        #[cfg(feature = "wgpu")]
+        let callback_wgpu_texture_cache = wgpu_texture_cache.clone();
+        #[cfg(feature = "bitmap")]
+        let callback_change_metric = config.change_metric;
         #[cfg(feature = "wgpu")]
         let wgpu_device = config.impl_capture_config.wgpu_device.clone();
 
+        let mut cursor_image_cache: Option<(HCURSOR, Option<Arc<CursorImage>>)> = None;
+
         let frame_handler = TypedEventHandler::new(move |frame_pool: &Option<Direct3D11CaptureFramePool>, _: &Option<IInspectable>| {
             if frame_pool.is_none() {
                 return Ok(());
             }
             let frame_pool = frame_pool.as_ref().unwrap();
             if frame_handler_data.closed.load(atomic::Ordering::Acquire) {
+                frame_handler_data.dropped_frames.fetch_add(1, atomic::Ordering::Relaxed);
                 return Ok(());
             }
             let t_capture = Instant::now();
+            // Throttled frames still mean the content changed, so the stream isn't idle
+            *frame_handler_data.idle_state.lock() = WindowsIdleState {
+                last_frame_arrived: t_capture,
+                idle: false,
+            };
+            // Frames sooner than the frame interval are returned to the frame pool unprocessed, accepting frames up to about a
+            // display refresh early rather than waiting a whole extra display refresh
+            if !frame_handler_data.live_settings.lock().frame_interval_limiter.should_deliver(t_capture) {
+                if let Ok(frame) = frame_pool.TryGetNextFrame() {
+                    let _ = frame.Close();
+                }
+                frame_handler_data.dropped_frames.fetch_add(1, atomic::Ordering::Relaxed);
+                return Ok(());
+            }
             let t_origin = match t_first_frame {
                 Some(t_first_frame) => t_capture - t_first_frame,
                 None => {
@@ -336,15 +822,18 @@ impl WindowsCaptureStream {
                     Duration::ZERO
                 }
             };
-            let duration = match t_last_frame {
+            let duration = match t_last_frame.replace(t_capture) {
                 Some(t_last_frame) => t_capture - t_last_frame,
-                None => {
-                    t_last_frame = Some(t_capture);
-                    Duration::ZERO
-                }
+                None => Duration::ZERO,
+            };
+            let mut callback = frame_handler_data.callback.lock();
+            let target = frame_handler_data.target.lock().clone();
+            let monitor = match &target {
+                Capturable::Window(window) => unsafe { MonitorFromWindow(window.impl_capturable_window.0, MONITOR_DEFAULTTONEAREST) },
+                Capturable::Display(display) => display.impl_capturable_display.0,
             };
             let dpi = unsafe { 
-                match &callback_target {
+                match &target {
                     Capturable::Window(window) => GetDpiForWindow(window.impl_capturable_window.0),
                     Capturable::Display(display) => {
                         let mut dpi_x = 0u32;
@@ -354,44 +843,133 @@ impl WindowsCaptureStream {
                     }
                 }
             };
-            let mut callback = frame_handler_data.callback.lock();
+            let (cursor_offset, cursor_visible, cursor) = sample_cursor(&target);
+            // The cursor's image is only read again when its shape changes
+            let cursor_image = match &cursor_image_cache {
+                Some((cached_cursor, image)) if *cached_cursor == cursor => image.clone(),
+                _ => {
+                    let image = cursor_image(cursor).map(Arc::new);
+                    cursor_image_cache = Some((cursor, image.clone()));
+                    image
+                }
+            };
             //let window_rect = RECT::default();
             let frame = match frame_pool.TryGetNextFrame() {
                 Ok(frame) => frame,
+                // The frame of this event was discarded when the target changed
+                Err(_) if frame_handler_data.stale_frame_events.fetch_update(atomic::Ordering::AcqRel, atomic::Ordering::Acquire, |count| count.checked_sub(1)).is_ok() => {
+                    return Ok(());
+                },
                 Err(e) => {
-                    (*callback)(Err(StreamError::Other(format!("Failed to capture frame: {}", e.to_string()))));
+                    (*callback)(Err(StreamError::Platform(format!("Failed to capture frame: {}", e.to_string()), PlatformErrorCode::HResult(e.code().0))));
                     return Ok(());
                 }
             };
 
+            let source_rect = *frame_handler_data.source_rect.lock();
+            let mut live_settings = frame_handler_data.live_settings.lock();
+            let scales_frames = live_settings.scales_frames(scaling_quality);
+            if (scales_frames || source_rect.is_some()) && frame_scaler.is_none() {
+                match WindowsFrameScaler::new(callback_direct3d_device.clone()) {
+                    Ok(new_frame_scaler) => frame_scaler = Some(new_frame_scaler),
+                    Err(error) => {
+                        (*callback)(Err(StreamError::Other(format!("Failed to create frame scaler: {}", error))));
+                        return Ok(());
+                    }
+                }
+            }
+
+            let (frame_size, scaled_surface) = match &frame_scaler {
+                Some(frame_scaler) if scales_frames || source_rect.is_some() => {
+                    let surface_size = live_settings.frame_pool_size;
+                    let mut content_size = frame.ContentSize()
+                        .map(|size| (size.Width.max(1) as usize, size.Height.max(1) as usize))
+                        .unwrap_or(surface_size);
+                    if !scales_frames {
+                        // The frame pool keeps the output size, clipping larger content
+                        content_size = (content_size.0.min(surface_size.0), content_size.1.min(surface_size.1));
+                    } else if content_size != live_settings.frame_pool_size {
+                        live_settings.frame_pool_size = content_size;
+                        let _ = frame_pool.Recreate(&callback_direct3d_device_winrt, pixel_format, live_settings.buffer_count as i32, SizeInt32 { Width: content_size.0 as i32, Height: content_size.1 as i32 });
+                    }
+                    let FrameLayout { crop_rect, target_size, content_rect, unscaled } = FrameLayout::new(
+                        content_size,
+                        source_rect,
+                        scaling_quality,
+                        live_settings.max_dimensions,
+                        live_settings.scaled_output_size
+                    );
+                    if unscaled {
+                        (surface_size, None)
+                    } else {
+                        let scaled_surface = frame.Surface()
+                            .map_err(|e| e.to_string())
+                            .and_then(|surface| frame_scaler.scale(&surface, pixel_format, crop_rect, target_size, content_rect, scaling_quality == ScalingQuality::NearestNeighbor));
+                        match scaled_surface {
+                            Ok(scaled_surface) => (target_size, Some(scaled_surface)),
+                            Err(error) => {
+                                (*callback)(Err(StreamError::Other(format!("Failed to scale frame: {}", error))));
+                                return Ok(());
+                            }
+                        }
+                    }
+                },
+                _ => (live_settings.output_size, None),
+            };
+            drop(live_settings);
+
+            if let Some(frame_compositor) = &frame_compositor {
+                let surface = match &scaled_surface {
+                    Some(scaled_surface) => Ok(scaled_surface.clone()),
+                    None => frame.Surface(),
+                };
+                if let Err(error) = surface.map_err(|e| e.to_string()).and_then(|surface| composite_surface(frame_compositor, &callback_direct3d_device, &surface, callback_pixel_format)) {
+                    (*callback)(Err(StreamError::Other(format!("Failed to composite frame: {}", error))));
+                    return Ok(());
+                }
+            }
+
             let frame_id = frame_handler_data.frame_id_counter.fetch_add(1, atomic::Ordering::AcqRel);
             let impl_video_frame = WindowsVideoFrame {
                 device: callback_direct3d_device.clone(),
                 frame,
                 frame_id,
-                frame_size: (width, height),
+                frame_size,
+                scaled_surface,
                 pixel_format,
                 dpi,
+                monitor,
                 t_capture,
                 t_origin,
                 duration,
+                cursor_offset,
+                cursor_visible,
+                cursor_image,
+                #[cfg(feature = "wgpu")]
+                wgpu_device: callback_wgpu_device.lock().clone(),
                 #[cfg(feature = "wgpu")]
-                wgpu_device: callback_wgpu_device.clone(),
+                wgpu_texture_cache: callback_wgpu_texture_cache.clone(),
+                #[cfg(feature = "wgpu")]
+                wgpu_texture_leases: Default::default(),
             };
             let video_frame = VideoFrame {
-                impl_video_frame
+                impl_video_frame,
+                clock_origin: None,
+                #[cfg(feature = "bitmap")]
+                change_metric: callback_change_metric,
             };
             (*callback)(Ok(StreamEvent::Video(video_frame)));
             Ok(())
         });
 
         frame_pool.FrameArrived(&frame_handler).map_err(|_| StreamCreateError::Other("Failed to listen to FrameArrived event".into()))?;
-        graphics_capture_item.Closed(&close_handler).map_err(|_| StreamCreateError::Other("Failed to listen to Closed event".into()))?;
+        let capture_item_closed_token = graphics_capture_item.Closed(&close_handler).map_err(|_| StreamCreateError::Other("Failed to listen to Closed event".into()))?;
 
+        let borderless = config.impl_capture_config.borderless;
+        let show_cursor = config.show_cursor;
         let capture_session = frame_pool.CreateCaptureSession(&graphics_capture_item)
             .map_err(|_| StreamCreateError::Other("Failed to create GraphicsCaptureSession".into()))?;
-        let _ = capture_session.SetIsBorderRequired(!config.impl_capture_config.borderless);
-        let _ = capture_session.SetIsCursorCaptureEnabled(config.show_cursor);
+        configure_capture_session(&capture_session, borderless, show_cursor);
 
         let audio_stream = if let Some(audio_config) = config.capture_audio {
             let handler_config = audio_config.clone();
@@ -409,23 +987,37 @@ impl WindowsCaptureStream {
                                 sample_rate: handler_config.sample_rate,
                                 duration: packet.duration,
                                 origin_time: packet.origin_time,
+                                silent: packet.silent,
+                                discontinuity: packet.discontinuity,
                                 frame_id: audio_frame_id
                             }
                         });
                         (*audio_handler_data.callback.lock())(Ok(event));
                     },
-                    Err(_) => {
-                        (*audio_handler_data.callback.lock())(Err(StreamError::Other("Audio stream error".to_string())));
+                    Err(error) => {
+                        let error = match error {
+                            WindowsAudioCaptureStreamError::Other(message) => StreamError::Other(format!("Audio stream error: {}", message)),
+                            WindowsAudioCaptureStreamError::GetBufferFailed => StreamError::Other("Audio stream error: failed to get the capture buffer".to_string()),
+                        };
+                        (*audio_handler_data.callback.lock())(Err(error));
                     }
                 }
             });
 
-            match WindowsAudioCaptureStream::new(audio_config, audio_handler) {
+            match WindowsAudioCaptureStream::new(audio_config, thread_priority.map(|(thread_priority, _)| thread_priority), audio_handler) {
                 Ok(audio_stream) => {
                     Some(audio_stream)
                 },
-                Err(_) => {
-                    return Err(StreamCreateError::Other("Failed to create audio stream".into()))
+                Err(error) => {
+                    let message = match error {
+                        WindowsAudioCaptureStreamCreateError::Other(message) => message,
+                        WindowsAudioCaptureStreamCreateError::EndpointEnumerationFailed => "no default audio output device".into(),
+                        WindowsAudioCaptureStreamCreateError::AudioClientActivationFailed => "failed to activate the audio client".into(),
+                        WindowsAudioCaptureStreamCreateError::AudioClientInitializeFailed => "failed to initialize loopback capture with the configured format".into(),
+                        WindowsAudioCaptureStreamCreateError::AudioCaptureCreationFailed => "failed to create the audio capture client".into(),
+                        WindowsAudioCaptureStreamCreateError::StreamStartFailed => "failed to start the audio stream".into(),
+                    };
+                    return Err(StreamCreateError::Other(format!("Failed to create audio stream: {}", message)))
                 }
             }
         } else {
@@ -436,14 +1028,32 @@ impl WindowsCaptureStream {
                 auto_com,
                 audio_stream,
                 capture_session,
+                capture_item: graphics_capture_item,
+                capture_item_closed_token,
+                close_handler,
+                borderless,
+                show_cursor,
+                direct3d_device,
+                directx_pixel_format: pixel_format,
+                scaling_quality,
                 dxgi_adapter: dxgi_adapter.map(|adapter| adapter.cast().unwrap()),
                 dxgi_adapter_error,
                 d3d11_device,
                 #[cfg(feature = "wgpu")]
                 wgpu_device,
+                #[cfg(feature = "wgpu")]
+                frame_wgpu_device,
+                #[cfg(feature = "wgpu")]
+                wgpu_texture_cache,
                 dxgi_device,
                 frame_pool,
-                shared_handler_data
+                shared_handler_data,
+                effective_config: EffectiveCaptureConfig {
+                    power_profile,
+                    minimum_frame_interval: frame_interval,
+                    buffer_count,
+                    platform_settings: thread_priority.map(|(_, description)| description).into_iter().collect(),
+                },
             }
         )
     }
@@ -452,8 +1062,13 @@ impl WindowsCaptureStream {
         let auto_com = AutoCom::new(COINIT_APARTMENTTHREADED);
 
         let (init_tx, init_rx) = std::sync::mpsc::channel();
+        let thread_priority = power_profile_thread_priority(config.power_profile).map(|(thread_priority, _)| thread_priority);
+        let capture_video = config.capture_video;
+        let idle_timeout = config.impl_capture_config.idle_timeout;
 
         std::thread::spawn(move || {
+            // Frame pool events are delivered on this thread's message loop
+            set_current_thread_priority(thread_priority);
             match Self::create_capture_stream(token, config, callback) {
                 Err(error) => {
                     _ = init_tx.send(Err(error));
@@ -467,19 +1082,48 @@ impl WindowsCaptureStream {
                         d3d11_device,
                         #[cfg(feature = "wgpu")]
                         wgpu_device,
+                        #[cfg(feature = "wgpu")]
+                        frame_wgpu_device,
+                        #[cfg(feature = "wgpu")]
+                        wgpu_texture_cache,
                         frame_pool,
                         capture_session,
-                        auto_com: _auto_com,
+                        capture_item,
+                        capture_item_closed_token,
+                        close_handler,
+                        borderless,
+                        show_cursor,
+                        direct3d_device,
+                        directx_pixel_format,
+                        scaling_quality,
+                        auto_com: thread_auto_com,
                         shared_handler_data,
                         audio_stream,
+                        effective_config,
                     } = stream_create_output;
 
-                    if let Err(error) = capture_session.StartCapture() {
-                        _ = init_tx.send(Err(StreamCreateError::Other(format!("Failed to start capture session: {}", error.to_string()))));
-                        return;
-                    };
+                    // The session of an audio-only stream is never started, so the target still ends the stream when it closes,
+                    // but no frames are captured and no capture border is shown
+                    if capture_video {
+                        if let Err(error) = capture_session.StartCapture() {
+                            _ = init_tx.send(Err(StreamCreateError::Platform(format!("Failed to start capture session: {}", error.to_string()), PlatformErrorCode::HResult(error.code().0))));
+                            return;
+                        };
+                        if let Some(idle_timeout) = idle_timeout {
+                            let idle_handler_data = shared_handler_data.clone();
+                            let _ = std::thread::Builder::new()
+                                .name("crabgrab-idle-detection".into())
+                                .spawn(move || run_idle_detection(idle_handler_data, idle_timeout));
+                        }
+                    }
                     
                     let thread_shared_handler_data = shared_handler_data.clone();
+                    let (thread_exit_tx, thread_exit_rx) = oneshot::channel();
+                    let stop_handle = WindowsStopHandle {
+                        shared_handler_data: shared_handler_data.clone(),
+                        capture_session: Arc::new(Mutex::new(capture_session)),
+                        audio_stopped_flag: audio_stream.as_ref().map(WindowsAudioCaptureStream::stopped_flag),
+                    };
 
                     let stream = WindowsCaptureStream {
                         dxgi_adapter,
@@ -488,11 +1132,27 @@ impl WindowsCaptureStream {
                         d3d11_device,
                         #[cfg(feature = "wgpu")]
                         wgpu_device,
+                        #[cfg(feature = "wgpu")]
+                        frame_wgpu_device,
+                        #[cfg(feature = "wgpu")]
+                        wgpu_texture_cache,
                         frame_pool,
-                        capture_session,
+                        stop_handle,
+                        capture_item,
+                        capture_item_closed_token,
+                        close_handler,
+                        borderless,
+                        show_cursor,
+                        direct3d_device,
+                        directx_pixel_format,
+                        scaling_quality,
                         auto_com: AutoCom::no_init(),
                         shared_handler_data,
                         audio_stream,
+                        capture_video,
+                        thread_id: unsafe { GetCurrentThreadId() },
+                        thread_exit_rx: Some(thread_exit_rx),
+                        effective_config,
                     };
 
                     _ = init_tx.send(Ok(stream));
@@ -504,6 +1164,8 @@ impl WindowsCaptureStream {
                             DispatchMessageW(&message as *const _);
                         }
                     }
+                    drop(thread_auto_com);
+                    _ = thread_exit_tx.send(());
                 }
             }
         });
@@ -517,13 +1179,147 @@ impl WindowsCaptureStream {
     }
 
     pub fn stop(&self) -> Result<(), StreamStopError> {
-        let already_closed = self.shared_handler_data.closed.fetch_and(true, atomic::Ordering::AcqRel);
-        if !already_closed {
-            (*self.shared_handler_data.callback.lock())(Ok(StreamEvent::End));
+        self.stop_handle.stop(true)
+    }
+
+    pub fn stop_handle(&self) -> WindowsStopHandle {
+        self.stop_handle.clone()
+    }
+
+    pub async fn close(mut self) -> Result<(), StreamStopError> {
+        let result = self.stop();
+        if let Some(mut audio_stream) = self.audio_stream.take() {
+            audio_stream.stop();
+        }
+        let frame_pool_result = self.frame_pool.Close().map_err(|_| StreamStopError::Other("Failed to close frame pool".into()));
+        // Wake the stream thread's message loop so that it exits
+        let _ = unsafe { PostThreadMessageW(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0)) };
+        if let Some(thread_exit_rx) = self.thread_exit_rx.take() {
+            if thread_exit_rx.await.is_err() {
+                return Err(StreamStopError::Other("Stream thread exited without signalling".into()));
+            }
+        }
+        result.and(frame_pool_result)
+    }
+
+    pub fn update_target(&mut self, target: Capturable) -> Result<(), StreamUpdateError> {
+        if self.shared_handler_data.closed.load(atomic::Ordering::Acquire) {
+            return Err(StreamUpdateError::AlreadyStopped);
         }
-        self.capture_session.Close().map_err(|_| StreamStopError::Other("Failed to close capture session".into()))?;
+        let capture_item = create_graphics_capture_item(&target)
+            .map_err(|error| StreamUpdateError::Platform(format!("Failed to create graphics capture item: {}", error.to_string()), PlatformErrorCode::HResult(error.code().0)))?;
+        let capture_item_closed_token = capture_item.Closed(&self.close_handler)
+            .map_err(|_| StreamUpdateError::Other("Failed to listen to Closed event".into()))?;
+        // Hold the callback lock so that no frame is delivered mid-swap
+        let _callback = self.shared_handler_data.callback.lock();
+        let result = self.frame_pool.CreateCaptureSession(&capture_item)
+            .map_err(|error| StreamUpdateError::Platform(format!("Failed to create GraphicsCaptureSession: {}", error.to_string()), PlatformErrorCode::HResult(error.code().0)));
+        let capture_session = match result {
+            Ok(capture_session) => capture_session,
+            Err(error) => {
+                let _ = capture_item.RemoveClosed(capture_item_closed_token);
+                return Err(error);
+            }
+        };
+        configure_capture_session(&capture_session, self.borderless, self.show_cursor);
+        // The old item closing no longer ends the stream
+        let _ = self.capture_item.RemoveClosed(self.capture_item_closed_token);
+        let mut current_capture_session = self.stop_handle.capture_session.lock();
+        let _ = current_capture_session.Close();
+        self.discard_pooled_frames();
+        *self.shared_handler_data.target.lock() = target;
+        self.capture_item = capture_item;
+        self.capture_item_closed_token = capture_item_closed_token;
+        *current_capture_session = capture_session;
+        // The new target gets the whole idle timeout to produce its first frame
+        *self.shared_handler_data.idle_state.lock() = WindowsIdleState {
+            last_frame_arrived: Instant::now(),
+            idle: false,
+        };
+        if !self.capture_video {
+            return Ok(());
+        }
+        current_capture_session.StartCapture()
+            .map_err(|error| StreamUpdateError::Platform(format!("Failed to start capture session: {}", error.to_string()), PlatformErrorCode::HResult(error.code().0)))
+    }
+
+    // Discard the frames still in the pool, and the events raised for them - the callback lock must be held
+    fn discard_pooled_frames(&self) {
+        let mut stale_frame_count = 0;
+        while let Ok(frame) = self.frame_pool.TryGetNextFrame() {
+            let _ = frame.Close();
+            stale_frame_count += 1;
+        }
+        self.shared_handler_data.stale_frame_events.fetch_add(stale_frame_count, atomic::Ordering::AcqRel);
+    }
+
+    pub fn update_config(&mut self, config: &CaptureConfig) -> Result<(), StreamUpdateError> {
+        if self.shared_handler_data.closed.load(atomic::Ordering::Acquire) {
+            return Err(StreamUpdateError::AlreadyStopped);
+        }
+        let buffer_count = self.effective_config.power_profile.buffer_count(config.buffer_count);
+        let frame_interval = config_frame_interval(config, self.effective_config.power_profile);
+        let output_size = ((config.output_size.width + 0.1) as usize, (config.output_size.height + 0.1) as usize);
+        // Hold the callback lock so that the frame handler doesn't use or recreate the frame pool mid-update
+        let _callback = self.shared_handler_data.callback.lock();
+        let mut live_settings = self.shared_handler_data.live_settings.lock();
+        live_settings.output_size = output_size;
+        live_settings.max_dimensions = config.max_dimensions;
+        live_settings.scaled_output_size = config.cap_size(config.output_size);
+        live_settings.buffer_count = buffer_count;
+        live_settings.frame_interval_limiter.set_interval(frame_interval);
+        // Frame pools of scaled streams follow the content size, and are recreated when it changes
+        if !live_settings.scales_frames(self.scaling_quality) {
+            live_settings.frame_pool_size = output_size;
+        }
+        let frame_pool_size = SizeInt32 { Width: live_settings.frame_pool_size.0 as i32, Height: live_settings.frame_pool_size.1 as i32 };
+        drop(live_settings);
+        self.discard_pooled_frames();
+        self.frame_pool.Recreate(&self.direct3d_device, self.directx_pixel_format, buffer_count as i32, frame_pool_size)
+            .map_err(|error| StreamUpdateError::Platform(format!("Failed to recreate Direct3D11CaptureFramePool: {}", error.to_string()), PlatformErrorCode::HResult(error.code().0)))?;
+        self.effective_config.buffer_count = buffer_count;
+        self.effective_config.minimum_frame_interval = frame_interval;
+        Ok(())
+    }
+
+    pub fn set_source_rect(&mut self, source_rect: Option<Rect>, content_size: Size) -> Result<(), StreamError> {
+        let normalized_source_rect = source_rect.map(|source_rect| source_rect.scaled_2d((1.0 / content_size.width, 1.0 / content_size.height)));
+        *self.shared_handler_data.source_rect.lock() = normalized_source_rect;
         Ok(())
     }
+
+    pub fn backend_name(&self) -> &'static str {
+        WINDOWS_GRAPHICS_CAPTURE_BACKEND_NAME
+    }
+
+    pub fn effective_config(&self) -> EffectiveCaptureConfig {
+        self.effective_config.clone()
+    }
+
+    pub fn dropped_frame_count(&self) -> u64 {
+        self.shared_handler_data.dropped_frames.load(atomic::Ordering::Relaxed)
+    }
+
+    pub fn output_size(&self) -> Size {
+        let live_settings = self.shared_handler_data.live_settings.lock();
+        if self.scaling_quality != ScalingQuality::Smooth {
+            // The content and any crop are placed into the output
+            let Size { width, height } = live_settings.scaled_output_size;
+            return Size { width: (width as usize).max(1) as f64, height: (height as usize).max(1) as f64 };
+        }
+        let size = match live_settings.max_dimensions {
+            Some(_) => live_settings.scaled_output_size,
+            None => Size { width: live_settings.output_size.0 as f64, height: live_settings.output_size.1 as f64 },
+        };
+        // Smoothly scaled frames are cropped at the density of the content
+        match *self.shared_handler_data.source_rect.lock() {
+            Some(source_rect) => Size {
+                width: (size.width * source_rect.size.width).round().max(1.0),
+                height: (size.height * source_rect.size.height).round().max(1.0),
+            },
+            None => size,
+        }
+    }
 }
 
 impl Drop for WindowsCaptureStream {
@@ -534,3 +1330,34 @@ impl Drop for WindowsCaptureStream {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::{CapturableContent, CapturableContentFilter, CaptureStream};
+
+    #[test]
+    fn only_stream_creation_creates_d3d11_devices() {
+        // The supported formats are probed once per process, so they're probed before creation is made to fail
+        let pixel_format = CaptureStream::supported_pixel_formats()[0];
+        FAIL_D3D11_DEVICE_CREATION.store(true, atomic::Ordering::SeqCst);
+        let creations = D3D11_DEVICE_CREATIONS.load(atomic::Ordering::SeqCst);
+
+        let content = futures::executor::block_on(CapturableContent::new(CapturableContentFilter::EVERYTHING_NORMAL))
+            .expect("Expected enumeration to succeed when D3D11 devices can't be created");
+        assert_eq!(D3D11_DEVICE_CREATIONS.load(atomic::Ordering::SeqCst), creations);
+
+        // Streams need a display and capture access, which test machines don't always have
+        if let (Some(display), Some(token)) = (content.displays().next(), CaptureStream::test_access(false)) {
+            let config = CaptureConfig::with_display(display, pixel_format);
+            assert_eq!(D3D11_DEVICE_CREATIONS.load(atomic::Ordering::SeqCst), creations);
+            match CaptureStream::new(token, config, |_| {}) {
+                Err(StreamCreateError::Platform(message, _)) => assert!(message.contains("ID3D11Device"), "Unexpected error: {}", message),
+                Err(error) => panic!("Expected device creation to fail, got: {}", error),
+                Ok(_) => panic!("Expected device creation to fail"),
+            }
+            assert!(D3D11_DEVICE_CREATIONS.load(atomic::Ordering::SeqCst) > creations);
+        }
+        FAIL_D3D11_DEVICE_CREATION.store(false, atomic::Ordering::SeqCst);
+    }
+}
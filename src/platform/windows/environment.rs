@@ -0,0 +1,37 @@
+use windows::{Graphics::Capture::GraphicsCaptureSession, System::Profile::AnalyticsInfo, Win32::Graphics::Dxgi::{CreateDXGIFactory, IDXGIAdapter, IDXGIFactory5}};
+
+use crate::environment::{PlatformApiAvailability, PlatformEnvironment};
+
+use super::capture_stream::{graphics_capture_access_is_present, graphics_capture_session_has_property, IS_BORDER_REQUIRED_PROPERTY, IS_CURSOR_CAPTURE_ENABLED_PROPERTY, WINDOWS_GRAPHICS_CAPTURE_BACKEND_NAME};
+
+fn os_version() -> Option<String> {
+    let version_info = AnalyticsInfo::VersionInfo().ok()?;
+    // The device family version packs the four 16-bit version components into a u64
+    let version: u64 = version_info.DeviceFamilyVersion().ok()?.to_string().parse().ok()?;
+    Some(format!("{}.{}.{}.{}", (version >> 48) & 0xFFFF, (version >> 32) & 0xFFFF, (version >> 16) & 0xFFFF, version & 0xFFFF))
+}
+
+// The same adapter that WindowsCaptureStream::new uses when no device or adapter is configured
+fn gpu_adapter() -> Option<String> {
+    unsafe {
+        let dxgi_factory: IDXGIFactory5 = CreateDXGIFactory().ok()?;
+        let dxgi_adapter: IDXGIAdapter = dxgi_factory.EnumAdapters(0).ok()?;
+        let desc = dxgi_adapter.GetDesc().ok()?;
+        let description_length = desc.Description.iter().position(|c| *c == 0).unwrap_or(desc.Description.len());
+        Some(String::from_utf16_lossy(&desc.Description[..description_length]))
+    }
+}
+
+pub(crate) fn probe_environment() -> PlatformEnvironment {
+    PlatformEnvironment {
+        os_version: os_version(),
+        capture_backend: Some(WINDOWS_GRAPHICS_CAPTURE_BACKEND_NAME),
+        gpu_adapter: gpu_adapter(),
+        platform_apis: vec![
+            PlatformApiAvailability { name: "GraphicsCaptureSession", available: GraphicsCaptureSession::IsSupported().unwrap_or(false) },
+            PlatformApiAvailability { name: "GraphicsCaptureSession.IsBorderRequired", available: graphics_capture_session_has_property(IS_BORDER_REQUIRED_PROPERTY) },
+            PlatformApiAvailability { name: "GraphicsCaptureSession.IsCursorCaptureEnabled", available: graphics_capture_session_has_property(IS_CURSOR_CAPTURE_ENABLED_PROPERTY) },
+            PlatformApiAvailability { name: "GraphicsCaptureAccess", available: graphics_capture_access_is_present() },
+        ],
+    }
+}
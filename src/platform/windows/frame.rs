@@ -1,30 +1,75 @@
 use std::{marker::PhantomData, sync::Arc, time::Duration};
 
-use windows::{Graphics::{Capture::Direct3D11CaptureFrame, DirectX::DirectXPixelFormat, SizeInt32}, Win32::Graphics::Direct3D11::ID3D11Device};
+use windows::{core::ComInterface, Graphics::{Capture::Direct3D11CaptureFrame, DirectX::{Direct3D11::IDirect3DSurface, DirectXPixelFormat}, SizeInt32}, Win32::{Graphics::{Direct3D11::{ID3D11Device, ID3D11Texture2D, D3D11_CPU_ACCESS_READ, D3D11_MAPPED_SUBRESOURCE, D3D11_MAP_READ, D3D11_TEXTURE2D_DESC, D3D11_USAGE_STAGING}, Dxgi::{Common::{DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020, DXGI_COLOR_SPACE_RGB_FULL_G22_NONE_P709, DXGI_COLOR_SPACE_TYPE}, CreateDXGIFactory1, IDXGIFactory1, IDXGIOutput6}, Gdi::HMONITOR}, System::WinRT::Direct3D11::IDirect3DDxgiInterfaceAccess}};
 
-use crate::{prelude::{AudioBufferError, AudioCaptureFrame, AudioChannelCount, AudioChannelDataSamples, AudioSampleRate, Point, Rect, VideoCaptureFrame}, util::Size};
+use crate::{frame::{ColorSpace, CursorImage, PlaneData, VideoFrameMapError}, prelude::{AudioBufferError, AudioCaptureFrame, AudioChannelCount, AudioChannelDataSamples, AudioSampleRate, CapturePixelFormat, Point, Rect, VideoCaptureFrame}, util::Size};
 
 pub struct WindowsVideoFrame {
     pub(crate) device           : ID3D11Device,
     pub(crate) frame            : Direct3D11CaptureFrame,
     pub(crate) frame_size       : (usize, usize),
+    pub(crate) scaled_surface   : Option<IDirect3DSurface>,
     pub(crate) pixel_format     : DirectXPixelFormat,
     pub(crate) frame_id         : u64,
     pub(crate) dpi              : u32,
+    // The monitor showing the captured content as the frame arrived
+    pub(crate) monitor          : HMONITOR,
     pub(crate) t_capture        : std::time::Instant,
     pub(crate) t_origin         : std::time::Duration,
     pub(crate) duration         : std::time::Duration,
+    // The cursor as the frame arrived, relative to the top-left of the captured content
+    pub(crate) cursor_offset    : Option<Point>,
+    pub(crate) cursor_visible   : bool,
+    pub(crate) cursor_image     : Option<Arc<CursorImage>>,
     #[cfg(feature = "wgpu")]
     pub(crate) wgpu_device      : Option<Arc<dyn AsRef<wgpu::Device> + Send + Sync + 'static>>,
+    #[cfg(feature = "wgpu")]
+    pub(crate) wgpu_texture_cache: crate::feature::wgpu::SharedWgpuTextureCache,
+    // The leases on the shared textures handed out for the frame by `get_wgpu_texture`
+    #[cfg(feature = "wgpu")]
+    pub(crate) wgpu_texture_leases: parking_lot::Mutex<Vec<crate::feature::wgpu::WgpuTextureLease>>,
+}
+
+impl WindowsVideoFrame {
+    // The surface holding the frame contents - either the captured surface, or its scaled copy
+    pub(crate) fn surface(&self) -> windows::core::Result<IDirect3DSurface> {
+        match &self.scaled_surface {
+            Some(scaled_surface) => Ok(scaled_surface.clone()),
+            None => self.frame.Surface(),
+        }
+    }
+}
+
+// The color space of the DXGI output showing a monitor
+fn output_color_space(monitor: HMONITOR) -> Option<DXGI_COLOR_SPACE_TYPE> {
+    unsafe {
+        let factory: IDXGIFactory1 = CreateDXGIFactory1().ok()?;
+        let mut adapter_index = 0;
+        while let Ok(adapter) = factory.EnumAdapters1(adapter_index) {
+            let mut output_index = 0;
+            while let Ok(output) = adapter.EnumOutputs(output_index) {
+                if let Ok(output_desc) = output.cast::<IDXGIOutput6>().and_then(|output| output.GetDesc1()) {
+                    if output_desc.Monitor == monitor {
+                        return Some(output_desc.ColorSpace);
+                    }
+                }
+                output_index += 1;
+            }
+            adapter_index += 1;
+        }
+        None
+    }
 }
 
 impl VideoCaptureFrame for WindowsVideoFrame {
     fn size(&self) -> Size {
-        let size = self.frame.ContentSize().unwrap_or(SizeInt32::default());
-        Size {
-            width: size.Width as f64,
-            height: size.Height as f64,
+        if self.scaled_surface.is_some() {
+            return Size {
+                width: self.frame_size.0 as f64,
+                height: self.frame_size.1 as f64,
+            };
         }
+        self.native_content_size()
     }
 
     fn dpi(&self) -> f64 {
@@ -53,6 +98,104 @@ impl VideoCaptureFrame for WindowsVideoFrame {
             size: self.size()
         }
     }
+
+    fn native_content_size(&self) -> Size {
+        let size = self.frame.ContentSize().unwrap_or(SizeInt32::default());
+        Size {
+            width: size.Width as f64,
+            height: size.Height as f64,
+        }
+    }
+
+    fn cursor_position(&self) -> Option<Point> {
+        let native_rect = Rect { origin: Point::ZERO, size: self.native_content_size() };
+        native_rect.map_point_to(self.cursor_offset?, &self.content_rect())
+    }
+
+    fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    fn cursor_image(&self) -> Option<Arc<CursorImage>> {
+        self.cursor_image.clone()
+    }
+
+    // Direct3D11CaptureFrame only reports dirty regions on recent Windows 11 builds, which the windows 0.52 bindings predate
+    fn dirty_rects(&self) -> Option<Vec<Rect>> {
+        None
+    }
+
+    fn color_space(&self) -> ColorSpace {
+        // Windows.Graphics.Capture converts content to sRGB for 8 bit formats, and otherwise keeps the color space of the output
+        if self.pixel_format == DirectXPixelFormat::B8G8R8A8UIntNormalized {
+            return ColorSpace::Srgb;
+        }
+        // ...and to scRGB for floating point formats
+        if self.pixel_format == DirectXPixelFormat::R16G16B16A16Float {
+            return ColorSpace::LinearExtendedSrgb;
+        }
+        match output_color_space(self.monitor) {
+            Some(DXGI_COLOR_SPACE_RGB_FULL_G22_NONE_P709) | None => ColorSpace::Srgb,
+            Some(DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020) => ColorSpace::Rec2020Pq,
+            Some(_) => ColorSpace::Unknown,
+        }
+    }
+
+    fn map_planes<T>(&self, map: impl FnOnce(&[PlaneData<'_>]) -> T) -> Result<T, VideoFrameMapError> {
+        let format = match self.pixel_format {
+            DirectXPixelFormat::B8G8R8A8UIntNormalized => CapturePixelFormat::Bgra8888,
+            DirectXPixelFormat::R10G10B10A2UIntNormalized => CapturePixelFormat::Argb2101010,
+            DirectXPixelFormat::R16G16B16A16UIntNormalized => CapturePixelFormat::Rgba16,
+            DirectXPixelFormat::R16G16B16A16Float => CapturePixelFormat::RgbaF16,
+            _ => return Err(VideoFrameMapError::UnsupportedPixelFormat),
+        };
+        let (width, height) = self.frame_size;
+        let surface = self.surface()
+            .map_err(|error| VideoFrameMapError::Other(format!("Couldn't get frame surface: {}", error.to_string())))?;
+        let dxgi_interface_access: IDirect3DDxgiInterfaceAccess = surface.cast()
+            .map_err(|_| VideoFrameMapError::Other("Couldn't create surface interface access".to_string()))?;
+        unsafe {
+            let surface_texture: ID3D11Texture2D = dxgi_interface_access.GetInterface()
+                .map_err(|_| VideoFrameMapError::Other("Couldn't create surface texture from surface IDirect3DDxgiInterfaceAccess".to_string()))?;
+            let mut surface_desc = D3D11_TEXTURE2D_DESC::default();
+            surface_texture.GetDesc(&mut surface_desc as *mut _);
+            // A CPU readable copy of the surface, as the captured surface itself can't be mapped
+            let mut staging_texture_desc = D3D11_TEXTURE2D_DESC::default();
+            staging_texture_desc.CPUAccessFlags = D3D11_CPU_ACCESS_READ.0 as u32;
+            staging_texture_desc.ArraySize = 1;
+            staging_texture_desc.BindFlags = 0;
+            staging_texture_desc.Width = surface_desc.Width;
+            staging_texture_desc.Height = surface_desc.Height;
+            staging_texture_desc.MipLevels = 1;
+            staging_texture_desc.SampleDesc.Count = 1;
+            staging_texture_desc.SampleDesc.Quality = 0;
+            staging_texture_desc.Usage = D3D11_USAGE_STAGING;
+            staging_texture_desc.Format = surface_desc.Format;
+            let mut staging_texture = Option::<ID3D11Texture2D>::None;
+            self.device.CreateTexture2D(&staging_texture_desc as *const _, None, Some(&mut staging_texture as *mut _))
+                .map_err(|error| VideoFrameMapError::Other(format!("Failed to create staging texture: {}", error.to_string())))?;
+            let staging_texture = staging_texture
+                .ok_or(VideoFrameMapError::Other("Failed to create staging texture".to_string()))?;
+            let context = self.device.GetImmediateContext()
+                .map_err(|_| VideoFrameMapError::Other("Couldn't get immediate d3d11 context".to_string()))?;
+            context.CopyResource(&staging_texture, &surface_texture);
+            let mut mapped_resource = D3D11_MAPPED_SUBRESOURCE::default();
+            context.Map(&staging_texture, 0, D3D11_MAP_READ, 0, Some(&mut mapped_resource as *mut _))
+                .map_err(|_| VideoFrameMapError::Other("Couldn't map staging texture".to_string()))?;
+            let bytes_per_row = mapped_resource.RowPitch as usize;
+            // The plane borrows the mapped staging texture, which is only unmapped after `map` returns
+            let planes = [PlaneData {
+                data: std::slice::from_raw_parts(mapped_resource.pData as *const u8, bytes_per_row * height),
+                width,
+                height,
+                bytes_per_row,
+                format,
+            }];
+            let result = map(&planes);
+            let _ = context.Unmap(&staging_texture, 0);
+            Ok(result)
+        }
+    }
 }
 
 impl Drop for WindowsVideoFrame {
@@ -62,11 +205,14 @@ impl Drop for WindowsVideoFrame {
 }
 
 pub struct WindowsAudioFrame {
-    pub(crate) data: Box<[i16]>,
+    // Interleaved samples, one per channel for each audio frame
+    pub(crate) data: Box<[f32]>,
     pub(crate) channel_count: AudioChannelCount,
     pub(crate) sample_rate: AudioSampleRate,
     pub(crate) duration: Duration,
     pub(crate) origin_time: Duration,
+    pub(crate) silent: bool,
+    pub(crate) discontinuity: bool,
     pub(crate) frame_id: u64,
 }
 
@@ -80,25 +226,18 @@ impl AudioCaptureFrame for WindowsAudioFrame {
     }
 
     fn audio_channel_buffer(&mut self, channel: usize) -> Result<crate::prelude::AudioChannelData<'_>, crate::prelude::AudioBufferError> {
-        let element_stride = match self.channel_count {
-            AudioChannelCount::Mono => {
-                if channel != 0 {
-                    return Err(AudioBufferError::InvalidChannel)
-                }
-                0
-            },
-            AudioChannelCount::Stereo => {
-                if channel > 1 {
-                    return Err(AudioBufferError::InvalidChannel)
-                }
-                channel
-            },
+        let channel_count = match self.channel_count {
+            AudioChannelCount::Mono => 1,
+            AudioChannelCount::Stereo => 2,
         };
-        let data = &self.data[element_stride] as *const i16 as *const u8;
-        Ok(crate::prelude::AudioChannelData::I16(AudioChannelDataSamples {
+        if channel >= channel_count {
+            return Err(AudioBufferError::InvalidChannel)
+        }
+        let data = self.data.as_ptr().wrapping_add(channel) as *const u8;
+        Ok(crate::prelude::AudioChannelData::F32(AudioChannelDataSamples {
             data,
-            stride: element_stride / 2,
-            length: self.data.len() / element_stride,
+            stride: channel_count * std::mem::size_of::<f32>(),
+            length: self.data.len() / channel_count,
             phantom_lifetime: PhantomData
         }))
     }
@@ -111,6 +250,14 @@ impl AudioCaptureFrame for WindowsAudioFrame {
         self.origin_time
     }
 
+    fn silent(&self) -> bool {
+        self.silent
+    }
+
+    fn discontinuity(&self) -> bool {
+        self.discontinuity
+    }
+
     fn frame_id(&self) -> u64 {
         self.frame_id
     }
@@ -0,0 +1,235 @@
+use std::sync::OnceLock;
+
+use crate::util::Rect;
+
+use windows::{core::{ComInterface, PCSTR}, Graphics::DirectX::{Direct3D11::IDirect3DSurface, DirectXPixelFormat}, Win32::{Graphics::{Direct3D::{Fxc::D3DCompile, ID3DBlob, D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST}, Direct3D11::{ID3D11Buffer, ID3D11Device, ID3D11PixelShader, ID3D11RenderTargetView, ID3D11SamplerState, ID3D11ShaderResourceView, ID3D11Texture2D, ID3D11VertexShader, D3D11_BIND_CONSTANT_BUFFER, D3D11_BIND_RENDER_TARGET, D3D11_BIND_SHADER_RESOURCE, D3D11_BUFFER_DESC, D3D11_COMPARISON_NEVER, D3D11_FILTER, D3D11_FILTER_MIN_MAG_MIP_LINEAR, D3D11_FILTER_MIN_MAG_MIP_POINT, D3D11_SAMPLER_DESC, D3D11_TEXTURE2D_DESC, D3D11_TEXTURE_ADDRESS_CLAMP, D3D11_USAGE_DEFAULT, D3D11_VIEWPORT}, Dxgi::{Common::{DXGI_FORMAT, DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_R10G10B10A2_UNORM, DXGI_FORMAT_R16G16B16A16_FLOAT, DXGI_FORMAT_R16G16B16A16_UNORM}, IDXGISurface}}, System::WinRT::Direct3D11::{CreateDirect3D11SurfaceFromDXGISurface, IDirect3DDxgiInterfaceAccess}}};
+
+const SCALE_SHADER_SOURCE: &str = r#"
+Texture2D source_texture : register(t0);
+SamplerState source_sampler : register(s0);
+
+cbuffer ScaleParams : register(b0) {
+    float2 uv_scale;
+    float2 uv_offset;
+};
+
+struct VertexOutput {
+    float4 position : SV_Position;
+    float2 uv : TEXCOORD0;
+};
+
+VertexOutput vs_main(uint vertex_id : SV_VertexID) {
+    // A single triangle covering the whole render target
+    float2 t = float2((vertex_id << 1) & 2, vertex_id & 2);
+    VertexOutput output;
+    output.uv = uv_offset + t * uv_scale;
+    output.position = float4(t * float2(2.0, -2.0) + float2(-1.0, 1.0), 0.0, 1.0);
+    return output;
+}
+
+float4 ps_main(VertexOutput input) : SV_Target {
+    return source_texture.Sample(source_sampler, input.uv);
+}
+"#;
+
+// Scales captured frames on the GPU, since Windows.Graphics.Capture always captures at the native size of the content
+pub(crate) struct WindowsFrameScaler {
+    device: ID3D11Device,
+    vertex_shader: ID3D11VertexShader,
+    pixel_shader: ID3D11PixelShader,
+    linear_sampler: ID3D11SamplerState,
+    point_sampler: ID3D11SamplerState,
+    params: ID3D11Buffer,
+}
+
+unsafe impl Send for WindowsFrameScaler {}
+
+fn compile_shader(entry_point: &[u8], target: &[u8]) -> Result<ID3DBlob, String> {
+    unsafe {
+        let mut code = None;
+        let mut errors = None;
+        let result = D3DCompile(
+            SCALE_SHADER_SOURCE.as_ptr() as *const _,
+            SCALE_SHADER_SOURCE.len(),
+            PCSTR::null(),
+            None,
+            None,
+            PCSTR(entry_point.as_ptr()),
+            PCSTR(target.as_ptr()),
+            0,
+            0,
+            &mut code as *mut _,
+            Some(&mut errors as *mut _)
+        );
+        match (result, code) {
+            (Ok(_), Some(code)) => Ok(code),
+            (result, _) => {
+                let message = errors.map(|errors: ID3DBlob| {
+                    let bytes = std::slice::from_raw_parts(errors.GetBufferPointer() as *const u8, errors.GetBufferSize());
+                    String::from_utf8_lossy(bytes).to_string()
+                }).unwrap_or_else(|| format!("{:?}", result));
+                Err(format!("Failed to compile frame scaling shader: {}", message))
+            }
+        }
+    }
+}
+
+fn blob_bytes(blob: &ID3DBlob) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(blob.GetBufferPointer() as *const u8, blob.GetBufferSize()) }
+}
+
+// The compiled vertex and pixel shaders, compiled once since scalers are also created for single bitmap readbacks
+fn shader_code() -> Result<&'static (Vec<u8>, Vec<u8>), String> {
+    static SHADER_CODE: OnceLock<Result<(Vec<u8>, Vec<u8>), String>> = OnceLock::new();
+    SHADER_CODE.get_or_init(|| {
+        let vertex_shader_code = compile_shader(b"vs_main\0", b"vs_5_0\0")?;
+        let pixel_shader_code = compile_shader(b"ps_main\0", b"ps_5_0\0")?;
+        Ok((blob_bytes(&vertex_shader_code).to_vec(), blob_bytes(&pixel_shader_code).to_vec()))
+    }).as_ref().map_err(Clone::clone)
+}
+
+fn create_sampler(device: &ID3D11Device, filter: D3D11_FILTER) -> Result<ID3D11SamplerState, String> {
+    let mut sampler_desc = D3D11_SAMPLER_DESC::default();
+    sampler_desc.Filter = filter;
+    sampler_desc.AddressU = D3D11_TEXTURE_ADDRESS_CLAMP;
+    sampler_desc.AddressV = D3D11_TEXTURE_ADDRESS_CLAMP;
+    sampler_desc.AddressW = D3D11_TEXTURE_ADDRESS_CLAMP;
+    sampler_desc.ComparisonFunc = D3D11_COMPARISON_NEVER;
+    sampler_desc.MaxLOD = f32::MAX;
+    let mut sampler = None;
+    unsafe { device.CreateSamplerState(&sampler_desc as *const _, Some(&mut sampler as *mut _)) }
+        .map_err(|error| format!("Failed to create frame scaling sampler: {}", error.to_string()))?;
+    sampler.ok_or_else(|| "Failed to create frame scaling sampler".to_string())
+}
+
+fn dxgi_format(pixel_format: DirectXPixelFormat) -> Result<DXGI_FORMAT, String> {
+    match pixel_format {
+        DirectXPixelFormat::B8G8R8A8UIntNormalized => Ok(DXGI_FORMAT_B8G8R8A8_UNORM),
+        DirectXPixelFormat::R10G10B10A2UIntNormalized => Ok(DXGI_FORMAT_R10G10B10A2_UNORM),
+        DirectXPixelFormat::R16G16B16A16UIntNormalized => Ok(DXGI_FORMAT_R16G16B16A16_UNORM),
+        DirectXPixelFormat::R16G16B16A16Float => Ok(DXGI_FORMAT_R16G16B16A16_FLOAT),
+        _ => Err("Unsupported pixel format for frame scaling".to_string()),
+    }
+}
+
+impl WindowsFrameScaler {
+    pub(crate) fn new(device: ID3D11Device) -> Result<Self, String> {
+        let (vertex_shader_code, pixel_shader_code) = shader_code()?;
+        unsafe {
+            let mut vertex_shader = None;
+            device.CreateVertexShader(vertex_shader_code, None, Some(&mut vertex_shader as *mut _))
+                .map_err(|error| format!("Failed to create frame scaling vertex shader: {}", error.to_string()))?;
+            let mut pixel_shader = None;
+            device.CreatePixelShader(pixel_shader_code, None, Some(&mut pixel_shader as *mut _))
+                .map_err(|error| format!("Failed to create frame scaling pixel shader: {}", error.to_string()))?;
+            let linear_sampler = create_sampler(&device, D3D11_FILTER_MIN_MAG_MIP_LINEAR)?;
+            let point_sampler = create_sampler(&device, D3D11_FILTER_MIN_MAG_MIP_POINT)?;
+            let mut params_desc = D3D11_BUFFER_DESC::default();
+            params_desc.ByteWidth = std::mem::size_of::<[f32; 4]>() as u32;
+            params_desc.Usage = D3D11_USAGE_DEFAULT;
+            params_desc.BindFlags = D3D11_BIND_CONSTANT_BUFFER.0 as u32;
+            let mut params = None;
+            device.CreateBuffer(&params_desc as *const _, None, Some(&mut params as *mut _))
+                .map_err(|error| format!("Failed to create frame scaling parameter buffer: {}", error.to_string()))?;
+            match (vertex_shader, pixel_shader, params) {
+                (Some(vertex_shader), Some(pixel_shader), Some(params)) => Ok(Self {
+                    device,
+                    vertex_shader,
+                    pixel_shader,
+                    linear_sampler,
+                    point_sampler,
+                    params,
+                }),
+                _ => Err("Failed to create frame scaling resources".to_string()),
+            }
+        }
+    }
+
+    // Scale the top-left content_size region of a captured surface into content_rect of a new surface of target_size,
+    // filling the rest of the new surface with opaque black
+    // Scale the source rect of a surface (in pixels) into the content rect of a new surface of the target size
+    pub(crate) fn scale(&self, surface: &IDirect3DSurface, pixel_format: DirectXPixelFormat, source_rect: Rect, target_size: (usize, usize), content_rect: Rect, nearest_neighbor: bool) -> Result<IDirect3DSurface, String> {
+        let format = dxgi_format(pixel_format)?;
+        unsafe {
+            let interface_access: IDirect3DDxgiInterfaceAccess = surface.cast()
+                .map_err(|_| "Couldn't create surface interface access".to_string())?;
+            let source_texture: ID3D11Texture2D = interface_access.GetInterface()
+                .map_err(|_| "Couldn't get ID3D11Texture2D from frame surface".to_string())?;
+            let target_texture = self.scale_texture(&source_texture, format, source_rect, target_size, content_rect, nearest_neighbor)?;
+            let target_surface: IDXGISurface = target_texture.cast()
+                .map_err(|_| "Couldn't cast scaled frame texture to IDXGISurface".to_string())?;
+            let target_surface = CreateDirect3D11SurfaceFromDXGISurface(&target_surface)
+                .map_err(|error| format!("Failed to create IDirect3DSurface for scaled frame: {}", error.to_string()))?;
+            target_surface.cast()
+                .map_err(|_| "Couldn't cast scaled frame to IDirect3DSurface".to_string())
+        }
+    }
+
+    // Scale the source rect of a texture (in pixels of its top mip level) into the content rect of a new texture of the target size -
+    // with smooth scaling, every mip level of the source is sampled from, so a texture with generated mips is downscaled without aliasing
+    pub(crate) fn scale_texture(&self, source_texture: &ID3D11Texture2D, format: DXGI_FORMAT, source_rect: Rect, target_size: (usize, usize), content_rect: Rect, nearest_neighbor: bool) -> Result<ID3D11Texture2D, String> {
+        unsafe {
+            let mut source_desc = D3D11_TEXTURE2D_DESC::default();
+            source_texture.GetDesc(&mut source_desc as *mut _);
+
+            let mut target_desc = D3D11_TEXTURE2D_DESC::default();
+            target_desc.Width = target_size.0 as u32;
+            target_desc.Height = target_size.1 as u32;
+            target_desc.MipLevels = 1;
+            target_desc.ArraySize = 1;
+            target_desc.Format = format;
+            target_desc.SampleDesc.Count = 1;
+            target_desc.SampleDesc.Quality = 0;
+            target_desc.Usage = D3D11_USAGE_DEFAULT;
+            target_desc.BindFlags = (D3D11_BIND_RENDER_TARGET.0 | D3D11_BIND_SHADER_RESOURCE.0) as u32;
+            let mut target_texture = None;
+            self.device.CreateTexture2D(&target_desc as *const _, None, Some(&mut target_texture as *mut _))
+                .map_err(|error| format!("Failed to create scaled frame texture: {}", error.to_string()))?;
+            let target_texture: ID3D11Texture2D = target_texture.ok_or_else(|| "Failed to create scaled frame texture".to_string())?;
+
+            let mut source_view: Option<ID3D11ShaderResourceView> = None;
+            self.device.CreateShaderResourceView(source_texture, None, Some(&mut source_view as *mut _))
+                .map_err(|error| format!("Failed to create frame shader resource view: {}", error.to_string()))?;
+            let mut target_view: Option<ID3D11RenderTargetView> = None;
+            self.device.CreateRenderTargetView(&target_texture, None, Some(&mut target_view as *mut _))
+                .map_err(|error| format!("Failed to create scaled frame render target view: {}", error.to_string()))?;
+
+            let context = self.device.GetImmediateContext()
+                .map_err(|_| "Couldn't get immediate d3d11 context".to_string())?;
+            let params = [
+                source_rect.size.width as f32 / source_desc.Width.max(1) as f32,
+                source_rect.size.height as f32 / source_desc.Height.max(1) as f32,
+                source_rect.origin.x as f32 / source_desc.Width.max(1) as f32,
+                source_rect.origin.y as f32 / source_desc.Height.max(1) as f32,
+            ];
+            if let Some(target_view) = &target_view {
+                context.ClearRenderTargetView(target_view, &[0.0, 0.0, 0.0, 1.0]);
+            }
+            let sampler = if nearest_neighbor { &self.point_sampler } else { &self.linear_sampler };
+            context.UpdateSubresource(&self.params, 0, None, params.as_ptr() as *const _, 0, 0);
+            context.IASetPrimitiveTopology(D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+            context.IASetInputLayout(None);
+            context.VSSetShader(&self.vertex_shader, None);
+            context.VSSetConstantBuffers(0, Some(&[Some(self.params.clone())]));
+            context.PSSetShader(&self.pixel_shader, None);
+            context.PSSetShaderResources(0, Some(&[source_view]));
+            context.PSSetSamplers(0, Some(&[Some(sampler.clone())]));
+            context.OMSetRenderTargets(Some(&[target_view]), None);
+            context.RSSetViewports(Some(&[D3D11_VIEWPORT {
+                TopLeftX: content_rect.origin.x as f32,
+                TopLeftY: content_rect.origin.y as f32,
+                Width: content_rect.size.width as f32,
+                Height: content_rect.size.height as f32,
+                MinDepth: 0.0,
+                MaxDepth: 1.0,
+            }]));
+            context.Draw(3, 0);
+            // Unbind the source so the capture frame pool can reuse its surface
+            context.PSSetShaderResources(0, Some(&[None]));
+            context.OMSetRenderTargets(None, None);
+            context.Flush();
+
+            Ok(target_texture)
+        }
+    }
+}
@@ -4,6 +4,8 @@ pub(crate) mod capture_stream;
 mod capturable_content;
 mod audio_capture_stream;
 pub(crate) mod frame;
+pub(crate) mod frame_scaler;
+mod environment;
 
 pub(crate) struct AutoHandle(pub HANDLE);
 impl Drop for AutoHandle {
@@ -45,12 +47,16 @@ pub(crate) use capturable_content::WindowsCapturableDisplay as ImplCapturableDis
 pub(crate) use capturable_content::WindowsCapturableWindow as ImplCapturableWindow;
 pub(crate) use capturable_content::WindowsCapturableContent as ImplCapturableContent;
 pub(crate) use capturable_content::WindowsCapturableContentFilter as ImplCapturableContentFilter;
+pub(crate) use capturable_content::WindowsContentChangeNotifier as ImplContentChangeNotifier;
 
 pub(crate) use capture_stream::WindowsCaptureStream as ImplCaptureStream;
+pub(crate) use capture_stream::WindowsStopHandle as ImplStopHandle;
 pub(crate) use capture_stream::WindowsCaptureConfig as ImplCaptureConfig;
 pub(crate) use capture_stream::WindowsAudioCaptureConfig as ImplAudioCaptureConfig;
 pub(crate) use capture_stream::WindowsCaptureAccessToken as ImplCaptureAccessToken;
 
+pub(crate) use environment::probe_environment;
+
 pub(crate) use frame::WindowsVideoFrame as ImplVideoFrame;
 pub(crate) use frame::WindowsAudioFrame as ImplAudioFrame;
 
@@ -2,8 +2,14 @@ pub use crate::capturable_content::*;
 pub use crate::frame::*;
 pub use crate::capture_stream::*;
 pub use crate::util::*;
+pub use crate::error::*;
+pub use crate::environment::*;
+pub use crate::compositor::*;
+pub use crate::frame_stream::*;
+pub use crate::capture_stream_group::*;
 
 #[cfg(feature = "wgpu")]
+#[cfg(any(target_os = "macos", target_os = "windows"))]
 pub use crate::feature::wgpu::*;
 #[cfg(feature = "bitmap")]
 pub use crate::feature::bitmap::*;
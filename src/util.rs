@@ -1,3 +1,8 @@
+use std::{future::Future, sync::Once, time::{Duration, Instant}};
+
+use futures::{channel::oneshot, future::{select, Either}};
+use parking_lot::{Condvar, Mutex};
+
 /// Represents a 2D size
 #[derive(Debug, Copy, Clone)]
 pub struct Size {
@@ -21,6 +26,18 @@ impl Size {
             height: self.height * scale.1
         }
     }
+
+    // Scale the size down to fit within a maximum size, preserving its aspect ratio
+    pub(crate) fn fit_within(&self, max_size: Size) -> Self {
+        if self.width <= max_size.width && self.height <= max_size.height {
+            return *self;
+        }
+        let scale = (max_size.width / self.width).min(max_size.height / self.height);
+        Self {
+            width: (self.width * scale).floor().max(1.0),
+            height: (self.height * scale).floor().max(1.0),
+        }
+    }
 }
 
 /// Represents a 2D point
@@ -77,4 +94,126 @@ impl Rect {
             size: self.size.scaled_2d(scale)
         }
     }
+
+    /// The area of the rectangle
+    pub fn area(&self) -> f64 {
+        self.size.width.max(0.0) * self.size.height.max(0.0)
+    }
+
+    /// The overlapping region of two rectangles, if they overlap
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let min_x = self.origin.x.max(other.origin.x);
+        let min_y = self.origin.y.max(other.origin.y);
+        let max_x = (self.origin.x + self.size.width).min(other.origin.x + other.size.width);
+        let max_y = (self.origin.y + self.size.height).min(other.origin.y + other.size.height);
+        if max_x <= min_x || max_y <= min_y {
+            return None;
+        }
+        Some(Rect {
+            origin: Point { x: min_x, y: min_y },
+            size: Size { width: max_x - min_x, height: max_y - min_y }
+        })
+    }
+
+    // Map a point within this rectangle to the same relative position within another - returns None if the point is outside this rectangle
+    pub(crate) fn map_point_to(&self, point: Point, other: &Rect) -> Option<Point> {
+        if self.size.width <= 0.0 || self.size.height <= 0.0 {
+            return None;
+        }
+        let relative_x = (point.x - self.origin.x) / self.size.width;
+        let relative_y = (point.y - self.origin.y) / self.size.height;
+        if !(0.0..1.0).contains(&relative_x) || !(0.0..1.0).contains(&relative_y) {
+            return None;
+        }
+        Some(Point {
+            x: other.origin.x + relative_x * other.size.width,
+            y: other.origin.y + relative_y * other.size.height,
+        })
+    }
+}
+
+// Pending sleeps and their deadlines (None for durations too long to represent), resolved by a single timer thread
+static SLEEPS: Mutex<Vec<(Option<Instant>, oneshot::Sender<()>)>> = parking_lot::const_mutex(Vec::new());
+static SLEEPS_CHANGED: Condvar = Condvar::new();
+static SLEEP_THREAD: Once = Once::new();
+
+fn sleep_thread() {
+    let mut sleeps = SLEEPS.lock();
+    loop {
+        // Resolve the sleeps which are due, and forget the ones nobody is waiting on any more
+        let now = Instant::now();
+        let mut i = 0;
+        while i < sleeps.len() {
+            let (deadline, tx) = &sleeps[i];
+            if deadline.is_some_and(|deadline| deadline <= now) || tx.is_canceled() {
+                let (_, tx) = sleeps.swap_remove(i);
+                let _ = tx.send(());
+            } else {
+                i += 1;
+            }
+        }
+        match sleeps.iter().filter_map(|(deadline, _)| *deadline).min() {
+            Some(deadline) => { SLEEPS_CHANGED.wait_until(&mut sleeps, deadline); },
+            None => SLEEPS_CHANGED.wait(&mut sleeps),
+        }
+    }
+}
+
+// Resolve after the given duration, timed on a timer thread shared by every sleep so no executor is blocked
+pub(crate) fn sleep(duration: Duration) -> oneshot::Receiver<()> {
+    let (tx, rx) = oneshot::channel();
+    SLEEP_THREAD.call_once(|| {
+        std::thread::Builder::new()
+            .name("crabgrab-sleep".into())
+            .spawn(sleep_thread)
+            .expect("Failed to start the sleep timer thread");
+    });
+    SLEEPS.lock().push((Instant::now().checked_add(duration), tx));
+    SLEEPS_CHANGED.notify_one();
+    rx
+}
+
+// Wait for the future to resolve, or for the timeout to elapse (resolving to None)
+pub(crate) async fn with_timeout<F: Future + Unpin>(future: F, timeout: Option<Duration>) -> Option<F::Output> {
+    let Some(timeout) = timeout else {
+        return Some(future.await);
+    };
+    match select(future, sleep(timeout)).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use futures::{executor::block_on, future::{select, Either}};
+
+    use super::{sleep, with_timeout};
+
+    #[test]
+    fn sleeps_resolve_after_their_own_duration() {
+        let start = Instant::now();
+        // A longer sleep registered first doesn't hold up a shorter one registered after it
+        let long = sleep(Duration::from_secs(60));
+        let short = sleep(Duration::from_millis(20));
+        match block_on(select(long, short)) {
+            Either::Right((result, _)) => assert!(result.is_ok()),
+            Either::Left(_) => panic!("the longer sleep resolved first"),
+        }
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(20));
+        assert!(elapsed < Duration::from_secs(10));
+        // Durations too long to represent never resolve
+        assert_eq!(block_on(with_timeout(sleep(Duration::MAX), Some(Duration::from_millis(10)))), None);
+    }
+
+    #[test]
+    fn timeouts_resolve_with_the_future_when_it_finishes_first() {
+        let start = Instant::now();
+        let result = block_on(with_timeout(sleep(Duration::from_millis(10)), Some(Duration::from_secs(60))));
+        assert_eq!(result, Some(Ok(())));
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
 }
@@ -0,0 +1,503 @@
+//! Checks the crate's public API against the snapshot in `tests/public_api.txt`, so that changes to it are deliberate
+//!
+//! The public items are read from the sources rather than from rustdoc, so the check runs on any platform with a stable
+//! toolchain, and covers the MacOS and Windows APIs everywhere. After an intended API change, update the snapshot with
+//! `UPDATE_PUBLIC_API=1 cargo test --test public_api`, and commit it along with the change.
+
+use std::{collections::HashSet, path::{Path, PathBuf}};
+
+const SNAPSHOT_PATH: &str = "tests/public_api.txt";
+
+#[test]
+fn public_api_matches_snapshot() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let api = public_api(&manifest_dir.join("src").join("lib.rs"));
+    let snapshot_path = manifest_dir.join(SNAPSHOT_PATH);
+    if std::env::var_os("UPDATE_PUBLIC_API").is_some() {
+        std::fs::write(&snapshot_path, api.join("\n") + "\n").unwrap();
+        return;
+    }
+    let snapshot = std::fs::read_to_string(&snapshot_path).unwrap_or_default();
+    let snapshot: Vec<&str> = snapshot.lines().collect();
+    if snapshot != api {
+        let snapshot_set: HashSet<&str> = snapshot.iter().copied().collect();
+        let api_set: HashSet<&str> = api.iter().map(String::as_str).collect();
+        let removed: Vec<&str> = snapshot.iter().copied().filter(|line| !api_set.contains(line)).collect();
+        let added: Vec<&str> = api.iter().map(String::as_str).filter(|line| !snapshot_set.contains(line)).collect();
+        panic!(
+            "The public API differs from {}\n\nRemoved:\n{}\n\nAdded:\n{}\n\nIf this is intended, run `UPDATE_PUBLIC_API=1 cargo test --test public_api` and commit the updated snapshot",
+            SNAPSHOT_PATH,
+            removed.join("\n"),
+            added.join("\n"),
+        );
+    }
+}
+
+// Lists the public items reachable from the crate root, sorted, one per line
+fn public_api(lib_path: &Path) -> Vec<String> {
+    let mut scan = ApiScan::default();
+    let text = strip_comments_and_literals(&std::fs::read_to_string(lib_path).unwrap());
+    scan.scan_items(&text, "crabgrab", &ModuleSource::Directory(lib_path.parent().unwrap().to_path_buf()), None);
+    let mut lines = scan.lines;
+    for deferred_impl in scan.impls {
+        lines.extend(deferred_impl.lines(&scan.public_names));
+    }
+    lines.sort();
+    lines.dedup();
+    lines
+}
+
+// Where the files of a module's child modules are found
+enum ModuleSource {
+    // Children are `dir/child.rs` or `dir/child/mod.rs`
+    Directory(PathBuf),
+}
+
+impl ModuleSource {
+    fn child_file(&self, name: &str) -> (PathBuf, ModuleSource) {
+        let ModuleSource::Directory(dir) = self;
+        let flat_file = dir.join(format!("{name}.rs"));
+        if flat_file.exists() {
+            (flat_file, ModuleSource::Directory(dir.join(name)))
+        } else {
+            (dir.join(name).join("mod.rs"), ModuleSource::Directory(dir.join(name)))
+        }
+    }
+}
+
+#[derive(Default)]
+struct ApiScan {
+    lines: Vec<String>,
+    // Impl blocks, which are only listed once every public type and trait is known
+    impls: Vec<DeferredImpl>,
+    public_names: HashSet<String>,
+}
+
+struct DeferredImpl {
+    module: String,
+    attributes: String,
+    header: String,
+    body: Option<String>,
+}
+
+impl DeferredImpl {
+    fn lines(&self, public_names: &HashSet<String>) -> Vec<String> {
+        let (trait_name, self_type) = impl_names(&self.header);
+        let public_trait = trait_name.as_ref().is_some_and(|name| public_names.contains(name));
+        if !public_names.contains(&self_type) && !public_trait {
+            return Vec::new();
+        }
+        if trait_name.is_some() {
+            return vec![format!("{} {}{}", self.module, self.attributes, self.header)];
+        }
+        parse_items(self.body.as_deref().unwrap_or(""))
+            .into_iter()
+            .filter(|item| !item.is_test() && is_public(&item.header))
+            .map(|item| format!("{} {}{}: {}{}", self.module, self.attributes, self.header, item.attributes(), item.signature()))
+            .collect()
+    }
+}
+
+impl ApiScan {
+    // Scans the items of a module - `exported` limits a private module to the items its parent re-exports, which are listed under `module`
+    fn scan_items(&mut self, text: &str, module: &str, source: &ModuleSource, exported: Option<&HashSet<String>>) {
+        let items = parse_items(text);
+        for item in items.iter() {
+            if item.is_test() {
+                continue;
+            }
+            let words: Vec<&str> = item.header.split_whitespace().collect();
+            let visibility_len = if words.first().is_some_and(|word| *word == "pub" || word.starts_with("pub(")) { 1 } else { 0 };
+            // Generic items have their parameters attached, as in `impl<T>`
+            let kind = identifier_prefix(words.get(visibility_len).copied().unwrap_or(""));
+            let kind = kind.as_str();
+            if kind == "impl" || (kind == "unsafe" && words.get(visibility_len + 1).is_some_and(|word| word.starts_with("impl"))) {
+                self.impls.push(DeferredImpl { module: module.to_string(), attributes: item.attributes(), header: item.signature(), body: item.body.clone() });
+                continue;
+            }
+            if kind == "mod" {
+                let name = words[visibility_len + 1].trim_end_matches(';').to_string();
+                let public_module = is_public(&item.header) && exported.is_none();
+                let child_exported = if public_module {
+                    None
+                } else {
+                    Some(reexported_names(&items, &name, exported))
+                };
+                let child_module = if public_module {
+                    self.lines.push(format!("{} {}{}", module, item.attributes(), item.signature()));
+                    format!("{module}::{name}")
+                } else {
+                    module.to_string()
+                };
+                match &item.body {
+                    Some(body) => self.scan_items(body, &child_module, source, child_exported.as_ref()),
+                    None => {
+                        let (file, child_source) = source.child_file(&name);
+                        let child_text = strip_comments_and_literals(&std::fs::read_to_string(&file).unwrap_or_else(|_| panic!("Couldn't read {}", file.display())));
+                        self.scan_items(&child_text, &child_module, &child_source, child_exported.as_ref());
+                    }
+                }
+                continue;
+            }
+            if !is_public(&item.header) {
+                continue;
+            }
+            let name = words.iter()
+                .skip_while(|word| !["struct", "enum", "trait", "type", "fn", "const", "static", "union"].contains(word))
+                .nth(1)
+                .map(|word| identifier_prefix(word))
+                .unwrap_or_default();
+            if kind == "use" {
+                if exported.is_none() {
+                    self.lines.push(format!("{} {}{}", module, item.attributes(), item.signature()));
+                }
+                continue;
+            }
+            if exported.is_some_and(|exported| !exported.contains(&name)) {
+                continue;
+            }
+            if ["struct", "enum", "trait", "type", "union"].contains(&kind) {
+                self.public_names.insert(name.clone());
+            }
+            self.lines.push(format!("{} {}{}", module, item.attributes(), item.signature()));
+            let member_prefix = format!("{module}::{name}");
+            match (kind, item.body.as_deref()) {
+                ("struct" | "union", Some(body)) => {
+                    for field in split_top_level(body, ',') {
+                        if is_public(strip_attributes(&field)) {
+                            self.lines.push(format!("{member_prefix} field {}", normalize(&field)));
+                        }
+                    }
+                },
+                ("enum", Some(body)) => {
+                    for variant in split_top_level(body, ',') {
+                        self.lines.push(format!("{member_prefix} variant {}", normalize(&variant)));
+                    }
+                },
+                ("trait", Some(body)) => {
+                    for trait_item in parse_items(body) {
+                        self.lines.push(format!("{member_prefix} {}{}", trait_item.attributes(), trait_item.signature()));
+                    }
+                },
+                _ => {}
+            }
+        }
+    }
+}
+
+// The names a module's `pub use child::...` items re-export from the given child module
+fn reexported_names(items: &[Item], child: &str, exported: Option<&HashSet<String>>) -> HashSet<String> {
+    let mut names = HashSet::new();
+    for item in items.iter().filter(|item| is_public(&item.header)) {
+        let Some(path) = item.header.trim().strip_prefix("pub use ") else {
+            continue;
+        };
+        let path = path.trim().trim_end_matches(';');
+        let Some(rest) = path.strip_prefix(&format!("{child}::")) else {
+            continue;
+        };
+        let rest = rest.trim();
+        let imported: Vec<&str> = match rest.strip_prefix('{') {
+            Some(list) => list.trim_end_matches('}').split(',').collect(),
+            None => vec![rest.rsplit("::").next().unwrap()],
+        };
+        for name in imported {
+            let name = name.split_whitespace().next().unwrap_or("").rsplit("::").next().unwrap_or("").to_string();
+            if !name.is_empty() && exported.is_none_or(|exported| exported.contains(&name)) {
+                names.insert(name);
+            }
+        }
+    }
+    names
+}
+
+fn is_public(header: &str) -> bool {
+    let header = header.trim_start();
+    header.starts_with("pub ") || header == "pub"
+}
+
+fn identifier_prefix(word: &str) -> String {
+    word.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect()
+}
+
+// Gets the trait (if any) and the self type named by an impl header, without generics or paths
+fn impl_names(header: &str) -> (Option<String>, String) {
+    let header = header.trim_start_matches("unsafe ").trim_start_matches("impl");
+    let header = skip_generics(header.trim_start());
+    let header = header.split(" where ").next().unwrap();
+    let last_segment = |path: &str| -> String {
+        let path = path.trim().trim_start_matches('!').trim_start_matches("dyn ");
+        let without_generics = path.split('<').next().unwrap();
+        identifier_prefix(without_generics.rsplit("::").next().unwrap().trim())
+    };
+    match header.split_once(" for ") {
+        Some((trait_path, self_type)) => (Some(last_segment(trait_path)), last_segment(self_type)),
+        None => (None, last_segment(header)),
+    }
+}
+
+fn skip_generics(text: &str) -> &str {
+    if !text.starts_with('<') {
+        return text;
+    }
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return text[i + 1..].trim_start();
+                }
+            },
+            _ => {}
+        }
+    }
+    text
+}
+
+struct Item {
+    attributes: Vec<String>,
+    header: String,
+    body: Option<String>,
+}
+
+impl Item {
+    fn is_test(&self) -> bool {
+        self.attributes.iter().any(|attribute| attribute.replace(' ', "") == "#[cfg(test)]")
+    }
+
+    // The attributes which affect the API, each followed by a space
+    fn attributes(&self) -> String {
+        self.attributes.iter()
+            .filter(|attribute| !["#[doc", "#[cfg_attr(docsrs", "#[allow", "#[inline", "#[expect"].iter().any(|prefix| attribute.starts_with(prefix)))
+            .map(|attribute| normalize(attribute) + " ")
+            .collect()
+    }
+
+    // The header of the item, without the values of constants and statics
+    fn signature(&self) -> String {
+        let header = normalize(&self.header);
+        let header = header.trim_end_matches(';').trim_end().to_string();
+        let words: Vec<&str> = header.split_whitespace().collect();
+        if words.iter().any(|word| *word == "const" || *word == "static") && !words.contains(&"fn") {
+            if let Some((declaration, _value)) = header.split_once(" = ") {
+                return declaration.to_string();
+            }
+        }
+        header
+    }
+}
+
+// Splits source text into items, each with its attributes, its header up to `{` or `;`, and its braced body if it has one
+fn parse_items(text: &str) -> Vec<Item> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut items = Vec::new();
+    let mut attributes = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i].is_whitespace() || chars[i] == ';' {
+            i += 1;
+            continue;
+        }
+        if chars[i] == '#' {
+            let inner = chars.get(i + 1) == Some(&'!');
+            let start = i;
+            let open = if inner { i + 2 } else { i + 1 };
+            let end = matching_close(&chars, open);
+            if !inner {
+                attributes.push(chars[start..=end].iter().collect());
+            }
+            i = end + 1;
+            continue;
+        }
+        let start = i;
+        let mut depth = 0;
+        let mut body = None;
+        // The braces of a `use` item are part of its path, so it has no body
+        let is_use = text[char_offset(text, start)..].split_whitespace().take(2).any(|word| word == "use");
+        while i < chars.len() {
+            match chars[i] {
+                '(' | '[' => depth += 1,
+                ')' | ']' => depth -= 1,
+                '{' | '}' if is_use => {},
+                ';' if depth == 0 => break,
+                '{' if depth == 0 => {
+                    let end = matching_close(&chars, i);
+                    body = Some(chars[i + 1..end].iter().collect::<String>());
+                    break;
+                },
+                _ => {}
+            }
+            i += 1;
+        }
+        let header: String = chars[start..i.min(chars.len())].iter().collect();
+        if let Some(body) = &body {
+            i += body.chars().count() + 2;
+        } else {
+            i += 1;
+        }
+        items.push(Item { attributes: std::mem::take(&mut attributes), header, body });
+    }
+    items
+}
+
+fn char_offset(text: &str, char_index: usize) -> usize {
+    text.char_indices().nth(char_index).map_or(text.len(), |(offset, _)| offset)
+}
+
+// Finds the bracket closing the one at `open`
+fn matching_close(chars: &[char], open: usize) -> usize {
+    let mut depth = 0;
+    for (i, c) in chars.iter().enumerate().skip(open) {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            },
+            _ => {}
+        }
+    }
+    chars.len() - 1
+}
+
+// Splits a struct or enum body on the separator, outside of any brackets
+fn split_top_level(text: &str, separator: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut current = String::new();
+    let mut previous = ' ';
+    for c in text.chars() {
+        match c {
+            '(' | '[' | '{' | '<' => depth += 1,
+            // The arrow of a function type isn't a closing bracket
+            '>' if previous == '-' => {},
+            ')' | ']' | '}' | '>' => depth -= 1,
+            _ => {}
+        }
+        if c == separator && depth == 0 {
+            parts.push(std::mem::take(&mut current));
+        } else {
+            current.push(c);
+        }
+        previous = c;
+    }
+    parts.push(current);
+    parts.into_iter().filter(|part| !part.trim().is_empty()).collect()
+}
+
+fn strip_attributes(text: &str) -> &str {
+    let mut text = text.trim_start();
+    while text.starts_with("#[") {
+        let chars: Vec<char> = text.chars().collect();
+        let end = matching_close(&chars, 1);
+        let end_byte = text.char_indices().nth(end).map(|(i, _)| i).unwrap_or(text.len() - 1);
+        text = text[end_byte + 1..].trim_start();
+    }
+    text
+}
+
+// Collapses whitespace, and drops the trailing commas of multi-line lists
+fn normalize(text: &str) -> String {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    collapsed.replace("( ", "(").replace(" )", ")").replace(",)", ")").replace(", }", " }").replace(",}", "}").replace(",>", ">").replace(", >", ">")
+}
+
+// Removes comments, empties character literals, and replaces the brackets in string literals, so that brackets within them aren't counted
+fn strip_comments_and_literals(source: &str) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let mut out = String::with_capacity(source.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let previous_is_identifier = i > 0 && (chars[i - 1].is_alphanumeric() || chars[i - 1] == '_');
+        if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && next == Some('*') {
+            let mut depth = 0;
+            while i < chars.len() {
+                if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+                    depth += 1;
+                    i += 2;
+                } else if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    i += 1;
+                }
+            }
+            out.push(' ');
+        } else if c == 'r' && (next == Some('"') || next == Some('#')) && (!previous_is_identifier || (i > 0 && chars[i - 1] == 'b' && !(i > 1 && (chars[i - 2].is_alphanumeric() || chars[i - 2] == '_')))) {
+            let mut hashes = 0;
+            let mut j = i + 1;
+            while chars.get(j) == Some(&'#') {
+                hashes += 1;
+                j += 1;
+            }
+            if chars.get(j) != Some(&'"') {
+                out.push(c);
+                i += 1;
+                continue;
+            }
+            j += 1;
+            while j < chars.len() && !(chars[j] == '"' && (0..hashes).all(|k| chars.get(j + 1 + k) == Some(&'#'))) {
+                j += 1;
+            }
+            out.push('"');
+            out.extend(chars[i + 2 + hashes..j].iter().map(|&c| neutral_char(c)));
+            out.push('"');
+            i = j + 1 + hashes;
+        } else if c == '"' {
+            let mut j = i + 1;
+            while j < chars.len() && chars[j] != '"' {
+                if chars[j] == '\\' {
+                    j += 1;
+                }
+                j += 1;
+            }
+            out.push('"');
+            out.extend(chars[i + 1..j.min(chars.len())].iter().map(|&c| neutral_char(c)));
+            out.push('"');
+            i = j + 1;
+        } else if c == '\'' {
+            // A character literal, rather than a lifetime or label
+            let literal_end = if next == Some('\\') {
+                (i + 2..chars.len()).find(|&j| chars[j] == '\'')
+            } else if chars.get(i + 2) == Some(&'\'') {
+                Some(i + 2)
+            } else {
+                None
+            };
+            match literal_end {
+                Some(end) => {
+                    out.push_str("''");
+                    i = end + 1;
+                },
+                None => {
+                    out.push(c);
+                    i += 1;
+                }
+            }
+        } else {
+            out.push(c);
+            i += 1;
+        }
+    }
+    out
+}
+
+fn neutral_char(c: char) -> char {
+    match c {
+        '(' | ')' | '[' | ']' | '{' | '}' | ';' | '\\' | '"' => '_',
+        c => c,
+    }
+}